@@ -0,0 +1,251 @@
+#![no_std]
+
+use soroban_sdk::{
+    contract, contracterror, contractimpl, contracttype, panic_with_error, symbol_short, Address, Env, String,
+};
+
+// Shares carry 7 decimals, like the Stellar amounts they are priced in
+const DECIMALS: u32 = 7;
+// Entries are kept alive for about two months past their last use
+const LEDGERS_PER_DAY: u32 = 17_280;
+const TTL_THRESHOLD: u32 = 30 * LEDGERS_PER_DAY;
+const TTL_EXTEND_TO: u32 = 60 * LEDGERS_PER_DAY;
+
+// ============================================================================
+// TYPES
+// ============================================================================
+
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum TokenError {
+    InvalidAmount = 1,
+    InsufficientBalance = 2,
+    InsufficientAllowance = 3,
+    // An allowance must expire in the future, unless it is being cleared
+    InvalidExpiration = 4,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Allowance {
+    pub amount: i128,
+    pub expiration_ledger: u32,
+}
+
+#[contracttype]
+enum DataKey {
+    Admin,
+    Name,
+    Symbol,
+    Balance(Address),
+    Allowance(Address, Address),
+}
+
+// ============================================================================
+// CONTRACT
+// ============================================================================
+
+// One risk level's vault shares as a SEP-41 token, so they can be held,
+// moved and used by other Soroban protocols. Only the admin, the vault
+// contract, mints; holders burn their own shares when they redeem them.
+#[contract]
+pub struct ShareToken;
+
+#[contractimpl]
+impl ShareToken {
+    pub fn __constructor(env: Env, admin: Address, name: String, symbol: String) {
+        env.storage().instance().set(&DataKey::Admin, &admin);
+        env.storage().instance().set(&DataKey::Name, &name);
+        env.storage().instance().set(&DataKey::Symbol, &symbol);
+    }
+
+    pub fn mint(env: Env, to: Address, amount: i128) {
+        let admin: Address = env.storage().instance().get(&DataKey::Admin).unwrap();
+        admin.require_auth();
+        check_amount(&env, amount);
+        extend_instance(&env);
+
+        set_balance(&env, &to, balance_of(&env, &to) + amount);
+        env.events().publish((symbol_short!("mint"), admin, to), amount);
+    }
+
+    // ------------------------------------------------------------------------
+    // SEP-41
+    // ------------------------------------------------------------------------
+
+    pub fn allowance(env: Env, from: Address, spender: Address) -> i128 {
+        allowance_of(&env, &from, &spender).amount
+    }
+
+    pub fn approve(env: Env, from: Address, spender: Address, amount: i128, expiration_ledger: u32) {
+        from.require_auth();
+        if amount < 0 {
+            panic_with_error!(&env, TokenError::InvalidAmount);
+        }
+        if amount > 0 && expiration_ledger < env.ledger().sequence() {
+            panic_with_error!(&env, TokenError::InvalidExpiration);
+        }
+        extend_instance(&env);
+
+        let key = DataKey::Allowance(from.clone(), spender.clone());
+        env.storage().temporary().set(&key, &Allowance { amount, expiration_ledger });
+        if amount > 0 {
+            let live_for = expiration_ledger - env.ledger().sequence();
+            env.storage().temporary().extend_ttl(&key, live_for, live_for);
+        }
+        env.events().publish((symbol_short!("approve"), from, spender), (amount, expiration_ledger));
+    }
+
+    pub fn balance(env: Env, id: Address) -> i128 {
+        balance_of(&env, &id)
+    }
+
+    pub fn transfer(env: Env, from: Address, to: Address, amount: i128) {
+        from.require_auth();
+        check_amount(&env, amount);
+        extend_instance(&env);
+
+        move_balance(&env, &from, &to, amount);
+        env.events().publish((symbol_short!("transfer"), from, to), amount);
+    }
+
+    pub fn transfer_from(env: Env, spender: Address, from: Address, to: Address, amount: i128) {
+        spender.require_auth();
+        check_amount(&env, amount);
+        extend_instance(&env);
+
+        spend_allowance(&env, &from, &spender, amount);
+        move_balance(&env, &from, &to, amount);
+        env.events().publish((symbol_short!("transfer"), from, to), amount);
+    }
+
+    pub fn burn(env: Env, from: Address, amount: i128) {
+        from.require_auth();
+        check_amount(&env, amount);
+        extend_instance(&env);
+
+        take_balance(&env, &from, amount);
+        env.events().publish((symbol_short!("burn"), from), amount);
+    }
+
+    pub fn burn_from(env: Env, spender: Address, from: Address, amount: i128) {
+        spender.require_auth();
+        check_amount(&env, amount);
+        extend_instance(&env);
+
+        spend_allowance(&env, &from, &spender, amount);
+        take_balance(&env, &from, amount);
+        env.events().publish((symbol_short!("burn"), from), amount);
+    }
+
+    pub fn decimals(_env: Env) -> u32 {
+        DECIMALS
+    }
+
+    pub fn name(env: Env) -> String {
+        env.storage().instance().get(&DataKey::Name).unwrap()
+    }
+
+    pub fn symbol(env: Env) -> String {
+        env.storage().instance().get(&DataKey::Symbol).unwrap()
+    }
+}
+
+// ============================================================================
+// STORAGE
+// ============================================================================
+
+fn check_amount(env: &Env, amount: i128) {
+    if amount <= 0 {
+        panic_with_error!(env, TokenError::InvalidAmount);
+    }
+}
+
+fn extend_instance(env: &Env) {
+    env.storage().instance().extend_ttl(TTL_THRESHOLD, TTL_EXTEND_TO);
+}
+
+fn balance_of(env: &Env, id: &Address) -> i128 {
+    let key = DataKey::Balance(id.clone());
+    let balance = env.storage().persistent().get(&key).unwrap_or(0);
+    if balance > 0 {
+        env.storage().persistent().extend_ttl(&key, TTL_THRESHOLD, TTL_EXTEND_TO);
+    }
+    balance
+}
+
+fn set_balance(env: &Env, id: &Address, balance: i128) {
+    let key = DataKey::Balance(id.clone());
+    if balance == 0 {
+        env.storage().persistent().remove(&key);
+    } else {
+        env.storage().persistent().set(&key, &balance);
+        env.storage().persistent().extend_ttl(&key, TTL_THRESHOLD, TTL_EXTEND_TO);
+    }
+}
+
+fn take_balance(env: &Env, id: &Address, amount: i128) {
+    let balance = balance_of(env, id);
+    if balance < amount {
+        panic_with_error!(env, TokenError::InsufficientBalance);
+    }
+    set_balance(env, id, balance - amount);
+}
+
+fn move_balance(env: &Env, from: &Address, to: &Address, amount: i128) {
+    take_balance(env, from, amount);
+    set_balance(env, to, balance_of(env, to) + amount);
+}
+
+// An expired allowance reads as zero
+fn allowance_of(env: &Env, from: &Address, spender: &Address) -> Allowance {
+    let key = DataKey::Allowance(from.clone(), spender.clone());
+    match env.storage().temporary().get::<_, Allowance>(&key) {
+        Some(allowance) if allowance.expiration_ledger >= env.ledger().sequence() => allowance,
+        _ => Allowance { amount: 0, expiration_ledger: 0 },
+    }
+}
+
+fn spend_allowance(env: &Env, from: &Address, spender: &Address, amount: i128) {
+    let allowance = allowance_of(env, from, spender);
+    if allowance.amount < amount {
+        panic_with_error!(env, TokenError::InsufficientAllowance);
+    }
+    let key = DataKey::Allowance(from.clone(), spender.clone());
+    let remaining = Allowance { amount: allowance.amount - amount, ..allowance };
+    env.storage().temporary().set(&key, &remaining);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use soroban_sdk::testutils::{Address as _, Ledger};
+
+    #[test]
+    fn shares_move_by_transfer_and_allowance() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let vault = Address::generate(&env);
+        let (alice, bob, protocol) = (Address::generate(&env), Address::generate(&env), Address::generate(&env));
+        let args = (&vault, String::from_str(&env, "StellarVault Low Risk Share"), String::from_str(&env, "SYIALOW"));
+        let shares = ShareTokenClient::new(&env, &env.register(ShareToken, args));
+
+        shares.mint(&alice, &1_000);
+        shares.transfer(&alice, &bob, &400);
+        assert_eq!((shares.balance(&alice), shares.balance(&bob)), (600, 400));
+        assert_eq!(shares.try_transfer(&bob, &alice, &401), Err(Ok(TokenError::InsufficientBalance.into())));
+
+        shares.approve(&alice, &protocol, &500, &(env.ledger().sequence() + 100));
+        shares.transfer_from(&protocol, &alice, &protocol, &300);
+        assert_eq!(shares.allowance(&alice, &protocol), 200);
+        shares.burn_from(&protocol, &alice, &200);
+        assert_eq!(shares.balance(&alice), 100);
+
+        // Allowances lapse at their expiration ledger
+        shares.approve(&bob, &protocol, &100, &(env.ledger().sequence() + 10));
+        env.ledger().with_mut(|ledger| ledger.sequence_number += 11);
+        assert_eq!(shares.allowance(&bob, &protocol), 0);
+        assert_eq!(shares.decimals(), 7);
+    }
+}
@@ -0,0 +1,317 @@
+#![no_std]
+
+use soroban_sdk::{
+    contract, contractclient, contracterror, contractimpl, contracttype, panic_with_error, symbol_short, token, Address,
+    BytesN, Env, Map, Symbol, Vec,
+};
+
+// Basis points in 100%
+const BPS: i128 = 10_000;
+// Share prices carry 7 decimals, like Stellar amounts
+const PRICE_SCALAR: i128 = 10_000_000;
+// Entries are kept alive for about two months past their last use
+const LEDGERS_PER_DAY: u32 = 17_280;
+const TTL_THRESHOLD: u32 = 30 * LEDGERS_PER_DAY;
+const TTL_EXTEND_TO: u32 = 60 * LEDGERS_PER_DAY;
+
+// ============================================================================
+// TYPES
+// ============================================================================
+
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum VaultError {
+    NotConfigured = 1,
+    InvalidAmount = 2,
+    InsufficientShares = 3,
+    InvalidStrategies = 4,
+    InvalidFee = 5,
+    // The deposit is too small to be worth a single share
+    NothingToMint = 6,
+    // Yield can't be credited to a vault nobody holds shares in
+    EmptyVault = 7,
+    InsufficientReserve = 8,
+    // The constructor needs a share token for every risk level
+    MissingShareToken = 9,
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RiskLevel {
+    Low,
+    Medium,
+    High,
+}
+
+// A strategy the vault allocates to, by name, and its share of the vault
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Strategy {
+    pub kind: Symbol,
+    pub allocation_bps: u32,
+}
+
+// One risk level's pool. Every share is worth total_assets / total_shares;
+// the insurance reserve is held apart and belongs to no share.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VaultState {
+    pub total_assets: i128,
+    pub total_shares: i128,
+    pub insurance_fee_bps: u32,
+    pub insurance_reserve: i128,
+    pub strategies: Vec<Strategy>,
+}
+
+#[contracttype]
+enum DataKey {
+    Admin,
+    Token,
+    Vault(RiskLevel),
+    ShareToken(RiskLevel),
+}
+
+// The parts of contracts/share_token the vault calls. A Stellar asset
+// contract whose admin is the vault has the same functions.
+#[allow(dead_code)]
+#[contractclient(name = "ShareTokenClient")]
+trait ShareToken {
+    fn mint(env: Env, to: Address, amount: i128);
+    fn burn(env: Env, from: Address, amount: i128);
+    fn balance(env: Env, id: Address) -> i128;
+}
+
+// ============================================================================
+// CONTRACT
+// ============================================================================
+
+// The vault's accounting on-chain: deposits mint shares at the current share
+// price after the insurance fee, withdrawals burn them for their value, and
+// the admin credits harvested strategy yield, which raises the share price.
+// Strategy allocations are recorded for clients to read; the deposit token
+// itself stays in the contract. Each risk level's shares are a SEP-41 token
+// the vault mints and burns, so holders can move them like any other token.
+#[contract]
+pub struct VaultContract;
+
+#[contractimpl]
+impl VaultContract {
+    // `share_tokens` must name a token for every risk level, each with this
+    // contract as its admin
+    pub fn __constructor(env: Env, admin: Address, token: Address, share_tokens: Map<RiskLevel, Address>) {
+        env.storage().instance().set(&DataKey::Admin, &admin);
+        env.storage().instance().set(&DataKey::Token, &token);
+        for risk in [RiskLevel::Low, RiskLevel::Medium, RiskLevel::High] {
+            let share_token = share_tokens.get(risk).unwrap_or_else(|| panic_with_error!(&env, VaultError::MissingShareToken));
+            env.storage().instance().set(&DataKey::ShareToken(risk), &share_token);
+        }
+    }
+
+    // Sets a risk level's insurance fee and strategies, opening it if new
+    pub fn configure(env: Env, risk: RiskLevel, insurance_fee_bps: u32, strategies: Vec<Strategy>) {
+        admin(&env).require_auth();
+        if insurance_fee_bps as i128 >= BPS {
+            panic_with_error!(&env, VaultError::InvalidFee);
+        }
+        let allocated: u32 = strategies.iter().map(|strategy| strategy.allocation_bps).sum();
+        if strategies.is_empty() || allocated as i128 != BPS {
+            panic_with_error!(&env, VaultError::InvalidStrategies);
+        }
+
+        let state = match env.storage().persistent().get::<_, VaultState>(&DataKey::Vault(risk)) {
+            Some(state) => VaultState { insurance_fee_bps, strategies, ..state },
+            None => VaultState { total_assets: 0, total_shares: 0, insurance_fee_bps, insurance_reserve: 0, strategies },
+        };
+        save_vault(&env, risk, &state);
+    }
+
+    // Takes `amount` of the token from `user` and returns the shares minted
+    pub fn deposit(env: Env, user: Address, risk: RiskLevel, amount: i128) -> i128 {
+        user.require_auth();
+        if amount <= 0 {
+            panic_with_error!(&env, VaultError::InvalidAmount);
+        }
+        let mut state = vault(&env, risk);
+        let fee = amount * state.insurance_fee_bps as i128 / BPS;
+        let net = amount - fee;
+        let shares = if state.total_shares == 0 { net } else { net * state.total_shares / state.total_assets };
+        if shares <= 0 {
+            panic_with_error!(&env, VaultError::NothingToMint);
+        }
+
+        token::Client::new(&env, &token(&env)).transfer(&user, &env.current_contract_address(), &amount);
+        state.total_assets += net;
+        state.total_shares += shares;
+        state.insurance_reserve += fee;
+        save_vault(&env, risk, &state);
+        share_token(&env, risk).mint(&user, &shares);
+
+        env.events().publish((symbol_short!("deposit"), user, risk), (amount, fee, shares));
+        shares
+    }
+
+    // Burns `shares` of `user`'s share token and pays out their value, which
+    // it returns
+    pub fn withdraw(env: Env, user: Address, risk: RiskLevel, shares: i128) -> i128 {
+        user.require_auth();
+        if shares <= 0 {
+            panic_with_error!(&env, VaultError::InvalidAmount);
+        }
+        let share_token = share_token(&env, risk);
+        if share_token.balance(&user) < shares {
+            panic_with_error!(&env, VaultError::InsufficientShares);
+        }
+        let mut state = vault(&env, risk);
+        let amount = shares * state.total_assets / state.total_shares;
+
+        state.total_assets -= amount;
+        state.total_shares -= shares;
+        save_vault(&env, risk, &state);
+        share_token.burn(&user, &shares);
+        token::Client::new(&env, &token(&env)).transfer(&env.current_contract_address(), &user, &amount);
+
+        env.events().publish((symbol_short!("withdraw"), user, risk), (amount, shares));
+        amount
+    }
+
+    // Pays `amount` of strategy yield in from the admin, raising the share price
+    pub fn harvest(env: Env, risk: RiskLevel, amount: i128) {
+        let admin = admin(&env);
+        admin.require_auth();
+        if amount <= 0 {
+            panic_with_error!(&env, VaultError::InvalidAmount);
+        }
+        let mut state = vault(&env, risk);
+        if state.total_shares == 0 {
+            panic_with_error!(&env, VaultError::EmptyVault);
+        }
+
+        token::Client::new(&env, &token(&env)).transfer(&admin, &env.current_contract_address(), &amount);
+        state.total_assets += amount;
+        save_vault(&env, risk, &state);
+
+        env.events().publish((symbol_short!("harvest"), risk), amount);
+    }
+
+    // Pays an approved insurance claim out of the risk level's reserve
+    pub fn pay_claim(env: Env, risk: RiskLevel, to: Address, amount: i128) {
+        admin(&env).require_auth();
+        if amount <= 0 {
+            panic_with_error!(&env, VaultError::InvalidAmount);
+        }
+        let mut state = vault(&env, risk);
+        if state.insurance_reserve < amount {
+            panic_with_error!(&env, VaultError::InsufficientReserve);
+        }
+
+        state.insurance_reserve -= amount;
+        save_vault(&env, risk, &state);
+        token::Client::new(&env, &token(&env)).transfer(&env.current_contract_address(), &to, &amount);
+
+        env.events().publish((symbol_short!("claim"), to, risk), amount);
+    }
+
+    // Replaces the contract's code with uploaded Wasm, keeping its storage
+    pub fn upgrade(env: Env, wasm_hash: BytesN<32>) {
+        admin(&env).require_auth();
+        env.deployer().update_current_contract_wasm(wasm_hash.clone());
+        env.events().publish((symbol_short!("upgrade"),), wasm_hash);
+    }
+
+    pub fn vault(env: Env, risk: RiskLevel) -> VaultState {
+        vault(&env, risk)
+    }
+
+    pub fn shares(env: Env, user: Address, risk: RiskLevel) -> i128 {
+        share_token(&env, risk).balance(&user)
+    }
+
+    pub fn share_token(env: Env, risk: RiskLevel) -> Address {
+        share_token(&env, risk).address
+    }
+
+    // Value of one share, scaled by 10^7
+    pub fn share_price(env: Env, risk: RiskLevel) -> i128 {
+        let state = vault(&env, risk);
+        if state.total_shares == 0 {
+            return PRICE_SCALAR;
+        }
+        state.total_assets * PRICE_SCALAR / state.total_shares
+    }
+
+    pub fn token(env: Env) -> Address {
+        token(&env)
+    }
+}
+
+// ============================================================================
+// STORAGE
+// ============================================================================
+
+fn admin(env: &Env) -> Address {
+    env.storage().instance().extend_ttl(TTL_THRESHOLD, TTL_EXTEND_TO);
+    env.storage().instance().get(&DataKey::Admin).unwrap()
+}
+
+fn token(env: &Env) -> Address {
+    env.storage().instance().get(&DataKey::Token).unwrap()
+}
+
+fn vault(env: &Env, risk: RiskLevel) -> VaultState {
+    let key = DataKey::Vault(risk);
+    let state = env.storage().persistent().get(&key)
+        .unwrap_or_else(|| panic_with_error!(env, VaultError::NotConfigured));
+    env.storage().persistent().extend_ttl(&key, TTL_THRESHOLD, TTL_EXTEND_TO);
+    state
+}
+
+fn save_vault(env: &Env, risk: RiskLevel, state: &VaultState) {
+    let key = DataKey::Vault(risk);
+    env.storage().persistent().set(&key, state);
+    env.storage().persistent().extend_ttl(&key, TTL_THRESHOLD, TTL_EXTEND_TO);
+}
+
+fn share_token(env: &Env, risk: RiskLevel) -> ShareTokenClient<'_> {
+    let address: Address = env.storage().instance().get(&DataKey::ShareToken(risk)).unwrap();
+    ShareTokenClient::new(env, &address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use soroban_sdk::testutils::Address as _;
+    use soroban_sdk::{map, token::StellarAssetClient, vec};
+
+    #[test]
+    fn yield_raises_the_share_price_and_fees_fund_insurance() {
+        let env = Env::default();
+        env.mock_all_auths();
+        let admin = Address::generate(&env);
+        let user = Address::generate(&env);
+        let token = env.register_stellar_asset_contract_v2(admin.clone()).address();
+        StellarAssetClient::new(&env, &token).mint(&user, &10_000_000_000);
+        StellarAssetClient::new(&env, &token).mint(&admin, &1_000_000_000);
+
+        // Stellar asset contracts stand in for the share tokens; the vault
+        // becomes their admin, as it is of contracts/share_token
+        let [low, medium, high] = [(); 3].map(|_| env.register_stellar_asset_contract_v2(admin.clone()).address());
+        let share_tokens = map![&env, (RiskLevel::Low, low.clone()), (RiskLevel::Medium, medium), (RiskLevel::High, high)];
+        let vault = VaultContractClient::new(&env, &env.register(VaultContract, (&admin, &token, share_tokens)));
+        StellarAssetClient::new(&env, &low).set_admin(&vault.address);
+        let strategies = vec![&env, Strategy { kind: Symbol::new(&env, "YieldBloxLending"), allocation_bps: 10_000 }];
+        vault.configure(&RiskLevel::Low, &50, &strategies);
+
+        // 0.5% of the deposit goes to the insurance reserve
+        let shares = vault.deposit(&user, &RiskLevel::Low, &10_000_000_000);
+        assert_eq!(shares, 9_950_000_000);
+        assert_eq!(vault.vault(&RiskLevel::Low).insurance_reserve, 50_000_000);
+        assert_eq!(token::Client::new(&env, &low).balance(&user), shares);
+
+        vault.harvest(&RiskLevel::Low, &995_000_000);
+        assert_eq!(vault.share_price(&RiskLevel::Low), 11_000_000);
+        assert_eq!(vault.withdraw(&user, &RiskLevel::Low, &shares), 10_945_000_000);
+        assert_eq!(vault.shares(&user, &RiskLevel::Low), 0);
+        assert_eq!(vault.try_withdraw(&user, &RiskLevel::Low, &1), Err(Ok(VaultError::InsufficientShares.into())));
+    }
+}
@@ -0,0 +1,213 @@
+use crate::amount::{mul_div, MathError, Rounding, SharePrice, Shares, Stroops, PRICE_SCALE};
+
+// ============================================================================
+// SHARE ACCOUNTING
+// ============================================================================
+
+// The share bookkeeping of a single vault, free of I/O so it can be worked
+// on a copy and only written back once the matching payment has landed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SharePool {
+    pub total_value: Stroops,
+    pub total_shares: Shares,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepositQuote {
+    pub shares: Shares,
+    pub insurance: Stroops,
+    pub net: Stroops,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WithdrawalQuote {
+    pub gross: Stroops,
+    pub penalty: Stroops,
+    pub net: Stroops,
+}
+
+impl SharePool {
+    pub fn share_price(&self) -> Result<SharePrice, MathError> {
+        SharePrice::from_totals(self.total_value, self.total_shares)
+    }
+
+    // Conversions go through the totals rather than the rounded share price,
+    // so large amounts don't pick up the price's rounding error
+    pub fn shares_for(&self, amount: Stroops, rounding: Rounding) -> Result<Shares, MathError> {
+        if self.total_shares == Shares::ZERO {
+            return mul_div(amount.0, PRICE_SCALE, SharePrice::INITIAL.0, rounding).map(Shares);
+        }
+        mul_div(amount.0, self.total_shares.0, self.total_value.0, rounding).map(Shares)
+    }
+
+    pub fn value_of(&self, shares: Shares, rounding: Rounding) -> Result<Stroops, MathError> {
+        if shares == Shares::ZERO {
+            return Ok(Stroops::ZERO);
+        }
+        mul_div(shares.0, self.total_value.0, self.total_shares.0, rounding).map(Stroops)
+    }
+
+    // Mints shares for stroops entering the pool at the current price
+    pub fn mint(&mut self, amount: Stroops) -> Result<Shares, MathError> {
+        let shares = self.shares_for(amount, Rounding::Down)?;
+        let total_value = self.total_value.checked_add(amount)?;
+        let total_shares = self.total_shares.checked_add(shares)?;
+
+        self.total_value = total_value;
+        self.total_shares = total_shares;
+        Ok(shares)
+    }
+
+    // Burns shares at the current price and returns the stroops released
+    pub fn burn(&mut self, shares: Shares) -> Result<Stroops, MathError> {
+        let amount = self.value_of(shares, Rounding::Down)?;
+        let total_value = self.total_value.checked_sub(amount)?;
+        let total_shares = self.total_shares.checked_sub(shares)?;
+
+        self.total_value = total_value;
+        self.total_shares = total_shares;
+        Ok(amount)
+    }
+
+    // The insurance premium goes to the insurance pool, so shares are only
+    // minted for the net amount; minting on the gross would dilute holders
+    pub fn deposit(&mut self, amount: Stroops, insurance_fee_bps: u16) -> Result<DepositQuote, MathError> {
+        let insurance = amount.bps(insurance_fee_bps, Rounding::Up)?;
+        let net = amount.checked_sub(insurance)?;
+        let shares = self.mint(net)?;
+        Ok(DepositQuote { shares, insurance, net })
+    }
+
+    pub fn withdraw(&mut self, shares: Shares, penalty_bps: u16) -> Result<WithdrawalQuote, MathError> {
+        let gross = self.burn(shares)?;
+        let penalty = gross.bps(penalty_bps, Rounding::Up)?;
+        let net = gross.checked_sub(penalty)?;
+        Ok(WithdrawalQuote { gross, penalty, net })
+    }
+
+    // A withdrawal where only `locked` of the shares pay the penalty
+    pub fn withdraw_partly_locked(&mut self, shares: Shares, locked: Shares, penalty_bps: u16) -> Result<WithdrawalQuote, MathError> {
+        let gross = self.burn(shares)?;
+        let locked_value = match locked >= shares {
+            true => gross,
+            false => Stroops(mul_div(gross.0, locked.0, shares.0, Rounding::Up)?),
+        };
+        let penalty = locked_value.bps(penalty_bps, Rounding::Up)?;
+        let net = gross.checked_sub(penalty)?;
+        Ok(WithdrawalQuote { gross, penalty, net })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const USERS: usize = 4;
+
+    #[derive(Debug, Clone)]
+    enum Op {
+        Deposit { user: usize, amount: u64, fee_bps: u16 },
+        Withdraw { user: usize, fraction_bps: u16, penalty_bps: u16 },
+        Harvest { amount: u64 },
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            (0..USERS, 1..1_000_000_000_000u64, 0..=500u16)
+                .prop_map(|(user, amount, fee_bps)| Op::Deposit { user, amount, fee_bps }),
+            (0..USERS, 1..=10_000u16, 0..=500u16)
+                .prop_map(|(user, fraction_bps, penalty_bps)| Op::Withdraw { user, fraction_bps, penalty_bps }),
+            (0..10_000_000_000u64).prop_map(|amount| Op::Harvest { amount }),
+        ]
+    }
+
+    // Strategy yield raises the value behind every existing share
+    fn accrue(pool: &mut SharePool, amount: u64) {
+        pool.total_value = pool.total_value.checked_add(Stroops(amount)).unwrap();
+    }
+
+    // Compares prices without the rounding of either one
+    fn price_at_least(after: SharePool, before: SharePool) -> bool {
+        if before.total_shares == Shares::ZERO || after.total_shares == Shares::ZERO {
+            return true;
+        }
+        after.total_value.0 as u128 * before.total_shares.0 as u128
+            >= before.total_value.0 as u128 * after.total_shares.0 as u128
+    }
+
+    proptest! {
+        #[test]
+        fn user_shares_always_sum_to_total(ops in prop::collection::vec(op(), 1..64)) {
+            let mut pool = SharePool::default();
+            let mut held = [Shares::ZERO; USERS];
+
+            for op in ops {
+                match op {
+                    Op::Deposit { user, amount, fee_bps } => {
+                        let quote = pool.deposit(Stroops(amount), fee_bps).unwrap();
+                        held[user] = held[user].checked_add(quote.shares).unwrap();
+                    }
+                    Op::Withdraw { user, fraction_bps, penalty_bps } => {
+                        let shares = Shares((held[user].0 as u128 * fraction_bps as u128 / 10_000) as u64);
+                        pool.withdraw(shares, penalty_bps).unwrap();
+                        held[user] = held[user].checked_sub(shares).unwrap();
+                    }
+                    Op::Harvest { amount } => accrue(&mut pool, amount),
+                }
+
+                let sum: u64 = held.iter().map(|s| s.0).sum();
+                prop_assert_eq!(sum, pool.total_shares.0);
+            }
+        }
+
+        #[test]
+        fn deposits_never_lower_the_share_price(
+            ops in prop::collection::vec(op(), 0..32),
+            deposits in prop::collection::vec((1..1_000_000_000_000u64, 0..=500u16), 1..32),
+        ) {
+            let mut pool = SharePool::default();
+            let mut held = [Shares::ZERO; USERS];
+            for op in ops {
+                match op {
+                    Op::Deposit { user, amount, fee_bps } => {
+                        held[user] = held[user].checked_add(pool.deposit(Stroops(amount), fee_bps).unwrap().shares).unwrap();
+                    }
+                    Op::Withdraw { user, fraction_bps, .. } => {
+                        let shares = Shares((held[user].0 as u128 * fraction_bps as u128 / 10_000) as u64);
+                        pool.withdraw(shares, 0).unwrap();
+                        held[user] = held[user].checked_sub(shares).unwrap();
+                    }
+                    Op::Harvest { amount } => accrue(&mut pool, amount),
+                }
+            }
+
+            for (amount, fee_bps) in deposits {
+                let before = pool;
+                pool.deposit(Stroops(amount), fee_bps).unwrap();
+                prop_assert!(price_at_least(pool, before));
+            }
+        }
+
+        #[test]
+        fn round_trip_never_returns_more_than_deposited(
+            seed_amount in 10_000_000..1_000_000_000_000u64,
+            seed_yield in 0..10_000_000_000u64,
+            amount in 1..1_000_000_000_000u64,
+            fee_bps in 0..=500u16,
+            penalty_bps in 0..=500u16,
+        ) {
+            let mut pool = SharePool::default();
+            pool.deposit(Stroops(seed_amount), 0).unwrap();
+            accrue(&mut pool, seed_yield);
+
+            let quote = pool.deposit(Stroops(amount), fee_bps).unwrap();
+            let before = pool;
+            let withdrawal = pool.withdraw(quote.shares, penalty_bps).unwrap();
+
+            prop_assert!(withdrawal.gross <= quote.net);
+            prop_assert!(withdrawal.net <= Stroops(amount).checked_sub(quote.insurance).unwrap());
+            prop_assert!(price_at_least(pool, before));
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use serde::Serialize;
+
+use crate::stellar_vault::StellarVault;
+use crate::{format_duration, strategy, RiskLevel};
+
+// How long a rule that stays broken waits before it fires again
+pub const DEFAULT_COOLDOWN_SECS: u64 = 3_600;
+
+// ============================================================================
+// ALERT RULES
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlertRule {
+    // A vault's blended APY, or one of its strategies', below min_apy_bps.
+    // Every vault when risk is None.
+    ApyBelow { risk: Option<RiskLevel>, strategy: Option<String>, min_apy_bps: u16 },
+    // A vault's share price moved more than max_move_bps, either way, within
+    // the last window_secs
+    SharePriceMove { risk: Option<RiskLevel>, max_move_bps: u64, window_secs: u64 },
+    // Insurance assets below min_coverage_bps of the TVL they cover
+    CoverageBelow { min_coverage_bps: u64 },
+}
+
+impl AlertRule {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AlertRule::ApyBelow { .. } => "apy_below",
+            AlertRule::SharePriceMove { .. } => "share_price_move",
+            AlertRule::CoverageBelow { .. } => "coverage_below",
+        }
+    }
+
+    fn covers(&self, vault: RiskLevel) -> bool {
+        match self {
+            AlertRule::ApyBelow { risk, .. } | AlertRule::SharePriceMove { risk, .. } => risk.is_none_or(|risk| risk == vault),
+            AlertRule::CoverageBelow { .. } => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AlertConfig {
+    pub rules: Vec<AlertRule>,
+    pub cooldown_secs: u64,
+}
+
+// A broken rule, as sent through the notification channels
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub rule: &'static str,
+    // The vault it broke for; None for vault-wide rules
+    pub risk: Option<RiskLevel>,
+    pub message: String,
+}
+
+// ============================================================================
+// READINGS
+// ============================================================================
+
+// What the rules are checked against, read from the vault on each check
+#[derive(Debug, Clone, Default)]
+pub struct Readings {
+    pub vaults: Vec<VaultReading>,
+    pub coverage_bps: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct VaultReading {
+    pub risk: RiskLevel,
+    pub share_price: u64,
+    pub apy_bps: u16,
+    pub strategy_apys: Vec<(String, u16)>,
+}
+
+impl Readings {
+    pub fn of(vault: &StellarVault) -> Self {
+        let vaults = RiskLevel::ALL.into_iter()
+            .filter_map(|risk| {
+                let info = vault.get_vault_info(risk)?;
+                Some(VaultReading {
+                    risk,
+                    share_price: vault.live_share_price(risk).unwrap_or_else(|_| info.get_share_price()),
+                    apy_bps: info.blended_apy(),
+                    strategy_apys: info.strategies.iter()
+                        .map(|s| (s.strategy.clone(), strategy::lookup(&s.strategy).current_apy(s)))
+                        .collect(),
+                })
+            })
+            .collect();
+        Readings { vaults, coverage_bps: vault.coverage_ratio_bps() }
+    }
+}
+
+// ============================================================================
+// ENGINE
+// ============================================================================
+
+// Checks the rules against each reading. A rule fires once when it breaks,
+// then again every cooldown while it stays broken, and re-arms once it holds.
+pub struct AlertEngine {
+    config: AlertConfig,
+    // Share prices seen per vault, oldest first, as (time, price)
+    prices: HashMap<RiskLevel, VecDeque<(u64, u64)>>,
+    // When each broken rule last fired, by rule index and vault
+    firing: HashMap<(usize, Option<RiskLevel>), u64>,
+}
+
+fn percent(bps: u64) -> f64 {
+    bps as f64 / 100.0
+}
+
+impl AlertEngine {
+    pub fn new(config: AlertConfig) -> Self {
+        AlertEngine { config, prices: HashMap::new(), firing: HashMap::new() }
+    }
+
+    // Largest move from a price seen within window_secs to the current one
+    fn price_move_bps(&self, risk: RiskLevel, current: u64, window_secs: u64, now: u64) -> u64 {
+        self.prices.get(&risk).into_iter().flatten()
+            .filter(|(at, price)| now.saturating_sub(*at) <= window_secs && *price > 0)
+            .map(|(_, price)| (current.abs_diff(*price) as u128 * 10_000 / *price as u128) as u64)
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn broken(&self, rule: &AlertRule, readings: &Readings, now: u64) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        if let AlertRule::CoverageBelow { min_coverage_bps } = rule {
+            if let Some(coverage) = readings.coverage_bps.filter(|coverage| coverage < min_coverage_bps) {
+                alerts.push(Alert {
+                    rule: rule.kind(),
+                    risk: None,
+                    message: format!("Insurance covers {:.2}% of the vaults' TVL, below {:.2}%", percent(coverage), percent(*min_coverage_bps)),
+                });
+            }
+            return alerts;
+        }
+        for reading in readings.vaults.iter().filter(|reading| rule.covers(reading.risk)) {
+            let message = match rule {
+                AlertRule::ApyBelow { strategy: None, min_apy_bps, .. } if reading.apy_bps < *min_apy_bps => Some(format!(
+                    "{:?} Risk Vault APY is {:.2}%, below {:.2}%", reading.risk, percent(reading.apy_bps as u64), percent(*min_apy_bps as u64))),
+                AlertRule::ApyBelow { strategy: Some(strategy), min_apy_bps, .. } => reading.strategy_apys.iter()
+                    .find(|(id, apy)| id == strategy && apy < min_apy_bps)
+                    .map(|(_, apy)| format!("{:?} Risk Vault {} APY is {:.2}%, below {:.2}%",
+                        reading.risk, strategy, percent(*apy as u64), percent(*min_apy_bps as u64))),
+                AlertRule::SharePriceMove { max_move_bps, window_secs, .. } => {
+                    let moved = self.price_move_bps(reading.risk, reading.share_price, *window_secs, now);
+                    (moved > *max_move_bps).then(|| format!("{:?} Risk Vault share price moved {:.2}% within {} (limit {:.2}%)",
+                        reading.risk, percent(moved), format_duration(*window_secs), percent(*max_move_bps)))
+                }
+                _ => None,
+            };
+            if let Some(message) = message {
+                alerts.push(Alert { rule: rule.kind(), risk: Some(reading.risk), message });
+            }
+        }
+        alerts
+    }
+
+    // The alerts due now
+    pub fn check(&mut self, readings: &Readings, now: u64) -> Vec<Alert> {
+        let window = self.config.rules.iter()
+            .filter_map(|rule| match rule {
+                AlertRule::SharePriceMove { window_secs, .. } => Some(*window_secs),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+        for reading in &readings.vaults {
+            let prices = self.prices.entry(reading.risk).or_default();
+            prices.push_back((now, reading.share_price));
+            while prices.front().is_some_and(|(at, _)| now.saturating_sub(*at) > window) {
+                prices.pop_front();
+            }
+        }
+
+        let broken: Vec<((usize, Option<RiskLevel>), Alert)> = self.config.rules.iter().enumerate()
+            .flat_map(|(index, rule)| self.broken(rule, readings, now).into_iter()
+                .map(move |alert| ((index, alert.risk), alert)))
+            .collect();
+        let still_broken: HashSet<_> = broken.iter().map(|(key, _)| *key).collect();
+        self.firing.retain(|key, _| still_broken.contains(key));
+
+        let mut due = Vec::new();
+        for (key, alert) in broken {
+            if self.firing.get(&key).is_some_and(|last| now < last + self.config.cooldown_secs) {
+                continue;
+            }
+            self.firing.insert(key, now);
+            due.push(alert);
+        }
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn readings(share_price: u64, coverage_bps: u64) -> Readings {
+        Readings {
+            vaults: vec![VaultReading {
+                risk: RiskLevel::High,
+                share_price,
+                apy_bps: 1_200,
+                strategy_apys: vec![("YieldBloxLending".into(), 250)],
+            }],
+            coverage_bps: Some(coverage_bps),
+        }
+    }
+
+    #[test]
+    fn fires_once_per_cooldown_and_rearms() {
+        let mut engine = AlertEngine::new(AlertConfig {
+            rules: vec![
+                AlertRule::ApyBelow { risk: None, strategy: Some("YieldBloxLending".into()), min_apy_bps: 300 },
+                AlertRule::SharePriceMove { risk: Some(RiskLevel::High), max_move_bps: 200, window_secs: 600 },
+                AlertRule::CoverageBelow { min_coverage_bps: 500 },
+            ],
+            cooldown_secs: 3_600,
+        });
+        let alerts = engine.check(&readings(10_000_000, 800), 0);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].message, "High Risk Vault YieldBloxLending APY is 2.50%, below 3.00%");
+
+        // A 3% drop within the window, and coverage falling under 5%
+        let alerts = engine.check(&readings(9_700_000, 400), 300);
+        let rules: Vec<_> = alerts.iter().map(|alert| alert.rule).collect();
+        assert_eq!(rules, ["share_price_move", "coverage_below"]);
+
+        // Still broken, but within the cooldown
+        assert!(engine.check(&readings(9_700_000, 400), 600).is_empty());
+        // Coverage recovers and breaks again: it fires straight away
+        assert!(engine.check(&readings(9_700_000, 900), 700).is_empty());
+        let alerts = engine.check(&readings(9_700_000, 400), 800);
+        assert_eq!(alerts.iter().map(|alert| alert.rule).collect::<Vec<_>>(), ["coverage_below"]);
+        // The APY rule has been broken all along and is due again
+        let alerts = engine.check(&readings(9_700_000, 400), 3_601);
+        assert_eq!(alerts.iter().map(|alert| alert.rule).collect::<Vec<_>>(), ["apy_below"]);
+    }
+}
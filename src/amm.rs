@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use stellar_xdr::curr::{
+    Asset, ChangeTrustAsset, ChangeTrustOp, Hash, LiquidityPoolConstantProductParameters, LiquidityPoolDepositOp,
+    LiquidityPoolParameters, LiquidityPoolWithdrawOp, Limits, OperationBody, PoolId, WriteXdr,
+};
+
+use crate::amount::{mul_div, Rounding, Stroops};
+use crate::assets::{self, AssetId};
+use crate::error::VaultError;
+use crate::horizon::LiquidityPool;
+use crate::path_payment;
+use crate::sdex;
+
+// The only fee constant-product pools charge, in basis points
+pub const POOL_FEE_BP: i32 = 30;
+
+// ============================================================================
+// POOLS
+// ============================================================================
+
+// Pools list their assets in a fixed order, so both orders name one pool
+pub fn pool_parameters(a: Asset, b: Asset) -> LiquidityPoolParameters {
+    let (asset_a, asset_b) = if a <= b { (a, b) } else { (b, a) };
+    LiquidityPoolParameters::LiquidityPoolConstantProduct(LiquidityPoolConstantProductParameters {
+        asset_a,
+        asset_b,
+        fee: POOL_FEE_BP,
+    })
+}
+
+// SHA-256 of the pool's parameters, as Horizon shows it in hex
+pub fn pool_id(parameters: &LiquidityPoolParameters) -> Result<[u8; 32], VaultError> {
+    Ok(Sha256::digest(parameters.to_xdr(Limits::none())?).into())
+}
+
+pub fn pool_id_hex(parameters: &LiquidityPoolParameters) -> Result<String, VaultError> {
+    Ok(pool_id(parameters)?.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+// Pool shares need their own trustline before a deposit
+pub fn share_trust(parameters: &LiquidityPoolParameters) -> OperationBody {
+    OperationBody::ChangeTrust(ChangeTrustOp {
+        line: ChangeTrustAsset::PoolShare(parameters.clone()),
+        limit: assets::UNLIMITED,
+    })
+}
+
+// Adds up to the given amounts at a pool price (asset A per asset B) no more
+// than `slippage_bps` from `price`
+pub fn deposit(parameters: &LiquidityPoolParameters, max_a: u64, max_b: u64, price: f64, slippage_bps: u16) -> Result<OperationBody, VaultError> {
+    let band = slippage_bps as f64 / 10_000.0;
+    Ok(OperationBody::LiquidityPoolDeposit(LiquidityPoolDepositOp {
+        liquidity_pool_id: PoolId(Hash(pool_id(parameters)?)),
+        max_amount_a: i64::try_from(max_a)?,
+        max_amount_b: i64::try_from(max_b)?,
+        min_price: sdex::to_price(price * (1.0 - band))?,
+        max_price: sdex::to_price(price * (1.0 + band))?,
+    }))
+}
+
+pub fn withdraw(parameters: &LiquidityPoolParameters, shares: u64, min_a: u64, min_b: u64) -> Result<OperationBody, VaultError> {
+    Ok(OperationBody::LiquidityPoolWithdraw(LiquidityPoolWithdrawOp {
+        liquidity_pool_id: PoolId(Hash(pool_id(parameters)?)),
+        amount: i64::try_from(shares)?,
+        min_amount_a: i64::try_from(min_a)?,
+        min_amount_b: i64::try_from(min_b)?,
+    }))
+}
+
+// A pool's reserve of `asset`, in stroops
+pub fn reserve(pool: &LiquidityPool, asset: Option<&AssetId>) -> Result<u64, VaultError> {
+    let name = path_payment::canonical(asset);
+    let reserve = pool.reserves.iter()
+        .find(|reserve| reserve.asset == name)
+        .ok_or_else(|| format!("Pool {} doesn't hold {}", pool.id, path_payment::label(asset)))?;
+    Ok(Stroops::from_xlm_str(&reserve.amount)?.0)
+}
+
+// ============================================================================
+// POSITION
+// ============================================================================
+
+// The vault's stake in the pool pairing its asset with `counter` (None for
+// XLM). Yield is growth in the stake's value, read from the pool reserves.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolPosition {
+    pub counter: Option<AssetId>,
+    #[serde(default)]
+    pub shares: u64,
+    // Vault asset put into the pool and not yet taken out
+    #[serde(default)]
+    pub deposited: u64,
+    // Value at the last sync, in the vault's asset
+    #[serde(default)]
+    pub value: u64,
+    // Highest value already credited or deposited; growth above it is yield
+    #[serde(default)]
+    pub high_water: u64,
+}
+
+impl PoolPosition {
+    pub fn new(counter: Option<AssetId>) -> Self {
+        PoolPosition { counter, shares: 0, deposited: 0, value: 0, high_water: 0 }
+    }
+
+    pub fn parameters(&self, base: Option<&AssetId>) -> Result<LiquidityPoolParameters, VaultError> {
+        Ok(pool_parameters(path_payment::to_xdr(base)?, path_payment::to_xdr(self.counter.as_ref())?))
+    }
+
+    pub fn record_deposit(&mut self, amount: u64) {
+        self.deposited += amount;
+        self.high_water += amount;
+    }
+
+    pub fn record_withdrawal(&mut self) {
+        self.shares = 0;
+        self.deposited = 0;
+        self.value = 0;
+        self.high_water = 0;
+    }
+
+    // Value growth since the last harvest
+    pub fn take_unharvested(&mut self) -> u64 {
+        let gain = self.value.saturating_sub(self.high_water);
+        self.high_water = self.high_water.max(self.value);
+        gain
+    }
+}
+
+// Both halves of a stake valued in the base asset at the pool's own price,
+// which makes it twice the base reserve the shares are entitled to
+pub fn position_value(shares: u64, total_shares: u64, base_reserve: u64) -> u64 {
+    mul_div(shares, base_reserve, total_shares, Rounding::Down)
+        .map(|base| base.saturating_mul(2))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ISSUER: &str = "GCZEAWUJY3BRHCOKU6C5WRLCF5RFSGY22UGBPBXWL4T4G4SSEQMIYMCX";
+
+    #[test]
+    fn pools_are_named_by_their_sorted_assets() {
+        let usdc = AssetId::new("USDC", ISSUER).unwrap().to_xdr().unwrap();
+        let forward = pool_parameters(Asset::Native, usdc.clone());
+        let backward = pool_parameters(usdc, Asset::Native);
+        assert_eq!(pool_id_hex(&forward).unwrap(), pool_id_hex(&backward).unwrap());
+        let LiquidityPoolParameters::LiquidityPoolConstantProduct(params) = forward;
+        assert_eq!(params.asset_a, Asset::Native);
+
+        // 10% of a pool with 1,000 XLM on one side is worth 200 XLM
+        assert_eq!(position_value(100, 1_000, 10_000_000_000), 2_000_000_000);
+        let mut position = PoolPosition::new(None);
+        position.record_deposit(2_000_000_000);
+        position.value = 2_100_000_000;
+        assert_eq!(position.take_unharvested(), 100_000_000);
+        assert_eq!(position.take_unharvested(), 0);
+    }
+}
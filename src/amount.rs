@@ -0,0 +1,137 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::error::VaultError;
+
+// ============================================================================
+// FIXED-POINT AMOUNTS
+// ============================================================================
+
+pub const STROOPS_PER_XLM: u64 = 10_000_000;
+// Share prices are stroops per share, scaled so 1 share starts at 1 XLM
+pub const PRICE_SCALE: u64 = 10_000_000;
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum MathError {
+    #[error("arithmetic overflow")]
+    Overflow,
+    #[error("arithmetic underflow")]
+    Underflow,
+    #[error("division by zero")]
+    DivisionByZero,
+}
+
+// Every division states which way it rounds. Amounts leaving the vault round
+// down and amounts owed to the vault or the insurance pool round up, so
+// rounding dust always stays with the remaining holders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    Down,
+    Up,
+}
+
+// a * b / denominator with a u128 intermediate
+pub fn mul_div(a: u64, b: u64, denominator: u64, rounding: Rounding) -> Result<u64, MathError> {
+    if denominator == 0 {
+        return Err(MathError::DivisionByZero);
+    }
+    let product = a as u128 * b as u128;
+    let quotient = match rounding {
+        Rounding::Down => product / denominator as u128,
+        Rounding::Up => product.div_ceil(denominator as u128),
+    };
+    u64::try_from(quotient).map_err(|_| MathError::Overflow)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Stroops(pub u64);
+
+impl Stroops {
+    pub const ZERO: Stroops = Stroops(0);
+
+    pub fn checked_add(self, other: Stroops) -> Result<Stroops, MathError> {
+        self.0.checked_add(other.0).map(Stroops).ok_or(MathError::Overflow)
+    }
+
+    pub fn checked_sub(self, other: Stroops) -> Result<Stroops, MathError> {
+        self.0.checked_sub(other.0).map(Stroops).ok_or(MathError::Underflow)
+    }
+
+    // The given fraction of this amount, in basis points
+    pub fn bps(self, bps: u16, rounding: Rounding) -> Result<Stroops, MathError> {
+        mul_div(self.0, bps as u64, BPS_DENOMINATOR, rounding).map(Stroops)
+    }
+
+    // Exact decimal form with all 7 places, as Horizon expects payment amounts
+    pub fn to_xlm_string(self) -> String {
+        format!("{}.{:07}", self.0 / STROOPS_PER_XLM, self.0 % STROOPS_PER_XLM)
+    }
+
+    // Parses Horizon's decimal amounts ("12.5000000") without going through floats
+    pub fn from_xlm_str(amount: &str) -> Result<Stroops, VaultError> {
+        let invalid = || VaultError::Validation(format!("Invalid XLM amount {:?}", amount));
+        let (whole, fraction) = amount.trim().split_once('.').unwrap_or((amount.trim(), ""));
+        if whole.is_empty() || fraction.len() > 7
+            || !whole.bytes().chain(fraction.bytes()).all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let whole: u64 = whole.parse().map_err(|_| invalid())?;
+        let fraction: u64 = format!("{:0<7}", fraction).parse().map_err(|_| invalid())?;
+        whole.checked_mul(STROOPS_PER_XLM)
+            .and_then(|w| w.checked_add(fraction))
+            .map(Stroops)
+            .ok_or_else(invalid)
+    }
+}
+
+impl fmt::Display for Stroops {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} XLM", self.to_xlm_string())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Shares(pub u64);
+
+impl Shares {
+    pub const ZERO: Shares = Shares(0);
+
+    pub fn checked_add(self, other: Shares) -> Result<Shares, MathError> {
+        self.0.checked_add(other.0).map(Shares).ok_or(MathError::Overflow)
+    }
+
+    pub fn checked_sub(self, other: Shares) -> Result<Shares, MathError> {
+        self.0.checked_sub(other.0).map(Shares).ok_or(MathError::Underflow)
+    }
+}
+
+impl fmt::Display for Shares {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SharePrice(pub u64);
+
+impl SharePrice {
+    pub const INITIAL: SharePrice = SharePrice(PRICE_SCALE);
+
+    // An empty vault prices shares at INITIAL
+    pub fn from_totals(total_value: Stroops, total_shares: Shares) -> Result<SharePrice, MathError> {
+        if total_shares == Shares::ZERO {
+            return Ok(SharePrice::INITIAL);
+        }
+        mul_div(total_value.0, PRICE_SCALE, total_shares.0, Rounding::Down).map(SharePrice)
+    }
+}
+
+impl fmt::Display for SharePrice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Stroops(self.0).fmt(f)
+    }
+}
@@ -0,0 +1,1473 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use actix_cors::Cors;
+use actix_http::ws;
+use actix_web::body::{BodySize, MessageBody};
+use actix_web::dev::{Service, ServerHandle, ServiceRequest};
+use actix_web::http::{header, Method, StatusCode};
+use actix_web::web::{Bytes, BytesMut};
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer, ResponseError, Route};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::amount::Stroops;
+use crate::config::parse_risk;
+use crate::builder::{MAX_MANAGEMENT_FEE_BPS, MAX_PERFORMANCE_FEE_BPS};
+use crate::error::VaultError;
+use crate::events::{EventRecord, VaultEvent};
+use crate::health::{self, Check, Readiness};
+use crate::idempotency;
+use crate::horizon::HorizonClient;
+use crate::multisig::SignerSet;
+use crate::rate_limit::RateLimiter;
+use crate::rebalance::DEFAULT_MAX_DRIFT_BPS;
+use crate::screening::{AddressList, BLOCKED_SHOWN};
+use crate::soroban::RpcClient;
+use crate::web_auth::WebAuth;
+use crate::{failover, muxed, unix_now, RiskLevel};
+
+// ============================================================================
+// REST API
+// ============================================================================
+
+// Served with actix-web, which the crate already depends on, rather than
+// pulling in a second HTTP stack for it. The HTTP side only parses requests.
+// Each one is handed to the task that owns the vault, which answers them in
+// order, so the API sees exactly the vault the CLI does.
+pub enum ApiRequest {
+    Vaults,
+    Vault(RiskLevel),
+    Positions(String),
+    Deposit(DepositRequest),
+    Withdraw(WithdrawalRequest),
+    Pause { risk: RiskLevel, reason: String },
+    Resume(RiskLevel),
+    Rebalance { risk: RiskLevel, max_drift_bps: u64 },
+    // Approving pays the claim out of the insurance pool
+    DecideClaim { id: u64, approve: bool, note: String },
+    // None keeps the current rate
+    SetFees { risk: RiskLevel, management_fee_bps: Option<u16>, performance_fee_bps: Option<u16> },
+    // Both lists, and the operations they refused lately
+    AddressLists,
+    ListAddress { list: AddressList, address: String, note: String },
+    UnlistAddress { list: AddressList, address: String },
+    // Each scheduled job's last run and next one
+    Schedule,
+    // For /readyz: saves the vault's state, which shows both that its task
+    // is answering and that the storage takes writes
+    CheckStorage,
+}
+
+impl ApiRequest {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ApiRequest::Vaults => "vaults",
+            ApiRequest::Vault(_) => "vault",
+            ApiRequest::Positions(_) => "positions",
+            ApiRequest::Deposit(_) => "deposit",
+            ApiRequest::Withdraw(_) => "withdraw",
+            ApiRequest::Pause { .. } => "pause",
+            ApiRequest::Resume(_) => "resume",
+            ApiRequest::Rebalance { .. } => "rebalance",
+            ApiRequest::DecideClaim { .. } => "decide_claim",
+            ApiRequest::SetFees { .. } => "set_fees",
+            ApiRequest::AddressLists => "address_lists",
+            ApiRequest::ListAddress { .. } => "list_address",
+            ApiRequest::UnlistAddress { .. } => "unlist_address",
+            ApiRequest::Schedule => "schedule",
+            ApiRequest::CheckStorage => "check_storage",
+        }
+    }
+
+    // What an operator or admin request does, for the server's log
+    pub fn action(&self) -> Option<String> {
+        match self {
+            ApiRequest::Pause { risk, .. } => Some(format!("{:?} Risk Vault paused", risk)),
+            ApiRequest::Resume(risk) => Some(format!("{:?} Risk Vault resumed", risk)),
+            ApiRequest::Rebalance { risk, .. } => Some(format!("{:?} Risk Vault rebalanced", risk)),
+            ApiRequest::DecideClaim { id, approve, .. } => Some(format!("Claim #{} {}", id, if *approve { "approved" } else { "denied" })),
+            ApiRequest::SetFees { risk, .. } => Some(format!("{:?} Risk Vault fees changed", risk)),
+            ApiRequest::ListAddress { list, address, .. } => Some(format!("{} put on the {}", address, list)),
+            ApiRequest::UnlistAddress { list, address } => Some(format!("{} taken off the {}", address, list)),
+            _ => None,
+        }
+    }
+}
+
+// POST /deposits, signed by the account the server unlocked
+pub struct DepositRequest {
+    pub risk: RiskLevel,
+    pub amount: Stroops,
+    // XLM or CODE:ISSUER; the vault's own asset when absent
+    pub pay_with: Option<String>,
+    pub dry_run: bool,
+    // From the Idempotency-Key header: a retry with the same key gets the
+    // first request's answer instead of paying again
+    pub idempotency_key: Option<String>,
+}
+
+impl DepositRequest {
+    // What the request asks for, recorded with its idempotency key
+    pub fn fingerprint(&self) -> String {
+        format!("deposit {} {} into the {:?} Risk Vault", self.amount.to_xlm_string(),
+            self.pay_with.as_deref().unwrap_or("of the vault's asset"), self.risk)
+    }
+}
+
+// POST /withdrawals
+pub struct WithdrawalRequest {
+    pub risk: RiskLevel,
+    pub shares: u64,
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Error)]
+pub enum ApiError {
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("{0}")]
+    NotFound(String),
+    // No token, or one this server didn't issue or that has expired
+    #[error("{0}")]
+    Unauthorized(String),
+    // Valid credentials, but for another account or without the role needed
+    #[error("{0}")]
+    Forbidden(String),
+    // The vault refused or couldn't complete the action
+    #[error("{0}")]
+    Failed(String),
+    // The idempotency key's earlier request is still unresolved
+    #[error("{0}")]
+    Conflict(String),
+    #[error("The vault stopped answering requests")]
+    Unavailable,
+    // How long until the client may try again
+    #[error("Too many requests; retry in {}s", retry_after_secs(.0))]
+    RateLimited(Duration),
+}
+
+fn retry_after_secs(wait: &Duration) -> u64 {
+    wait.as_secs_f64().ceil().max(1.0) as u64
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::Failed(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let ApiError::RateLimited(wait) = self {
+            response.insert_header((header::RETRY_AFTER, retry_after_secs(wait)));
+        }
+        response.json(json!({ "error": self.to_string() }))
+    }
+}
+
+pub type ApiReply = Result<Value, ApiError>;
+
+pub struct ApiCall {
+    pub request: ApiRequest,
+    // None for the public endpoints
+    pub caller: Option<Caller>,
+    pub reply: oneshot::Sender<ApiReply>,
+}
+
+type Calls = web::Data<mpsc::UnboundedSender<ApiCall>>;
+
+// ============================================================================
+// ACCESS CONTROL
+// ============================================================================
+
+// What a caller may do; each role can do everything the ones before it can
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    // Reads positions
+    Viewer,
+    // Deposits and withdraws
+    Depositor,
+    // Pauses, resumes and rebalances vaults and decides insurance claims
+    Operator,
+    // Changes fees
+    Admin,
+}
+
+impl Role {
+    pub const ALL: [Role; 4] = [Role::Viewer, Role::Depositor, Role::Operator, Role::Admin];
+
+    pub fn parse(name: &str) -> Result<Role, VaultError> {
+        Role::ALL.into_iter()
+            .find(|role| role.to_string().eq_ignore_ascii_case(name))
+            .ok_or_else(|| VaultError::Validation(format!("Unknown role (expected viewer, depositor, operator or admin): {}", name)))
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Role::Viewer => "viewer",
+            Role::Depositor => "depositor",
+            Role::Operator => "operator",
+            Role::Admin => "admin",
+        })
+    }
+}
+
+// Who a request came from
+#[derive(Debug, Clone)]
+pub struct Caller {
+    pub role: Role,
+    // The account a SEP-10 token was issued for. API keys aren't tied to an
+    // account and act for the server as a whole.
+    pub account: Option<String>,
+}
+
+impl fmt::Display for Caller {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.account {
+            Some(account) => write!(f, "{} ({})", account, self.role),
+            None => write!(f, "API key ({})", self.role),
+        }
+    }
+}
+
+// How callers prove who they are, and the role each one gets
+pub struct Access {
+    pub web_auth: WebAuth,
+    // Accounts signing in with SEP-10 get these roles; any other account is a
+    // depositor for its own positions only
+    pub accounts: HashMap<String, Role>,
+    // Keys sent as X-API-Key, by the digest of the key
+    keys: Vec<([u8; 32], Role)>,
+}
+
+const API_KEY_HEADER: &str = "X-API-Key";
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+// Comparing digests keeps the time a comparison takes from leaking the key
+fn digest(key: &str) -> [u8; 32] {
+    Sha256::digest(key.as_bytes()).into()
+}
+
+impl Access {
+    pub fn new(web_auth: WebAuth) -> Self {
+        Access { web_auth, accounts: HashMap::new(), keys: Vec::new() }
+    }
+
+    pub fn with_account(mut self, account: &str, role: Role) -> Self {
+        self.accounts.insert(account.to_string(), role);
+        self
+    }
+
+    pub fn with_key(mut self, key: &str, role: Role) -> Self {
+        self.keys.push((digest(key), role));
+        self
+    }
+
+    fn known_key(&self, key: &str) -> Option<[u8; 32]> {
+        let key = digest(key);
+        self.keys.iter().any(|(known, _)| *known == key).then_some(key)
+    }
+
+    // The caller behind the request's API key or bearer token
+    fn caller(&self, request: &HttpRequest) -> Result<Caller, ApiError> {
+        let value_of = |name| request.headers().get(name).and_then(|value| value.to_str().ok());
+        if let Some(key) = value_of(API_KEY_HEADER) {
+            let key = digest(key);
+            return self.keys.iter()
+                .find(|(known, _)| *known == key)
+                .map(|&(_, role)| Caller { role, account: None })
+                .ok_or_else(|| ApiError::Unauthorized("Unknown API key".to_string()));
+        }
+        let token = value_of(header::AUTHORIZATION.as_str())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| ApiError::Unauthorized(format!(
+                "Authenticate at /auth and send the token as Authorization: Bearer <token>, or send an {} header", API_KEY_HEADER)))?;
+        let account = self.web_auth.authenticate(token, unix_now()).map_err(|e| ApiError::Unauthorized(e.to_string()))?;
+        let role = self.accounts.get(&account).copied().unwrap_or(Role::Depositor);
+        Ok(Caller { role, account: Some(account) })
+    }
+
+    // The caller, if their role is at least `role`
+    fn require(&self, request: &HttpRequest, role: Role) -> Result<Caller, ApiError> {
+        let caller = self.caller(request)?;
+        if caller.role < role {
+            return Err(ApiError::Forbidden(format!("This needs the {} role; the request came from {}", role, caller)));
+        }
+        Ok(caller)
+    }
+}
+
+pub struct ApiServer {
+    // Requests for the vault's task to answer
+    pub calls: mpsc::UnboundedReceiver<ApiCall>,
+    handle: ServerHandle,
+    subscribers: Subscribers,
+}
+
+// Requests a minute each client may make; 0 lifts the limit
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimits {
+    // Every request without a known API key, by the address it came from
+    pub per_ip: u32,
+    pub per_key: u32,
+}
+
+pub const DEFAULT_IP_RATE_LIMIT: u32 = 120;
+pub const DEFAULT_KEY_RATE_LIMIT: u32 = 1_200;
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        RateLimits { per_ip: DEFAULT_IP_RATE_LIMIT, per_key: DEFAULT_KEY_RATE_LIMIT }
+    }
+}
+
+struct Limiters {
+    per_ip: Option<RateLimiter>,
+    per_key: Option<RateLimiter>,
+}
+
+impl Limiters {
+    fn new(limits: RateLimits) -> Self {
+        let limiter = |per_minute| (per_minute > 0).then(|| RateLimiter::new(per_minute));
+        Limiters { per_ip: limiter(limits.per_ip), per_key: limiter(limits.per_key) }
+    }
+
+    // Unknown keys count against the address, so making keys up doesn't
+    // escape the limit. The address is the peer's own: behind a proxy every
+    // client shares the proxy's, and X-Forwarded-For is anyone's to forge.
+    fn admit(&self, request: &ServiceRequest, access: &Access) -> Result<(), ApiError> {
+        let now = Instant::now();
+        let key = request.headers().get(API_KEY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|key| access.known_key(key));
+        let verdict = match (key, &self.per_key, &self.per_ip) {
+            (Some(key), Some(limiter), _) => limiter.check(&key.iter().map(|b| format!("{:02x}", b)).collect::<String>(), now),
+            (Some(_), None, _) | (None, _, None) => Ok(()),
+            (None, _, Some(limiter)) => {
+                let ip = request.peer_addr().map(|addr| addr.ip().to_string()).unwrap_or_default();
+                limiter.check(&ip, now)
+            }
+        };
+        verdict.map_err(ApiError::RateLimited)
+    }
+}
+
+// What the vault relies on outside this process, for /readyz
+pub struct Dependencies {
+    pub horizon: HorizonClient,
+    pub soroban: Option<RpcClient>,
+    pub vault_address: String,
+    // Reads the configuration again, as a restart would
+    pub config_check: Box<dyn Fn() -> Result<(), VaultError> + Send + Sync>,
+}
+
+// Starts the server in the background. Browsers may only call it from
+// `origins`; with none, only same-origin pages and non-browser clients can.
+// Everything but the vault listings and health checks needs an API key or a
+// SEP-10 token from `access`, whose challenges are checked against the
+// account's signers on Horizon. Each client is held to `limits`, which also
+// keeps the server from being used to flood Horizon. The server runs until
+// it is stopped or `calls` is dropped.
+pub fn spawn(bind: &str, origins: Vec<String>, access: Access, dependencies: Dependencies, limits: RateLimits) -> std::io::Result<ApiServer> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let calls = web::Data::new(sender);
+    let access = web::Data::new(access);
+    let horizon = web::Data::new(dependencies.horizon.clone());
+    let dependencies = web::Data::new(dependencies);
+    let subscribers = Subscribers::default();
+    let clients = web::Data::new(subscribers.clone());
+    let limiters = Arc::new(Limiters::new(limits));
+
+    let server = HttpServer::new(move || {
+        let cors = origins.iter().fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+            .allowed_methods(["GET", "POST", "DELETE"])
+            .allow_any_header();
+        let (limiters, admitting) = (limiters.clone(), access.clone());
+        // Inside CORS, so browsers can read the 429
+        App::new()
+            .wrap_fn(move |request, service| {
+                let response = limiters.admit(&request, &admitting).map(|()| service.call(request));
+                async move { response?.await }
+            })
+            .wrap(cors)
+            .app_data(calls.clone())
+            .app_data(clients.clone())
+            .app_data(access.clone())
+            .app_data(horizon.clone())
+            .app_data(dependencies.clone())
+            .configure(routes)
+    })
+        .disable_signals()
+        .bind(bind)?
+        .run();
+    let handle = server.handle();
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            crate::say!("❌ API server stopped: {}", e);
+        }
+    });
+
+    Ok(ApiServer { calls: receiver, handle, subscribers })
+}
+
+// One line per endpoint, so what is registered can be listed and checked
+// against `openapi`
+macro_rules! endpoints {
+    ($($method:ident $path:literal => $handler:expr,)*) => {
+        vec![$((Method::$method, $path, web::route().method(Method::$method).to($handler)),)*]
+    };
+}
+
+fn endpoints() -> Vec<(Method, &'static str, Route)> {
+    endpoints![
+        GET "/auth" => auth_challenge,
+        POST "/auth" => auth_token,
+        GET "/vaults" => vaults,
+        GET "/vaults/{risk}" => vault,
+        POST "/vaults/{risk}/pause" => pause,
+        POST "/vaults/{risk}/resume" => resume,
+        POST "/vaults/{risk}/rebalance" => rebalance,
+        POST "/vaults/{risk}/fees" => set_fees,
+        GET "/positions/{account}" => positions,
+        POST "/deposits" => deposit,
+        POST "/withdrawals" => withdraw,
+        POST "/claims/{id}/{decision}" => decide_claim,
+        GET "/address-lists" => address_lists,
+        POST "/address-lists/{list}" => list_address,
+        DELETE "/address-lists/{list}/{address}" => unlist_address,
+        GET "/schedule" => schedule,
+        GET "/horizon" => horizon_endpoints,
+        GET "/ws" => live_events,
+        GET "/healthz" => || async { HttpResponse::Ok().json(json!({ "status": "ok" })) },
+        GET "/readyz" => readyz,
+        GET "/openapi.json" => || async { HttpResponse::Ok().json(openapi()) },
+        GET "/docs" => || async { HttpResponse::Ok().content_type("text/html; charset=utf-8").body(SWAGGER_UI) },
+    ]
+}
+
+pub fn routes(config: &mut web::ServiceConfig) {
+    for (_, path, route) in endpoints() {
+        config.route(path, route);
+    }
+    // Malformed bodies get the same {"error": ...} shape as everything else
+    config.app_data(web::JsonConfig::default()
+        .error_handler(|e, _| ApiError::BadRequest(e.to_string()).into()));
+}
+
+async fn ask(calls: &Calls, caller: Option<Caller>, request: ApiRequest) -> ApiReply {
+    let (reply, answer) = oneshot::channel();
+    calls.send(ApiCall { request, caller, reply }).map_err(|_| ApiError::Unavailable)?;
+    answer.await.map_err(|_| ApiError::Unavailable)?
+}
+
+async fn call(calls: &Calls, caller: Option<Caller>, request: ApiRequest) -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(ask(calls, caller, request).await?))
+}
+
+// 200 when everything the vault needs answers, 503 with the failing checks
+// otherwise. /healthz only says the server is up, so a slow dependency never
+// gets the process restarted.
+async fn readyz(calls: Calls, dependencies: web::Data<Dependencies>) -> HttpResponse {
+    let soroban = async {
+        match &dependencies.soroban {
+            Some(rpc) => health::check(rpc.latest_ledger()).await,
+            None => Check::not_configured(),
+        }
+    };
+    let (horizon, soroban, storage, config) = tokio::join!(
+        health::check(dependencies.horizon.fresh_account(&dependencies.vault_address)),
+        soroban,
+        health::check(ask(&calls, None, ApiRequest::CheckStorage)),
+        health::check(async { (dependencies.config_check)() }),
+    );
+    let mut readiness = Readiness::default();
+    readiness.add("horizon", horizon);
+    readiness.add("soroban_rpc", soroban);
+    readiness.add("storage", storage);
+    readiness.add("config", config);
+    let status = if readiness.is_ready() { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    HttpResponse::build(status).json(readiness.to_json())
+}
+
+#[derive(Deserialize)]
+struct ChallengeQuery {
+    account: String,
+}
+
+async fn auth_challenge(access: web::Data<Access>, query: web::Query<ChallengeQuery>) -> Result<HttpResponse, ApiError> {
+    let auth = &access.web_auth;
+    let transaction = auth.challenge(&query.account, unix_now()).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    Ok(HttpResponse::Ok().json(json!({ "transaction": transaction, "network_passphrase": auth.passphrase() })))
+}
+
+#[derive(Deserialize)]
+struct SignedChallenge {
+    transaction: String,
+}
+
+async fn auth_token(access: web::Data<Access>, horizon: web::Data<HorizonClient>, body: web::Json<SignedChallenge>) -> Result<HttpResponse, ApiError> {
+    let auth = &access.web_auth;
+    let now = unix_now();
+    let challenge = auth.read_challenge(&body.transaction, now).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    // The signers as they are now, so a key removed from the account stops working
+    let signers = match horizon.account(&challenge.account).await {
+        Ok(account) => SignerSet::from_account(&account),
+        Err(e) if e.is_not_found() => SignerSet::master_only(&challenge.account),
+        Err(e) => return Err(ApiError::Failed(format!("Could not load the signers of {}: {}", challenge.account, e))),
+    };
+    auth.verify(&challenge, &signers).map_err(|e| ApiError::Unauthorized(e.to_string()))?;
+    Ok(HttpResponse::Ok().json(json!({ "token": auth.token(&challenge, now) })))
+}
+
+fn risk(name: &str) -> Result<RiskLevel, ApiError> {
+    parse_risk(name, "risk").map_err(|e| ApiError::NotFound(e.to_string()))
+}
+
+async fn vaults(calls: Calls) -> Result<HttpResponse, ApiError> {
+    call(&calls, None, ApiRequest::Vaults).await
+}
+
+async fn vault(calls: Calls, path: web::Path<String>) -> Result<HttpResponse, ApiError> {
+    call(&calls, None, ApiRequest::Vault(risk(&path)?)).await
+}
+
+async fn schedule(request: HttpRequest, access: web::Data<Access>, calls: Calls) -> Result<HttpResponse, ApiError> {
+    let caller = access.require(&request, Role::Viewer)?;
+    call(&calls, Some(caller), ApiRequest::Schedule).await
+}
+
+// Which Horizon server calls go to and how each has fared, straight from the
+// client so it answers while the vault is busy
+async fn horizon_endpoints(request: HttpRequest, access: web::Data<Access>, dependencies: web::Data<Dependencies>) -> Result<HttpResponse, ApiError> {
+    access.require(&request, Role::Viewer)?;
+    Ok(HttpResponse::Ok().json(dependencies.horizon.status()))
+}
+
+// Tokens see their own account, unless it is an operator's or admin's; API
+// keys see every account
+async fn positions(request: HttpRequest, access: web::Data<Access>, calls: Calls, path: web::Path<String>) -> Result<HttpResponse, ApiError> {
+    let caller = access.require(&request, Role::Viewer)?;
+    let account = path.into_inner();
+    let address = muxed::parse(&account).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    if caller.role < Role::Operator && caller.account.as_ref().is_some_and(|own| *own != address.account) {
+        return Err(ApiError::Forbidden(format!("The token isn't for {}", account)));
+    }
+    call(&calls, Some(caller), ApiRequest::Positions(account)).await
+}
+
+// Amounts are decimal strings, as every amount in the API's responses is
+#[derive(Deserialize)]
+struct DepositBody {
+    risk: String,
+    amount: String,
+    #[serde(default)]
+    pay_with: Option<String>,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+async fn deposit(request: HttpRequest, access: web::Data<Access>, calls: Calls, body: web::Json<DepositBody>) -> Result<HttpResponse, ApiError> {
+    let caller = access.require(&request, Role::Depositor)?;
+    let body = body.into_inner();
+    let risk = parse_risk(&body.risk, "risk").map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    let amount = match Stroops::from_xlm_str(&body.amount) {
+        Ok(Stroops(0)) => return Err(ApiError::BadRequest("The amount must be more than 0".to_string())),
+        Ok(amount) => amount,
+        Err(e) => return Err(ApiError::BadRequest(e.to_string())),
+    };
+    let idempotency_key = match request.headers().get(IDEMPOTENCY_KEY_HEADER) {
+        Some(value) => {
+            let key = value.to_str().map_err(|_| ApiError::BadRequest(format!("{} must be printable ASCII", IDEMPOTENCY_KEY_HEADER)))?;
+            idempotency::check_key(key).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+            Some(key.to_string())
+        }
+        None => None,
+    };
+    let deposit = DepositRequest { risk, amount, pay_with: body.pay_with, dry_run: body.dry_run, idempotency_key };
+    call(&calls, Some(caller), ApiRequest::Deposit(deposit)).await
+}
+
+#[derive(Deserialize)]
+struct WithdrawalBody {
+    risk: String,
+    shares: u64,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+async fn withdraw(request: HttpRequest, access: web::Data<Access>, calls: Calls, body: web::Json<WithdrawalBody>) -> Result<HttpResponse, ApiError> {
+    let caller = access.require(&request, Role::Depositor)?;
+    let body = body.into_inner();
+    let risk = parse_risk(&body.risk, "risk").map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    if body.shares == 0 {
+        return Err(ApiError::BadRequest("The shares to withdraw must be more than 0".to_string()));
+    }
+    call(&calls, Some(caller), ApiRequest::Withdraw(WithdrawalRequest { risk, shares: body.shares, dry_run: body.dry_run })).await
+}
+
+#[derive(Deserialize)]
+struct PauseBody {
+    reason: String,
+}
+
+async fn pause(request: HttpRequest, access: web::Data<Access>, calls: Calls, path: web::Path<String>, body: web::Json<PauseBody>) -> Result<HttpResponse, ApiError> {
+    let caller = access.require(&request, Role::Operator)?;
+    let reason = body.into_inner().reason.trim().to_string();
+    if reason.is_empty() {
+        return Err(ApiError::BadRequest("Give a reason for pausing the vault".to_string()));
+    }
+    call(&calls, Some(caller), ApiRequest::Pause { risk: risk(&path)?, reason }).await
+}
+
+async fn resume(request: HttpRequest, access: web::Data<Access>, calls: Calls, path: web::Path<String>) -> Result<HttpResponse, ApiError> {
+    let caller = access.require(&request, Role::Operator)?;
+    call(&calls, Some(caller), ApiRequest::Resume(risk(&path)?)).await
+}
+
+#[derive(Deserialize)]
+struct RebalanceQuery {
+    max_drift_bps: Option<u64>,
+}
+
+async fn rebalance(request: HttpRequest, access: web::Data<Access>, calls: Calls, path: web::Path<String>, query: web::Query<RebalanceQuery>) -> Result<HttpResponse, ApiError> {
+    let caller = access.require(&request, Role::Operator)?;
+    let max_drift_bps = query.max_drift_bps.unwrap_or(DEFAULT_MAX_DRIFT_BPS);
+    call(&calls, Some(caller), ApiRequest::Rebalance { risk: risk(&path)?, max_drift_bps }).await
+}
+
+#[derive(Deserialize)]
+struct FeesBody {
+    #[serde(default)]
+    management_fee_bps: Option<u16>,
+    #[serde(default)]
+    performance_fee_bps: Option<u16>,
+}
+
+async fn set_fees(request: HttpRequest, access: web::Data<Access>, calls: Calls, path: web::Path<String>, body: web::Json<FeesBody>) -> Result<HttpResponse, ApiError> {
+    let caller = access.require(&request, Role::Admin)?;
+    let body = body.into_inner();
+    if body.management_fee_bps.is_none() && body.performance_fee_bps.is_none() {
+        return Err(ApiError::BadRequest("Give management_fee_bps, performance_fee_bps or both".to_string()));
+    }
+    call(&calls, Some(caller), ApiRequest::SetFees {
+        risk: risk(&path)?,
+        management_fee_bps: body.management_fee_bps,
+        performance_fee_bps: body.performance_fee_bps,
+    }).await
+}
+
+async fn address_lists(request: HttpRequest, access: web::Data<Access>, calls: Calls) -> Result<HttpResponse, ApiError> {
+    let caller = access.require(&request, Role::Admin)?;
+    call(&calls, Some(caller), ApiRequest::AddressLists).await
+}
+
+fn address_list(name: &str) -> Result<AddressList, ApiError> {
+    AddressList::parse(name).map_err(|_| ApiError::NotFound(format!("The lists are allowlist and denylist, not {}", name)))
+}
+
+#[derive(Deserialize)]
+struct ListBody {
+    address: String,
+    #[serde(default)]
+    note: String,
+}
+
+async fn list_address(request: HttpRequest, access: web::Data<Access>, calls: Calls, path: web::Path<String>, body: web::Json<ListBody>) -> Result<HttpResponse, ApiError> {
+    let caller = access.require(&request, Role::Admin)?;
+    let list = address_list(&path)?;
+    let body = body.into_inner();
+    muxed::parse(&body.address).map_err(|e| ApiError::BadRequest(e.to_string()))?;
+    call(&calls, Some(caller), ApiRequest::ListAddress { list, address: body.address, note: body.note.trim().to_string() }).await
+}
+
+async fn unlist_address(request: HttpRequest, access: web::Data<Access>, calls: Calls, path: web::Path<(String, String)>) -> Result<HttpResponse, ApiError> {
+    let caller = access.require(&request, Role::Admin)?;
+    let (list, address) = path.into_inner();
+    call(&calls, Some(caller), ApiRequest::UnlistAddress { list: address_list(&list)?, address }).await
+}
+
+#[derive(Deserialize)]
+struct DecisionBody {
+    note: String,
+}
+
+async fn decide_claim(request: HttpRequest, access: web::Data<Access>, calls: Calls, path: web::Path<(u64, String)>, body: web::Json<DecisionBody>) -> Result<HttpResponse, ApiError> {
+    let caller = access.require(&request, Role::Operator)?;
+    let (id, decision) = path.into_inner();
+    let approve = match decision.as_str() {
+        "approve" => true,
+        "deny" => false,
+        _ => return Err(ApiError::NotFound(format!("Claims are approved or denied, not {}", decision))),
+    };
+    call(&calls, Some(caller), ApiRequest::DecideClaim { id, approve, note: body.into_inner().note }).await
+}
+
+// ============================================================================
+// LIVE EVENTS
+// ============================================================================
+
+// What /ws clients receive, one JSON text frame per event
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveEvent<'a> {
+    // An entry in the vault's event log: deposits with the shares they
+    // minted, withdrawals, harvests, rebalances and the rest
+    Vault(&'a EventRecord),
+    SharePrice { risk: RiskLevel, share_price: String, timestamp: u64 },
+}
+
+// Frames a client may fall behind by before it is disconnected
+const CLIENT_BACKLOG: usize = 64;
+
+struct Subscriber {
+    frames: mpsc::Sender<Bytes>,
+    // A depositor's or viewer's token only sees its own account's events
+    account: Option<String>,
+}
+
+#[derive(Clone, Default)]
+struct Subscribers(Arc<Mutex<Vec<Subscriber>>>);
+
+fn frame(event: &LiveEvent) -> Option<Bytes> {
+    let json = serde_json::to_string(event).ok()?;
+    let mut frame = BytesMut::new();
+    ws::Parser::write_message(&mut frame, json, ws::OpCode::Text, true, false);
+    Some(frame.freeze())
+}
+
+fn is_account(address: &str, account: &str) -> bool {
+    muxed::parse(address).is_ok_and(|parsed| parsed.account == account)
+}
+
+// What of a logged event `account` may see: its own deposits, withdrawals
+// and yield, and vault-wide events without other accounts' shares in them.
+// Refusals by the address lists are for admins.
+fn visible_to(record: &EventRecord, account: &str) -> Option<EventRecord> {
+    let event = match &record.event {
+        VaultEvent::Deposit { user, .. } | VaultEvent::Withdrawal { user, .. } if !is_account(user, account) => return None,
+        VaultEvent::Harvest { risk, strategy_yield, performance_fee, credits, insurance_yield, insurance_shares } => VaultEvent::Harvest {
+            risk: *risk,
+            strategy_yield: strategy_yield.clone(),
+            performance_fee: *performance_fee,
+            credits: credits.iter().filter(|credit| is_account(&credit.user, account)).cloned().collect(),
+            insurance_yield: *insurance_yield,
+            insurance_shares: *insurance_shares,
+        },
+        VaultEvent::YieldDistributed { risk, payouts, tx_hash } => {
+            let payouts: Vec<_> = payouts.iter().filter(|payout| is_account(&payout.user, account)).cloned().collect();
+            if payouts.is_empty() {
+                return None;
+            }
+            VaultEvent::YieldDistributed { risk: *risk, payouts, tx_hash: tx_hash.clone() }
+        }
+        VaultEvent::OperationBlocked { .. } => return None,
+        event => event.clone(),
+    };
+    Some(EventRecord { seq: record.seq, timestamp: record.timestamp, event })
+}
+
+impl ApiServer {
+    // Sends the event to every /ws client allowed to see it. A client that
+    // can't keep up is dropped, so one stalled dashboard never holds up the vault.
+    pub fn publish(&self, event: &LiveEvent) {
+        let Some(whole) = frame(event) else {
+            return;
+        };
+        self.subscribers.0.lock().unwrap().retain(|client| {
+            let frame = match (&client.account, event) {
+                (Some(account), LiveEvent::Vault(record)) => match visible_to(record, account) {
+                    Some(own) => frame(&LiveEvent::Vault(&own)),
+                    None => return true,
+                },
+                _ => Some(whole.clone()),
+            };
+            frame.is_none_or(|frame| client.frames.try_send(frame).is_ok())
+        });
+    }
+
+    // Closes the /ws streams, which would otherwise hold a graceful stop
+    // open until it times out, then lets in-flight requests finish
+    pub async fn stop(self) {
+        self.subscribers.0.lock().unwrap().clear();
+        self.handle.stop(true).await;
+    }
+}
+
+// The channel is one-way: events go out and anything a client sends is ignored
+// Authenticated as /positions is: tokens below operator only get their own
+// account's events
+async fn live_events(request: HttpRequest, access: web::Data<Access>, subscribers: web::Data<Subscribers>) -> Result<HttpResponse<EventStream>, actix_web::Error> {
+    let caller = access.require(&request, Role::Viewer)?;
+    let account = caller.account.filter(|_| caller.role < Role::Operator);
+    let mut response = ws::handshake(request.head())?;
+    let (sender, receiver) = mpsc::channel(CLIENT_BACKLOG);
+    subscribers.0.lock().unwrap().push(Subscriber { frames: sender, account });
+    Ok(response.message_body(EventStream(receiver))?.into())
+}
+
+// One client's frames; the socket closes once publish drops the client
+struct EventStream(mpsc::Receiver<Bytes>);
+
+impl MessageBody for EventStream {
+    type Error = std::convert::Infallible;
+
+    fn size(&self) -> BodySize {
+        BodySize::Stream
+    }
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        self.0.poll_recv(cx).map(|frame| frame.map(Ok))
+    }
+}
+
+// ============================================================================
+// OPENAPI DOCUMENT
+// ============================================================================
+
+// Swagger UI from its CDN, pointed at /openapi.json
+const SWAGGER_UI: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+  <title>StellarVault API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });</script>
+</body>
+</html>
+"##;
+
+// Amounts are decimal strings with 7 places, so no precision is lost
+fn amount() -> Value {
+    json!({ "type": "string", "pattern": "^-?[0-9]+\\.[0-9]{7}$", "example": "100.0000000" })
+}
+
+fn schema(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+fn object(required: &[&str], properties: Value) -> Value {
+    json!({ "type": "object", "required": required, "properties": properties })
+}
+
+fn reply(description: &str, body: Value) -> Value {
+    json!({ "description": description, "content": { "application/json": { "schema": body } } })
+}
+
+fn errors(statuses: &[(u16, &str)]) -> serde_json::Map<String, Value> {
+    statuses.iter().map(|(status, description)| (status.to_string(), reply(description, schema("Error")))).collect()
+}
+
+fn responses(ok: Value, failures: &[(u16, &str)]) -> Value {
+    let mut responses = errors(failures);
+    responses.insert("200".to_string(), ok);
+    // Every client is rate limited
+    responses.insert("429".to_string(), reply("Too many requests; retry after the Retry-After header's seconds", schema("Error")));
+    Value::Object(responses)
+}
+
+fn signed_in() -> Value {
+    json!([{ "sep10": [] }, { "apiKey": [] }])
+}
+
+// An endpoint for operators and admins, or admins only
+fn privileged(summary: &str, operation: &str, role: Role, parameters: Value, body: Option<Value>, ok: Value) -> Value {
+    let mut endpoint = json!({
+        "summary": summary,
+        "description": format!("Needs the {} role or above.", role),
+        "operationId": operation,
+        "security": signed_in(),
+        "parameters": parameters,
+        "responses": responses(ok, &[(400, "Invalid request"), (401, "No valid token or API key"),
+            (403, "The caller's role is below the one needed"), (404, "No such vault or claim"),
+            (422, "The vault refused or couldn't complete the action")]),
+    });
+    if let Some(body) = body {
+        endpoint["requestBody"] = json!({ "required": true, "content": { "application/json": { "schema": body } } });
+    }
+    json!({ "post": endpoint })
+}
+
+// `privileged` for a method other than POST
+fn privileged_as(method: &str, mut endpoint: Value) -> Value {
+    let mut methods = serde_json::Map::new();
+    methods.insert(method.to_string(), endpoint["post"].take());
+    Value::Object(methods)
+}
+
+fn list_parameter() -> Value {
+    json!({ "name": "list", "in": "path", "required": true, "schema": { "type": "string", "enum": ["allowlist", "denylist"] } })
+}
+
+fn claim_parameter() -> Value {
+    json!({ "name": "id", "in": "path", "required": true, "schema": { "type": "integer" } })
+}
+
+fn risk_parameter() -> Value {
+    json!({ "name": "risk", "in": "path", "required": true, "schema": schema("Risk") })
+}
+
+// The OpenAPI 3.0 description of `routes`, served at /openapi.json. The
+// response shapes are the JSON the matching commands print with --output json.
+pub fn openapi() -> Value {
+    let transaction = object(&["hash", "ledger", "created_at", "fee_charged"], json!({
+        "hash": { "type": "string" },
+        "ledger": { "type": "integer" },
+        "created_at": { "type": "string" },
+        "fee_charged": { "type": "integer", "description": "Stroops" },
+    }));
+    let preview = object(&["hash", "fee_per_operation", "xdr"], json!({
+        "hash": { "type": "string" },
+        "fee_per_operation": { "type": "integer", "description": "Stroops" },
+        "xdr": { "type": "string", "description": "Unsigned transaction envelope, base64" },
+    }));
+
+    let sep10 = json!({ "type": "http", "scheme": "bearer", "bearerFormat": "JWT",
+        "description": "A token from POST /auth. The account gets the role it is configured with, or depositor for its own positions." });
+    let api_key = json!({ "type": "apiKey", "in": "header", "name": API_KEY_HEADER,
+        "description": "A key from STELLARVAULT_API_KEYS, carrying its role for every account" });
+    let note = object(&["note"], json!({ "note": { "type": "string" } }));
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "StellarVault API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "The vaults served by `stellarvault serve`. Deposits and withdrawals are signed by the account the server unlocked. Roles, each allowed everything the ones before it are: viewer (positions), depositor (deposits and withdrawals), operator (pausing, rebalancing, insurance claims) and admin (fees, address lists).",
+        },
+        "paths": {
+            "/auth": {
+                "get": {
+                    "summary": "A SEP-10 challenge transaction for the account to sign",
+                    "operationId": "getChallenge",
+                    "parameters": [{ "name": "account", "in": "query", "required": true, "schema": { "type": "string", "description": "G... address" } }],
+                    "responses": responses(reply("The challenge", schema("Challenge")), &[(400, "Invalid account")]),
+                },
+                "post": {
+                    "summary": "Trade a signed challenge for a token",
+                    "operationId": "getToken",
+                    "requestBody": { "required": true, "content": { "application/json": { "schema": object(&["transaction"], json!({
+                        "transaction": { "type": "string", "description": "The challenge, signed by keys carrying the account's medium threshold" },
+                    })) } } },
+                    "responses": responses(reply("A JWT for the account, sent as Authorization: Bearer <token>", object(&["token"], json!({
+                        "token": { "type": "string" },
+                    }))), &[(400, "Not a current challenge from this server"), (401, "Not signed by the account's signers")]),
+                },
+            },
+            "/vaults": { "get": {
+                "summary": "Every vault",
+                "operationId": "listVaults",
+                "responses": responses(reply("The vaults, low risk first", json!({ "type": "array", "items": schema("Vault") })), &[]),
+            } },
+            "/vaults/{risk}": { "get": {
+                "summary": "One vault's TVL, fees and strategies",
+                "operationId": "getVault",
+                "parameters": [risk_parameter()],
+                "responses": responses(reply("The vault", schema("Vault")), &[(404, "No such vault")]),
+            } },
+            "/vaults/{risk}/pause": privileged("Pause deposits and withdrawals", "pauseVault", Role::Operator, json!([risk_parameter()]),
+                Some(object(&["reason"], json!({ "reason": { "type": "string" } }))), reply("The paused vault", schema("Vault"))),
+            "/vaults/{risk}/resume": privileged("Resume a paused vault", "resumeVault", Role::Operator, json!([risk_parameter()]),
+                None, reply("The resumed vault", schema("Vault"))),
+            "/vaults/{risk}/rebalance": privileged("Move allocation back to the strategies' targets", "rebalanceVault", Role::Operator,
+                json!([risk_parameter(), { "name": "max_drift_bps", "in": "query", "schema": { "type": "integer", "default": DEFAULT_MAX_DRIFT_BPS },
+                    "description": "Only rebalance when a strategy drifts further than this" }]),
+                None, reply("The moves made, none when within the threshold", object(&["risk", "max_drift_bps", "moves"], json!({
+                    "risk": { "type": "string" },
+                    "max_drift_bps": { "type": "integer" },
+                    "moves": { "type": "array", "items": object(&["from", "to", "amount"], json!({
+                        "from": { "type": "string" },
+                        "to": { "type": "string" },
+                        "amount": { "type": "integer", "description": "Stroops" },
+                    })) },
+                })))),
+            "/vaults/{risk}/fees": privileged("Change a vault's fee rates; increases wait out the admin delay", "setFees", Role::Admin, json!([risk_parameter()]),
+                Some(object(&[], json!({
+                    "management_fee_bps": { "type": "integer", "maximum": MAX_MANAGEMENT_FEE_BPS, "description": "Yearly; unchanged when absent" },
+                    "performance_fee_bps": { "type": "integer", "maximum": MAX_PERFORMANCE_FEE_BPS, "description": "Of harvested yield; unchanged when absent" },
+                }))),
+                reply("The vault with its new rates and what changed, or the queued change for an increase", json!({ "oneOf": [
+                    { "allOf": [schema("Vault"), object(&["changes"], json!({
+                        "changes": { "type": "array", "items": { "type": "string" } },
+                    }))] },
+                    object(&["status", "change"], json!({
+                        "status": { "type": "string", "enum": ["queued"] },
+                        "change": { "type": "object", "description": "With its id and takes_effect_at, in Unix seconds; 'admin cancel' stops it until then" },
+                    })),
+                ] }))),
+            "/claims/{id}/approve": privileged("Approve an insurance claim and pay it from the pool", "approveClaim", Role::Operator,
+                json!([claim_parameter()]), Some(note.clone()), reply("The paid claim", object(&["id", "status", "transaction"], json!({
+                    "id": { "type": "integer" },
+                    "status": { "type": "string", "enum": ["paid"] },
+                    "transaction": transaction.clone(),
+                })))),
+            "/claims/{id}/deny": privileged("Deny an insurance claim", "denyClaim", Role::Operator,
+                json!([claim_parameter()]), Some(note), reply("The denied claim", object(&["id", "status"], json!({
+                    "id": { "type": "integer" },
+                    "status": { "type": "string", "enum": ["denied"] },
+                })))),
+            "/address-lists": privileged_as("get", privileged("The allowlist and denylist, and the latest deposits and payouts they refused",
+                "getAddressLists", Role::Admin, json!([]), None, reply("The lists", schema("AddressLists")))),
+            "/address-lists/{list}": privileged("Put an account (G..., covering its muxed addresses) or one M... address on a list; it leaves the other",
+                "listAddress", Role::Admin, json!([list_parameter()]), Some(object(&["address"], json!({
+                    "address": { "type": "string" },
+                    "note": { "type": "string", "description": "Why, such as a case reference" },
+                }))), reply("The listed address", schema("ListChange"))),
+            "/address-lists/{list}/{address}": privileged_as("delete", privileged("Take an address off a list", "unlistAddress", Role::Admin,
+                json!([list_parameter(), { "name": "address", "in": "path", "required": true, "schema": { "type": "string" } }]),
+                None, reply("The unlisted address", schema("ListChange")))),
+            "/positions/{account}": { "get": {
+                "summary": "An account's position in each vault",
+                "operationId": "getPositions",
+                "parameters": [{ "name": "account", "in": "path", "required": true, "schema": { "type": "string", "description": "G... or M... address" } }],
+                "security": signed_in(),
+                "responses": responses(reply("The positions", schema("Positions")),
+                    &[(400, "Invalid address"), (401, "No valid token or API key"), (403, "The token is for another account")]),
+            } },
+            "/deposits": { "post": {
+                "summary": "Deposit into a vault",
+                "operationId": "deposit",
+                "security": signed_in(),
+                "parameters": [{ "name": IDEMPOTENCY_KEY_HEADER, "in": "header", "schema": { "type": "string", "maxLength": idempotency::MAX_KEY_LEN },
+                    "description": "A key unique to this deposit, such as a UUID. Retrying with the same key within the server's idempotency window returns the first request's answer instead of depositing again." }],
+                "requestBody": { "required": true, "content": { "application/json": { "schema": schema("DepositRequest") } } },
+                "responses": responses(reply("The deposit, or what it would do with dry_run", schema("Deposit")),
+                    &[(400, "Invalid request, or an Idempotency-Key already used for a different deposit"), (401, "No valid token or API key"),
+                      (403, "A viewer, or a token for an account the server doesn't sign for"),
+                      (409, "The deposit first made with this Idempotency-Key is still being settled"),
+                      (422, "The vault refused or couldn't complete the deposit")]),
+            } },
+            "/withdrawals": { "post": {
+                "summary": "Redeem shares from a vault",
+                "operationId": "withdraw",
+                "security": signed_in(),
+                "requestBody": { "required": true, "content": { "application/json": { "schema": schema("WithdrawalRequest") } } },
+                "responses": responses(reply("The withdrawal, queued if the liquid reserve can't cover it yet", schema("Withdrawal")),
+                    &[(400, "Invalid request"), (401, "No valid token or API key"), (403, "A viewer, or a token for an account the server doesn't sign for"),
+                      (422, "The vault refused or couldn't complete the withdrawal")]),
+            } },
+            "/schedule": { "get": {
+                "summary": "Scheduled jobs: when each last ran, how it went and when it runs next",
+                "operationId": "getSchedule",
+                "security": signed_in(),
+                "responses": responses(reply("The jobs, empty when none are scheduled", json!({ "type": "array", "items": schema("JobStatus") })),
+                    &[(401, "No valid token or API key")]),
+            } },
+            "/horizon": { "get": {
+                "summary": "Horizon endpoints: which one is in use, failovers so far, each one's request counts and the circuit breaker",
+                "description": format!("After {} failed calls or answers slower than {}s in a row, calls move to the next configured endpoint; the primary is tried again every {}s.",
+                    failover::FAIL_OVER_AFTER, failover::SLOW_RESPONSE.as_secs(), failover::PROBE_INTERVAL.as_secs()),
+                "operationId": "getHorizon",
+                "security": signed_in(),
+                "responses": responses(reply("The endpoints, primary first", schema("HorizonEndpoints")),
+                    &[(401, "No valid token or API key")]),
+            } },
+            "/healthz": { "get": {
+                "summary": "Liveness: the server is up",
+                "description": "Checks no dependencies, so a slow one never gets a healthy process restarted.",
+                "operationId": "healthz",
+                "responses": responses(reply("Up", object(&["status"], json!({ "status": { "type": "string", "enum": ["ok"] } }))), &[]),
+            } },
+            "/readyz": { "get": {
+                "summary": "Readiness: Horizon, Soroban RPC, storage and configuration",
+                "description": format!("Each dependency gets {}s to answer. storage also stalls while the vault is busy with a slow request.", health::CHECK_TIMEOUT.as_secs()),
+                "operationId": "readyz",
+                "responses": responses(reply("Every dependency is ok or not configured", schema("Readiness")),
+                    &[(503, "A dependency is down; see its check")]),
+            } },
+            "/ws": { "get": {
+                "summary": "Live vault events over a WebSocket",
+                "description": "Each text frame is one LiveEvent as JSON. Messages sent by the client are ignored. Tokens below the operator role get only their own account's deposits, withdrawals and yield, and vault-wide events with other accounts left out.",
+                "operationId": "liveEvents",
+                "security": signed_in(),
+                "responses": {
+                    "101": { "description": "Switching to the WebSocket protocol; frames follow the LiveEvent schema" },
+                    "400": { "description": "Not a WebSocket handshake" },
+                    "401": { "description": "No valid token or API key" },
+                },
+            } },
+        },
+        "components": { "securitySchemes": { "sep10": sep10, "apiKey": api_key }, "schemas": {
+            "Challenge": object(&["transaction", "network_passphrase"], json!({
+                "transaction": { "type": "string", "description": "Base64 TransactionEnvelope XDR" },
+                "network_passphrase": { "type": "string" },
+            })),
+            "Readiness": object(&["status", "checks"], json!({
+                "status": { "type": "string", "enum": ["ready", "degraded"] },
+                "checks": {
+                    "type": "object",
+                    "description": "horizon, soroban_rpc, storage and config",
+                    "additionalProperties": object(&["status"], json!({
+                        "status": { "type": "string", "enum": ["ok", "down", "not_configured"] },
+                        "latency_ms": { "type": "integer" },
+                        "error": { "type": "string" },
+                    })),
+                },
+            })),
+            "HorizonEndpoints": object(&["active", "failovers", "endpoints", "breaker"], json!({
+                "active": { "type": "string", "description": "The URL calls go to now" },
+                "failovers": { "type": "integer", "description": "Switches between endpoints since the server started, returns to the primary included" },
+                "endpoints": { "type": "array", "items": object(&["url", "active", "requests", "failures", "slow"], json!({
+                    "url": { "type": "string" },
+                    "active": { "type": "boolean" },
+                    "requests": { "type": "integer" },
+                    "failures": { "type": "integer", "description": "Dropped connections, timeouts, 429s and 5xxs" },
+                    "slow": { "type": "integer", "description": "Answers that succeeded but took too long" },
+                })) },
+                "breaker": { "type": "string", "enum": ["closed", "open", "half_open"],
+                    "description": "open while Horizon keeps failing: calls fail at once with a network degraded error until one is let through to probe" },
+            })),
+            "JobStatus": object(&["job", "interval_secs", "next_run_at", "running", "runs", "failures", "last_run"], json!({
+                "job": { "type": "string", "description": "harvest, rebalance, apy_refresh or coverage_check, and the vault when it has one" },
+                "interval_secs": { "type": "integer" },
+                "next_run_at": { "type": "integer", "description": "Unix time" },
+                "running": { "type": "boolean" },
+                "runs": { "type": "integer" },
+                "failures": { "type": "integer" },
+                "last_run": json!({ "nullable": true, "allOf": [object(&["started_at", "duration_ms", "ok"], json!({
+                    "started_at": { "type": "integer", "description": "Unix time" },
+                    "duration_ms": { "type": "integer" },
+                    "ok": { "type": "boolean" },
+                    "error": { "type": "string" },
+                }))] }),
+            })),
+            "AddressLists": object(&["allowlist_only", "allowlist", "denylist", "blocked"], json!({
+                "allowlist_only": { "type": "boolean", "description": "Whether only allowlisted accounts may deposit or be paid" },
+                "allowlist": { "type": "array", "items": schema("ListedAddress") },
+                "denylist": { "type": "array", "items": schema("ListedAddress") },
+                "blocked": { "type": "array", "maxItems": BLOCKED_SHOWN, "description": "Refused deposits and payouts from the event log, newest first",
+                    "items": { "type": "object", "description": "seq, timestamp and an operation_blocked event: operation, address, risk, amount_stroops and reason" } },
+            })),
+            "ListedAddress": object(&["address", "note", "added_at"], json!({
+                "address": { "type": "string" },
+                "note": { "type": "string" },
+                "added_at": { "type": "integer", "description": "Unix seconds" },
+            })),
+            "ListChange": object(&["list", "address", "status"], json!({
+                "list": { "type": "string", "enum": ["allowlist", "denylist"] },
+                "address": { "type": "string" },
+                "status": { "type": "string", "enum": ["listed", "unlisted"] },
+            })),
+            "Risk": { "type": "string", "enum": ["low", "medium", "high"], "description": "Case-insensitive in requests; responses spell it Low, Medium or High" },
+            "Error": object(&["error"], json!({ "error": { "type": "string" } })),
+            "Vault": object(&["risk", "asset", "paused", "tvl", "total_shares", "share_price", "apy_bps", "fees", "strategies"], json!({
+                "risk": { "type": "string" },
+                "asset": { "type": "string" },
+                "paused": { "type": "boolean" },
+                "tvl": amount(),
+                "tvl_usd": { "type": "string", "nullable": true },
+                "total_shares": { "type": "integer" },
+                "share_price": amount(),
+                "liquid_reserve": amount(),
+                "liquidity_buffer_bps": { "type": "integer" },
+                "apy_bps": { "type": "integer" },
+                "insurance_fee_bps": { "type": "integer" },
+                "insurance_pool": amount(),
+                "fees": object(&[], json!({
+                    "management_fee_bps": { "type": "integer" },
+                    "performance_fee_bps": { "type": "integer" },
+                    "accrued_management": amount(),
+                    "accrued_performance": amount(),
+                    "collected": amount(),
+                })),
+                "strategies": { "type": "array", "items": object(&["id", "allocation", "allocated", "apy_bps"], json!({
+                    "id": { "type": "string" },
+                    "allocation": { "type": "integer", "description": "Target percentage" },
+                    "allocated": amount(),
+                    "apy_bps": { "type": "integer" },
+                })) },
+            })),
+            "Position": object(&["shares", "value"], json!({
+                "shares": { "type": "integer" },
+                "value": amount(),
+                "accumulated_yield": amount(),
+                "cost_basis": amount(),
+                "unrealized_pnl": amount(),
+                "insurance_paid": amount(),
+                "apy_bps": { "type": "integer" },
+                "auto_compound": { "type": "boolean" },
+                "lock_remaining_secs": { "type": "integer" },
+                "ledger": { "type": "integer", "description": "Vault contract mode only: the ledger the index is current to" },
+            })),
+            "Positions": object(&["account", "positions"], json!({
+                "account": { "type": "string" },
+                "positions": { "type": "array", "items": object(&["risk", "position"], json!({
+                    "risk": { "type": "string" },
+                    "position": { "allOf": [schema("Position")], "nullable": true },
+                })) },
+            })),
+            "DepositRequest": object(&["risk", "amount"], json!({
+                "risk": schema("Risk"),
+                "amount": { "type": "string", "example": "100", "description": "Amount to pay, in whole units" },
+                "pay_with": { "type": "string", "description": "XLM or CODE:ISSUER, converted to the vault's asset; defaults to the vault's asset" },
+                "dry_run": { "type": "boolean", "default": false, "description": "Check, quote and build the transaction without signing it" },
+            })),
+            "WithdrawalRequest": object(&["risk", "shares"], json!({
+                "risk": schema("Risk"),
+                "shares": { "type": "integer", "minimum": 1 },
+                "dry_run": { "type": "boolean", "default": false },
+            })),
+            "Deposit": object(&["risk", "asset", "paid", "paid_asset", "credited", "shares", "transaction"], json!({
+                "status": { "type": "string", "enum": ["dry_run"], "description": "Only present for dry runs" },
+                "risk": { "type": "string" },
+                "asset": { "type": "string" },
+                "paid": amount(),
+                "paid_asset": { "type": "string" },
+                "credited": amount(),
+                "shares": { "type": "integer" },
+                "insurance_fee_bps": { "type": "integer" },
+                "insurance_fee": amount(),
+                "net": amount(),
+                "transaction": { "oneOf": [transaction.clone(), preview.clone()] },
+            })),
+            "LiveEvent": object(&["type"], json!({
+                "type": { "type": "string", "enum": ["vault", "share_price"] },
+                "seq": { "type": "integer", "description": "vault: position in the vault's event log" },
+                "timestamp": { "type": "integer", "description": "Unix seconds" },
+                "event": {
+                    "type": "object",
+                    "description": "vault: the logged event, tagged by its own type: deposit (with shares_minted), withdrawal, harvest, rebalance, fees_collected and the rest",
+                    "additionalProperties": true,
+                },
+                "risk": { "type": "string", "description": "share_price only" },
+                "share_price": { "allOf": [amount()], "description": "share_price only" },
+            })),
+            "Withdrawal": object(&["status", "risk", "shares"], json!({
+                "status": { "type": "string", "enum": ["completed", "queued", "dry_run", "dry_run_queued"] },
+                "risk": { "type": "string" },
+                "shares": { "type": "integer" },
+                "gross": amount(),
+                "penalty": amount(),
+                "net": amount(),
+                "request_id": { "type": "integer", "description": "Queued withdrawals only" },
+                "ahead": { "type": "integer", "description": "Queued withdrawals only: requests ahead of this one" },
+                "transaction": { "oneOf": [transaction, preview], "nullable": true },
+            })),
+        } },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test;
+    use crate::network::OFFLINE_PASSPHRASE;
+    use crate::offline;
+    use crate::transaction::{self, Keypair};
+
+    #[actix_web::test]
+    async fn forwards_requests_and_maps_errors() {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<ApiCall>();
+        tokio::spawn(async move {
+            while let Some(call) = receiver.recv().await {
+                let reply = match call.request {
+                    ApiRequest::Vault(risk) => Ok(json!({ "risk": risk })),
+                    ApiRequest::Pause { .. } => Ok(json!({ "by": call.caller.map(|caller| caller.role) })),
+                    _ => Err(ApiError::Failed("refused".to_string())),
+                };
+                let _ = call.reply.send(reply);
+            }
+        });
+        let auth = WebAuth::new(Keypair::from_seed(&[7; 32]), OFFLINE_PASSPHRASE, "localhost");
+        let client = Keypair::from_seed(&[8; 32]);
+        let issued = offline::decode(&auth.challenge(&client.public_key(), unix_now()).unwrap()).unwrap();
+        let signed = transaction::to_base64(&offline::sign(issued, OFFLINE_PASSPHRASE, &client).unwrap()).unwrap();
+        let bearer = (header::AUTHORIZATION, format!("Bearer {}", auth.token(&auth.read_challenge(&signed, unix_now()).unwrap(), unix_now())));
+        let access = Access::new(auth).with_key("viewer-key", Role::Viewer).with_key("operator-key", Role::Operator);
+        let app = test::init_service(App::new().app_data(web::Data::new(sender)).app_data(web::Data::new(access))
+            .app_data(web::Data::new(Subscribers::default())).configure(routes)).await;
+
+        let found: serde_json::Value = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/vaults/medium").to_request()).await;
+        assert_eq!(found["risk"], "Medium");
+
+        let unknown = test::call_service(&app, test::TestRequest::get().uri("/vaults/extreme").to_request()).await;
+        assert_eq!(unknown.status(), StatusCode::NOT_FOUND);
+
+        let anonymous = test::TestRequest::post().uri("/withdrawals").set_json(json!({ "risk": "low", "shares": 5 })).to_request();
+        assert_eq!(test::call_service(&app, anonymous).await.status(), StatusCode::UNAUTHORIZED);
+        let listening = test::TestRequest::get().uri("/ws").to_request();
+        assert_eq!(test::call_service(&app, listening).await.status(), StatusCode::UNAUTHORIZED);
+
+        let zero = test::TestRequest::post().uri("/deposits").insert_header(bearer.clone())
+            .set_json(json!({ "risk": "low", "amount": "0" })).to_request();
+        assert_eq!(test::call_service(&app, zero).await.status(), StatusCode::BAD_REQUEST);
+        let spaced = test::TestRequest::post().uri("/deposits").insert_header(bearer.clone())
+            .insert_header((IDEMPOTENCY_KEY_HEADER, "order 1")).set_json(json!({ "risk": "low", "amount": "5" })).to_request();
+        assert_eq!(test::call_service(&app, spaced).await.status(), StatusCode::BAD_REQUEST);
+
+        let refused = test::TestRequest::post().uri("/withdrawals").insert_header(bearer.clone())
+            .set_json(json!({ "risk": "low", "shares": 5 })).to_request();
+        assert_eq!(test::call_service(&app, refused).await.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let other = Keypair::from_seed(&[9; 32]).public_key();
+        let theirs = test::TestRequest::get().uri(&format!("/positions/{}", other)).insert_header(bearer.clone()).to_request();
+        assert_eq!(test::call_service(&app, theirs).await.status(), StatusCode::FORBIDDEN);
+
+        let pause = |key: &str| test::TestRequest::post().uri("/vaults/high/pause").insert_header((API_KEY_HEADER, key))
+            .set_json(json!({ "reason": "oracle outage" })).to_request();
+        assert_eq!(test::call_service(&app, pause("viewer-key")).await.status(), StatusCode::FORBIDDEN);
+        assert_eq!(test::call_service(&app, pause("wrong-key")).await.status(), StatusCode::UNAUTHORIZED);
+        let paused: Value = test::call_and_read_body_json(&app, pause("operator-key")).await;
+        assert_eq!(paused["by"], "operator");
+
+        let fees = test::TestRequest::post().uri("/vaults/low/fees").insert_header(bearer)
+            .set_json(json!({ "management_fee_bps": 0 })).to_request();
+        assert_eq!(test::call_service(&app, fees).await.status(), StatusCode::FORBIDDEN);
+
+        let spec: Value = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/openapi.json").to_request()).await;
+        let paths: Vec<&String> = spec["paths"].as_object().unwrap().keys().collect();
+        assert_eq!(paths, ["/address-lists", "/address-lists/{list}", "/address-lists/{list}/{address}", "/auth", "/claims/{id}/approve", "/claims/{id}/deny", "/deposits", "/healthz", "/horizon", "/positions/{account}",
+            "/readyz", "/schedule", "/vaults", "/vaults/{risk}", "/vaults/{risk}/fees", "/vaults/{risk}/pause", "/vaults/{risk}/rebalance", "/vaults/{risk}/resume",
+            "/withdrawals", "/ws"]);
+    }
+
+    #[actix_web::test]
+    async fn live_events_show_an_account_only_its_own() {
+        use crate::events::{YieldCredit, YieldPayout};
+        let own = Keypair::from_seed(&[10; 32]).public_key();
+        let other = Keypair::from_seed(&[11; 32]).public_key();
+        let record = |event| EventRecord { seq: 1, timestamp: 1_700_000_000, event };
+        let deposit = |user: &str| record(VaultEvent::Deposit {
+            user: user.to_string(), risk: RiskLevel::Low, amount_stroops: 100, insurance_stroops: 1, shares_minted: 99, tx_hash: "ab".into(),
+        });
+        assert!(visible_to(&deposit(&own), &own).is_some());
+        assert!(visible_to(&deposit(&muxed::address(&own, 3).unwrap()), &own).is_some());
+        assert!(visible_to(&deposit(&other), &own).is_none());
+
+        let credit = |user: &str| YieldCredit { user: user.to_string(), amount_stroops: 5, compounded: false, shares: 0 };
+        let harvest = record(VaultEvent::Harvest {
+            risk: RiskLevel::Low, strategy_yield: vec![10], performance_fee: 1, credits: vec![credit(&own), credit(&other)], insurance_yield: 0, insurance_shares: 0,
+        });
+        let Some(EventRecord { event: VaultEvent::Harvest { credits, .. }, .. }) = visible_to(&harvest, &own) else {
+            panic!("harvests are vault-wide");
+        };
+        assert_eq!(credits.iter().map(|c| c.user.as_str()).collect::<Vec<_>>(), [own.as_str()]);
+
+        let paid = record(VaultEvent::YieldDistributed {
+            risk: RiskLevel::Low, payouts: vec![YieldPayout { user: other.clone(), amount_stroops: 5 }], tx_hash: "cd".into(),
+        });
+        assert!(visible_to(&paid, &own).is_none());
+        assert!(visible_to(&record(VaultEvent::ManagementFeeAccrued { risk: RiskLevel::Low, amount_stroops: 3 }), &own).is_some());
+    }
+
+    // A `$ref`'d schema from the spec's components
+    fn resolve<'a>(spec: &'a Value, schema: &'a Value) -> &'a Value {
+        match schema["$ref"].as_str() {
+            Some(reference) => {
+                let name = reference.trim_start_matches("#/components/schemas/");
+                assert!(spec["components"]["schemas"].get(name).is_some(), "{} is not defined", reference);
+                resolve(spec, &spec["components"]["schemas"][name])
+            }
+            None => schema,
+        }
+    }
+
+    // The least a body matching `schema` can hold: its required fields
+    fn sample(spec: &Value, schema: &Value) -> Value {
+        let schema = resolve(spec, schema);
+        if let Some(first) = schema["oneOf"].get(0).or(schema["allOf"].get(0)) {
+            return sample(spec, first);
+        }
+        match schema["type"].as_str() {
+            Some("object") => {
+                let required = schema["required"].as_array().cloned().unwrap_or_default();
+                Value::Object(required.iter().filter_map(Value::as_str)
+                    .map(|field| (field.to_string(), sample(spec, &schema["properties"][field])))
+                    .collect())
+            }
+            Some("integer") => json!(1),
+            Some("boolean") => json!(false),
+            Some("array") => json!([]),
+            _ => schema["enum"].get(0).or(schema.get("example")).cloned().unwrap_or(json!("text")),
+        }
+    }
+
+    // Whether a registered path template serves a documented path
+    fn serves(registered: &str, documented: &str) -> bool {
+        let (registered, documented): (Vec<&str>, Vec<&str>) = (registered.split('/').collect(), documented.split('/').collect());
+        registered.len() == documented.len()
+            && registered.iter().zip(&documented).all(|(r, d)| r == d || r.starts_with('{'))
+    }
+
+    #[actix_web::test]
+    async fn the_spec_documents_every_route_and_body() {
+        let spec = openapi();
+        let documented: Vec<(String, String)> = spec["paths"].as_object().unwrap().iter()
+            .flat_map(|(path, operations)| operations.as_object().unwrap().keys().map(move |method| (method.to_uppercase(), path.clone())))
+            .collect();
+        let registered: Vec<(String, &str)> = endpoints().into_iter()
+            .map(|(method, path, _)| (method.to_string(), path))
+            .filter(|(_, path)| !["/openapi.json", "/docs"].contains(path))
+            .collect();
+        for (method, path) in &documented {
+            assert!(registered.iter().any(|(m, r)| m == method && serves(r, path)), "{} {} is documented but not served", method, path);
+        }
+        for (method, path) in &registered {
+            assert!(documented.iter().any(|(m, d)| m == method && serves(path, d)), "{} {} is served but not documented", method, path);
+        }
+
+        // Each documented body is one its handler takes, with nothing it
+        // needs left out; one missing a required field is refused
+        let (sender, mut receiver) = mpsc::unbounded_channel::<ApiCall>();
+        tokio::spawn(async move {
+            while let Some(call) = receiver.recv().await {
+                let _ = call.reply.send(Err(ApiError::Failed("refused".to_string())));
+            }
+        });
+        let auth = WebAuth::new(Keypair::from_seed(&[12; 32]), OFFLINE_PASSPHRASE, "localhost");
+        let access = Access::new(auth).with_key("admin-key", Role::Admin);
+        let app = test::init_service(App::new().app_data(web::Data::new(sender)).app_data(web::Data::new(access))
+            .app_data(web::Data::new(HorizonClient::for_network(&crate::network::Network::Offline)))
+            .configure(routes)).await;
+        let account = Keypair::from_seed(&[13; 32]).public_key();
+        for (method, path) in &documented {
+            let operation = &spec["paths"][path.as_str()][method.to_lowercase()];
+            let Some(schema) = operation["requestBody"]["content"]["application/json"].get("schema") else {
+                continue;
+            };
+            let uri = path.replace("{risk}", "low").replace("{id}", "1").replace("{list}", "denylist").replace("{address}", &account);
+            let send = |body: Value| test::TestRequest::default().method(Method::from_bytes(method.as_bytes()).unwrap())
+                .uri(&uri).insert_header((API_KEY_HEADER, "admin-key")).set_json(body).to_request();
+            let body = sample(&spec, schema);
+            let answer: Value = test::call_and_read_body_json(&app, send(body.clone())).await;
+            let error = answer["error"].as_str().unwrap_or_default();
+            assert!(!error.starts_with("Json deserialize error"), "{} {} refuses its documented body {}: {}", method, path, body, error);
+            for field in body.as_object().unwrap().keys() {
+                let mut missing = body.clone();
+                missing.as_object_mut().unwrap().remove(field);
+                let answer: Value = test::call_and_read_body_json(&app, send(missing)).await;
+                assert!(answer["error"].as_str().is_some_and(|e| e.starts_with("Json deserialize error")),
+                    "{} {} takes a body without {}, which the spec requires", method, path, field);
+            }
+        }
+
+        // Every response's schema is defined
+        for (method, path) in &documented {
+            for response in spec["paths"][path.as_str()][method.to_lowercase()]["responses"].as_object().unwrap().values() {
+                if let Some(schema) = response["content"]["application/json"].get("schema") {
+                    resolve(&spec, schema);
+                }
+            }
+        }
+    }
+}
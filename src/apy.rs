@@ -0,0 +1,296 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+
+use crate::blend;
+use crate::error::VaultError;
+use crate::soroban::RpcClient;
+use crate::unix_now;
+
+pub const DEFAULT_INTERVAL_SECS: u64 = 300;
+pub const MIN_INTERVAL_SECS: u64 = 10;
+pub const DEFAULT_MAX_AGE_SECS: u64 = 3_600;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+// ============================================================================
+// APY PROVIDERS
+// ============================================================================
+
+pub type RateFuture<'a> = Pin<Box<dyn Future<Output = Result<Option<u16>, VaultError>> + Send + 'a>>;
+
+// A live source for one strategy's APY. `fetch` resolves to the current
+// rate in basis points, or None while the source can't tell yet.
+pub trait ApyProvider: Send + Sync {
+    // Id of the strategy whose APY this sets
+    fn strategy(&self) -> &str;
+    // Where the rate comes from, for messages
+    fn source(&self) -> String;
+    fn fetch(&self) -> RateFuture<'_>;
+}
+
+// Supply APY of a Blend pool reserve, annualized from its b_rate growth
+// between fetches. The first fetch only takes a sample.
+pub struct BlendApy {
+    rpc: RpcClient,
+    pool: String,
+    token: String,
+    // Account the reads are simulated as
+    source_account: String,
+    strategy: String,
+    last_sample: Mutex<Option<(u64, u64)>>,
+}
+
+impl BlendApy {
+    pub fn new(rpc: RpcClient, pool: &str, token: &str, source_account: &str, strategy: &str) -> Self {
+        BlendApy {
+            rpc,
+            pool: pool.to_string(),
+            token: token.to_string(),
+            source_account: source_account.to_string(),
+            strategy: strategy.to_string(),
+            last_sample: Mutex::new(None),
+        }
+    }
+}
+
+impl ApyProvider for BlendApy {
+    fn strategy(&self) -> &str {
+        &self.strategy
+    }
+
+    fn source(&self) -> String {
+        format!("Blend pool {}", self.pool)
+    }
+
+    fn fetch(&self) -> RateFuture<'_> {
+        Box::pin(async move {
+            let reserve = self.rpc.read(&self.source_account, blend::get_reserve(&self.pool, &self.token)?).await?;
+            let (_, b_rate) = blend::parse_reserve(&reserve)?;
+            let now = unix_now();
+            let mut last_sample = self.last_sample.lock().unwrap_or_else(PoisonError::into_inner);
+            let apy = last_sample.and_then(|(rate, at)| blend::rate_apy_bps(rate, b_rate, now.saturating_sub(at)));
+            *last_sample = Some((b_rate, now));
+            Ok(apy)
+        })
+    }
+}
+
+// How an API quotes its rates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateUnit {
+    // 8.5 for 8.5%
+    Percent,
+    // 0.085 for 8.5%
+    Fraction,
+    Bps,
+}
+
+impl RateUnit {
+    pub fn parse(unit: &str) -> Result<RateUnit, VaultError> {
+        match unit.to_lowercase().as_str() {
+            "percent" | "%" => Ok(RateUnit::Percent),
+            "fraction" => Ok(RateUnit::Fraction),
+            "bps" => Ok(RateUnit::Bps),
+            _ => Err(VaultError::Validation(format!("Unknown rate unit {:?} (expected percent, fraction or bps)", unit))),
+        }
+    }
+
+    pub fn to_bps(self, rate: f64) -> Option<u16> {
+        let bps = match self {
+            RateUnit::Percent => rate * 100.0,
+            RateUnit::Fraction => rate * 10_000.0,
+            RateUnit::Bps => rate,
+        };
+        (bps.is_finite() && bps >= 0.0 && bps <= u16::MAX as f64).then(|| bps.round() as u16)
+    }
+}
+
+// A rate published as JSON, such as a pool's APY from the Aquarius AMM API.
+// `field` is a JSON pointer ("/apy", "/pools/0/apy"); numbers quoted as
+// strings are accepted.
+#[derive(Debug, Clone)]
+pub struct HttpApy {
+    pub strategy: String,
+    pub url: String,
+    pub field: String,
+    pub unit: RateUnit,
+    http: reqwest::Client,
+}
+
+impl HttpApy {
+    pub fn new(strategy: &str, url: &str, field: &str, unit: RateUnit) -> Result<Self, VaultError> {
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            return Err(VaultError::Validation(format!("APY source for {} must be an http(s) URL: {}", strategy, url)));
+        }
+        if !field.starts_with('/') {
+            return Err(VaultError::Validation(format!("APY field for {} must be a JSON pointer such as /apy: {}", strategy, field)));
+        }
+        Ok(HttpApy {
+            strategy: strategy.to_string(),
+            url: url.to_string(),
+            field: field.to_string(),
+            unit,
+            http: reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build().unwrap_or_default(),
+        })
+    }
+
+    pub fn read_rate(&self, body: &serde_json::Value) -> Result<u16, VaultError> {
+        let value = body.pointer(&self.field)
+            .ok_or_else(|| VaultError::Other(format!("{} has no {}", self.url, self.field)))?;
+        let rate = match value {
+            serde_json::Value::Number(n) => n.as_f64(),
+            serde_json::Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        };
+        rate.and_then(|rate| self.unit.to_bps(rate))
+            .ok_or_else(|| VaultError::Other(format!("{} at {} is not a usable rate: {}", self.field, self.url, value)))
+    }
+}
+
+impl ApyProvider for HttpApy {
+    fn strategy(&self) -> &str {
+        &self.strategy
+    }
+
+    fn source(&self) -> String {
+        self.url.clone()
+    }
+
+    fn fetch(&self) -> RateFuture<'_> {
+        Box::pin(async move {
+            let response = self.http.get(&self.url).send().await
+                .map_err(|e| VaultError::Other(format!("{}: {}", self.url, e)))?;
+            if !response.status().is_success() {
+                return Err(VaultError::Other(format!("{} answered {}", self.url, response.status())));
+            }
+            let body: serde_json::Value = response.json().await
+                .map_err(|e| VaultError::Other(format!("{} sent invalid JSON: {}", self.url, e)))?;
+            self.read_rate(&body).map(Some)
+        })
+    }
+}
+
+// ============================================================================
+// ORACLE
+// ============================================================================
+
+pub struct RateUpdate {
+    pub strategy: String,
+    pub source: String,
+    pub result: Result<Option<u16>, String>,
+}
+
+// Fetches every provider's rate now and then every `interval`, in the
+// background. Dropping the receiver stops the task.
+pub fn spawn(providers: Vec<Box<dyn ApyProvider>>, interval: Duration) -> mpsc::UnboundedReceiver<RateUpdate> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let providers: Vec<Arc<dyn ApyProvider>> = providers.into_iter().map(Arc::from).collect();
+
+    tokio::spawn(async move {
+        loop {
+            // Every source is asked at once and reported as it answers, so a
+            // slow one holds up nothing else
+            let mut fetches = JoinSet::new();
+            for provider in &providers {
+                let provider = Arc::clone(provider);
+                fetches.spawn(async move {
+                    let result = provider.fetch().await.map_err(|e| e.to_string());
+                    RateUpdate { strategy: provider.strategy().to_string(), source: provider.source(), result }
+                });
+            }
+            while let Some(fetched) = fetches.join_next().await {
+                let Ok(update) = fetched else { continue };
+                if sender.send(update).is_err() {
+                    return;
+                }
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+
+    receiver
+}
+
+// A strategy whose live rate is older than allowed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleRate {
+    pub strategy: String,
+    // None when no fetch has succeeded yet
+    pub last_apy_bps: Option<u16>,
+    pub age_secs: u64,
+}
+
+struct LiveRate {
+    apy_bps: Option<u16>,
+    // When the rate was fetched, or tracking began
+    since: u64,
+    warned: bool,
+}
+
+// When each strategy's APY was last fetched. Between fetches the vaults keep
+// the last known rate, or the configured one before the first.
+pub struct LiveRates {
+    max_age_secs: u64,
+    rates: BTreeMap<String, LiveRate>,
+}
+
+impl LiveRates {
+    pub fn new<'a>(strategies: impl IntoIterator<Item = &'a str>, max_age_secs: u64, now: u64) -> Self {
+        LiveRates {
+            max_age_secs,
+            rates: strategies.into_iter()
+                .map(|s| (s.to_string(), LiveRate { apy_bps: None, since: now, warned: false }))
+                .collect(),
+        }
+    }
+
+    pub fn record(&mut self, strategy: &str, apy_bps: u16, now: u64) {
+        self.rates.insert(strategy.to_string(), LiveRate { apy_bps: Some(apy_bps), since: now, warned: false });
+    }
+
+    pub fn last(&self, strategy: &str) -> Option<u16> {
+        self.rates.get(strategy).and_then(|rate| rate.apy_bps)
+    }
+
+    // Rates that have gone stale since the last call; each is reported once
+    // until a fresh one arrives
+    pub fn newly_stale(&mut self, now: u64) -> Vec<StaleRate> {
+        let max_age_secs = self.max_age_secs;
+        self.rates.iter_mut()
+            .filter(|(_, rate)| !rate.warned && now.saturating_sub(rate.since) > max_age_secs)
+            .map(|(strategy, rate)| {
+                rate.warned = true;
+                StaleRate { strategy: strategy.clone(), last_apy_bps: rate.apy_bps, age_secs: now - rate.since }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::AQUA_LIQUIDITY_POOL;
+
+    #[test]
+    fn api_rates_are_read_and_go_stale() {
+        let api = HttpApy::new(AQUA_LIQUIDITY_POOL, "https://amm-api.aqua.network/pools/1", "/apy", RateUnit::Percent).unwrap();
+        assert_eq!(api.read_rate(&serde_json::json!({"apy": "9.125"})).unwrap(), 913);
+        assert!(api.read_rate(&serde_json::json!({"apy": -1})).is_err());
+        assert!(api.read_rate(&serde_json::json!({"rate": 9})).is_err());
+        assert_eq!(RateUnit::Fraction.to_bps(0.085), Some(850));
+        assert!(HttpApy::new(AQUA_LIQUIDITY_POOL, "ftp://example.com", "/apy", RateUnit::Bps).is_err());
+
+        let mut rates = LiveRates::new([AQUA_LIQUIDITY_POOL], 600, 1_000);
+        assert!(rates.newly_stale(1_600).is_empty());
+        assert_eq!(rates.newly_stale(1_601), vec![StaleRate { strategy: AQUA_LIQUIDITY_POOL.to_string(), last_apy_bps: None, age_secs: 601 }]);
+        assert!(rates.newly_stale(5_000).is_empty());
+
+        rates.record(AQUA_LIQUIDITY_POOL, 913, 5_000);
+        assert_eq!(rates.last(AQUA_LIQUIDITY_POOL), Some(913));
+        assert_eq!(rates.newly_stale(6_000)[0].last_apy_bps, Some(913));
+    }
+}
@@ -0,0 +1,127 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use stellar_xdr::curr::{
+    AlphaNum12, AlphaNum4, Asset, AssetCode12, AssetCode4, ChangeTrustAsset, ChangeTrustOp, OperationBody,
+};
+
+use crate::error::VaultError;
+use crate::horizon::Balance;
+use crate::transaction;
+
+// Limit that leaves a trustline effectively unbounded
+pub const UNLIMITED: i64 = i64::MAX;
+
+// ============================================================================
+// ASSETS
+// ============================================================================
+
+// A credit asset: its code and the account that issues it
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AssetId {
+    pub code: String,
+    pub issuer: String,
+}
+
+impl AssetId {
+    pub fn new(code: &str, issuer: &str) -> Result<Self, VaultError> {
+        if code.is_empty() || code.len() > 12 || !code.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(VaultError::Validation(format!("Invalid asset code {:?}", code)));
+        }
+        stellar_strkey::ed25519::PublicKey::from_string(issuer)
+            .map_err(|_| VaultError::Validation(format!("Invalid issuer {} for {}", issuer, code)))?;
+        Ok(AssetId { code: code.to_string(), issuer: issuer.to_string() })
+    }
+
+    // CODE:ISSUER, as Horizon writes assets
+    pub fn parse(asset: &str) -> Result<Self, VaultError> {
+        let (code, issuer) = asset.split_once(':')
+            .ok_or_else(|| VaultError::Validation(format!("Expected CODE:ISSUER, got {:?}", asset)))?;
+        Self::new(code, issuer)
+    }
+
+    // Codes of up to 4 characters are alphanum4, up to 12 alphanum12
+    pub fn to_xdr(&self) -> Result<Asset, VaultError> {
+        let issuer = transaction::account_id(&self.issuer)?;
+        let mut bytes = [0u8; 12];
+        bytes[..self.code.len()].copy_from_slice(self.code.as_bytes());
+        if self.code.len() <= 4 {
+            let mut short = [0u8; 4];
+            short.copy_from_slice(&bytes[..4]);
+            return Ok(Asset::CreditAlphanum4(AlphaNum4 { asset_code: AssetCode4(short), issuer }));
+        }
+        Ok(Asset::CreditAlphanum12(AlphaNum12 { asset_code: AssetCode12(bytes), issuer }))
+    }
+
+    pub fn matches(&self, balance: &Balance) -> bool {
+        balance.asset_code.as_deref() == Some(self.code.as_str())
+            && balance.asset_issuer.as_deref() == Some(self.issuer.as_str())
+    }
+
+    // The asset a Horizon balance or payment refers to; None for XLM
+    pub fn from_horizon(asset_type: &str, code: Option<&str>, issuer: Option<&str>) -> Result<Option<Self>, VaultError> {
+        match (asset_type, code, issuer) {
+            ("native", _, _) => Ok(None),
+            (_, Some(code), Some(issuer)) => Self::new(code, issuer).map(Some),
+            _ => Err(VaultError::Validation(format!("Unsupported asset type {}", asset_type))),
+        }
+    }
+
+    // The account's trustline to this asset, if it has one
+    pub fn trustline<'a>(&self, balances: &'a [Balance]) -> Option<&'a Balance> {
+        balances.iter().find(|balance| self.matches(balance))
+    }
+}
+
+impl fmt::Display for AssetId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.code, self.issuer)
+    }
+}
+
+// "XLM" or the asset code, for display
+pub fn code_of(asset: &Asset) -> String {
+    let code = match asset {
+        Asset::Native => return "XLM".to_string(),
+        Asset::CreditAlphanum4(asset) => asset.asset_code.0.to_vec(),
+        Asset::CreditAlphanum12(asset) => asset.asset_code.0.to_vec(),
+    };
+    String::from_utf8_lossy(&code).trim_end_matches('\0').to_string()
+}
+
+// ============================================================================
+// TRUSTLINES
+// ============================================================================
+
+// Creates the trustline, or changes its limit if it exists. A limit of 0
+// removes it, which only succeeds once its balance is empty.
+pub fn change_trust(asset: &Asset, limit: i64) -> Result<OperationBody, VaultError> {
+    let line = match asset.clone() {
+        Asset::CreditAlphanum4(asset) => ChangeTrustAsset::CreditAlphanum4(asset),
+        Asset::CreditAlphanum12(asset) => ChangeTrustAsset::CreditAlphanum12(asset),
+        Asset::Native => return Err(VaultError::Validation("XLM needs no trustline".into())),
+    };
+    if limit < 0 {
+        return Err(VaultError::Validation("Trustline limit can't be negative".into()));
+    }
+    Ok(OperationBody::ChangeTrust(ChangeTrustOp { line, limit }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ISSUER: &str = "GCZEAWUJY3BRHCOKU6C5WRLCF5RFSGY22UGBPBXWL4T4G4SSEQMIYMCX";
+
+    #[test]
+    fn asset_codes_pick_their_xdr_width() {
+        let usdc = AssetId::parse(&format!("USDC:{}", ISSUER)).unwrap();
+        assert!(matches!(usdc.to_xdr().unwrap(), Asset::CreditAlphanum4(_)));
+        assert!(matches!(AssetId::new("SYIAMED", ISSUER).unwrap().to_xdr().unwrap(), Asset::CreditAlphanum12(_)));
+        assert_eq!(usdc.to_string(), format!("USDC:{}", ISSUER));
+
+        assert!(AssetId::new("TOO-LONG-CODE", ISSUER).is_err());
+        assert!(AssetId::new("USDC", "GBAD").is_err());
+        assert!(AssetId::parse("USDC").is_err());
+        assert!(change_trust(&Asset::Native, UNLIMITED).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+use std::io::Write;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::VaultError;
+use crate::events::EventRecord;
+use crate::soroban::hex;
+
+// What the first exported record follows
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+// ============================================================================
+// HASH-CHAINED AUDIT TRAIL
+// ============================================================================
+
+// One line of an export: the logged event, the previous line's hash and its
+// own. Altering a record changes its hash, and removing or reordering one
+// breaks the next line's link, so an auditor holding the last hash can tell
+// the trail is the one exported.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditLine {
+    #[serde(flatten)]
+    pub record: EventRecord,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+// sha256 of the previous hash (hex) followed by the record's JSON
+pub fn link(prev_hash: &str, record: &EventRecord) -> Result<String, VaultError> {
+    let mut hasher = Sha256::new();
+    hasher.update(prev_hash.as_bytes());
+    hasher.update(serde_json::to_string(record)?.as_bytes());
+    Ok(hex(&hasher.finalize()))
+}
+
+// Writes `records` as JSON lines, returning the last line's hash
+pub fn export(records: &[EventRecord], out: &mut impl Write) -> Result<String, VaultError> {
+    let mut prev_hash = GENESIS_HASH.to_string();
+    for record in records {
+        let hash = link(&prev_hash, record)?;
+        let line = AuditLine { record: record.clone(), prev_hash, hash: hash.clone() };
+        writeln!(out, "{}", serde_json::to_string(&line)?)?;
+        prev_hash = hash;
+    }
+    out.flush()?;
+    Ok(prev_hash)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verified {
+    pub records: usize,
+    pub head: String,
+}
+
+// Checks every line's hash and link to the one before; the first failure is
+// the error, by line number
+pub fn verify(trail: &str) -> Result<Verified, VaultError> {
+    let mut prev_hash = GENESIS_HASH.to_string();
+    let mut prev_seq = None;
+    let mut records = 0;
+    for (i, text) in trail.lines().enumerate().filter(|(_, text)| !text.trim().is_empty()) {
+        let fail = |problem: String| VaultError::Validation(format!("Line {}: {}", i + 1, problem));
+        let line: AuditLine = serde_json::from_str(text).map_err(|e| fail(format!("unreadable: {}", e)))?;
+        if line.prev_hash != prev_hash {
+            return Err(fail("doesn't follow the line before it; a record was removed, added or reordered".into()));
+        }
+        if prev_seq.is_some_and(|seq: u64| line.record.seq != seq + 1) {
+            return Err(fail(format!("event #{} follows #{}; the events between are missing", line.record.seq, prev_seq.unwrap_or(0))));
+        }
+        if link(&prev_hash, &line.record)? != line.hash {
+            return Err(fail(format!("event #{} doesn't match its hash; it was altered", line.record.seq)));
+        }
+        prev_hash = line.hash;
+        prev_seq = Some(line.record.seq);
+        records += 1;
+    }
+    Ok(Verified { records, head: prev_hash })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::VaultEvent;
+    use crate::RiskLevel;
+
+    #[test]
+    fn detects_altered_removed_and_reordered_records() {
+        let records: Vec<EventRecord> = (1..=3)
+            .map(|seq| EventRecord { seq, timestamp: 1_700_000_000 + seq, event: VaultEvent::ManagementFeeAccrued { risk: RiskLevel::Low, amount_stroops: seq * 100 } })
+            .collect();
+        let mut out = Vec::new();
+        let head = export(&records, &mut out).unwrap();
+        let trail = String::from_utf8(out).unwrap();
+        assert_eq!(verify(&trail).unwrap(), Verified { records: 3, head });
+
+        let lines: Vec<&str> = trail.lines().collect();
+        let altered = trail.replacen("1700000002", "1700000009", 1);
+        assert!(verify(&altered).unwrap_err().to_string().contains("Line 2"));
+        let removed = [lines[0], lines[2]].join("\n");
+        assert!(verify(&removed).unwrap_err().to_string().contains("Line 2"));
+        let reordered = [lines[1], lines[0], lines[2]].join("\n");
+        assert!(verify(&reordered).is_err());
+        // Cutting the tail leaves a valid chain, but not the published head
+        assert_ne!(verify(&lines[..2].join("\n")).unwrap().head, verify(&trail).unwrap().head);
+    }
+}
@@ -0,0 +1,181 @@
+use serde::{Deserialize, Serialize};
+use stellar_xdr::curr::{OperationBody, ScVal};
+
+use crate::amount::{mul_div, Rounding};
+use crate::soroban;
+use crate::SECONDS_PER_YEAR;
+use crate::error::VaultError;
+
+// Blend pools hold b_rate with 12 decimals: underlying per b-token
+pub const RATE_SCALAR: u64 = 1_000_000_000_000;
+// Request types `submit` takes; supplies that aren't collateral earn the
+// same interest without backing any borrowing
+const REQUEST_SUPPLY: u32 = 0;
+const REQUEST_WITHDRAW: u32 = 1;
+// Rate samples closer together than this are too noisy to annualize
+const MIN_RATE_SAMPLE_SECS: u64 = 3_600;
+
+// ============================================================================
+// POOL CALLS
+// ============================================================================
+
+fn submit(pool: &str, account: &str, token: &str, request_type: u32, amount: u64) -> Result<OperationBody, VaultError> {
+    let request = soroban::record(vec![
+        ("address", soroban::address(token)?),
+        ("amount", soroban::amount(amount)),
+        ("request_type", ScVal::U32(request_type)),
+    ])?;
+    let account = soroban::address(account)?;
+    soroban::invoke(pool, "submit", vec![account.clone(), account.clone(), account, soroban::vec(vec![request])?])
+}
+
+// Lends `amount` of `token` to the pool from `account`
+pub fn supply(pool: &str, account: &str, token: &str, amount: u64) -> Result<OperationBody, VaultError> {
+    submit(pool, account, token, REQUEST_SUPPLY, amount)
+}
+
+// Pools cap a withdrawal at the position, so asking for more takes it all
+pub fn withdraw(pool: &str, account: &str, token: &str, amount: u64) -> Result<OperationBody, VaultError> {
+    submit(pool, account, token, REQUEST_WITHDRAW, amount)
+}
+
+pub fn get_positions(pool: &str, account: &str) -> Result<OperationBody, VaultError> {
+    soroban::invoke(pool, "get_positions", vec![soroban::address(account)?])
+}
+
+pub fn get_reserve(pool: &str, token: &str) -> Result<OperationBody, VaultError> {
+    soroban::invoke(pool, "get_reserve", vec![soroban::address(token)?])
+}
+
+// The reserve's index in position maps and its current b_rate
+pub fn parse_reserve(reserve: &ScVal) -> Result<(u32, u64), VaultError> {
+    let index = match soroban::field(reserve, "config").and_then(|config| soroban::field(config, "index")) {
+        Some(ScVal::U32(index)) => *index,
+        _ => return Err("Pool returned a reserve without an index".into()),
+    };
+    let b_rate = soroban::field(reserve, "data")
+        .and_then(|data| soroban::field(data, "b_rate"))
+        .and_then(soroban::to_i128)
+        .ok_or("Pool returned a reserve without a b_rate")?;
+    Ok((index, u64::try_from(b_rate).map_err(|_| format!("b_rate {} is out of range", b_rate))?))
+}
+
+// Uncollateralized b-tokens held in the reserve at `index`
+pub fn supplied_b_tokens(positions: &ScVal, index: u32) -> Result<u64, VaultError> {
+    let b_tokens = soroban::field(positions, "supply")
+        .and_then(|supply| soroban::entry(supply, index))
+        .map_or(Some(0), soroban::to_i128)
+        .ok_or("Pool returned an unreadable supply position")?;
+    Ok(u64::try_from(b_tokens).map_err(|_| format!("Supply of {} b-tokens is out of range", b_tokens))?)
+}
+
+// ============================================================================
+// POSITION
+// ============================================================================
+
+// What the vault has lent to a Blend pool. Interest accrues to b_rate, so
+// yield is growth in the b-tokens' value, and the supply APY is how fast
+// b_rate has been growing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LendingPosition {
+    pub pool: String,
+    #[serde(default)]
+    pub b_tokens: u64,
+    // Vault asset lent and not yet withdrawn
+    #[serde(default)]
+    pub supplied: u64,
+    // Value at the last sync, in the vault's asset
+    #[serde(default)]
+    pub value: u64,
+    // Highest value already credited or supplied; growth above it is yield
+    #[serde(default)]
+    pub high_water: u64,
+    // The last b_rate sample and when it was taken
+    #[serde(default)]
+    pub b_rate: u64,
+    #[serde(default)]
+    pub rate_time: u64,
+    #[serde(default)]
+    pub supply_apy_bps: Option<u16>,
+}
+
+impl LendingPosition {
+    pub fn new(pool: &str) -> Result<Self, VaultError> {
+        soroban::contract_id(pool)?;
+        Ok(LendingPosition {
+            pool: pool.to_string(),
+            b_tokens: 0,
+            supplied: 0,
+            value: 0,
+            high_water: 0,
+            b_rate: 0,
+            rate_time: 0,
+            supply_apy_bps: None,
+        })
+    }
+
+    // Takes a fresh reading of the position and the reserve's b_rate
+    pub fn observe(&mut self, b_tokens: u64, b_rate: u64, now: u64) {
+        self.b_tokens = b_tokens;
+        self.value = mul_div(b_tokens, b_rate, RATE_SCALAR, Rounding::Down).unwrap_or(0);
+        let elapsed = now.saturating_sub(self.rate_time);
+        if self.b_rate == 0 || elapsed >= MIN_RATE_SAMPLE_SECS {
+            if let Some(apy) = rate_apy_bps(self.b_rate, b_rate, elapsed) {
+                self.supply_apy_bps = Some(apy);
+            }
+            self.b_rate = b_rate;
+            self.rate_time = now;
+        }
+    }
+
+    pub fn record_supply(&mut self, amount: u64) {
+        self.supplied += amount;
+        self.high_water += amount;
+    }
+
+    pub fn record_withdrawal(&mut self) {
+        self.b_tokens = 0;
+        self.supplied = 0;
+        self.value = 0;
+        self.high_water = 0;
+    }
+
+    // Interest accrued since the last harvest
+    pub fn take_unharvested(&mut self) -> u64 {
+        let gain = self.value.saturating_sub(self.high_water);
+        self.high_water = self.high_water.max(self.value);
+        gain
+    }
+}
+
+// b_rate growth between two samples as a simple annual rate, in basis points
+pub fn rate_apy_bps(previous: u64, current: u64, elapsed_secs: u64) -> Option<u16> {
+    if previous == 0 || elapsed_secs == 0 || current < previous {
+        return None;
+    }
+    let growth = (current - previous) as u128 * 10_000 * SECONDS_PER_YEAR as u128 / (previous as u128 * elapsed_secs as u128);
+    Some(growth.min(u16::MAX as u128) as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interest_shows_up_as_value_and_rate() {
+        let pool = stellar_strkey::Contract([7; 32]).to_string();
+        let mut position = LendingPosition::new(&pool).unwrap();
+        position.observe(1_000_000_000, RATE_SCALAR, 0);
+        position.record_supply(1_000_000_000);
+        assert_eq!(position.take_unharvested(), 0);
+
+        // A year later b_rate is 5% higher
+        position.observe(1_000_000_000, RATE_SCALAR * 105 / 100, SECONDS_PER_YEAR);
+        assert_eq!(position.value, 1_050_000_000);
+        assert_eq!(position.supply_apy_bps, Some(500));
+        assert_eq!(position.take_unharvested(), 50_000_000);
+        assert_eq!(position.take_unharvested(), 0);
+
+        assert!(LendingPosition::new("GCZEAWUJY3BRHCOKU6C5WRLCF5RFSGY22UGBPBXWL4T4G4SSEQMIYMCX").is_err());
+    }
+}
@@ -0,0 +1,519 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{events, federation, path_payment, storage, strategy};
+use crate::amount::BPS_DENOMINATOR;
+use crate::assets::AssetId;
+use crate::circuit_breaker::{BreakerPolicy, CircuitBreaker};
+use crate::claims::ClaimBook;
+use crate::client::UserRegistry;
+use crate::contract_index::ContractIndex;
+use crate::error::VaultError;
+use crate::fee_strategy::FeeStrategy;
+use crate::fees::{FeeAccrual, FeeConfig};
+use crate::horizon::HorizonClient;
+use crate::insurance::{CoveragePolicy, InsuranceInvestment, PremiumBounds};
+use crate::muxed::DepositRoutes;
+use crate::network::Network;
+use crate::oracle::PriceBook;
+use crate::idempotency::{self, IdempotencyBook};
+use crate::journal::OperationJournal;
+use crate::pending_deposits::DepositBook;
+use crate::recurring::RecurringBook;
+use crate::retry::RetryPolicy;
+use crate::screening::AddressLists;
+use crate::share_asset::ShareIssuance;
+use crate::submission::Submitter;
+use crate::timelock::{self, Timelock};
+use crate::withdrawal_queue::WithdrawalQueue;
+use crate::{RiskLevel, StellarVault, StrategyAllocation, Vault};
+
+// Upper limits on what a vault may charge, in basis points
+pub const MAX_INSURANCE_FEE_BPS: u16 = 1_000;
+pub const MAX_MANAGEMENT_FEE_BPS: u16 = 500;
+pub const MAX_PERFORMANCE_FEE_BPS: u16 = 5_000;
+pub const MAX_EARLY_WITHDRAWAL_PENALTY_BPS: u16 = 1_000;
+
+pub const DEFAULT_MANAGEMENT_FEE_BPS: u16 = 50;
+pub const DEFAULT_PERFORMANCE_FEE_BPS: u16 = 1_000;
+
+const XLM: u64 = 10_000_000;
+
+// ============================================================================
+// VAULT BUILDER
+// ============================================================================
+
+/// Defines one vault: its strategies and their allocations, what it charges
+/// and how much it takes. [`VaultBuilder::build`] checks the definition
+/// before any funds can reach it.
+///
+/// ```
+/// use stellarvault::builder::VaultBuilder;
+/// use stellarvault::{strategy, RiskLevel};
+///
+/// let vault = VaultBuilder::new(RiskLevel::Medium)
+///     .strategy(strategy::AQUA_LIQUIDITY_POOL, 70, 850)
+///     .strategy(strategy::YIELDBLOX_LENDING, 30, 400)
+///     .insurance_fee(100)
+///     .max_tvl(1_000_000 * 10_000_000)
+///     .build()
+///     .unwrap();
+/// assert_eq!(vault.blended_apy(), 715);
+/// ```
+#[derive(Debug, Clone)]
+pub struct VaultBuilder {
+    vault: Vault,
+}
+
+impl VaultBuilder {
+    /// A vault with no strategies yet, XLM deposits from 1 XLM, no caps and
+    /// the standard 0.5% management and 10% performance fees.
+    pub fn new(risk_level: RiskLevel) -> Self {
+        VaultBuilder {
+            vault: Vault {
+                risk_level,
+                total_value: 0,
+                total_shares: 0,
+                insurance_fee: 0,
+                liquidity_buffer_bps: 1000,
+                liquid_reserve: 0,
+                premium_bounds: None,
+                strategies: Vec::new(),
+                last_harvest: 0,
+                max_tvl: None,
+                per_user_cap: None,
+                max_tvl_usd: None,
+                per_user_cap_usd: None,
+                min_deposit: XLM,
+                lockup_secs: 0,
+                early_withdrawal_penalty: 0,
+                fees: FeeConfig {
+                    management_fee_bps: DEFAULT_MANAGEMENT_FEE_BPS,
+                    performance_fee_bps: DEFAULT_PERFORMANCE_FEE_BPS,
+                },
+                fee_accrual: FeeAccrual::default(),
+                circuit: CircuitBreaker::default(),
+                asset: None,
+                market_making: None,
+                liquidity_pool: None,
+                lending: None,
+                soroswap: None,
+            },
+        }
+    }
+
+    /// Adds the registered strategy `id`, holding `allocation` percent of
+    /// the vault's funds and expected to earn `apy_bps` a year.
+    pub fn strategy(mut self, id: &str, allocation: u8, apy_bps: u16) -> Self {
+        self.vault.strategies.push(StrategyAllocation {
+            strategy: id.to_string(),
+            allocation_percentage: allocation,
+            current_apy: apy_bps,
+            total_allocated: 0,
+            current_yield: 0,
+        });
+        self
+    }
+
+    /// The premium each deposit pays into the insurance pool.
+    pub fn insurance_fee(mut self, fee_bps: u16) -> Self {
+        self.vault.insurance_fee = fee_bps;
+        self
+    }
+
+    /// Lets the premium float between `min_bps` and `max_bps` to track how
+    /// well the insurance pool covers the vaults.
+    pub fn premium_bounds(mut self, min_bps: u16, max_bps: u16) -> Self {
+        self.vault.premium_bounds = Some(PremiumBounds { min_bps, max_bps });
+        self
+    }
+
+    pub fn fees(mut self, management_fee_bps: u16, performance_fee_bps: u16) -> Self {
+        self.vault.fees = FeeConfig { management_fee_bps, performance_fee_bps };
+        self
+    }
+
+    /// Caps the vault's total value; further deposits are refused.
+    pub fn max_tvl(mut self, cap: u64) -> Self {
+        self.vault.max_tvl = Some(cap);
+        self
+    }
+
+    /// Caps what any one user may hold in the vault.
+    pub fn per_user_cap(mut self, cap: u64) -> Self {
+        self.vault.per_user_cap = Some(cap);
+        self
+    }
+
+    /// Caps the vault's total value in USD (7 decimals) at the oracle price.
+    pub fn max_tvl_usd(mut self, cap: u64) -> Self {
+        self.vault.max_tvl_usd = Some(cap);
+        self
+    }
+
+    /// Caps what any one user may hold in USD (7 decimals).
+    pub fn per_user_cap_usd(mut self, cap: u64) -> Self {
+        self.vault.per_user_cap_usd = Some(cap);
+        self
+    }
+
+    pub fn min_deposit(mut self, minimum: u64) -> Self {
+        self.vault.min_deposit = minimum;
+        self
+    }
+
+    /// Shares withdrawn within `secs` of the deposit that bought them pay
+    /// `penalty_bps` to the insurance pool.
+    pub fn lockup(mut self, secs: u64, penalty_bps: u16) -> Self {
+        self.vault.lockup_secs = secs;
+        self.vault.early_withdrawal_penalty = penalty_bps;
+        self
+    }
+
+    /// Share of incoming funds kept liquid for instant withdrawals.
+    pub fn liquidity_buffer(mut self, buffer_bps: u16) -> Self {
+        self.vault.liquidity_buffer_bps = buffer_bps;
+        self
+    }
+
+    /// Pauses the vault when its share price moves more than this between
+    /// operations; 0 disables the breaker.
+    pub fn circuit_limit(mut self, max_price_move_bps: u16) -> Self {
+        self.vault.circuit = CircuitBreaker::with_limit(max_price_move_bps);
+        self
+    }
+
+    /// Takes deposits in an issued asset instead of XLM.
+    pub fn asset(mut self, asset: AssetId) -> Self {
+        self.vault.asset = Some(asset);
+        self
+    }
+
+    pub fn build(self) -> Result<Vault, VaultError> {
+        let vault = self.vault;
+        let invalid = |reason: String| Err(VaultError::Validation(format!("{:?} Risk Vault: {}", vault.risk_level, reason)));
+
+        if let Err(reason) = check_strategies(&vault.strategies) {
+            return invalid(reason);
+        }
+
+        let limits = [
+            ("insurance fee", vault.insurance_fee, MAX_INSURANCE_FEE_BPS),
+            ("management fee", vault.fees.management_fee_bps, MAX_MANAGEMENT_FEE_BPS),
+            ("performance fee", vault.fees.performance_fee_bps, MAX_PERFORMANCE_FEE_BPS),
+            ("early withdrawal penalty", vault.early_withdrawal_penalty, MAX_EARLY_WITHDRAWAL_PENALTY_BPS),
+            ("liquidity buffer", vault.liquidity_buffer_bps, BPS_DENOMINATOR as u16),
+        ];
+        for (label, bps, max) in limits {
+            if bps > max {
+                return invalid(format!("{} of {} bps is above the {} bps limit", label, bps, max));
+            }
+        }
+        if let Some(bounds) = &vault.premium_bounds {
+            if bounds.min_bps > bounds.max_bps || bounds.max_bps > MAX_INSURANCE_FEE_BPS {
+                return invalid(format!("premium bounds {}-{} bps must be ordered and within {} bps",
+                    bounds.min_bps, bounds.max_bps, MAX_INSURANCE_FEE_BPS));
+            }
+            if !(bounds.min_bps..=bounds.max_bps).contains(&vault.insurance_fee) {
+                return invalid(format!("insurance fee of {} bps is outside its {}-{} bps bounds",
+                    vault.insurance_fee, bounds.min_bps, bounds.max_bps));
+            }
+        }
+
+        if vault.min_deposit == 0 {
+            return invalid("minimum deposit must be above zero".into());
+        }
+        for cap in [vault.per_user_cap, vault.max_tvl].into_iter().flatten() {
+            if cap < vault.min_deposit {
+                return invalid("caps must allow at least the minimum deposit".into());
+            }
+        }
+        for (per_user, max_tvl) in [(vault.per_user_cap, vault.max_tvl), (vault.per_user_cap_usd, vault.max_tvl_usd)] {
+            if let (Some(per_user), Some(max_tvl)) = (per_user, max_tvl) {
+                if per_user > max_tvl {
+                    return invalid("the per-user cap is above the vault cap".into());
+                }
+            }
+        }
+        if vault.per_user_cap_usd == Some(0) || vault.max_tvl_usd == Some(0) {
+            return invalid("USD caps must be above zero".into());
+        }
+        Ok(vault)
+    }
+}
+
+// Registered strategies, each listed once, whose allocations sum to 100%
+pub fn check_strategies(strategies: &[StrategyAllocation]) -> Result<(), String> {
+    if strategies.is_empty() {
+        return Err("needs at least one strategy".into());
+    }
+    for (i, strategy) in strategies.iter().enumerate() {
+        if !strategy::is_registered(&strategy.strategy) {
+            return Err(format!("no strategy {:?} is registered", strategy.strategy));
+        }
+        if strategy.allocation_percentage == 0 {
+            return Err(format!("{} has no allocation", strategy.strategy));
+        }
+        if strategies[..i].iter().any(|s| s.strategy == strategy.strategy) {
+            return Err(format!("{} is listed twice", strategy.strategy));
+        }
+    }
+    let allocated: u32 = strategies.iter().map(|s| s.allocation_percentage as u32).sum();
+    if allocated != 100 {
+        return Err(format!("strategy allocations sum to {}%, not 100%", allocated));
+    }
+    Ok(())
+}
+
+// The three tiers StellarVault runs when no vaults are defined
+pub fn default_vaults() -> Vec<VaultBuilder> {
+    vec![
+        VaultBuilder::new(RiskLevel::Low)
+            .strategy(strategy::YIELDBLOX_LENDING, 100, 350)
+            .insurance_fee(50)
+            .premium_bounds(25, 100)
+            .circuit_limit(500),
+        VaultBuilder::new(RiskLevel::Medium)
+            .strategy(strategy::AQUA_LIQUIDITY_POOL, 60, 850)
+            .strategy(strategy::YIELDBLOX_LENDING, 40, 400)
+            .insurance_fee(100)
+            .premium_bounds(50, 200)
+            .circuit_limit(1000),
+        VaultBuilder::new(RiskLevel::High)
+            .strategy(strategy::MONEY_MARKET, 100, 1500)
+            .insurance_fee(200)
+            .premium_bounds(100, 400)
+            // Limited exposure while the money market strategy is rolled out
+            .max_tvl(100_000 * XLM)
+            .per_user_cap(10_000 * XLM)
+            .lockup(30 * 24 * 60 * 60, 300)
+            .circuit_limit(2000),
+    ]
+}
+
+// Builds every definition, one per risk level, so none is used unless all
+// of them are valid
+pub fn build_vaults(definitions: Vec<VaultBuilder>) -> Result<HashMap<RiskLevel, Vault>, VaultError> {
+    let mut vaults = HashMap::new();
+    for definition in definitions {
+        let vault = definition.build()?;
+        let risk = vault.risk_level;
+        if vaults.insert(risk, vault).is_some() {
+            return Err(VaultError::Validation(format!("The {:?} Risk Vault is defined twice", risk)));
+        }
+    }
+    Ok(vaults)
+}
+
+// ============================================================================
+// STELLARVAULT BUILDER
+// ============================================================================
+
+/// Sets up a [`StellarVault`] with the vaults an integrator defines, or the
+/// standard Low, Medium and High tiers when none are.
+///
+/// State saved by an earlier run is restored over the definitions. Its
+/// balances are kept either way; when vaults are defined, their settings
+/// (strategy targets, fees, caps and limits) replace the saved ones, so a
+/// product can be retuned between runs. Raised fees or lockups and changed
+/// strategies are queued behind the admin delay instead, as an admin would
+/// have to.
+///
+/// ```no_run
+/// use stellarvault::builder::{StellarVaultBuilder, VaultBuilder};
+/// use stellarvault::{strategy, Network, RiskLevel};
+///
+/// # fn run(vault_address: &str) -> Result<(), stellarvault::VaultError> {
+/// let vault = StellarVaultBuilder::new(vault_address, &Network::Testnet)
+///     .vault(VaultBuilder::new(RiskLevel::Low)
+///         .strategy(strategy::YIELDBLOX_LENDING, 100, 350)
+///         .insurance_fee(50))
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct StellarVaultBuilder {
+    vault_address: String,
+    network: Network,
+    fee_strategy: FeeStrategy,
+    retry: RetryPolicy,
+    horizon_fallbacks: Vec<String>,
+    horizon_breaker: BreakerPolicy,
+    admin_delay_secs: u64,
+    vaults: Vec<VaultBuilder>,
+}
+
+impl StellarVaultBuilder {
+    pub fn new(vault_address: &str, network: &Network) -> Self {
+        StellarVaultBuilder {
+            vault_address: vault_address.to_string(),
+            network: network.clone(),
+            fee_strategy: FeeStrategy::default(),
+            retry: RetryPolicy::default(),
+            horizon_fallbacks: Vec::new(),
+            horizon_breaker: BreakerPolicy::default(),
+            admin_delay_secs: timelock::DEFAULT_ADMIN_DELAY_SECS,
+            vaults: Vec::new(),
+        }
+    }
+
+    /// How transaction fees are bid when the network is busy.
+    pub fn fee_strategy(mut self, fee_strategy: FeeStrategy) -> Self {
+        self.fee_strategy = fee_strategy;
+        self
+    }
+
+    /// Timeouts and retries for Horizon calls.
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Horizon servers to fail over to, in order, when the network's own
+    /// keeps failing or answering slowly.
+    pub fn horizon_fallbacks(mut self, urls: Vec<String>) -> Self {
+        self.horizon_fallbacks = urls;
+        self
+    }
+
+    /// When Horizon calls stop being attempted after repeated failures.
+    pub fn horizon_breaker(mut self, policy: BreakerPolicy) -> Self {
+        self.horizon_breaker = policy;
+        self
+    }
+
+    /// How long fee and lockup rises, strategy changes and treasury moves
+    /// wait before taking effect, including ones made by editing the vault
+    /// definitions. A delay shorter than the saved one has to wait out the
+    /// saved one first; until then building fails.
+    pub fn admin_delay(mut self, secs: u64) -> Self {
+        self.admin_delay_secs = secs;
+        self
+    }
+
+    /// Adds a vault. Each risk level may be defined once.
+    pub fn vault(mut self, vault: VaultBuilder) -> Self {
+        self.vaults.push(vault);
+        self
+    }
+
+    pub fn build(self) -> Result<StellarVault, VaultError> {
+        if !self.vault_address.starts_with('G') || self.vault_address.len() != 56 {
+            return Err(VaultError::Validation("Invalid vault address format (must start with G and be 56 chars)".into()));
+        }
+
+        let redefine = !self.vaults.is_empty();
+        let definitions = match redefine {
+            true => self.vaults,
+            false => default_vaults(),
+        };
+        let vaults = build_vaults(definitions)?;
+
+        let defined = vaults.clone();
+        let network = self.network;
+        // One client for reads and submissions, so both follow a failover
+        let horizon = HorizonClient::for_network(&network)
+            .with_retry(self.retry)
+            .with_fallbacks(&self.horizon_fallbacks)
+            .with_breaker(self.horizon_breaker);
+        // The mock ledger starts empty every run, so nothing is kept for it
+        let storage: Box<dyn storage::Store> = match network.is_offline() {
+            true => Box::new(storage::MemoryStorage::default()),
+            false => storage::open_default()?,
+        };
+        let mut stellar_vault = StellarVault {
+            vaults,
+            user_positions: HashMap::new(),
+            insurance_pool: 0,
+            asset_insurance: BTreeMap::new(),
+            insurance_investment: InsuranceInvestment::default(),
+            coverage_policy: CoveragePolicy::default(),
+            claims: ClaimBook::default(),
+            users: UserRegistry::new(),
+            vault_signer: None,
+            vault_signer_addresses: Vec::new(),
+            vault_address: self.vault_address,
+            treasury_address: None,
+            treasury_memo: None,
+            share_issuer: None,
+            share_issuance: ShareIssuance::default(),
+            withdrawal_queue: WithdrawalQueue::default(),
+            pending_deposits: DepositBook::default(),
+            ingest_cursor: None,
+            federation: federation::Resolver::default(),
+            deposit_routes: DepositRoutes::default(),
+            contract_index: ContractIndex::default(),
+            recurring: RecurringBook::default(),
+            journal: OperationJournal::default(),
+            idempotency: IdempotencyBook::default(),
+            idempotency_window_secs: idempotency::DEFAULT_IDEMPOTENCY_WINDOW_SECS,
+            timelock: Timelock::default(),
+            admin_delay_secs: self.admin_delay_secs,
+            address_lists: AddressLists::default(),
+            allowlist_only: false,
+            event_seq: 0,
+            event_feed: None,
+            notifications: None,
+            submitter: Submitter::new(horizon.clone(), network.passphrase(), self.fee_strategy),
+            horizon,
+            network,
+            fee_payer_address: None,
+            max_slippage_bps: path_payment::DEFAULT_MAX_SLIPPAGE_BPS,
+            soroban: None,
+            vault_contract: None,
+            share_tokens: Vec::new(),
+            storage,
+            prices: PriceBook::default(),
+        };
+
+        let events = stellar_vault.storage.load_events()?;
+        stellar_vault.event_seq = events.last().map(|e| e.seq).unwrap_or(0);
+
+        // Without a saved state the event log is the only record of balances
+        match stellar_vault.storage.load()? {
+            Some(state) => stellar_vault.restore(state),
+            None if !events.is_empty() => {
+                let replayed = events::replay(&stellar_vault.vaults, &events)?;
+                stellar_vault.apply_replay(replayed);
+                stellar_vault.persist()?;
+                say!("♻️  Rebuilt vault state from {} logged events", events.len());
+            }
+            None => {}
+        }
+        stellar_vault.configure_admin_delay(self.admin_delay_secs)?;
+        if redefine {
+            let (applied, queued) = stellar_vault.apply_definitions(&defined);
+            for (risk, changes) in applied {
+                say!("🔧 {:?} Risk Vault settings changed since the last run: {}", risk, changes.join(", "));
+            }
+            for change in queued {
+                say!("⏳ Admin change #{} queued from the vault definitions: {}", change.id, change.change.describe());
+            }
+        }
+
+        Ok(stellar_vault)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vault_definitions_are_checked() {
+        for vault in default_vaults() {
+            assert!(vault.build().is_ok());
+        }
+
+        let lending = || VaultBuilder::new(RiskLevel::Low).strategy(strategy::YIELDBLOX_LENDING, 60, 350);
+        assert!(matches!(lending().build(), Err(VaultError::Validation(_))));
+        assert!(lending().strategy(strategy::MONEY_MARKET, 40, 900).build().is_ok());
+        assert!(lending().strategy(strategy::YIELDBLOX_LENDING, 40, 350).build().is_err());
+
+        let balanced = || lending().strategy(strategy::MONEY_MARKET, 40, 900);
+        assert!(balanced().insurance_fee(MAX_INSURANCE_FEE_BPS + 1).build().is_err());
+        assert!(balanced().fees(50, MAX_PERFORMANCE_FEE_BPS + 1).build().is_err());
+        assert!(balanced().insurance_fee(300).premium_bounds(25, 100).build().is_err());
+        assert!(balanced().max_tvl(100 * XLM).per_user_cap(200 * XLM).build().is_err());
+        assert!(balanced().max_tvl(XLM / 2).build().is_err());
+    }
+}
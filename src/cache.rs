@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+// ============================================================================
+// TTL CACHE
+// ============================================================================
+
+#[derive(Debug)]
+struct Entries<K, V> {
+    values: HashMap<K, (Instant, V)>,
+    // Bumped by every clear, so a fetch that started before one can't put
+    // what it read back afterwards
+    generation: u64,
+}
+
+// Values reused for `ttl` after they were fetched. Clones share the entries,
+// so whatever clears the cache clears it for every holder.
+#[derive(Debug, Clone)]
+pub struct TtlCache<K, V> {
+    ttl: Duration,
+    entries: Arc<Mutex<Entries<K, V>>>,
+}
+
+impl<K: Eq + Hash, V: Clone> TtlCache<K, V> {
+    pub fn new(ttl: Duration) -> Self {
+        TtlCache {
+            ttl,
+            entries: Arc::new(Mutex::new(Entries { values: HashMap::new(), generation: 0 })),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Entries<K, V>> {
+        self.entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let entries = self.lock();
+        entries.values.get(key)
+            .filter(|(fetched_at, _)| fetched_at.elapsed() < self.ttl)
+            .map(|(_, value)| value.clone())
+    }
+
+    // Taken before fetching and handed back to insert
+    pub fn ticket(&self) -> u64 {
+        self.lock().generation
+    }
+
+    // Dropped when the cache was cleared since `ticket` was taken
+    pub fn insert(&self, ticket: u64, key: K, value: V) {
+        let mut entries = self.lock();
+        if entries.generation != ticket {
+            return;
+        }
+        let ttl = self.ttl;
+        entries.values.retain(|_, (fetched_at, _)| fetched_at.elapsed() < ttl);
+        entries.values.insert(key, (Instant::now(), value));
+    }
+
+    pub fn clear(&self) {
+        let mut entries = self.lock();
+        entries.values.clear();
+        entries.generation += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serves_until_expired_or_cleared() {
+        let cache = TtlCache::new(Duration::from_secs(60));
+        let ticket = cache.ticket();
+        cache.insert(ticket, "GVAULT", 100);
+        assert_eq!(cache.get(&"GVAULT"), Some(100));
+
+        // A fetch that raced a clear doesn't bring the old value back
+        let stale = cache.ticket();
+        cache.clear();
+        cache.insert(stale, "GVAULT", 100);
+        assert_eq!(cache.get(&"GVAULT"), None);
+        let shared = cache.clone();
+        shared.insert(shared.ticket(), "GVAULT", 90);
+        assert_eq!(cache.get(&"GVAULT"), Some(90));
+
+        let expired = TtlCache::new(Duration::ZERO);
+        expired.insert(expired.ticket(), "GVAULT", 100);
+        assert_eq!(expired.get(&"GVAULT"), None);
+    }
+}
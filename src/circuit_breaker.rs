@@ -0,0 +1,208 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_DEPENDENCY_FAILURES: u32 = 5;
+pub const DEFAULT_DEPENDENCY_COOLDOWN_SECS: u64 = 30;
+
+// ============================================================================
+// PAUSE & CIRCUIT BREAKER
+// ============================================================================
+
+// A paused vault rejects deposits and withdrawals until an admin resumes it.
+// The breaker pauses it automatically when the share price jumps between
+// operations, which usually means the accounting has gone wrong.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CircuitBreaker {
+    pub paused: bool,
+    pub reason: Option<String>,
+    pub paused_at: Option<u64>,
+    // Largest share price move allowed between operations, in basis points;
+    // 0 disables the automatic breaker
+    pub max_price_move_bps: u16,
+    // Share price after the last deposit or withdrawal; 0 until the first one
+    pub last_share_price: u64,
+}
+
+pub fn price_move_bps(previous: u64, current: u64) -> u64 {
+    if previous == 0 {
+        return 0;
+    }
+    (previous.abs_diff(current) as u128 * 10_000 / previous as u128) as u64
+}
+
+impl CircuitBreaker {
+    pub fn with_limit(max_price_move_bps: u16) -> Self {
+        CircuitBreaker {
+            max_price_move_bps,
+            ..Default::default()
+        }
+    }
+
+    // Returns the move in basis points when it exceeds the configured limit
+    pub fn exceeded(&self, share_price: u64) -> Option<u64> {
+        if self.max_price_move_bps == 0 {
+            return None;
+        }
+        let moved = price_move_bps(self.last_share_price, share_price);
+        (moved > self.max_price_move_bps as u64).then_some(moved)
+    }
+
+    pub fn pause(&mut self, reason: &str, now: u64) {
+        self.paused = true;
+        self.reason = Some(reason.to_string());
+        self.paused_at = Some(now);
+    }
+
+    // Resuming accepts the current share price as the new baseline, otherwise
+    // the same move would trip the breaker again on the next operation
+    pub fn resume(&mut self, share_price: u64) {
+        self.paused = false;
+        self.reason = None;
+        self.paused_at = None;
+        self.last_share_price = share_price;
+    }
+}
+
+// ============================================================================
+// DEPENDENCY BREAKER
+// ============================================================================
+
+// When calls to a service stop being attempted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreakerPolicy {
+    // Failed calls in a row that open the breaker; 0 never opens it
+    pub failures: u32,
+    // How long it stays open before a call is let through to probe
+    pub cooldown: Duration,
+}
+
+impl Default for BreakerPolicy {
+    fn default() -> Self {
+        BreakerPolicy {
+            failures: DEFAULT_DEPENDENCY_FAILURES,
+            cooldown: Duration::from_secs(DEFAULT_DEPENDENCY_COOLDOWN_SECS),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    Closed,
+    Open,
+    // Cooled down: the next call is let through, and decides which way it goes
+    HalfOpen,
+}
+
+#[derive(Debug, Default)]
+struct Breaker {
+    failures: u32,
+    opened_at: Option<Instant>,
+    // The call let through while half open, if it hasn't finished
+    probe_started: Option<Instant>,
+}
+
+// Stops calling a service that keeps failing, so callers fail at once with a
+// clear error instead of each waiting out its own timeouts and retries.
+// Clones share the state.
+#[derive(Debug, Clone)]
+pub struct DependencyBreaker {
+    policy: BreakerPolicy,
+    breaker: Arc<Mutex<Breaker>>,
+}
+
+impl DependencyBreaker {
+    pub fn new(policy: BreakerPolicy) -> Self {
+        DependencyBreaker { policy, breaker: Arc::default() }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Breaker> {
+        self.breaker.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn state(&self, now: Instant) -> BreakerState {
+        match self.lock().opened_at {
+            None => BreakerState::Closed,
+            Some(opened_at) if now.duration_since(opened_at) < self.policy.cooldown => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+
+    // Whether a call may go ahead, or how long until one will. Once half
+    // open, one call at a time is let through; a probe that never reports
+    // back is given up on after another cooldown.
+    pub fn allow(&self, now: Instant) -> Result<(), Duration> {
+        let mut breaker = self.lock();
+        let Some(opened_at) = breaker.opened_at else {
+            return Ok(());
+        };
+        let reopens = opened_at + self.policy.cooldown;
+        if now < reopens {
+            return Err(reopens - now);
+        }
+        if let Some(started) = breaker.probe_started.filter(|started| now.duration_since(*started) < self.policy.cooldown) {
+            return Err(started + self.policy.cooldown - now);
+        }
+        breaker.probe_started = Some(now);
+        Ok(())
+    }
+
+    // True when this closed an open breaker
+    pub fn succeeded(&self) -> bool {
+        let mut breaker = self.lock();
+        let was_open = breaker.opened_at.is_some();
+        *breaker = Breaker::default();
+        was_open
+    }
+
+    // True when this failure opened the breaker, or kept it open after a
+    // failed probe
+    pub fn failed(&self, now: Instant) -> bool {
+        let mut breaker = self.lock();
+        breaker.failures = breaker.failures.saturating_add(1);
+        let probing = breaker.probe_started.take().is_some();
+        if self.policy.failures == 0 || (!probing && breaker.failures < self.policy.failures) {
+            return false;
+        }
+        breaker.opened_at = Some(now);
+        true
+    }
+
+    pub fn failures(&self) -> u32 {
+        self.lock().failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_after_repeated_failures_and_probes_once_cooled_down() {
+        let breaker = DependencyBreaker::new(BreakerPolicy { failures: 3, cooldown: Duration::from_secs(30) });
+        let start = Instant::now();
+        assert!(!breaker.failed(start));
+        breaker.succeeded();
+        assert!(!breaker.failed(start));
+        assert!(!breaker.failed(start));
+        assert!(breaker.failed(start));
+        assert_eq!(breaker.state(start), BreakerState::Open);
+        assert_eq!(breaker.allow(start + Duration::from_secs(10)), Err(Duration::from_secs(20)));
+
+        // Cooled down: one probe goes through, the rest wait for it
+        let cooled = start + Duration::from_secs(30);
+        assert_eq!(breaker.state(cooled), BreakerState::HalfOpen);
+        assert_eq!(breaker.allow(cooled), Ok(()));
+        assert!(breaker.allow(cooled).is_err());
+        // The probe failing opens it again for a whole cooldown
+        assert!(breaker.failed(cooled));
+        assert_eq!(breaker.allow(cooled + Duration::from_secs(29)), Err(Duration::from_secs(1)));
+
+        let again = cooled + Duration::from_secs(30);
+        assert_eq!(breaker.allow(again), Ok(()));
+        breaker.succeeded();
+        assert_eq!((breaker.state(again), breaker.failures()), (BreakerState::Closed, 0));
+        assert_eq!(breaker.allow(again), Ok(()));
+    }
+}
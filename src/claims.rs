@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+
+use crate::RiskLevel;
+use crate::error::VaultError;
+
+// ============================================================================
+// CLAIMS
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClaimStatus {
+    Pending,
+    Approved,
+    Denied,
+    Paid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claim {
+    pub id: u64,
+    pub claimant: String,
+    pub risk: RiskLevel,
+    pub amount_stroops: u64,
+    pub loss_event: String,
+    pub status: ClaimStatus,
+    pub filed_at: u64,
+    pub decided_at: Option<u64>,
+    pub decision_note: Option<String>,
+    pub payout_tx: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClaimBook {
+    claims: Vec<Claim>,
+    next_id: u64,
+}
+
+impl ClaimBook {
+    #[cfg_attr(not(feature = "storage-sqlite"), allow(dead_code))]
+    pub fn from_claims(claims: Vec<Claim>) -> Self {
+        let next_id = claims.iter().map(|c| c.id).max().unwrap_or(0);
+        ClaimBook { claims, next_id }
+    }
+
+    pub fn file(&mut self, claimant: &str, risk: RiskLevel, amount_stroops: u64, loss_event: &str, now: u64) -> u64 {
+        self.next_id += 1;
+        self.claims.push(Claim {
+            id: self.next_id,
+            claimant: claimant.to_string(),
+            risk,
+            amount_stroops,
+            loss_event: loss_event.to_string(),
+            status: ClaimStatus::Pending,
+            filed_at: now,
+            decided_at: None,
+            decision_note: None,
+            payout_tx: None,
+        });
+        self.next_id
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Claim> {
+        self.claims.iter().find(|c| c.id == id)
+    }
+
+    pub fn all(&self) -> &[Claim] {
+        &self.claims
+    }
+
+    pub fn for_claimant<'a>(&'a self, claimant: &'a str) -> impl Iterator<Item = &'a Claim> + 'a {
+        self.claims.iter().filter(move |c| c.claimant == claimant)
+    }
+
+    // Moves a pending claim to its decided state; payouts are recorded separately
+    pub fn decide(&mut self, id: u64, status: ClaimStatus, note: Option<String>, now: u64) -> Result<&Claim, VaultError> {
+        let claim = self.claims.iter_mut().find(|c| c.id == id)
+            .ok_or_else(|| VaultError::Validation(format!("Claim #{} not found", id)))?;
+        if claim.status != ClaimStatus::Pending {
+            return Err(VaultError::Validation(format!("Claim #{} is already {:?}", id, claim.status)));
+        }
+
+        claim.status = status;
+        claim.decided_at = Some(now);
+        claim.decision_note = note;
+        Ok(claim)
+    }
+
+    pub fn mark_paid(&mut self, id: u64, tx_hash: &str) -> Result<(), VaultError> {
+        let claim = self.claims.iter_mut().find(|c| c.id == id)
+            .ok_or_else(|| VaultError::Validation(format!("Claim #{} not found", id)))?;
+        claim.status = ClaimStatus::Paid;
+        claim.payout_tx = Some(tx_hash.to_string());
+        Ok(())
+    }
+}
@@ -0,0 +1,554 @@
+use std::collections::HashMap;
+use stellar_xdr::curr::Memo;
+
+use crate::{assets, horizon, offline, path_payment, soroban, transaction};
+use crate::amount::Stroops;
+use crate::assets::AssetId;
+use crate::error::VaultError;
+use crate::events::YieldPayout;
+use crate::fee_strategy::FeeEstimate;
+use crate::horizon::{Balance, HorizonClient};
+use crate::multisig::{SignerSet, Threshold};
+use crate::network::Network;
+use crate::signer::Signer;
+use crate::submission::{SubmitError, Submitter};
+use crate::transaction::{Keypair, TransactionBuilder};
+use crate::{get_user_input, unix_now};
+
+// ============================================================================
+// STELLAR INTEGRATION
+// ============================================================================
+
+/// A transaction that made it into a ledger.
+#[derive(Debug, Clone)]
+pub struct TransactionReceipt {
+    pub hash: String,
+    pub ledger: u32,
+    pub fee_charged: u64,
+    pub created_at: String,
+    pub result_xdr: String,
+}
+
+impl From<horizon::Transaction> for TransactionReceipt {
+    fn from(transaction: horizon::Transaction) -> Self {
+        TransactionReceipt {
+            hash: transaction.hash,
+            ledger: transaction.ledger,
+            fee_charged: transaction.fee_charged,
+            created_at: transaction.created_at,
+            result_xdr: transaction.result_xdr,
+        }
+    }
+}
+
+impl From<(String, soroban::TransactionInfo)> for TransactionReceipt {
+    fn from((hash, info): (String, soroban::TransactionInfo)) -> Self {
+        TransactionReceipt {
+            hash,
+            ledger: info.ledger.unwrap_or(0),
+            fee_charged: info.fee_charged().unwrap_or(0),
+            created_at: info.created_at(),
+            result_xdr: info.result_xdr.clone().unwrap_or_default(),
+        }
+    }
+}
+
+// Transactions built for offline signing stay valid this long by default
+pub const OFFLINE_VALIDITY_HOURS: u64 = 24;
+
+// Payments expire if they haven't made it into a ledger by then, so a signed
+// deposit can't land long after its share price was quoted
+pub const PAYMENT_TIMEOUT_SECS: u64 = 300;
+// The same window in ledgers (~5s each), which holds even if the local clock is off
+pub const PAYMENT_TIMEOUT_LEDGERS: u32 = 60;
+
+/// A transaction built as it would be sent, but neither signed nor submitted.
+#[derive(Debug, Clone)]
+pub struct TransactionPreview {
+    pub hash: String,
+    pub fee: FeeEstimate,
+    // Unsigned TransactionEnvelope, base64
+    pub xdr: String,
+}
+
+/// Signs and submits transactions for one Stellar account, through Horizon
+/// and the shared [`Submitter`].
+pub struct StellarClient {
+    // Keys that may sign for the account: its own key, or on a multisig
+    // account any of its signers, held locally or on a hardware wallet
+    pub signers: Vec<Box<dyn Signer>>,
+    pub public_key: String,
+    pub network: Network,
+    pub horizon: HorizonClient,
+    pub submitter: Submitter,
+}
+
+impl StellarClient {
+    /// A client signing with the account's own secret key.
+    pub fn new(secret_key: &str, public_key: &str, network: &Network, submitter: &Submitter) -> Result<Self, VaultError> {
+        let mut client = Self::for_account(public_key, network, submitter)?;
+        if client.add_secret_key(secret_key)? != public_key {
+            return Err(VaultError::Validation("Secret key does not belong to the given public key".into()));
+        }
+        Ok(client)
+    }
+
+    /// A client with no keys yet, for an account signed for by other keys.
+    pub fn for_account(public_key: &str, network: &Network, submitter: &Submitter) -> Result<Self, VaultError> {
+        if !public_key.starts_with('G') || public_key.len() != 56 {
+            return Err(VaultError::Validation("Invalid Stellar public key format (must start with G and be 56 chars)".into()));
+        }
+
+        Ok(StellarClient {
+            signers: Vec::new(),
+            public_key: public_key.to_string(),
+            network: network.clone(),
+            horizon: submitter.horizon().clone(),
+            submitter: submitter.clone(),
+        })
+    }
+
+    pub fn add_secret_key(&mut self, secret_key: &str) -> Result<String, VaultError> {
+        if !secret_key.starts_with('S') || secret_key.len() != 56 {
+            return Err(VaultError::Validation("Invalid Stellar secret key format (must start with S and be 56 chars)".into()));
+        }
+        Ok(self.add_signer(Box::new(Keypair::from_secret(secret_key)?)))
+    }
+
+    pub fn add_signer(&mut self, signer: Box<dyn Signer>) -> String {
+        let public_key = signer.public_key();
+        if !self.signers.iter().any(|s| s.public_key() == public_key) {
+            self.signers.push(signer);
+        }
+        public_key
+    }
+
+    // Whether the key was loaded
+    pub fn remove_signer(&mut self, public_key: &str) -> bool {
+        let before = self.signers.len();
+        self.signers.retain(|s| s.public_key() != public_key);
+        self.signers.len() != before
+    }
+
+    pub fn signer_keys(&self) -> Vec<String> {
+        self.signers.iter().map(|s| s.public_key()).collect()
+    }
+
+    // The loaded keys the account accepts, once together they meet the
+    // threshold. Keys that aren't signers are left out, since Horizon refuses
+    // transactions carrying extra signatures.
+    pub async fn signing_keys(&self, threshold: Threshold) -> Result<Vec<&dyn Signer>, VaultError> {
+        let account = self.horizon.account(&self.public_key).await
+            .map_err(|e| format!("Failed to load signers of {}: {}", self.public_key, e))?;
+        let signer_set = SignerSet::from_account(&account);
+        let keys: Vec<&dyn Signer> = self.signers.iter()
+            .map(|k| k.as_ref())
+            .filter(|k| signer_set.weight(&k.public_key()) > 0)
+            .collect();
+        let public_keys: Vec<String> = keys.iter().map(|k| k.public_key()).collect();
+        let weight = signer_set.check(&public_keys.iter().map(String::as_str).collect::<Vec<_>>(), threshold)
+            .map_err(|e| format!("{}; load more operator keys with 'multisig cosign' or sign offline with 'tx build'", e))?;
+        if signer_set.is_multisig() {
+            say!("   🔏 Signed by {} of {} signers (weight {} of {})", keys.len(), signer_set.signers.len(),
+                weight, signer_set.required(threshold));
+        }
+        Ok(keys)
+    }
+
+    pub fn get_public_key(&self) -> String {
+        self.public_key.clone()
+    }
+
+    // Horizon balance entries: native XLM plus one per trustline
+    pub async fn get_balances(&self) -> Result<Vec<Balance>, VaultError> {
+        let account = self.horizon.account(&self.public_key).await
+            .map_err(|e| format!("Failed to get balance: {}", e))?;
+        Ok(account.balances)
+    }
+
+    pub async fn get_balance(&self) -> Result<f64, VaultError> {
+        let balances = self.get_balances().await?;
+        // Horizon lists the native balance after any trustlines
+        let native = balances.iter().find(|b| b.is_native());
+        Ok(native.and_then(|b| b.balance.parse().ok()).unwrap_or(0.0))
+    }
+
+    // The native balance exactly, where get_balance is for showing
+    pub async fn get_xlm_balance(&self) -> Result<Stroops, VaultError> {
+        let balances = self.get_balances().await?;
+        balances.iter()
+            .find(|b| b.is_native())
+            .map_or(Ok(Stroops::ZERO), |b| Stroops::from_xlm_str(&b.balance))
+    }
+
+    // The account's balance of a credit asset, None without a trustline
+    pub async fn get_asset_balance(&self, asset: &AssetId) -> Result<Option<Stroops>, VaultError> {
+        let balances = self.get_balances().await?;
+        asset.trustline(&balances)
+            .map(|balance| Stroops::from_xlm_str(&balance.balance))
+            .transpose()
+    }
+
+    // Builds, signs and submits a native payment
+    pub async fn send_payment(&self, destination: &str, amount: Stroops, memo: Memo) -> Result<TransactionReceipt, VaultError> {
+        self.send_asset(destination, stellar_xdr::curr::Asset::Native, amount, memo).await
+    }
+
+    pub async fn send_asset(&self, destination: &str, asset: stellar_xdr::curr::Asset, amount: Stroops, memo: Memo) -> Result<TransactionReceipt, VaultError> {
+        say!("\n🚀 Submitting transaction to {}...", self.network);
+        say!("   From: {}", self.public_key);
+        say!("   To: {}", destination);
+        say!("   Amount: {} {}", amount.to_xlm_string(), assets::code_of(&asset));
+        if let Some(memo) = transaction::describe_memo(&memo) {
+            say!("   Memo: {}", memo);
+        }
+        if memo == Memo::None {
+            check_memo_not_required(&self.horizon, destination).await
+                .map_err(|e| SubmitError::Rejected(e.to_string()))?;
+        }
+        let fee = self.submitter.fee_strategy.estimate(&self.horizon).await;
+        say!("   Network Fee: {}", fee);
+        // Nothing is submitted unless the signatures meet the account's threshold
+        let signers = self.signing_keys(Threshold::Medium).await
+            .map_err(|e| SubmitError::Rejected(e.to_string()))?;
+
+        if self.network.is_mainnet() {
+            say!("\n⚠️  This is a MAINNET transaction and moves real funds.");
+            if get_user_input("   Type 'send' to confirm: ") != "send" {
+                return Err(VaultError::Validation("Mainnet transaction cancelled".into()));
+            }
+        }
+
+        let build = |sequence| self.payment_transaction(sequence, &fee, destination, asset.clone(), amount, memo.clone());
+
+        // Errors are returned as SubmitError so callers can tell a payment
+        // that may still land from one that never will
+        let submitted = self.submitter.submit(&self.public_key, &signers, build).await?;
+        let receipt = TransactionReceipt::from(submitted);
+        say!("\n✅ TRANSACTION SUCCESSFUL!");
+        say!("   Hash: {}", receipt.hash);
+        say!("   Ledger: {}", receipt.ledger);
+        say!("   Fee Charged: {} stroops", receipt.fee_charged);
+        say!("   🔗 View on the explorer:");
+        say!("      Transaction: {}", self.network.tx_link(&receipt.hash));
+        say!("      Sender: {}", self.network.account_link(&self.public_key));
+        say!("      Recipient: {}", self.network.account_link(destination));
+        Ok(receipt)
+    }
+
+    // Converts through the DEX as the quote describes; the network rejects
+    // the payment if it would deliver less than the quote's minimum
+    pub async fn path_pay(&self, destination: &str, quote: &path_payment::Quote, memo: Memo) -> Result<TransactionReceipt, VaultError> {
+        say!("\n🚀 Submitting path payment to {}...", self.network);
+        say!("   From: {}", self.public_key);
+        say!("   To: {}", destination);
+        say!("   Sending: {} {}", quote.send_amount.to_xlm_string(), path_payment::label(quote.send_asset.as_ref()));
+        say!("   Receiving at least: {} {}", quote.min_received.to_xlm_string(), path_payment::label(quote.dest_asset.as_ref()));
+        if memo == Memo::None {
+            check_memo_not_required(&self.horizon, destination).await
+                .map_err(|e| SubmitError::Rejected(e.to_string()))?;
+        }
+        let fee = self.submitter.fee_strategy.estimate(&self.horizon).await;
+        let signers = self.signing_keys(Threshold::Medium).await
+            .map_err(|e| SubmitError::Rejected(e.to_string()))?;
+
+        if self.network.is_mainnet() {
+            say!("\n⚠️  This is a MAINNET transaction and moves real funds.");
+            if get_user_input("   Type 'send' to confirm: ") != "send" {
+                return Err(VaultError::Validation("Mainnet transaction cancelled".into()));
+            }
+        }
+
+        let build = |sequence| self.path_payment_transaction(sequence, &fee, destination, quote, memo.clone());
+
+        let submitted = self.submitter.submit(&self.public_key, &signers, build).await?;
+        let receipt = TransactionReceipt::from(submitted);
+        say!("\n✅ TRANSACTION SUCCESSFUL!");
+        say!("   Hash: {}", receipt.hash);
+        say!("   🔗 {}", self.network.tx_link(&receipt.hash));
+        Ok(receipt)
+    }
+
+    fn payment_transaction(&self, sequence: i64, fee: &FeeEstimate, destination: &str, asset: stellar_xdr::curr::Asset,
+                           amount: Stroops, memo: Memo) -> Result<stellar_xdr::curr::Transaction, VaultError> {
+        let mut builder = TransactionBuilder::new(&self.public_key, sequence)?
+            .fee_per_operation(fee.per_operation)
+            .time_bounds(0, unix_now() + PAYMENT_TIMEOUT_SECS)?
+            .payment(destination, asset, amount.0)?;
+        if let Some(last_ledger) = fee.last_ledger {
+            builder = builder.ledger_bounds(0, last_ledger + PAYMENT_TIMEOUT_LEDGERS)?;
+        }
+        builder.memo(memo).build()
+    }
+
+    fn path_payment_transaction(&self, sequence: i64, fee: &FeeEstimate, destination: &str, quote: &path_payment::Quote,
+                                memo: Memo) -> Result<stellar_xdr::curr::Transaction, VaultError> {
+        let mut builder = TransactionBuilder::new(&self.public_key, sequence)?
+            .fee_per_operation(fee.per_operation)
+            .time_bounds(0, unix_now() + PAYMENT_TIMEOUT_SECS)?
+            .path_payment_strict_send(destination, path_payment::to_xdr(quote.send_asset.as_ref())?, quote.send_amount.0,
+                path_payment::to_xdr(quote.dest_asset.as_ref())?, quote.min_received.0, quote.path_xdr()?)?;
+        if let Some(last_ledger) = fee.last_ledger {
+            builder = builder.ledger_bounds(0, last_ledger + PAYMENT_TIMEOUT_LEDGERS)?;
+        }
+        builder.memo(memo).build()
+    }
+
+    // The payment send_asset, or path_pay with a quote, would make, on the
+    // account's next sequence number
+    pub async fn preview_payment(&self, destination: &str, asset: stellar_xdr::curr::Asset, amount: Stroops,
+                                 conversion: Option<&path_payment::Quote>, memo: Memo) -> Result<TransactionPreview, VaultError> {
+        if memo == Memo::None {
+            check_memo_not_required(&self.horizon, destination).await?;
+        }
+        let fee = self.submitter.fee_strategy.estimate(&self.horizon).await;
+        let sequence = self.horizon.fresh_account(&self.public_key).await?.sequence_number()?;
+        let transaction = match conversion {
+            Some(quote) => self.path_payment_transaction(sequence, &fee, destination, quote, memo)?,
+            None => self.payment_transaction(sequence, &fee, destination, asset, amount, memo)?,
+        };
+        Ok(TransactionPreview {
+            hash: transaction::hash_hex(&transaction, self.network.passphrase())?,
+            fee,
+            xdr: transaction::to_base64(&offline::unsigned(transaction))?,
+        })
+    }
+
+    // Changes the account's signers or thresholds, all in one transaction,
+    // which needs the high threshold
+    pub async fn set_options(&self, operations: Vec<stellar_xdr::curr::OperationBody>) -> Result<TransactionReceipt, VaultError> {
+        let signers = self.signing_keys(Threshold::High).await?;
+        let fee = self.submitter.fee_strategy.estimate(&self.horizon).await;
+        let build = |sequence| {
+            let mut builder = TransactionBuilder::new(&self.public_key, sequence)?
+                .fee_per_operation(fee.per_operation)
+                .time_bounds(0, unix_now() + PAYMENT_TIMEOUT_SECS)?;
+            for operation in &operations {
+                builder = builder.operation(operation.clone())?;
+            }
+            builder.build()
+        };
+        let submitted = self.submitter.submit(&self.public_key, &signers, build).await?;
+        Ok(TransactionReceipt::from(submitted))
+    }
+
+    // Creates and funds a new account, opening its trustlines to `assets` in
+    // the same transaction, which the new key co-signs. A sponsored account's
+    // reserves are held by this account, so it can start with nothing.
+    pub async fn create_account(&self, new_account: &Keypair, starting_balance: Stroops, assets: &[stellar_xdr::curr::Asset], sponsored: bool) -> Result<TransactionReceipt, VaultError> {
+        let mut signers = self.signing_keys(Threshold::Medium).await
+            .map_err(|e| SubmitError::Rejected(e.to_string()))?;
+        if sponsored || !assets.is_empty() {
+            signers.push(new_account);
+        }
+        if self.network.is_mainnet() {
+            say!("\n⚠️  This is a MAINNET transaction and moves real funds.");
+            if get_user_input("   Type 'send' to confirm: ") != "send" {
+                return Err(VaultError::Validation("Mainnet transaction cancelled".into()));
+            }
+        }
+
+        let destination = new_account.public_key();
+        let fee = self.submitter.fee_strategy.estimate(&self.horizon).await;
+        let build = |sequence| {
+            let mut builder = TransactionBuilder::new(&self.public_key, sequence)?
+                .fee_per_operation(fee.per_operation)
+                .time_bounds(0, unix_now() + PAYMENT_TIMEOUT_SECS)?;
+            if sponsored {
+                builder = builder.begin_sponsoring(&destination)?;
+            }
+            builder = builder.create_account(&destination, starting_balance.0)?;
+            for asset in assets {
+                builder = builder.trust(&destination, asset.clone())?;
+            }
+            if sponsored {
+                builder = builder.end_sponsoring(&destination)?;
+            }
+            builder.build()
+        };
+        let submitted = self.submitter.submit(&self.public_key, &signers, build).await?;
+        Ok(TransactionReceipt::from(submitted))
+    }
+
+    // One unconditional claimable balance per payout, in one transaction
+    pub async fn create_claimable_balances(&self, asset: stellar_xdr::curr::Asset, payouts: &[YieldPayout]) -> Result<TransactionReceipt, VaultError> {
+        let signers = self.signing_keys(Threshold::Medium).await
+            .map_err(|e| SubmitError::Rejected(e.to_string()))?;
+        let fee = self.submitter.fee_strategy.estimate(&self.horizon).await;
+        let build = |sequence| {
+            let mut builder = TransactionBuilder::new(&self.public_key, sequence)?
+                .fee_per_operation(fee.per_operation)
+                .time_bounds(0, unix_now() + PAYMENT_TIMEOUT_SECS)?;
+            for payout in payouts {
+                builder = builder.claimable_balance(&payout.user, asset.clone(), payout.amount_stroops)?;
+            }
+            builder.build()
+        };
+        let submitted = self.submitter.submit(&self.public_key, &signers, build).await?;
+        Ok(TransactionReceipt::from(submitted))
+    }
+
+    // Creates the trustline or changes its limit; 0 removes it
+    pub async fn change_trust(&self, asset: &AssetId, limit: i64) -> Result<TransactionReceipt, VaultError> {
+        let operation = assets::change_trust(&asset.to_xdr()?, limit)?;
+        let signers = self.signing_keys(Threshold::Medium).await
+            .map_err(|e| SubmitError::Rejected(e.to_string()))?;
+        let fee = self.submitter.fee_strategy.estimate(&self.horizon).await;
+        let build = |sequence| {
+            TransactionBuilder::new(&self.public_key, sequence)?
+                .fee_per_operation(fee.per_operation)
+                .time_bounds(0, unix_now() + PAYMENT_TIMEOUT_SECS)?
+                .operation(operation.clone())?
+                .build()
+        };
+        let submitted = self.submitter.submit(&self.public_key, &signers, build).await?;
+        Ok(TransactionReceipt::from(submitted))
+    }
+
+    pub async fn submit_operations(&self, operations: Vec<stellar_xdr::curr::OperationBody>) -> Result<TransactionReceipt, VaultError> {
+        let signers = self.signing_keys(Threshold::Medium).await
+            .map_err(|e| SubmitError::Rejected(e.to_string()))?;
+        let fee = self.submitter.fee_strategy.estimate(&self.horizon).await;
+        let build = |sequence| {
+            let mut builder = TransactionBuilder::new(&self.public_key, sequence)?
+                .fee_per_operation(fee.per_operation)
+                .time_bounds(0, unix_now() + PAYMENT_TIMEOUT_SECS)?;
+            for operation in &operations {
+                builder = builder.operation(operation.clone())?;
+            }
+            builder.build()
+        };
+        let submitted = self.submitter.submit(&self.public_key, &signers, build).await?;
+        Ok(TransactionReceipt::from(submitted))
+    }
+
+    // One contract call: simulated first for the ledger entries it touches,
+    // its resource fee and the authorizations it needs, then signed, sent
+    // through Soroban RPC and polled until it applies. Returns what the
+    // contract returned alongside the receipt.
+    pub async fn invoke_contract(&self, rpc: &soroban::RpcClient, operation: stellar_xdr::curr::OperationBody)
+        -> Result<(TransactionReceipt, Option<stellar_xdr::curr::ScVal>), VaultError> {
+        let signers = self.signing_keys(Threshold::Medium).await
+            .map_err(|e| SubmitError::Rejected(e.to_string()))?;
+        let simulation = rpc.simulate(&self.public_key, operation.clone()).await
+            .map_err(|e| SubmitError::Rejected(e.to_string()))?;
+        let operation = soroban::authorize(operation, simulation.auth)?;
+        let fee = self.submitter.fee_strategy.estimate(&self.horizon).await;
+        let build = |sequence| {
+            TransactionBuilder::new(&self.public_key, sequence)?
+                .fee_per_operation(fee.per_operation)
+                .time_bounds(0, unix_now() + PAYMENT_TIMEOUT_SECS)?
+                .soroban_data(simulation.transaction_data)
+                .operation(operation)?
+                .build()
+        };
+        let (hash, info) = self.submitter.submit_contract_call(rpc, &self.public_key, &signers, build).await?;
+        let returned = info.return_value();
+        Ok((TransactionReceipt::from((hash, info)), returned))
+    }
+
+    pub async fn claim_balances(&self, balance_ids: &[String]) -> Result<TransactionReceipt, VaultError> {
+        let signers = self.signing_keys(Threshold::Medium).await
+            .map_err(|e| SubmitError::Rejected(e.to_string()))?;
+        let fee = self.submitter.fee_strategy.estimate(&self.horizon).await;
+        let build = |sequence| {
+            let mut builder = TransactionBuilder::new(&self.public_key, sequence)?
+                .fee_per_operation(fee.per_operation)
+                .time_bounds(0, unix_now() + PAYMENT_TIMEOUT_SECS)?;
+            for id in balance_ids {
+                builder = builder.claim_balance(id)?;
+            }
+            builder.build()
+        };
+        let submitted = self.submitter.submit(&self.public_key, &signers, build).await?;
+        Ok(TransactionReceipt::from(submitted))
+    }
+
+    // Opens trustlines to `assets`. With a sponsor, the sponsor submits and
+    // holds the reserves, and both accounts sign.
+    pub async fn open_trustlines(&self, assets: &[stellar_xdr::curr::Asset], sponsor: Option<&StellarClient>) -> Result<TransactionReceipt, VaultError> {
+        let mut signers = self.signing_keys(Threshold::Medium).await
+            .map_err(|e| SubmitError::Rejected(e.to_string()))?;
+        let source = match sponsor {
+            Some(sponsor) => {
+                let mut sponsor_signers = sponsor.signing_keys(Threshold::Medium).await
+                    .map_err(|e| SubmitError::Rejected(format!("Sponsor {}: {}", sponsor.public_key, e)))?;
+                sponsor_signers.retain(|key| !signers.iter().any(|signer| signer.public_key() == key.public_key()));
+                signers.extend(sponsor_signers);
+                sponsor.public_key.as_str()
+            }
+            None => self.public_key.as_str(),
+        };
+
+        let fee = self.submitter.fee_strategy.estimate(&self.horizon).await;
+        let build = |sequence| {
+            let mut builder = TransactionBuilder::new(source, sequence)?
+                .fee_per_operation(fee.per_operation)
+                .time_bounds(0, unix_now() + PAYMENT_TIMEOUT_SECS)?;
+            if sponsor.is_some() {
+                builder = builder.begin_sponsoring(&self.public_key)?;
+            }
+            for asset in assets {
+                builder = builder.trust(&self.public_key, asset.clone())?;
+            }
+            if sponsor.is_some() {
+                builder = builder.end_sponsoring(&self.public_key)?;
+            }
+            builder.build()
+        };
+        let submitted = self.submitter.submit(source, &signers, build).await?;
+        Ok(TransactionReceipt::from(submitted))
+    }
+}
+
+// SEP-29: a payment without a memo to an account that requires one would
+// arrive with nothing saying whose it is
+pub async fn check_memo_not_required(horizon: &HorizonClient, destination: &str) -> Result<(), VaultError> {
+    let required = horizon.memo_required(destination).await
+        .map_err(|e| format!("Could not check whether {} requires a memo: {}", destination, e))?;
+    if required {
+        return Err(VaultError::Validation(format!("{} requires a memo on incoming payments (SEP-29); refusing to send without one", destination)));
+    }
+    Ok(())
+}
+
+// ============================================================================
+// USER REGISTRY
+// ============================================================================
+
+// Each registered user gets their own signing context, keyed by public key
+#[derive(Default)]
+pub struct UserRegistry {
+    pub clients: HashMap<String, StellarClient>,
+}
+
+impl UserRegistry {
+    pub fn new() -> Self {
+        UserRegistry {
+            clients: HashMap::new(),
+        }
+    }
+
+    pub fn register(&mut self, secret_key: &str, public_key: &str, network: &Network, submitter: &Submitter) -> Result<(), VaultError> {
+        let client = StellarClient::new(secret_key, public_key, network, submitter)?;
+        self.clients.insert(client.get_public_key(), client);
+        Ok(())
+    }
+
+    // A user whose key is held elsewhere, e.g. on a hardware wallet
+    pub fn register_signer(&mut self, signer: Box<dyn Signer>, network: &Network, submitter: &Submitter) -> Result<String, VaultError> {
+        let mut client = StellarClient::for_account(&signer.public_key(), network, submitter)?;
+        let public_key = client.add_signer(signer);
+        self.clients.insert(public_key.clone(), client);
+        Ok(public_key)
+    }
+
+    pub fn get(&self, public_key: &str) -> Result<&StellarClient, VaultError> {
+        self.clients.get(public_key)
+            .ok_or_else(|| format!("User {} is not registered", public_key).into())
+    }
+
+    pub fn contains(&self, public_key: &str) -> bool {
+        self.clients.contains_key(public_key)
+    }
+}
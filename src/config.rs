@@ -0,0 +1,1128 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::Deserialize;
+
+use crate::alerts::{AlertConfig, AlertRule, DEFAULT_COOLDOWN_SECS};
+use crate::amount::Stroops;
+use crate::api::{RateLimits, Role};
+use crate::apy::{self, HttpApy, RateUnit};
+use crate::assets::AssetId;
+use crate::builder::VaultBuilder;
+use crate::circuit_breaker::{BreakerPolicy, DEFAULT_DEPENDENCY_COOLDOWN_SECS, DEFAULT_DEPENDENCY_FAILURES};
+use crate::fee_strategy::{FeeStrategy, DEFAULT_FEE_PERCENTILE, DEFAULT_MAX_FEE};
+use crate::idempotency::DEFAULT_IDEMPOTENCY_WINDOW_SECS;
+use crate::timelock::DEFAULT_ADMIN_DELAY_SECS;
+use crate::keystore::DEFAULT_KEYSTORE_FILE;
+use crate::logging::{self, LogConfig, LogRotation};
+use crate::federation;
+use crate::muxed;
+use crate::network::Network;
+use crate::notify::{NotifyConfig, TelegramConfig};
+use crate::oracle::{self, MockPrices, ReflectorFeed};
+use crate::path_payment::{DEFAULT_MAX_SLIPPAGE_BPS, MAX_SLIPPAGE_BPS};
+use crate::rebalance::DEFAULT_MAX_DRIFT_BPS;
+use crate::retry::RetryPolicy;
+use crate::scheduler::{self, Job, ScheduledJob};
+use crate::sdex::MarketMaker;
+use crate::soroswap::SoroswapPosition;
+use crate::signer::remote::{Endpoint, DEFAULT_TIMEOUT_SECS};
+use crate::transaction::Keypair;
+use crate::vault_config::{self, DEFAULT_VAULTS_FILE};
+use crate::RiskLevel;
+use crate::error::VaultError;
+
+pub const DEFAULT_CONFIG_FILE: &str = "stellarvault.toml";
+pub const CONFIG_PATH_ENV: &str = "STELLARVAULT_CONFIG";
+
+// The public demo vault on testnet, used when no vault is configured
+pub const DEFAULT_VAULT_ADDRESS: &str = "GCZEAWUJY3BRHCOKU6C5WRLCF5RFSGY22UGBPBXWL4T4G4SSEQMIYMCX";
+
+// Short enough to guess is short enough to refuse
+const MIN_API_KEY_LENGTH: usize = 24;
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub network: Network,
+    pub vault_address: String,
+    pub keystore_path: PathBuf,
+    // Account to make active when the keystore holds several
+    pub user_public_key: Option<String>,
+    // Secrets are only ever read from the environment, never the config file
+    pub user_secret_key: Option<String>,
+    // The vault key, or several operator keys for a multisig vault
+    pub vault_secret_keys: Vec<String>,
+    // Operators of a multisig vault; their keystore keys co-sign vault payments
+    pub vault_signers: Vec<String>,
+    // An external signing service, and the accounts whose keys it holds
+    pub signer_url: Option<String>,
+    pub signer_keys: Vec<String>,
+    pub signer_timeout_secs: u64,
+    // Pays for fee bumps; its key comes from the keystore or the environment
+    pub fee_payer: Option<String>,
+    pub fee_payer_secret_key: Option<String>,
+    // Signs the API server's SEP-10 challenges; a throwaway key when unset
+    pub web_auth_secret_key: Option<String>,
+    // Accounts whose SEP-10 tokens carry more than the depositor role
+    pub api_operators: Vec<String>,
+    pub api_admins: Vec<String>,
+    // API keys and the role each grants
+    pub api_keys: Vec<(Role, String)>,
+    pub api_rate_limits: RateLimits,
+    // How long an API client's Idempotency-Key is remembered
+    pub idempotency_window_secs: u64,
+    // How long fee and lockup increases, strategy and treasury changes wait
+    // before taking effect; 0 applies them at once. Lowering it below the
+    // saved delay itself waits out the saved delay.
+    pub admin_delay_secs: u64,
+    // Only accounts on the allowlist may deposit or be paid; the denylist
+    // applies either way
+    pub allowlist_only: bool,
+    pub log: LogConfig,
+    // Where operators are notified of vault activity
+    pub notify: NotifyConfig,
+    // Rules checked while watching or serving; alerts go out as notifications
+    pub alerts: Option<AlertConfig>,
+    // Upkeep run on intervals while watching or serving
+    pub schedule: Vec<ScheduledJob>,
+    pub share_issuer: Option<String>,
+    pub treasury_address: Option<String>,
+    // Vaults that hold an issued asset instead of XLM
+    pub vault_assets: Vec<(RiskLevel, AssetId)>,
+    // Whether to stream the vault's payments and credit deposits made from other wallets
+    pub ingest: bool,
+    pub fee_strategy: FeeStrategy,
+    // How far below the quote a converted deposit may land, in basis points
+    pub max_slippage_bps: u16,
+    pub market_making: Option<MarketMakingConfig>,
+    pub liquidity_pool: Option<LiquidityPoolConfig>,
+    // Where contract calls are simulated; the network's public server by default
+    pub soroban_rpc_url: Option<String>,
+    // Timeouts and retries for Horizon and Soroban RPC calls
+    pub retry: RetryPolicy,
+    // Horizon servers to fail over to, in order
+    pub horizon_fallback_urls: Vec<String>,
+    // When Horizon calls stop being attempted after repeated failures
+    pub horizon_breaker: BreakerPolicy,
+    // The vault contract that holds shares on-chain, when deposits go through it
+    pub vault_contract: Option<String>,
+    pub blend: Option<BlendConfig>,
+    pub soroswap: Option<SoroswapConfig>,
+    pub apy_oracle: Option<ApyOracleConfig>,
+    pub price_oracle: Option<PriceOracleConfig>,
+    // Vault definitions from vaults.toml; empty runs the standard tiers
+    pub vaults: Vec<VaultBuilder>,
+    pub vaults_source: Option<PathBuf>,
+    // The file the settings were read from, if any
+    pub source: Option<PathBuf>,
+}
+
+// A vault that quotes its asset against `maker.counter` on the DEX with
+// `allocation` percent of its strategy funds
+#[derive(Debug, Clone)]
+pub struct MarketMakingConfig {
+    pub risk: RiskLevel,
+    pub maker: MarketMaker,
+    pub allocation: u8,
+}
+
+// A vault whose AquaLiquidityPool strategy deposits into the on-chain pool
+// pairing its asset with `counter` (None for XLM)
+#[derive(Debug, Clone)]
+pub struct LiquidityPoolConfig {
+    pub risk: RiskLevel,
+    pub counter: Option<AssetId>,
+}
+
+// A vault whose YieldBloxLending strategy lends to the Blend pool contract
+// `pool` (C...)
+#[derive(Debug, Clone)]
+pub struct BlendConfig {
+    pub risk: RiskLevel,
+    pub pool: String,
+}
+
+// A vault that provides liquidity to the Soroswap pair of its asset and
+// `position.counter` with `allocation` percent of its strategy funds
+#[derive(Debug, Clone)]
+pub struct SoroswapConfig {
+    pub risk: RiskLevel,
+    pub position: SoroswapPosition,
+    pub allocation: u8,
+}
+
+// Where strategies' live APYs are fetched from, and how often
+#[derive(Debug, Clone)]
+pub struct ApyOracleConfig {
+    pub interval_secs: u64,
+    // Rates older than this are reported as stale
+    pub max_age_secs: u64,
+    // Whether YieldBloxLending follows the [blend] pool's supply rate
+    pub blend: bool,
+    pub apis: Vec<HttpApy>,
+}
+
+// Where vault assets' USD prices come from
+#[derive(Debug, Clone)]
+pub struct PriceOracleConfig {
+    // Reflector contract (C...) and how it names assets
+    pub reflector: Option<(String, ReflectorFeed)>,
+    pub mock: MockPrices,
+    pub interval_secs: u64,
+    pub max_age_secs: u64,
+}
+
+// stellarvault.toml; every key is optional and environment variables win
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    vault_address: Option<String>,
+    network: Option<String>,
+    horizon_url: Option<String>,
+    horizon_fallback_urls: Option<Vec<String>>,
+    horizon_breaker_failures: Option<u32>,
+    horizon_breaker_cooldown_secs: Option<u64>,
+    network_passphrase: Option<String>,
+    explorer_url: Option<String>,
+    keystore: Option<PathBuf>,
+    user_public_key: Option<String>,
+    share_issuer: Option<String>,
+    treasury: Option<String>,
+    vault_assets: Option<BTreeMap<String, String>>,
+    fee_payer: Option<String>,
+    vault_signers: Option<Vec<String>>,
+    signer_url: Option<String>,
+    signer_keys: Option<Vec<String>>,
+    signer_timeout_secs: Option<u64>,
+    api_operators: Option<Vec<String>>,
+    api_admins: Option<Vec<String>>,
+    api_rate_limit: Option<u32>,
+    api_key_rate_limit: Option<u32>,
+    idempotency_window_secs: Option<u64>,
+    admin_delay_secs: Option<u64>,
+    allowlist_only: Option<bool>,
+    log_level: Option<String>,
+    log_dir: Option<PathBuf>,
+    log_file_level: Option<String>,
+    log_rotation: Option<String>,
+    log_max_files: Option<usize>,
+    webhook_urls: Option<Vec<String>>,
+    telegram_chat_id: Option<String>,
+    telegram_account: Option<String>,
+    ingest: Option<bool>,
+    fee_percentile: Option<u8>,
+    max_fee: Option<u32>,
+    max_slippage_bps: Option<u16>,
+    soroban_rpc_url: Option<String>,
+    connect_timeout_secs: Option<u64>,
+    request_timeout_secs: Option<u64>,
+    max_retries: Option<u32>,
+    retry_backoff_ms: Option<u64>,
+    vault_contract: Option<String>,
+    vaults_file: Option<PathBuf>,
+    market_making: Option<MarketMakingFile>,
+    liquidity_pool: Option<LiquidityPoolFile>,
+    blend: Option<BlendFile>,
+    soroswap: Option<SoroswapFile>,
+    apy_oracle: Option<ApyOracleFile>,
+    price_oracle: Option<PriceOracleFile>,
+    alerts: Option<AlertsFile>,
+    schedule: Option<Vec<ScheduleFile>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScheduleFile {
+    job: String,
+    vault: Option<String>,
+    interval_secs: u64,
+    jitter_secs: Option<u64>,
+    max_drift_bps: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AlertsFile {
+    cooldown_secs: Option<u64>,
+    apy_below: Option<Vec<ApyBelowFile>>,
+    share_price_move: Option<Vec<SharePriceMoveFile>>,
+    coverage_below: Option<Vec<CoverageBelowFile>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApyBelowFile {
+    vault: Option<String>,
+    strategy: Option<String>,
+    min_apy_bps: u16,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SharePriceMoveFile {
+    vault: Option<String>,
+    max_move_bps: u64,
+    window_secs: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CoverageBelowFile {
+    min_coverage_bps: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PriceOracleFile {
+    reflector: Option<String>,
+    feed: Option<String>,
+    interval_secs: Option<u64>,
+    max_age_secs: Option<u64>,
+    mock: Option<BTreeMap<String, toml::Value>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApyOracleFile {
+    interval_secs: Option<u64>,
+    max_age_secs: Option<u64>,
+    blend: Option<bool>,
+    api: Option<Vec<ApyApiFile>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApyApiFile {
+    strategy: String,
+    url: String,
+    field: String,
+    unit: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LiquidityPoolFile {
+    vault: String,
+    counter: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BlendFile {
+    vault: String,
+    pool: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SoroswapFile {
+    vault: String,
+    router: String,
+    counter: String,
+    allocation: u8,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MarketMakingFile {
+    vault: String,
+    counter: String,
+    spread_bps: u16,
+    allocation: u8,
+}
+
+// A setting's value and where it came from, for error messages
+struct Setting {
+    value: String,
+    origin: String,
+}
+
+fn env(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+fn pick(env_name: &str, file_value: Option<String>, file_key: &str, file: &str) -> Option<Setting> {
+    match env(env_name) {
+        Some(value) => Some(Setting { value, origin: env_name.to_string() }),
+        None => file_value.map(|value| Setting { value, origin: format!("{} in {}", file_key, file) }),
+    }
+}
+
+// A list from a comma-separated environment variable or a TOML array
+fn pick_list(env_name: &str, file_value: Option<Vec<String>>, file_key: &str, file: &str) -> Vec<Setting> {
+    match env(env_name) {
+        Some(value) => value.split(',')
+            .map(|item| Setting { value: item.trim().to_string(), origin: env_name.to_string() })
+            .collect(),
+        None => file_value.unwrap_or_default().into_iter()
+            .map(|value| Setting { value, origin: format!("{} in {}", file_key, file) })
+            .collect(),
+    }
+}
+
+// "low=USDC:G...,high=EURC:G..." from the environment, or a TOML table
+fn pick_vault_assets(file_value: Option<BTreeMap<String, String>>, file: &str) -> Result<Vec<(RiskLevel, AssetId)>, VaultError> {
+    let (entries, origin): (Vec<(String, String)>, String) = match env("STELLARVAULT_VAULT_ASSETS") {
+        Some(value) => {
+            let entries = value.split(',')
+                .map(|item| item.split_once('=')
+                    .map(|(risk, asset)| (risk.trim().to_string(), asset.trim().to_string()))
+                    .ok_or_else(|| VaultError::Validation(format!("STELLARVAULT_VAULT_ASSETS entries look like low=CODE:ISSUER: {}", item))))
+                .collect::<Result<_, _>>()?;
+            (entries, "STELLARVAULT_VAULT_ASSETS".to_string())
+        }
+        None => (file_value.unwrap_or_default().into_iter().collect(), format!("vault_assets in {}", file)),
+    };
+    entries.into_iter().map(|(risk, asset)| {
+        let risk = parse_risk(&risk, &origin)?;
+        let asset = AssetId::parse(&asset).map_err(|e| VaultError::Validation(format!("{}: {}", origin, e)))?;
+        Ok((risk, asset))
+    }).collect()
+}
+
+// "operator=KEY,viewer=KEY"; secrets, so only ever from the environment
+fn pick_api_keys() -> Result<Vec<(Role, String)>, VaultError> {
+    let Some(value) = env("STELLARVAULT_API_KEYS") else {
+        return Ok(Vec::new());
+    };
+    value.split(',').map(|item| {
+        let (role, key) = item.split_once('=')
+            .ok_or_else(|| VaultError::Validation("STELLARVAULT_API_KEYS entries look like operator=KEY".into()))?;
+        let role = Role::parse(role.trim())
+            .map_err(|e| VaultError::Validation(format!("STELLARVAULT_API_KEYS: {}", e)))?;
+        let key = key.trim();
+        if key.len() < MIN_API_KEY_LENGTH {
+            return Err(VaultError::Validation(format!("STELLARVAULT_API_KEYS: the {} key is shorter than {} characters", role, MIN_API_KEY_LENGTH)));
+        }
+        Ok((role, key.to_string()))
+    }).collect()
+}
+
+pub fn parse_risk(name: &str, origin: &str) -> Result<RiskLevel, VaultError> {
+    match name.to_lowercase().as_str() {
+        "low" => Ok(RiskLevel::Low),
+        "medium" => Ok(RiskLevel::Medium),
+        "high" => Ok(RiskLevel::High),
+        _ => Err(VaultError::Validation(format!("{} names an unknown vault (expected low, medium or high): {}", origin, name))),
+    }
+}
+
+// XLM or CODE:ISSUER
+fn parse_counter(counter: &str, origin: &str) -> Result<Option<AssetId>, VaultError> {
+    if counter.eq_ignore_ascii_case("xlm") || counter == "native" {
+        return Ok(None);
+    }
+    AssetId::parse(counter).map(Some).map_err(|e| VaultError::Validation(format!("{}: {}", origin, e)))
+}
+
+fn liquidity_pool(file: Option<LiquidityPoolFile>, file_name: &str) -> Result<Option<LiquidityPoolConfig>, VaultError> {
+    let Some(file) = file else {
+        return Ok(None);
+    };
+    let origin = format!("liquidity_pool in {}", file_name);
+    Ok(Some(LiquidityPoolConfig {
+        risk: parse_risk(&file.vault, &origin)?,
+        counter: parse_counter(&file.counter, &origin)?,
+    }))
+}
+
+fn blend(file: Option<BlendFile>, file_name: &str) -> Result<Option<BlendConfig>, VaultError> {
+    let Some(file) = file else {
+        return Ok(None);
+    };
+    let origin = format!("blend in {}", file_name);
+    if stellar_strkey::Contract::from_string(&file.pool).is_err() {
+        return Err(VaultError::Validation(format!("{}: pool is not a valid contract address (expected C...): {}", origin, file.pool)));
+    }
+    Ok(Some(BlendConfig {
+        risk: parse_risk(&file.vault, &origin)?,
+        pool: file.pool,
+    }))
+}
+
+fn soroswap(file: Option<SoroswapFile>, file_name: &str) -> Result<Option<SoroswapConfig>, VaultError> {
+    let Some(file) = file else {
+        return Ok(None);
+    };
+    let origin = format!("soroswap in {}", file_name);
+    let counter = parse_counter(&file.counter, &origin)?;
+    if file.allocation == 0 || file.allocation >= 100 {
+        return Err(VaultError::Validation(format!("{}: allocation must be a percentage between 1 and 99: {}", origin, file.allocation)));
+    }
+    Ok(Some(SoroswapConfig {
+        risk: parse_risk(&file.vault, &origin)?,
+        position: SoroswapPosition::new(&file.router, counter).map_err(|e| VaultError::Validation(format!("{}: router: {}", origin, e)))?,
+        allocation: file.allocation,
+    }))
+}
+
+fn apy_oracle(file: Option<ApyOracleFile>, has_blend: bool, file_name: &str) -> Result<Option<ApyOracleConfig>, VaultError> {
+    let Some(file) = file else {
+        return Ok(None);
+    };
+    let origin = format!("apy_oracle in {}", file_name);
+    let interval_secs = file.interval_secs.unwrap_or(apy::DEFAULT_INTERVAL_SECS);
+    if interval_secs < apy::MIN_INTERVAL_SECS {
+        return Err(VaultError::Validation(format!("{}: interval_secs must be at least {}: {}", origin, apy::MIN_INTERVAL_SECS, interval_secs)));
+    }
+    let max_age_secs = file.max_age_secs.unwrap_or(apy::DEFAULT_MAX_AGE_SECS);
+    if max_age_secs < interval_secs {
+        return Err(VaultError::Validation(format!("{}: max_age_secs can't be shorter than interval_secs", origin)));
+    }
+    let blend = file.blend.unwrap_or(false);
+    if blend && !has_blend {
+        return Err(VaultError::Validation(format!("{}: blend = true needs a [blend] pool", origin)));
+    }
+    let apis = file.api.unwrap_or_default().into_iter()
+        .map(|api| {
+            let unit = RateUnit::parse(&api.unit).map_err(|e| VaultError::Validation(format!("{}: {}", origin, e)))?;
+            HttpApy::new(&api.strategy, &api.url, &api.field, unit).map_err(|e| VaultError::Validation(format!("{}: {}", origin, e)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if !blend && apis.is_empty() {
+        return Err(VaultError::Validation(format!("{}: set blend = true or add an [[apy_oracle.api]] source", origin)));
+    }
+    Ok(Some(ApyOracleConfig { interval_secs, max_age_secs, blend, apis }))
+}
+
+fn alerts(file: Option<AlertsFile>, file_name: &str) -> Result<Option<AlertConfig>, VaultError> {
+    let Some(file) = file else {
+        return Ok(None);
+    };
+    let origin = format!("alerts in {}", file_name);
+    let vault = |name: Option<String>| name.map(|name| parse_risk(&name, &origin)).transpose();
+    let mut rules = Vec::new();
+    for rule in file.apy_below.unwrap_or_default() {
+        rules.push(AlertRule::ApyBelow { risk: vault(rule.vault)?, strategy: rule.strategy, min_apy_bps: rule.min_apy_bps });
+    }
+    for rule in file.share_price_move.unwrap_or_default() {
+        if rule.window_secs == 0 {
+            return Err(VaultError::Validation(format!("{}: share_price_move needs a window_secs above 0", origin)));
+        }
+        rules.push(AlertRule::SharePriceMove { risk: vault(rule.vault)?, max_move_bps: rule.max_move_bps, window_secs: rule.window_secs });
+    }
+    for rule in file.coverage_below.unwrap_or_default() {
+        rules.push(AlertRule::CoverageBelow { min_coverage_bps: rule.min_coverage_bps });
+    }
+    if rules.is_empty() {
+        return Err(VaultError::Validation(format!("{}: add an [[alerts.apy_below]], [[alerts.share_price_move]] or [[alerts.coverage_below]] rule", origin)));
+    }
+    Ok(Some(AlertConfig { rules, cooldown_secs: file.cooldown_secs.unwrap_or(DEFAULT_COOLDOWN_SECS) }))
+}
+
+fn schedule(file: Option<Vec<ScheduleFile>>, has_apy_oracle: bool, file_name: &str) -> Result<Vec<ScheduledJob>, VaultError> {
+    let origin = format!("schedule in {}", file_name);
+    file.unwrap_or_default().into_iter().map(|entry| {
+        let risk = entry.vault.as_deref().map(|name| parse_risk(name, &origin)).transpose()?;
+        let job = match entry.job.as_str() {
+            "harvest" => Job::Harvest(risk),
+            "rebalance" => Job::Rebalance { risk, max_drift_bps: entry.max_drift_bps.unwrap_or(DEFAULT_MAX_DRIFT_BPS) },
+            "apy_refresh" if !has_apy_oracle => return Err(VaultError::Validation(format!("{}: apy_refresh needs an [apy_oracle] to fetch from", origin))),
+            "apy_refresh" => Job::ApyRefresh,
+            "coverage_check" => Job::CoverageCheck,
+            other => return Err(VaultError::Validation(format!(
+                "{}: unknown job (expected harvest, rebalance, apy_refresh or coverage_check): {}", origin, other))),
+        };
+        if risk.is_some() && !matches!(job, Job::Harvest(_) | Job::Rebalance { .. }) {
+            return Err(VaultError::Validation(format!("{}: {} runs for every vault and takes no vault", origin, job)));
+        }
+        if entry.max_drift_bps.is_some() && !matches!(job, Job::Rebalance { .. }) {
+            return Err(VaultError::Validation(format!("{}: max_drift_bps only applies to rebalance", origin)));
+        }
+        if entry.interval_secs < scheduler::MIN_INTERVAL_SECS {
+            return Err(VaultError::Validation(format!("{}: {} interval_secs must be at least {}: {}",
+                origin, job, scheduler::MIN_INTERVAL_SECS, entry.interval_secs)));
+        }
+        let jitter_secs = entry.jitter_secs.unwrap_or(0);
+        if jitter_secs >= entry.interval_secs {
+            return Err(VaultError::Validation(format!("{}: {} jitter_secs must be shorter than interval_secs", origin, job)));
+        }
+        Ok(ScheduledJob { job, interval_secs: entry.interval_secs, jitter_secs })
+    }).collect()
+}
+
+fn price_oracle(file: Option<PriceOracleFile>, file_name: &str) -> Result<Option<PriceOracleConfig>, VaultError> {
+    let Some(file) = file else {
+        return Ok(None);
+    };
+    let origin = format!("price_oracle in {}", file_name);
+    let reflector = match file.reflector {
+        Some(contract) => {
+            if stellar_strkey::Contract::from_string(&contract).is_err() {
+                return Err(VaultError::Validation(format!("{}: reflector is not a valid contract address (expected C...): {}", origin, contract)));
+            }
+            let feed = ReflectorFeed::parse(file.feed.as_deref().unwrap_or("stellar"))
+                .map_err(|e| VaultError::Validation(format!("{}: {}", origin, e)))?;
+            Some((contract, feed))
+        }
+        None if file.feed.is_some() => return Err(VaultError::Validation(format!("{}: feed needs a reflector contract", origin))),
+        None => None,
+    };
+    // USD with 7 decimals, like amounts; numbers or decimal strings
+    let mock = file.mock.unwrap_or_default().into_iter()
+        .map(|(asset, price)| {
+            let text = match &price {
+                toml::Value::String(s) => s.clone(),
+                toml::Value::Integer(_) | toml::Value::Float(_) => price.to_string(),
+                _ => String::new(),
+            };
+            Stroops::from_xlm_str(&text).ok()
+                .filter(|usd| usd.0 > 0)
+                .map(|usd| (asset.clone(), usd.0))
+                .ok_or_else(|| VaultError::Validation(format!("{}: mock price of {} must be a positive USD amount: {}", origin, asset, price)))
+        })
+        .collect::<Result<BTreeMap<_, _>, _>>()?;
+    if reflector.is_none() && mock.is_empty() {
+        return Err(VaultError::Validation(format!("{}: set a reflector contract or [price_oracle.mock] prices", origin)));
+    }
+    let interval_secs = file.interval_secs.unwrap_or(oracle::DEFAULT_INTERVAL_SECS);
+    let max_age_secs = file.max_age_secs.unwrap_or(oracle::DEFAULT_MAX_AGE_SECS);
+    if interval_secs == 0 || max_age_secs < interval_secs {
+        return Err(VaultError::Validation(format!("{}: interval_secs must be above zero and no longer than max_age_secs", origin)));
+    }
+    Ok(Some(PriceOracleConfig { reflector, mock: MockPrices::new(mock), interval_secs, max_age_secs }))
+}
+
+fn market_making(file: Option<MarketMakingFile>, file_name: &str) -> Result<Option<MarketMakingConfig>, VaultError> {
+    let Some(file) = file else {
+        return Ok(None);
+    };
+    let origin = format!("market_making in {}", file_name);
+    let counter = parse_counter(&file.counter, &origin)?;
+    if file.allocation == 0 || file.allocation >= 100 {
+        return Err(VaultError::Validation(format!("{}: allocation must be a percentage between 1 and 99: {}", origin, file.allocation)));
+    }
+    Ok(Some(MarketMakingConfig {
+        risk: parse_risk(&file.vault, &origin)?,
+        maker: MarketMaker::new(counter, file.spread_bps).map_err(|e| VaultError::Validation(format!("{}: {}", origin, e)))?,
+        allocation: file.allocation,
+    }))
+}
+
+// vaults.toml in the working directory is optional; a file named by
+// vaults_file or $STELLARVAULT_VAULTS_FILE must exist
+fn vault_definitions(file_value: Option<PathBuf>, file_name: &str) -> Result<(Vec<VaultBuilder>, Option<PathBuf>), VaultError> {
+    let (path, origin) = match env("STELLARVAULT_VAULTS_FILE") {
+        Some(path) => (PathBuf::from(path), Some("STELLARVAULT_VAULTS_FILE".to_string())),
+        None => match file_value {
+            Some(path) => (path, Some(format!("vaults_file in {}", file_name))),
+            None => (PathBuf::from(DEFAULT_VAULTS_FILE), None),
+        },
+    };
+    match (path.exists(), origin) {
+        (true, _) => Ok((vault_config::load(&path)?, Some(path))),
+        (false, Some(origin)) => Err(VaultError::Validation(format!("{} points to {}, which does not exist", origin, path.display()))),
+        (false, None) => Ok((Vec::new(), None)),
+    }
+}
+
+fn validate_account(setting: &Setting) -> Result<(), VaultError> {
+    stellar_strkey::ed25519::PublicKey::from_string(&setting.value)
+        .map(|_| ())
+        .map_err(|_| VaultError::Validation(format!("{} is not a valid Stellar account (expected G..., 56 characters): {}",
+            setting.origin, setting.value)))
+}
+
+fn validate_secret(name: &str, secret: &str) -> Result<(), VaultError> {
+    Keypair::from_secret(secret)
+        .map(|_| ())
+        .map_err(|_| VaultError::Validation(format!("{} is not a valid Stellar secret key (expected S..., 56 characters)", name)))
+}
+
+impl Config {
+    // Reads stellarvault.toml (or $STELLARVAULT_CONFIG) if present, then
+    // applies environment overrides and validates the result. A --network
+    // flag beats both.
+    pub fn load(network_flag: Option<&str>) -> Result<Config, VaultError> {
+        let (path, explicit) = match env(CONFIG_PATH_ENV) {
+            Some(path) => (PathBuf::from(path), true),
+            None => (PathBuf::from(DEFAULT_CONFIG_FILE), false),
+        };
+
+        let (file, source) = if path.exists() {
+            let contents = fs::read_to_string(&path)
+                .map_err(|e| VaultError::Storage(format!("Failed to read {}: {}", path.display(), e)))?;
+            (Self::parse(&contents, &path.display().to_string())?, Some(path))
+        } else if explicit {
+            return Err(VaultError::Validation(format!("{} points to {}, which does not exist", CONFIG_PATH_ENV, path.display())));
+        } else {
+            (ConfigFile::default(), None)
+        };
+
+        let file_name = source.as_ref()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|| DEFAULT_CONFIG_FILE.to_string());
+        Self::resolve(file, &file_name, source, network_flag)
+    }
+
+    // The file settings are read from, and recorded to
+    pub fn path(&self) -> PathBuf {
+        self.source.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_FILE))
+    }
+
+    // Writes a top-level string setting into the config file, creating it if
+    // needed. Other lines, comments included, are kept as they are.
+    pub fn record(path: &Path, key: &str, value: &str) -> Result<(), VaultError> {
+        Self::record_value(path, key, toml::Value::String(value.to_string()))
+    }
+
+    // Like `record`, for a list of strings such as vault_signers
+    pub fn record_list(path: &Path, key: &str, values: &[String]) -> Result<(), VaultError> {
+        Self::record_value(path, key, toml::Value::Array(values.iter().cloned().map(toml::Value::String).collect()))
+    }
+
+    // The file is replaced in one rename, so a failed write leaves the old one
+    fn record_value(path: &Path, key: &str, value: toml::Value) -> Result<(), VaultError> {
+        let contents = match path.exists() {
+            true => fs::read_to_string(path).map_err(|e| VaultError::Storage(format!("Failed to read {}: {}", path.display(), e)))?,
+            false => String::new(),
+        };
+        let updated = set_top_level(&contents, key, &value);
+        Self::parse(&updated, &path.display().to_string())?;
+        let tmp_path = path.with_extension("toml.tmp");
+        fs::write(&tmp_path, updated).map_err(|e| VaultError::Storage(format!("Failed to write {}: {}", tmp_path.display(), e)))?;
+        fs::rename(&tmp_path, path).map_err(|e| VaultError::Storage(format!("Failed to replace {}: {}", path.display(), e)))
+    }
+
+    fn parse(contents: &str, file_name: &str) -> Result<ConfigFile, VaultError> {
+        let table: toml::Table = contents.parse()
+            .map_err(|e| VaultError::Validation(format!("Invalid TOML in {}: {}", file_name, e)))?;
+        if let Some(key) = table.keys().find(|k| k.contains("secret")) {
+            return Err(VaultError::Validation(format!(
+                "{} contains `{}`; keep secret keys out of config files and use the keystore \
+                 or the STELLARVAULT_USER_SECRET / STELLARVAULT_VAULT_SECRET environment variables",
+                file_name, key)));
+        }
+        table.try_into()
+            .map_err(|e| VaultError::Validation(format!("Invalid setting in {}: {}", file_name, e)))
+    }
+
+    fn resolve(file: ConfigFile, file_name: &str, source: Option<PathBuf>, network_flag: Option<&str>) -> Result<Config, VaultError> {
+        let vault_address = pick("STELLARVAULT_VAULT_ADDRESS", file.vault_address, "vault_address", file_name);
+        let network = match network_flag {
+            Some(name) => Some(Setting { value: name.to_string(), origin: "--network".to_string() }),
+            None => pick("STELLARVAULT_NETWORK", file.network, "network", file_name),
+        };
+        let horizon_url = pick("STELLARVAULT_HORIZON_URL", file.horizon_url, "horizon_url", file_name);
+        let horizon_fallback_urls = pick_list("STELLARVAULT_HORIZON_FALLBACK_URLS", file.horizon_fallback_urls, "horizon_fallback_urls", file_name);
+        let passphrase = pick("STELLARVAULT_NETWORK_PASSPHRASE", file.network_passphrase, "network_passphrase", file_name);
+        let explorer_url = pick("STELLARVAULT_EXPLORER_URL", file.explorer_url, "explorer_url", file_name);
+        let user_public_key = pick("STELLARVAULT_USER_PUBLIC_KEY", file.user_public_key, "user_public_key", file_name);
+        let share_issuer = pick("STELLARVAULT_SHARE_ISSUER", file.share_issuer, "share_issuer", file_name);
+        let treasury = pick("STELLARVAULT_TREASURY", file.treasury, "treasury", file_name);
+        let fee_payer = pick("STELLARVAULT_FEE_PAYER", file.fee_payer, "fee_payer", file_name);
+
+        for setting in [&vault_address, &user_public_key, &share_issuer, &fee_payer].into_iter().flatten() {
+            validate_account(setting)?;
+        }
+        if let Some(setting) = &treasury {
+            if !muxed::is_valid(&setting.value) && !federation::is_federation_address(&setting.value) {
+                return Err(VaultError::Validation(format!("{} is not a valid Stellar address (expected G..., M... or name*domain): {}",
+                    setting.origin, setting.value)));
+            }
+        }
+        let vault_assets = pick_vault_assets(file.vault_assets, file_name)?;
+        let market_making = market_making(file.market_making, file_name)?;
+        let liquidity_pool = liquidity_pool(file.liquidity_pool, file_name)?;
+        let blend = blend(file.blend, file_name)?;
+        let soroswap = soroswap(file.soroswap, file_name)?;
+        let apy_oracle = apy_oracle(file.apy_oracle, blend.is_some(), file_name)?;
+        let price_oracle = price_oracle(file.price_oracle, file_name)?;
+        let alerts = alerts(file.alerts, file_name)?;
+        let schedule = schedule(file.schedule, apy_oracle.is_some(), file_name)?;
+        let vault_signers = pick_list("STELLARVAULT_VAULT_SIGNERS", file.vault_signers, "vault_signers", file_name);
+        let signer_keys = pick_list("STELLARVAULT_SIGNER_KEYS", file.signer_keys, "signer_keys", file_name);
+        for setting in vault_signers.iter().chain(&signer_keys) {
+            validate_account(setting)?;
+        }
+
+        let api_operators = pick_list("STELLARVAULT_API_OPERATORS", file.api_operators, "api_operators", file_name);
+        let api_admins = pick_list("STELLARVAULT_API_ADMINS", file.api_admins, "api_admins", file_name);
+        for setting in api_operators.iter().chain(&api_admins) {
+            validate_account(setting)?;
+        }
+
+        let signer_url = pick("STELLARVAULT_SIGNER_URL", file.signer_url, "signer_url", file_name);
+        if let Some(url) = &signer_url {
+            Endpoint::parse(&url.value).map_err(|e| VaultError::Validation(format!("{}: {}", url.origin, e)))?;
+        }
+        if signer_url.is_none() && !signer_keys.is_empty() {
+            return Err(VaultError::Validation("signer_keys needs signer_url (or STELLARVAULT_SIGNER_URL) to say where the signer listens".into()));
+        }
+        let signer_timeout_secs = match env("STELLARVAULT_SIGNER_TIMEOUT") {
+            Some(value) => value.parse()
+                .map_err(|_| VaultError::Validation(format!("STELLARVAULT_SIGNER_TIMEOUT must be a number of seconds: {}", value)))?,
+            None => file.signer_timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS),
+        };
+
+        let soroban_rpc_url = pick("STELLARVAULT_SOROBAN_RPC_URL", file.soroban_rpc_url, "soroban_rpc_url", file_name);
+        for url in [&horizon_url, &explorer_url, &soroban_rpc_url].into_iter().flatten().chain(&horizon_fallback_urls) {
+            if !url.value.starts_with("https://") && !url.value.starts_with("http://") {
+                return Err(VaultError::Validation(format!("{} must be an http(s) URL: {}", url.origin, url.value)));
+            }
+        }
+
+        let retry_defaults = RetryPolicy::default();
+        let connect_timeout_secs = match env("STELLARVAULT_CONNECT_TIMEOUT") {
+            Some(value) => value.parse()
+                .map_err(|_| VaultError::Validation(format!("STELLARVAULT_CONNECT_TIMEOUT must be a number of seconds: {}", value)))?,
+            None => file.connect_timeout_secs.unwrap_or(retry_defaults.connect_timeout.as_secs()),
+        };
+        let request_timeout_secs = match env("STELLARVAULT_REQUEST_TIMEOUT") {
+            Some(value) => value.parse()
+                .map_err(|_| VaultError::Validation(format!("STELLARVAULT_REQUEST_TIMEOUT must be a number of seconds: {}", value)))?,
+            None => file.request_timeout_secs.unwrap_or(retry_defaults.request_timeout.as_secs()),
+        };
+        if connect_timeout_secs == 0 || request_timeout_secs == 0 {
+            return Err(VaultError::Validation("connect_timeout_secs and request_timeout_secs must be at least 1".into()));
+        }
+        let max_retries = match env("STELLARVAULT_MAX_RETRIES") {
+            Some(value) => value.parse()
+                .map_err(|_| VaultError::Validation(format!("STELLARVAULT_MAX_RETRIES must be a number of retries: {}", value)))?,
+            None => file.max_retries.unwrap_or(retry_defaults.max_retries),
+        };
+        let retry_backoff_ms = match env("STELLARVAULT_RETRY_BACKOFF_MS") {
+            Some(value) => value.parse()
+                .map_err(|_| VaultError::Validation(format!("STELLARVAULT_RETRY_BACKOFF_MS must be a number of milliseconds: {}", value)))?,
+            None => file.retry_backoff_ms.unwrap_or(retry_defaults.initial_backoff.as_millis() as u64),
+        };
+        let retry = RetryPolicy {
+            connect_timeout: Duration::from_secs(connect_timeout_secs),
+            request_timeout: Duration::from_secs(request_timeout_secs),
+            max_retries,
+            initial_backoff: Duration::from_millis(retry_backoff_ms),
+        };
+        let horizon_breaker_failures = match env("STELLARVAULT_HORIZON_BREAKER_FAILURES") {
+            Some(value) => value.parse()
+                .map_err(|_| VaultError::Validation(format!("STELLARVAULT_HORIZON_BREAKER_FAILURES must be a number of failures: {}", value)))?,
+            None => file.horizon_breaker_failures.unwrap_or(DEFAULT_DEPENDENCY_FAILURES),
+        };
+        let horizon_breaker_cooldown_secs = match env("STELLARVAULT_HORIZON_BREAKER_COOLDOWN") {
+            Some(value) => value.parse()
+                .map_err(|_| VaultError::Validation(format!("STELLARVAULT_HORIZON_BREAKER_COOLDOWN must be a number of seconds: {}", value)))?,
+            None => file.horizon_breaker_cooldown_secs.unwrap_or(DEFAULT_DEPENDENCY_COOLDOWN_SECS),
+        };
+        if horizon_breaker_cooldown_secs == 0 {
+            return Err(VaultError::Validation("horizon_breaker_cooldown_secs must be at least 1".into()));
+        }
+        let horizon_breaker = BreakerPolicy {
+            failures: horizon_breaker_failures,
+            cooldown: Duration::from_secs(horizon_breaker_cooldown_secs),
+        };
+
+        let base = match &network {
+            Some(setting) => setting.value.parse::<Network>()
+                .map_err(|e| VaultError::Validation(format!("{}: {}", setting.origin, e)))?,
+            None => Network::Testnet,
+        };
+        // Any explicit endpoint turns the base network into a custom one
+        let network = if horizon_url.is_none() && passphrase.is_none() && explorer_url.is_none() {
+            base
+        } else {
+            Network::Custom {
+                horizon_url: horizon_url.map(|s| s.value.trim_end_matches('/').to_string())
+                    .unwrap_or_else(|| base.horizon_url().to_string()),
+                passphrase: passphrase.map(|s| s.value).unwrap_or_else(|| base.passphrase().to_string()),
+                explorer_url: explorer_url.map(|s| s.value.trim_end_matches('/').to_string())
+                    .or_else(|| base.explorer_url().map(str::to_string)),
+            }
+        };
+
+        let vault_contract = pick("STELLARVAULT_VAULT_CONTRACT", file.vault_contract, "vault_contract", file_name);
+        if let Some(contract) = &vault_contract {
+            if stellar_strkey::Contract::from_string(&contract.value).is_err() {
+                return Err(VaultError::Validation(format!("{} is not a valid contract address (expected C...): {}", contract.origin, contract.value)));
+            }
+        }
+
+        let soroban_rpc_url = soroban_rpc_url.map(|s| s.value)
+            .or_else(|| network.soroban_rpc_url().map(str::to_string));
+        let reflector = price_oracle.as_ref().is_some_and(|oracle| oracle.reflector.is_some());
+        if (blend.is_some() || soroswap.is_some() || vault_contract.is_some() || reflector) && soroban_rpc_url.is_none() {
+            return Err(VaultError::Validation("vault_contract, [blend], [soroswap] and the Reflector oracle need soroban_rpc_url (or STELLARVAULT_SOROBAN_RPC_URL) on this network".into()));
+        }
+
+        // The built-in vault only exists on testnet
+        if network.is_mainnet() && vault_address.is_none() {
+            return Err(VaultError::Validation("There is no default vault on mainnet; set vault_address or STELLARVAULT_VAULT_ADDRESS".into()));
+        }
+
+        let user_secret_key = env("STELLARVAULT_USER_SECRET");
+        if let Some(secret) = &user_secret_key {
+            validate_secret("STELLARVAULT_USER_SECRET", secret)?;
+        }
+        // Comma-separated when several operators sign for the vault
+        let vault_secret_keys: Vec<String> = env("STELLARVAULT_VAULT_SECRET")
+            .map(|value| value.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default();
+        for secret in &vault_secret_keys {
+            validate_secret("STELLARVAULT_VAULT_SECRET", secret)?;
+        }
+
+        let fee_payer_secret_key = env("STELLARVAULT_FEE_PAYER_SECRET");
+        if let Some(secret) = &fee_payer_secret_key {
+            validate_secret("STELLARVAULT_FEE_PAYER_SECRET", secret)?;
+            let public_key = Keypair::from_secret(secret)?.public_key();
+            if fee_payer.as_ref().is_some_and(|s| s.value != public_key) {
+                return Err(VaultError::Validation("STELLARVAULT_FEE_PAYER_SECRET does not belong to the configured fee_payer".into()));
+            }
+        }
+
+        let web_auth_secret_key = env("STELLARVAULT_WEB_AUTH_SECRET");
+        if let Some(secret) = &web_auth_secret_key {
+            validate_secret("STELLARVAULT_WEB_AUTH_SECRET", secret)?;
+        }
+        let api_keys = pick_api_keys()?;
+        let defaults = RateLimits::default();
+        let mut api_rate_limits = RateLimits {
+            per_ip: file.api_rate_limit.unwrap_or(defaults.per_ip),
+            per_key: file.api_key_rate_limit.unwrap_or(defaults.per_key),
+        };
+        for (name, limit) in [("STELLARVAULT_API_RATE_LIMIT", &mut api_rate_limits.per_ip),
+                              ("STELLARVAULT_API_KEY_RATE_LIMIT", &mut api_rate_limits.per_key)] {
+            if let Some(value) = env(name) {
+                *limit = value.parse()
+                    .map_err(|_| VaultError::Validation(format!("{} must be requests per minute, or 0 for no limit: {}", name, value)))?;
+            }
+        }
+        let idempotency_window_secs = match env("STELLARVAULT_IDEMPOTENCY_WINDOW") {
+            Some(value) => value.parse()
+                .map_err(|_| VaultError::Validation(format!("STELLARVAULT_IDEMPOTENCY_WINDOW must be a number of seconds: {}", value)))?,
+            None => file.idempotency_window_secs.unwrap_or(DEFAULT_IDEMPOTENCY_WINDOW_SECS),
+        };
+        if idempotency_window_secs == 0 {
+            return Err(VaultError::Validation("idempotency_window_secs must be at least 1".into()));
+        }
+        let admin_delay_secs = match env("STELLARVAULT_ADMIN_DELAY") {
+            Some(value) => value.parse()
+                .map_err(|_| VaultError::Validation(format!("STELLARVAULT_ADMIN_DELAY must be a number of seconds: {}", value)))?,
+            None => file.admin_delay_secs.unwrap_or(DEFAULT_ADMIN_DELAY_SECS),
+        };
+        let allowlist_only = match env("STELLARVAULT_ALLOWLIST_ONLY") {
+            Some(value) => match value.to_lowercase().as_str() {
+                "1" | "true" | "yes" | "on" => true,
+                "0" | "false" | "no" | "off" => false,
+                _ => return Err(VaultError::Validation(format!("STELLARVAULT_ALLOWLIST_ONLY must be true or false: {}", value))),
+            },
+            None => file.allowlist_only.unwrap_or(false),
+        };
+
+        let ingest = match env("STELLARVAULT_INGEST") {
+            Some(value) => match value.to_lowercase().as_str() {
+                "1" | "true" | "yes" | "on" => true,
+                "0" | "false" | "no" | "off" => false,
+                _ => return Err(VaultError::Validation(format!("STELLARVAULT_INGEST must be true or false: {}", value))),
+            },
+            None => file.ingest.unwrap_or(true),
+        };
+
+        let fee_percentile = match env("STELLARVAULT_FEE_PERCENTILE") {
+            Some(value) => value.trim_start_matches(['p', 'P']).parse()
+                .map_err(|_| VaultError::Validation(format!("STELLARVAULT_FEE_PERCENTILE must be a percentile such as 90: {}", value)))?,
+            None => file.fee_percentile.unwrap_or(DEFAULT_FEE_PERCENTILE),
+        };
+        let max_fee = match env("STELLARVAULT_MAX_FEE") {
+            Some(value) => value.parse()
+                .map_err(|_| VaultError::Validation(format!("STELLARVAULT_MAX_FEE must be a fee in stroops: {}", value)))?,
+            None => file.max_fee.unwrap_or(DEFAULT_MAX_FEE),
+        };
+        let fee_strategy = FeeStrategy::new(fee_percentile, max_fee)
+            .map_err(|e| VaultError::Validation(format!("Invalid fee settings: {}", e)))?;
+        let max_slippage_bps = match env("STELLARVAULT_MAX_SLIPPAGE_BPS") {
+            Some(value) => value.parse()
+                .map_err(|_| VaultError::Validation(format!("STELLARVAULT_MAX_SLIPPAGE_BPS must be basis points such as 100: {}", value)))?,
+            None => file.max_slippage_bps.unwrap_or(DEFAULT_MAX_SLIPPAGE_BPS),
+        };
+        if max_slippage_bps > MAX_SLIPPAGE_BPS {
+            return Err(VaultError::Validation(format!("max_slippage_bps can be at most {}: {}", MAX_SLIPPAGE_BPS, max_slippage_bps)));
+        }
+
+        let log_defaults = LogConfig::default();
+        let log_level = pick("STELLARVAULT_LOG", file.log_level, "log_level", file_name);
+        let log_file_level = pick("STELLARVAULT_LOG_FILE_LEVEL", file.log_file_level, "log_file_level", file_name);
+        for setting in log_level.iter().chain(&log_file_level) {
+            logging::filter(&setting.value).map_err(|e| VaultError::Validation(format!("{}: {}", setting.origin, e)))?;
+        }
+        let log_rotation = match pick("STELLARVAULT_LOG_ROTATION", file.log_rotation, "log_rotation", file_name) {
+            Some(setting) => LogRotation::parse(&setting.value)
+                .map_err(|e| VaultError::Validation(format!("{}: {}", setting.origin, e)))?,
+            None => log_defaults.rotation,
+        };
+        let log_max_files = match env("STELLARVAULT_LOG_MAX_FILES") {
+            Some(value) => value.parse()
+                .map_err(|_| VaultError::Validation(format!("STELLARVAULT_LOG_MAX_FILES must be a number of files: {}", value)))?,
+            None => file.log_max_files.unwrap_or(log_defaults.max_files),
+        };
+        let log = LogConfig {
+            level: log_level.map_or(log_defaults.level, |s| s.value),
+            dir: env("STELLARVAULT_LOG_DIR").map(PathBuf::from).or(file.log_dir),
+            file_level: log_file_level.map_or(log_defaults.file_level, |s| s.value),
+            rotation: log_rotation,
+            max_files: log_max_files,
+        };
+
+        let webhook_urls = pick_list("STELLARVAULT_WEBHOOK_URLS", file.webhook_urls, "webhook_urls", file_name);
+        for url in &webhook_urls {
+            if !url.value.starts_with("https://") && !url.value.starts_with("http://") {
+                return Err(VaultError::Validation(format!("{} must be an http(s) URL: {}", url.origin, url.value)));
+            }
+        }
+        let webhook_secret = env("STELLARVAULT_WEBHOOK_SECRET");
+        if !webhook_urls.is_empty() && webhook_secret.is_none() {
+            return Err(VaultError::Validation("webhook_urls needs STELLARVAULT_WEBHOOK_SECRET to sign the payloads with".into()));
+        }
+        let telegram_chat_id = pick("STELLARVAULT_TELEGRAM_CHAT_ID", file.telegram_chat_id, "telegram_chat_id", file_name);
+        let telegram_bot_token = env("STELLARVAULT_TELEGRAM_BOT_TOKEN");
+        let telegram = match (telegram_bot_token, telegram_chat_id) {
+            (Some(bot_token), Some(chat_id)) => {
+                // The account unlocked from the keystore isn't known yet
+                let account = match pick("STELLARVAULT_TELEGRAM_ACCOUNT", file.telegram_account, "telegram_account", file_name) {
+                    Some(setting) => {
+                        validate_account(&setting)?;
+                        setting.value
+                    }
+                    None => user_public_key.as_ref().map(|s| s.value.clone())
+                        .ok_or_else(|| VaultError::Validation("Telegram notifications need telegram_account (or user_public_key) to say whose activity to send".into()))?,
+                };
+                Some(TelegramConfig { bot_token, chat_id: chat_id.value, account })
+            }
+            (None, None) => None,
+            (None, Some(_)) => return Err(VaultError::Validation("telegram_chat_id needs STELLARVAULT_TELEGRAM_BOT_TOKEN for the bot that sends to it".into())),
+            (Some(_), None) => return Err(VaultError::Validation("STELLARVAULT_TELEGRAM_BOT_TOKEN needs telegram_chat_id (or STELLARVAULT_TELEGRAM_CHAT_ID) to send to".into())),
+        };
+        let notify = NotifyConfig {
+            webhook_urls: webhook_urls.into_iter().map(|s| s.value).collect(),
+            webhook_secret,
+            telegram,
+        };
+
+        let (vaults, vaults_source) = vault_definitions(file.vaults_file, file_name)?;
+
+        let keystore_path = env("STELLARVAULT_KEYSTORE").map(PathBuf::from)
+            .or(file.keystore)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_KEYSTORE_FILE));
+
+        Ok(Config {
+            network,
+            vault_address: vault_address.map(|s| s.value).unwrap_or_else(|| DEFAULT_VAULT_ADDRESS.to_string()),
+            keystore_path,
+            user_public_key: user_public_key.map(|s| s.value),
+            user_secret_key,
+            vault_secret_keys,
+            vault_signers: vault_signers.into_iter().map(|s| s.value).collect(),
+            signer_url: signer_url.map(|s| s.value),
+            signer_keys: signer_keys.into_iter().map(|s| s.value).collect(),
+            signer_timeout_secs,
+            fee_payer: fee_payer.map(|s| s.value),
+            fee_payer_secret_key,
+            web_auth_secret_key,
+            api_operators: api_operators.into_iter().map(|s| s.value).collect(),
+            api_admins: api_admins.into_iter().map(|s| s.value).collect(),
+            api_keys,
+            api_rate_limits,
+            idempotency_window_secs,
+            admin_delay_secs,
+            allowlist_only,
+            log,
+            notify,
+            alerts,
+            schedule,
+            share_issuer: share_issuer.map(|s| s.value),
+            treasury_address: treasury.map(|s| s.value),
+            vault_assets,
+            ingest,
+            fee_strategy,
+            max_slippage_bps,
+            market_making,
+            liquidity_pool,
+            soroban_rpc_url,
+            retry,
+            horizon_fallback_urls: horizon_fallback_urls.into_iter().map(|s| s.value.trim_end_matches('/').to_string()).collect(),
+            horizon_breaker,
+            vault_contract: vault_contract.map(|s| s.value),
+            blend,
+            soroswap,
+            apy_oracle,
+            price_oracle,
+            vaults,
+            vaults_source,
+            source,
+        })
+    }
+}
+
+// Replaces `key`'s line above the first table (all of it, for a list split
+// over lines), or its commented-out example, or adds it just before the
+// first table
+fn set_top_level(contents: &str, key: &str, value: &toml::Value) -> String {
+    let line = format!("{} = {}", key, value);
+    let sets_key = |line: &str, commented: bool| {
+        let line = line.trim_start();
+        let line = if commented { line.strip_prefix('#').map(str::trim_start) } else { Some(line) };
+        line.and_then(|line| line.strip_prefix(key)).is_some_and(|rest| rest.trim_start().starts_with('='))
+    };
+
+    let mut lines: Vec<String> = contents.lines().map(str::to_string).collect();
+    let top_level = lines.iter().position(|l| l.trim_start().starts_with('[')).unwrap_or(lines.len());
+    let existing = lines[..top_level].iter().position(|l| sets_key(l, false))
+        .or_else(|| lines[..top_level].iter().position(|l| sets_key(l, true)));
+    match existing {
+        Some(index) => {
+            let open = |line: &str| line.matches('[').count() as i64 - line.matches(']').count() as i64;
+            let mut depth = open(&lines[index]);
+            while depth > 0 && index + 1 < lines.len() {
+                depth += open(&lines.remove(index + 1));
+            }
+            lines[index] = line;
+        }
+        None if top_level < lines.len() => {
+            lines.insert(top_level, String::new());
+            lines.insert(top_level, line);
+        }
+        None => lines.push(line),
+    }
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example_config_parses() {
+        let file = Config::parse(include_str!("../stellarvault.example.toml"), "example").unwrap();
+        assert_eq!(file.vault_address.as_deref(), Some(DEFAULT_VAULT_ADDRESS));
+        assert_eq!(file.network.as_deref(), Some("testnet"));
+    }
+
+    #[test]
+    fn secrets_and_unknown_keys_are_rejected() {
+        let err = Config::parse("user_secret_key = \"S...\"", "test.toml").unwrap_err();
+        assert!(matches!(&err, VaultError::Validation(message) if message.contains("keystore")));
+        assert!(matches!(Config::parse("horizon = \"https://example.com\"", "test.toml"), Err(VaultError::Validation(_))));
+    }
+
+    #[test]
+    fn recorded_settings_replace_their_example() {
+        let example = include_str!("../stellarvault.example.toml");
+        let updated = set_top_level(example, "vault_contract", &"CABC".into());
+        assert_eq!(updated.lines().count(), example.lines().count());
+        assert_eq!(Config::parse(&updated, "example").unwrap().vault_contract.as_deref(), Some("CABC"));
+        assert_eq!(set_top_level(&updated, "vault_contract", &"CDEF".into()), set_top_level(example, "vault_contract", &"CDEF".into()));
+
+        let added = set_top_level("network = \"testnet\"\n\n[risk.low]\n", "vault_contract", &"CABC".into());
+        assert_eq!(added, "network = \"testnet\"\n\nvault_contract = \"CABC\"\n\n[risk.low]\n");
+
+        // A list written over several lines is replaced whole
+        let signers = toml::Value::Array(vec!["GNEW".into()]);
+        let replaced = set_top_level("vault_signers = [\n  \"GOLD\",\n  \"GKEEP\",\n]\nnetwork = \"testnet\"\n", "vault_signers", &signers);
+        assert_eq!(replaced, "vault_signers = [\"GNEW\"]\nnetwork = \"testnet\"\n");
+    }
+}
@@ -0,0 +1,297 @@
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use stellar_xdr::curr::ScVal;
+use tokio::sync::mpsc;
+
+use crate::amount::{mul_div, Rounding};
+use crate::soroban::{self, RpcClient};
+use crate::vault_contract;
+use crate::RiskLevel;
+
+// Soroban RPC has no event stream, so the indexer polls about once a ledger
+const POLL_INTERVAL_SECS: u64 = 5;
+const PAGE_LIMIT: u32 = 100;
+const MAX_BACKOFF_SECS: u64 = 60;
+
+// ============================================================================
+// EVENT POLLING
+// ============================================================================
+
+pub enum IndexEvent {
+    Event(Box<soroban::Event>),
+    // Polling failed and will retry after the given delay
+    Disconnected { error: String, retry_secs: u64 },
+}
+
+// Polls the events of the vault contract and its share tokens in the
+// background. The receiver sees every event after `cursor`, in order, or
+// from the oldest ledger the server keeps when there is no cursor yet;
+// dropping it stops the task.
+pub fn spawn(rpc: RpcClient, contracts: Vec<String>, cursor: Option<String>) -> mpsc::UnboundedReceiver<IndexEvent> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let mut cursor = cursor;
+        let mut backoff = 1;
+
+        loop {
+            let page = match &cursor {
+                Some(cursor) => rpc.get_events(&contracts, None, Some(cursor), PAGE_LIMIT).await,
+                None => match rpc.oldest_ledger().await {
+                    Ok(oldest) => rpc.get_events(&contracts, Some(oldest), None, PAGE_LIMIT).await,
+                    Err(e) => Err(e),
+                },
+            };
+            if sender.is_closed() {
+                return;
+            }
+
+            match page {
+                Ok(page) => {
+                    backoff = 1;
+                    let full = page.events.len() as u32 == PAGE_LIMIT;
+                    for event in page.events {
+                        cursor = Some(event.id.clone());
+                        if sender.send(IndexEvent::Event(Box::new(event))).is_err() {
+                            return;
+                        }
+                    }
+                    // The page cursor also moves past ledgers with no events
+                    if page.cursor.is_some() {
+                        cursor = page.cursor;
+                    }
+                    if !full {
+                        tokio::time::sleep(Duration::from_secs(POLL_INTERVAL_SECS)).await;
+                    }
+                }
+                Err(e) => {
+                    let event = IndexEvent::Disconnected { error: e.to_string(), retry_secs: backoff };
+                    if sender.send(event).is_err() {
+                        return;
+                    }
+                    tokio::time::sleep(Duration::from_secs(backoff)).await;
+                    backoff = (backoff * 2).min(MAX_BACKOFF_SECS);
+                }
+            }
+        }
+    });
+
+    receiver
+}
+
+// ============================================================================
+// CONTRACT EVENTS
+// ============================================================================
+
+// What the vault contract reported, amounts and shares in stroops
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContractEvent {
+    Deposit { user: String, risk: RiskLevel, amount: u64, fee: u64, shares: u64 },
+    Withdraw { user: String, risk: RiskLevel, amount: u64, shares: u64 },
+    Harvest { risk: RiskLevel, amount: u64 },
+    Claim { to: String, risk: RiskLevel, amount: u64 },
+    // Shares moved between holders through the risk level's share token
+    ShareTransfer { from: String, to: String, risk: RiskLevel, amount: u64 },
+}
+
+impl ContractEvent {
+    // None for events the vault contract doesn't emit, or can't be read
+    pub fn parse(topics: &[ScVal], data: &ScVal) -> Option<Self> {
+        let amounts = || soroban::items(data)?.iter().map(soroban::to_amount).collect::<Option<Vec<_>>>();
+        match topics {
+            [name, to, risk] if soroban::describe(name) == "claim" => Some(ContractEvent::Claim {
+                to: soroban::to_address(to)?,
+                risk: vault_contract::parse_risk(risk)?,
+                amount: soroban::to_amount(data)?,
+            }),
+            [name, user, risk] => {
+                let (user, risk) = (soroban::to_address(user)?, vault_contract::parse_risk(risk)?);
+                match (soroban::describe(name).as_str(), amounts()?.as_slice()) {
+                    ("deposit", [amount, fee, shares]) => Some(ContractEvent::Deposit { user, risk, amount: *amount, fee: *fee, shares: *shares }),
+                    ("withdraw", [amount, shares]) => Some(ContractEvent::Withdraw { user, risk, amount: *amount, shares: *shares }),
+                    _ => None,
+                }
+            }
+            [name, risk] if soroban::describe(name) == "harvest" => Some(ContractEvent::Harvest {
+                risk: vault_contract::parse_risk(risk)?,
+                amount: soroban::to_amount(data)?,
+            }),
+            _ => None,
+        }
+    }
+
+    // Transfers are all the index needs from a share token; its mints and
+    // burns mirror the vault contract's deposits and withdrawals
+    pub fn parse_share_transfer(risk: RiskLevel, topics: &[ScVal], data: &ScVal) -> Option<Self> {
+        match topics {
+            [name, from, to] if soroban::describe(name) == "transfer" => Some(ContractEvent::ShareTransfer {
+                from: soroban::to_address(from)?,
+                to: soroban::to_address(to)?,
+                risk,
+                amount: soroban::to_amount(data)?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+// ============================================================================
+// READ MODEL
+// ============================================================================
+
+// The vault contract's state rebuilt from its events, so positions, TVL and
+// share prices can be shown without a round trip to the network
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContractIndex {
+    // getEvents cursor of the last event applied
+    #[serde(default)]
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub vaults: Vec<IndexedVault>,
+    #[serde(default)]
+    pub positions: Vec<IndexedPosition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedVault {
+    pub risk: RiskLevel,
+    pub total_assets: u64,
+    pub total_shares: u64,
+    pub insurance_reserve: u64,
+    // Ledger of the last event applied
+    pub ledger: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedPosition {
+    pub user: String,
+    pub risk: RiskLevel,
+    pub shares: u64,
+}
+
+impl IndexedVault {
+    // Value of one share, in the vault's asset
+    pub fn share_price(&self) -> f64 {
+        if self.total_shares == 0 {
+            return 1.0;
+        }
+        self.total_assets as f64 / self.total_shares as f64
+    }
+
+    pub fn value_of(&self, shares: u64) -> u64 {
+        mul_div(shares, self.total_assets, self.total_shares, Rounding::Down).unwrap_or(0)
+    }
+}
+
+impl ContractIndex {
+    // Applies an event once, returning false for events at or before the
+    // cursor. Event ids sort in the order the events happened.
+    pub fn apply(&mut self, id: &str, ledger: u32, event: &ContractEvent) -> bool {
+        if self.cursor.as_deref().is_some_and(|cursor| id <= cursor) {
+            return false;
+        }
+        self.cursor = Some(id.to_string());
+
+        let risk = match event {
+            ContractEvent::Deposit { risk, .. } | ContractEvent::Withdraw { risk, .. }
+            | ContractEvent::Harvest { risk, .. } | ContractEvent::Claim { risk, .. }
+            | ContractEvent::ShareTransfer { risk, .. } => *risk,
+        };
+        let vault = match self.vaults.iter().position(|v| v.risk == risk) {
+            Some(index) => &mut self.vaults[index],
+            None => {
+                self.vaults.push(IndexedVault { risk, total_assets: 0, total_shares: 0, insurance_reserve: 0, ledger });
+                self.vaults.last_mut().unwrap()
+            }
+        };
+        vault.ledger = ledger;
+
+        match event {
+            ContractEvent::Deposit { user, amount, fee, shares, .. } => {
+                vault.total_assets += amount.saturating_sub(*fee);
+                vault.total_shares += shares;
+                vault.insurance_reserve += fee;
+                *self.position_mut(user, risk) += shares;
+            }
+            ContractEvent::Withdraw { user, amount, shares, .. } => {
+                vault.total_assets = vault.total_assets.saturating_sub(*amount);
+                vault.total_shares = vault.total_shares.saturating_sub(*shares);
+                let held = self.position_mut(user, risk);
+                *held = held.saturating_sub(*shares);
+            }
+            ContractEvent::Harvest { amount, .. } => vault.total_assets += amount,
+            ContractEvent::Claim { amount, .. } => {
+                vault.insurance_reserve = vault.insurance_reserve.saturating_sub(*amount);
+            }
+            ContractEvent::ShareTransfer { from, to, amount, .. } => {
+                let held = self.position_mut(from, risk);
+                *held = held.saturating_sub(*amount);
+                *self.position_mut(to, risk) += amount;
+            }
+        }
+        self.positions.retain(|p| p.shares > 0);
+        true
+    }
+
+    fn position_mut(&mut self, user: &str, risk: RiskLevel) -> &mut u64 {
+        let index = match self.positions.iter().position(|p| p.user == user && p.risk == risk) {
+            Some(index) => index,
+            None => {
+                self.positions.push(IndexedPosition { user: user.to_string(), risk, shares: 0 });
+                self.positions.len() - 1
+            }
+        };
+        &mut self.positions[index].shares
+    }
+
+    pub fn vault(&self, risk: RiskLevel) -> Option<&IndexedVault> {
+        self.vaults.iter().find(|v| v.risk == risk)
+    }
+
+    pub fn shares(&self, user: &str, risk: RiskLevel) -> u64 {
+        self.positions.iter().find(|p| p.user == user && p.risk == risk).map_or(0, |p| p.shares)
+    }
+
+    // Every risk level's assets together; the contract holds a single token
+    pub fn tvl(&self) -> u64 {
+        self.vaults.iter().map(|v| v.total_assets).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_rebuild_positions_and_share_price() {
+        let user = stellar_strkey::ed25519::PublicKey([1; 32]).to_string();
+        let topics = [
+            soroban::symbol("deposit").unwrap(),
+            soroban::address(&user).unwrap(),
+            vault_contract::risk(RiskLevel::Low).unwrap(),
+        ];
+        let data = soroban::vec(vec![soroban::amount(1_000), soroban::amount(5), soroban::amount(995)]).unwrap();
+        let deposit = ContractEvent::parse(&topics, &data).unwrap();
+
+        let mut index = ContractIndex::default();
+        assert!(index.apply("0000000100-0000000001", 100, &deposit));
+        // Re-delivered events are ignored
+        assert!(!index.apply("0000000100-0000000001", 100, &deposit));
+        index.apply("0000000101-0000000001", 101, &ContractEvent::Harvest { risk: RiskLevel::Low, amount: 995 });
+
+        let vault = index.vault(RiskLevel::Low).unwrap();
+        assert_eq!((vault.total_assets, vault.insurance_reserve), (1_990, 5));
+        assert_eq!(vault.share_price(), 2.0);
+        assert_eq!(vault.value_of(index.shares(&user, RiskLevel::Low)), 1_990);
+        assert_eq!(index.tvl(), 1_990);
+
+        // Share token transfers move positions without touching the vault
+        let other = stellar_strkey::Contract([2; 32]).to_string();
+        let topics = [soroban::symbol("transfer").unwrap(), soroban::address(&user).unwrap(), soroban::address(&other).unwrap()];
+        let transfer = ContractEvent::parse_share_transfer(RiskLevel::Low, &topics, &soroban::i128(500)).unwrap();
+        assert!(ContractEvent::parse(&topics, &soroban::i128(500)).is_none());
+        index.apply("0000000102-0000000001", 102, &transfer);
+        assert_eq!((index.shares(&user, RiskLevel::Low), index.shares(&other, RiskLevel::Low)), (495, 500));
+        assert_eq!(index.tvl(), 1_990);
+    }
+}
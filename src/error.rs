@@ -0,0 +1,165 @@
+use thiserror::Error;
+
+use crate::amount::{MathError, Stroops};
+use crate::horizon::HorizonError;
+use crate::oracle::format_usd;
+use crate::soroban::RpcError;
+use crate::submission::SubmitError;
+use crate::RiskLevel;
+
+// ============================================================================
+// VAULT ERRORS
+// ============================================================================
+
+/// Everything a vault operation can fail with, by cause, so callers can match
+/// on what went wrong rather than on message text.
+#[derive(Debug, Error)]
+pub enum VaultError {
+    // Input or a setting the vault can't use, or an action its rules refuse
+    #[error("{0}")]
+    Validation(String),
+    #[error("{0:?} Risk Vault not found")]
+    VaultNotFound(RiskLevel),
+    #[error("Insufficient {asset} balance: {} available", .available.to_xlm_string())]
+    InsufficientBalance { asset: String, available: Stroops },
+    #[error("Insufficient shares: requested {requested}, available {available}")]
+    InsufficientShares { requested: u64, available: u64 },
+    #[error(transparent)]
+    DepositLimit(#[from] DepositError),
+    #[error(transparent)]
+    Math(#[from] MathError),
+    // Talking to the network failed, or it refused what was sent
+    #[error(transparent)]
+    Stellar(#[from] StellarError),
+    // Saved state, the event log or a file couldn't be read or written
+    #[error("{0}")]
+    Storage(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    // A failure with no more specific cause, described by its message
+    #[error("{0}")]
+    Other(String),
+}
+
+/// A deposit the vault's limits refuse.
+#[derive(Debug, Error)]
+pub enum DepositError {
+    #[error("Deposit of {} is below the vault minimum of {}", Stroops(*.amount), Stroops(*.minimum))]
+    BelowMinimum { amount: u64, minimum: u64 },
+    #[error("Deposit of {} would take vault TVL to {}, above its {} cap",
+        Stroops(*.amount), Stroops(.tvl.saturating_add(*.amount)), Stroops(*.cap))]
+    VaultCapExceeded { tvl: u64, amount: u64, cap: u64 },
+    #[error("Deposit of {} would take your position to {}, above the {} per-user cap",
+        Stroops(*.amount), Stroops(.position_value.saturating_add(*.amount)), Stroops(*.cap))]
+    UserCapExceeded { position_value: u64, amount: u64, cap: u64 },
+    #[error("Deposit would take {scope} to {}, above its {} cap", format_usd(*.value_usd), format_usd(*.cap_usd))]
+    UsdCapExceeded { scope: &'static str, value_usd: u64, cap_usd: u64 },
+    #[error("No current USD price for {asset}, so the vault's USD caps can't be checked")]
+    NoPrice { asset: String },
+}
+
+impl VaultError {
+    pub fn horizon(&self) -> Option<&HorizonError> {
+        match self {
+            VaultError::Stellar(StellarError::Horizon(e)) => Some(e),
+            _ => None,
+        }
+    }
+
+    pub fn submit(&self) -> Option<&SubmitError> {
+        match self {
+            VaultError::Stellar(StellarError::Submit(e)) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<String> for VaultError {
+    fn from(message: String) -> Self {
+        VaultError::Other(message)
+    }
+}
+
+impl From<&str> for VaultError {
+    fn from(message: &str) -> Self {
+        VaultError::Other(message.to_string())
+    }
+}
+
+// ============================================================================
+// STELLAR ERRORS
+// ============================================================================
+
+/// Failures reaching Horizon, Soroban RPC or other Stellar services, or
+/// reading what they returned.
+#[derive(Debug, Error)]
+pub enum StellarError {
+    #[error(transparent)]
+    Horizon(#[from] HorizonError),
+    #[error(transparent)]
+    Rpc(#[from] RpcError),
+    #[error(transparent)]
+    Submit(#[from] SubmitError),
+    #[error("HTTP request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Invalid XDR: {0}")]
+    Xdr(#[from] stellar_xdr::curr::Error),
+    #[error("Invalid Stellar key or address: {0}")]
+    Strkey(#[from] stellar_strkey::DecodeError),
+}
+
+impl From<HorizonError> for VaultError {
+    fn from(e: HorizonError) -> Self {
+        VaultError::Stellar(e.into())
+    }
+}
+
+impl From<RpcError> for VaultError {
+    fn from(e: RpcError) -> Self {
+        VaultError::Stellar(e.into())
+    }
+}
+
+impl From<SubmitError> for VaultError {
+    fn from(e: SubmitError) -> Self {
+        VaultError::Stellar(e.into())
+    }
+}
+
+impl From<reqwest::Error> for VaultError {
+    fn from(e: reqwest::Error) -> Self {
+        VaultError::Stellar(e.into())
+    }
+}
+
+impl From<stellar_xdr::curr::Error> for VaultError {
+    fn from(e: stellar_xdr::curr::Error) -> Self {
+        VaultError::Stellar(e.into())
+    }
+}
+
+impl From<stellar_strkey::DecodeError> for VaultError {
+    fn from(e: stellar_strkey::DecodeError) -> Self {
+        VaultError::Stellar(e.into())
+    }
+}
+
+// Amounts are u64 stroops; XDR and contracts take other integer widths
+impl From<std::num::TryFromIntError> for VaultError {
+    fn from(_: std::num::TryFromIntError) -> Self {
+        VaultError::Math(MathError::Overflow)
+    }
+}
+
+impl From<serde_json::Error> for VaultError {
+    fn from(e: serde_json::Error) -> Self {
+        VaultError::Storage(format!("Invalid JSON: {}", e))
+    }
+}
+
+#[cfg(feature = "storage-sqlite")]
+impl From<rusqlite::Error> for VaultError {
+    fn from(e: rusqlite::Error) -> Self {
+        VaultError::Storage(format!("Database error: {}", e))
+    }
+}
@@ -0,0 +1,342 @@
+use std::collections::{BTreeMap, HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::error::VaultError;
+use crate::fees;
+use crate::insurance::INSURANCE_VAULT;
+use crate::rebalance::{self, RebalanceMove};
+use crate::screening::Screened;
+use crate::{RiskLevel, UserPosition, Vault};
+
+// ============================================================================
+// EVENTS
+// ============================================================================
+
+// Every balance-changing operation is appended to the event log with the
+// amounts it actually produced, so replaying the log reproduces the
+// accounting without re-running any price or fee math.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VaultEvent {
+    Deposit {
+        user: String,
+        risk: RiskLevel,
+        amount_stroops: u64,
+        insurance_stroops: u64,
+        shares_minted: u64,
+        tx_hash: String,
+    },
+    Withdrawal {
+        user: String,
+        risk: RiskLevel,
+        shares_burned: u64,
+        gross_stroops: u64,
+        penalty_stroops: u64,
+        tx_hash: String,
+    },
+    ManagementFeeAccrued {
+        risk: RiskLevel,
+        amount_stroops: u64,
+    },
+    FeesCollected {
+        risk: RiskLevel,
+        amount_stroops: u64,
+        tx_hash: String,
+    },
+    Harvest {
+        risk: RiskLevel,
+        // Earned per strategy, in the vault's strategy order
+        strategy_yield: Vec<u64>,
+        performance_fee: u64,
+        credits: Vec<YieldCredit>,
+        insurance_yield: u64,
+        insurance_shares: u64,
+    },
+    Rebalance {
+        risk: RiskLevel,
+        moves: Vec<RebalanceMove>,
+    },
+    InsuranceInvested {
+        amount_stroops: u64,
+        shares: u64,
+    },
+    InsuranceRedeemed {
+        shares: u64,
+        amount_stroops: u64,
+        principal_removed: u64,
+    },
+    ClaimPaid {
+        claim_id: u64,
+        amount_stroops: u64,
+        tx_hash: String,
+    },
+    // Accumulated yield paid out as claimable balances
+    YieldDistributed {
+        risk: RiskLevel,
+        payouts: Vec<YieldPayout>,
+        tx_hash: String,
+    },
+    // Settings a vault took from redefined vault definitions, with the
+    // definition itself so replay applies it at the same point
+    Reconfigured {
+        risk: RiskLevel,
+        changes: Vec<String>,
+        definition: Box<Vault>,
+    },
+    // A deposit or payout the address lists refused, before anything moved
+    OperationBlocked {
+        operation: Screened,
+        address: String,
+        risk: RiskLevel,
+        amount_stroops: u64,
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct YieldPayout {
+    pub user: String,
+    pub amount_stroops: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct YieldCredit {
+    pub user: String,
+    pub amount_stroops: u64,
+    // Compounded credits were reinvested as shares instead of paid as yield
+    pub compounded: bool,
+    pub shares: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventRecord {
+    pub seq: u64,
+    pub timestamp: u64,
+    pub event: VaultEvent,
+}
+
+impl VaultEvent {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            VaultEvent::Deposit { .. } => "deposit",
+            VaultEvent::Withdrawal { .. } => "withdrawal",
+            VaultEvent::ManagementFeeAccrued { .. } => "management_fee_accrued",
+            VaultEvent::FeesCollected { .. } => "fees_collected",
+            VaultEvent::Harvest { .. } => "harvest",
+            VaultEvent::Rebalance { .. } => "rebalance",
+            VaultEvent::InsuranceInvested { .. } => "insurance_invested",
+            VaultEvent::InsuranceRedeemed { .. } => "insurance_redeemed",
+            VaultEvent::ClaimPaid { .. } => "claim_paid",
+            VaultEvent::YieldDistributed { .. } => "yield_distributed",
+            VaultEvent::Reconfigured { .. } => "reconfigured",
+            VaultEvent::OperationBlocked { .. } => "operation_blocked",
+        }
+    }
+}
+
+// ============================================================================
+// REPLAY
+// ============================================================================
+
+// Balances rebuilt from the log. Vault configuration (fees, caps, strategy
+// targets) is taken from the live vaults; only balances are replayed.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub vaults: HashMap<RiskLevel, Vault>,
+    pub positions: HashMap<(String, RiskLevel), UserPosition>,
+    pub insurance_pool: u64,
+    // Premiums and penalties from vaults holding other assets, by CODE:ISSUER
+    pub asset_insurance: BTreeMap<String, u64>,
+    pub insurance_shares: u64,
+    pub insurance_principal: u64,
+}
+
+fn underflow(what: &str) -> VaultError {
+    format!("{} would go negative", what).into()
+}
+
+impl Replay {
+    pub fn from_config(vaults: &HashMap<RiskLevel, Vault>) -> Self {
+        let vaults = vaults.iter()
+            .map(|(risk, vault)| {
+                let mut vault = vault.clone();
+                vault.total_value = 0;
+                vault.total_shares = 0;
+                vault.liquid_reserve = 0;
+                vault.last_harvest = 0;
+                vault.fee_accrual = fees::FeeAccrual::default();
+                vault.circuit.last_share_price = 0;
+                for strategy in &mut vault.strategies {
+                    strategy.total_allocated = 0;
+                    strategy.current_yield = 0;
+                }
+                (*risk, vault)
+            })
+            .collect();
+
+        Replay {
+            vaults,
+            positions: HashMap::new(),
+            insurance_pool: 0,
+            asset_insurance: BTreeMap::new(),
+            insurance_shares: 0,
+            insurance_principal: 0,
+        }
+    }
+
+    fn vault(&mut self, risk: RiskLevel) -> Result<&mut Vault, VaultError> {
+        self.vaults.get_mut(&risk).ok_or_else(|| VaultError::Storage(format!("{:?} vault is not configured", risk)))
+    }
+
+    // Insurance income stays in the asset it was paid in
+    fn add_insurance(&mut self, risk: RiskLevel, amount: u64) -> Result<(), VaultError> {
+        match self.vault(risk)?.asset.as_ref().map(|asset| asset.to_string()) {
+            Some(asset) => *self.asset_insurance.entry(asset).or_default() += amount,
+            None => self.insurance_pool += amount,
+        }
+        Ok(())
+    }
+
+    pub fn apply(&mut self, record: &EventRecord) -> Result<(), VaultError> {
+        let now = record.timestamp;
+
+        match &record.event {
+            VaultEvent::Deposit { user, risk, amount_stroops, insurance_stroops, shares_minted, .. } => {
+                let net = amount_stroops.checked_sub(*insurance_stroops).ok_or_else(|| underflow("Deposit"))?;
+                let vault = self.vault(*risk)?;
+                vault.total_value += net;
+                vault.total_shares += shares_minted;
+                vault.allocate(net);
+                vault.fee_accrual.last_accrual = now;
+                if vault.last_harvest == 0 {
+                    vault.last_harvest = now;
+                }
+                self.add_insurance(*risk, *insurance_stroops)?;
+
+                let position = self.positions.entry((user.clone(), *risk)).or_default();
+                position.shares += shares_minted;
+                position.cost_basis += amount_stroops;
+                position.insurance_paid += insurance_stroops;
+                position.lock(*shares_minted, now);
+            }
+            VaultEvent::Withdrawal { user, risk, shares_burned, gross_stroops, penalty_stroops, .. } => {
+                let vault = self.vault(*risk)?;
+                vault.total_value = vault.total_value.checked_sub(*gross_stroops).ok_or_else(|| underflow("Vault TVL"))?;
+                vault.total_shares = vault.total_shares.checked_sub(*shares_burned).ok_or_else(|| underflow("Vault shares"))?;
+                vault.deallocate(*gross_stroops);
+                vault.fee_accrual.last_accrual = now;
+                let lockup_secs = vault.lockup_secs;
+                self.add_insurance(*risk, *penalty_stroops)?;
+
+                let position = self.positions.get_mut(&(user.clone(), *risk))
+                    .ok_or_else(|| VaultError::Storage(format!("Withdrawal by {} without a position", user)))?;
+                if position.shares < *shares_burned {
+                    return Err(underflow("Position shares"));
+                }
+                let cost_removed = (position.cost_basis as u128 * *shares_burned as u128
+                    / position.shares.max(1) as u128) as u64;
+                position.cost_basis -= cost_removed;
+                position.burn(*shares_burned, lockup_secs, now);
+            }
+            VaultEvent::ManagementFeeAccrued { risk, amount_stroops } => {
+                let vault = self.vault(*risk)?;
+                if vault.total_value < *amount_stroops {
+                    return Err(underflow("Vault TVL"));
+                }
+                fees::deduct_management_fee(vault, *amount_stroops);
+                vault.fee_accrual.last_accrual = now;
+            }
+            VaultEvent::FeesCollected { risk, amount_stroops, .. } => {
+                let vault = self.vault(*risk)?;
+                vault.fee_accrual.management_accrued = 0;
+                vault.fee_accrual.performance_accrued = 0;
+                vault.fee_accrual.total_collected += amount_stroops;
+            }
+            VaultEvent::Harvest { risk, strategy_yield, performance_fee, credits, insurance_yield, insurance_shares } => {
+                let vault = self.vault(*risk)?;
+                vault.last_harvest = now;
+                vault.fee_accrual.last_accrual = now;
+                for (strategy, earned) in vault.strategies.iter_mut().zip(strategy_yield) {
+                    strategy.current_yield += earned;
+                }
+                vault.fee_accrual.performance_accrued += performance_fee;
+
+                for credit in credits {
+                    if credit.compounded {
+                        vault.total_value += credit.amount_stroops;
+                        vault.total_shares += credit.shares;
+                        vault.allocate(credit.amount_stroops);
+                    }
+                }
+                if *insurance_shares > 0 || *insurance_yield > 0 {
+                    vault.total_value += insurance_yield;
+                    vault.total_shares += insurance_shares;
+                    vault.allocate(*insurance_yield);
+                    self.insurance_shares += insurance_shares;
+                }
+
+                for credit in credits {
+                    let position = self.positions.entry((credit.user.clone(), *risk)).or_default();
+                    if credit.compounded {
+                        position.shares += credit.shares;
+                    } else {
+                        position.accumulated_yield += credit.amount_stroops;
+                    }
+                }
+            }
+            VaultEvent::Rebalance { risk, moves } => {
+                let vault = self.vault(*risk)?;
+                for m in moves {
+                    rebalance::apply_move(&mut vault.strategies, m)?;
+                }
+            }
+            VaultEvent::InsuranceInvested { amount_stroops, shares } => {
+                self.insurance_pool = self.insurance_pool.checked_sub(*amount_stroops)
+                    .ok_or_else(|| underflow("Insurance pool"))?;
+                let vault = self.vault(INSURANCE_VAULT)?;
+                vault.total_value += amount_stroops;
+                vault.total_shares += shares;
+                vault.allocate(*amount_stroops);
+                self.insurance_shares += shares;
+                self.insurance_principal += amount_stroops;
+            }
+            VaultEvent::InsuranceRedeemed { shares, amount_stroops, principal_removed } => {
+                let vault = self.vault(INSURANCE_VAULT)?;
+                vault.total_value = vault.total_value.checked_sub(*amount_stroops).ok_or_else(|| underflow("Vault TVL"))?;
+                vault.total_shares = vault.total_shares.checked_sub(*shares).ok_or_else(|| underflow("Vault shares"))?;
+                vault.deallocate(*amount_stroops);
+                self.insurance_shares = self.insurance_shares.checked_sub(*shares)
+                    .ok_or_else(|| underflow("Insurance shares"))?;
+                self.insurance_principal = self.insurance_principal.saturating_sub(*principal_removed);
+                self.insurance_pool += amount_stroops;
+            }
+            VaultEvent::ClaimPaid { amount_stroops, .. } => {
+                self.insurance_pool = self.insurance_pool.checked_sub(*amount_stroops)
+                    .ok_or_else(|| underflow("Insurance pool"))?;
+            }
+            VaultEvent::YieldDistributed { risk, payouts, .. } => {
+                for payout in payouts {
+                    let position = self.positions.get_mut(&(payout.user.clone(), *risk))
+                        .ok_or_else(|| VaultError::Storage(format!("Yield paid to {} without a position", payout.user)))?;
+                    position.accumulated_yield = position.accumulated_yield.checked_sub(payout.amount_stroops)
+                        .ok_or_else(|| underflow("Accumulated yield"))?;
+                }
+            }
+            VaultEvent::Reconfigured { risk, definition, .. } => {
+                self.vault(*risk)?.redefine(definition);
+            }
+            VaultEvent::OperationBlocked { .. } => {}
+        }
+
+        Ok(())
+    }
+}
+
+pub fn replay(vaults: &HashMap<RiskLevel, Vault>, events: &[EventRecord]) -> Result<Replay, VaultError> {
+    let mut state = Replay::from_config(vaults);
+    for record in events {
+        state.apply(record)
+            .map_err(|e| VaultError::Storage(format!("Event #{} ({}): {}", record.seq, record.event.kind(), e)))?;
+    }
+    Ok(state)
+}
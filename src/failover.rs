@@ -0,0 +1,236 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use serde::Serialize;
+
+use crate::unix_now;
+
+// Consecutive failed or slow calls before switching to the next endpoint
+pub const FAIL_OVER_AFTER: u32 = 3;
+// Answers slower than this count against an endpoint even when they succeed
+pub const SLOW_RESPONSE: Duration = Duration::from_secs(5);
+// How often the primary is tried again while a fallback is in use
+pub const PROBE_INTERVAL: Duration = Duration::from_secs(60);
+// Failovers kept for watch and serve to report; older ones are dropped
+const MAX_PENDING: usize = 32;
+
+// ============================================================================
+// EVENTS & STATUS
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Failover {
+    pub from: String,
+    pub to: String,
+    pub reason: String,
+    pub at: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EndpointStatus {
+    pub url: String,
+    pub active: bool,
+    pub requests: u64,
+    pub failures: u64,
+    pub slow: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FailoverStatus {
+    pub active: String,
+    pub failovers: u64,
+    pub endpoints: Vec<EndpointStatus>,
+}
+
+// ============================================================================
+// ENDPOINTS
+// ============================================================================
+
+struct State {
+    endpoints: Vec<EndpointStatus>,
+    active: usize,
+    // Failed or slow calls in a row on the active endpoint
+    strikes: u32,
+    last_probe: Option<Instant>,
+    failovers: u64,
+    pending: VecDeque<Failover>,
+}
+
+// A primary URL and its fallbacks, shared by every clone so a failover seen
+// by one request applies to all of them. The first URL is the primary: after
+// failing over, it is probed every PROBE_INTERVAL and returned to once it
+// answers promptly again.
+#[derive(Clone)]
+pub struct Endpoints {
+    state: Arc<Mutex<State>>,
+}
+
+impl std::fmt::Debug for Endpoints {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Endpoints").field("active", &self.active()).finish()
+    }
+}
+
+impl Endpoints {
+    pub fn new(urls: Vec<String>) -> Self {
+        let endpoints = urls.into_iter()
+            .map(|url| EndpointStatus { url: url.trim_end_matches('/').to_string(), ..Default::default() })
+            .collect();
+        Endpoints {
+            state: Arc::new(Mutex::new(State {
+                endpoints,
+                active: 0,
+                strikes: 0,
+                last_probe: None,
+                failovers: 0,
+                pending: VecDeque::new(),
+            })),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn primary(&self) -> String {
+        self.lock().endpoints[0].url.clone()
+    }
+
+    pub fn active(&self) -> String {
+        let state = self.lock();
+        state.endpoints[state.active].url.clone()
+    }
+
+    // Records how a call to `url` went: Err for a failure that says nothing
+    // about the request itself. Results from an endpoint that is no longer
+    // active don't count against the one that is.
+    pub fn record(&self, url: &str, took: Duration, result: Result<(), String>) {
+        let mut state = self.lock();
+        let Some(index) = state.endpoints.iter().position(|endpoint| endpoint.url == url) else {
+            return;
+        };
+        let slow = took >= SLOW_RESPONSE;
+        let endpoint = &mut state.endpoints[index];
+        endpoint.requests += 1;
+        if result.is_err() {
+            endpoint.failures += 1;
+        } else if slow {
+            endpoint.slow += 1;
+        }
+        if index != state.active {
+            return;
+        }
+        if result.is_ok() && !slow {
+            state.strikes = 0;
+            return;
+        }
+        state.strikes += 1;
+        if state.strikes >= FAIL_OVER_AFTER && state.endpoints.len() > 1 {
+            let reason = match result {
+                Err(e) => format!("{} failures in a row, the last: {}", state.strikes, e),
+                Ok(()) => format!("{} answers in a row slower than {}s", state.strikes, SLOW_RESPONSE.as_secs()),
+            };
+            let next = (state.active + 1) % state.endpoints.len();
+            Self::switch(&mut state, next, reason);
+        }
+    }
+
+    fn switch(state: &mut State, to: usize, reason: String) {
+        let failover = Failover {
+            from: state.endpoints[state.active].url.clone(),
+            to: state.endpoints[to].url.clone(),
+            reason,
+            at: unix_now(),
+        };
+        tracing::warn!(from = %failover.from, to = %failover.to, reason = %failover.reason, "horizon failover");
+        state.active = to;
+        state.strikes = 0;
+        state.failovers += 1;
+        // The first probe comes an interval after leaving the primary
+        state.last_probe = if to == 0 { None } else { state.last_probe.or(Some(Instant::now())) };
+        if state.pending.len() == MAX_PENDING {
+            state.pending.pop_front();
+        }
+        state.pending.push_back(failover);
+    }
+
+    // The primary's URL when a fallback is in use and it's time to try the
+    // primary again; counts as the probe, so only one caller gets it
+    pub fn probe_due(&self, now: Instant) -> Option<String> {
+        let mut state = self.lock();
+        if state.active == 0 || state.last_probe.is_some_and(|last| now.duration_since(last) < PROBE_INTERVAL) {
+            return None;
+        }
+        state.last_probe = Some(now);
+        Some(state.endpoints[0].url.clone())
+    }
+
+    // The primary answered a probe promptly
+    pub fn recovered(&self) {
+        let mut state = self.lock();
+        if state.active != 0 {
+            Self::switch(&mut state, 0, "primary recovered".into());
+        }
+    }
+
+    // Failovers since the last call, oldest first
+    pub fn take_failovers(&self) -> Vec<Failover> {
+        self.lock().pending.drain(..).collect()
+    }
+
+    pub fn status(&self) -> FailoverStatus {
+        let state = self.lock();
+        FailoverStatus {
+            active: state.endpoints[state.active].url.clone(),
+            failovers: state.failovers,
+            endpoints: state.endpoints.iter().enumerate()
+                .map(|(index, endpoint)| EndpointStatus { active: index == state.active, ..endpoint.clone() })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRIMARY: &str = "https://horizon.example.org";
+    const FALLBACK: &str = "https://horizon.backup.example.org";
+
+    #[test]
+    fn fails_over_on_repeated_failures_and_returns_when_the_primary_recovers() {
+        let endpoints = Endpoints::new(vec![format!("{}/", PRIMARY), FALLBACK.to_string()]);
+        let quick = Duration::from_millis(200);
+        endpoints.record(PRIMARY, quick, Err("503".into()));
+        endpoints.record(PRIMARY, quick, Err("503".into()));
+        // A good answer resets the count
+        endpoints.record(PRIMARY, quick, Ok(()));
+        endpoints.record(PRIMARY, quick, Err("503".into()));
+        endpoints.record(PRIMARY, SLOW_RESPONSE, Ok(()));
+        assert_eq!(endpoints.active(), PRIMARY);
+        endpoints.record(PRIMARY, quick, Err("connection refused".into()));
+        assert_eq!(endpoints.active(), FALLBACK);
+
+        // Late failures from the primary don't count against the fallback
+        for _ in 0..FAIL_OVER_AFTER {
+            endpoints.record(PRIMARY, quick, Err("503".into()));
+        }
+        assert_eq!(endpoints.active(), FALLBACK);
+
+        let start = Instant::now();
+        assert_eq!(endpoints.probe_due(start), None);
+        assert_eq!(endpoints.probe_due(start + PROBE_INTERVAL).as_deref(), Some(PRIMARY));
+        // Only one caller probes per interval
+        assert_eq!(endpoints.probe_due(start + PROBE_INTERVAL), None);
+        endpoints.recovered();
+
+        let failovers = endpoints.take_failovers();
+        assert_eq!(failovers.iter().map(|f| (f.from.as_str(), f.to.as_str())).collect::<Vec<_>>(),
+            [(PRIMARY, FALLBACK), (FALLBACK, PRIMARY)]);
+        assert!(failovers[0].reason.contains("connection refused"));
+        assert!(endpoints.take_failovers().is_empty());
+        let status = endpoints.status();
+        assert_eq!((status.active.as_str(), status.failovers), (PRIMARY, 2));
+        assert_eq!((status.endpoints[0].requests, status.endpoints[0].failures, status.endpoints[0].slow), (9, 7, 1));
+    }
+}
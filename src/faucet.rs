@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use crate::error::VaultError;
+use crate::horizon::{Account, HorizonClient};
+
+// How long to wait for the funded account to show up in Horizon
+const WAIT_ATTEMPTS: u32 = 15;
+const WAIT_INTERVAL: Duration = Duration::from_secs(2);
+
+// ============================================================================
+// FRIENDBOT
+// ============================================================================
+
+pub enum Funding {
+    Funded,
+    // Friendbot only creates accounts; it won't top up one that exists
+    AlreadyExists,
+}
+
+// Asks friendbot to create and fund `account` with test XLM
+pub async fn fund(friendbot_url: &str, account: &str) -> Result<Funding, VaultError> {
+    stellar_strkey::ed25519::PublicKey::from_string(account)
+        .map_err(|_| format!("Invalid Stellar account {}", account))?;
+    let response = reqwest::Client::new()
+        .get(friendbot_url)
+        .query(&[("addr", account)])
+        .send()
+        .await
+        .map_err(|e| format!("Could not reach friendbot at {}: {}", friendbot_url, e))?;
+    if response.status().is_success() {
+        return Ok(Funding::Funded);
+    }
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    if body.contains("op_already_exists") || body.contains("createAccountAlreadyExist") {
+        return Ok(Funding::AlreadyExists);
+    }
+    Err(format!("Friendbot refused to fund {} ({}): {}", account, status, body.trim()).into())
+}
+
+// Horizon can lag friendbot by a ledger or two
+pub async fn wait_for_account(horizon: &HorizonClient, account: &str) -> Result<Account, VaultError> {
+    for _ in 0..WAIT_ATTEMPTS {
+        match horizon.account(account).await {
+            Ok(account) => return Ok(account),
+            Err(e) if e.is_not_found() => tokio::time::sleep(WAIT_INTERVAL).await,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(format!("{} still doesn't exist after {}s", account, WAIT_ATTEMPTS as u64 * WAIT_INTERVAL.as_secs()).into())
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::Deserialize;
+use stellar_xdr::curr::{Hash, Memo};
+
+use crate::error::VaultError;
+use crate::muxed;
+use crate::transaction;
+
+// How long a resolved address is trusted before asking the server again
+const CACHE_TTL: Duration = Duration::from_secs(3600);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+// ============================================================================
+// FEDERATION (SEP-2)
+// ============================================================================
+
+// name*domain.com style addresses
+pub fn is_federation_address(address: &str) -> bool {
+    address.rsplit_once('*').is_some_and(|(name, domain)| !name.is_empty() && domain.contains('.'))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub stellar_address: String,
+    // G... or M...
+    pub account_id: String,
+    // Payments to the address must carry this memo
+    pub memo: Option<Memo>,
+}
+
+#[derive(Deserialize)]
+struct StellarToml {
+    #[serde(rename = "FEDERATION_SERVER")]
+    federation_server: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    #[serde(default)]
+    stellar_address: Option<String>,
+    account_id: String,
+    #[serde(default)]
+    memo_type: Option<String>,
+    // Horizon-style: IDs arrive as strings, hashes as base64
+    #[serde(default)]
+    memo: Option<serde_json::Value>,
+}
+
+fn parse_memo(memo_type: Option<&str>, memo: Option<&serde_json::Value>) -> Result<Option<Memo>, VaultError> {
+    let Some(value) = memo else {
+        return Ok(None);
+    };
+    let value = match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    match memo_type.unwrap_or("text") {
+        "text" => Ok(Some(transaction::text_memo(&value)?)),
+        "id" => Ok(Some(Memo::Id(value.parse().map_err(|_| format!("Invalid memo ID {:?}", value))?))),
+        "hash" => {
+            let bytes: [u8; 32] = BASE64.decode(&value).ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or_else(|| format!("Invalid memo hash {:?}", value))?;
+            Ok(Some(Memo::Hash(Hash(bytes))))
+        }
+        other => Err(format!("Unsupported memo type {:?}", other).into()),
+    }
+}
+
+// Resolves addresses through each domain's federation server, found in its
+// stellar.toml, and remembers the answers for an hour
+pub struct Resolver {
+    http: reqwest::Client,
+    cache: Mutex<HashMap<String, (Record, Instant)>>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Resolver {
+            http: reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build().unwrap_or_default(),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Resolver {
+    pub async fn resolve(&self, address: &str) -> Result<Record, VaultError> {
+        let (_, domain) = address.rsplit_once('*')
+            .filter(|_| is_federation_address(address))
+            .ok_or_else(|| format!("{} is not a federation address (name*domain.com)", address))?;
+        let key = address.to_lowercase();
+        if let Some((record, fetched)) = self.cache.lock().unwrap().get(&key) {
+            if fetched.elapsed() < CACHE_TTL {
+                return Ok(record.clone());
+            }
+        }
+
+        let toml_url = format!("https://{}/.well-known/stellar.toml", domain);
+        let body = self.http.get(&toml_url).send().await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("Could not fetch {}: {}", toml_url, e))?
+            .text().await?;
+        let server = toml::from_str::<StellarToml>(&body)
+            .map_err(|e| format!("Invalid stellar.toml at {}: {}", toml_url, e))?
+            .federation_server
+            .ok_or_else(|| format!("{} has no FEDERATION_SERVER", domain))?;
+        if !server.starts_with("https://") {
+            return Err(format!("Federation server for {} is not https: {}", domain, server).into());
+        }
+
+        let response = self.http.get(&server)
+            .query(&[("q", address), ("type", "name")])
+            .send().await
+            .map_err(|e| format!("Could not reach the federation server for {}: {}", domain, e))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(format!("{} is not a known address", address).into());
+        }
+        let response: Response = response.error_for_status()
+            .map_err(|e| format!("Federation lookup for {} failed: {}", address, e))?
+            .json().await
+            .map_err(|e| format!("Invalid federation response for {}: {}", address, e))?;
+
+        if !muxed::is_valid(&response.account_id) {
+            return Err(format!("Federation server returned an invalid account for {}: {}", address, response.account_id).into());
+        }
+        let record = Record {
+            stellar_address: response.stellar_address.unwrap_or_else(|| address.to_string()),
+            account_id: response.account_id,
+            memo: parse_memo(response.memo_type.as_deref(), response.memo.as_ref())?,
+        };
+        self.cache.lock().unwrap().insert(key, (record.clone(), Instant::now()));
+        Ok(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn federation_records_carry_typed_memos() {
+        assert!(is_federation_address("alice*example.com"));
+        assert!(!is_federation_address("*example.com"));
+        assert!(!is_federation_address("GCZEAWUJY3BRHCOKU6C5WRLCF5RFSGY22UGBPBXWL4T4G4SSEQMIYMCX"));
+
+        let id = serde_json::json!("42");
+        assert_eq!(parse_memo(Some("id"), Some(&id)).unwrap(), Some(Memo::Id(42)));
+        let hash = serde_json::json!(BASE64.encode([9u8; 32]));
+        assert_eq!(parse_memo(Some("hash"), Some(&hash)).unwrap(), Some(Memo::Hash(Hash([9; 32]))));
+        assert_eq!(parse_memo(None, None).unwrap(), None);
+        assert!(parse_memo(Some("return"), Some(&id)).is_err());
+    }
+}
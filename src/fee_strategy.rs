@@ -0,0 +1,136 @@
+use std::fmt;
+
+use crate::error::VaultError;
+use crate::horizon::{FeeStats, HorizonClient};
+use crate::transaction::BASE_FEE;
+
+pub const DEFAULT_FEE_PERCENTILE: u8 = 90;
+// 0.001 XLM per operation
+pub const DEFAULT_MAX_FEE: u32 = 10_000;
+
+// ============================================================================
+// NETWORK FEE STRATEGY
+// ============================================================================
+
+// Bids the fee recent transactions paid at a chosen percentile, never less
+// than the network minimum and never more than the configured cap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeStrategy {
+    pub percentile: u8,
+    // Per operation, in stroops
+    pub max_fee: u32,
+}
+
+impl Default for FeeStrategy {
+    fn default() -> Self {
+        FeeStrategy { percentile: DEFAULT_FEE_PERCENTILE, max_fee: DEFAULT_MAX_FEE }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeEstimate {
+    // Per operation, in stroops
+    pub per_operation: u32,
+    pub percentile: u8,
+    // The percentile fee was above max_fee
+    pub capped: bool,
+    // Horizon couldn't be asked, so this is the base fee
+    pub fallback: bool,
+    // Latest closed ledger the stats cover, when known
+    pub last_ledger: Option<u32>,
+}
+
+impl fmt::Display for FeeEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} stroops per operation", self.per_operation)?;
+        if self.fallback {
+            write!(f, " (base fee; fee stats unavailable)")
+        } else if self.capped {
+            write!(f, " (capped; p{} is higher)", self.percentile)
+        } else {
+            write!(f, " (p{} of recent ledgers)", self.percentile)
+        }
+    }
+}
+
+impl FeeStrategy {
+    pub fn new(percentile: u8, max_fee: u32) -> Result<Self, VaultError> {
+        if !matches!(percentile, 10 | 20 | 30 | 40 | 50 | 60 | 70 | 80 | 90 | 95 | 99) {
+            return Err(format!("Fee percentile must be one of 10, 20, ..., 90, 95 or 99, not {}", percentile).into());
+        }
+        if max_fee < BASE_FEE {
+            return Err(format!("Max fee must be at least the {} stroop base fee", BASE_FEE).into());
+        }
+        Ok(FeeStrategy { percentile, max_fee })
+    }
+
+    pub fn pick(&self, stats: &FeeStats) -> Result<FeeEstimate, VaultError> {
+        let parse = |value: &str| value.parse::<u32>()
+            .map_err(|e| format!("Invalid fee in fee stats {:?}: {}", value, e));
+        let charged = stats.fee_charged.percentile(self.percentile)
+            .ok_or_else(|| format!("Horizon reports no p{} fee", self.percentile))?;
+        let wanted = parse(charged)?
+            .max(parse(&stats.last_ledger_base_fee)?)
+            .max(BASE_FEE);
+
+        Ok(FeeEstimate {
+            per_operation: wanted.min(self.max_fee),
+            percentile: self.percentile,
+            capped: wanted > self.max_fee,
+            fallback: false,
+            last_ledger: stats.last_ledger.parse().ok(),
+        })
+    }
+
+    // Falls back to the base fee when fee stats can't be read, which is what
+    // every transaction bid before this strategy existed
+    pub async fn estimate(&self, horizon: &HorizonClient) -> FeeEstimate {
+        let stats: Result<FeeStats, VaultError> = horizon.fee_stats().await.map_err(|e| e.into());
+        match stats.and_then(|stats| self.pick(&stats)) {
+            Ok(estimate) => estimate,
+            Err(e) => {
+                say!("   ⚠️  Could not estimate the network fee: {}", e);
+                FeeEstimate {
+                    per_operation: BASE_FEE,
+                    percentile: self.percentile,
+                    capped: false,
+                    fallback: true,
+                    last_ledger: None,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(base_fee: &str, p90: &str) -> FeeStats {
+        serde_json::from_value(serde_json::json!({
+            "last_ledger": "1000",
+            "last_ledger_base_fee": base_fee,
+            "ledger_capacity_usage": "0.97",
+            "fee_charged": {
+                "p10": "100", "p20": "100", "p30": "100", "p40": "100", "p50": "100",
+                "p60": "150", "p70": "200", "p80": "400", "p90": p90, "p95": "20000", "p99": "50000",
+            },
+        })).unwrap()
+    }
+
+    #[test]
+    fn fee_follows_the_percentile_within_the_cap() {
+        let strategy = FeeStrategy::new(90, 5_000).unwrap();
+        assert_eq!(strategy.pick(&stats("100", "1200")).unwrap().per_operation, 1_200);
+
+        let capped = strategy.pick(&stats("100", "9000")).unwrap();
+        assert_eq!(capped.per_operation, 5_000);
+        assert!(capped.capped);
+
+        // Never below the current base fee
+        assert_eq!(strategy.pick(&stats("300", "100")).unwrap().per_operation, 300);
+
+        assert!(FeeStrategy::new(85, 5_000).is_err());
+        assert!(FeeStrategy::new(90, 50).is_err());
+    }
+}
@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+
+use crate::amount::{MathError, Rounding, Stroops, BPS_DENOMINATOR};
+use crate::{Vault, SECONDS_PER_YEAR};
+
+// ============================================================================
+// FEE CONFIG & ACCRUAL
+// ============================================================================
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FeeConfig {
+    // Annualized, charged continuously on TVL
+    pub management_fee_bps: u16,
+    // Charged on each harvest's gross yield
+    pub performance_fee_bps: u16,
+}
+
+// Fees accrue here until they are collected to the treasury
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FeeAccrual {
+    pub management_accrued: u64,
+    pub performance_accrued: u64,
+    pub total_collected: u64,
+    pub last_accrual: u64,
+}
+
+impl FeeAccrual {
+    pub fn outstanding(&self) -> u64 {
+        self.management_accrued + self.performance_accrued
+    }
+}
+
+// Rounds down: it accrues on every operation, so rounding up would overcharge
+pub fn management_fee(tvl: u64, fee_bps: u16, elapsed_secs: u64) -> Result<u64, MathError> {
+    let numerator = (tvl as u128)
+        .checked_mul(fee_bps as u128 * elapsed_secs as u128)
+        .ok_or(MathError::Overflow)?;
+    let fee = numerator / (BPS_DENOMINATOR as u128 * SECONDS_PER_YEAR as u128);
+    u64::try_from(fee).map_err(|_| MathError::Overflow)
+}
+
+pub fn performance_fee(gross_yield: u64, fee_bps: u16) -> Result<u64, MathError> {
+    Stroops(gross_yield).bps(fee_bps, Rounding::Up).map(|fee| fee.0.min(gross_yield))
+}
+
+// Management fee earned since the last accrual but not yet deducted from TVL
+pub fn pending_management_fee(vault: &Vault, now: u64) -> Result<u64, MathError> {
+    let last = vault.fee_accrual.last_accrual;
+    if last == 0 {
+        return Ok(0);
+    }
+    management_fee(vault.total_value, vault.fees.management_fee_bps, now.saturating_sub(last))
+        .map(|fee| fee.min(vault.total_value))
+}
+
+// Deducts the management fee for the time since the last accrual from vault
+// TVL, so it shows up as a slightly lower share price for every holder
+pub fn accrue_management_fee(vault: &mut Vault, now: u64) -> Result<u64, MathError> {
+    // pending_management_fee caps the fee at TVL, so only the accrued
+    // balance needs checking before the deduction
+    let fee = pending_management_fee(vault, now)?;
+    Stroops(vault.fee_accrual.management_accrued).checked_add(Stroops(fee))?;
+    vault.fee_accrual.last_accrual = now;
+    if fee == 0 {
+        return Ok(0);
+    }
+
+    deduct_management_fee(vault, fee);
+    Ok(fee)
+}
+
+// Moves an already computed fee out of TVL and into the accrued balance;
+// callers check that the vault holds at least `fee`
+pub fn deduct_management_fee(vault: &mut Vault, fee: u64) {
+    vault.total_value -= fee;
+    for strategy in &mut vault.strategies {
+        let share = (fee as u128 * strategy.allocation_percentage as u128 / 100) as u64;
+        strategy.total_allocated = strategy.total_allocated.saturating_sub(share);
+    }
+    vault.fee_accrual.management_accrued += fee;
+}
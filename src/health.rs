@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use serde::Serialize;
+
+// A dependency slower than this counts as down
+pub const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+// ============================================================================
+// READINESS CHECKS
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Ok,
+    Down,
+    // Not used by this deployment, so it can't hold readiness back
+    NotConfigured,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub status: CheckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Check {
+    pub fn not_configured() -> Self {
+        Check { status: CheckStatus::NotConfigured, latency_ms: None, error: None }
+    }
+
+    pub fn down(error: impl Display) -> Self {
+        Check { status: CheckStatus::Down, latency_ms: None, error: Some(error.to_string()) }
+    }
+}
+
+// Runs one probe, timing it and giving up after CHECK_TIMEOUT
+pub async fn check<T, E: Display>(probe: impl Future<Output = Result<T, E>>) -> Check {
+    let started = Instant::now();
+    let result = tokio::time::timeout(CHECK_TIMEOUT, probe).await;
+    let latency_ms = Some(started.elapsed().as_millis() as u64);
+    match result {
+        Ok(Ok(_)) => Check { status: CheckStatus::Ok, latency_ms, error: None },
+        Ok(Err(e)) => Check { latency_ms, ..Check::down(e) },
+        Err(_) => Check { latency_ms, ..Check::down(format!("no answer within {}s", CHECK_TIMEOUT.as_secs())) },
+    }
+}
+
+// Each dependency's check, by name
+#[derive(Debug, Default, Serialize)]
+pub struct Readiness {
+    pub checks: BTreeMap<&'static str, Check>,
+}
+
+impl Readiness {
+    pub fn add(&mut self, name: &'static str, check: Check) {
+        self.checks.insert(name, check);
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.checks.values().all(|check| check.status != CheckStatus::Down)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "status": if self.is_ready() { "ready" } else { "degraded" },
+            "checks": self.checks,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn one_failing_dependency_degrades_readiness() {
+        let mut readiness = Readiness::default();
+        readiness.add("horizon", check(async { Ok::<_, String>(()) }).await);
+        readiness.add("soroban_rpc", Check::not_configured());
+        assert!(readiness.is_ready());
+
+        let failed = check(async { Err::<(), _>("read-only file system") }).await;
+        assert_eq!(failed.status, CheckStatus::Down);
+        readiness.add("storage", failed);
+        assert!(!readiness.is_ready());
+        assert_eq!(readiness.to_json()["status"], "degraded");
+        assert_eq!(readiness.to_json()["checks"]["soroban_rpc"]["status"], "not_configured");
+    }
+}
@@ -0,0 +1,865 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use serde::de::DeserializeOwned;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::cache::TtlCache;
+use crate::circuit_breaker::{BreakerPolicy, BreakerState, DependencyBreaker};
+use crate::error::VaultError;
+use crate::failover::{Endpoints, FailoverStatus, SLOW_RESPONSE};
+use crate::mock_ledger::MockLedger;
+use crate::network::Network;
+use crate::retry::RetryPolicy;
+
+// ============================================================================
+// ERRORS
+// ============================================================================
+
+#[derive(Debug, Error)]
+pub enum HorizonError {
+    #[error("Horizon request failed: {0}")]
+    Http(reqwest::Error),
+    // Horizon's problem+json body for non-2xx responses
+    #[error("Horizon {status} {title}: {detail}{}", .result_codes.as_ref().map(|codes| format!(" (result codes: {})", codes)).unwrap_or_default())]
+    Problem {
+        status: u16,
+        title: String,
+        detail: String,
+        result_codes: Option<serde_json::Value>,
+    },
+    // Not sent: the circuit breaker is open after repeated failures
+    #[error("Network degraded: Horizon failed {failures} times in a row, so nothing is sent to it for now; try again in {retry_in_secs}s")]
+    Degraded { failures: u32, retry_in_secs: u64 },
+}
+
+impl HorizonError {
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            HorizonError::Http(e) => e.status().map(|s| s.as_u16()),
+            HorizonError::Problem { status, .. } => Some(*status),
+            HorizonError::Degraded { .. } => None,
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.status() == Some(404)
+    }
+
+    // Transaction-level result code of a rejected submission, e.g. "tx_bad_seq"
+    pub fn transaction_code(&self) -> Option<&str> {
+        match self {
+            HorizonError::Problem { result_codes: Some(codes), .. } => codes.get("transaction")?.as_str(),
+            _ => None,
+        }
+    }
+
+    // Connection failures, rate limits, server errors and Horizon's 504 when
+    // a submission isn't in a ledger yet: none of these say the transaction
+    // was rejected
+    pub fn is_transient(&self) -> bool {
+        match self {
+            HorizonError::Http(e) => e.status().is_none_or(|s| s.as_u16() == 429 || s.is_server_error()),
+            HorizonError::Problem { status, .. } => *status == 429 || *status >= 500,
+            // Waiting out the cooldown is the caller's call, not a retry's
+            HorizonError::Degraded { .. } => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for HorizonError {
+    fn from(e: reqwest::Error) -> Self {
+        HorizonError::Http(e)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Problem {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    detail: String,
+    #[serde(default)]
+    extras: Option<ProblemExtras>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProblemExtras {
+    result_codes: Option<serde_json::Value>,
+}
+
+// ============================================================================
+// RESOURCES
+// ============================================================================
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Account {
+    #[serde(default)]
+    pub account_id: String,
+    // Horizon encodes the 64-bit sequence number as a string
+    pub sequence: String,
+    pub balances: Vec<Balance>,
+    #[serde(default)]
+    pub thresholds: Thresholds,
+    // Includes the master key, with the master weight
+    #[serde(default)]
+    pub signers: Vec<AccountSigner>,
+    // Data entries, values base64 encoded
+    #[serde(default)]
+    pub data: HashMap<String, String>,
+    // Where the account's stellar.toml is published
+    #[serde(default)]
+    pub home_domain: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct Thresholds {
+    pub low_threshold: u8,
+    pub med_threshold: u8,
+    pub high_threshold: u8,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AccountSigner {
+    pub key: String,
+    pub weight: u8,
+    // ed25519_public_key, sha256_hash, preauth_tx or ed25519_signed_payload
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+impl Account {
+    pub fn sequence_number(&self) -> Result<i64, VaultError> {
+        self.sequence.parse()
+            .map_err(|e| format!("Invalid sequence number {:?}: {}", self.sequence, e).into())
+    }
+
+    // SEP-29: exchanges and other shared accounts set config.memo_required
+    // to "1" so payments without a memo are refused rather than lost
+    pub fn memo_required(&self) -> bool {
+        self.data.get(MEMO_REQUIRED_KEY)
+            .and_then(|value| BASE64.decode(value).ok())
+            .is_some_and(|value| value == b"1")
+    }
+}
+
+// A balance parked on the ledger for its claimants to take
+// One route Horizon found for a strict-send path payment
+#[derive(Debug, Clone, Deserialize)]
+pub struct PaymentPath {
+    pub destination_amount: String,
+    // Assets traded through between the source and destination, in order
+    pub path: Vec<AssetRecord>,
+}
+
+// An asset as Horizon spells it out in paths and offers
+#[derive(Debug, Clone, Deserialize)]
+pub struct AssetRecord {
+    pub asset_type: String,
+    #[serde(default)]
+    pub asset_code: Option<String>,
+    #[serde(default)]
+    pub asset_issuer: Option<String>,
+}
+
+// An open offer on the DEX
+#[derive(Debug, Clone, Deserialize)]
+pub struct Offer {
+    // A number, but kept as Horizon sends it
+    #[serde(deserialize_with = "string_or_number")]
+    pub id: u64,
+    pub selling: AssetRecord,
+    pub buying: AssetRecord,
+    pub amount: String,
+    pub price_r: PriceRatio,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct PriceRatio {
+    pub n: i32,
+    pub d: i32,
+}
+
+// Bids buy the selling asset and asks sell it; prices are in the buying
+// asset, best first
+#[derive(Debug, Clone, Deserialize)]
+pub struct OrderBook {
+    pub bids: Vec<PriceLevel>,
+    pub asks: Vec<PriceLevel>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PriceLevel {
+    pub price: String,
+}
+
+// A fill on the DEX. base_account sent base_amount of the base asset to
+// counter_account in exchange for counter_amount of the counter asset; the
+// accounts are missing when a liquidity pool took a side.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Trade {
+    pub paging_token: String,
+    #[serde(default)]
+    pub base_account: Option<String>,
+    pub base_amount: String,
+    pub base_asset_type: String,
+    #[serde(default)]
+    pub base_asset_code: Option<String>,
+    #[serde(default)]
+    pub base_asset_issuer: Option<String>,
+    #[serde(default)]
+    pub counter_account: Option<String>,
+    pub counter_amount: String,
+    pub counter_asset_type: String,
+    #[serde(default)]
+    pub counter_asset_code: Option<String>,
+    #[serde(default)]
+    pub counter_asset_issuer: Option<String>,
+}
+
+// A constant-product pool; total_shares and reserve amounts are decimals
+#[derive(Debug, Clone, Deserialize)]
+pub struct LiquidityPool {
+    pub id: String,
+    pub total_shares: String,
+    pub reserves: Vec<Reserve>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Reserve {
+    // "native" or CODE:ISSUER
+    pub asset: String,
+    pub amount: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClaimableBalance {
+    pub id: String,
+    // "native" or CODE:ISSUER
+    pub asset: String,
+    pub amount: String,
+    // Whoever created it, unless the reserve was transferred
+    #[serde(default)]
+    pub sponsor: Option<String>,
+    #[serde(default)]
+    pub last_modified_time: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Balance {
+    pub balance: String,
+    pub asset_type: String,
+    pub asset_code: Option<String>,
+    pub asset_issuer: Option<String>,
+    // Trustlines only
+    #[serde(default)]
+    pub limit: Option<String>,
+    // Pool share trustlines only
+    #[serde(default)]
+    pub liquidity_pool_id: Option<String>,
+}
+
+impl Balance {
+    pub fn is_native(&self) -> bool {
+        self.asset_type == "native"
+    }
+}
+
+// A payment-like operation from /accounts/{id}/payments
+#[derive(Debug, Clone, Deserialize)]
+pub struct Payment {
+    // Cursor to resume a stream after this record
+    pub paging_token: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub created_at: String,
+    pub transaction_hash: String,
+    #[serde(default)]
+    pub transaction_successful: bool,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    // Set when the payment used muxed accounts; `from` and `to` are always
+    // the underlying G... accounts
+    #[serde(default)]
+    pub from_muxed: Option<String>,
+    #[serde(default)]
+    pub to_muxed_id: Option<String>,
+    pub amount: Option<String>,
+    pub asset_type: Option<String>,
+    pub asset_code: Option<String>,
+    #[serde(default)]
+    pub asset_issuer: Option<String>,
+    // Only present when requested with join=transactions
+    #[serde(default)]
+    pub transaction: Option<Transaction>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Transaction {
+    pub hash: String,
+    pub ledger: u32,
+    pub created_at: String,
+    // Older Horizon versions return this as a number
+    #[serde(deserialize_with = "string_or_number")]
+    pub fee_charged: u64,
+    pub successful: bool,
+    #[serde(default)]
+    pub memo_type: String,
+    pub memo: Option<String>,
+    // TransactionResult XDR, with what each operation actually did
+    #[serde(default)]
+    pub result_xdr: String,
+}
+
+// Query parameters naming "native" or CODE:ISSUER under the given type,
+// code and issuer keys
+fn asset_query(asset: &str, keys: [&'static str; 3]) -> Vec<(&'static str, String)> {
+    match asset.split_once(':') {
+        Some((code, issuer)) => {
+            let asset_type = if code.len() <= 4 { "credit_alphanum4" } else { "credit_alphanum12" };
+            vec![(keys[0], asset_type.to_string()), (keys[1], code.to_string()), (keys[2], issuer.to_string())]
+        }
+        None => vec![(keys[0], "native".to_string())],
+    }
+}
+
+fn string_or_number<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(s) => s.parse().map_err(serde::de::Error::custom),
+        serde_json::Value::Number(n) => n.as_u64().ok_or_else(|| serde::de::Error::custom("fee is not a u64")),
+        other => Err(serde::de::Error::custom(format!("unexpected fee value {}", other))),
+    }
+}
+
+// Fee percentiles over recent ledgers, in stroops (as strings)
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeeStats {
+    pub last_ledger: String,
+    pub last_ledger_base_fee: String,
+    pub ledger_capacity_usage: String,
+    pub fee_charged: FeeDistribution,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeeDistribution {
+    pub p10: String,
+    pub p20: String,
+    pub p30: String,
+    pub p40: String,
+    pub p50: String,
+    pub p60: String,
+    pub p70: String,
+    pub p80: String,
+    pub p90: String,
+    pub p95: String,
+    pub p99: String,
+}
+
+impl FeeDistribution {
+    // Horizon reports p10 to p90 in steps of ten, plus p95 and p99
+    pub fn percentile(&self, percentile: u8) -> Option<&str> {
+        let value = match percentile {
+            10 => &self.p10,
+            20 => &self.p20,
+            30 => &self.p30,
+            40 => &self.p40,
+            50 => &self.p50,
+            60 => &self.p60,
+            70 => &self.p70,
+            80 => &self.p80,
+            90 => &self.p90,
+            95 => &self.p95,
+            99 => &self.p99,
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Page<T> {
+    #[serde(rename = "_embedded")]
+    embedded: Embedded<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Embedded<T> {
+    records: Vec<T>,
+}
+
+// ============================================================================
+// CLIENT
+// ============================================================================
+
+const MEMO_REQUIRED_KEY: &str = "config.memo_required";
+// Horizon holds a submission for up to 30 seconds before answering 504
+const SUBMIT_TIMEOUT: Duration = Duration::from_secs(45);
+// How long account records and fee stats are reused: about a ledger, so one
+// operation's checks and its transaction share a single fetch
+pub const ACCOUNT_TTL: Duration = Duration::from_secs(5);
+pub const FEE_STATS_TTL: Duration = Duration::from_secs(10);
+
+// Where calls go and whether they are being let through at all
+#[derive(Debug, Clone, Serialize)]
+pub struct HorizonStatus {
+    #[serde(flatten)]
+    pub endpoints: FailoverStatus,
+    pub breaker: BreakerState,
+}
+
+#[derive(Debug, Clone)]
+pub struct HorizonClient {
+    // The configured URL first, then any fallbacks
+    endpoints: Endpoints,
+    http: reqwest::Client,
+    retry: RetryPolicy,
+    // Shared by clones; every submission clears the accounts
+    accounts: TtlCache<String, Account>,
+    fee_stats: TtlCache<(), FeeStats>,
+    breaker: DependencyBreaker,
+    // Answers every request instead of Horizon on the offline network
+    mock: Option<MockLedger>,
+}
+
+impl HorizonClient {
+    pub fn new(base_url: &str) -> Self {
+        let retry = RetryPolicy::default();
+        HorizonClient {
+            endpoints: Endpoints::new(vec![base_url.to_string()]),
+            http: retry.http_client(),
+            retry,
+            accounts: TtlCache::new(ACCOUNT_TTL),
+            fee_stats: TtlCache::new(FEE_STATS_TTL),
+            breaker: DependencyBreaker::new(BreakerPolicy::default()),
+            mock: None,
+        }
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.http = retry.http_client();
+        self.retry = retry;
+        self
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry
+    }
+
+    // Servers to fail over to, in order, when the primary keeps failing or
+    // answering slowly
+    pub fn with_fallbacks(mut self, urls: &[String]) -> Self {
+        let mut all = vec![self.endpoints.primary()];
+        all.extend_from_slice(urls);
+        self.endpoints = Endpoints::new(all);
+        self
+    }
+
+    pub fn with_breaker(mut self, policy: BreakerPolicy) -> Self {
+        self.breaker = DependencyBreaker::new(policy);
+        self
+    }
+
+    pub fn endpoints(&self) -> &Endpoints {
+        &self.endpoints
+    }
+
+    pub fn status(&self) -> HorizonStatus {
+        HorizonStatus { endpoints: self.endpoints.status(), breaker: self.breaker.state(Instant::now()) }
+    }
+
+    // Fails while the breaker is open, without using up the probe it lets
+    // through once cooled down; for checking before an operation starts
+    pub fn available(&self) -> Result<(), HorizonError> {
+        match self.breaker.state(Instant::now()) {
+            BreakerState::Open => self.admit(),
+            _ => Ok(()),
+        }
+    }
+
+    fn admit(&self) -> Result<(), HorizonError> {
+        self.breaker.allow(Instant::now()).map_err(|wait| HorizonError::Degraded {
+            failures: self.breaker.failures(),
+            retry_in_secs: wait.as_secs() + u64::from(wait.subsec_nanos() > 0),
+        })
+    }
+
+    // The network's Horizon, or the shared mock ledger when offline
+    pub fn for_network(network: &Network) -> Self {
+        let mut client = Self::new(network.horizon_url());
+        if network.is_offline() {
+            client.mock = Some(MockLedger::shared());
+        }
+        client
+    }
+
+    pub fn mock(&self) -> Option<&MockLedger> {
+        self.mock.as_ref()
+    }
+
+    async fn parse<T: DeserializeOwned>(response: reqwest::Response) -> Result<T, HorizonError> {
+        if response.status().is_success() {
+            return Ok(response.json().await?);
+        }
+        Err(Self::problem(response).await)
+    }
+
+    async fn problem(response: reqwest::Response) -> HorizonError {
+        let status = response.status();
+        let problem: Option<Problem> = response.json().await.ok();
+        HorizonError::Problem {
+            status: status.as_u16(),
+            title: problem.as_ref().map(|p| p.title.clone()).unwrap_or_default(),
+            detail: problem.as_ref().map(|p| p.detail.clone()).unwrap_or_default(),
+            result_codes: problem.and_then(|p| p.extras).and_then(|e| e.result_codes),
+        }
+    }
+
+    // Failures that say Horizon is unwell rather than that the request was wrong
+    fn outcome<T>(result: &Result<T, HorizonError>) -> Result<(), String> {
+        match result {
+            Err(e) if e.is_transient() => Err(e.to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    // Counts a call against the endpoint that answered it, so repeated
+    // failures or slow answers move later calls to a fallback, and against
+    // the breaker, which stops calls altogether when every endpoint fails
+    fn record(&self, url: &str, took: Duration, outcome: Result<(), String>) {
+        match &outcome {
+            Ok(()) => if self.breaker.succeeded() {
+                tracing::info!(endpoint = url, "horizon recovered; circuit breaker closed");
+            },
+            Err(e) => if self.breaker.failed(Instant::now()) {
+                tracing::warn!(endpoint = url, failures = self.breaker.failures(), error = %e, "horizon circuit breaker open");
+            },
+        }
+        self.endpoints.record(url, took, outcome);
+    }
+
+    // While on a fallback, tries the primary's root now and then and goes
+    // back to it once it answers promptly
+    async fn probe_primary(&self) {
+        let Some(primary) = self.endpoints.probe_due(Instant::now()) else {
+            return;
+        };
+        let started = Instant::now();
+        let answered = self.http.get(format!("{}/", primary))
+            .timeout(SLOW_RESPONSE)
+            .send()
+            .await
+            .is_ok_and(|response| response.status().is_success());
+        if answered && started.elapsed() < SLOW_RESPONSE {
+            self.endpoints.recovered();
+        }
+    }
+
+    // Reads are safe to repeat, so transient failures are retried, each
+    // attempt on whichever endpoint is active by then
+    async fn get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, String)]) -> Result<T, HorizonError> {
+        self.probe_primary().await;
+        self.retry.run(path, HorizonError::is_transient, || async {
+            self.admit()?;
+            let base_url = self.endpoints.active();
+            let started = Instant::now();
+            let result = async {
+                let response = self.http.get(format!("{}{}", base_url, path))
+                    .query(query)
+                    .timeout(self.retry.request_timeout)
+                    .send()
+                    .await?;
+                Self::parse(response).await
+            }.await;
+            self.record(&base_url, started.elapsed(), Self::outcome(&result));
+            result
+        }).await
+    }
+
+    // Served from the cache for up to ACCOUNT_TTL. Submissions and payments
+    // seen on a stream clear it; changes made elsewhere show up once it
+    // expires.
+    pub async fn account(&self, account_id: &str) -> Result<Account, HorizonError> {
+        if let Some(account) = self.accounts.get(&account_id.to_string()) {
+            return Ok(account);
+        }
+        self.fresh_account(account_id).await
+    }
+
+    // Skips the cache, for sequence numbers and health checks, and refreshes it
+    pub async fn fresh_account(&self, account_id: &str) -> Result<Account, HorizonError> {
+        if let Some(mock) = &self.mock {
+            return mock.account(account_id);
+        }
+        let ticket = self.accounts.ticket();
+        let account: Account = self.get(&format!("/accounts/{}", account_id), &[]).await?;
+        self.accounts.insert(ticket, account_id.to_string(), account.clone());
+        Ok(account)
+    }
+
+    // Drops every cached account, for transactions sent some other way, such
+    // as contract calls through Soroban RPC
+    pub fn invalidate_accounts(&self) {
+        self.accounts.clear();
+    }
+
+    // Whether payments to `destination` must carry a memo, by its data entry
+    // or its home domain's stellar.toml. Muxed addresses already identify the
+    // recipient, and an account that doesn't exist yet can't have asked for one.
+    pub async fn memo_required(&self, destination: &str) -> Result<bool, HorizonError> {
+        if destination.starts_with('M') {
+            return Ok(false);
+        }
+        let account = match self.account(destination).await {
+            Ok(account) => account,
+            Err(e) if e.is_not_found() => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        if account.memo_required() {
+            return Ok(true);
+        }
+        match account.home_domain.as_deref().filter(|domain| !domain.is_empty()) {
+            Some(domain) if self.mock.is_none() => Ok(self.listed_as_memo_required(domain, destination).await),
+            _ => Ok(false),
+        }
+    }
+
+    // A stellar.toml that can't be fetched lists nothing; the data entry is
+    // what SEP-29 relies on
+    async fn listed_as_memo_required(&self, domain: &str, account: &str) -> bool {
+        let url = format!("https://{}/.well-known/stellar.toml", domain);
+        let body = match self.http.get(&url).send().await.and_then(|r| r.error_for_status()) {
+            Ok(response) => response.text().await,
+            Err(e) => Err(e),
+        };
+        match body {
+            Ok(body) => memo_required_by_toml(&body, account),
+            Err(e) => {
+                tracing::warn!(url = %url, error = %e, "could not fetch stellar.toml for the memo check");
+                false
+            }
+        }
+    }
+
+    // Most recent first, each with its transaction
+    pub async fn payments(&self, account_id: &str, limit: u32) -> Result<Vec<Payment>, HorizonError> {
+        if let Some(mock) = &self.mock {
+            return Ok(mock.payments(account_id, limit));
+        }
+        let query = [("order", "desc".to_string()), ("limit", limit.to_string()), ("join", "transactions".to_string())];
+        let page: Page<Payment> = self.get(&format!("/accounts/{}/payments", account_id), &query).await?;
+        Ok(page.embedded.records)
+    }
+
+    pub async fn claimable_balances(&self, claimant: &str, limit: u32) -> Result<Vec<ClaimableBalance>, HorizonError> {
+        if self.mock.is_some() {
+            return Ok(Vec::new());
+        }
+        let query = [("claimant", claimant.to_string()), ("limit", limit.to_string())];
+        let page: Page<ClaimableBalance> = self.get("/claimable_balances", &query).await?;
+        Ok(page.embedded.records)
+    }
+
+    // Routes for sending `source_amount` of `source_asset` that end in
+    // `destination_asset`; assets are "native" or CODE:ISSUER
+    pub async fn strict_send_paths(&self, source_asset: &str, source_amount: &str, destination_asset: &str) -> Result<Vec<PaymentPath>, HorizonError> {
+        // The mock ledger has no DEX to route through
+        if self.mock.is_some() {
+            return Ok(Vec::new());
+        }
+        let mut query = vec![
+            ("source_amount", source_amount.to_string()),
+            ("destination_assets", destination_asset.to_string()),
+        ];
+        query.extend(asset_query(source_asset, ["source_asset_type", "source_asset_code", "source_asset_issuer"]));
+        let page: Page<PaymentPath> = self.get("/paths/strict-send", &query).await?;
+        Ok(page.embedded.records)
+    }
+
+    pub async fn liquidity_pool(&self, pool_id: &str) -> Result<LiquidityPool, HorizonError> {
+        if self.mock.is_some() {
+            return Err(HorizonError::Problem { status: 404, title: "Resource Missing".into(),
+                detail: format!("Liquidity pool {} is not on the mock ledger", pool_id), result_codes: None });
+        }
+        self.get(&format!("/liquidity_pools/{}", pool_id), &[]).await
+    }
+
+    pub async fn offers(&self, account_id: &str) -> Result<Vec<Offer>, HorizonError> {
+        if self.mock.is_some() {
+            return Ok(Vec::new());
+        }
+        let query = [("limit", "200".to_string())];
+        let page: Page<Offer> = self.get(&format!("/accounts/{}/offers", account_id), &query).await?;
+        Ok(page.embedded.records)
+    }
+
+    pub async fn order_book(&self, selling_asset: &str, buying_asset: &str) -> Result<OrderBook, HorizonError> {
+        if self.mock.is_some() {
+            return Ok(OrderBook { bids: Vec::new(), asks: Vec::new() });
+        }
+        let mut query = asset_query(selling_asset, ["selling_asset_type", "selling_asset_code", "selling_asset_issuer"]);
+        query.extend(asset_query(buying_asset, ["buying_asset_type", "buying_asset_code", "buying_asset_issuer"]));
+        self.get("/order_book", &query).await
+    }
+
+    // Oldest first, after `cursor` when given
+    pub async fn trades(&self, account_id: &str, cursor: Option<&str>, limit: u32) -> Result<Vec<Trade>, HorizonError> {
+        if self.mock.is_some() {
+            return Ok(Vec::new());
+        }
+        let mut query = vec![("order", "asc".to_string()), ("limit", limit.to_string())];
+        if let Some(cursor) = cursor {
+            query.push(("cursor", cursor.to_string()));
+        }
+        let page: Page<Trade> = self.get(&format!("/accounts/{}/trades", account_id), &query).await?;
+        Ok(page.embedded.records)
+    }
+
+    // Follows the account's payments as Server-Sent Events, oldest first,
+    // starting after `cursor` ("now" skips history). Each payment carries its
+    // transaction so memos can be read. Returns when the server closes the
+    // stream; callers resume from the last paging_token they saw.
+    pub async fn stream_payments(&self, account_id: &str, cursor: &str, mut on_payment: impl FnMut(Payment)) -> Result<(), HorizonError> {
+        if let Some(mock) = &self.mock {
+            return mock.stream_payments(account_id, cursor, on_payment).await;
+        }
+        self.admit()?;
+        self.probe_primary().await;
+        let base_url = self.endpoints.active();
+        let started = Instant::now();
+        let connected = match self.http.get(format!("{}/accounts/{}/payments", base_url, account_id))
+            .header(reqwest::header::ACCEPT, "text/event-stream")
+            .query(&[("cursor", cursor), ("join", "transactions")])
+            .send()
+            .await
+        {
+            Ok(response) if response.status().is_success() => Ok(response),
+            Ok(response) => Err(Self::problem(response).await),
+            Err(e) => Err(e.into()),
+        };
+        self.record(&base_url, started.elapsed(), Self::outcome(&connected));
+        let mut response = connected?;
+
+        let mut buffer: Vec<u8> = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            buffer.extend_from_slice(&chunk);
+            while let Some(end) = buffer.windows(2).position(|w| w == b"\n\n") {
+                let event: Vec<u8> = buffer.drain(..end + 2).collect();
+                let data = String::from_utf8_lossy(&event).lines()
+                    .filter_map(|line| line.strip_prefix("data:"))
+                    .map(str::trim)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                // Horizon opens with "hello", closes with "byebye" and
+                // sends keep-alive comments with no data
+                if let Ok(payment) = serde_json::from_str::<Payment>(&data) {
+                    self.accounts.clear();
+                    on_payment(payment);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn transaction(&self, hash: &str) -> Result<Transaction, HorizonError> {
+        if let Some(mock) = &self.mock {
+            return mock.transaction(hash);
+        }
+        self.get(&format!("/transactions/{}", hash), &[]).await
+    }
+
+    // Submits a signed, base64-encoded TransactionEnvelope and waits for it
+    // to be included in a ledger. Tried once: a failure can leave it unclear
+    // whether the transaction applied, which only the submitter can settle.
+    pub async fn submit_transaction(&self, envelope_xdr: &str) -> Result<Transaction, HorizonError> {
+        if let Some(mock) = &self.mock {
+            return mock.submit_transaction(envelope_xdr);
+        }
+        self.admit()?;
+        self.probe_primary().await;
+        let base_url = self.endpoints.active();
+        let result = async {
+            let response = self.http.post(format!("{}/transactions", base_url))
+                .form(&[("tx", envelope_xdr)])
+                .timeout(self.retry.request_timeout.max(SUBMIT_TIMEOUT))
+                .send()
+                .await?;
+            Self::parse(response).await
+        }.await;
+        // Horizon holds a submission until it lands, so its time says
+        // nothing about how slow the server is
+        let outcome = match &result {
+            Err(e) if e.is_transient() && e.status() != Some(504) => Err(e.to_string()),
+            _ => Ok(()),
+        };
+        self.record(&base_url, Duration::ZERO, outcome);
+        // Even a failed attempt may have applied, so nothing cached is trusted
+        self.accounts.clear();
+        result
+    }
+
+    pub async fn fee_stats(&self) -> Result<FeeStats, HorizonError> {
+        if let Some(mock) = &self.mock {
+            return Ok(mock.fee_stats());
+        }
+        if let Some(stats) = self.fee_stats.get(&()) {
+            return Ok(stats);
+        }
+        let ticket = self.fee_stats.ticket();
+        let stats: FeeStats = self.get("/fee_stats", &[]).await?;
+        self.fee_stats.insert(ticket, (), stats.clone());
+        Ok(stats)
+    }
+}
+
+// The accounts an exchange's stellar.toml says need a memo, for those that
+// publish it there instead of setting the data entry
+#[derive(Deserialize)]
+struct StellarToml {
+    #[serde(rename = "MEMO_REQUIRED_ACCOUNTS", default)]
+    memo_required_accounts: Vec<String>,
+}
+
+// Whether a stellar.toml lists `account` under MEMO_REQUIRED_ACCOUNTS
+fn memo_required_by_toml(stellar_toml: &str, account: &str) -> bool {
+    toml::from_str::<StellarToml>(stellar_toml)
+        .is_ok_and(|parsed| parsed.memo_required_accounts.iter().any(|listed| listed == account))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::Keypair;
+
+    fn account(data: &[(&str, &str)]) -> Account {
+        serde_json::from_value(serde_json::json!({
+            "sequence": "1",
+            "balances": [],
+            "data": data.iter().cloned().collect::<HashMap<_, _>>(),
+        })).unwrap()
+    }
+
+    #[tokio::test]
+    async fn memo_requirements_follow_sep29() {
+        // Data entry values are base64: "MQ==" is "1", "MA==" is "0"
+        assert!(account(&[(MEMO_REQUIRED_KEY, "MQ==")]).memo_required());
+        assert!(!account(&[(MEMO_REQUIRED_KEY, "MA==")]).memo_required());
+        assert!(!account(&[(MEMO_REQUIRED_KEY, "not base64")]).memo_required());
+        assert!(!account(&[]).memo_required());
+
+        let exchange = Keypair::from_seed(&[50; 32]).public_key();
+        let listed = format!("ACCOUNTS = [\"{0}\"]\nMEMO_REQUIRED_ACCOUNTS = [\"{0}\"]\n", exchange);
+        assert!(memo_required_by_toml(&listed, &exchange));
+        assert!(!memo_required_by_toml(&format!("ACCOUNTS = [\"{}\"]\n", exchange), &exchange));
+        assert!(!memo_required_by_toml("not = [toml", &exchange));
+
+        // Muxed addresses name their recipient, and a missing account has
+        // asked for nothing
+        let horizon = HorizonClient::for_network(&Network::Offline);
+        let sub_account = crate::muxed::address(&exchange, 7).unwrap();
+        assert!(!horizon.memo_required(&sub_account).await.unwrap());
+        assert!(!horizon.memo_required(&exchange).await.unwrap());
+    }
+}
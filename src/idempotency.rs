@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::VaultError;
+
+pub const DEFAULT_IDEMPOTENCY_WINDOW_SECS: u64 = 86_400;
+// Longer keys are refused; UUIDs and request hashes fit easily
+pub const MAX_KEY_LEN: usize = 255;
+
+// ============================================================================
+// IDEMPOTENCY KEYS
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Outcome {
+    // Saved before the request runs. `deposit_id` is the record the deposit
+    // made, set in the same save that made it: while that record shows no
+    // payment went out, the request can safely run again. A request that
+    // fails before making one drops its key instead.
+    Started { deposit_id: Option<u64> },
+    Succeeded { reply: Value },
+}
+
+// What a key says about the deposit sent with it
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyedDeposit {
+    // Nothing went out under the key, so the deposit can run
+    Run,
+    // The first answer, for a deposit that went through
+    Replay(Value),
+    // One may have gone out; the reason says where to look
+    Conflict(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyedRequest {
+    pub key: String,
+    // The account whose funds the request moves; keys are only unique per account
+    pub account: String,
+    // What was asked, so a key can't be reused for a different request
+    pub request: String,
+    pub outcome: Outcome,
+    pub at: u64,
+}
+
+// Client-chosen keys for requests that move funds, each with what became of
+// its request. A request repeating a key within the window gets the first
+// one's answer instead of running again.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IdempotencyBook {
+    requests: Vec<KeyedRequest>,
+}
+
+// Keys are opaque to the vault, but have to fit in a header and a log line
+pub fn check_key(key: &str) -> Result<(), VaultError> {
+    if key.is_empty() || key.len() > MAX_KEY_LEN || !key.chars().all(|c| c.is_ascii_graphic()) {
+        return Err(VaultError::Validation(format!(
+            "Idempotency keys are 1 to {} printable ASCII characters without spaces", MAX_KEY_LEN)));
+    }
+    Ok(())
+}
+
+impl IdempotencyBook {
+    // The earlier request with this key, unless it has aged out of `window`;
+    // an error if that request asked for something else
+    pub fn lookup(&self, account: &str, key: &str, request: &str, now: u64, window: u64) -> Result<Option<&KeyedRequest>, VaultError> {
+        let found = self.requests.iter()
+            .find(|r| r.account == account && r.key == key && now < r.at.saturating_add(window));
+        match found {
+            Some(found) if found.request != request => Err(VaultError::Validation(format!(
+                "Idempotency key {} was already used for a different request: {}", key, found.request))),
+            found => Ok(found),
+        }
+    }
+
+    // Replaces whatever was recorded for the key, and forgets requests that
+    // have aged out of `window`
+    pub fn record(&mut self, account: &str, key: &str, request: &str, outcome: Outcome, now: u64, window: u64) {
+        self.requests.retain(|r| now < r.at.saturating_add(window) && !(r.account == account && r.key == key));
+        self.requests.push(KeyedRequest {
+            key: key.to_string(),
+            account: account.to_string(),
+            request: request.to_string(),
+            outcome,
+            at: now,
+        });
+    }
+
+    // Ties a started request to the deposit record made for it
+    pub fn attach(&mut self, account: &str, key: &str, deposit_id: u64) {
+        if let Some(found) = self.requests.iter_mut().find(|r| r.account == account && r.key == key) {
+            found.outcome = Outcome::Started { deposit_id: Some(deposit_id) };
+        }
+    }
+
+    // Drops a key whose request failed before anything was sent, so a retry runs
+    pub fn forget(&mut self, account: &str, key: &str) {
+        self.requests.retain(|r| !(r.account == account && r.key == key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn replays_within_the_window_and_refuses_reuse() {
+        let mut book = IdempotencyBook::default();
+        let request = "deposit 100.0000000 XLM to the Medium Risk Vault";
+        assert!(book.lookup("GUSER", "order-1", request, 1_000, 60).unwrap().is_none());
+
+        book.record("GUSER", "order-1", request, Outcome::Started { deposit_id: None }, 1_000, 60);
+        book.attach("GUSER", "order-1", 4);
+        assert_eq!(book.lookup("GUSER", "order-1", request, 1_001, 60).unwrap().unwrap().outcome, Outcome::Started { deposit_id: Some(4) });
+        book.record("GUSER", "order-1", request, Outcome::Succeeded { reply: json!({ "shares": 7 }) }, 1_005, 60);
+        let found = book.lookup("GUSER", "order-1", request, 1_030, 60).unwrap().unwrap();
+        assert_eq!(found.outcome, Outcome::Succeeded { reply: json!({ "shares": 7 }) });
+
+        // Keys belong to an account, and only to the request they were first used for
+        assert!(book.lookup("GOTHER", "order-1", request, 1_030, 60).unwrap().is_none());
+        assert!(book.lookup("GUSER", "order-1", "deposit 5.0000000 XLM to the Low Risk Vault", 1_030, 60).is_err());
+
+        // Aged out, the key is free again and dropped at the next record
+        assert!(book.lookup("GUSER", "order-1", "anything", 1_065, 60).unwrap().is_none());
+        book.record("GUSER", "order-2", request, Outcome::Started { deposit_id: None }, 1_065, 60);
+        assert_eq!(book.requests.len(), 1);
+        book.forget("GUSER", "order-2");
+        assert!(book.requests.is_empty());
+
+        assert!(check_key("3f1c9a2e-8d4b-4c1e-9a7f-2b6d5e8c1a0f").is_ok());
+        assert!(check_key("").is_err());
+        assert!(check_key("has space").is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::amount::Stroops;
+use crate::assets::AssetId;
+use crate::horizon::{HorizonClient, Payment};
+use crate::pending_deposits;
+use crate::RiskLevel;
+
+const MAX_BACKOFF_SECS: u64 = 60;
+
+// ============================================================================
+// PAYMENT INGESTION
+// ============================================================================
+
+pub enum StreamEvent {
+    Payment(Box<Payment>),
+    // The stream dropped and will reconnect after the given delay
+    Disconnected { error: String, retry_secs: u64 },
+}
+
+// Streams the vault account's payments in the background. The receiver sees
+// every payment after `cursor`, in order, across reconnects; dropping it
+// stops the task.
+pub fn spawn(horizon: HorizonClient, account: String, cursor: Option<String>) -> mpsc::UnboundedReceiver<StreamEvent> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let mut cursor = cursor.unwrap_or_else(|| "now".to_string());
+        let mut backoff = 1;
+
+        loop {
+            let from = cursor.clone();
+            let mut received = false;
+            let result = horizon.stream_payments(&account, &from, |payment| {
+                cursor = payment.paging_token.clone();
+                received = true;
+                let _ = sender.send(StreamEvent::Payment(Box::new(payment)));
+            }).await;
+            if sender.is_closed() {
+                return;
+            }
+            if received {
+                backoff = 1;
+            }
+
+            // Horizon closes idle streams, so a clean end just reconnects
+            if let Err(e) = result {
+                let event = StreamEvent::Disconnected { error: e.to_string(), retry_secs: backoff };
+                if sender.send(event).is_err() {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_secs(backoff)).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF_SECS);
+            }
+        }
+    });
+
+    receiver
+}
+
+// What an incoming payment asks the vault to do, judged from the payment alone
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Incoming {
+    // Not a payment into the vault, e.g. an outgoing refund
+    Ignore,
+    Deposit {
+        // M... when sent from a muxed account, so each exchange customer
+        // gets their own position
+        from: String,
+        // None for XLM; amounts use the same 7 decimal places either way
+        asset: Option<AssetId>,
+        amount: Stroops,
+        tx_hash: String,
+        ledger: u32,
+        // Memo naming a deposit record made by this CLI
+        deposit_id: Option<u64>,
+        // Vault chosen by a "SYIA low|medium|high" memo
+        risk: Option<RiskLevel>,
+        // ID of the vault sub-address (M...) it was sent to
+        route_id: Option<u64>,
+    },
+}
+
+pub fn classify(payment: &Payment, vault_address: &str) -> Incoming {
+    let is_payment = matches!(payment.kind.as_str(),
+        "payment" | "path_payment_strict_send" | "path_payment_strict_receive");
+    if !is_payment || !payment.transaction_successful || payment.to.as_deref() != Some(vault_address) {
+        return Incoming::Ignore;
+    }
+    let asset = AssetId::from_horizon(payment.asset_type.as_deref().unwrap_or(""),
+        payment.asset_code.as_deref(), payment.asset_issuer.as_deref());
+    let Ok(asset) = asset else {
+        return Incoming::Ignore;
+    };
+    let (Some(from), Some(amount)) = (&payment.from, &payment.amount) else {
+        return Incoming::Ignore;
+    };
+    if from == vault_address {
+        return Incoming::Ignore;
+    }
+    let Ok(amount) = Stroops::from_xlm_str(amount) else {
+        return Incoming::Ignore;
+    };
+
+    let transaction = payment.transaction.as_ref();
+    let memo = transaction
+        .filter(|tx| tx.memo_type == "text")
+        .and_then(|tx| tx.memo.as_deref())
+        .unwrap_or("");
+
+    Incoming::Deposit {
+        from: payment.from_muxed.clone().unwrap_or_else(|| from.clone()),
+        asset,
+        amount,
+        tx_hash: payment.transaction_hash.clone(),
+        ledger: transaction.map(|tx| tx.ledger).unwrap_or(0),
+        deposit_id: pending_deposits::parse_deposit_memo(memo),
+        risk: parse_risk_memo(memo),
+        route_id: payment.to_muxed_id.as_deref().and_then(|id| id.parse().ok()),
+    }
+}
+
+fn parse_risk_memo(memo: &str) -> Option<RiskLevel> {
+    match memo.trim().strip_prefix("SYIA ")?.to_lowercase().as_str() {
+        "low" => Some(RiskLevel::Low),
+        "medium" => Some(RiskLevel::Medium),
+        "high" => Some(RiskLevel::High),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = "GCZEAWUJY3BRHCOKU6C5WRLCF5RFSGY22UGBPBXWL4T4G4SSEQMIYMCX";
+    const USER: &str = "GDRXE2BQUC3AZNPVFSCEZ76NJ3WWL25FYFK6RGZGIEKWE4SOOHSUJUJ6";
+
+    fn payment(from: &str, to: &str, asset_type: &str, memo: &str) -> Payment {
+        serde_json::from_value(serde_json::json!({
+            "paging_token": "123",
+            "type": "payment",
+            "created_at": "2024-01-01T00:00:00Z",
+            "transaction_hash": "abc",
+            "transaction_successful": true,
+            "from": from,
+            "to": to,
+            "amount": "12.5000000",
+            "asset_type": asset_type,
+            "transaction": {
+                "hash": "abc",
+                "ledger": 42,
+                "created_at": "2024-01-01T00:00:00Z",
+                "fee_charged": "100",
+                "successful": true,
+                "memo_type": "text",
+                "memo": memo,
+            },
+        })).unwrap()
+    }
+
+    #[test]
+    fn payments_into_the_vault_are_deposits() {
+        assert_eq!(classify(&payment(USER, VAULT, "native", "SYIA high"), VAULT), Incoming::Deposit {
+            from: USER.to_string(),
+            asset: None,
+            amount: Stroops(125_000_000),
+            tx_hash: "abc".to_string(),
+            ledger: 42,
+            deposit_id: None,
+            risk: Some(RiskLevel::High),
+            route_id: None,
+        });
+        assert!(matches!(classify(&payment(USER, VAULT, "native", "SYIA deposit #7"), VAULT),
+            Incoming::Deposit { deposit_id: Some(7), risk: None, .. }));
+
+        // Muxed on both ends: the sender keeps its ID, the vault sub-address routes it
+        let mut muxed = payment(USER, VAULT, "native", "");
+        muxed.from_muxed = Some("MUSER".to_string());
+        muxed.to_muxed_id = Some("5".to_string());
+        assert!(matches!(classify(&muxed, VAULT),
+            Incoming::Deposit { from, route_id: Some(5), .. } if from == "MUSER"));
+
+        assert_eq!(classify(&payment(VAULT, USER, "native", ""), VAULT), Incoming::Ignore);
+        assert_eq!(classify(&payment(USER, VAULT, "credit_alphanum4", ""), VAULT), Incoming::Ignore);
+
+        // Other assets are deposits too; the vault decides whether it holds them
+        let mut usdc = payment(USER, VAULT, "credit_alphanum4", "SYIA low");
+        usdc.asset_code = Some("USDC".to_string());
+        usdc.asset_issuer = Some(VAULT.to_string());
+        assert!(matches!(classify(&usdc, VAULT),
+            Incoming::Deposit { asset: Some(asset), .. } if asset == AssetId::new("USDC", VAULT).unwrap()));
+    }
+}
@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+
+use crate::amount::{Rounding, Shares};
+use crate::{RiskLevel, Vault};
+
+// Idle insurance capital is only ever deployed into the most conservative vault
+pub const INSURANCE_VAULT: RiskLevel = RiskLevel::Low;
+
+// ============================================================================
+// INSURANCE POOL INVESTMENT
+// ============================================================================
+
+// Shares the insurance pool holds in INSURANCE_VAULT. They are kept apart from
+// user positions so claims can always see how much cover is liquid vs invested.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InsuranceInvestment {
+    // Portion of total insurance assets to keep invested, in basis points
+    pub target_bps: u16,
+    pub shares: u64,
+    // Stroops moved in, net of redemptions at cost
+    pub principal: u64,
+}
+
+impl InsuranceInvestment {
+    pub fn value(&self, vault: &Vault) -> u64 {
+        vault.pool()
+            .value_of(Shares(self.shares), Rounding::Down)
+            .map(|value| value.0)
+            .unwrap_or(0)
+    }
+
+    // Positive: stroops to move from the liquid pool into the vault.
+    // Negative: stroops to redeem back into the liquid pool.
+    pub fn rebalance_delta(&self, liquid: u64, vault: &Vault) -> i128 {
+        let invested = self.value(vault);
+        let total = liquid as u128 + invested as u128;
+        let target = total * self.target_bps as u128 / 10_000;
+        target as i128 - invested as i128
+    }
+}
+
+// ============================================================================
+// COVERAGE & DYNAMIC PREMIUMS
+// ============================================================================
+
+// Range a vault's insurance_fee may be moved within, in basis points
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PremiumBounds {
+    pub min_bps: u16,
+    pub max_bps: u16,
+}
+
+// Coverage is insurance assets / total TVL, in basis points
+#[derive(Debug, Clone, Copy)]
+pub struct CoveragePolicy {
+    pub floor_bps: u64,
+    pub ceiling_bps: u64,
+    pub step_bps: u16,
+}
+
+impl Default for CoveragePolicy {
+    fn default() -> Self {
+        CoveragePolicy {
+            floor_bps: 500,
+            ceiling_bps: 1_500,
+            step_bps: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PremiumAction {
+    Raise,
+    Lower,
+    Hold,
+}
+
+#[derive(Debug, Clone)]
+pub struct CoverageReport {
+    pub coverage_bps: Option<u64>,
+    pub action: PremiumAction,
+    pub changes: Vec<(RiskLevel, u16, u16)>,
+}
+
+// None while there is no TVL to cover
+pub fn coverage_ratio_bps(insurance_assets: u64, total_tvl: u64) -> Option<u64> {
+    if total_tvl == 0 {
+        None
+    } else {
+        Some((insurance_assets as u128 * 10_000 / total_tvl as u128) as u64)
+    }
+}
+
+impl CoveragePolicy {
+    pub fn action_for(&self, coverage_bps: Option<u64>) -> PremiumAction {
+        match coverage_bps {
+            Some(c) if c < self.floor_bps => PremiumAction::Raise,
+            Some(c) if c > self.ceiling_bps => PremiumAction::Lower,
+            _ => PremiumAction::Hold,
+        }
+    }
+
+    pub fn adjusted_fee(&self, fee_bps: u16, bounds: PremiumBounds, action: PremiumAction) -> u16 {
+        match action {
+            PremiumAction::Raise => fee_bps.saturating_add(self.step_bps).min(bounds.max_bps),
+            PremiumAction::Lower => fee_bps.saturating_sub(self.step_bps).max(bounds.min_bps),
+            PremiumAction::Hold => fee_bps,
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use serde::{Deserialize, Serialize};
+
+use crate::RiskLevel;
+use crate::client::PAYMENT_TIMEOUT_SECS;
+use crate::error::VaultError;
+use crate::horizon::Payment;
+
+// Recent vault payments searched for an interrupted one (Horizon's page limit)
+pub const RECOVERY_SCAN: u32 = 200;
+// A payment still missing this long after its entry was last touched never
+// lands: transactions are built to expire within PAYMENT_TIMEOUT_SECS, and
+// the margin covers one rebuilt after a rejection
+pub const RESOLVE_AFTER_SECS: u64 = 2 * PAYMENT_TIMEOUT_SECS;
+
+// ============================================================================
+// FINDING PAYMENTS
+// ============================================================================
+
+#[derive(Debug, Clone)]
+pub enum Located {
+    Landed(Box<Payment>),
+    // Not among payments reaching back past the one searched for
+    Missing,
+    // Too many payments since to tell either way
+    Unknown,
+}
+
+// Looks for the successful payment from `from` tagged `memo` among an
+// account's most recent `limit` payments, newest first. Not finding it only
+// means Missing when the page reaches back before `since_ledger`, or is the
+// account's whole history.
+pub fn locate(payments: Vec<Payment>, from: &str, memo: &str, since_ledger: u32, limit: u32) -> Located {
+    let complete = payments.len() < limit as usize
+        || payments.last().and_then(|p| p.transaction.as_ref()).is_some_and(|t| t.ledger < since_ledger);
+    let found = payments.into_iter().find(|p| {
+        p.transaction_successful && p.from.as_deref() == Some(from)
+            && p.transaction.as_ref().and_then(|t| t.memo.as_deref()) == Some(memo)
+    });
+    match found {
+        Some(payment) => Located::Landed(Box::new(payment)),
+        None if complete => Located::Missing,
+        None => Located::Unknown,
+    }
+}
+
+// ============================================================================
+// OPERATION JOURNAL
+// ============================================================================
+
+// A payout moves Intended -> Sent -> Completed. The entry is saved before its
+// payment goes out and completed in the same save as the books it changes, so
+// one still open at startup was interrupted in between: it is looked up on the
+// ledger and either booked, or Abandoned with the books untouched once its
+// payment can no longer land.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JournalStage {
+    Intended,
+    Sent,
+    Completed,
+    Abandoned,
+}
+
+impl JournalStage {
+    pub fn is_open(self) -> bool {
+        matches!(self, JournalStage::Intended | JournalStage::Sent)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OperationKind {
+    // Shares redeemed for `gross_stroops`, less a penalty kept by the
+    // insurance pool; `queued` is the withdrawal queue entry it pays
+    Withdrawal { shares: u64, gross_stroops: u64, penalty_stroops: u64, queued: Option<u64> },
+    // Accumulated yield, leaving the shares in place
+    YieldPayout,
+}
+
+// Text memo attached to a payout, so the payment can be found on the ledger
+// (28 bytes at most)
+pub fn payout_memo(id: u64) -> String {
+    format!("SYIA payout #{}", id)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: u64,
+    pub user: String,
+    pub risk: RiskLevel,
+    pub kind: OperationKind,
+    pub payee: String,
+    // What the payee receives
+    pub amount_stroops: u64,
+    pub memo: String,
+    // The latest ledger when the entry was written, so a search for its
+    // payment knows how far back to look; 0 if unknown
+    pub since_ledger: u32,
+    pub tx_hash: Option<String>,
+    // 0 until the payment is known to be in a ledger
+    pub ledger: u32,
+    pub stage: JournalStage,
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OperationJournal {
+    entries: Vec<JournalEntry>,
+    next_id: u64,
+}
+
+impl OperationJournal {
+    #[allow(clippy::too_many_arguments)]
+    pub fn intend(&mut self, user: &str, risk: RiskLevel, kind: OperationKind, payee: &str, amount_stroops: u64,
+                  since_ledger: u32, now: u64) -> u64 {
+        self.next_id += 1;
+        self.entries.push(JournalEntry {
+            id: self.next_id,
+            user: user.to_string(),
+            risk,
+            kind,
+            payee: payee.to_string(),
+            amount_stroops,
+            memo: payout_memo(self.next_id),
+            since_ledger,
+            tx_hash: None,
+            ledger: 0,
+            stage: JournalStage::Intended,
+            created_at: now,
+            updated_at: now,
+            note: None,
+        });
+        self.next_id
+    }
+
+    pub fn get(&self, id: u64) -> Option<&JournalEntry> {
+        self.entries.iter().find(|e| e.id == id)
+    }
+
+    fn get_open(&mut self, id: u64) -> Result<&mut JournalEntry, VaultError> {
+        let entry = self.entries.iter_mut().find(|e| e.id == id)
+            .ok_or_else(|| format!("Payout #{} not found", id))?;
+        if !entry.stage.is_open() {
+            return Err(format!("Payout #{} is already {:?}", id, entry.stage).into());
+        }
+        Ok(entry)
+    }
+
+    // Records the transaction carrying the payment; ledger 0 while it may
+    // still be included
+    pub fn note_sent(&mut self, id: u64, tx_hash: &str, ledger: u32, now: u64) -> Result<(), VaultError> {
+        let entry = self.get_open(id)?;
+        entry.tx_hash = Some(tx_hash.to_string());
+        entry.ledger = ledger;
+        entry.stage = JournalStage::Sent;
+        entry.updated_at = now;
+        Ok(())
+    }
+
+    // Only once the payment is in a ledger
+    pub fn complete(&mut self, id: u64, now: u64) -> Result<(), VaultError> {
+        let entry = self.get_open(id)?;
+        if entry.ledger == 0 {
+            return Err(format!("Payout #{}'s payment has not been confirmed in a ledger", id).into());
+        }
+        entry.stage = JournalStage::Completed;
+        entry.updated_at = now;
+        Ok(())
+    }
+
+    // Only for a payment that never reached a ledger
+    pub fn abandon(&mut self, id: u64, reason: &str, now: u64) -> Result<(), VaultError> {
+        let entry = self.get_open(id)?;
+        if entry.ledger != 0 {
+            return Err(format!("Payout #{} was paid in ledger {}", id, entry.ledger).into());
+        }
+        entry.stage = JournalStage::Abandoned;
+        entry.note = Some(reason.to_string());
+        entry.updated_at = now;
+        Ok(())
+    }
+
+    pub fn open(&self) -> Vec<JournalEntry> {
+        self.entries.iter()
+            .filter(|e| e.stage.is_open())
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payment(memo: &str, ledger: u32) -> Payment {
+        serde_json::from_value(serde_json::json!({
+            "paging_token": ledger.to_string(),
+            "type": "payment",
+            "created_at": "2025-01-06T00:00:00Z",
+            "transaction_hash": format!("tx{}", ledger),
+            "transaction_successful": true,
+            "from": "GVAULT",
+            "to": "GUSER",
+            "amount": "12.5000000",
+            "asset_type": "native",
+            "transaction": {
+                "hash": format!("tx{}", ledger),
+                "ledger": ledger,
+                "created_at": "2025-01-06T00:00:00Z",
+                "fee_charged": "100",
+                "successful": true,
+                "memo_type": "text",
+                "memo": memo,
+            },
+        })).unwrap()
+    }
+
+    #[test]
+    fn finds_interrupted_payouts_and_only_closes_them_once() {
+        let mut journal = OperationJournal::default();
+        let kind = OperationKind::Withdrawal { shares: 100, gross_stroops: 130_000_000, penalty_stroops: 5_000_000, queued: None };
+        let id = journal.intend("GUSER", RiskLevel::Medium, kind, "GUSER", 125_000_000, 40, 1_000);
+        assert_eq!(journal.get(id).unwrap().memo, "SYIA payout #1");
+
+        // Newest first, as Horizon pages them
+        let page = vec![payment("SYIA payout #2", 45), payment("SYIA payout #1", 42), payment("SYIA fees", 38)];
+        assert!(matches!(locate(page.clone(), "GVAULT", "SYIA payout #1", 40, 3),
+            Located::Landed(payment) if payment.transaction_hash == "tx42"));
+        // Someone else's payment with the same memo doesn't count
+        assert!(matches!(locate(page.clone(), "GOTHER", "SYIA payout #1", 40, 3), Located::Missing));
+        assert!(matches!(locate(page.clone(), "GVAULT", "SYIA payout #9", 40, 3), Located::Missing));
+        // A full page that doesn't reach back far enough settles nothing
+        assert!(matches!(locate(page[..2].to_vec(), "GVAULT", "SYIA payout #9", 40, 2), Located::Unknown));
+
+        assert!(journal.complete(id, 1_010).is_err());
+        journal.note_sent(id, "tx42", 42, 1_010).unwrap();
+        assert!(journal.abandon(id, "never sent", 1_020).is_err());
+        journal.complete(id, 1_020).unwrap();
+        assert!(journal.open().is_empty());
+        assert!(journal.note_sent(id, "tx43", 43, 1_030).is_err());
+
+        let id = journal.intend("GUSER", RiskLevel::Medium, OperationKind::YieldPayout, "GOTHER", 1_000_000, 50, 2_000);
+        journal.abandon(id, "never sent", 2_000 + RESOLVE_AFTER_SECS + 1).unwrap();
+        assert_eq!(journal.get(id).unwrap().stage, JournalStage::Abandoned);
+    }
+}
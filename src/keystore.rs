@@ -0,0 +1,234 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+
+use crate::error::VaultError;
+use crate::transaction::Keypair;
+
+pub const DEFAULT_KEYSTORE_FILE: &str = "stellarvault_keystore.json";
+
+const KEYSTORE_VERSION: u32 = 1;
+// OWASP's minimum recommendation for Argon2id
+const ARGON2_MEMORY_KIB: u32 = 19_456;
+const ARGON2_ITERATIONS: u32 = 2;
+const ARGON2_PARALLELISM: u32 = 1;
+const SALT_LEN: usize = 16;
+
+// ============================================================================
+// ENCRYPTED KEYSTORE
+// ============================================================================
+
+// Secret keys are encrypted with XChaCha20-Poly1305 under a key derived from
+// the password with Argon2id. Public keys and labels stay readable so the
+// store can be listed without unlocking it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KdfParams {
+    salt: String,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeystoreEntry {
+    pub label: String,
+    pub public_key: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KeystoreFile {
+    version: u32,
+    kdf: KdfParams,
+    entries: Vec<KeystoreEntry>,
+}
+
+pub struct UnlockedKey {
+    pub label: String,
+    pub public_key: String,
+    pub secret_key: String,
+}
+
+pub struct Keystore {
+    path: PathBuf,
+    file: KeystoreFile,
+    // Set once the password has been checked
+    cipher: Option<XChaCha20Poly1305>,
+}
+
+impl Keystore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, VaultError> {
+        let path = path.as_ref().to_path_buf();
+        let file = if path.exists() {
+            let contents = fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let file: KeystoreFile = serde_json::from_str(&contents)
+                .map_err(|e| format!("Corrupt keystore {}: {}", path.display(), e))?;
+            if file.version != KEYSTORE_VERSION {
+                return Err(format!("Unsupported keystore version {} in {}", file.version, path.display()).into());
+            }
+            file
+        } else {
+            let mut salt = [0u8; SALT_LEN];
+            OsRng.fill_bytes(&mut salt);
+            KeystoreFile {
+                version: KEYSTORE_VERSION,
+                kdf: KdfParams {
+                    salt: BASE64.encode(salt),
+                    memory_kib: ARGON2_MEMORY_KIB,
+                    iterations: ARGON2_ITERATIONS,
+                    parallelism: ARGON2_PARALLELISM,
+                },
+                entries: Vec::new(),
+            }
+        };
+
+        Ok(Keystore { path, file, cipher: None })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.file.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &[KeystoreEntry] {
+        &self.file.entries
+    }
+
+    pub fn is_unlocked(&self) -> bool {
+        self.cipher.is_some()
+    }
+
+    fn derive_cipher(&self, password: &str) -> Result<XChaCha20Poly1305, VaultError> {
+        let kdf = &self.file.kdf;
+        let salt = BASE64.decode(&kdf.salt)
+            .map_err(|e| VaultError::Storage(format!("Corrupt keystore salt: {}", e)))?;
+        let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(32))
+            .map_err(|e| format!("Invalid keystore KDF parameters: {}", e))?;
+        let mut key = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(password.as_bytes(), &salt, &mut key)
+            .map_err(|e| format!("Key derivation failed: {}", e))?;
+        Ok(XChaCha20Poly1305::new(&key.into()))
+    }
+
+    // Decrypts every entry. On an empty store this sets the password that
+    // future entries are encrypted under.
+    pub fn unlock(&mut self, password: &str) -> Result<Vec<UnlockedKey>, VaultError> {
+        let cipher = self.derive_cipher(password)?;
+        let keys = self.file.entries.iter()
+            .map(|entry| decrypt(&cipher, entry))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.cipher = Some(cipher);
+        Ok(keys)
+    }
+
+    // Adds or replaces the entry for this key's account and saves the store
+    pub fn add(&mut self, label: &str, secret_key: &str) -> Result<(), VaultError> {
+        let cipher = self.cipher.as_ref().ok_or("Unlock the keystore before adding keys")?;
+        let public_key = Keypair::from_secret(secret_key)?.public_key();
+
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, Payload { msg: secret_key.as_bytes(), aad: public_key.as_bytes() })
+            .map_err(|_| "Failed to encrypt secret key")?;
+
+        let entry = KeystoreEntry {
+            label: label.to_string(),
+            public_key: public_key.clone(),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        };
+        self.file.entries.retain(|e| e.public_key != public_key);
+        self.file.entries.push(entry);
+        self.save()
+    }
+
+    // Drops this account's entry, if there is one, and saves the store
+    pub fn remove(&mut self, public_key: &str) -> Result<bool, VaultError> {
+        let before = self.file.entries.len();
+        self.file.entries.retain(|e| e.public_key != public_key);
+        if self.file.entries.len() == before {
+            return Ok(false);
+        }
+        self.save()?;
+        Ok(true)
+    }
+
+    fn save(&self) -> Result<(), VaultError> {
+        let json = serde_json::to_string_pretty(&self.file)?;
+
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, json)
+            .map_err(|e| format!("Failed to write {}: {}", tmp_path.display(), e))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&tmp_path, fs::Permissions::from_mode(0o600))?;
+        }
+        fs::rename(&tmp_path, &self.path)
+            .map_err(|e| format!("Failed to replace {}: {}", self.path.display(), e))?;
+
+        Ok(())
+    }
+}
+
+// The public key is authenticated as associated data, so an entry can't be
+// relabelled as a different account
+fn decrypt(cipher: &XChaCha20Poly1305, entry: &KeystoreEntry) -> Result<UnlockedKey, VaultError> {
+    let corrupt = || VaultError::Storage(format!("Corrupt keystore entry for {}", entry.public_key));
+    let nonce: [u8; 24] = BASE64.decode(&entry.nonce).map_err(|_| corrupt())?
+        .try_into().map_err(|_| corrupt())?;
+    let ciphertext = BASE64.decode(&entry.ciphertext).map_err(|_| corrupt())?;
+    let plaintext = cipher
+        .decrypt(&XNonce::from(nonce), Payload { msg: &ciphertext, aad: entry.public_key.as_bytes() })
+        .map_err(|_| VaultError::Validation("Wrong keystore password".to_string()))?;
+
+    Ok(UnlockedKey {
+        label: entry.label.clone(),
+        public_key: entry.public_key.clone(),
+        secret_key: String::from_utf8(plaintext).map_err(|_| corrupt())?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "SBGWSG6BTNCKCOB3DIFBGCVMUPQFYPA2G4O34RMTB343OYPXU5DJDVMN";
+    const PUBLIC: &str = "GDRXE2BQUC3AZNPVFSCEZ76NJ3WWL25FYFK6RGZGIEKWE4SOOHSUJUJ6";
+
+    #[test]
+    fn secrets_round_trip_only_with_the_right_password() {
+        let path = std::env::temp_dir().join(format!("stellarvault_keystore_test_{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut keystore = Keystore::open(&path).unwrap();
+        keystore.unlock("correct horse").unwrap();
+        keystore.add("test", SECRET).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains(SECRET));
+
+        let mut reopened = Keystore::open(&path).unwrap();
+        assert_eq!(reopened.entries()[0].public_key, PUBLIC);
+        assert!(reopened.unlock("wrong horse").is_err());
+        assert!(!reopened.is_unlocked());
+
+        let keys = reopened.unlock("correct horse").unwrap();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].secret_key, SECRET);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,155 @@
+//! StellarVault (SYIA): a yield vault with smart allocation and insurance,
+//! built on Stellar.
+//!
+//! Deposits go into one of three risk-tiered vaults, each spreading its
+//! funds across yield strategies and paying a premium into a shared
+//! insurance pool. [`StellarVault`] owns the vaults, the users' positions
+//! and the vault account, and is the entry point for everything else;
+//! [`StellarClient`] signs and submits on behalf of one Stellar account.
+//! [`StellarVaultBuilder`] sets one up with custom vaults and strategies.
+//!
+//! ```no_run
+//! use stellarvault::{Network, RiskLevel, StellarVault};
+//! use stellarvault::fee_strategy::FeeStrategy;
+//!
+//! # async fn run(secret_key: &str, public_key: &str) -> Result<(), stellarvault::VaultError> {
+//! let network = Network::Testnet;
+//! let mut vault = StellarVault::new(stellarvault::config::DEFAULT_VAULT_ADDRESS, &network, FeeStrategy::default())?;
+//! vault.register_user(secret_key, public_key)?;
+//! vault.deposit(public_key, RiskLevel::Low, 100_0000000, None).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Amounts are in stroops (1 XLM = 10,000,000 stroops) throughout. Every
+//! fallible operation returns a [`VaultError`].
+
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// println! for messages meant for people: they move to stderr when
+// --output json keeps stdout for results, and are logged in the span of
+// whatever operation said them
+#[macro_export]
+macro_rules! say {
+    ($($arg:tt)*) => {
+        $crate::output::say(format_args!($($arg)*))
+    };
+}
+
+pub mod accounting;
+pub mod alerts;
+pub mod amm;
+pub mod amount;
+pub mod api;
+pub mod apy;
+pub mod assets;
+pub mod audit;
+pub mod blend;
+pub mod builder;
+pub mod cache;
+pub mod circuit_breaker;
+pub mod claims;
+pub mod client;
+pub mod config;
+pub mod contract_index;
+pub mod error;
+pub mod events;
+pub mod failover;
+pub mod faucet;
+pub mod fee_strategy;
+pub mod federation;
+pub mod fees;
+pub mod health;
+pub mod horizon;
+pub mod idempotency;
+pub mod ingest;
+pub mod journal;
+pub mod insurance;
+pub mod keystore;
+pub mod logging;
+pub mod mock_ledger;
+pub mod multisig;
+pub mod muxed;
+pub mod network;
+pub mod notify;
+pub mod offline;
+pub mod oracle;
+pub mod output;
+pub mod path_payment;
+pub mod pending_deposits;
+pub mod rate_limit;
+pub mod rebalance;
+pub mod reconcile;
+pub mod recurring;
+pub mod reload;
+pub mod reserves;
+pub mod retry;
+pub mod scheduler;
+pub mod screening;
+pub mod sdex;
+pub mod sequence;
+pub mod share_asset;
+pub mod signer;
+pub mod soroban;
+pub mod soroswap;
+pub mod stellar_vault;
+pub mod storage;
+pub mod strategy;
+pub mod submission;
+pub mod timelock;
+pub mod transaction;
+pub mod tui;
+pub mod vault;
+pub mod vault_config;
+pub mod vault_contract;
+pub mod wallet;
+pub mod web_auth;
+pub mod withdrawal_queue;
+
+pub use builder::{StellarVaultBuilder, VaultBuilder};
+pub use client::{StellarClient, TransactionReceipt};
+pub use error::{DepositError, StellarError, VaultError};
+pub use amount::{MathError, Stroops};
+pub use network::Network;
+pub use stellar_vault::StellarVault;
+pub use vault::{risk_level_to_string, Portfolio, PositionSummary, RiskLevel, StrategyAllocation, UserPosition, Vault};
+pub use strategy::Strategy;
+
+pub const SECONDS_PER_YEAR: u64 = 31_536_000;
+
+/// Seconds since the Unix epoch, or 0 if the clock is before it.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// A span of seconds as "2d 3h 4m", "3h 4m" or "4m 5s".
+pub fn format_duration(secs: u64) -> String {
+    let days = secs / 86_400;
+    let hours = (secs % 86_400) / 3_600;
+    let minutes = (secs % 3_600) / 60;
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m {}s", minutes, secs % 60)
+    }
+}
+
+/// Reads one trimmed line from stdin after printing `prompt`. Mainnet
+/// payments ask for confirmation this way before they are sent.
+pub fn get_user_input(prompt: &str) -> String {
+    if output::is_json() {
+        eprint!("{}", prompt);
+    } else {
+        print!("{}", prompt);
+        io::stdout().flush().unwrap();
+    }
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    input.trim().to_string()
+}
@@ -0,0 +1,132 @@
+use std::fs;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::{filter_fn, EnvFilter};
+use tracing_subscriber::fmt;
+use tracing_subscriber::prelude::*;
+
+use crate::error::VaultError;
+use crate::output;
+
+pub const DEFAULT_LOG_LEVEL: &str = "off";
+pub const DEFAULT_LOG_FILE_LEVEL: &str = "info";
+pub const DEFAULT_LOG_MAX_FILES: usize = 14;
+
+// ============================================================================
+// LOGGING
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogRotation {
+    Hourly,
+    Daily,
+    Never,
+}
+
+impl LogRotation {
+    pub fn parse(name: &str) -> Result<LogRotation, VaultError> {
+        match name.to_lowercase().as_str() {
+            "hourly" => Ok(LogRotation::Hourly),
+            "daily" => Ok(LogRotation::Daily),
+            "never" => Ok(LogRotation::Never),
+            _ => Err(VaultError::Validation(format!("Unknown log rotation (expected hourly, daily or never): {}", name))),
+        }
+    }
+}
+
+// The console log is off unless asked for, since everything worth telling
+// the user is already said; its lines go to stderr, apart from what commands
+// print and from --output json. The file gets JSON lines: every event with the spans it
+// happened in (deposit, withdraw, harvest, API requests) and their fields.
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    // EnvFilter directives: a level such as "info", or per module such as
+    // "warn,stellarvault::horizon=debug"
+    pub level: String,
+    // Where log files go; no file log when unset
+    pub dir: Option<PathBuf>,
+    pub file_level: String,
+    pub rotation: LogRotation,
+    // Rotated files kept before the oldest is deleted
+    pub max_files: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            level: DEFAULT_LOG_LEVEL.to_string(),
+            dir: None,
+            file_level: DEFAULT_LOG_FILE_LEVEL.to_string(),
+            rotation: LogRotation::Daily,
+            max_files: DEFAULT_LOG_MAX_FILES,
+        }
+    }
+}
+
+pub fn filter(directives: &str) -> Result<EnvFilter, VaultError> {
+    EnvFilter::try_new(directives).map_err(|e| VaultError::Validation(format!("Invalid log filter {}: {}", directives, e)))
+}
+
+// Installs the logger for the rest of the run, without the console when the
+// TUI owns the terminal. Dropping the guard flushes the file, so hold it
+// until exit.
+pub fn init(config: &LogConfig, console: bool) -> Result<Option<WorkerGuard>, VaultError> {
+    let console = if console {
+        // Lines from say! have been printed already
+        Some(fmt::layer()
+            .with_writer(std::io::stderr)
+            .with_ansi(std::io::stderr().is_terminal())
+            .with_filter(filter(&config.level)?)
+            .with_filter(filter_fn(|metadata| metadata.target() != output::LOG_TARGET)))
+    } else {
+        None
+    };
+    let (file, guard) = match &config.dir {
+        Some(dir) => {
+            let rotation = match config.rotation {
+                LogRotation::Hourly => Rotation::HOURLY,
+                LogRotation::Daily => Rotation::DAILY,
+                LogRotation::Never => Rotation::NEVER,
+            };
+            let unwritable = |e: &dyn std::fmt::Display| VaultError::Validation(format!("Cannot write logs to {}: {}", dir.display(), e));
+            fs::create_dir_all(dir).map_err(|e| unwritable(&e))?;
+            let appender = RollingFileAppender::builder()
+                .rotation(rotation)
+                .filename_prefix("stellarvault")
+                .filename_suffix("log")
+                .max_log_files(config.max_files)
+                .build(dir)
+                .map_err(|e| unwritable(&e))?;
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let layer = fmt::layer()
+                .json()
+                .with_current_span(true)
+                .with_span_list(true)
+                .with_writer(writer)
+                .with_filter(filter(&config.file_level)?);
+            (Some(layer), Some(guard))
+        }
+        None => (None, None),
+    };
+    tracing_subscriber::registry()
+        .with(console)
+        .with(file)
+        .try_init()
+        .map_err(|e| VaultError::Validation(format!("Logging was already set up: {}", e)))?;
+    Ok(guard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_bad_filters_and_rotations() {
+        assert!(filter("info,stellarvault::horizon=debug").is_ok());
+        assert!(filter("stellarvault=loud").is_err());
+        assert_eq!(LogRotation::parse("Hourly").unwrap(), LogRotation::Hourly);
+        assert!(LogRotation::parse("weekly").is_err());
+    }
+}
@@ -0,0 +1,4754 @@
+﻿use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde_json::json;
+use stellarvault::{format_duration, get_user_input, say, risk_level_to_string, unix_now, RiskLevel, StellarClient, StellarVault, StellarVaultBuilder, TransactionReceipt, Vault};
+use stellarvault::{api, apy, assets, audit, faucet, logging, oracle, output, fees, horizon, ingest, mock_ledger, multisig, muxed, offline, path_payment, rebalance, sdex, soroban, strategy, transaction, tui, wallet};
+use stellarvault::client::{check_memo_not_required, TransactionPreview, OFFLINE_VALIDITY_HOURS};
+use stellarvault::stellar_vault::{format_coverage, print_premium_changes, Caps, Proposal};
+use stellarvault::timelock::{AdminChange, QueuedChange, StrategyTarget};
+use stellarvault::vault::{PositionSummary, WithdrawalOutcome};
+use stellarvault::assets::AssetId;
+use stellarvault::amount::{mul_div, Rounding, Stroops};
+use stellarvault::claims::Claim;
+use stellarvault::builder::build_vaults;
+use stellarvault::config::{parse_risk, ApyOracleConfig, Config, PriceOracleConfig};
+use stellarvault::alerts::{AlertEngine, Readings};
+use stellarvault::apy::{ApyProvider, BlendApy, LiveRates, RateUpdate};
+use stellarvault::error::VaultError;
+use stellarvault::events::VaultEvent;
+use stellarvault::reload::ConfigWatcher;
+use stellarvault::recurring::{Cadence, Payout, PlanAction, RecurringPlan};
+use stellarvault::scheduler::{Job, Scheduler};
+use stellarvault::horizon::{Balance, HorizonClient};
+use stellarvault::contract_index::{ContractEvent, IndexEvent};
+use stellarvault::ingest::StreamEvent;
+use stellarvault::idempotency::KeyedDeposit;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tracing::Instrument;
+use stellarvault::insurance::INSURANCE_VAULT;
+use stellarvault::reconcile::DEFAULT_TOLERANCE_BPS;
+use stellarvault::reserves::{InclusionProof, SignedSummary};
+use stellarvault::screening::{AddressList, BLOCKED_SHOWN};
+use stellarvault::keystore::Keystore;
+use stellarvault::mock_ledger::MockLedger;
+use stellarvault::multisig::{SignerSet, Threshold};
+use stellarvault::network::Network;
+use stellarvault::notify::{Notification, Notifier};
+use stellarvault::oracle::{format_usd, PriceSource, PriceUpdate, Reflector};
+use stellarvault::pending_deposits::PendingDeposit;
+use stellarvault::share_asset::ShareAsset;
+use stellarvault::signer::remote::RemoteSigner;
+use stellarvault::signer::Signer;
+use stellar_xdr::curr::Memo;
+use stellarvault::transaction::{Keypair, TransactionBuilder};
+use stellarvault::web_auth::WebAuth;
+
+
+// Like get_user_input, but without echoing what is typed
+fn get_password_input(prompt: &str) -> String {
+    rpassword::prompt_password(prompt).unwrap_or_default()
+}
+
+fn prompt_risk_level() -> RiskLevel {
+    let risk_choice = get_user_input("Enter risk level (low/medium/high): ").to_lowercase();
+    
+    match risk_choice.as_str() {
+        "low" | "l" | "1" => RiskLevel::Low,
+        "medium" | "m" | "2" => RiskLevel::Medium,
+        "high" | "h" | "3" => RiskLevel::High,
+        _ => {
+            say!("❌ Invalid choice. Defaulting to Low Risk.");
+            RiskLevel::Low
+        }
+    }
+}
+
+// Funds an account with test XLM from friendbot, creating a fresh wallet when
+// no account is given
+async fn run_faucet(network: &Network, account: Option<&str>) -> CommandResult {
+    // The offline network's mock ledger funds accounts itself
+    let horizon = HorizonClient::for_network(network);
+    let friendbot_url = network.friendbot_url();
+    let no_friendbot = || format!("{} has no friendbot; fund accounts from an existing one", network);
+    if friendbot_url.is_none() && horizon.mock().is_none() {
+        return Err(no_friendbot());
+    }
+    let account = match account {
+        Some(account) => account.to_string(),
+        None => {
+            let phrase = wallet::new_mnemonic();
+            let keypair = wallet::from_mnemonic(&phrase, "", 0)
+                .map_err(|e| format!("Could not create wallet: {}", e))?;
+            say!("\n🆕 New wallet created");
+            say!("   📝 Recovery phrase (write it down, it is shown only once):");
+            say!("      {}", phrase);
+            say!("   💡 Add it with 'wallet import' to use it in the vault");
+            keypair.public_key()
+        }
+    };
+
+    say!("\n🚰 Requesting test XLM for {} from friendbot...", account);
+    let funding = match (horizon.mock(), friendbot_url) {
+        (Some(ledger), _) => {
+            ledger.fund(&account, Stroops(mock_ledger::FAUCET_STROOPS));
+            faucet::Funding::Funded
+        }
+        (None, Some(url)) => faucet::fund(url, &account).await.map_err(|e| e.to_string())?,
+        (None, None) => return Err(no_friendbot()),
+    };
+    let funded = match funding {
+        faucet::Funding::Funded => {
+            say!("   ✅ Funding requested");
+            true
+        }
+        faucet::Funding::AlreadyExists => {
+            say!("   ℹ️  The account already exists; friendbot only funds new accounts");
+            false
+        }
+    };
+    let created = faucet::wait_for_account(&horizon, &account).await.map_err(|e| e.to_string())?;
+    let balance = created.balances.iter()
+        .find(|balance| balance.is_native())
+        .map(|balance| balance.balance.as_str())
+        .unwrap_or("0");
+    say!("   👤 Account: {}", account);
+    say!("   💰 Balance: {} XLM", balance);
+    say!("   🔗 {}", network.account_link(&account));
+    Ok(json!({ "account": account, "funded": funded, "balance": balance }))
+}
+
+// Hands out an M... address for deposits from exchanges and other wallets
+fn run_deposit_address(vault: &mut StellarVault, user: &str, ingesting: bool) {
+    say!("\n📬 Which vault should deposits to this address go to?");
+    let risk = prompt_risk_level();
+    match vault.deposit_address(user, risk) {
+        Ok(address) => {
+            say!("✅ Deposit address for the {:?} Risk Vault:", risk);
+            say!("   {}", address);
+            say!("   XLM sent here from any wallet or exchange is credited to {}", user);
+            if !ingesting {
+                say!("⚠️  Payment ingestion is off, so deposits to it won't be credited until it is enabled");
+            }
+        }
+        Err(e) => say!("❌ Could not create a deposit address: {}", e),
+    }
+
+    let routes = vault.deposit_routes.for_user(user);
+    if routes.len() > 1 {
+        say!("\n   All your deposit addresses:");
+        for (id, route) in routes {
+            if let Ok(address) = muxed::address(&vault.vault_address, id) {
+                say!("   {:?}: {}", route.risk, address);
+            }
+        }
+    }
+}
+
+async fn run_deposit(vault: &mut StellarVault, user: &str) {
+    // Ask user for risk level
+    say!("\n💼 Choose your investment strategy:");
+    let risk_level = prompt_risk_level();
+
+    say!("✅ Selected: {:?} Risk Vault", risk_level);
+    if let Some(info) = vault.get_vault_info(risk_level) {
+        let xlm = |stroops: u64| stroops as f64 / 10_000_000.0;
+        say!("   Minimum Deposit: {} XLM", xlm(info.min_deposit));
+        if let Some(cap) = info.per_user_cap {
+            say!("   Per-User Cap: {} XLM", xlm(cap));
+        }
+        if let Some(cap) = info.max_tvl {
+            say!("   Vault Capacity: {:.2} / {} XLM", xlm(info.total_value), xlm(cap));
+        }
+        if let Some(cap) = info.per_user_cap_usd {
+            say!("   Per-User Cap: {}", format_usd(cap));
+        }
+        if let Some(cap) = info.max_tvl_usd {
+            let tvl = vault.prices.usd_value(info.asset.as_ref(), info.total_value, unix_now());
+            say!("   Vault Capacity: {} / {}", tvl.map_or("unpriced".to_string(), format_usd), format_usd(cap));
+        }
+        if info.lockup_secs > 0 {
+            say!("   Lock-up: {} ({:.2}% early withdrawal penalty)",
+                format_duration(info.lockup_secs),
+                info.early_withdrawal_penalty as f64 / 100.0);
+        }
+    }
+
+    // Any asset with a market to the vault's asset can be converted on the way in
+    let vault_asset = vault.get_vault_info(risk_level).and_then(|info| info.asset.clone());
+    let vault_code = path_payment::label(vault_asset.as_ref()).to_string();
+    let pay_input = get_user_input(&format!("\n💱 Pay with (Enter for {}, or XLM / CODE:ISSUER): ", vault_code));
+    let pay_with = match pay_input.as_str() {
+        "" => vault_asset.clone(),
+        input => match parse_pay_with(input) {
+            Ok(asset) => asset,
+            Err(e) => {
+                say!("❌ {}", e);
+                return;
+            }
+        },
+    };
+    let pay_code = path_payment::label(pay_with.as_ref()).to_string();
+
+    // Ask user for deposit amount
+    let amount_input = get_user_input(&format!("\n💰 Enter deposit amount ({}): ", pay_code));
+    let amount_xlm: f64 = match amount_input.parse() {
+        Ok(amt) if amt > 0.0 => amt,
+        _ => {
+            say!("❌ Invalid amount. Using default 100 {}.", pay_code);
+            100.0
+        }
+    };
+
+    if let Err(e) = execute_deposit(vault, user, risk_level, pay_with, (amount_xlm * 10_000_000.0) as u64, false, false, None).await {
+        say!("❌ {}", e);
+    }
+}
+
+// XLM or CODE:ISSUER
+fn parse_pay_with(input: &str) -> Result<Option<AssetId>, VaultError> {
+    if input.eq_ignore_ascii_case("xlm") {
+        return Ok(None);
+    }
+    AssetId::parse(input).map(Some)
+}
+
+// Deposits `amount_stroops` of `pay_with`, converting it to the vault's asset
+// when they differ; `confirmed` skips the conversion prompt, `dry_run` stops
+// before anything is signed and `key` is an idempotency key already started
+#[allow(clippy::too_many_arguments)]
+async fn execute_deposit(vault: &mut StellarVault, user: &str, risk_level: RiskLevel, pay_with: Option<AssetId>,
+                         amount_stroops: u64, confirmed: bool, dry_run: bool, key: Option<&str>) -> CommandResult {
+    let vault_asset = vault.get_vault_info(risk_level).and_then(|info| info.asset.clone());
+    let vault_code = path_payment::label(vault_asset.as_ref()).to_string();
+    let pay_code = path_payment::label(pay_with.as_ref()).to_string();
+
+    let conversion = if pay_with != vault_asset {
+        let quote = match path_payment::quote(&vault.horizon, pay_with.as_ref(), Stroops(amount_stroops),
+                                              vault_asset.as_ref(), vault.max_slippage_bps).await {
+            Ok(quote) => quote,
+            Err(e) => return Err(format!("Could not quote the conversion: {}", e)),
+        };
+        say!("\n💱 CONVERSION PREVIEW");
+        say!("   Sending: {} {}", quote.send_amount.to_xlm_string(), pay_code);
+        say!("   Expected: {} {}", quote.expected.to_xlm_string(), vault_code);
+        say!("   Rate: 1 {} = {:.7} {}", pay_code, quote.rate(), vault_code);
+        say!("   Minimum ({:.2}% max slippage): {} {}",
+            vault.max_slippage_bps as f64 / 100.0, quote.min_received.to_xlm_string(), vault_code);
+        if !quote.path.is_empty() {
+            let hops: Vec<&str> = quote.path.iter().map(|asset| path_payment::label(asset.as_ref())).collect();
+            say!("   Via: {}", hops.join(" → "));
+        }
+        let confirm = if confirmed || dry_run { "yes".to_string() } else { get_user_input("\nConvert and deposit? (yes/no): ").to_lowercase() };
+        if confirm != "yes" && confirm != "y" {
+            return Err("Deposit cancelled".to_string());
+        }
+        Some(quote)
+    } else {
+        None
+    };
+
+    if dry_run {
+        return match vault.preview_deposit(user, risk_level, amount_stroops, conversion.as_ref()).await {
+            Ok(preview) => {
+                say!("\n🧪 DRY RUN: nothing was signed or submitted");
+                if conversion.is_some() {
+                    say!("   Paid: {} {}", Stroops(amount_stroops).to_xlm_string(), pay_code);
+                }
+                say!("   Amount: {} {}", preview.amount.to_xlm_string(), vault_code);
+                say!("   Vault: {:?} Risk", risk_level);
+                say!("   Shares Minted: {}", preview.shares);
+                say!("   Insurance Fee: {} {}", preview.insurance.to_xlm_string(), vault_code);
+                say!("   Net Investment: {} {}", preview.net.to_xlm_string(), vault_code);
+                Ok(json!({
+                    "status": "dry_run",
+                    "risk": risk_level,
+                    "asset": vault_code,
+                    "paid": Stroops(amount_stroops).to_xlm_string(),
+                    "paid_asset": pay_code,
+                    "credited": preview.amount.to_xlm_string(),
+                    "shares": preview.shares,
+                    "insurance_fee": preview.insurance.to_xlm_string(),
+                    "net": preview.net.to_xlm_string(),
+                    "transaction": print_transaction_preview(&preview.transaction),
+                }))
+            }
+            Err(e) => Err(format!("Dry run failed: {}", e)),
+        };
+    }
+
+    // Premiums are dynamic, so read the fee this deposit will actually pay
+    let insurance_fee = vault.get_vault_info(risk_level)
+        .map(|v| v.insurance_fee as f64 / 100.0)
+        .unwrap_or(0.0);
+
+    say!("\n{}", "=".repeat(70));
+
+    // Process deposit
+    say!("\n📥 Processing your deposit to SYIA Vault...");
+    
+    match vault.deposit_keyed(user, risk_level, amount_stroops, conversion.as_ref(), key).await {
+        Ok((shares, credited_stroops, receipt)) => {
+            let credited = credited_stroops.0 as f64 / 10_000_000.0;
+            say!("\n✅ DEPOSIT COMPLETE!");
+            if conversion.is_some() {
+                say!("   Paid: {} {}", Stroops(amount_stroops).to_xlm_string(), pay_code);
+            }
+            say!("   Amount: {} {}", credited, vault_code);
+            say!("   Vault: {:?} Risk", risk_level);
+            say!("   Shares Received: {}", shares);
+            say!("   Insurance Fee: {:.2}% ({:.2} {})", 
+                insurance_fee, 
+                credited * insurance_fee / 100.0, vault_code);
+            say!("   Net Investment: {:.2} {}", 
+                credited * (1.0 - insurance_fee / 100.0), vault_code);
+            say!("   Transaction Hash: {}", receipt.hash);
+            say!("   Confirmed At: {} (ledger {})", receipt.created_at, receipt.ledger);
+            say!("   🔗 {}", vault.network.tx_link(&receipt.hash));
+            Ok(json!({
+                "risk": risk_level,
+                "asset": vault_code,
+                "paid": Stroops(amount_stroops).to_xlm_string(),
+                "paid_asset": pay_code,
+                "credited": credited_stroops.to_xlm_string(),
+                "shares": shares,
+                "insurance_fee_bps": (insurance_fee * 100.0).round() as u16,
+                "transaction": receipt_json(&receipt),
+            }))
+        },
+        Err(e) => Err(format!("Deposit failed: {}", e)),
+    }
+}
+
+// The unsigned transaction a dry run built, as JSON
+fn print_transaction_preview(preview: &TransactionPreview) -> serde_json::Value {
+    say!("   Network Fee: {}", preview.fee);
+    say!("   Transaction Hash: {}", preview.hash);
+    say!("   Unsigned XDR: {}", preview.xdr);
+    json!({
+        "hash": preview.hash,
+        "fee_per_operation": preview.fee.per_operation,
+        "xdr": preview.xdr,
+    })
+}
+
+fn receipt_json(receipt: &TransactionReceipt) -> serde_json::Value {
+    json!({
+        "hash": receipt.hash,
+        "ledger": receipt.ledger,
+        "created_at": receipt.created_at,
+        "fee_charged": receipt.fee_charged,
+    })
+}
+
+async fn run_withdraw(vault: &mut StellarVault, user: &str) {
+    say!("\n💸 Choose the vault to withdraw from:");
+    let risk_level = prompt_risk_level();
+
+    let shares_input = get_user_input("\n📤 Enter number of shares to withdraw: ");
+    let shares: u64 = match shares_input.parse() {
+        Ok(s) if s > 0 => s,
+        _ => {
+            say!("❌ Invalid share amount.");
+            return;
+        }
+    };
+
+    if let Err(e) = execute_withdraw(vault, user, risk_level, shares, false).await {
+        say!("❌ {}", e);
+    }
+}
+
+async fn execute_withdraw(vault: &mut StellarVault, user: &str, risk_level: RiskLevel, shares: u64,
+                          dry_run: bool) -> CommandResult {
+    say!("\n{}", "=".repeat(70));
+
+    if dry_run {
+        let preview = vault.preview_withdraw(user, risk_level, shares).await
+            .map_err(|e| format!("Dry run failed: {}", e))?;
+        let asset = vault.get_vault_info(risk_level).map(|info| info.asset_code()).unwrap_or_default();
+        say!("\n🧪 DRY RUN: nothing was signed or submitted");
+        say!("   Vault: {:?} Risk", risk_level);
+        say!("   Shares Burned: {}", shares);
+        say!("   Gross Amount: {} {}", preview.gross.to_xlm_string(), asset);
+        if preview.penalty > Stroops::ZERO {
+            say!("   Early Withdrawal Penalty: {} {}", preview.penalty.to_xlm_string(), asset);
+        }
+        say!("   Amount Received: {} {}", preview.net.to_xlm_string(), asset);
+        let transaction = match &preview.transaction {
+            Some(transaction) => print_transaction_preview(transaction),
+            None => {
+                say!("   ⏳ The liquid reserve can't cover this yet, so it would join the withdrawal queue");
+                serde_json::Value::Null
+            }
+        };
+        return Ok(json!({
+            "status": if preview.queued { "dry_run_queued" } else { "dry_run" },
+            "risk": risk_level,
+            "shares": shares,
+            "gross": preview.gross.to_xlm_string(),
+            "penalty": preview.penalty.to_xlm_string(),
+            "net": preview.net.to_xlm_string(),
+            "transaction": transaction,
+        }));
+    }
+
+    match vault.withdraw(user, risk_level, shares).await {
+        Ok(WithdrawalOutcome::Queued { id, ahead }) => {
+            say!("\n⏳ WITHDRAWAL QUEUED (request #{})", id);
+            say!("   The {:?} Risk Vault's liquid reserve can't cover this right now.", risk_level);
+            say!("   Requests ahead of yours: {}", ahead);
+            say!("   It will be paid as harvests and rebalances free up liquidity.");
+            Ok(json!({ "status": "queued", "risk": risk_level, "shares": shares, "request_id": id, "ahead": ahead }))
+        },
+        Ok(WithdrawalOutcome::Completed(withdrawal)) => {
+            say!("\n✅ WITHDRAWAL COMPLETE!");
+            say!("   Vault: {:?} Risk", risk_level);
+            say!("   Shares Burned: {}", shares);
+            say!("   Gross Amount: {:.7} XLM", withdrawal.gross_stroops as f64 / 10_000_000.0);
+            if withdrawal.penalty_stroops > 0 {
+                say!("   Early Withdrawal Penalty: {:.7} XLM", withdrawal.penalty_stroops as f64 / 10_000_000.0);
+            }
+            say!("   Amount Received: {:.7} XLM", withdrawal.net_stroops as f64 / 10_000_000.0);
+            say!("   Transaction Hash: {}", withdrawal.transaction.hash);
+            say!("   🔗 {}", vault.network.tx_link(&withdrawal.transaction.hash));
+            Ok(json!({
+                "status": "completed",
+                "risk": risk_level,
+                "shares": shares,
+                "gross": Stroops(withdrawal.gross_stroops).to_xlm_string(),
+                "penalty": Stroops(withdrawal.penalty_stroops).to_xlm_string(),
+                "net": Stroops(withdrawal.net_stroops).to_xlm_string(),
+                "transaction": receipt_json(&withdrawal.transaction),
+            }))
+        },
+        Err(e) => Err(format!("Withdrawal failed: {}", e)),
+    }
+}
+
+fn run_position(vault: &StellarVault, user: &str) {
+    say!("\n📈 Choose the vault to inspect:");
+    print_position(vault, user, prompt_risk_level());
+}
+
+// The position as JSON, null when there is none
+fn print_position(vault: &StellarVault, user: &str, risk_level: RiskLevel) -> serde_json::Value {
+    if vault.vault_contract.is_some() {
+        let shares = vault.contract_index.shares(user, risk_level);
+        let position = match vault.contract_index.vault(risk_level) {
+            Some(indexed) if shares > 0 => {
+                say!("\n📊 POSITION: {} Risk Vault (vault contract, as of ledger {})", risk_level_to_string(risk_level), indexed.ledger);
+                say!("   Shares: {}", shares);
+                say!("   Current Value: {:.7}", indexed.value_of(shares) as f64 / 10_000_000.0);
+                json!({ "shares": shares, "value": Stroops(indexed.value_of(shares)).to_xlm_string(), "ledger": indexed.ledger })
+            }
+            _ => {
+                say!("ℹ️  No position in the {:?} Risk Vault's contract", risk_level);
+                serde_json::Value::Null
+            }
+        };
+        return json!({ "risk": risk_level, "position": position });
+    }
+
+    let position = match vault.get_position(user, risk_level) {
+        Some(summary) => {
+            say!("\n📊 POSITION: {} Risk Vault", risk_level_to_string(summary.risk));
+            say!("   Shares: {}", summary.shares);
+            say!("   Current Value: {:.7} XLM", summary.value_stroops as f64 / 10_000_000.0);
+            say!("   Accumulated Yield: {:.7} XLM", summary.accumulated_yield as f64 / 10_000_000.0);
+            say!("   Cost Basis: {:.7} XLM", summary.cost_basis as f64 / 10_000_000.0);
+            say!("   Unrealized P&L: {:+.7} XLM", summary.unrealized_pnl() as f64 / 10_000_000.0);
+            say!("   Auto-Compound: {}", if summary.auto_compound { "ON" } else { "OFF" });
+            if summary.lock_remaining_secs > 0 {
+                say!("   🔒 Locked: {} remaining", format_duration(summary.lock_remaining_secs));
+            } else {
+                say!("   🔓 Unlocked");
+            }
+            position_json(&summary)
+        }
+        None => {
+            say!("ℹ️  No position in the {:?} Risk Vault", risk_level);
+            serde_json::Value::Null
+        }
+    };
+    json!({ "risk": risk_level, "position": position })
+}
+
+fn position_json(summary: &PositionSummary) -> serde_json::Value {
+    let pnl = summary.unrealized_pnl();
+    json!({
+        "shares": summary.shares,
+        "value": Stroops(summary.value_stroops).to_xlm_string(),
+        "accumulated_yield": Stroops(summary.accumulated_yield).to_xlm_string(),
+        "cost_basis": Stroops(summary.cost_basis).to_xlm_string(),
+        "unrealized_pnl": format!("{}{}", if pnl < 0 { "-" } else { "" }, Stroops(pnl.unsigned_abs() as u64).to_xlm_string()),
+        "insurance_paid": Stroops(summary.insurance_paid).to_xlm_string(),
+        "apy_bps": summary.apy,
+        "auto_compound": summary.auto_compound,
+        "lock_remaining_secs": summary.lock_remaining_secs,
+    })
+}
+
+// The account's wallet balances, then its vault positions
+async fn run_balance(vault: &StellarVault, user: &str) -> serde_json::Value {
+    let balances = match vault.horizon.account(user).await {
+        Ok(account) => {
+            say!("\n💰 WALLET: {}", user);
+            let balances: Vec<serde_json::Value> = account.balances.iter().map(|balance| {
+                let asset = if balance.is_native() { "XLM" } else { balance.asset_code.as_deref().unwrap_or("pool shares") };
+                say!("   {} {}", balance.balance, asset);
+                json!({ "asset": asset, "issuer": balance.asset_issuer, "balance": balance.balance })
+            }).collect();
+            json!(balances)
+        }
+        Err(e) => {
+            say!("⚠️  Could not fetch balance: {}", e);
+            serde_json::Value::Null
+        }
+    };
+    run_portfolio(vault, user);
+
+    let portfolio = vault.portfolio(user);
+    let positions: Vec<serde_json::Value> = portfolio.positions.iter()
+        .map(|summary| json!({ "risk": summary.risk, "position": position_json(summary) }))
+        .collect();
+    json!({
+        "account": user,
+        "balances": balances,
+        "positions": positions,
+        "total_value": Stroops(portfolio.total_value).to_xlm_string(),
+        "total_yield": Stroops(portfolio.total_yield).to_xlm_string(),
+        "blended_apy_bps": portfolio.blended_apy,
+    })
+}
+
+fn run_portfolio(vault: &StellarVault, user: &str) {
+    let portfolio = vault.portfolio(user);
+
+    if portfolio.positions.is_empty() {
+        say!("\nℹ️  No positions yet. Make a deposit to get started!");
+        return;
+    }
+
+    say!("\n💼 PORTFOLIO: {}", user);
+    say!("{}", "-".repeat(70));
+    say!("{:<8} {:>14} {:>16} {:>14} {:>14}", "Vault", "Shares", "Value (XLM)", "Yield (XLM)", "APY");
+    say!("{}", "-".repeat(70));
+    for p in &portfolio.positions {
+        say!("{:<8} {:>14} {:>16.7} {:>14.7} {:>13.2}%",
+            risk_level_to_string(p.risk),
+            p.shares,
+            p.value_stroops as f64 / 10_000_000.0,
+            p.accumulated_yield as f64 / 10_000_000.0,
+            p.apy as f64 / 100.0);
+    }
+    say!("{}", "-".repeat(70));
+    say!("{:<8} {:>14} {:>16.7} {:>14.7} {:>13.2}%",
+        "Total",
+        "",
+        portfolio.total_value as f64 / 10_000_000.0,
+        portfolio.total_yield as f64 / 10_000_000.0,
+        portfolio.blended_apy as f64 / 100.0);
+    say!("\n   Cost Basis: {:.7} XLM", portfolio.total_cost_basis as f64 / 10_000_000.0);
+    say!("   Insurance Paid: {:.7} XLM", portfolio.total_insurance_paid as f64 / 10_000_000.0);
+}
+
+fn run_compound_toggle(vault: &mut StellarVault, user: &str) {
+    say!("\n🔁 Choose the vault position to configure:");
+    let risk_level = prompt_risk_level();
+
+    let current = vault.get_position(user, risk_level).map(|p| p.auto_compound).unwrap_or(false);
+    match vault.set_auto_compound(user, risk_level, !current) {
+        Ok(()) => say!("✅ Auto-compound for {:?} Risk Vault is now {}",
+            risk_level,
+            if current { "OFF" } else { "ON" }),
+        Err(e) => say!("❌ Could not update auto-compound: {}", e),
+    }
+}
+
+async fn run_harvest(vault: &mut StellarVault) {
+    say!("\n🌾 Choose the vault to harvest:");
+    if let Err(e) = execute_harvest(vault, prompt_risk_level()).await {
+        say!("❌ {}", e);
+    }
+}
+
+async fn execute_harvest(vault: &mut StellarVault, risk_level: RiskLevel) -> CommandResult {
+    // Fills since the last harvest decide the market-making yield, pool and
+    // pair reserves the liquidity strategies', and Blend's b_rate the lending
+    // interest
+    if let Err(e) = vault.sync_market_making(risk_level).await {
+        say!("   ⚠️  Could not read DEX trades; market-making spread waits for the next harvest: {}", e);
+    }
+    if let Err(e) = vault.sync_liquidity_pool(risk_level).await {
+        say!("   ⚠️  Could not value the liquidity pool stake; its yield waits for the next harvest: {}", e);
+    }
+    if let Err(e) = vault.sync_blend(risk_level).await {
+        say!("   ⚠️  Could not value the Blend position; its interest waits for the next harvest: {}", e);
+    }
+    if let Err(e) = vault.sync_soroswap(risk_level).await {
+        say!("   ⚠️  Could not value the Soroswap liquidity; its fees wait for the next harvest: {}", e);
+    }
+
+    match vault.harvest(risk_level) {
+        Ok(report) => {
+            say!("\n✅ HARVEST COMPLETE: {} Risk Vault", risk_level_to_string(report.risk));
+            say!("   Period: {} seconds", report.elapsed_secs);
+            say!("   Total Yield: {:.7} XLM", report.total_yield as f64 / 10_000_000.0);
+            say!("   Performance Fee: {:.7} XLM", report.performance_fee as f64 / 10_000_000.0);
+            say!("   Credited to Positions: {:.7} XLM", report.distributed_yield as f64 / 10_000_000.0);
+            say!("   Auto-Compounded: {:.7} XLM ({} shares)",
+                report.compounded_yield as f64 / 10_000_000.0,
+                report.compounded_shares);
+            Ok(json!({
+                "risk": report.risk,
+                "elapsed_secs": report.elapsed_secs,
+                "total_yield": Stroops(report.total_yield).to_xlm_string(),
+                "performance_fee": Stroops(report.performance_fee).to_xlm_string(),
+                "distributed_yield": Stroops(report.distributed_yield).to_xlm_string(),
+                "compounded_yield": Stroops(report.compounded_yield).to_xlm_string(),
+                "compounded_shares": report.compounded_shares,
+            }))
+        }
+        Err(e) => Err(format!("Harvest failed: {}", e)),
+    }
+}
+
+async fn run_liquidity_pool(vault: &mut StellarVault) {
+    say!("\n💧 Choose the vault whose pool to manage:");
+    let risk = prompt_risk_level();
+
+    let pool = match vault.sync_liquidity_pool(risk).await {
+        Ok(Some(pool)) => pool,
+        Ok(None) => {
+            say!("ℹ️  The {:?} Risk Vault has no [liquidity_pool] section configured", risk);
+            return;
+        }
+        Err(e) => {
+            say!("❌ {}", e);
+            return;
+        }
+    };
+    let Some(info) = vault.get_vault_info(risk) else {
+        say!("❌ Vault not found");
+        return;
+    };
+    let Some(position) = &info.liquidity_pool else {
+        return;
+    };
+    let xlm = |stroops: u64| stroops as f64 / 10_000_000.0;
+    let code = info.asset_code().to_string();
+    let allocated = info.strategies.iter()
+        .find(|s| s.strategy == strategy::AQUA_LIQUIDITY_POOL)
+        .map_or(0, |s| s.total_allocated);
+
+    say!("\n💧 LIQUIDITY POOL: {}", sdex::pair_label(info.asset.as_ref(), position.counter.as_ref()));
+    say!("   Pool: {}", pool.id);
+    for reserve in &pool.reserves {
+        let asset = reserve.asset.split(':').next().filter(|code| *code != "native").unwrap_or("XLM");
+        say!("   Reserve: {} {}", reserve.amount, asset);
+    }
+    say!("   Vault Shares: {} of {}", Stroops(position.shares).to_xlm_string(), pool.total_shares);
+    say!("   Position Value: {:.7} {}", xlm(position.value), code);
+    say!("   Deposited: {:.7} {} of {:.7} allocated", xlm(position.deposited), code, xlm(allocated));
+
+    let action = get_user_input("\nAction (deposit/withdraw, Enter to skip): ").to_lowercase();
+    match action.as_str() {
+        "deposit" => match vault.deposit_liquidity(risk).await {
+            Ok((receipt, amount)) => {
+                say!("✅ Deposited {:.7} {} into the pool", xlm(amount), code);
+                say!("   🔗 {}", vault.network.tx_link(&receipt.hash));
+            }
+            Err(e) => say!("❌ Pool deposit failed: {}", e),
+        },
+        "withdraw" => match vault.withdraw_liquidity(risk).await {
+            Ok(receipt) => {
+                say!("✅ Withdrew the vault's pool shares");
+                say!("   🔗 {}", vault.network.tx_link(&receipt.hash));
+            }
+            Err(e) => say!("❌ Pool withdrawal failed: {}", e),
+        },
+        _ => {}
+    }
+}
+
+// How far back the events command looks: about an hour and a half of ledgers
+const EVENT_LOOKBACK_LEDGERS: u32 = 1_000;
+const EVENT_PAGE_LIMIT: u32 = 50;
+
+async fn print_contract_code(rpc: &soroban::RpcClient, label: &str, contract: &str) {
+    match rpc.contract_executable(contract).await {
+        Ok(Some(executable)) => say!("   {} Code: {}", label, executable),
+        Ok(None) => say!("   ⚠️  {} {} is not deployed on this network, or its instance has expired", label, contract),
+        Err(e) => say!("   ⚠️  Could not read the {} contract: {}", label, e),
+    }
+}
+
+// Recent events from the contracts the strategies call
+async fn run_contract_events(vault: &StellarVault) {
+    let Some(rpc) = &vault.soroban else {
+        say!("ℹ️  No Soroban RPC server is configured; set soroban_rpc_url");
+        return;
+    };
+    let mut contracts: Vec<String> = vault.vaults.values()
+        .flat_map(|v| [v.lending.as_ref().map(|p| p.pool.clone()), v.soroswap.as_ref().map(|p| p.router.clone())])
+        .flatten()
+        .collect();
+    contracts.sort();
+    contracts.dedup();
+    if contracts.is_empty() {
+        say!("ℹ️  No [blend] or [soroswap] contracts are configured");
+        return;
+    }
+
+    let start = match rpc.latest_ledger().await {
+        Ok(latest) => latest.saturating_sub(EVENT_LOOKBACK_LEDGERS),
+        Err(e) => {
+            say!("❌ {}", e);
+            return;
+        }
+    };
+    let page = match rpc.get_events(&contracts, Some(start), None, EVENT_PAGE_LIMIT).await {
+        Ok(page) => page,
+        Err(e) => {
+            say!("❌ {}", e);
+            return;
+        }
+    };
+
+    say!("\n📜 CONTRACT EVENTS since ledger {}:", start);
+    if page.events.is_empty() {
+        say!("   None");
+    }
+    for event in &page.events {
+        let topics = match event.topics() {
+            Ok(topics) => topics.iter().map(soroban::describe).collect::<Vec<_>>().join(" "),
+            Err(e) => format!("unreadable topics ({})", e),
+        };
+        let data = event.data().map_or_else(|e| format!("unreadable ({})", e), |data| soroban::describe(&data));
+        say!("\n   {} [ledger {}, {}] {}", event.id, event.ledger, event.ledger_closed_at, event.contract_id);
+        say!("   {}: {}", topics, data);
+        say!("   🔗 {}", vault.network.tx_link(&event.tx_hash));
+    }
+    if let Some(cursor) = page.cursor.filter(|_| page.events.len() as u32 == EVENT_PAGE_LIMIT) {
+        say!("\n   Showing the first {}; later events follow cursor {}", EVENT_PAGE_LIMIT, cursor);
+    }
+}
+
+async fn run_vault_contract(vault: &StellarVault, user: &str) {
+    let contract = match vault.vault_contract_call() {
+        Ok((_, contract)) => contract.to_string(),
+        Err(e) => {
+            say!("ℹ️  {}", e);
+            return;
+        }
+    };
+    let xlm = |stroops: u64| stroops as f64 / 10_000_000.0;
+
+    say!("\n📜 VAULT CONTRACT: {}", contract);
+    for risk in RiskLevel::ALL {
+        let state = match vault.contract_vault(risk).await {
+            Ok(state) => state,
+            Err(e) => {
+                say!("\n   {:?} Risk: not readable ({})", risk, e);
+                continue;
+            }
+        };
+        let code = vault.get_vault_info(risk).map_or("XLM", Vault::asset_code).to_string();
+        say!("\n   {:?} Risk", risk);
+        say!("   Total Assets: {:.7} {}", xlm(state.total_assets), code);
+        say!("   Total Shares: {}", Stroops(state.total_shares).to_xlm_string());
+        say!("   Share Price: {:.7} {}", state.share_price(), code);
+        if let Some((_, share_token)) = vault.share_tokens.iter().find(|(r, _)| *r == risk) {
+            say!("   Share Token: {}", share_token);
+        }
+        say!("   Insurance Fee: {:.2}% (reserve {:.7} {})", state.insurance_fee_bps as f64 / 100.0, xlm(state.insurance_reserve), code);
+        for (kind, allocation_bps) in &state.strategies {
+            say!("   Strategy: {} ({:.2}%)", kind, *allocation_bps as f64 / 100.0);
+        }
+        match vault.contract_shares(user, risk).await {
+            Ok(shares) => say!("   Your Shares: {} (≈ {:.7} {})", shares, xlm(shares) * state.share_price(), code),
+            Err(e) => say!("   ⚠️  Could not read your shares: {}", e),
+        }
+    }
+
+    let action = get_user_input("\nAction (configure/harvest, Enter to skip): ").to_lowercase();
+    match action.as_str() {
+        "configure" => {
+            let risk = prompt_risk_level();
+            match vault.configure_contract(risk).await {
+                Ok(receipt) => {
+                    say!("✅ Copied the {:?} Risk Vault's fee and strategies into the contract", risk);
+                    say!("   🔗 {}", vault.network.tx_link(&receipt.hash));
+                }
+                Err(e) => say!("❌ Configuring the contract failed: {}", e),
+            }
+        }
+        "harvest" => {
+            let risk = prompt_risk_level();
+            let amount = match Stroops::from_xlm_str(&get_user_input("Yield to pay into the contract: ")) {
+                Ok(amount) => amount,
+                Err(e) => {
+                    say!("❌ {}", e);
+                    return;
+                }
+            };
+            match vault.harvest_to_contract(risk, amount.0).await {
+                Ok(receipt) => {
+                    say!("✅ Paid {} of yield into the {:?} Risk Vault", amount.to_xlm_string(), risk);
+                    say!("   🔗 {}", vault.network.tx_link(&receipt.hash));
+                }
+                Err(e) => say!("❌ Contract harvest failed: {}", e),
+            }
+        }
+        _ => {}
+    }
+}
+
+// Where `stellar contract build --profile contract` leaves the contracts
+const DEFAULT_CONTRACT_WASM: &str = "target/wasm32v1-none/contract/stellarvault_contract.wasm";
+const DEFAULT_SHARE_TOKEN_WASM: &str = "target/wasm32v1-none/contract/stellarvault_share_token.wasm";
+
+fn read_contract_wasm(label: &str, default: &str) -> Result<Vec<u8>, VaultError> {
+    let path = get_user_input(&format!("{} Wasm [{}]: ", label, default));
+    let path = if path.is_empty() { default } else { path.as_str() };
+    std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e).into())
+}
+
+// Deploys a fresh vault contract and records it in the config file, so
+// deposits and withdrawals go through it from now on
+async fn run_contract_deploy(vault: &mut StellarVault, config: &Config) -> bool {
+    if let Some(contract) = &vault.vault_contract {
+        say!("⚠️  Vault contract {} is already configured; shares held there stay with it", contract);
+        if !get_user_input("Deploy a new contract and switch to it? (y/N): ").eq_ignore_ascii_case("y") {
+            return false;
+        }
+    }
+    let wasm = read_contract_wasm("Vault contract", DEFAULT_CONTRACT_WASM)
+        .and_then(|wasm| Ok((wasm, read_contract_wasm("Share token", DEFAULT_SHARE_TOKEN_WASM)?)));
+    let (wasm, share_token_wasm) = match wasm {
+        Ok(wasm) => wasm,
+        Err(e) => {
+            say!("❌ {}", e);
+            return false;
+        }
+    };
+
+    say!("\n🚀 Deploying the vault contract...");
+    let contract = match vault.deploy_contract(&wasm, &share_token_wasm).await {
+        Ok(contract) => contract,
+        Err(e) => {
+            say!("❌ Contract deployment failed: {}", e);
+            return false;
+        }
+    };
+    say!("✅ Vault contract {} deployed and configured for every risk level", contract);
+
+    let path = config.path();
+    match Config::record(&path, "vault_contract", &contract) {
+        Ok(()) => say!("   📝 Recorded vault_contract in {}", path.display()),
+        Err(e) => say!("⚠️  Set vault_contract = \"{}\" in your config yourself: {}", contract, e),
+    }
+    true
+}
+
+async fn run_contract_upgrade(vault: &StellarVault) {
+    let contract = match vault.vault_contract_call() {
+        Ok((rpc, contract)) => {
+            print_contract_code(rpc, "Vault", contract).await;
+            contract.to_string()
+        }
+        Err(e) => {
+            say!("ℹ️  {}", e);
+            return;
+        }
+    };
+    let wasm = match read_contract_wasm("Vault contract", DEFAULT_CONTRACT_WASM) {
+        Ok(wasm) => wasm,
+        Err(e) => {
+            say!("❌ {}", e);
+            return;
+        }
+    };
+
+    say!("\n⬆️  Upgrading vault contract {}...", contract);
+    match vault.upgrade_contract(&wasm).await {
+        Ok(receipt) => {
+            say!("✅ Vault contract now runs code {}", soroban::hex(&soroban::wasm_hash(&wasm)));
+            say!("   🔗 {}", vault.network.tx_link(&receipt.hash));
+        }
+        Err(e) => say!("❌ Contract upgrade failed: {}", e),
+    }
+}
+
+async fn run_blend(vault: &mut StellarVault) {
+    say!("\n🏦 Choose the vault whose lending to manage:");
+    let risk = prompt_risk_level();
+
+    match vault.sync_blend(risk).await {
+        Ok(true) => {}
+        Ok(false) => {
+            say!("ℹ️  The {:?} Risk Vault has no [blend] section configured", risk);
+            return;
+        }
+        Err(e) => {
+            say!("❌ {}", e);
+            return;
+        }
+    }
+    let Some(info) = vault.get_vault_info(risk) else {
+        say!("❌ Vault not found");
+        return;
+    };
+    let Some(position) = &info.lending else {
+        return;
+    };
+    let xlm = |stroops: u64| stroops as f64 / 10_000_000.0;
+    let code = info.asset_code().to_string();
+    let allocated = info.strategies.iter()
+        .find(|s| s.strategy == strategy::YIELDBLOX_LENDING)
+        .map_or(0, |s| s.total_allocated);
+
+    say!("\n🏦 BLEND LENDING: {}", code);
+    say!("   Pool: {}", position.pool);
+    if let Some(rpc) = &vault.soroban {
+        say!("   Soroban RPC: {}", rpc.url());
+        print_contract_code(rpc, "Pool", &position.pool).await;
+    }
+    say!("   b-Tokens: {}", Stroops(position.b_tokens).to_xlm_string());
+    say!("   Position Value: {:.7} {}", xlm(position.value), code);
+    say!("   Supplied: {:.7} {} of {:.7} allocated", xlm(position.supplied), code, xlm(allocated));
+    match position.supply_apy_bps {
+        Some(apy) => say!("   Supply APY: {:.2}%", apy as f64 / 100.0),
+        None => say!("   Supply APY: measured once b_rate has been sampled an hour apart"),
+    }
+
+    let action = get_user_input("\nAction (supply/withdraw, Enter to skip): ").to_lowercase();
+    match action.as_str() {
+        "supply" => match vault.supply_blend(risk).await {
+            Ok((receipt, amount)) => {
+                say!("✅ Lent {:.7} {} to the pool", xlm(amount), code);
+                say!("   🔗 {}", vault.network.tx_link(&receipt.hash));
+            }
+            Err(e) => say!("❌ Blend supply failed: {}", e),
+        },
+        "withdraw" => match vault.withdraw_blend(risk).await {
+            Ok((receipt, value)) => {
+                say!("✅ Withdrew about {:.7} {} from the pool", xlm(value), code);
+                say!("   🔗 {}", vault.network.tx_link(&receipt.hash));
+            }
+            Err(e) => say!("❌ Blend withdrawal failed: {}", e),
+        },
+        _ => {}
+    }
+}
+
+async fn run_soroswap(vault: &mut StellarVault) {
+    say!("\n🔄 Choose the vault whose Soroswap liquidity to manage:");
+    let risk = prompt_risk_level();
+
+    let pair = match vault.sync_soroswap(risk).await {
+        Ok(Some(pair)) => pair,
+        Ok(None) => {
+            say!("ℹ️  The {:?} Risk Vault has no [soroswap] section configured", risk);
+            return;
+        }
+        Err(e) => {
+            say!("❌ {}", e);
+            return;
+        }
+    };
+    let Some(info) = vault.get_vault_info(risk) else {
+        say!("❌ Vault not found");
+        return;
+    };
+    let Some(position) = &info.soroswap else {
+        return;
+    };
+    let xlm = |stroops: u64| stroops as f64 / 10_000_000.0;
+    let code = info.asset_code().to_string();
+    let counter_code = path_payment::label(position.counter.as_ref()).to_string();
+    let allocated = info.strategies.iter()
+        .find(|s| s.strategy == strategy::SOROSWAP_LIQUIDITY)
+        .map_or(0, |s| s.total_allocated);
+
+    say!("\n🔄 SOROSWAP: {}", sdex::pair_label(info.asset.as_ref(), position.counter.as_ref()));
+    say!("   Router: {}", position.router);
+    if let Some(rpc) = &vault.soroban {
+        print_contract_code(rpc, "Router", &position.router).await;
+    }
+    say!("   Pair: {}", pair.pair);
+    say!("   Reserves: {:.7} {} / {:.7} {}", xlm(pair.base_reserve), code, xlm(pair.counter_reserve), counter_code);
+    if pair.base_reserve > 0 {
+        say!("   Price: {:.7} {} per {}", pair.price(), counter_code, code);
+    }
+    say!("   Vault Shares: {} of {}", Stroops(position.shares).to_xlm_string(), Stroops(pair.total_shares).to_xlm_string());
+    say!("   Position Value: {:.7} {}", xlm(position.value), code);
+    say!("   Deposited: {:.7} {} of {:.7} allocated", xlm(position.deposited), code, xlm(allocated));
+
+    let action = get_user_input("\nAction (quote/deposit/withdraw, Enter to skip): ").to_lowercase();
+    match action.as_str() {
+        "quote" => {
+            let amount = match Stroops::from_xlm_str(&get_user_input(&format!("Amount of {} to swap: ", code))) {
+                Ok(amount) => amount,
+                Err(e) => {
+                    say!("❌ {}", e);
+                    return;
+                }
+            };
+            match vault.soroswap_quote(risk, amount.0, true).await {
+                Ok(out) => say!("💱 {} {} swaps for about {:.7} {}", amount.to_xlm_string(), code, xlm(out), counter_code),
+                Err(e) => say!("❌ Quote failed: {}", e),
+            }
+        }
+        "deposit" => match vault.deposit_soroswap(risk).await {
+            Ok((receipt, amount)) => {
+                say!("✅ Added {:.7} {} of liquidity to the pair", xlm(amount), code);
+                say!("   🔗 {}", vault.network.tx_link(&receipt.hash));
+            }
+            Err(e) => say!("❌ Soroswap deposit failed: {}", e),
+        },
+        "withdraw" => match vault.withdraw_soroswap(risk).await {
+            Ok(receipt) => {
+                say!("✅ Removed the vault's liquidity from the pair");
+                say!("   🔗 {}", vault.network.tx_link(&receipt.hash));
+            }
+            Err(e) => say!("❌ Soroswap withdrawal failed: {}", e),
+        },
+        _ => {}
+    }
+}
+
+async fn run_market_making(vault: &mut StellarVault) {
+    say!("\n📈 Choose the market-making vault:");
+    let risk = prompt_risk_level();
+
+    match vault.sync_market_making(risk).await {
+        Ok(0) => {}
+        Ok(count) => say!("   📥 Counted {} new fill(s)", count),
+        Err(e) => say!("   ⚠️  Could not read DEX trades: {}", e),
+    }
+    let Some(info) = vault.get_vault_info(risk) else {
+        say!("❌ Vault not found");
+        return;
+    };
+    let Some(maker) = &info.market_making else {
+        say!("ℹ️  The {:?} Risk Vault has no [market_making] section configured", risk);
+        return;
+    };
+    let xlm = |stroops: u64| stroops as f64 / 10_000_000.0;
+    let (base, counter) = (info.asset_code(), path_payment::label(maker.counter.as_ref()));
+    let allocation = info.strategies.iter()
+        .find(|s| s.strategy == strategy::SDEX_MARKET_MAKING)
+        .map_or(0, |s| s.total_allocated);
+
+    say!("\n📈 SDEX MARKET MAKING: {}", sdex::pair_label(info.asset.as_ref(), maker.counter.as_ref()));
+    say!("   Spread: {:.2}%", maker.spread_bps as f64 / 100.0);
+    say!("   Allocation: {:.7} {}", xlm(allocation), base);
+    say!("   Sold: {:.7} {} for {:.7} {}", xlm(maker.fills.base_sold), base, xlm(maker.fills.counter_received), counter);
+    say!("   Bought: {:.7} {} for {:.7} {}", xlm(maker.fills.base_bought), base, xlm(maker.fills.counter_spent), counter);
+    say!("   Realized Spread: {:.7} {} ({:.7} harvested)", xlm(maker.fills.realized_spread()), base, xlm(maker.harvested));
+
+    match vault.horizon.offers(&vault.vault_address).await {
+        Ok(offers) if offers.is_empty() => say!("   No open offers"),
+        Ok(offers) => {
+            for offer in offers {
+                let selling = offer.selling.asset_code.as_deref().unwrap_or("XLM");
+                let buying = offer.buying.asset_code.as_deref().unwrap_or("XLM");
+                say!("   Offer #{}: {} {} for {} at {}/{}", offer.id, offer.amount, selling, buying,
+                    offer.price_r.n, offer.price_r.d);
+            }
+        }
+        Err(e) => say!("   ⚠️  Could not load open offers: {}", e),
+    }
+
+    let confirm = get_user_input("\nReplace the offers around the current mid price? (yes/no): ").to_lowercase();
+    if confirm != "yes" && confirm != "y" {
+        return;
+    }
+    match vault.refresh_offers(risk).await {
+        Ok(receipt) => {
+            say!("✅ Offers placed in transaction {}", receipt.hash);
+            say!("   🔗 {}", vault.network.tx_link(&receipt.hash));
+        }
+        Err(e) => say!("❌ Could not place offers: {}", e),
+    }
+}
+
+fn run_vault_info(vault: &StellarVault) {
+    say!("\n🏦 Choose the vault to inspect:");
+    if let Err(e) = print_vault_info(vault, prompt_risk_level()) {
+        say!("❌ {}", e);
+    }
+}
+
+fn print_vault_info(vault: &StellarVault, risk_level: RiskLevel) -> CommandResult {
+    let info = vault.get_vault_info(risk_level).ok_or("Vault not found")?;
+
+    let xlm = |stroops: u64| stroops as f64 / 10_000_000.0;
+    let accrued_management = info.fee_accrual.management_accrued + fees::pending_management_fee(info, unix_now()).unwrap_or(0);
+
+    let code = info.asset_code();
+    say!("\n🏦 {} RISK VAULT", risk_level_to_string(risk_level).to_uppercase());
+    if info.circuit.paused {
+        say!("   ⛔ PAUSED: {}", info.circuit.reason.as_deref().unwrap_or("paused by admin"));
+    }
+    if let Some(asset) = &info.asset {
+        say!("   Asset: {}", asset);
+    }
+    let tvl_usd = vault.prices.usd_value(info.asset.as_ref(), info.total_value, unix_now());
+    match tvl_usd {
+        Some(usd) => say!("   TVL: {:.7} {} ({}; {} across all vaults)", xlm(info.total_value), code, format_usd(usd), format_usd(vault.tvl_usd().0)),
+        None => say!("   TVL: {:.7} {}", xlm(info.total_value), code),
+    }
+    say!("   Total Shares: {}", info.total_shares);
+    say!("   Share Price: {:.7} {}", xlm(info.get_share_price()), code);
+    say!("   Liquid Reserve: {:.7} {} ({:.2}% buffer target)",
+        xlm(info.liquid_reserve), code,
+        info.liquidity_buffer_bps as f64 / 100.0);
+    say!("   Blended APY: {:.2}%", info.blended_apy() as f64 / 100.0);
+    say!("   Insurance Fee: {:.2}%", info.insurance_fee as f64 / 100.0);
+    say!("   Insurance Pool: {:.7} {}", xlm(vault.insurance_balance(info.asset.as_ref()).0), code);
+    if let Some(indexed) = vault.contract_index.vault(risk_level) {
+        say!("\n📜 Vault Contract (as of ledger {}):", indexed.ledger);
+        say!("   TVL: {:.7} {} ({:.7} across all vaults)", xlm(indexed.total_assets), code, xlm(vault.contract_index.tvl()));
+        say!("   Total Shares: {}", indexed.total_shares);
+        say!("   Share Price: {:.7} {}", indexed.share_price(), code);
+        say!("   Insurance Reserve: {:.7} {}", xlm(indexed.insurance_reserve), code);
+    }
+    say!("\n💵 Fees:");
+    say!("   Management Fee: {:.2}% / year", info.fees.management_fee_bps as f64 / 100.0);
+    say!("   Performance Fee: {:.2}% of yield", info.fees.performance_fee_bps as f64 / 100.0);
+    say!("   Accrued Management: {:.7} {}", xlm(accrued_management), code);
+    say!("   Accrued Performance: {:.7} {}", xlm(info.fee_accrual.performance_accrued), code);
+    say!("   Collected to Treasury: {:.7} {}", xlm(info.fee_accrual.total_collected), code);
+    if let Some(treasury) = &vault.treasury_address {
+        say!("   Treasury: {}", treasury);
+    }
+    say!("\n📐 Strategies:");
+    for strategy in &info.strategies {
+        say!("   {}: {}% target, {:.7} {} allocated, {:.2}% APY",
+            strategy.strategy,
+            strategy.allocation_percentage,
+            xlm(strategy.total_allocated), code,
+            strategy::lookup(&strategy.strategy).current_apy(strategy) as f64 / 100.0);
+    }
+
+    let amount = |stroops: u64| Stroops(stroops).to_xlm_string();
+    Ok(json!({
+        "risk": risk_level,
+        "asset": code,
+        "paused": info.circuit.paused,
+        "tvl": amount(info.total_value),
+        "tvl_usd": tvl_usd.map(amount),
+        "total_shares": info.total_shares,
+        "share_price": amount(info.get_share_price()),
+        "liquid_reserve": amount(info.liquid_reserve),
+        "liquidity_buffer_bps": info.liquidity_buffer_bps,
+        "apy_bps": info.blended_apy(),
+        "insurance_fee_bps": info.insurance_fee,
+        "insurance_pool": amount(vault.insurance_balance(info.asset.as_ref()).0),
+        "fees": {
+            "management_fee_bps": info.fees.management_fee_bps,
+            "performance_fee_bps": info.fees.performance_fee_bps,
+            "accrued_management": amount(accrued_management),
+            "accrued_performance": amount(info.fee_accrual.performance_accrued),
+            "collected": amount(info.fee_accrual.total_collected),
+        },
+        "strategies": info.strategies.iter().map(|strategy| json!({
+            "id": strategy.strategy,
+            "allocation": strategy.allocation_percentage,
+            "allocated": amount(strategy.total_allocated),
+            "apy_bps": strategy::lookup(&strategy.strategy).current_apy(strategy),
+        })).collect::<Vec<_>>(),
+    }))
+}
+
+async fn run_collect_fees(vault: &mut StellarVault) {
+    say!("\n💵 Choose the vault to collect fees from:");
+    let risk_level = prompt_risk_level();
+
+    match vault.collect_fees(risk_level).await {
+        Ok((amount_stroops, receipt)) => {
+            say!("\n✅ FEES COLLECTED: {:.7} XLM", amount_stroops as f64 / 10_000_000.0);
+            say!("   Transaction Hash: {}", receipt.hash);
+        }
+        Err(e) => say!("❌ Fee collection failed: {}", e),
+    }
+}
+
+async fn run_distribute_yield(vault: &mut StellarVault) {
+    say!("\n💸 Choose the vault whose yield to distribute:");
+    let risk = prompt_risk_level();
+    match vault.distribute_yield(risk).await {
+        Ok(Some((receipt, payouts))) => {
+            let total: u64 = payouts.iter().map(|p| p.amount_stroops).sum();
+            say!("\n✅ {} paid to {} holder(s) as claimable balances", Stroops(total), payouts.len());
+            say!("   Ledger {} ({})", receipt.ledger, vault.network.tx_link(&receipt.hash));
+            say!("   💡 Each balance holds {} of the vault's reserve until it is claimed", Stroops(transaction::BASE_RESERVE));
+        }
+        Ok(None) => say!("\nℹ️  No accumulated yield to distribute in the {} Risk Vault", risk_level_to_string(risk)),
+        Err(e) => say!("❌ {}", e),
+    }
+}
+
+// Yield balances the vault created for `user` that are still unclaimed
+async fn yield_balances(vault: &StellarVault, user: &str) -> Result<Vec<horizon::ClaimableBalance>, VaultError> {
+    let balances = vault.horizon.claimable_balances(user, transaction::MAX_OPERATIONS as u32).await?;
+    Ok(balances.into_iter()
+        .filter(|balance| balance.sponsor.as_deref() == Some(vault.vault_address.as_str()))
+        .collect())
+}
+
+async fn run_yield_balances(vault: &StellarVault, user: &str) {
+    match yield_balances(vault, user).await {
+        Ok(balances) if balances.is_empty() => say!("\nℹ️  No unclaimed yield for {}", user),
+        Ok(balances) => {
+            say!("\n🎁 UNCLAIMED YIELD");
+            for balance in &balances {
+                let asset = balance.asset.split(':').next().filter(|code| *code != "native").unwrap_or("XLM");
+                say!("   {} {}  {}  ({})", balance.amount, asset, balance.last_modified_time.as_deref().unwrap_or("-"), balance.id);
+            }
+            say!("   💡 Use 'claim-yield' to claim them all");
+        }
+        Err(e) => say!("❌ Could not list claimable balances: {}", e),
+    }
+}
+
+async fn run_claim_yield(vault: &StellarVault, user: &str) {
+    let client = match vault.users.get(user) {
+        Ok(client) => client,
+        Err(e) => {
+            say!("❌ {}", e);
+            return;
+        }
+    };
+    let balances = match yield_balances(vault, user).await {
+        Ok(balances) if balances.is_empty() => {
+            say!("\nℹ️  No unclaimed yield for {}", user);
+            return;
+        }
+        Ok(balances) => balances,
+        Err(e) => {
+            say!("❌ Could not list claimable balances: {}", e);
+            return;
+        }
+    };
+    let ids: Vec<String> = balances.iter().map(|balance| balance.id.clone()).collect();
+    say!("\n🎁 Claiming {} yield balance(s)...", ids.len());
+    match client.claim_balances(&ids).await {
+        Ok(receipt) => say!("✅ Yield claimed in ledger {} ({})", receipt.ledger, vault.network.tx_link(&receipt.hash)),
+        Err(e) => say!("❌ Could not claim yield: {}", e),
+    }
+}
+
+async fn run_claims(vault: &mut StellarVault, user: &str) {
+    let action = get_user_input("\n🛡️  Claims (file/list/all/approve/deny/pay): ").to_lowercase();
+
+    match action.as_str() {
+        "file" => {
+            say!("Choose the vault that suffered the loss:");
+            let risk_level = prompt_risk_level();
+            let amount_xlm: f64 = match get_user_input("Claim amount (XLM): ").parse() {
+                Ok(amt) if amt > 0.0 => amt,
+                _ => {
+                    say!("❌ Invalid amount.");
+                    return;
+                }
+            };
+            let loss_event = get_user_input("Describe the loss event: ");
+            let amount_stroops = (amount_xlm * 10_000_000.0) as u64;
+
+            match vault.file_claim(user, risk_level, amount_stroops, &loss_event) {
+                Ok(id) => say!("✅ Claim #{} filed and pending review", id),
+                Err(e) => say!("❌ Could not file claim: {}", e),
+            }
+        }
+        "list" | "all" => {
+            let claims: Vec<&Claim> = if action == "all" {
+                vault.claims.all().iter().collect()
+            } else {
+                vault.claims.for_claimant(user).collect()
+            };
+            if claims.is_empty() {
+                say!("ℹ️  No claims filed");
+                return;
+            }
+            for claim in claims {
+                say!("   #{} [{:?}] {} — {:.7} XLM from {:?} Risk Vault",
+                    claim.id,
+                    claim.status,
+                    claim.claimant,
+                    claim.amount_stroops as f64 / 10_000_000.0,
+                    claim.risk);
+                say!("      Loss: {}", claim.loss_event);
+                if let Some(note) = &claim.decision_note {
+                    say!("      Decision: {}", note);
+                }
+                if let Some(tx) = &claim.payout_tx {
+                    say!("      Payout: {}", vault.network.tx_link(tx));
+                }
+            }
+        }
+        "approve" | "deny" | "pay" => {
+            let id: u64 = match get_user_input("Claim ID: ").trim_start_matches('#').parse() {
+                Ok(id) => id,
+                Err(_) => {
+                    say!("❌ Invalid claim ID.");
+                    return;
+                }
+            };
+
+            let result = match action.as_str() {
+                "approve" => {
+                    let note = get_user_input("Approval note: ");
+                    vault.approve_claim(id, &note).await.map(Some)
+                }
+                "pay" => vault.pay_claim(id).await.map(Some),
+                _ => {
+                    let note = get_user_input("Reason for denial: ");
+                    vault.deny_claim(id, &note).map(|_| None)
+                }
+            };
+
+            match result {
+                Ok(Some(receipt)) => say!("✅ Claim #{} paid: {}", id, vault.network.tx_link(&receipt.hash)),
+                Ok(None) => say!("✅ Claim #{} denied", id),
+                Err(e) => say!("❌ Claim #{} not processed: {}", id, e),
+            }
+        }
+        _ => say!("❌ Unknown claims action: {}", action),
+    }
+}
+
+fn run_insurance(vault: &mut StellarVault) {
+    let xlm = |stroops: u64| stroops as f64 / 10_000_000.0;
+    let invested = vault.get_vault_info(INSURANCE_VAULT)
+        .map(|v| vault.insurance_investment.value(v))
+        .unwrap_or(0);
+
+    say!("\n🛡️  INSURANCE POOL");
+    say!("   Liquid: {:.7} XLM", xlm(vault.insurance_pool));
+    say!("   Invested in {:?} Risk Vault: {:.7} XLM ({} shares, cost {:.7} XLM)",
+        INSURANCE_VAULT,
+        xlm(invested),
+        vault.insurance_investment.shares,
+        xlm(vault.insurance_investment.principal));
+    say!("   Total Cover: {:.7} XLM", xlm(vault.insurance_assets()));
+    say!("   Investment Target: {:.2}%", vault.insurance_investment.target_bps as f64 / 100.0);
+
+    let input = get_user_input("\nNew investment target % (blank to keep): ");
+    if !input.is_empty() {
+        match input.parse::<f64>() {
+            Ok(pct) if (0.0..=100.0).contains(&pct) => {
+                if let Err(e) = vault.set_insurance_investment_target((pct * 100.0).round() as u16) {
+                    say!("❌ Could not update target: {}", e);
+                    return;
+                }
+            }
+            _ => {
+                say!("❌ Invalid percentage.");
+                return;
+            }
+        }
+    }
+
+    match vault.rebalance_insurance_investment() {
+        Ok(delta) if delta > 0 => say!("✅ Invested {:.7} XLM of idle insurance capital", delta as f64 / 10_000_000.0),
+        Ok(delta) if delta < 0 => say!("✅ Redeemed {:.7} XLM back to the liquid pool", delta.unsigned_abs() as f64 / 10_000_000.0),
+        Ok(_) => say!("ℹ️  Insurance investment already at target"),
+        Err(e) => say!("❌ Could not rebalance insurance pool: {}", e),
+    }
+}
+
+fn run_coverage(vault: &mut StellarVault) {
+    let policy = vault.coverage_policy;
+
+    say!("\n🛡️  INSURANCE COVERAGE");
+    say!("   Insurance Assets: {:.7} XLM", vault.insurance_assets() as f64 / 10_000_000.0);
+    say!("   Total TVL: {:.7} XLM", vault.total_tvl() as f64 / 10_000_000.0);
+    for (asset, total) in vault.asset_totals().iter().filter(|(asset, _)| asset.as_str() != "XLM") {
+        let held = vault.asset_insurance.get(asset).copied().unwrap_or(0);
+        say!("   {}: {:.7} TVL, {:.7} in premiums (not covered)", asset,
+            *total as f64 / 10_000_000.0, held as f64 / 10_000_000.0);
+    }
+    say!("   Coverage Ratio: {}", format_coverage(vault.coverage_ratio_bps()));
+    say!("   Target Band: {:.2}% – {:.2}%",
+        policy.floor_bps as f64 / 100.0,
+        policy.ceiling_bps as f64 / 100.0);
+
+    match vault.adjust_premiums() {
+        Ok(report) if report.changes.is_empty() => {
+            say!("   ℹ️  Premiums unchanged ({:?})", report.action);
+        }
+        Ok(report) => print_premium_changes(&report),
+        Err(e) => say!("❌ Could not adjust premiums: {}", e),
+    }
+
+    for risk in RiskLevel::ALL {
+        if let Some(info) = vault.get_vault_info(risk) {
+            let bounds = match info.premium_bounds {
+                Some(b) => format!("bounds {:.2}% – {:.2}%", b.min_bps as f64 / 100.0, b.max_bps as f64 / 100.0),
+                None => "fixed".to_string(),
+            };
+            say!("   {:?}: {:.2}% ({})", risk, info.insurance_fee as f64 / 100.0, bounds);
+        }
+    }
+}
+
+async fn run_shares(vault: &StellarVault, user: &str) {
+    let issuer = match &vault.share_issuer {
+        Some(issuer) => issuer.clone(),
+        None => {
+            say!("\nℹ️  Share tokens are disabled (set STELLARVAULT_SHARE_ISSUER to enable)");
+            return;
+        }
+    };
+
+    let balances = match vault.users.get(user) {
+        Ok(client) => client.get_balances().await.unwrap_or_default(),
+        Err(_) => Vec::new(),
+    };
+
+    say!("\n🪙 VAULT SHARE TOKENS (issuer {})", issuer);
+    for risk in RiskLevel::ALL {
+        let asset = ShareAsset::for_vault(risk, &issuer);
+        let on_chain = match asset.on_chain_balance(&balances) {
+            Some(balance) => format!("{:.7}", balance),
+            None => "no trustline".to_string(),
+        };
+        say!("   {} ({:?} Risk): on-chain {}", asset.code, risk, on_chain);
+        if let Some(record) = vault.share_issuance.get(user, risk) {
+            say!("      Issued: {:.7}  Pending Mint: {:.7}  Owed Back: {:.7}",
+                record.issued as f64 / 10_000_000.0,
+                record.pending_mint as f64 / 10_000_000.0,
+                record.pending_burn as f64 / 10_000_000.0);
+        }
+    }
+
+    let outstanding = vault.share_issuance.records().iter()
+        .filter(|r| r.pending_mint > 0 || r.pending_burn > 0)
+        .count();
+    if outstanding > 0 {
+        say!("\n   ⏳ {} holder(s) have share token transfers awaiting settlement", outstanding);
+    }
+}
+
+// Opens the user's missing share token trustlines. When the vault's keys are
+// loaded the vault sponsors their reserves, so users need no extra XLM.
+async fn run_trust_shares(vault: &StellarVault, user: &str) {
+    let Some(issuer) = &vault.share_issuer else {
+        say!("\nℹ️  Share tokens are disabled (set STELLARVAULT_SHARE_ISSUER to enable)");
+        return;
+    };
+    let client = match vault.users.get(user) {
+        Ok(client) => client,
+        Err(e) => {
+            say!("❌ {}", e);
+            return;
+        }
+    };
+    let balances = match client.get_balances().await {
+        Ok(balances) => balances,
+        Err(e) => {
+            say!("❌ {}", e);
+            return;
+        }
+    };
+
+    let mut assets = Vec::new();
+    for risk in RiskLevel::ALL {
+        let share = ShareAsset::for_vault(risk, issuer);
+        if share.has_trustline(&balances) {
+            continue;
+        }
+        match share.id().to_xdr() {
+            Ok(asset) => assets.push(asset),
+            Err(e) => {
+                say!("❌ {}", e);
+                return;
+            }
+        }
+    }
+    if assets.is_empty() {
+        say!("\nℹ️  {} already trusts every vault share token", user);
+        return;
+    }
+
+    let sponsor = vault.vault_signer.as_ref();
+    match sponsor {
+        Some(sponsor) => say!("\n🤝 Opening {} trustline(s), reserves sponsored by {}", assets.len(), sponsor.get_public_key()),
+        None => say!("\n🪙 Opening {} trustline(s); each holds {} of your XLM in reserve",
+            assets.len(), Stroops(transaction::BASE_RESERVE)),
+    }
+    match client.open_trustlines(&assets, sponsor).await {
+        Ok(receipt) => say!("✅ Trustlines open in ledger {} ({})", receipt.ledger, vault.network.tx_link(&receipt.hash)),
+        Err(e) => say!("❌ Could not open trustlines: {}", e),
+    }
+}
+
+// trust <code> <issuer> [limit] (or CODE:ISSUER): opens a trustline, or changes its limit;
+// a limit of 0 removes it. Without arguments, lists the account's trustlines.
+async fn run_trust(vault: &StellarVault, user: &str, args: &str) {
+    let client = match vault.users.get(user) {
+        Ok(client) => client,
+        Err(e) => {
+            say!("❌ {}", e);
+            return;
+        }
+    };
+    let balances = match client.get_balances().await {
+        Ok(balances) => balances,
+        Err(e) => {
+            say!("❌ {}", e);
+            return;
+        }
+    };
+
+    let args: Vec<&str> = args.split_whitespace().collect();
+    let (code_issuer, limit) = match args.as_slice() {
+        [] => {
+            let trustlines: Vec<&Balance> = balances.iter().filter(|balance| !balance.is_native()).collect();
+            if trustlines.is_empty() {
+                say!("\nℹ️  {} has no trustlines", user);
+            } else {
+                say!("\n🪙 TRUSTLINES");
+                for balance in trustlines {
+                    say!("   {}:{}  balance {}  limit {}",
+                        balance.asset_code.as_deref().unwrap_or("?"),
+                        balance.asset_issuer.as_deref().unwrap_or("?"),
+                        balance.balance,
+                        balance.limit.as_deref().unwrap_or("-"));
+                }
+            }
+            return;
+        }
+        [asset] => (AssetId::parse(asset), None),
+        [asset, limit] if asset.contains(':') => (AssetId::parse(asset), Some(*limit)),
+        [code, issuer] => (AssetId::new(&code.to_uppercase(), issuer), None),
+        [code, issuer, limit] => (AssetId::new(&code.to_uppercase(), issuer), Some(*limit)),
+        _ => {
+            say!("❌ Usage: trust <asset> <issuer> [limit, 0 removes]");
+            return;
+        }
+    };
+    let asset = match code_issuer {
+        Ok(asset) => asset,
+        Err(e) => {
+            say!("❌ {}", e);
+            return;
+        }
+    };
+    // Asset amounts use the same 7 decimal places as XLM
+    let limit = match limit.map(Stroops::from_xlm_str) {
+        None => assets::UNLIMITED,
+        Some(Ok(limit)) => i64::try_from(limit.0).unwrap_or(assets::UNLIMITED),
+        Some(Err(e)) => {
+            say!("❌ {}", e);
+            return;
+        }
+    };
+
+    let existing = asset.trustline(&balances);
+    if limit == 0 {
+        match existing {
+            None => {
+                say!("ℹ️  {} has no trustline to {}", user, asset);
+                return;
+            }
+            Some(balance) if Stroops::from_xlm_str(&balance.balance).map_or(true, |b| b.0 > 0) => {
+                say!("❌ The trustline still holds {} {}; send it back to the issuer first", balance.balance, asset.code);
+                return;
+            }
+            Some(_) => say!("\n🗑️  Removing the trustline to {}", asset),
+        }
+    } else if existing.is_some() {
+        say!("\n✏️  Changing the limit on {}", asset);
+    } else {
+        say!("\n🪙 Trusting {}; the trustline holds {} of your XLM in reserve", asset, Stroops(transaction::BASE_RESERVE));
+    }
+    match client.change_trust(&asset, limit).await {
+        Ok(receipt) => say!("✅ Trustline updated in ledger {} ({})", receipt.ledger, vault.network.tx_link(&receipt.hash)),
+        Err(e) => say!("❌ Could not update the trustline: {}", e),
+    }
+}
+
+fn run_pending_withdrawals(vault: &StellarVault) {
+    let entries: Vec<_> = vault.withdrawal_queue.iter().collect();
+    if entries.is_empty() {
+        say!("\nℹ️  No pending withdrawals");
+        return;
+    }
+
+    say!("\n⏳ PENDING WITHDRAWALS");
+    for entry in entries {
+        let estimate = vault.get_vault_info(entry.risk)
+            .map(|v| (entry.shares as u128 * v.get_share_price() as u128 / 10_000_000) as u64)
+            .unwrap_or(0);
+        say!("   #{} {:?} Risk — {} shares (~{:.7} XLM) for {}, queued {} ago",
+            entry.id,
+            entry.risk,
+            entry.shares,
+            estimate as f64 / 10_000_000.0,
+            entry.user,
+            format_duration(unix_now().saturating_sub(entry.requested_at)));
+    }
+}
+
+async fn run_process_queue(vault: &mut StellarVault) {
+    for (id, result) in vault.process_withdrawal_queue().await {
+        match result {
+            Ok(receipt) => say!("   ✅ Queued withdrawal #{} paid: {:.7} XLM",
+                id, receipt.net_stroops as f64 / 10_000_000.0),
+            Err(e) => say!("   ❌ Queued withdrawal #{} still pending: {}", id, e),
+        }
+    }
+}
+
+fn run_pause(vault: &mut StellarVault) {
+    say!("\n⛔ Vault pause controls:");
+    for risk in RiskLevel::ALL {
+        if let Some(info) = vault.get_vault_info(risk) {
+            let status = if info.circuit.paused { "PAUSED" } else { "active" };
+            say!("   {:?}: {} (breaker at {:.2}% share price move)",
+                risk, status, info.circuit.max_price_move_bps as f64 / 100.0);
+        }
+    }
+
+    let command = get_user_input("Action (pause/resume/limit): ").to_lowercase();
+    let risk_level = prompt_risk_level();
+
+    let result = match command.as_str() {
+        "pause" => {
+            let reason = get_user_input("Reason: ");
+            let reason = if reason.is_empty() { "paused by admin".to_string() } else { reason };
+            vault.pause_vault(risk_level, &reason)
+        }
+        "resume" => vault.resume_vault(risk_level),
+        "limit" => match get_user_input("Max share price move between operations (bps, 0 disables): ").parse::<u16>() {
+            Ok(bps) => vault.set_circuit_limit(risk_level, bps),
+            Err(_) => Err(VaultError::Validation("Invalid basis points".into())),
+        },
+        _ => Err(VaultError::Validation(format!("Unknown pause action: {}", command))),
+    };
+
+    match result {
+        Ok(()) => say!("✅ {:?} Risk Vault updated", risk_level),
+        Err(e) => say!("❌ {}", e),
+    }
+}
+
+// Replays the event log and compares the result against the live balances
+fn run_audit(vault: &mut StellarVault) {
+    let (replayed, count) = match vault.replay_events() {
+        Ok(result) => result,
+        Err(e) => {
+            say!("❌ Could not replay the event log: {}", e);
+            return;
+        }
+    };
+
+    say!("\n🧾 EVENT LOG AUDIT ({} events)", count);
+    let mut mismatches = 0;
+    let mut compare = |label: String, live: u64, logged: u64| {
+        if live == logged {
+            say!("   ✅ {}: {}", label, Stroops(live));
+        } else {
+            mismatches += 1;
+            say!("   ❌ {}: live {} vs replayed {}", label, Stroops(live), Stroops(logged));
+        }
+    };
+
+    for risk in RiskLevel::ALL {
+        if let (Some(live), Some(logged)) = (vault.get_vault_info(risk), replayed.vaults.get(&risk)) {
+            compare(format!("{:?} TVL", risk), live.total_value, logged.total_value);
+            compare(format!("{:?} shares (units)", risk), live.total_shares, logged.total_shares);
+            compare(format!("{:?} uncollected fees", risk),
+                live.fee_accrual.outstanding(), logged.fee_accrual.outstanding());
+        }
+    }
+    compare("Insurance pool".to_string(), vault.insurance_pool, replayed.insurance_pool);
+    for (asset, &held) in &vault.asset_insurance {
+        compare(format!("{} insurance", asset), held, replayed.asset_insurance.get(asset).copied().unwrap_or(0));
+    }
+    compare("Insurance stake (units)".to_string(), vault.insurance_investment.shares, replayed.insurance_shares);
+
+    for (key, position) in &vault.user_positions {
+        let logged = replayed.positions.get(key).map(|p| p.shares).unwrap_or(0);
+        if position.shares != logged {
+            mismatches += 1;
+            say!("   ❌ {} {:?} shares: live {} vs replayed {}", key.0, key.1, position.shares, logged);
+        }
+    }
+
+    if mismatches == 0 {
+        say!("\n✅ Live state matches the event log");
+        return;
+    }
+
+    say!("\n⚠️  {} mismatch(es) between live state and the event log", mismatches);
+    let confirm = get_user_input("Rebuild balances from the event log? (yes/no): ").to_lowercase();
+    if confirm == "yes" || confirm == "y" {
+        match vault.rebuild_from_events() {
+            Ok(count) => say!("✅ Rebuilt vault state from {} events", count),
+            Err(e) => say!("❌ Rebuild failed: {}", e),
+        }
+    }
+}
+
+// Reads the vault's holdings straight from the ledger, strategy positions
+// included, and flags every figure local accounting disagrees with. With
+// `pause`, vaults off by more than `tolerance_bps` are paused.
+async fn execute_reconcile(vault: &mut StellarVault, tolerance_bps: u16, pause: bool) -> CommandResult {
+    let source = match &vault.vault_contract {
+        Some(contract) => format!("vault contract {} storage", contract),
+        None => format!("vault account {} balances", vault.vault_address),
+    };
+    let checks = vault.reconcile().await.map_err(|e| format!("Could not read the ledger: {}", e))?;
+
+    say!("\n🔎 LEDGER RECONCILIATION ({})", source);
+    let amount = |stroops: u64| Stroops(stroops).to_xlm_string();
+    let parts = |parts: &[(String, u64)]| parts.iter()
+        .map(|(label, stroops)| format!("{} {}", label, amount(*stroops)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut report = Vec::new();
+    for check in &checks {
+        if check.diverges() {
+            let material = if check.is_material(tolerance_bps) { "" } else { ", within tolerance" };
+            say!("   ❌ {}: on-chain {} vs local {} ({}{})", check.label,
+                amount(check.on_chain), amount(check.local), check.difference(), material);
+        } else {
+            say!("   ✅ {}: {}", check.label, amount(check.on_chain));
+        }
+        if !check.on_chain_parts.is_empty() {
+            say!("      On-chain: {}", parts(&check.on_chain_parts));
+            say!("      Local: {}", parts(&check.local_parts));
+        }
+        let parts_json = |parts: &[(String, u64)]| parts.iter()
+            .map(|(label, stroops)| json!({ "label": label, "amount": amount(*stroops) }))
+            .collect::<Vec<_>>();
+        report.push(json!({
+            "label": check.label,
+            "on_chain": amount(check.on_chain),
+            "local": amount(check.local),
+            "difference": check.difference(),
+            "diverges": check.diverges(),
+            "material": check.is_material(tolerance_bps),
+            "vaults": check.vaults,
+            "on_chain_parts": parts_json(&check.on_chain_parts),
+            "local_parts": parts_json(&check.local_parts),
+        }));
+    }
+
+    let divergences = checks.iter().filter(|c| c.diverges()).count();
+    let material = checks.iter().filter(|c| c.is_material(tolerance_bps)).count();
+    if divergences == 0 {
+        say!("\n✅ Local accounting matches the ledger");
+    } else if vault.vault_contract.is_some() {
+        say!("\n⚠️  {} divergence(s), {} beyond {:.2}%; the index trails the ledger by a poll or two, so check again if it is catching up",
+            divergences, material, tolerance_bps as f64 / 100.0);
+    } else {
+        say!("\n⚠️  {} divergence(s), {} beyond {:.2}%; the vault holds less than its books owe",
+            divergences, material, tolerance_bps as f64 / 100.0);
+    }
+
+    let paused = if pause {
+        vault.pause_mismatched(&checks, tolerance_bps).map_err(|e| format!("Could not pause the mismatched vaults: {}", e))?
+    } else {
+        Vec::new()
+    };
+    for risk in &paused {
+        say!("⏸️  Paused the {:?} Risk Vault until the mismatch is resolved", risk);
+    }
+    Ok(json!({
+        "source": source,
+        "tolerance_bps": tolerance_bps,
+        "divergences": divergences,
+        "material": material,
+        "checks": report,
+        "paused": paused,
+    }))
+}
+
+// Writes the signed summary to <out>/summary.json and each account's
+// inclusion proofs to <out>/proofs/<account>.json, to publish and hand out
+async fn execute_proof_of_reserves(vault: &mut StellarVault, out: &Path) -> CommandResult {
+    let (signed, proofs) = vault.proof_of_reserves().await
+        .map_err(|e| format!("Could not build the proof of reserves: {}", e))?;
+    let proofs_dir = out.join("proofs");
+    std::fs::create_dir_all(&proofs_dir).map_err(|e| format!("Could not create {}: {}", proofs_dir.display(), e))?;
+    let write = |path: PathBuf, json: String| std::fs::write(&path, json)
+        .map_err(|e| format!("Could not write {}: {}", path.display(), e));
+    write(out.join("summary.json"), serde_json::to_string_pretty(&signed).map_err(|e| e.to_string())?)?;
+    let mut by_account: BTreeMap<&str, Vec<&InclusionProof>> = BTreeMap::new();
+    for proof in &proofs {
+        by_account.entry(&proof.liability.account).or_default().push(proof);
+    }
+    for (account, proofs) in &by_account {
+        write(proofs_dir.join(format!("{}.json", account)), serde_json::to_string_pretty(proofs).map_err(|e| e.to_string())?)?;
+    }
+
+    let summary = &signed.summary;
+    say!("\n🧾 PROOF OF RESERVES (ledger {})", summary.ledger);
+    for reserves in &summary.reserves {
+        let holdings: Vec<String> = reserves.holdings.iter()
+            .map(|h| format!("{} {}", h.label, Stroops(h.stroops).to_xlm_string()))
+            .collect();
+        say!("   Held: {} {} ({})", Stroops(reserves.total_stroops).to_xlm_string(), reserves.asset, holdings.join(", "));
+    }
+    for vault in &summary.liabilities {
+        say!("   {:?} Risk Vault: {} position(s) worth {} {}, root {}", vault.risk, vault.positions,
+            Stroops(vault.total_stroops).to_xlm_string(), vault.asset, vault.root);
+    }
+    for (asset, held, owed) in summary.shortfalls() {
+        say!("   ⚠️  {} held is {} short of the {} owed to users", asset, Stroops(owed - held).to_xlm_string(), Stroops(owed).to_xlm_string());
+    }
+    say!("   Signed by: {}", signed.signer);
+    say!("\n✅ Wrote {} and proofs for {} account(s) to {}", out.join("summary.json").display(), by_account.len(), proofs_dir.display());
+    Ok(json!({
+        "summary": signed,
+        "directory": out,
+        "accounts": by_account.len(),
+    }))
+}
+
+fn execute_audit_export(vault: &StellarVault, out: &Path) -> CommandResult {
+    let records = vault.storage.load_events().map_err(|e| format!("Could not read the event log: {}", e))?;
+    let mut file = std::io::BufWriter::new(std::fs::File::create(out)
+        .map_err(|e| format!("Could not create {}: {}", out.display(), e))?);
+    let head = audit::export(&records, &mut file).map_err(|e| format!("Could not write {}: {}", out.display(), e))?;
+    say!("\n📜 Exported {} event(s) to {}", records.len(), out.display());
+    say!("   Last hash: {}", head);
+    say!("   Publish or hand over the last hash separately: with it, auditors can tell nothing was cut from the end");
+    Ok(json!({
+        "file": out,
+        "records": records.len(),
+        "first_seq": records.first().map(|r| r.seq),
+        "last_seq": records.last().map(|r| r.seq),
+        "head": head,
+    }))
+}
+
+// Replays an exported trail's hash chain; needs no vault or network
+fn run_audit_verify(file: &Path, head: Option<&str>) -> CommandResult {
+    let trail = std::fs::read_to_string(file).map_err(|e| format!("Could not read {}: {}", file.display(), e))?;
+    let verified = audit::verify(&trail).map_err(|e| format!("{} fails: {}", file.display(), e))?;
+    if let Some(head) = head {
+        if !verified.head.eq_ignore_ascii_case(head.trim()) {
+            return Err(format!("{} ends at hash {}, not the published {}; records were cut or added at the end",
+                file.display(), verified.head, head.trim()));
+        }
+    }
+    say!("\n✅ {}: {} record(s), hash chain unbroken", file.display(), verified.records);
+    say!("   Last hash: {}", verified.head);
+    if head.is_none() {
+        say!("   ⚠️  Compare it with the hash published at export; without it, records cut from the end go unnoticed");
+    }
+    Ok(json!({
+        "file": file,
+        "records": verified.records,
+        "head": verified.head,
+        "head_checked": head.is_some(),
+    }))
+}
+
+// Checks a published summary's signature and that each position in a proof
+// file is counted in it; needs no vault or network
+fn run_verify_reserves(summary: &Path, proof: &Path) -> CommandResult {
+    let read = |path: &Path| std::fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path.display(), e));
+    let signed: SignedSummary = serde_json::from_str(&read(summary)?)
+        .map_err(|e| format!("{} is not a proof of reserves summary: {}", summary.display(), e))?;
+    let proofs: Vec<InclusionProof> = serde_json::from_str(&read(proof)?)
+        .map_err(|e| format!("{} is not a list of inclusion proofs: {}", proof.display(), e))?;
+    signed.verify().map_err(|e| e.to_string())?;
+
+    let summary = &signed.summary;
+    say!("\n🔏 Summary of {} at ledger {}, signed by {}", summary.vault, summary.ledger, signed.signer);
+    if signed.signer != summary.vault {
+        say!("   ⚠️  Check on the ledger that {} is one of {}'s signers", signed.signer, summary.vault);
+    }
+    let mut verified = Vec::new();
+    for proof in &proofs {
+        proof.check(summary).map_err(|e| format!("{}'s position fails: {}", proof.liability.account, e))?;
+        let vault = summary.liabilities.iter().find(|l| l.risk == proof.risk).ok_or("Checked against a missing vault")?;
+        say!("   ✅ {} has {} shares in the {:?} Risk Vault worth {} {}, counted in its {} total",
+            proof.liability.account, proof.liability.shares, proof.risk,
+            Stroops(proof.liability.value_stroops).to_xlm_string(), vault.asset, Stroops(vault.total_stroops).to_xlm_string());
+        verified.push(json!({ "account": proof.liability.account, "risk": proof.risk, "shares": proof.liability.shares,
+            "value": Stroops(proof.liability.value_stroops).to_xlm_string() }));
+    }
+    let shortfalls = summary.shortfalls();
+    for (asset, held, owed) in &shortfalls {
+        say!("   ❌ The vault holds {} {} but owes its users {}", Stroops(*held).to_xlm_string(), asset, Stroops(*owed).to_xlm_string());
+    }
+    if shortfalls.is_empty() {
+        say!("\n✅ Reserves cover every vault's positions");
+    }
+    Ok(json!({
+        "vault": summary.vault,
+        "ledger": summary.ledger,
+        "signer": signed.signer,
+        "positions": verified,
+        "backed": shortfalls.is_empty(),
+    }))
+}
+
+async fn run_reconcile(vault: &mut StellarVault) {
+    let report = match execute_reconcile(vault, DEFAULT_TOLERANCE_BPS, false).await {
+        Ok(report) => report,
+        Err(e) => {
+            say!("❌ {}", e);
+            return;
+        }
+    };
+    if report["material"].as_u64().unwrap_or(0) == 0 {
+        return;
+    }
+    let confirm = get_user_input("Pause the vaults with a material mismatch? (yes/no): ").to_lowercase();
+    if confirm == "yes" || confirm == "y" {
+        match vault.reconcile().await.and_then(|checks| vault.pause_mismatched(&checks, DEFAULT_TOLERANCE_BPS)) {
+            Ok(paused) if paused.is_empty() => say!("ℹ️  Nothing paused: the mismatch cleared, or its vaults already are"),
+            Ok(paused) => say!("⏸️  Paused: {:?}", paused),
+            Err(e) => say!("❌ {}", e),
+        }
+    }
+}
+
+async fn run_pending_deposits(vault: &mut StellarVault) {
+    let unsettled = vault.pending_deposits.unsettled();
+    if unsettled.is_empty() {
+        say!("\nℹ️  No unsettled deposits");
+        return;
+    }
+
+    say!("\n🧾 UNSETTLED DEPOSITS");
+    for deposit in &unsettled {
+        say!("   #{} {:?} Risk — {} from {} ({:?}, tx {})",
+            deposit.id,
+            deposit.risk,
+            Stroops(deposit.amount_stroops),
+            deposit.user,
+            deposit.stage,
+            if deposit.tx_hash.is_empty() { "unknown" } else { &deposit.tx_hash });
+        if !deposit.memo.is_empty() {
+            say!("      Memo: {}", deposit.memo);
+        }
+        if deposit.ledger == 0 && deposit.expires_at > 0 {
+            let now = unix_now();
+            if now > deposit.expires_at {
+                say!("      Payment window closed {} ago", format_duration(now - deposit.expires_at));
+            } else {
+                say!("      Payment window closes in {}", format_duration(deposit.expires_at - now));
+            }
+        }
+        if let Some(failure) = &deposit.failure {
+            say!("      Failure: {}", failure);
+        }
+    }
+
+    say!("\n🔁 Settling...");
+    print_settlements(&vault.settle_pending_deposits().await);
+}
+
+fn print_settlements(results: &[(PendingDeposit, Result<String, String>)]) {
+    for (deposit, result) in results {
+        match result {
+            Ok(outcome) => say!("   ✅ Deposit #{}: {}", deposit.id, outcome),
+            Err(e) => say!("   ❌ Deposit #{} still unsettled: {}", deposit.id, e),
+        }
+    }
+}
+
+async fn run_contract_index(vault: &mut StellarVault, stream: &mut UnboundedReceiver<IndexEvent>) {
+    while let Ok(event) = stream.try_recv() {
+        match event {
+            IndexEvent::Event(event) => match vault.index_contract_event(&event) {
+                Ok(Some(ContractEvent::Deposit { user, risk, amount, shares, .. })) => say!("📜 Contract deposit: {} put {} into the {:?} Risk Vault for {} shares",
+                    user, Stroops(amount).to_xlm_string(), risk, shares),
+                Ok(Some(ContractEvent::Withdraw { user, risk, amount, shares })) => say!("📜 Contract withdrawal: {} redeemed {} {:?} Risk shares for {}",
+                    user, shares, risk, Stroops(amount).to_xlm_string()),
+                Ok(Some(ContractEvent::Harvest { risk, amount })) => say!("📜 Contract harvest: {} of yield into the {:?} Risk Vault",
+                    Stroops(amount).to_xlm_string(), risk),
+                Ok(Some(ContractEvent::Claim { to, risk, amount })) => say!("📜 Contract claim: {} paid to {} from the {:?} Risk reserve",
+                    Stroops(amount).to_xlm_string(), to, risk),
+                Ok(Some(ContractEvent::ShareTransfer { from, to, risk, amount })) => say!("📜 Share transfer: {} {:?} Risk shares from {} to {}",
+                    Stroops(amount).to_xlm_string(), risk, from, to),
+                Ok(None) => {}
+                Err(e) => say!("📜 ❌ Could not index contract event {}: {}", event.id, e),
+            },
+            IndexEvent::Disconnected { error, retry_secs } => {
+                say!("⚠️  Could not poll vault contract events ({}); retrying in {}s", error, retry_secs);
+            }
+        }
+    }
+}
+
+async fn run_ingest(vault: &mut StellarVault, stream: &mut UnboundedReceiver<StreamEvent>) {
+    while let Ok(event) = stream.try_recv() {
+        match event {
+            StreamEvent::Payment(payment) => {
+                if let Some((id, outcome)) = vault.ingest_payment(&payment).await {
+                    let from = payment.from.as_deref().unwrap_or("unknown");
+                    match outcome {
+                        Ok(outcome) => say!("📥 Deposit #{} from {}: {}", id, from, outcome),
+                        Err(e) => say!("📥 ❌ Deposit #{} from {} still unsettled: {}", id, from, e),
+                    }
+                }
+            }
+            StreamEvent::Disconnected { error, retry_secs } => {
+                say!("⚠️  Payment stream disconnected ({}); reconnecting in {}s", error, retry_secs);
+            }
+        }
+    }
+}
+
+// Payments in and out of the user's wallet, as the stream delivers them
+fn run_wallet_stream(user: &str, stream: &mut UnboundedReceiver<StreamEvent>) {
+    while let Ok(event) = stream.try_recv() {
+        match event {
+            StreamEvent::Payment(payment) => {
+                let incoming = payment.to.as_deref() == Some(user);
+                say!("{} {} {} {} ({})",
+                    if incoming { "⬅️  Received" } else { "➡️  Sent" },
+                    payment.amount.as_deref().unwrap_or("-"),
+                    payment.asset_code.as_deref().unwrap_or("XLM"),
+                    if incoming { format!("from {}", payment.from.as_deref().unwrap_or("unknown")) }
+                    else { format!("to {}", payment.to.as_deref().unwrap_or("unknown")) },
+                    payment.transaction_hash);
+            }
+            StreamEvent::Disconnected { error, retry_secs } => {
+                say!("⚠️  Wallet stream disconnected ({}); reconnecting in {}s", error, retry_secs);
+            }
+        }
+    }
+}
+
+// Status refreshes this often in watch mode
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+// Streams the vault and wallet accounts and redraws a compact status until
+// Ctrl-C. With --output json each refresh is one JSON line on stdout.
+async fn run_watch(vault: &mut StellarVault, user: &str, config: &Config) -> CommandResult {
+    say!("👀 Watching {} and {}; Ctrl-C to stop", vault.vault_address, user);
+    // Only a vault with ingestion on credits deposits from its stream
+    let mut vault_stream = config.ingest
+        .then(|| ingest::spawn(vault.horizon.clone(), vault.vault_address.clone(), vault.ingest_cursor.clone()));
+    let mut wallet_stream = ingest::spawn(vault.horizon.clone(), user.to_string(), None);
+    let mut price_stream = match &config.price_oracle {
+        Some(oracle) => start_price_oracle(vault, oracle),
+        None => None,
+    };
+    let mut alerts = config.alerts.clone().map(AlertEngine::new);
+    let mut upkeep = Upkeep::new(vault, config);
+    let redraw = !output::is_json() && std::io::stdout().is_terminal();
+    let mut ticker = tokio::time::interval(WATCH_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            _ = ticker.tick() => {}
+        }
+        if let Some(stream) = vault_stream.as_mut() {
+            run_ingest(vault, stream).await;
+        }
+        if let Some(stream) = price_stream.as_mut() {
+            run_price_updates(vault, stream);
+        }
+        run_wallet_stream(user, &mut wallet_stream);
+
+        let balance = vault.horizon.account(user).await.ok()
+            .and_then(|account| account.balances.into_iter().find(|balance| balance.is_native()))
+            .map(|balance| balance.balance);
+        if redraw {
+            print!("\x1b[2J\x1b[H");
+        }
+        say!("\n👀 {} · refreshed every {}s", vault.network, WATCH_INTERVAL.as_secs());
+        match &balance {
+            Some(balance) => say!("💰 Wallet: {} XLM", balance),
+            None => say!("💰 Wallet: unavailable"),
+        }
+        say!("{:<8} {:>22} {:>12} {:>14} {:>16} {:>18}", "Vault", "TVL", "Share Price", "Accruing", "Your Shares", "Your Value");
+
+        let mut vaults = Vec::new();
+        for risk in RiskLevel::ALL {
+            let Some(info) = vault.get_vault_info(risk) else {
+                continue;
+            };
+            let price = vault.live_share_price(risk).unwrap_or_else(|_| info.get_share_price());
+            let pending = vault.pending_yield(risk).unwrap_or(0);
+            let position = vault.get_position(user, risk);
+            let shares = position.as_ref().map_or(0, |p| p.shares);
+            // The position's cut of the yield a harvest would credit now
+            let accruing = mul_div(pending, shares, info.total_shares.max(1), Rounding::Down).unwrap_or(0);
+            let value = position.as_ref().map_or(0, |p| p.value_stroops) + accruing;
+            say!("{:<8} {:>22} {:>12} {:>14} {:>16} {:>18}",
+                format!("{:?}", risk),
+                format!("{} {}", Stroops(info.total_value).to_xlm_string(), info.asset_code()),
+                Stroops(price).to_xlm_string(),
+                format!("+{}", Stroops(pending).to_xlm_string()),
+                shares,
+                Stroops(value).to_xlm_string());
+            vaults.push(json!({
+                "risk": risk,
+                "tvl": Stroops(info.total_value).to_xlm_string(),
+                "share_price": Stroops(price).to_xlm_string(),
+                "pending_yield": Stroops(pending).to_xlm_string(),
+                "shares": shares,
+                "value": Stroops(value).to_xlm_string(),
+            }));
+        }
+        let schedule = upkeep.scheduler.status();
+        for status in &schedule {
+            let last = match &status.last_run {
+                Some(run) => format!("last ran {} ago ({})", format_duration(unix_now().saturating_sub(run.started_at)),
+                    if run.ok { "ok" } else { "failed" }),
+                None => "not run yet".to_string(),
+            };
+            say!("⏰ {}: {}, next in {}", status.job, last, format_duration(status.next_run_at.saturating_sub(unix_now())));
+        }
+        output::emit(&json!({
+            "timestamp": unix_now(),
+            "balance": balance,
+            "vaults": vaults,
+            "schedule": schedule,
+            "horizon": vault.horizon.status(),
+        }));
+        // Below the table, so the redraw doesn't wipe them straight away
+        if let Some(alerts) = alerts.as_mut() {
+            run_alerts(vault, alerts);
+        }
+        report_failovers(vault);
+        run_schedule(vault, &mut upkeep).await;
+    }
+
+    say!("\n👋 Stopped watching");
+    Ok(json!({ "status": "stopped" }))
+}
+
+// What a scheduled job needs besides the vault; APY sources are kept
+// between runs since Blend's needs two samples to give a rate. With no
+// [[schedule]] it only runs users' recurring plans.
+struct Upkeep {
+    scheduler: Scheduler,
+    apy_sources: Vec<Box<dyn ApyProvider>>,
+}
+
+impl Upkeep {
+    fn new(vault: &StellarVault, config: &Config) -> Upkeep {
+        let apy_sources = match &config.apy_oracle {
+            Some(oracle) if config.schedule.iter().any(|scheduled| scheduled.job == Job::ApyRefresh) => apy_providers(vault, config, oracle),
+            _ => Vec::new(),
+        };
+        for scheduled in &config.schedule {
+            say!("⏰ {} every {}", scheduled.job, format_duration(scheduled.interval_secs));
+        }
+        Upkeep { scheduler: Scheduler::new(config.schedule.clone(), unix_now()), apy_sources }
+    }
+}
+
+async fn run_schedule(vault: &mut StellarVault, upkeep: &mut Upkeep) {
+    for (index, job) in upkeep.scheduler.start_due(unix_now()) {
+        let started_at = unix_now();
+        let started = Instant::now();
+        say!("\n⏰ Running scheduled {}", job);
+        let result = run_job(vault, &job, &upkeep.apy_sources)
+            .instrument(tracing::info_span!("scheduled_job", job = %job))
+            .await;
+        match &result {
+            Ok(()) => say!("⏰ Scheduled {} done in {:.1}s", job, started.elapsed().as_secs_f64()),
+            Err(e) => say!("⚠️  Scheduled {} failed: {}", job, e),
+        }
+        upkeep.scheduler.finish(index, started_at, started.elapsed(), result);
+    }
+    run_recurring(vault).await;
+    apply_due_changes(vault);
+}
+
+// Runs the recurring plans due now for the users this process signs for;
+// other users' plans wait for a process that holds their key
+async fn run_recurring(vault: &mut StellarVault) {
+    let now = unix_now();
+    for plan in vault.recurring.due(now) {
+        if !vault.users.contains(&plan.user) {
+            continue;
+        }
+        let description = describe_plan(vault, &plan);
+        say!("\n🔁 Recurring plan #{}: {}", plan.id, description);
+        let result = run_plan(vault, &plan)
+            .instrument(tracing::info_span!("recurring_plan", id = plan.id, user = %plan.user))
+            .await;
+        match &result {
+            Ok(detail) => say!("🔁 Recurring plan #{}: {}", plan.id, detail),
+            Err(reason) => {
+                say!("⏭️  Skipped recurring plan #{} this time: {}", plan.id, reason);
+                vault.notify(Notification::RecurringSkipped {
+                    plan_id: plan.id,
+                    user: plan.user.clone(),
+                    plan: description,
+                    reason: reason.clone(),
+                });
+            }
+        }
+        vault.recurring.record(plan.id, now, result);
+        if let Err(e) = vault.persist() {
+            say!("⚠️  Failed to save recurring plan #{}: {}", plan.id, e);
+        }
+    }
+}
+
+// Deposits check the user's funds first, and withdrawals their shares, so
+// a short balance skips the run before anything is signed
+async fn run_plan(vault: &mut StellarVault, plan: &RecurringPlan) -> Result<String, String> {
+    match plan.action {
+        PlanAction::Deposit { risk, amount_stroops } => {
+            let asset = vault.get_vault_info(risk)
+                .ok_or_else(|| format!("The {:?} Risk Vault is not available", risk))?
+                .asset.clone();
+            let fee = vault.submitter.fee_strategy.estimate(&vault.horizon).await;
+            vault.check_deposit_funds(&plan.user, risk, asset.as_ref(), Stroops(amount_stroops), &fee).await
+                .map_err(|e| e.to_string())?;
+            let receipt = execute_deposit(vault, &plan.user, risk, asset, amount_stroops, true, false, None).await?;
+            Ok(format!("deposited, transaction {}", receipt["transaction"]["hash"].as_str().unwrap_or("unknown")))
+        }
+        PlanAction::Withdraw { risk, payout: Payout::Fixed { amount_stroops }, ref payee } => {
+            let info = vault.get_vault_info(risk).ok_or_else(|| format!("The {:?} Risk Vault is not available", risk))?;
+            let shares = info.pool().shares_for(Stroops(amount_stroops), Rounding::Up).map_err(|e| e.to_string())?;
+            let receipt = vault.withdraw_to(&plan.user, risk, shares.0, payee).await.map_err(|e| e.to_string())?;
+            Ok(format!("paid {}, transaction {}", Stroops(receipt.net_stroops).to_xlm_string(), receipt.transaction.hash))
+        }
+        PlanAction::Withdraw { risk, payout: Payout::Yield, ref payee } => {
+            let (receipt, amount_stroops) = vault.pay_yield_to(&plan.user, risk, payee).await.map_err(|e| e.to_string())?;
+            Ok(format!("paid {} of yield, transaction {}", Stroops(amount_stroops).to_xlm_string(), receipt.hash))
+        }
+    }
+}
+
+async fn run_recurring_command(vault: &mut StellarVault, user: &str, action: RecurringCommand) -> CommandResult {
+    match action {
+        RecurringCommand::List => {
+            let plans: Vec<&RecurringPlan> = vault.recurring.for_user(user).collect();
+            if plans.is_empty() {
+                say!("🔁 No recurring plans; add one with 'recurring deposit'");
+            }
+            for plan in &plans {
+                say!("🔁 #{} {}", plan.id, describe_plan(vault, plan));
+                say!("   Next run in {}", format_duration(plan.next_run_at.saturating_sub(unix_now())));
+                if let Some(run) = &plan.last_run {
+                    say!("   Last {} {} ago: {}", if run.executed { "ran" } else { "skipped" },
+                        format_duration(unix_now().saturating_sub(run.at)), run.detail);
+                }
+            }
+            Ok(json!(plans))
+        }
+        RecurringCommand::Deposit { risk, amount, every } => add_plan(vault, user, PlanAction::Deposit { risk, amount_stroops: amount }, every),
+        RecurringCommand::Withdraw { risk, amount, accumulated_yield: _, to, every } => {
+            let payee = match to {
+                Some(to) => match vault.resolve_address(&to).await.map_err(|e| e.to_string())? {
+                    (_, Some(_)) => return Err(format!("{} needs a memo on each payment, which recurring payouts can't carry", to)),
+                    (account, None) => account,
+                },
+                None => user.to_string(),
+            };
+            let payout = match amount {
+                Some(amount_stroops) => Payout::Fixed { amount_stroops },
+                None => Payout::Yield,
+            };
+            add_plan(vault, user, PlanAction::Withdraw { risk, payout, payee }, every)
+        }
+        RecurringCommand::Cancel { id } => {
+            let plan = vault.recurring.cancel(user, id).map_err(|e| e.to_string())?;
+            vault.persist().map_err(|e| format!("Failed to save the cancellation: {}", e))?;
+            say!("🗑️  Cancelled recurring plan #{}: {}", plan.id, describe_plan(vault, &plan));
+            Ok(json!({ "status": "cancelled", "plan": plan }))
+        }
+    }
+}
+
+fn add_plan(vault: &mut StellarVault, user: &str, action: PlanAction, every: Cadence) -> CommandResult {
+    if vault.get_vault_info(action.risk()).is_none() {
+        return Err(format!("The {:?} Risk Vault is not available", action.risk()));
+    }
+    let plan = vault.recurring.add(user, action, every, unix_now()).clone();
+    vault.persist().map_err(|e| format!("Failed to save the plan: {}", e))?;
+    say!("✅ Recurring plan #{}: {}", plan.id, describe_plan(vault, &plan));
+    say!("   First run in {}, as long as 'watch' or 'serve' is running then",
+        format_duration(plan.next_run_at.saturating_sub(unix_now())));
+    Ok(json!(plan))
+}
+
+fn describe_plan(vault: &StellarVault, plan: &RecurringPlan) -> String {
+    let code = vault.get_vault_info(plan.action.risk()).map_or("XLM", |info| info.asset_code());
+    match &plan.action {
+        PlanAction::Deposit { risk, amount_stroops } => format!("deposit {} {} into the {:?} Risk Vault {}",
+            Stroops(*amount_stroops).to_xlm_string(), code, risk, plan.cadence),
+        PlanAction::Withdraw { risk, payout, payee } => {
+            let amount = match payout {
+                Payout::Yield => "the accumulated yield".to_string(),
+                Payout::Fixed { amount_stroops } => format!("{} {}", Stroops(*amount_stroops).to_xlm_string(), code),
+            };
+            let payee = if *payee == plan.user { "you".to_string() } else { payee.clone() };
+            format!("pay {} from the {:?} Risk Vault to {} {}", amount, risk, payee, plan.cadence)
+        }
+    }
+}
+
+fn scheduled_vaults(vault: &StellarVault, risk: Option<RiskLevel>) -> Vec<RiskLevel> {
+    RiskLevel::ALL.into_iter()
+        .filter(|candidate| risk.is_none_or(|risk| risk == *candidate) && vault.vaults.contains_key(candidate))
+        .collect()
+}
+
+async fn run_job(vault: &mut StellarVault, job: &Job, apy_sources: &[Box<dyn ApyProvider>]) -> Result<(), String> {
+    let mut errors = Vec::new();
+    match job {
+        Job::Harvest(risk) => {
+            for risk in scheduled_vaults(vault, *risk) {
+                if let Err(e) = execute_harvest(vault, risk).await {
+                    errors.push(e);
+                }
+            }
+            run_process_queue(vault).await;
+        }
+        Job::Rebalance { risk, max_drift_bps } => {
+            for risk in scheduled_vaults(vault, *risk) {
+                match vault.rebalance(risk, *max_drift_bps) {
+                    Ok(moves) if moves.is_empty() => {}
+                    Ok(moves) => say!("⚖️  {:?} Risk Vault rebalanced: {} move(s)", risk, moves.len()),
+                    Err(e) => errors.push(format!("{:?} Risk Vault: {}", risk, e)),
+                }
+            }
+        }
+        Job::ApyRefresh => {
+            if apy_sources.is_empty() {
+                return Err("No [apy_oracle] sources to fetch from".to_string());
+            }
+            let mut changed = false;
+            for source in apy_sources {
+                match source.fetch().await {
+                    Ok(Some(apy)) => for (risk, old) in vault.set_strategy_apy(source.strategy(), apy) {
+                        say!("📈 {:?} Risk Vault {} APY {:.2}% → {:.2}% ({})",
+                            risk, source.strategy(), old as f64 / 100.0, apy as f64 / 100.0, source.source());
+                        changed = true;
+                    },
+                    Ok(None) => {}
+                    Err(e) => errors.push(format!("{} from {}: {}", source.strategy(), source.source(), e)),
+                }
+            }
+            if changed {
+                vault.persist().map_err(|e| format!("Failed to save live APYs: {}", e))?;
+            }
+        }
+        Job::CoverageCheck => {
+            let report = vault.adjust_premiums().map_err(|e| format!("Could not adjust premiums: {}", e))?;
+            if !report.changes.is_empty() {
+                print_premium_changes(&report);
+            }
+        }
+    }
+    if errors.is_empty() { Ok(()) } else { Err(errors.join("; ")) }
+}
+
+fn run_alerts(vault: &StellarVault, alerts: &mut AlertEngine) {
+    for alert in alerts.check(&Readings::of(vault), unix_now()) {
+        say!("🚨 {}", alert.message);
+        vault.notify(Notification::Alert(alert));
+    }
+}
+
+// Failovers happen mid-request, so they are reported from here afterwards
+fn report_failovers(vault: &StellarVault) {
+    for failover in vault.horizon.endpoints().take_failovers() {
+        say!("🔀 Horizon moved from {} to {}: {}", failover.from, failover.to, failover.reason);
+        vault.notify(Notification::HorizonFailover(failover));
+    }
+}
+
+// A deposit through the API. With an idempotency key, the key is saved before
+// anything is paid; a retry gets the first answer back, or runs again only if
+// the first attempt sent nothing.
+async fn answer_deposit(vault: &mut StellarVault, user: &str, deposit: api::DepositRequest) -> api::ApiReply {
+    use api::ApiError;
+    let pay_with = match deposit.pay_with.as_deref().map(parse_pay_with).transpose() {
+        Ok(pay_with) => pay_with.unwrap_or_else(|| vault.get_vault_info(deposit.risk).and_then(|info| info.asset.clone())),
+        Err(e) => return Err(ApiError::BadRequest(e.to_string())),
+    };
+    let key = match &deposit.idempotency_key {
+        Some(key) if !deposit.dry_run => key.clone(),
+        _ => return execute_deposit(vault, user, deposit.risk, pay_with, deposit.amount.0, true, deposit.dry_run, None).await
+            .map_err(ApiError::Failed),
+    };
+    let request = deposit.fingerprint();
+    match vault.start_keyed_deposit(user, deposit.risk, &key, &request) {
+        Ok(KeyedDeposit::Run) => {}
+        Ok(KeyedDeposit::Replay(reply)) => return Ok(reply),
+        Ok(KeyedDeposit::Conflict(reason)) => return Err(ApiError::Conflict(reason)),
+        Err(VaultError::Validation(e)) => return Err(ApiError::BadRequest(e)),
+        Err(e) => return Err(ApiError::Failed(format!("Deposit not made: could not save its idempotency key: {}", e))),
+    }
+    let result = execute_deposit(vault, user, deposit.risk, pay_with, deposit.amount.0, true, false, Some(&key)).await;
+    vault.finish_keyed_deposit(user, &key, &request, result.as_ref().ok());
+    result.map_err(ApiError::Failed)
+}
+
+// Answers one API request with the same JSON the matching command prints
+// under --output json
+async fn answer_api(vault: &mut StellarVault, user: &str, request: api::ApiRequest, caller: Option<&api::Caller>,
+                    upkeep: &Upkeep) -> api::ApiReply {
+    use api::{ApiError, ApiRequest};
+    // Tokens for other accounts may read but not move the server's funds
+    let other_account = caller
+        .and_then(|caller| caller.account.clone())
+        .filter(|account| account != user);
+    let not_signer = |account: &str| ApiError::Forbidden(format!("This server signs for {}, not {}", user, account));
+    let failed = |e: VaultError| ApiError::Failed(e.to_string());
+    // What the commands would say is only for the terminal
+    output::capture(true);
+    let reply = match request {
+        ApiRequest::Vaults => {
+            let vaults: Vec<serde_json::Value> = RiskLevel::ALL.into_iter()
+                .filter_map(|risk| print_vault_info(vault, risk).ok())
+                .collect();
+            Ok(json!(vaults))
+        }
+        ApiRequest::Vault(risk) => print_vault_info(vault, risk).map_err(ApiError::NotFound),
+        ApiRequest::Positions(account) => {
+            let positions: Vec<serde_json::Value> = RiskLevel::ALL.into_iter()
+                .filter(|&risk| vault.get_vault_info(risk).is_some())
+                .map(|risk| print_position(vault, &account, risk))
+                .collect();
+            Ok(json!({ "account": account, "positions": positions }))
+        }
+        ApiRequest::Deposit(_) | ApiRequest::Withdraw(_) if other_account.is_some() => {
+            Err(not_signer(other_account.as_deref().unwrap_or_default()))
+        }
+        ApiRequest::Deposit(deposit) => answer_deposit(vault, user, deposit).await,
+        ApiRequest::Withdraw(withdrawal) => {
+            let result = execute_withdraw(vault, user, withdrawal.risk, withdrawal.shares, withdrawal.dry_run).await;
+            result.map_err(ApiError::Failed)
+        }
+        ApiRequest::Pause { risk, reason } => vault.pause_vault(risk, &reason)
+            .map_err(failed)
+            .and_then(|()| print_vault_info(vault, risk).map_err(ApiError::NotFound)),
+        ApiRequest::Resume(risk) => vault.resume_vault(risk)
+            .map_err(failed)
+            .and_then(|()| print_vault_info(vault, risk).map_err(ApiError::NotFound)),
+        ApiRequest::Rebalance { risk, max_drift_bps } => vault.rebalance(risk, max_drift_bps)
+            .map(|moves| json!({ "risk": risk, "max_drift_bps": max_drift_bps, "moves": moves }))
+            .map_err(failed),
+        ApiRequest::DecideClaim { id, approve: true, note } => vault.approve_claim(id, &note).await
+            .map(|receipt| json!({ "id": id, "status": "paid", "transaction": receipt_json(&receipt) }))
+            .map_err(failed),
+        ApiRequest::DecideClaim { id, approve: false, note } => vault.deny_claim(id, &note)
+            .map(|()| json!({ "id": id, "status": "denied" }))
+            .map_err(failed),
+        ApiRequest::SetFees { risk, management_fee_bps, performance_fee_bps } => match vault.get_vault_info(risk) {
+            Some(info) => {
+                let management_fee_bps = management_fee_bps.unwrap_or(info.fees.management_fee_bps);
+                let performance_fee_bps = performance_fee_bps.unwrap_or(info.fees.performance_fee_bps);
+                match vault.propose(AdminChange::Fees { risk, management_fee_bps, performance_fee_bps }) {
+                    Ok(Proposal::Applied(changes)) => print_vault_info(vault, risk).map_err(ApiError::NotFound).map(|mut info| {
+                        info["changes"] = json!(changes);
+                        info
+                    }),
+                    Ok(Proposal::Queued(queued)) => Ok(json!({ "status": "queued", "change": queued })),
+                    Err(e) => Err(failed(e)),
+                }
+            }
+            None => Err(ApiError::NotFound(format!("No {:?} Risk Vault", risk))),
+        },
+        ApiRequest::AddressLists => print_address_lists(vault).map_err(failed),
+        ApiRequest::ListAddress { list, address, note } => vault.list_address(list, &address, &note)
+            .map(|()| json!({ "list": list.to_string(), "address": address, "status": "listed" }))
+            .map_err(failed),
+        ApiRequest::UnlistAddress { list, address } => vault.unlist_address(list, &address)
+            .map(|removed| json!({ "list": list.to_string(), "address": removed.address, "status": "unlisted" }))
+            .map_err(failed),
+        ApiRequest::Schedule => Ok(json!(upkeep.scheduler.status())),
+        ApiRequest::CheckStorage => vault.persist().map(|()| json!({})).map_err(failed),
+    };
+    output::capture(false);
+    reply
+}
+
+// ============================================================================
+// ADMIN
+// ============================================================================
+
+// Admin commands act for the vault, so they need one of its keys: the vault
+// key or an operator's, from STELLARVAULT_VAULT_SECRET, a signing service or
+// the keystore
+async fn unlock_operator(vault: &mut StellarVault, config: &Config) -> Result<(), String> {
+    if vault.vault_signer.is_none() {
+        let mut keystore = Keystore::open(&config.keystore_path).map_err(|e| format!("Failed to open keystore: {}", e))?;
+        let holds_operator_key = keystore.entries().iter()
+            .any(|entry| entry.public_key == vault.vault_address || vault.vault_signer_addresses.contains(&entry.public_key));
+        if holds_operator_key {
+            for _ in 0..3 {
+                match keystore.unlock(&get_password_input("🔑 Keystore password: ")) {
+                    Ok(keys) => {
+                        for key in keys {
+                            if key.public_key == vault.vault_address || vault.vault_signer_addresses.contains(&key.public_key) {
+                                vault.add_vault_signer(&key.secret_key).map_err(|e| format!("Could not load {}: {}", key.public_key, e))?;
+                            }
+                        }
+                        break;
+                    }
+                    Err(e) => say!("❌ {}", e),
+                }
+            }
+        }
+    }
+    match &vault.vault_signer {
+        Some(_) => Ok(()),
+        None => Err(format!("Admin commands need the key of {} or one of its operators, in STELLARVAULT_VAULT_SECRET or the keystore",
+            vault.vault_address)),
+    }
+}
+
+async fn run_admin_command(vault: &mut StellarVault, config: &Config, action: AdminCommand) -> CommandResult {
+    // Anyone may see what is coming; depositors are who it is for
+    if !matches!(action, AdminCommand::Pending | AdminCommand::RotateSigner { dry_run: true, .. }) {
+        unlock_operator(vault, config).await?;
+    }
+    match action {
+        AdminCommand::Pending => Ok(print_pending_changes(vault)),
+        AdminCommand::SetFees { risk, management_bps, performance_bps } => {
+            let info = vault.get_vault_info(risk).ok_or(format!("No {:?} Risk Vault", risk))?;
+            if management_bps.is_none() && performance_bps.is_none() {
+                return Err("Give --management-bps, --performance-bps or both".to_string());
+            }
+            let change = AdminChange::Fees {
+                risk,
+                management_fee_bps: management_bps.unwrap_or(info.fees.management_fee_bps),
+                performance_fee_bps: performance_bps.unwrap_or(info.fees.performance_fee_bps),
+            };
+            match vault.propose(change).map_err(|e| e.to_string())? {
+                Proposal::Applied(changes) => print_settings_changed(vault, config, risk, changes),
+                Proposal::Queued(queued) => Ok(print_queued_change(&queued)),
+            }
+        }
+        AdminCommand::SetStrategies { risk, strategies } => {
+            let info = vault.get_vault_info(risk).ok_or(format!("No {:?} Risk Vault", risk))?;
+            let mut targets = Vec::new();
+            for target in strategies {
+                let running = info.strategies.iter().find(|s| s.strategy == target.id).map(|s| s.current_apy);
+                let apy_bps = target.apy_bps.or(running)
+                    .ok_or(format!("The {:?} Risk Vault doesn't run {} yet; give its APY as {}={}@APY_BPS", risk, target.id, target.id, target.allocation))?;
+                targets.push(StrategyTarget { id: target.id, allocation: target.allocation, apy_bps });
+            }
+            match vault.propose(AdminChange::Strategies { risk, targets }).map_err(|e| e.to_string())? {
+                Proposal::Applied(changes) => print_settings_changed(vault, config, risk, changes),
+                Proposal::Queued(queued) => Ok(print_queued_change(&queued)),
+            }
+        }
+        AdminCommand::RotateSigner { old, new, weight, thresholds, dry_run } => {
+            rotate_vault_signer(vault, config, &old, new, weight, thresholds, dry_run).await
+        }
+        AdminCommand::Cancel { id } => {
+            let cancelled = vault.cancel_change(id).map_err(|e| e.to_string())?;
+            say!("🗑️  Cancelled admin change #{}: {}", cancelled.id, cancelled.change.describe());
+            Ok(json!({ "status": "cancelled", "change": cancelled }))
+        }
+        AdminCommand::SetCaps { risk, max_tvl, per_user, max_tvl_usd, per_user_usd } => {
+            let current = Caps::of(vault.get_vault_info(risk).ok_or(format!("No {:?} Risk Vault", risk))?);
+            if max_tvl.is_none() && per_user.is_none() && max_tvl_usd.is_none() && per_user_usd.is_none() {
+                return Err("Give at least one of --max-tvl, --per-user, --max-tvl-usd and --per-user-usd".to_string());
+            }
+            let caps = Caps {
+                max_tvl: max_tvl.map_or(current.max_tvl, |cap| cap.0),
+                per_user_cap: per_user.map_or(current.per_user_cap, |cap| cap.0),
+                max_tvl_usd: max_tvl_usd.map_or(current.max_tvl_usd, |cap| cap.0),
+                per_user_cap_usd: per_user_usd.map_or(current.per_user_cap_usd, |cap| cap.0),
+            };
+            let changes = vault.set_caps(risk, caps).map_err(|e| e.to_string())?;
+            print_settings_changed(vault, config, risk, changes)
+        }
+        AdminCommand::Pause { risk, reason } => {
+            let reason = reason.trim();
+            if reason.is_empty() {
+                return Err("Give a reason for pausing the vault".to_string());
+            }
+            vault.pause_vault(risk, reason).map_err(|e| e.to_string())?;
+            say!("⛔ {:?} Risk Vault paused: {}", risk, reason);
+            print_vault_info(vault, risk)
+        }
+        AdminCommand::Resume { risk } => {
+            vault.resume_vault(risk).map_err(|e| e.to_string())?;
+            say!("▶️  {:?} Risk Vault resumed", risk);
+            print_vault_info(vault, risk)
+        }
+        AdminCommand::Rebalance { risk, max_drift_bps } => {
+            let moves = vault.rebalance(risk, max_drift_bps).map_err(|e| format!("Rebalance failed: {}", e))?;
+            if moves.is_empty() {
+                say!("ℹ️  No strategy of the {:?} Risk Vault is more than {} bps off its target", risk, max_drift_bps);
+            } else {
+                say!("✅ {:?} Risk Vault rebalanced: {} move(s)", risk, moves.len());
+            }
+            Ok(json!({ "risk": risk, "max_drift_bps": max_drift_bps, "moves": moves }))
+        }
+        AdminCommand::ApproveClaim { id, note } => {
+            let receipt = vault.approve_claim(id, &note).await.map_err(|e| format!("Claim #{} not paid: {}", id, e))?;
+            say!("✅ Claim #{} paid: {}", id, vault.network.tx_link(&receipt.hash));
+            Ok(json!({ "id": id, "status": "paid", "transaction": receipt_json(&receipt) }))
+        }
+        AdminCommand::DenyClaim { id, note } => {
+            vault.deny_claim(id, &note).map_err(|e| format!("Claim #{} not denied: {}", id, e))?;
+            say!("✅ Claim #{} denied", id);
+            Ok(json!({ "id": id, "status": "denied" }))
+        }
+        AdminCommand::Lists => print_address_lists(vault).map_err(|e| e.to_string()),
+        AdminCommand::List { list, address, note } => {
+            vault.list_address(list, &address, note.trim()).map_err(|e| e.to_string())?;
+            say!("📋 {} is on the {}", address, list);
+            if list == AddressList::Allow && !vault.allowlist_only {
+                say!("   ℹ️  The allowlist only matters with allowlist_only set");
+            }
+            Ok(json!({ "list": list.to_string(), "address": address, "status": "listed" }))
+        }
+        AdminCommand::Unlist { list, address } => {
+            let removed = vault.unlist_address(list, &address).map_err(|e| e.to_string())?;
+            say!("📋 {} is off the {}", removed.address, list);
+            Ok(json!({ "list": list.to_string(), "address": removed.address, "status": "unlisted" }))
+        }
+        AdminCommand::SetTreasury { address } => {
+            let proposal = vault.propose(AdminChange::Treasury { address: address.clone() }).map_err(|e| e.to_string())?;
+            match proposal {
+                Proposal::Applied(changes) => {
+                    say!("🏛️  Fees now go to {}", address);
+                    Ok(json!({ "status": "applied", "changes": changes }))
+                }
+                Proposal::Queued(queued) => Ok(print_queued_change(&queued)),
+            }
+        }
+    }
+}
+
+// Swaps one of the vault account's signers for another in one transaction:
+// the new key is added, thresholds changed if asked, and the old key
+// removed. The local vault_signers and keystore follow once it lands. Not
+// held back by the admin delay, since a leaked key has to go at once.
+async fn rotate_vault_signer(vault: &mut StellarVault, config: &Config, old: &str, new: Option<String>, weight: Option<u8>,
+                             thresholds: Option<Thresholds>, dry_run: bool) -> CommandResult {
+    let account = vault.horizon.fresh_account(&vault.vault_address).await
+        .map_err(|e| format!("Could not load the vault account: {}", e))?;
+    let signer_set = SignerSet::from_account(&account);
+    let generated = new.is_none().then(Keypair::random);
+    let new = new.unwrap_or_else(|| generated.as_ref().map(Keypair::public_key).unwrap_or_default());
+    let (updated, operations) = multisig::rotation(&signer_set, old, &new, weight,
+        thresholds.map(|Thresholds(low, medium, high)| (low, medium, high))).map_err(|e| e.to_string())?;
+
+    // Only operator keys other than the vault's own need listing
+    let mut vault_signers: Vec<String> = config.vault_signers.iter().filter(|key| *key != old).cloned().collect();
+    if new != vault.vault_address && !vault_signers.contains(&new) {
+        vault_signers.push(new.clone());
+    }
+
+    let sequence = account.sequence_number().map_err(|e| e.to_string())?;
+    let fee = vault.submitter.fee_strategy.estimate(&vault.horizon).await;
+    let mut builder = TransactionBuilder::new(&vault.vault_address, sequence)
+        .and_then(|builder| builder.fee_per_operation(fee.per_operation).time_bounds(0, unix_now() + OFFLINE_VALIDITY_HOURS * 3600))
+        .map_err(|e| e.to_string())?;
+    for operation in &operations {
+        builder = builder.operation(operation.clone()).map_err(|e| e.to_string())?;
+    }
+    let envelope = offline::unsigned(builder.build().map_err(|e| e.to_string())?);
+
+    say!("\n🔑 ROTATE VAULT SIGNER ({})", vault.vault_address);
+    let master = |key: &str| if key == vault.vault_address { " (master key)" } else { "" };
+    say!("   Retiring {}{}, weight {}", old, master(old), signer_set.weight(old));
+    say!("   Adding {}{}, weight {}{}", new, master(&new), updated.weight(&new), if generated.is_some() { " (new key)" } else { "" });
+    say!("   Thresholds after: low {}, medium {}, high {}", updated.low, updated.medium, updated.high);
+    for (key, weight) in &updated.signers {
+        say!("   {} weight {}{}", key, weight, master(key));
+    }
+    say!("");
+    print_envelope(&envelope, &vault.network).map_err(|e| e.to_string())?;
+    say!("\n   vault_signers in {} becomes {:?}", config.path().display(), vault_signers);
+
+    let signers_json: Vec<serde_json::Value> = updated.signers.iter().map(|(key, weight)| json!({ "key": key, "weight": weight })).collect();
+    if dry_run {
+        let loaded = vault.vault_signer.as_ref().map(StellarClient::signer_keys).unwrap_or_default();
+        if let Err(e) = signer_set.check(&loaded.iter().map(String::as_str).collect::<Vec<_>>(), Threshold::High) {
+            say!("⚠️  {}; load more operator keys before rotating, or sign the transaction below with 'stellarvault sign' and send it with 'tx submit'", e);
+        }
+        say!("\n{}", transaction::to_base64(&envelope).map_err(|e| e.to_string())?);
+        say!("ℹ️  Dry run: nothing was submitted");
+        return Ok(json!({ "dry_run": true, "old": old, "new": new, "signers": signers_json,
+            "operations": offline::describe(&envelope, vault.network.passphrase()).map_err(|e| e.to_string())?,
+            "vault_signers": vault_signers }));
+    }
+
+    if get_user_input("Type 'rotate' to replace the signer: ") != "rotate" {
+        return Err("Cancelled".to_string());
+    }
+    // A generated key is saved before the account depends on it
+    if let Some(keypair) = &generated {
+        let mut keystore = unlock_keystore_for_saving(config)?;
+        keystore.add("vault signer", &keypair.secret_key()).map_err(|e| format!("Could not save the new key: {}", e))?;
+        say!("🔒 New key saved to encrypted keystore {}", keystore.path().display());
+    }
+    let signer = vault.vault_signer.as_ref().ok_or("No vault keys are loaded")?;
+    let receipt = signer.set_options(operations).await.map_err(|e| format!("Could not rotate the signer: {}", e))?;
+    say!("✅ {} replaced by {} in ledger {} ({})", old, new, receipt.ledger, vault.network.tx_link(&receipt.hash));
+
+    vault.remove_vault_signer(old);
+    if let Some(keypair) = &generated {
+        vault.add_vault_signer(&keypair.secret_key()).map_err(|e| e.to_string())?;
+    }
+    vault.vault_signer_addresses = vault_signers.clone();
+    if std::env::var_os("STELLARVAULT_VAULT_SIGNERS").is_some() {
+        say!("⚠️  Set STELLARVAULT_VAULT_SIGNERS to {} from now on", vault_signers.join(","));
+    } else if vault_signers != config.vault_signers {
+        match Config::record_list(&config.path(), "vault_signers", &vault_signers) {
+            Ok(()) => say!("📝 vault_signers updated in {}", config.path().display()),
+            Err(e) => say!("⚠️  Could not update {}: {}; set vault_signers = {:?} there by hand", config.path().display(), e, vault_signers),
+        }
+    }
+    if config.vault_secret_keys.iter().any(|secret| Keypair::from_secret(secret).is_ok_and(|key| key.public_key() == old)) {
+        say!("⚠️  STELLARVAULT_VAULT_SECRET still holds the retired key; replace it");
+    }
+    // The retired master key stays: it is still the vault's key, and the
+    // other signers can give it weight again
+    if old != vault.vault_address {
+        match Keystore::open(&config.keystore_path).and_then(|mut keystore| keystore.remove(old)) {
+            Ok(true) => say!("🗑️  Retired key removed from the keystore"),
+            Ok(false) => {}
+            Err(e) => say!("⚠️  Could not remove the retired key from the keystore: {}", e),
+        }
+    }
+    Ok(json!({ "status": "rotated", "old": old, "new": new, "signers": signers_json,
+        "transaction": receipt_json(&receipt), "vault_signers": vault_signers }))
+}
+
+// The keystore, unlocked to take a new key; an empty one gets a password
+fn unlock_keystore_for_saving(config: &Config) -> Result<Keystore, String> {
+    let mut keystore = Keystore::open(&config.keystore_path).map_err(|e| format!("Failed to open keystore: {}", e))?;
+    if keystore.is_empty() {
+        let password = get_password_input("🔑 Choose a keystore password: ");
+        if password.len() < 8 {
+            return Err("Password must be at least 8 characters".to_string());
+        }
+        if get_password_input("🔑 Repeat the password: ") != password {
+            return Err("Passwords do not match".to_string());
+        }
+        keystore.unlock(&password).map_err(|e| format!("Could not create keystore: {}", e))?;
+        return Ok(keystore);
+    }
+    for _ in 0..3 {
+        match keystore.unlock(&get_password_input("🔑 Keystore password: ")) {
+            Ok(_) => return Ok(keystore),
+            Err(e) => say!("❌ {}", e),
+        }
+    }
+    Err("Keystore not unlocked".to_string())
+}
+
+fn print_queued_change(queued: &QueuedChange) -> serde_json::Value {
+    say!("⏳ Admin change #{} queued: {}", queued.id, queued.change.describe());
+    say!("   Takes effect in {}; 'admin cancel {}' stops it until then",
+        format_duration(queued.takes_effect_at.saturating_sub(unix_now())), queued.id);
+    json!({ "status": "queued", "change": queued })
+}
+
+fn print_pending_changes(vault: &StellarVault) -> serde_json::Value {
+    let pending: Vec<&QueuedChange> = vault.timelock.pending().collect();
+    if pending.is_empty() {
+        say!("⏳ No admin changes pending");
+    }
+    for queued in &pending {
+        let wait = queued.takes_effect_at.saturating_sub(unix_now());
+        let when = if wait == 0 { "due now".to_string() } else { format!("in {}", format_duration(wait)) };
+        say!("⏳ #{} {}: takes effect {}", queued.id, queued.change.describe(), when);
+    }
+    json!(pending)
+}
+
+fn print_address_lists(vault: &StellarVault) -> Result<serde_json::Value, VaultError> {
+    for list in [AddressList::Allow, AddressList::Deny] {
+        let entries: Vec<_> = vault.address_lists.entries(list).collect();
+        say!("📋 {} ({}):", list, entries.len());
+        for entry in &entries {
+            let note = if entry.note.is_empty() { String::new() } else { format!(" — {}", entry.note) };
+            say!("   {}{}, added {} ago", entry.address, note, format_duration(unix_now().saturating_sub(entry.added_at)));
+        }
+    }
+    if vault.allowlist_only {
+        say!("   Only allowlisted accounts may deposit or be paid");
+    }
+    let blocked = vault.blocked_operations(BLOCKED_SHOWN)?;
+    if !blocked.is_empty() {
+        say!("⛔ Latest refused:");
+    }
+    for record in &blocked {
+        let VaultEvent::OperationBlocked { operation, risk, amount_stroops, reason, .. } = &record.event else {
+            continue;
+        };
+        let amount = match vault.get_vault_info(*risk) {
+            Some(info) if *amount_stroops > 0 => format!(" of {} {}", Stroops(*amount_stroops).to_xlm_string(), info.asset_code()),
+            _ => String::new(),
+        };
+        say!("   #{} {}{} in the {:?} Risk Vault, {} ago: {}", record.seq, operation, amount, risk,
+            format_duration(unix_now().saturating_sub(record.timestamp)), reason);
+    }
+    Ok(json!({
+        "allowlist_only": vault.allowlist_only,
+        "allowlist": vault.address_lists.entries(AddressList::Allow).collect::<Vec<_>>(),
+        "denylist": vault.address_lists.entries(AddressList::Deny).collect::<Vec<_>>(),
+        "blocked": blocked,
+    }))
+}
+
+// Applies queued admin changes whose delay is out
+fn apply_due_changes(vault: &mut StellarVault) {
+    for (queued, result) in vault.apply_due_changes() {
+        match result {
+            Ok(changes) => say!("🔧 Admin change #{} took effect: {}", queued.id,
+                if changes.is_empty() { queued.change.describe() } else { changes.join(", ") }),
+            Err(e) => say!("❌ Admin change #{} ({}) could not take effect: {}", queued.id, queued.change.describe(), e),
+        }
+    }
+}
+
+// Vault definitions in the configuration are applied at every start, so a
+// change made here lasts only until then unless they say the same. Putting
+// back a higher fee or other strategies still waits out the admin delay.
+fn print_settings_changed(vault: &StellarVault, config: &Config, risk: RiskLevel, changes: Vec<String>) -> CommandResult {
+    if changes.is_empty() {
+        say!("ℹ️  The {:?} Risk Vault already has those settings", risk);
+    } else {
+        say!("🔧 {:?} Risk Vault: {}", risk, changes.join(", "));
+        if !config.vaults.is_empty() {
+            let source = config.vaults_source.as_ref().map_or("the configuration".to_string(), |path| path.display().to_string());
+            say!("   ⚠️  Change the vault definitions in {} too, or the next start puts them back (fee rises and strategy changes after the admin delay)", source);
+        }
+    }
+    let mut info = print_vault_info(vault, risk)?;
+    info["changes"] = json!(changes);
+    Ok(info)
+}
+
+// Tells /ws clients about share prices that moved since the last call
+fn publish_share_prices(vault: &StellarVault, server: &api::ApiServer, published: &mut HashMap<RiskLevel, u64>) {
+    for risk in RiskLevel::ALL {
+        let Some(info) = vault.get_vault_info(risk) else {
+            continue;
+        };
+        let price = info.get_share_price();
+        if published.insert(risk, price) != Some(price) {
+            server.publish(&api::LiveEvent::SharePrice { risk, share_price: Stroops(price).to_xlm_string(), timestamp: unix_now() });
+        }
+    }
+}
+
+// Serves the vault over HTTP until Ctrl-C, answering requests one at a time
+// between the same background updates the interactive menu runs
+async fn run_serve(vault: &mut StellarVault, user: &str, config: &Config, network: Option<&str>, bind: &str,
+                   origins: Vec<String>, home_domain: &str) -> CommandResult {
+    let signing_key = match &config.web_auth_secret_key {
+        Some(secret) => Keypair::from_secret(secret).map_err(|e| e.to_string())?,
+        None => Keypair::random(),
+    };
+    let auth = WebAuth::new(signing_key, vault.network.passphrase(), home_domain);
+    say!("🔑 SEP-10 challenges for {} are signed by {}", home_domain, auth.signing_key());
+    if config.web_auth_secret_key.is_none() {
+        say!("   A key for this run only; set STELLARVAULT_WEB_AUTH_SECRET to keep it across restarts");
+    }
+    let roles = config.api_operators.iter().map(|account| (account, api::Role::Operator))
+        .chain(config.api_admins.iter().map(|account| (account, api::Role::Admin)));
+    let access = roles.fold(api::Access::new(auth), |access, (account, role)| access.with_account(account, role));
+    let access = config.api_keys.iter().fold(access, |access, (role, key)| access.with_key(key, *role));
+    say!("🛂 {} operator/admin account(s) and {} API key(s)", config.api_operators.len() + config.api_admins.len(), config.api_keys.len());
+    let network = network.map(str::to_string);
+    let dependencies = api::Dependencies {
+        horizon: vault.horizon.clone(),
+        soroban: vault.soroban.clone(),
+        vault_address: vault.vault_address.clone(),
+        config_check: Box::new(move || Config::load(network.as_deref()).map(|_| ())),
+    };
+    let mut server = api::spawn(bind, origins, access, dependencies, config.api_rate_limits)
+        .map_err(|e| format!("Could not listen on {}: {}", bind, e))?;
+    say!("🌐 Serving the vault API on http://{} as {}; Ctrl-C to stop", bind, user);
+    say!("📡 Live events at ws://{}/ws", bind);
+    let (feed, mut events) = tokio::sync::mpsc::unbounded_channel();
+    vault.event_feed = Some(feed);
+    let mut share_prices = HashMap::new();
+    let mut payment_stream = config.ingest
+        .then(|| ingest::spawn(vault.horizon.clone(), vault.vault_address.clone(), vault.ingest_cursor.clone()));
+    let mut price_stream = match &config.price_oracle {
+        Some(oracle) => start_price_oracle(vault, oracle),
+        None => None,
+    };
+    let mut alerts = config.alerts.clone().map(AlertEngine::new);
+    let mut upkeep = Upkeep::new(vault, config);
+    let mut ticker = tokio::time::interval(WATCH_INTERVAL);
+
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            call = server.calls.recv() => {
+                let Some(call) = call else { break };
+                let action = call.request.action();
+                let span = tracing::info_span!("api_request",
+                    request = call.request.name(),
+                    caller = call.caller.as_ref().map(ToString::to_string).unwrap_or_default());
+                let reply = answer_api(vault, user, call.request, call.caller.as_ref(), &upkeep).instrument(span).await;
+                match (&reply, action, &call.caller) {
+                    (Err(e), _, _) => say!("⚠️  API request failed: {}", e),
+                    (Ok(_), Some(action), Some(caller)) => say!("🛂 {} by {}", action, caller),
+                    _ => {}
+                }
+                let _ = call.reply.send(reply);
+            }
+            _ = ticker.tick() => {
+                if let Some(stream) = payment_stream.as_mut() {
+                    run_ingest(vault, stream).await;
+                }
+                if let Some(stream) = price_stream.as_mut() {
+                    run_price_updates(vault, stream);
+                }
+                if let Some(alerts) = alerts.as_mut() {
+                    run_alerts(vault, alerts);
+                }
+                report_failovers(vault);
+                run_schedule(vault, &mut upkeep).await;
+            }
+        }
+        // Everything the vault logged while answering, then the prices it moved
+        while let Ok(record) = events.try_recv() {
+            server.publish(&api::LiveEvent::Vault(&record));
+        }
+        publish_share_prices(vault, &server, &mut share_prices);
+    }
+
+    vault.event_feed = None;
+    server.stop().await;
+    say!("\n👋 API server stopped");
+    Ok(json!({ "status": "stopped" }))
+}
+
+// Applies edits to the vault definitions file, all of them or none
+fn run_reload(vault: &mut StellarVault, watcher: &mut ConfigWatcher) {
+    let Some(loaded) = watcher.poll() else {
+        return;
+    };
+    let definitions = match loaded.and_then(build_vaults) {
+        Ok(definitions) => definitions,
+        Err(e) => {
+            say!("⚠️  Keeping the current vault settings; rejected {}: {}", watcher.path().display(), e);
+            return;
+        }
+    };
+    let (applied, queued) = vault.apply_definitions(&definitions);
+    if applied.is_empty() && queued.is_empty() {
+        say!("🔧 {} changed; no vault settings differ", watcher.path().display());
+        return;
+    }
+    for (risk, changes) in &applied {
+        say!("🔧 {:?} Risk Vault reconfigured from {}:", risk, watcher.path().display());
+        for change in changes {
+            say!("   {}", change);
+        }
+    }
+    for change in &queued {
+        print_queued_change(change);
+    }
+    if let Err(e) = vault.persist() {
+        say!("⚠️  Failed to save vault settings: {}", e);
+    }
+}
+
+fn apy_providers(vault: &StellarVault, config: &Config, oracle: &ApyOracleConfig) -> Vec<Box<dyn ApyProvider>> {
+    let mut providers: Vec<Box<dyn ApyProvider>> = Vec::new();
+    if let Some(blend) = config.blend.as_ref().filter(|_| oracle.blend) {
+        match vault.blend_call(blend.risk) {
+            Ok((rpc, pool, token)) => providers.push(Box::new(
+                BlendApy::new(rpc.clone(), &pool, &token, &vault.vault_address, strategy::YIELDBLOX_LENDING))),
+            Err(e) => say!("⚠️  Not following the Blend supply rate: {}", e),
+        }
+    }
+    for api in &oracle.apis {
+        providers.push(Box::new(api.clone()));
+    }
+    providers
+}
+
+fn start_apy_oracle(vault: &StellarVault, config: &Config, oracle: &ApyOracleConfig) -> (Option<UnboundedReceiver<RateUpdate>>, LiveRates) {
+    let providers = apy_providers(vault, config, oracle);
+    let strategies: Vec<String> = providers.iter().map(|p| p.strategy().to_string()).collect();
+    let live_rates = LiveRates::new(strategies.iter().map(String::as_str), oracle.max_age_secs, unix_now());
+    if providers.is_empty() {
+        return (None, live_rates);
+    }
+    say!("📈 Fetching live APYs for {} every {}", strategies.join(", "), format_duration(oracle.interval_secs));
+    (Some(apy::spawn(providers, Duration::from_secs(oracle.interval_secs))), live_rates)
+}
+
+// Applies fetched APYs; a failed fetch leaves the last known rate in place
+fn run_apy_updates(vault: &mut StellarVault, stream: &mut UnboundedReceiver<RateUpdate>, live_rates: &mut LiveRates) {
+    let mut changed = false;
+    while let Ok(update) = stream.try_recv() {
+        match update.result {
+            Ok(Some(apy)) => {
+                live_rates.record(&update.strategy, apy, unix_now());
+                for (risk, old) in vault.set_strategy_apy(&update.strategy, apy) {
+                    say!("📈 {:?} Risk Vault {} APY {:.2}% → {:.2}% ({})",
+                        risk, update.strategy, old as f64 / 100.0, apy as f64 / 100.0, update.source);
+                    changed = true;
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                let kept = live_rates.last(&update.strategy)
+                    .map_or("the configured rate".to_string(), |apy| format!("{:.2}%", apy as f64 / 100.0));
+                say!("⚠️  Could not fetch the {} APY from {}: {}; keeping {}", update.strategy, update.source, e, kept);
+            }
+        }
+    }
+    for stale in live_rates.newly_stale(unix_now()) {
+        match stale.last_apy_bps {
+            Some(apy) => say!("⚠️  The {} APY of {:.2}% is {} old; yields are estimated from it until a fresh rate arrives",
+                stale.strategy, apy as f64 / 100.0, format_duration(stale.age_secs)),
+            None => say!("⚠️  No live {} APY after {}; yields are estimated from the configured rate",
+                stale.strategy, format_duration(stale.age_secs)),
+        }
+    }
+    if changed {
+        if let Err(e) = vault.persist() {
+            say!("⚠️  Failed to save live APYs: {}", e);
+        }
+    }
+}
+
+const PRICE_WAIT: Duration = Duration::from_secs(30);
+
+fn start_price_oracle(vault: &mut StellarVault, oracle: &PriceOracleConfig) -> Option<UnboundedReceiver<PriceUpdate>> {
+    vault.prices.max_age_secs = oracle.max_age_secs;
+    let reflector: Option<Box<dyn PriceSource>> = match (&oracle.reflector, &vault.soroban) {
+        (Some((contract, feed)), Some(rpc)) => {
+            match Reflector::new(rpc.clone(), contract, *feed, &vault.vault_address, vault.network.passphrase()) {
+                Ok(reflector) => Some(Box::new(reflector)),
+                Err(e) => {
+                    say!("⚠️  Not reading prices from Reflector: {}", e);
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+    if reflector.is_none() && oracle.mock.is_empty() {
+        return None;
+    }
+    let assets: Vec<Option<AssetId>> = vault.vaults.values().map(|v| v.asset.clone())
+        .fold(Vec::new(), |mut assets, asset| {
+            if !assets.contains(&asset) {
+                assets.push(asset);
+            }
+            assets
+        });
+    say!("💲 Pricing vault assets in USD from {} every {}",
+        reflector.as_ref().map_or("mock prices".to_string(), |r| r.name()), format_duration(oracle.interval_secs));
+    Some(oracle::spawn(reflector, oracle.mock.clone(), assets, Duration::from_secs(oracle.interval_secs)))
+}
+
+// Prices that fail to arrive age out of the book, which then refuses
+// deposits into vaults with USD caps
+fn run_price_updates(vault: &mut StellarVault, stream: &mut UnboundedReceiver<PriceUpdate>) {
+    while let Ok(update) = stream.try_recv() {
+        record_price(vault, update);
+    }
+}
+
+fn record_price(vault: &mut StellarVault, update: PriceUpdate) {
+    let asset = update.asset.as_ref().map_or("XLM".to_string(), |asset| asset.code.clone());
+    match update.result {
+        Ok((quote, source)) => {
+            if source != vault.prices.quote(update.asset.as_ref(), unix_now()).map_or("", |(_, source)| source) {
+                say!("💲 {} priced at {} from {}", asset, format_usd(quote.usd), source);
+            }
+            vault.prices.record(update.asset.as_ref(), quote, &source);
+        }
+        Err(e) => say!("⚠️  Could not price {} in USD: {}", asset, e),
+    }
+}
+
+// One-shot commands wait for the first round of prices, which USD caps and
+// values need
+async fn prime_prices(vault: &mut StellarVault, config: &Config) {
+    let Some(mut stream) = config.price_oracle.as_ref().and_then(|oracle| start_price_oracle(vault, oracle)) else {
+        return;
+    };
+    let mut unpriced: Vec<Option<AssetId>> = vault.vaults.values().map(|v| v.asset.clone()).collect();
+    while !unpriced.is_empty() {
+        match tokio::time::timeout(PRICE_WAIT, stream.recv()).await {
+            Ok(Some(update)) => {
+                unpriced.retain(|asset| *asset != update.asset);
+                record_price(vault, update);
+            }
+            _ => break,
+        }
+    }
+}
+
+async fn run_bump(vault: &StellarVault, hash: &str) {
+    if hash.is_empty() {
+        let unconfirmed = vault.submitter.unconfirmed();
+        if unconfirmed.is_empty() {
+            say!("\nℹ️  No unconfirmed transactions this session");
+        } else {
+            say!("\n⏳ UNCONFIRMED TRANSACTIONS (use 'bump <hash>')");
+            for hash in unconfirmed {
+                say!("   {}", hash);
+            }
+        }
+        return;
+    }
+
+    match vault.submitter.fee_payer() {
+        Some(fee_payer) => say!("\n⛽ Fee-bumping {} (paid by {}, up to {} stroops per operation)...",
+            hash, fee_payer, vault.submitter.fee_strategy.max_fee),
+        None => {
+            say!("❌ No fee payer is configured; set fee_payer and unlock its key");
+            return;
+        }
+    }
+    match vault.submitter.bump(hash).await {
+        Ok(applied) => {
+            let receipt = TransactionReceipt::from(applied);
+            say!("✅ Transaction {} confirmed in ledger {} (fee {} stroops)", receipt.hash, receipt.ledger, receipt.fee_charged);
+            say!("   🔗 {}", vault.network.tx_link(&receipt.hash));
+        }
+        Err(e) => say!("❌ {}", e),
+    }
+}
+
+// Reads a transaction envelope from a file path, or from pasted base64 XDR
+fn read_envelope(input: &str) -> Result<stellar_xdr::curr::TransactionEnvelope, VaultError> {
+    let path = std::path::Path::new(input);
+    if path.is_file() {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        offline::decode(&contents)
+    } else {
+        offline::decode(input)
+    }
+}
+
+fn print_envelope(envelope: &stellar_xdr::curr::TransactionEnvelope, network: &Network) -> Result<(), VaultError> {
+    say!("   Network: {}", network);
+    for line in offline::describe(envelope, network.passphrase())? {
+        say!("   {}", line);
+    }
+    Ok(())
+}
+
+// Builds an unsigned payment, by default from the vault account, for signing
+// on another machine with `stellarvault sign <file>`
+async fn run_tx_build(vault: &StellarVault) {
+    say!("\n🧱 BUILD A TRANSACTION FOR OFFLINE SIGNING");
+    let source = match get_user_input(&format!("Source account (default vault {}): ", vault.vault_address)).as_str() {
+        "" => vault.vault_address.clone(),
+        source => source.to_string(),
+    };
+    let destination = get_user_input("Destination (G..., M... or name*domain): ");
+    let (destination, required_memo) = match vault.resolve_address(&destination).await {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            say!("❌ {}", e);
+            return;
+        }
+    };
+    let amount = match Stroops::from_xlm_str(&get_user_input("Amount (XLM): ")) {
+        Ok(amount) => amount,
+        Err(e) => {
+            say!("❌ {}", e);
+            return;
+        }
+    };
+    let memo = match &required_memo {
+        Some(memo) => memo.clone(),
+        None => match get_user_input("Memo (optional, up to 28 bytes): ").as_str() {
+            "" => Memo::None,
+            text => match transaction::text_memo(text) {
+                Ok(memo) => memo,
+                Err(e) => {
+                    say!("❌ {}", e);
+                    return;
+                }
+            },
+        },
+    };
+    if memo == Memo::None {
+        if let Err(e) = check_memo_not_required(&vault.horizon, &destination).await {
+            say!("❌ {}", e);
+            return;
+        }
+    }
+    let hours: u64 = get_user_input(&format!("Hours to sign and submit it in (default {}): ", OFFLINE_VALIDITY_HOURS))
+        .parse()
+        .unwrap_or(OFFLINE_VALIDITY_HOURS);
+
+    let sequence = match vault.horizon.fresh_account(&source).await.map_err(VaultError::from)
+        .and_then(|account| account.sequence_number()) {
+        Ok(sequence) => sequence,
+        Err(e) => {
+            say!("❌ Could not load {}: {}", source, e);
+            return;
+        }
+    };
+    let fee = vault.submitter.fee_strategy.estimate(&vault.horizon).await;
+
+    let built = TransactionBuilder::new(&source, sequence)
+        .map(|builder| builder.fee_per_operation(fee.per_operation))
+        .and_then(|builder| builder.time_bounds(0, unix_now() + hours * 3600))
+        .and_then(|builder| builder.payment(&destination, stellar_xdr::curr::Asset::Native, amount.0))
+        .map(|builder| builder.memo(memo))
+        .and_then(|builder| builder.build())
+        .map(offline::unsigned)
+        .and_then(|envelope| Ok((offline::hash(&envelope, vault.network.passphrase())?, transaction::to_base64(&envelope)?, envelope)));
+    let (hash, xdr, envelope) = match built {
+        Ok(built) => built,
+        Err(e) => {
+            say!("❌ Could not build the transaction: {}", e);
+            return;
+        }
+    };
+
+    say!("\n📄 Unsigned transaction:");
+    if let Err(e) = print_envelope(&envelope, &vault.network) {
+        say!("❌ {}", e);
+        return;
+    }
+    say!("\n{}\n", xdr);
+    say!("ℹ️  It uses sequence {}; any other transaction from {} before it is submitted invalidates it",
+        sequence + 1, source);
+
+    let path = format!("tx-{}.xdr", &hash[..8]);
+    match std::fs::write(&path, &xdr) {
+        Ok(()) => say!("💾 Saved to {}; sign it offline with `stellarvault sign {}`", path, path),
+        Err(e) => say!("⚠️  Could not save {}: {}", path, e),
+    }
+}
+
+// Refuses envelopes whose signatures don't yet meet the source account's
+// threshold, so each operator can sign in turn before anything is sent
+async fn check_signatures(vault: &StellarVault, envelope: &stellar_xdr::curr::TransactionEnvelope) -> Result<(), VaultError> {
+    let stellar_xdr::curr::TransactionEnvelope::Tx(signed) = envelope else {
+        return Err(VaultError::Validation("Only v1 transaction envelopes are supported".into()));
+    };
+    let source = signed.tx.source_account.to_string();
+    let account = vault.horizon.account(&source).await
+        .map_err(|e| format!("Failed to load signers of {}: {}", source, e))?;
+    let signer_set = SignerSet::from_account(&account);
+    let (signed_by, unknown) = signer_set.signed_by(&signed.tx, &signed.signatures, vault.network.passphrase())?;
+    let threshold = multisig::required_threshold(&signed.tx);
+
+    say!("   Signed by: {}", if signed_by.is_empty() { "nobody yet".to_string() } else { signed_by.join(", ") });
+    if unknown > 0 {
+        return Err(VaultError::Validation(format!("{} signature(s) are not from a signer of {}", unknown, source)));
+    }
+    let signed_by: Vec<&str> = signed_by.iter().map(String::as_str).collect();
+    signer_set.check(&signed_by, threshold)
+        .map_err(|e| format!("{}; have more signers run `stellarvault sign` on it", e))?;
+    Ok(())
+}
+
+async fn run_tx_submit(vault: &StellarVault) {
+    let input = get_user_input("\n📤 Signed transaction (file path or base64 XDR): ");
+    let envelope = match read_envelope(&input) {
+        Ok(envelope) => envelope,
+        Err(e) => {
+            say!("❌ {}", e);
+            return;
+        }
+    };
+    if let Err(e) = print_envelope(&envelope, &vault.network) {
+        say!("❌ {}", e);
+        return;
+    }
+    if let Err(e) = check_signatures(vault, &envelope).await {
+        say!("❌ {}", e);
+        return;
+    }
+    let prompt = if vault.network.is_mainnet() { "⚠️  MAINNET: type 'send' to submit: " } else { "Type 'send' to submit: " };
+    if get_user_input(prompt) != "send" {
+        say!("❌ Cancelled");
+        return;
+    }
+
+    match vault.submitter.submit_signed(&envelope).await {
+        Ok(applied) => {
+            let receipt = TransactionReceipt::from(applied);
+            say!("✅ Transaction {} confirmed in ledger {} (fee {} stroops)", receipt.hash, receipt.ledger, receipt.fee_charged);
+            say!("   🔗 {}", vault.network.tx_link(&receipt.hash));
+        }
+        Err(e) => say!("❌ {}", e),
+    }
+}
+
+// `stellarvault sign <file>`: adds a signature to an exported transaction
+// without touching the network, for keys kept on an air-gapped machine
+fn run_offline_sign(config: &Config, input: Option<&str>) -> CommandResult {
+    say!("✍️  OFFLINE SIGNING ({})\n", config.network);
+    let input = match input {
+        Some(input) => input.to_string(),
+        None => get_user_input("Transaction (file path or base64 XDR): "),
+    };
+    let envelope = read_envelope(&input).map_err(|e| e.to_string())?;
+    // The hash depends on the passphrase, so a wrong network means a useless signature
+    print_envelope(&envelope, &config.network).map_err(|e| e.to_string())?;
+
+    let secret = match Keystore::open(&config.keystore_path) {
+        Ok(mut keystore) if !keystore.is_empty() => {
+            let keys = keystore.unlock(&get_password_input("\n🔑 Keystore password: ")).map_err(|e| e.to_string())?;
+            for (i, key) in keys.iter().enumerate() {
+                say!("   {}. {} ({})", i + 1, key.public_key, key.label);
+            }
+            match get_user_input("👉 Sign with (default 1, or 'ledger'): ").as_str() {
+                "ledger" => "ledger".to_string(),
+                choice => keys[choice.parse().unwrap_or(1).clamp(1, keys.len()) - 1].secret_key.clone(),
+            }
+        }
+        _ => get_password_input("\n🔑 Secret key (S...), or 'ledger': "),
+    };
+    let signer: Result<Box<dyn Signer>, VaultError> = match secret.as_str() {
+        "ledger" => connect_ledger(),
+        secret => Keypair::from_secret(secret).map(|k| Box::new(k) as Box<dyn Signer>),
+    };
+    let signer = signer.map_err(|e| e.to_string())?;
+
+    if get_user_input(&format!("Type 'sign' to sign as {}: ", signer.public_key())) != "sign" {
+        return Err("Cancelled".to_string());
+    }
+    let signed = offline::sign(envelope, config.network.passphrase(), signer.as_ref())
+        .and_then(|envelope| transaction::to_base64(&envelope));
+    let xdr = signed.map_err(|e| format!("Could not sign: {}", e))?;
+    say!("\n✅ Signed transaction:\n\n{}\n", xdr);
+    let mut saved_to = None;
+    if std::path::Path::new(&input).is_file() {
+        let path = format!("{}.signed", input);
+        match std::fs::write(&path, &xdr) {
+            Ok(()) => {
+                say!("💾 Saved to {}; submit it online with 'tx submit'", path);
+                saved_to = Some(path);
+            }
+            Err(e) => say!("⚠️  Could not save {}: {}", path, e),
+        }
+    }
+    Ok(json!({ "signer": signer.public_key(), "transaction": xdr, "saved_to": saved_to }))
+}
+
+// Shows and changes who signs for the vault account
+async fn run_multisig(vault: &mut StellarVault, command: &str) {
+    let account = match vault.horizon.account(&vault.vault_address).await {
+        Ok(account) => account,
+        Err(e) => {
+            say!("❌ Could not load the vault account: {}", e);
+            return;
+        }
+    };
+    let signer_set = SignerSet::from_account(&account);
+    let loaded = vault.vault_signer.as_ref().map(StellarClient::signer_keys).unwrap_or_default();
+
+    match command {
+        "cosign" => {
+            let secret = get_password_input("🔑 Operator secret key (S...): ");
+            match vault.add_vault_signer(&secret) {
+                Ok(public_key) if signer_set.weight(&public_key) == 0 => {
+                    say!("⚠️  {} is not a signer of the vault account; it won't be used", public_key);
+                }
+                Ok(public_key) => say!("✅ {} will co-sign vault payments (weight {})",
+                    public_key, signer_set.weight(&public_key)),
+                Err(e) => say!("❌ {}", e),
+            }
+        }
+        "signer" => {
+            let key = get_user_input("Signer (G...): ");
+            let Ok(weight) = get_user_input("Weight (0 removes it): ").parse::<u8>() else {
+                say!("❌ Weight must be between 0 and 255");
+                return;
+            };
+            let updated = signer_set.clone().with_signer(&key, weight);
+            let operation = multisig::set_signer(&vault.vault_address, &key, weight);
+            update_vault_signers(vault, updated, operation).await;
+        }
+        "thresholds" => {
+            let thresholds: Vec<u8> = ["Low", "Medium", "High"].iter()
+                .filter_map(|level| get_user_input(&format!("{} threshold: ", level)).parse().ok())
+                .collect();
+            let [low, medium, high] = thresholds[..] else {
+                say!("❌ Thresholds must be between 0 and 255");
+                return;
+            };
+            let updated = signer_set.clone().with_thresholds(low, medium, high);
+            update_vault_signers(vault, updated, Ok(multisig::set_thresholds(low, medium, high))).await;
+        }
+        _ => {
+            say!("\n🔏 VAULT SIGNERS ({})", vault.vault_address);
+            say!("   Thresholds: low {}, medium {}, high {}", signer_set.low, signer_set.medium, signer_set.high);
+            for (key, weight) in &signer_set.signers {
+                let master = if *key == vault.vault_address { " (master key)" } else { "" };
+                let status = if loaded.contains(key) { "✅ loaded" } else { "—" };
+                say!("   {} weight {}{} {}", key, weight, master, status);
+            }
+            let weight = signer_set.weight_of(loaded.iter().map(String::as_str));
+            say!("   Loaded weight: {} (payments need {}, signer changes {})", weight,
+                signer_set.required(Threshold::Medium), signer_set.required(Threshold::High));
+            say!("   Commands: multisig cosign | multisig signer | multisig thresholds");
+        }
+    }
+}
+
+// Creates a brand-new user account funded from the vault account, with the
+// share token trustlines already open when share tokens are enabled
+async fn run_onboard(vault: &StellarVault) {
+    let Some(signer) = vault.vault_signer.as_ref() else {
+        say!("❌ New accounts are funded from the vault account; load its keys with 'multisig cosign'");
+        return;
+    };
+    let mut assets = Vec::new();
+    if let Some(issuer) = &vault.share_issuer {
+        if get_user_input("Open trustlines to the vault share tokens? (y/N): ").eq_ignore_ascii_case("y") {
+            for risk in RiskLevel::ALL {
+                let share = ShareAsset::for_vault(risk, issuer);
+                match share.id().to_xdr() {
+                    Ok(asset) => assets.push(asset),
+                    Err(e) => {
+                        say!("❌ {}", e);
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    // Sponsored accounts and trustlines have their reserves held by the vault
+    let sponsored = !get_user_input("Sponsor the account's reserves from the vault? (Y/n): ").eq_ignore_ascii_case("n");
+    let minimum = if sponsored { Stroops(0) } else { Stroops(transaction::minimum_balance(assets.len() as u64)) };
+    let input = get_user_input(&format!("Starting balance (XLM, default {}): ", minimum.to_xlm_string()));
+    let starting_balance = match input.as_str() {
+        "" => minimum,
+        amount => match Stroops::from_xlm_str(amount) {
+            Ok(amount) if amount >= minimum => amount,
+            Ok(_) => {
+                say!("❌ The account needs at least {} for its reserve", minimum);
+                return;
+            }
+            Err(e) => {
+                say!("❌ {}", e);
+                return;
+            }
+        },
+    };
+
+    let phrase = wallet::new_mnemonic();
+    let keypair = match wallet::from_mnemonic(&phrase, "", 0) {
+        Ok(keypair) => keypair,
+        Err(e) => {
+            say!("❌ Could not create wallet: {}", e);
+            return;
+        }
+    };
+    say!("\n🆕 Creating {} with {} from {}", keypair.public_key(), starting_balance, signer.get_public_key());
+    match signer.create_account(&keypair, starting_balance, &assets, sponsored).await {
+        Ok(receipt) => {
+            say!("✅ Account created in ledger {} ({})", receipt.ledger, vault.network.tx_link(&receipt.hash));
+            if !assets.is_empty() {
+                say!("   🪙 Trustlines open for {} share tokens", assets.len());
+            }
+            if sponsored {
+                say!("   🤝 Reserves sponsored by {}", signer.get_public_key());
+            }
+            say!("   📝 Recovery phrase for the new user (shown only once):");
+            say!("      {}", phrase);
+            say!("   💡 They can add it with 'wallet import'");
+        }
+        Err(e) => say!("❌ Could not create the account: {}", e),
+    }
+}
+
+async fn update_vault_signers(vault: &StellarVault, updated: SignerSet, operation: Result<stellar_xdr::curr::OperationBody, VaultError>) {
+    let operation = match updated.ensure_reachable().and(operation) {
+        Ok(operation) => operation,
+        Err(e) => {
+            say!("❌ {}", e);
+            return;
+        }
+    };
+    let Some(signer) = vault.vault_signer.as_ref() else {
+        say!("❌ No vault keys are loaded; add them with 'multisig cosign'");
+        return;
+    };
+
+    say!("\n   New thresholds: low {}, medium {}, high {}", updated.low, updated.medium, updated.high);
+    for (key, weight) in &updated.signers {
+        say!("   {} weight {}", key, weight);
+    }
+    if get_user_input("Type 'update' to change the vault's signers: ") != "update" {
+        say!("❌ Cancelled");
+        return;
+    }
+    match signer.set_options(vec![operation]).await {
+        Ok(receipt) => say!("✅ Vault signers updated in ledger {} ({})", receipt.ledger, vault.network.tx_link(&receipt.hash)),
+        Err(e) => say!("❌ Could not update the vault's signers: {}", e),
+    }
+}
+
+async fn run_history(vault: &StellarVault, user: &str) -> CommandResult {
+    let payments = vault.horizon.payments(user, 10).await
+        .map_err(|e| format!("Failed to load payments: {}", e))?;
+    if payments.is_empty() {
+        say!("\nℹ️  No payments found for {}", user);
+    } else {
+        say!("\n📜 RECENT PAYMENTS");
+    }
+    let payments: Vec<serde_json::Value> = payments.into_iter().map(|payment| {
+        let incoming = payment.to.as_deref() == Some(user);
+        let direction = if incoming { "⬅️  in " } else { "➡️  out" };
+        let asset = payment.asset_code.as_deref().unwrap_or("XLM");
+        let status = if payment.transaction_successful { "" } else { " (failed)" };
+        say!("   {} {} {} {} — {}{}",
+            payment.created_at,
+            direction,
+            payment.amount.as_deref().unwrap_or("-"),
+            asset,
+            payment.kind,
+            status);
+        say!("      {}", vault.network.tx_link(&payment.transaction_hash));
+        json!({
+            "created_at": payment.created_at,
+            "direction": if incoming { "in" } else { "out" },
+            "type": payment.kind,
+            "amount": payment.amount,
+            "asset": asset,
+            "issuer": payment.asset_issuer,
+            "successful": payment.transaction_successful,
+            "transaction_hash": payment.transaction_hash,
+        })
+    }).collect();
+
+    let fees = match vault.horizon.fee_stats().await {
+        Ok(stats) => {
+            say!("\n⛽ NETWORK FEES (ledger {})", stats.last_ledger);
+            say!("   Base Fee: {} stroops", stats.last_ledger_base_fee);
+            say!("   Charged p50/p90/p99: {}/{}/{} stroops",
+                stats.fee_charged.p50, stats.fee_charged.p90, stats.fee_charged.p99);
+            say!("   Ledger Capacity Usage: {}", stats.ledger_capacity_usage);
+            json!({
+                "ledger": stats.last_ledger,
+                "base_fee": stats.last_ledger_base_fee,
+                "charged_p50": stats.fee_charged.p50,
+                "charged_p90": stats.fee_charged.p90,
+                "charged_p99": stats.fee_charged.p99,
+                "ledger_capacity_usage": stats.ledger_capacity_usage,
+            })
+        }
+        Err(e) => {
+            say!("⚠️  Failed to load fee stats: {}", e);
+            serde_json::Value::Null
+        }
+    };
+    Ok(json!({ "account": user, "payments": payments, "network_fees": fees }))
+}
+
+fn run_rebalance(vault: &mut StellarVault) {
+    say!("\n⚖️  Choose the vault to rebalance:");
+    let risk_level = prompt_risk_level();
+
+    let threshold_input = get_user_input("Max drift before rebalancing (bps, default 100): ");
+    let max_drift_bps: u64 = threshold_input.parse().unwrap_or(rebalance::DEFAULT_MAX_DRIFT_BPS);
+
+    match vault.rebalance(risk_level, max_drift_bps) {
+        Ok(moves) if moves.is_empty() => say!("ℹ️  No rebalance needed"),
+        Ok(moves) => say!("✅ Rebalance complete: {} move(s)", moves.len()),
+        Err(e) => say!("❌ Rebalance failed: {}", e),
+    }
+}
+
+async fn run_login(vault: &mut StellarVault, keystore: &mut Keystore) -> Option<String> {
+    let public_key = get_user_input("\n👤 Enter your Stellar public key or name*domain address: ");
+    let public_key = match vault.resolve_address(&public_key).await {
+        // A memo or muxed ID means a shared custodial account, which nobody logs in to
+        Ok((account, None)) if !account.starts_with('M') => account,
+        Ok((account, _)) => {
+            say!("❌ {} is a custodial account; log in with an account you hold the key for", account);
+            return None;
+        }
+        Err(e) => {
+            say!("❌ {}", e);
+            return None;
+        }
+    };
+    let secret_key = get_password_input("🔑 Enter your Stellar secret key: ");
+
+    match vault.register_user(&secret_key, &public_key) {
+        Ok(()) => {
+            say!("✅ Switched to account {}", public_key);
+            save_to_keystore(keystore, "imported", &secret_key);
+            if let Ok(client) = vault.users.get(&public_key) {
+                match client.get_balance().await {
+                    Ok(balance) => say!("💰 Live Balance: {:.2} XLM", balance),
+                    Err(e) => say!("⚠️  Could not fetch balance: {}", e),
+                }
+            }
+            Some(public_key)
+        }
+        Err(e) => {
+            say!("❌ Could not register account: {}", e);
+            None
+        }
+    }
+}
+
+// Connects a Ledger running the Stellar app, in builds with the ledger feature
+fn connect_ledger() -> Result<Box<dyn Signer>, VaultError> {
+    #[cfg(feature = "ledger")]
+    {
+        let account: u32 = get_user_input("#️⃣  Ledger account index (default 0): ").parse().unwrap_or(0);
+        let ledger = signer::ledger::LedgerSigner::connect(account)?;
+        say!("   🔌 Ledger account #{}: {}", ledger.account(), ledger.public_key());
+        Ok(Box::new(ledger))
+    }
+
+    #[cfg(not(feature = "ledger"))]
+    {
+        Err("This build has no Ledger support; rebuild with --features ledger".into())
+    }
+}
+
+// Uses a key on a Ledger; nothing is saved, since the secret never leaves the device
+async fn run_ledger_login(vault: &mut StellarVault) -> Option<String> {
+    let public_key = match connect_ledger().and_then(|ledger| vault.register_signer(ledger)) {
+        Ok(public_key) => public_key,
+        Err(e) => {
+            say!("❌ {}", e);
+            return None;
+        }
+    };
+    say!("✅ Switched to Ledger account {}; approve each transaction on the device", public_key);
+    if let Ok(client) = vault.users.get(&public_key) {
+        match client.get_balance().await {
+            Ok(balance) => say!("💰 Live Balance: {:.2} XLM", balance),
+            Err(_) => say!("ℹ️  Account is not funded yet; send it at least 1 XLM to activate it"),
+        }
+    }
+    Some(public_key)
+}
+
+async fn run_wallet(vault: &mut StellarVault, keystore: &mut Keystore, command: &str) -> Option<String> {
+    let command = if command.is_empty() {
+        get_user_input("\n🔐 Wallet (new/import/ledger): ").to_lowercase()
+    } else {
+        command.to_string()
+    };
+    if command == "ledger" {
+        return run_ledger_login(vault).await;
+    }
+
+    let (keypair, label) = match command.as_str() {
+        "new" => {
+            let phrase = wallet::new_mnemonic();
+            match wallet::from_mnemonic(&phrase, "", 0) {
+                Ok(keypair) => {
+                    say!("\n🆕 New wallet created");
+                    say!("   📝 Recovery phrase (write it down, it is shown only once):");
+                    say!("      {}", phrase);
+                    (keypair, "generated".to_string())
+                }
+                Err(e) => {
+                    say!("❌ Could not create wallet: {}", e);
+                    return None;
+                }
+            }
+        }
+        "import" => {
+            let phrase = get_password_input("📝 Enter your recovery phrase: ");
+            let passphrase = get_password_input("🔑 Passphrase (leave blank for none): ");
+            let account: u32 = get_user_input("#️⃣  Account index (default 0): ").parse().unwrap_or(0);
+            match wallet::from_mnemonic(&phrase, &passphrase, account) {
+                Ok(keypair) => (keypair, format!("mnemonic #{}", account)),
+                Err(e) => {
+                    say!("❌ Could not import wallet: {}", e);
+                    return None;
+                }
+            }
+        }
+        _ => {
+            say!("❌ Unknown wallet command: {}", command);
+            return None;
+        }
+    };
+
+    let public_key = keypair.public_key();
+    say!("   👤 Public Key: {}", public_key);
+
+    match vault.register_user(&keypair.secret_key(), &public_key) {
+        Ok(()) => {
+            say!("✅ Switched to account {}", public_key);
+            save_to_keystore(keystore, &label, &keypair.secret_key());
+            if let Ok(client) = vault.users.get(&public_key) {
+                match client.get_balance().await {
+                    Ok(balance) => say!("💰 Live Balance: {:.2} XLM", balance),
+                    Err(_) => say!("ℹ️  Account is not funded yet; send it at least 1 XLM to activate it"),
+                }
+            }
+            Some(public_key)
+        }
+        Err(e) => {
+            say!("❌ Could not register account: {}", e);
+            None
+        }
+    }
+}
+
+// Payments in an asset the vault account doesn't trust would all fail
+async fn check_vault_trustlines(vault: &StellarVault) {
+    let assets: Vec<&AssetId> = vault.vaults.values().filter_map(|v| v.asset.as_ref()).collect();
+    if assets.is_empty() {
+        return;
+    }
+    let balances = match vault.horizon.account(&vault.vault_address).await {
+        Ok(account) => account.balances,
+        Err(e) => {
+            say!("⚠️  Could not check the vault's trustlines: {}", e);
+            return;
+        }
+    };
+    for asset in assets {
+        if asset.trustline(&balances).is_none() {
+            say!("⚠️  The vault account doesn't trust {}; deposits will fail until it does", asset);
+        }
+    }
+}
+
+fn save_to_keystore(keystore: &mut Keystore, label: &str, secret_key: &str) {
+    if !keystore.is_unlocked() {
+        say!("ℹ️  Keystore is locked; run 'wallet unlock' and log in again to save this key");
+        return;
+    }
+    match keystore.add(label, secret_key) {
+        Ok(()) => say!("🔒 Key saved to encrypted keystore {}", keystore.path().display()),
+        Err(e) => say!("⚠️  Could not save key to keystore: {}", e),
+    }
+}
+
+// Unlocks the keystore and registers every key in it, or sets a password and
+// onboards a first account when there is no keystore yet. Returns the account
+// to make active, preferring `preferred` when the keystore holds it.
+async fn run_wallet_unlock(vault: &mut StellarVault, keystore: &mut Keystore, preferred: Option<&str>) -> Option<String> {
+    if keystore.is_empty() {
+        say!("\n🔐 No keystore found at {}", keystore.path().display());
+        let password = get_password_input("🔑 Choose a keystore password: ");
+        if password.len() < 8 {
+            say!("❌ Password must be at least 8 characters");
+            return None;
+        }
+        if get_password_input("🔑 Repeat the password: ") != password {
+            say!("❌ Passwords do not match");
+            return None;
+        }
+        if let Err(e) = keystore.unlock(&password) {
+            say!("❌ Could not create keystore: {}", e);
+            return None;
+        }
+
+        return match get_user_input("👉 Add an account (new/import/login): ").to_lowercase().as_str() {
+            "login" | "l" => run_login(vault, keystore).await,
+            command => run_wallet(vault, keystore, command).await,
+        };
+    }
+
+    say!("\n🔐 Keystore {} holds {} key(s)", keystore.path().display(), keystore.entries().len());
+    let mut unlocked = None;
+    for _ in 0..3 {
+        match keystore.unlock(&get_password_input("🔑 Keystore password: ")) {
+            Ok(keys) => {
+                unlocked = Some(keys);
+                break;
+            }
+            Err(e) => say!("❌ {}", e),
+        }
+    }
+    let keys = unlocked?;
+
+    let mut accounts = Vec::new();
+    for key in keys {
+        if vault.fee_payer_address.as_deref() == Some(key.public_key.as_str()) {
+            if let Err(e) = vault.set_fee_payer(&key.secret_key) {
+                say!("⚠️  Could not use {} as the fee payer: {}", key.public_key, e);
+            }
+        }
+        // The vault account's own key and its operators' keys sign withdrawals
+        // rather than acting as users
+        let result = if key.public_key == vault.vault_address || vault.vault_signer_addresses.contains(&key.public_key) {
+            vault.add_vault_signer(&key.secret_key).map(|_| ())
+        } else {
+            vault.register_user(&key.secret_key, &key.public_key)
+                .map(|()| accounts.push((key.label.clone(), key.public_key.clone())))
+        };
+        if let Err(e) = result {
+            say!("⚠️  Skipping {} ({}): {}", key.public_key, key.label, e);
+        }
+    }
+    say!("✅ Keystore unlocked");
+
+    if let Some(preferred) = preferred {
+        if accounts.iter().any(|(_, public_key)| public_key == preferred) {
+            return Some(preferred.to_string());
+        }
+        say!("⚠️  Configured account {} is not in the keystore", preferred);
+    }
+
+    match accounts.len() {
+        0 => {
+            say!("ℹ️  The keystore has no user accounts; use 'wallet new', 'wallet import' or 'login'");
+            None
+        }
+        1 => accounts.pop().map(|(_, public_key)| public_key),
+        _ => {
+            for (i, (label, public_key)) in accounts.iter().enumerate() {
+                say!("   {}. {} ({})", i + 1, public_key, label);
+            }
+            let choice: usize = get_user_input("👉 Choose an account (default 1): ").parse().unwrap_or(1);
+            let index = choice.clamp(1, accounts.len()) - 1;
+            Some(accounts.swap_remove(index).1)
+        }
+    }
+}
+
+// ============================================================================
+// COMMAND LINE
+// ============================================================================
+
+fn parse_risk_arg(name: &str) -> Result<RiskLevel, String> {
+    parse_risk(name, "--risk").map_err(|_| "expected low, medium or high".to_string())
+}
+
+fn parse_list_arg(name: &str) -> Result<AddressList, String> {
+    AddressList::parse(name).map_err(|_| "expected allowlist or denylist".to_string())
+}
+
+fn parse_amount_arg(amount: &str) -> Result<u64, String> {
+    match Stroops::from_xlm_str(amount) {
+        Ok(Stroops(0)) => Err("the amount must be more than 0".to_string()),
+        Ok(stroops) => Ok(stroops.0),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// A deposit cap from the command line; Cap(None) lifts it
+#[derive(Debug, Clone, Copy)]
+struct Cap(Option<u64>);
+
+fn parse_cap_arg(cap: &str) -> Result<Cap, String> {
+    match cap.to_lowercase().as_str() {
+        "none" => Ok(Cap(None)),
+        _ => parse_amount_arg(cap).map(|stroops| Cap(Some(stroops))),
+    }
+}
+
+// A strategy target from the command line: ID=PERCENT[@APY_BPS]
+#[derive(Debug, Clone)]
+struct StrategyArg {
+    id: String,
+    allocation: u8,
+    apy_bps: Option<u16>,
+}
+
+fn parse_strategy_arg(target: &str) -> Result<StrategyArg, String> {
+    let invalid = || format!("expected ID=PERCENT or ID=PERCENT@APY_BPS, such as YieldBloxLending=60: {}", target);
+    let (id, rest) = target.split_once('=').ok_or_else(invalid)?;
+    let (allocation, apy_bps) = match rest.split_once('@') {
+        Some((allocation, apy)) => (allocation, Some(apy.trim().parse().map_err(|_| invalid())?)),
+        None => (rest, None),
+    };
+    let allocation = allocation.trim().trim_end_matches('%').parse().map_err(|_| invalid())?;
+    Ok(StrategyArg { id: id.trim().to_string(), allocation, apy_bps })
+}
+
+// Account thresholds from the command line: LOW,MEDIUM,HIGH
+#[derive(Debug, Clone, Copy)]
+struct Thresholds(u8, u8, u8);
+
+fn parse_thresholds_arg(thresholds: &str) -> Result<Thresholds, String> {
+    let invalid = || format!("expected LOW,MEDIUM,HIGH from 0 to 255, such as 1,2,2: {}", thresholds);
+    let levels: Vec<u8> = thresholds.split(',')
+        .map(|level| level.trim().parse().map_err(|_| invalid()))
+        .collect::<Result<_, _>>()?;
+    match levels[..] {
+        [low, medium, high] => Ok(Thresholds(low, medium, high)),
+        _ => Err(invalid()),
+    }
+}
+
+fn parse_cadence_arg(cadence: &str) -> Result<Cadence, String> {
+    Cadence::parse(cadence).map_err(|e| e.to_string())
+}
+
+// Every action is in the interactive menu; the everyday ones are also
+// subcommands that do one thing and exit, for scripts
+#[derive(Parser)]
+#[command(name = "stellarvault", version, about = "StellarVault (SYIA) - Smart Yield Insurance Aggregator")]
+struct Cli {
+    /// Network to use instead of the configured one: testnet, mainnet or futurenet
+    #[arg(long, global = true)]
+    network: Option<String>,
+    /// Run against an in-memory mock ledger with funded demo accounts; same as --network offline
+    #[arg(long, global = true, conflicts_with = "network")]
+    offline: bool,
+    /// json prints each command's result as JSON on stdout and moves messages to stderr
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+// A command's result for --output json; errors are said and emitted as
+// {"error": ...}
+type CommandResult = Result<serde_json::Value, String>;
+
+#[derive(Subcommand)]
+enum Command {
+    /// Guided menu of every action (the default)
+    Interactive,
+    /// Full-screen dashboard of the vaults and your positions, with deposit and withdraw
+    Tui,
+    /// Stream the vault and your wallet, refreshing share prices and positions until Ctrl-C
+    Watch,
+    /// Serve the vaults over an HTTP API; deposits and withdrawals are signed by your account
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        bind: String,
+        /// Origin a browser frontend is served from, such as http://localhost:3000; repeatable
+        #[arg(long = "allow-origin")]
+        allow_origins: Vec<String>,
+        /// Domain clients authenticate to; SEP-10 challenges name it
+        #[arg(long, default_value = "localhost")]
+        home_domain: String,
+    },
+    /// Deposit into a vault
+    Deposit {
+        /// low, medium or high
+        #[arg(long, value_parser = parse_risk_arg)]
+        risk: RiskLevel,
+        /// Amount to pay, such as 100 or 12.5
+        #[arg(long, value_parser = parse_amount_arg)]
+        amount: u64,
+        /// Pay with XLM or CODE:ISSUER, converted to the vault's asset
+        #[arg(long)]
+        pay_with: Option<String>,
+        /// Convert at the quoted rate without asking
+        #[arg(long, short)]
+        yes: bool,
+        /// Check, quote and build the transaction, but don't sign or submit it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Redeem shares from a vault
+    Withdraw {
+        /// low, medium or high
+        #[arg(long, value_parser = parse_risk_arg)]
+        risk: RiskLevel,
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        shares: u64,
+        /// Check, quote and build the transaction, but don't sign or submit it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Wallet balances and vault positions
+    Balance,
+    /// Your position in a vault
+    Position {
+        /// low, medium or high
+        #[arg(long, value_parser = parse_risk_arg)]
+        risk: RiskLevel,
+    },
+    /// A vault's TVL, fees and strategies
+    VaultInfo {
+        /// low, medium or high
+        #[arg(long, value_parser = parse_risk_arg)]
+        risk: RiskLevel,
+    },
+    /// Recent payments and network fees
+    History,
+    /// Credit a vault's yield, then pay queued withdrawals
+    Harvest {
+        /// low, medium or high
+        #[arg(long, value_parser = parse_risk_arg)]
+        risk: RiskLevel,
+    },
+    /// Compare the books (TVL, fees, insurance) against on-chain balances and strategy positions
+    Reconcile {
+        /// Divergence ignored, in basis points of the book figure
+        #[arg(long, default_value_t = DEFAULT_TOLERANCE_BPS)]
+        tolerance_bps: u16,
+        /// Pause every vault off by more than the tolerance
+        #[arg(long)]
+        pause: bool,
+    },
+    /// Publishable proof of reserves: on-chain balances, a Merkle sum tree of positions and a proof for each account
+    ProofOfReserves {
+        /// Directory for summary.json and proofs/<account>.json
+        #[arg(long, default_value = "proof-of-reserves")]
+        out: PathBuf,
+    },
+    /// Check a proof of reserves summary's signature and that your positions are counted in it
+    VerifyReserves {
+        /// The published summary.json
+        #[arg(long)]
+        summary: PathBuf,
+        /// Your proofs/<account>.json
+        #[arg(long)]
+        proof: PathBuf,
+    },
+    /// Export the event log as hash-chained JSON lines for auditors
+    AuditExport {
+        /// Where to write the trail
+        #[arg(long, default_value = "audit-trail.jsonl")]
+        out: PathBuf,
+    },
+    /// Check that an exported audit trail has not been altered or cut
+    AuditVerify {
+        /// The exported trail
+        file: PathBuf,
+        /// The last hash published with the export, to catch records cut from the end
+        #[arg(long)]
+        head: Option<String>,
+    },
+    /// Sign an exported transaction without touching the network
+    Sign {
+        /// File path or base64 XDR
+        transaction: Option<String>,
+    },
+    /// Fund an account with test XLM, or a new wallet when none is given
+    Faucet {
+        account: Option<String>,
+    },
+    /// Deposits and withdrawals repeated on a schedule; they run while watch or serve is running
+    Recurring {
+        #[command(subcommand)]
+        action: RecurringCommand,
+    },
+    /// Change vault settings and step in on operations; needs the vault key or an operator's
+    Admin {
+        #[command(subcommand)]
+        action: AdminCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum RecurringCommand {
+    /// Your recurring plans and when each runs next
+    List,
+    /// Deposit the same amount on a schedule
+    Deposit {
+        /// low, medium or high
+        #[arg(long, value_parser = parse_risk_arg)]
+        risk: RiskLevel,
+        /// Amount of the vault's asset each time, such as 50
+        #[arg(long, value_parser = parse_amount_arg)]
+        amount: u64,
+        /// daily or a weekday, optionally with a UTC time: "monday 09:00"
+        #[arg(long, value_parser = parse_cadence_arg)]
+        every: Cadence,
+    },
+    /// Pay out yield or a fixed amount on a schedule, to any account
+    Withdraw {
+        /// low, medium or high
+        #[arg(long, value_parser = parse_risk_arg)]
+        risk: RiskLevel,
+        /// Amount of the vault's asset each time, such as 25
+        #[arg(long, value_parser = parse_amount_arg, required_unless_present = "accumulated_yield")]
+        amount: Option<u64>,
+        /// Pay whatever yield has accumulated instead of a fixed amount
+        #[arg(long = "yield", conflicts_with = "amount")]
+        accumulated_yield: bool,
+        /// Account to pay: G..., M... or name*domain; your own by default
+        #[arg(long)]
+        to: Option<String>,
+        /// daily or a weekday, optionally with a UTC time: "monday 09:00"
+        #[arg(long, value_parser = parse_cadence_arg)]
+        every: Cadence,
+    },
+    /// Stop a recurring plan
+    Cancel {
+        id: u64,
+    },
+}
+
+#[derive(Subcommand)]
+enum AdminCommand {
+    /// Change a vault's management and performance fees; increases wait out the admin delay
+    SetFees {
+        /// low, medium or high
+        #[arg(long, value_parser = parse_risk_arg)]
+        risk: RiskLevel,
+        /// Yearly management fee, in basis points of deposits
+        #[arg(long)]
+        management_bps: Option<u16>,
+        /// Performance fee, in basis points of yield
+        #[arg(long)]
+        performance_bps: Option<u16>,
+    },
+    /// Change a vault's deposit caps; "none" lifts one
+    SetCaps {
+        /// low, medium or high
+        #[arg(long, value_parser = parse_risk_arg)]
+        risk: RiskLevel,
+        /// Most the vault takes in, in its asset
+        #[arg(long, value_parser = parse_cap_arg)]
+        max_tvl: Option<Cap>,
+        /// Most one account may hold, in the vault's asset
+        #[arg(long, value_parser = parse_cap_arg)]
+        per_user: Option<Cap>,
+        /// Most the vault takes in, in USD at the oracle price
+        #[arg(long, value_parser = parse_cap_arg)]
+        max_tvl_usd: Option<Cap>,
+        /// Most one account may hold, in USD at the oracle price
+        #[arg(long, value_parser = parse_cap_arg)]
+        per_user_usd: Option<Cap>,
+    },
+    /// Stop deposits and withdrawals in a vault
+    Pause {
+        /// low, medium or high
+        #[arg(long, value_parser = parse_risk_arg)]
+        risk: RiskLevel,
+        /// Why, shown to users while the vault is paused
+        #[arg(long)]
+        reason: String,
+    },
+    /// Let a paused vault take deposits and withdrawals again
+    #[command(alias = "unpause")]
+    Resume {
+        /// low, medium or high
+        #[arg(long, value_parser = parse_risk_arg)]
+        risk: RiskLevel,
+    },
+    /// Move a vault's allocation back to its strategies' targets
+    Rebalance {
+        /// low, medium or high
+        #[arg(long, value_parser = parse_risk_arg)]
+        risk: RiskLevel,
+        /// Drift from a target tolerated, in basis points
+        #[arg(long, default_value_t = rebalance::DEFAULT_MAX_DRIFT_BPS)]
+        max_drift_bps: u64,
+    },
+    /// Approve an insurance claim and pay it from the pool
+    ApproveClaim {
+        id: u64,
+        /// Recorded with the decision
+        #[arg(long)]
+        note: String,
+    },
+    /// Deny an insurance claim
+    DenyClaim {
+        id: u64,
+        /// Recorded with the decision
+        #[arg(long)]
+        note: String,
+    },
+    /// Send collected fees to a new treasury account (G... or M...), after the admin delay
+    SetTreasury {
+        address: String,
+    },
+    /// Replace a vault's strategy targets, after the admin delay
+    SetStrategies {
+        /// low, medium or high
+        #[arg(long, value_parser = parse_risk_arg)]
+        risk: RiskLevel,
+        /// ID=PERCENT, with @APY_BPS for a strategy the vault doesn't run yet; once per strategy
+        #[arg(long = "strategy", value_parser = parse_strategy_arg, required = true)]
+        strategies: Vec<StrategyArg>,
+    },
+    /// Replace one of the vault account's signing keys in one transaction, then update the local config and keystore
+    RotateSigner {
+        /// The key to retire; the vault's own address retires its master key
+        #[arg(long)]
+        old: String,
+        /// The replacement key (G...); without it a new key is generated into the keystore
+        #[arg(long)]
+        new: Option<String>,
+        /// The replacement's weight; the retired key's by default
+        #[arg(long)]
+        weight: Option<u8>,
+        /// New thresholds as LOW,MEDIUM,HIGH, set in the same transaction
+        #[arg(long, value_parser = parse_thresholds_arg)]
+        thresholds: Option<Thresholds>,
+        /// Show the operations and resulting signers without submitting
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// The allowlist and denylist, and the latest deposits and payouts they refused
+    Lists,
+    /// Put an account (G...) or a single muxed address (M...) on the allowlist or denylist
+    List {
+        /// allowlist or denylist
+        #[arg(value_parser = parse_list_arg)]
+        list: AddressList,
+        address: String,
+        /// Why, such as a case reference
+        #[arg(long, default_value = "")]
+        note: String,
+    },
+    /// Take an address off the allowlist or denylist
+    Unlist {
+        /// allowlist or denylist
+        #[arg(value_parser = parse_list_arg)]
+        list: AddressList,
+        address: String,
+    },
+    /// Changes waiting out the admin delay
+    Pending,
+    /// Stop a pending change before it takes effect
+    Cancel {
+        id: u64,
+    },
+}
+
+// ============================================================================
+// MAIN FUNCTION
+// ============================================================================
+
+// Fixed keys, so offline demos and scripts see the same addresses every run
+const OFFLINE_VAULT_SEED: [u8; 32] = [1; 32];
+const OFFLINE_USER_SEED: [u8; 32] = [2; 32];
+
+// Seeds the mock ledger with a funded vault and demo account, and drops the
+// settings that would reach the network
+fn prepare_offline(config: &mut Config) {
+    let ledger = MockLedger::shared();
+    let vault = Keypair::from_seed(&OFFLINE_VAULT_SEED);
+    ledger.fund(&vault.public_key(), Stroops(mock_ledger::FAUCET_STROOPS));
+    config.vault_address = vault.public_key();
+    config.vault_secret_keys = vec![vault.secret_key()];
+    config.vault_signers.clear();
+
+    // STELLARVAULT_USER_SECRET still picks the account; it just gets funded here
+    let user = config.user_secret_key.as_deref()
+        .and_then(|secret| Keypair::from_secret(secret).ok())
+        .unwrap_or_else(|| Keypair::from_seed(&OFFLINE_USER_SEED));
+    ledger.fund(&user.public_key(), Stroops(mock_ledger::FAUCET_STROOPS));
+    config.user_secret_key = Some(user.secret_key());
+
+    config.signer_url = None;
+    config.signer_keys.clear();
+    config.fee_payer = None;
+    config.fee_payer_secret_key = None;
+    config.share_issuer = None;
+    config.treasury_address = None;
+    config.vault_assets.clear();
+    config.market_making = None;
+    config.liquidity_pool = None;
+    config.soroban_rpc_url = None;
+    config.vault_contract = None;
+    config.blend = None;
+    config.soroswap = None;
+    config.apy_oracle = None;
+    config.price_oracle = None;
+    say!("🧪 Offline: using an in-memory mock ledger; nothing is sent to Stellar and nothing is saved");
+}
+
+async fn connect(config: &Config) -> Option<StellarVault> {
+    let vault_address = config.vault_address.as_str();
+    
+    say!("🔐 Connecting to {} ({})...", config.network, config.network.horizon_url());
+    if config.network.is_mainnet() {
+        say!("⚠️  MAINNET: deposits, withdrawals and payouts move real XLM");
+    }
+    if let Some(source) = &config.vaults_source {
+        say!("⚙️  Loaded {} vault definitions from {}", config.vaults.len(), source.display());
+    }
+    let builder = StellarVaultBuilder::new(vault_address, &config.network)
+        .fee_strategy(config.fee_strategy)
+        .retry_policy(config.retry)
+        .horizon_fallbacks(config.horizon_fallback_urls.clone())
+        .horizon_breaker(config.horizon_breaker)
+        .admin_delay(config.admin_delay_secs);
+    let builder = config.vaults.iter().cloned().fold(builder, StellarVaultBuilder::vault);
+    match builder.build() {
+        Ok(mut v) => {
+            // Withdrawals need the vault account's keys; deposits work without them
+            v.vault_signer_addresses = config.vault_signers.clone();
+            for vault_secret in &config.vault_secret_keys {
+                if let Err(e) = v.add_vault_signer(vault_secret) {
+                    say!("⚠️  Ignoring a key in STELLARVAULT_VAULT_SECRET: {}", e);
+                }
+            }
+            if let Some(issuer) = &config.share_issuer {
+                if let Err(e) = v.set_share_issuer(issuer) {
+                    say!("⚠️  Ignoring share issuer: {}", e);
+                }
+            }
+            // Keys in the integrator's signing service never pass through here
+            if let Some(url) = &config.signer_url {
+                let timeout = Duration::from_secs(config.signer_timeout_secs);
+                for key in &config.signer_keys {
+                    let is_vault_key = key == vault_address || config.vault_signers.contains(key);
+                    let result = RemoteSigner::new(url, key, timeout).and_then(|signer| if is_vault_key {
+                        v.add_external_vault_signer(Box::new(signer))
+                    } else {
+                        v.register_signer(Box::new(signer))
+                    });
+                    if let Err(e) = result {
+                        say!("⚠️  Ignoring signer key {}: {}", key, e);
+                    }
+                }
+            }
+            v.fee_payer_address = config.fee_payer.clone();
+            v.max_slippage_bps = config.max_slippage_bps;
+            v.idempotency_window_secs = config.idempotency_window_secs;
+            v.allowlist_only = config.allowlist_only;
+            if let Some(pool) = &config.liquidity_pool {
+                if let Err(e) = v.enable_liquidity_pool(pool.risk, pool.counter.clone()) {
+                    say!("⚠️  Ignoring [liquidity_pool]: {}", e);
+                }
+            }
+            v.soroban = config.soroban_rpc_url.as_deref().map(|url| soroban::RpcClient::new(url).with_retry(config.retry));
+            v.vault_contract = config.vault_contract.clone();
+            if let Some(blend) = &config.blend {
+                if let Err(e) = v.enable_blend(blend.risk, &blend.pool) {
+                    say!("⚠️  Ignoring [blend]: {}", e);
+                }
+            }
+            if let Some(pair) = &config.soroswap {
+                if let Err(e) = v.enable_soroswap(pair.risk, pair.position.clone(), pair.allocation) {
+                    say!("⚠️  Ignoring [soroswap]: {}", e);
+                }
+            }
+            if let Some(market_making) = &config.market_making {
+                if let Err(e) = v.enable_market_making(market_making.risk, market_making.maker.clone(), market_making.allocation) {
+                    say!("⚠️  Ignoring [market_making]: {}", e);
+                }
+            }
+            if let Some(secret) = &config.fee_payer_secret_key {
+                if let Err(e) = v.set_fee_payer(secret) {
+                    say!("⚠️  Ignoring STELLARVAULT_FEE_PAYER_SECRET: {}", e);
+                }
+            }
+            // Vaults left out of vault_assets hold XLM
+            let defined: Vec<RiskLevel> = RiskLevel::ALL.into_iter().filter(|risk| v.vaults.contains_key(risk)).collect();
+            for risk in defined {
+                let asset = config.vault_assets.iter()
+                    .find(|(configured, _)| *configured == risk)
+                    .map(|(_, asset)| asset.clone());
+                if let Err(e) = v.set_vault_asset(risk, asset) {
+                    say!("⚠️  Keeping the current asset: {}", e);
+                }
+            }
+            // Everything startup waits on is fetched at once. The fee stats
+            // and the user's account only warm the cache for the first
+            // command, which reads them again.
+            let known_user = config.user_public_key.clone()
+                .or_else(|| config.user_secret_key.as_deref().and_then(|secret| Keypair::from_secret(secret).ok()).map(|keypair| keypair.public_key()));
+            let (treasury, ..) = tokio::join!(
+                async {
+                    match &config.treasury_address {
+                        Some(treasury) => Some(v.resolve_address(treasury).await),
+                        None => None,
+                    }
+                },
+                check_vault_trustlines(&v),
+                v.horizon.fee_stats(),
+                async {
+                    if let Some(user) = &known_user {
+                        let _ = v.horizon.account(user).await;
+                    }
+                },
+            );
+            // The configured treasury only fills in for a vault without one;
+            // moving it afterwards is 'admin set-treasury', behind the delay
+            if let Some(resolved) = treasury {
+                let applied = resolved.and_then(|(address, memo)| match v.treasury_address.clone() {
+                    Some(current) if current != address => {
+                        say!("⚠️  Fees still go to {}, not the configured {}; 'admin set-treasury' moves them", current, address);
+                        Ok(())
+                    }
+                    _ => {
+                        v.treasury_memo = memo;
+                        v.set_treasury_address(&address)
+                    }
+                });
+                if let Err(e) = applied {
+                    say!("⚠️  Ignoring treasury: {}", e);
+                }
+            }
+            if let Err(e) = v.persist() {
+                say!("⚠️  Failed to save vault assets: {}", e);
+            }
+
+            say!("✅ Connected!");
+            say!("🏦 SYIA Vault Address: {}", vault_address);
+            Some(v)
+        }
+        Err(e) => {
+            say!("❌ Failed to connect: {}", e);
+            None
+        }
+    }
+}
+
+async fn unlock_user(vault: &mut StellarVault, keystore: &mut Keystore, config: &Config) -> Option<String> {
+    // A secret in the environment skips the keystore, for unattended runs
+    match &config.user_secret_key {
+        Some(secret) => match Keypair::from_secret(secret) {
+            Ok(keypair) => {
+                let public_key = keypair.public_key();
+                match vault.register_user(secret, &public_key) {
+                    Ok(()) => Some(public_key),
+                    Err(e) => {
+                        say!("❌ Failed to register STELLARVAULT_USER_SECRET: {}", e);
+                        None
+                    }
+                }
+            }
+            Err(e) => {
+                say!("❌ Invalid STELLARVAULT_USER_SECRET: {}", e);
+                None
+            }
+        },
+        // Accounts signed for by the external signer need no keystore
+        None => {
+            let remote_user = match &config.user_public_key {
+                Some(key) => Some(key).filter(|key| vault.users.contains(key)),
+                None => config.signer_keys.iter().find(|key| vault.users.contains(key)),
+            };
+            match remote_user {
+                Some(key) => Some(key.clone()),
+                None => run_wallet_unlock(vault, keystore, config.user_public_key.as_deref()).await,
+            }
+        }
+    }
+}
+
+async fn run_interactive(mut vault: StellarVault, mut keystore: Keystore, mut active_user: String, config: &Config) {
+    let vault_address = config.vault_address.as_str();
+
+    say!("👤 Your Address: {}", active_user);
+    if let Ok(client) = vault.users.get(&active_user) {
+        match client.get_balance().await {
+            Ok(balance) => {
+                say!("💰 Your Live Balance: {:.2} XLM", balance);
+            }
+            Err(e) => {
+                say!("⚠️  Could not fetch balance: {}", e);
+            }
+        }
+    }
+
+    say!("\n🔗 Explorer Links:");
+    say!("   Your Account: {}", vault.network.account_link(&active_user));
+    say!("   SYIA Vault: {}\n", vault.network.account_link(vault_address));
+
+    // Deposits paid from other wallets are credited as the stream delivers them
+    let mut payment_stream = config.ingest.then(|| {
+        say!("📡 Watching {} for incoming deposits", vault_address);
+        ingest::spawn(vault.horizon.clone(), vault.vault_address.clone(), vault.ingest_cursor.clone())
+    });
+
+    // Shares held in the vault contract are indexed from its events
+    if vault.vault_contract.is_some() {
+        if let Err(e) = vault.load_share_tokens().await {
+            say!("⚠️  Could not look up the vault's share tokens ({}); share transfers won't be indexed", e);
+        }
+    }
+    let mut contract_stream = vault.contract_stream();
+    // Edits to the vault definitions apply between actions
+    let mut vaults_watcher = config.vaults_source.as_deref().map(ConfigWatcher::new);
+    let (mut apy_stream, mut live_rates) = match &config.apy_oracle {
+        Some(oracle) => start_apy_oracle(&vault, config, oracle),
+        None => (None, LiveRates::new([], 0, 0)),
+    };
+    let mut price_stream = match &config.price_oracle {
+        Some(oracle) => start_price_oracle(&mut vault, oracle),
+        None => {
+            if vault.vaults.values().any(|v| v.max_tvl_usd.is_some() || v.per_user_cap_usd.is_some()) {
+                say!("⚠️  Some vaults have USD caps but no [price_oracle] is configured; their deposits will be refused");
+            }
+            None
+        }
+    };
+
+    let fee_of = |risk| vault.get_vault_info(risk)
+        .map(|v| v.insurance_fee as f64 / 100.0)
+        .unwrap_or(0.0);
+    let apy_of = |risk| vault.get_vault_info(risk)
+        .map(|v| v.blended_apy() as f64 / 100.0)
+        .unwrap_or(0.0);
+
+    say!("{}", "=".repeat(70));
+    say!("\n📊 StellarVault (SYIA) Risk Levels:\n");
+    
+    say!("1. 🟢 LOW RISK");
+    say!("   - APY: {:.2}%", apy_of(RiskLevel::Low));
+    say!("   - Insurance Fee: {:.2}%", fee_of(RiskLevel::Low));
+    say!("   - Strategy: YieldBlox Lending");
+    say!("   - Best for: Conservative investors\n");
+    
+    say!("2. 🟡 MEDIUM RISK");
+    say!("   - APY: {:.2}%", apy_of(RiskLevel::Medium));
+    say!("   - Insurance Fee: {:.2}%", fee_of(RiskLevel::Medium));
+    say!("   - Strategy: 60% Aqua LP + 40% YieldBlox");
+    say!("   - Best for: Balanced investors\n");
+    
+    say!("3. 🔴 HIGH RISK");
+    say!("   - APY: {:.2}%", apy_of(RiskLevel::High));
+    say!("   - Insurance Fee: {:.2}%", fee_of(RiskLevel::High));
+    say!("   - Strategy: Money Market");
+    say!("   - Lock-up: 30 days (3.00% early withdrawal penalty)");
+    say!("   - Best for: Aggressive investors\n");
+
+    say!("{}", "=".repeat(70));
+
+    loop {
+        if let Some(stream) = payment_stream.as_mut() {
+            run_ingest(&mut vault, stream).await;
+        }
+        if let Some(stream) = contract_stream.as_mut() {
+            run_contract_index(&mut vault, stream).await;
+        }
+        if let Some(watcher) = vaults_watcher.as_mut() {
+            run_reload(&mut vault, watcher);
+        }
+        if let Some(stream) = apy_stream.as_mut() {
+            run_apy_updates(&mut vault, stream, &mut live_rates);
+        }
+        if let Some(stream) = price_stream.as_mut() {
+            run_price_updates(&mut vault, stream);
+        }
+        say!("\n👤 Active account: {}", active_user);
+        let input = get_user_input("👉 Choose an action (deposit/deposit address/withdraw/balance/position/portfolio/info/harvest/compound/rebalance/fees/sdex/amm/blend/soroswap/contract events/vault contract/contract deploy/contract upgrade/claims/yield/claim-yield/distribute yield/insurance/coverage/shares/shares trust/trust/pending-withdrawals/pause/audit/reconcile/deposits/history/bump/faucet/onboard/tx build/tx submit/multisig/wallet/login/quit): ");
+        let action = input.to_lowercase();
+
+        match action.as_str() {
+            "deposit" | "d" => run_deposit(&mut vault, &active_user).await,
+            "deposit address" => run_deposit_address(&mut vault, &active_user, payment_stream.is_some()),
+            "withdraw" | "w" => run_withdraw(&mut vault, &active_user).await,
+            "balance" | "b" => {
+                run_balance(&vault, &active_user).await;
+            }
+            "position" | "p" => run_position(&vault, &active_user),
+            "portfolio" | "pf" => run_portfolio(&vault, &active_user),
+            "harvest" | "hv" => {
+                run_harvest(&mut vault).await;
+                run_process_queue(&mut vault).await;
+            }
+            "compound" | "c" => run_compound_toggle(&mut vault, &active_user),
+            "rebalance" | "rb" => {
+                run_rebalance(&mut vault);
+                run_process_queue(&mut vault).await;
+            }
+            "pending-withdrawals" | "pending" => run_pending_withdrawals(&vault),
+            "pause" => run_pause(&mut vault),
+            "audit" => run_audit(&mut vault),
+            "verify" | "reconcile" => run_reconcile(&mut vault).await,
+            "deposits" => run_pending_deposits(&mut vault).await,
+            "history" | "h" => {
+                if let Err(e) = run_history(&vault, &active_user).await {
+                    say!("❌ {}", e);
+                }
+            }
+            "tx build" => run_tx_build(&vault).await,
+            "faucet" => {
+                if let Err(e) = run_faucet(&vault.network, Some(&active_user)).await {
+                    say!("❌ {}", e);
+                }
+            }
+            "onboard" => run_onboard(&vault).await,
+            multisig if multisig == "multisig" || multisig.starts_with("multisig ") => {
+                run_multisig(&mut vault, multisig.trim_start_matches("multisig").trim()).await
+            }
+            "tx submit" => run_tx_submit(&vault).await,
+            bump if bump == "bump" || bump.starts_with("bump ") => {
+                run_bump(&vault, bump.trim_start_matches("bump").trim()).await;
+            }
+            "info" | "i" | "vault-info" => run_vault_info(&vault),
+            "fees" | "f" => run_collect_fees(&mut vault).await,
+            "sdex" => run_market_making(&mut vault).await,
+            "amm" => run_liquidity_pool(&mut vault).await,
+            "blend" => run_blend(&mut vault).await,
+            "soroswap" => run_soroswap(&mut vault).await,
+            "contract events" => run_contract_events(&vault).await,
+            "vault contract" => run_vault_contract(&vault, &active_user).await,
+            "contract deploy" => {
+                if run_contract_deploy(&mut vault, config).await {
+                    contract_stream = vault.contract_stream();
+                }
+            }
+            "contract upgrade" => run_contract_upgrade(&vault).await,
+            "claims" => run_claims(&mut vault, &active_user).await,
+            "distribute yield" => run_distribute_yield(&mut vault).await,
+            "yield" => run_yield_balances(&vault, &active_user).await,
+            "claim-yield" => run_claim_yield(&vault, &active_user).await,
+            "insurance" => run_insurance(&mut vault),
+            "coverage" => run_coverage(&mut vault),
+            "shares" => run_shares(&vault, &active_user).await,
+            "shares trust" => run_trust_shares(&vault, &active_user).await,
+            trust if trust == "trust" || trust.starts_with("trust ") => {
+                // Issuer keys are case sensitive
+                run_trust(&vault, &active_user, &input[trust.len().min(5)..]).await
+            }
+            "login" | "l" => {
+                if let Some(user) = run_login(&mut vault, &mut keystore).await {
+                    active_user = user;
+                }
+            }
+            "wallet unlock" => {
+                if let Some(user) = run_wallet_unlock(&mut vault, &mut keystore, None).await {
+                    active_user = user;
+                }
+            }
+            "wallet" | "wallet new" | "wallet import" | "wallet ledger" => {
+                if let Some(user) = run_wallet(&mut vault, &mut keystore, action.strip_prefix("wallet").unwrap_or("").trim()).await {
+                    active_user = user;
+                }
+            }
+            "quit" | "q" | "exit" => break,
+            _ => say!("❌ Unknown action: {}", action),
+        }
+    }
+
+    say!("\n{}", "=".repeat(70));
+    say!("\n✅ Session complete!");
+    say!("\n🔍 Check your transactions on the explorer:");
+    say!("   Your Account: {}", vault.network.account_link(&active_user));
+    say!("   SYIA Vault: {}", vault.network.account_link(vault_address));
+    say!("\n💡 Refresh the explorer in a few seconds to see the transaction appear!");
+}
+
+// Runs one command; the interactive menu is one too
+async fn run_command(command: Command, network: Option<&str>) -> CommandResult {
+    let mut config = Config::load(network).map_err(|e| format!("Configuration error: {}", e))?;
+    // The dashboard draws over the console, so it only logs to the file
+    let _log_guard = logging::init(&config.log, !matches!(command, Command::Tui)).map_err(|e| e.to_string())?;
+    if config.network.is_offline() {
+        prepare_offline(&mut config);
+    }
+    if let Some(source) = &config.source {
+        say!("⚙️  Loaded configuration from {}", source.display());
+    }
+    match &command {
+        Command::Sign { transaction } => return run_offline_sign(&config, transaction.as_deref()),
+        Command::VerifyReserves { summary, proof } => return run_verify_reserves(summary, proof),
+        Command::AuditVerify { file, head } => return run_audit_verify(file, head.as_deref()),
+        // Needs no vault or keystore, so a demo can start from nothing
+        Command::Faucet { account } => return run_faucet(&config.network, account.as_deref()).await,
+        _ => {}
+    }
+
+    let mut vault = connect(&config).await.ok_or("Failed to connect")?;
+    let notifier = Notifier::start(&config.notify, &config.network);
+    vault.notifications = notifier.as_ref().map(Notifier::sender);
+    // The vault is dropped by the time this returns, so deliveries can finish
+    let result = run_vault_command(command, vault, &config, network).await;
+    if let Some(notifier) = notifier {
+        notifier.finish().await;
+    }
+    result
+}
+
+async fn run_vault_command(command: Command, mut vault: StellarVault, config: &Config, network: Option<&str>) -> CommandResult {
+    if !vault.pending_deposits.unsettled().is_empty() {
+        say!("🔁 Settling deposits interrupted in a previous session...");
+        print_settlements(&vault.settle_pending_deposits().await);
+    }
+    if !vault.journal.open().is_empty() {
+        say!("🔁 Settling payouts interrupted in a previous session...");
+        for (entry, result) in vault.settle_payouts().await {
+            match result {
+                Ok(outcome) => say!("   ✅ Payout #{} to {}: {}", entry.id, entry.payee, outcome),
+                Err(e) => say!("   ❌ Payout #{} to {} still unsettled: {}", entry.id, entry.payee, e),
+            }
+        }
+    }
+
+    apply_due_changes(&mut vault);
+
+    // Vault-wide commands need no account
+    match command {
+        Command::VaultInfo { risk } => {
+            prime_prices(&mut vault, config).await;
+            return print_vault_info(&vault, risk);
+        }
+        Command::Harvest { risk } => {
+            let report = execute_harvest(&mut vault, risk).await;
+            run_process_queue(&mut vault).await;
+            return report;
+        }
+        Command::Reconcile { tolerance_bps, pause } => return execute_reconcile(&mut vault, tolerance_bps, pause).await,
+        Command::ProofOfReserves { out } => return execute_proof_of_reserves(&mut vault, &out).await,
+        Command::AuditExport { out } => return execute_audit_export(&vault, &out),
+        Command::Admin { action } => return run_admin_command(&mut vault, config, action).await,
+        _ => {}
+    }
+
+    let mut keystore = Keystore::open(&config.keystore_path).map_err(|e| format!("Failed to open keystore: {}", e))?;
+    let user = unlock_user(&mut vault, &mut keystore, config).await.ok_or("No account unlocked")?;
+    match command {
+        Command::Deposit { risk, amount, pay_with, yes, dry_run } => {
+            let pay_with = match pay_with.as_deref().map(parse_pay_with).transpose().map_err(|e| e.to_string())? {
+                Some(asset) => asset,
+                None => vault.get_vault_info(risk).and_then(|info| info.asset.clone()),
+            };
+            prime_prices(&mut vault, config).await;
+            execute_deposit(&mut vault, &user, risk, pay_with, amount, yes, dry_run, None).await
+        }
+        Command::Withdraw { risk, shares, dry_run } => execute_withdraw(&mut vault, &user, risk, shares, dry_run).await,
+        Command::Balance => Ok(run_balance(&vault, &user).await),
+        Command::Recurring { action } => run_recurring_command(&mut vault, &user, action).await,
+        Command::Position { risk } => Ok(print_position(&vault, &user, risk)),
+        Command::History => run_history(&vault, &user).await,
+        Command::Watch => run_watch(&mut vault, &user, config).await,
+        Command::Serve { bind, allow_origins, home_domain } => {
+            run_serve(&mut vault, &user, config, network, &bind, allow_origins, &home_domain).await
+        }
+        Command::Tui => {
+            tui::run(&mut vault, &user).await.map_err(|e| format!("Dashboard failed: {}", e))?;
+            Ok(serde_json::Value::Null)
+        }
+        _ => {
+            run_interactive(vault, keystore, user, config).await;
+            Ok(serde_json::Value::Null)
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    output::set_json(cli.output == OutputFormat::Json);
+    let command = cli.command.unwrap_or(Command::Interactive);
+    let network = if cli.offline { Some("offline") } else { cli.network.as_deref() };
+
+    let result = if output::is_json() && matches!(command, Command::Tui) {
+        Err("--output json has nothing to print for the dashboard".to_string())
+    } else if !matches!(command, Command::Interactive) {
+        run_command(command, network).await
+    } else if output::is_json() {
+        Err("--output json needs a command, such as vault-info; the interactive menu has no JSON output".to_string())
+    } else {
+        say!("🌟 StellarVault (SYIA) - Smart Yield Insurance Aggregator 🌟\n");
+        run_command(command, network).await
+    };
+    match result {
+        Ok(result) => {
+            output::emit(&result);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            say!("❌ {}", e);
+            output::emit(&json!({ "error": e }));
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,397 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use stellar_xdr::curr::{Asset, ChangeTrustAsset, MuxedAccount, OperationBody, TransactionEnvelope};
+
+use crate::amount::{Stroops, STROOPS_PER_XLM};
+use crate::horizon::{Account, AccountSigner, Balance, FeeDistribution, FeeStats, HorizonError, Payment, Thresholds, Transaction};
+use crate::network::OFFLINE_PASSPHRASE;
+use crate::transaction::{minimum_balance, BASE_FEE};
+use crate::{muxed, offline, unix_now};
+
+// How often a mock payment stream looks for new payments
+const STREAM_POLL_MILLIS: u64 = 500;
+// What the mock faucet gives, like friendbot on testnet
+pub const FAUCET_STROOPS: u64 = 10_000 * STROOPS_PER_XLM;
+
+// ============================================================================
+// MOCK LEDGER
+// ============================================================================
+
+// An in-memory stand-in for Horizon, so the vault can run with no network.
+// Accounts hold XLM and trustlines; submitted transactions apply payments,
+// account creation and trustline changes at once. Signatures aren't checked
+// and there is no DEX, so path payments and offers are refused.
+#[derive(Debug, Clone, Default)]
+pub struct MockLedger {
+    state: Arc<Mutex<LedgerState>>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct LedgerState {
+    ledger: u32,
+    accounts: HashMap<String, MockAccount>,
+    transactions: HashMap<String, Transaction>,
+    // Every payment, oldest first; the index is the paging token
+    payments: Vec<Payment>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct MockAccount {
+    sequence: i64,
+    native: i64,
+    // CODE:ISSUER to (balance, limit)
+    trustlines: BTreeMap<String, (i64, i64)>,
+}
+
+// A payment an operation made, for the account's payment history
+struct Paid {
+    to: muxed::Address,
+    asset: Asset,
+    amount: i64,
+}
+
+fn not_found(detail: String) -> HorizonError {
+    HorizonError::Problem { status: 404, title: "Resource Missing".into(), detail, result_codes: None }
+}
+
+// A 400 with Horizon's result codes, as a rejected submission gets
+fn rejected(transaction: &str, operation: Option<&str>) -> HorizonError {
+    let mut codes = serde_json::json!({ "transaction": transaction });
+    if let Some(operation) = operation {
+        codes["operations"] = serde_json::json!([operation]);
+    }
+    HorizonError::Problem {
+        status: 400,
+        title: "Transaction Failed".into(),
+        detail: format!("The mock ledger rejected the transaction: {}", operation.unwrap_or(transaction)),
+        result_codes: Some(codes),
+    }
+}
+
+// CODE:ISSUER, or None for XLM
+fn asset_key(asset: &Asset) -> Option<(String, String)> {
+    let issuer = match asset {
+        Asset::Native => return None,
+        Asset::CreditAlphanum4(asset) => asset.issuer.to_string(),
+        Asset::CreditAlphanum12(asset) => asset.issuer.to_string(),
+    };
+    Some((crate::assets::code_of(asset), issuer))
+}
+
+fn account_of(account: &MuxedAccount) -> muxed::Address {
+    let address = account.to_string();
+    muxed::parse(&address).unwrap_or(muxed::Address { account: address, id: None })
+}
+
+impl LedgerState {
+    fn account_mut(&mut self, id: &str) -> Result<&mut MockAccount, &'static str> {
+        self.accounts.get_mut(id).ok_or("op_no_destination")
+    }
+
+    // Moves `amount` of `asset` between existing accounts; issuers mint and
+    // burn their own asset rather than holding it
+    fn transfer(&mut self, from: &str, to: &str, asset: &Asset, amount: i64) -> Result<(), &'static str> {
+        if amount <= 0 {
+            return Err("op_malformed");
+        }
+        if !self.accounts.contains_key(to) {
+            return Err("op_no_destination");
+        }
+        match asset_key(asset) {
+            None => {
+                let source = self.account_mut(from)?;
+                if source.native < amount + minimum_balance(source.trustlines.len() as u64) as i64 {
+                    return Err("op_underfunded");
+                }
+                source.native -= amount;
+                self.account_mut(to)?.native += amount;
+            }
+            Some((code, issuer)) => {
+                let key = format!("{}:{}", code, issuer);
+                if from != issuer {
+                    let line = self.account_mut(from)?.trustlines.get_mut(&key).ok_or("op_src_no_trust")?;
+                    if line.0 < amount {
+                        return Err("op_underfunded");
+                    }
+                    line.0 -= amount;
+                }
+                if to != issuer {
+                    let line = self.account_mut(to)?.trustlines.get_mut(&key).ok_or("op_no_trust")?;
+                    if line.0 + amount > line.1 {
+                        return Err("op_line_full");
+                    }
+                    line.0 += amount;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, source: &str, body: &OperationBody) -> Result<Option<Paid>, &'static str> {
+        match body {
+            OperationBody::Payment(payment) => {
+                let destination = account_of(&payment.destination);
+                self.transfer(source, &destination.account, &payment.asset, payment.amount)?;
+                Ok(Some(Paid { to: destination, asset: payment.asset.clone(), amount: payment.amount }))
+            }
+            OperationBody::CreateAccount(create) => {
+                let destination = create.destination.to_string();
+                if self.accounts.contains_key(&destination) {
+                    return Err("op_already_exists");
+                }
+                if create.starting_balance < minimum_balance(0) as i64 {
+                    return Err("op_low_reserve");
+                }
+                let sequence = (self.ledger as i64) << 32;
+                self.accounts.insert(destination.clone(), MockAccount { sequence, ..MockAccount::default() });
+                self.transfer(source, &destination, &Asset::Native, create.starting_balance)?;
+                Ok(None)
+            }
+            OperationBody::ChangeTrust(change) => {
+                let asset = match &change.line {
+                    ChangeTrustAsset::CreditAlphanum4(asset) => Asset::CreditAlphanum4(asset.clone()),
+                    ChangeTrustAsset::CreditAlphanum12(asset) => Asset::CreditAlphanum12(asset.clone()),
+                    _ => return Err("op_not_supported"),
+                };
+                let (code, issuer) = asset_key(&asset).ok_or("op_malformed")?;
+                let lines = &mut self.account_mut(source)?.trustlines;
+                let key = format!("{}:{}", code, issuer);
+                match lines.get(&key).map(|line| line.0) {
+                    Some(balance) if change.limit < balance => return Err("op_invalid_limit"),
+                    _ if change.limit == 0 => {
+                        lines.remove(&key);
+                    }
+                    _ => {
+                        lines.entry(key).or_default().1 = change.limit;
+                    }
+                }
+                Ok(None)
+            }
+            _ => Err("op_not_supported"),
+        }
+    }
+}
+
+impl MockLedger {
+    // The ledger every client on the offline network shares
+    pub fn shared() -> MockLedger {
+        static SHARED: OnceLock<MockLedger> = OnceLock::new();
+        SHARED.get_or_init(MockLedger::default).clone()
+    }
+
+    // Creates the account with `amount`, or tops it up
+    pub fn fund(&self, account: &str, amount: Stroops) {
+        let mut state = self.state.lock().unwrap();
+        state.ledger += 1;
+        let ledger = state.ledger as i64;
+        let entry = state.accounts.entry(account.to_string()).or_insert_with(|| MockAccount {
+            // Real accounts start at their creation ledger shifted up 32 bits
+            sequence: ledger << 32,
+            ..MockAccount::default()
+        });
+        entry.native += amount.0 as i64;
+    }
+
+    pub fn account(&self, account_id: &str) -> Result<Account, HorizonError> {
+        let state = self.state.lock().unwrap();
+        let account = state.accounts.get(account_id)
+            .ok_or_else(|| not_found(format!("Account {} does not exist on the mock ledger", account_id)))?;
+        let mut balances: Vec<Balance> = account.trustlines.iter().map(|(key, (balance, limit))| {
+            let (code, issuer) = key.split_once(':').unwrap_or((key, ""));
+            Balance {
+                balance: Stroops(*balance as u64).to_xlm_string(),
+                asset_type: if code.len() <= 4 { "credit_alphanum4" } else { "credit_alphanum12" }.to_string(),
+                asset_code: Some(code.to_string()),
+                asset_issuer: Some(issuer.to_string()),
+                limit: Some(Stroops(*limit as u64).to_xlm_string()),
+                liquidity_pool_id: None,
+            }
+        }).collect();
+        balances.push(Balance {
+            balance: Stroops(account.native as u64).to_xlm_string(),
+            asset_type: "native".to_string(),
+            asset_code: None,
+            asset_issuer: None,
+            limit: None,
+            liquidity_pool_id: None,
+        });
+        Ok(Account {
+            account_id: account_id.to_string(),
+            sequence: account.sequence.to_string(),
+            balances,
+            thresholds: Thresholds::default(),
+            signers: vec![AccountSigner { key: account_id.to_string(), weight: 1, kind: "ed25519_public_key".to_string() }],
+            data: HashMap::new(),
+            home_domain: None,
+        })
+    }
+
+    pub fn transaction(&self, hash: &str) -> Result<Transaction, HorizonError> {
+        self.state.lock().unwrap().transactions.get(hash).cloned()
+            .ok_or_else(|| not_found(format!("Transaction {} is not on the mock ledger", hash)))
+    }
+
+    // Most recent first
+    pub fn payments(&self, account_id: &str, limit: u32) -> Vec<Payment> {
+        let state = self.state.lock().unwrap();
+        state.payments.iter().rev()
+            .filter(|p| p.from.as_deref() == Some(account_id) || p.to.as_deref() == Some(account_id))
+            .take(limit as usize)
+            .cloned()
+            .collect()
+    }
+
+    // Payments to or from the account after `cursor`, oldest first
+    fn payments_after(&self, account_id: &str, cursor: usize) -> Vec<Payment> {
+        let state = self.state.lock().unwrap();
+        state.payments.iter().skip(cursor)
+            .filter(|p| p.from.as_deref() == Some(account_id) || p.to.as_deref() == Some(account_id))
+            .cloned()
+            .collect()
+    }
+
+    // Like Horizon's stream, but polled; it never closes
+    pub async fn stream_payments(&self, account_id: &str, cursor: &str, mut on_payment: impl FnMut(Payment)) -> Result<(), HorizonError> {
+        let mut next = match cursor {
+            "now" => self.state.lock().unwrap().payments.len(),
+            cursor => cursor.parse::<usize>().map(|token| token + 1).unwrap_or(0),
+        };
+        loop {
+            for payment in self.payments_after(account_id, next) {
+                next = payment.paging_token.parse::<usize>().unwrap_or(next) + 1;
+                on_payment(payment);
+            }
+            tokio::time::sleep(Duration::from_millis(STREAM_POLL_MILLIS)).await;
+        }
+    }
+
+    // Every fee percentile is the base fee; the mock ledger is never busy
+    pub fn fee_stats(&self) -> FeeStats {
+        let base = BASE_FEE.to_string();
+        FeeStats {
+            last_ledger: self.state.lock().unwrap().ledger.to_string(),
+            last_ledger_base_fee: base.clone(),
+            ledger_capacity_usage: "0.0".to_string(),
+            fee_charged: FeeDistribution {
+                p10: base.clone(), p20: base.clone(), p30: base.clone(), p40: base.clone(), p50: base.clone(),
+                p60: base.clone(), p70: base.clone(), p80: base.clone(), p90: base.clone(), p95: base.clone(), p99: base,
+            },
+        }
+    }
+
+    // Applies a base64 TransactionEnvelope in a new ledger. Operations apply
+    // all together or not at all, as on the network.
+    pub fn submit_transaction(&self, envelope_xdr: &str) -> Result<Transaction, HorizonError> {
+        let envelope = offline::decode(envelope_xdr).map_err(|_| rejected("tx_malformed", None))?;
+        let TransactionEnvelope::Tx(inner) = &envelope else {
+            return Err(rejected("tx_not_supported", None));
+        };
+        let tx = &inner.tx;
+        let hash = offline::hash(&envelope, OFFLINE_PASSPHRASE).map_err(|_| rejected("tx_malformed", None))?;
+        let source = account_of(&tx.source_account).account;
+
+        let mut state = self.state.lock().unwrap();
+        let mut next = state.clone();
+        let fee_charged = (tx.operations.len() as i64 * BASE_FEE as i64).min(tx.fee as i64);
+        let account = next.accounts.get_mut(&source).ok_or_else(|| rejected("tx_no_source_account", None))?;
+        if tx.seq_num.0 != account.sequence + 1 {
+            return Err(rejected("tx_bad_seq", None));
+        }
+        if account.native < fee_charged {
+            return Err(rejected("tx_insufficient_balance", None));
+        }
+        account.native -= fee_charged;
+        account.sequence = tx.seq_num.0;
+
+        next.ledger += 1;
+        let created_at = unix_now().to_string();
+        let (memo_type, memo) = match &tx.memo {
+            stellar_xdr::curr::Memo::None => ("none".to_string(), None),
+            stellar_xdr::curr::Memo::Text(text) => ("text".to_string(), Some(String::from_utf8_lossy(text.as_slice()).to_string())),
+            stellar_xdr::curr::Memo::Id(id) => ("id".to_string(), Some(id.to_string())),
+            memo => ("hash".to_string(), crate::transaction::describe_memo(memo)),
+        };
+        let record = Transaction {
+            hash: hash.clone(),
+            ledger: next.ledger,
+            created_at: created_at.clone(),
+            fee_charged: fee_charged as u64,
+            successful: true,
+            memo_type,
+            memo,
+            result_xdr: String::new(),
+        };
+
+        for operation in tx.operations.iter() {
+            let from = operation.source_account.as_ref().map(|account| account_of(account).account)
+                .unwrap_or_else(|| source.clone());
+            let paid = next.apply(&from, &operation.body).map_err(|code| rejected("tx_failed", Some(code)))?;
+            if let Some(Paid { to, asset, amount }) = paid {
+                let (asset_type, asset_code, asset_issuer) = match asset_key(&asset) {
+                    None => ("native".to_string(), None, None),
+                    Some((code, issuer)) => {
+                        let asset_type = if code.len() <= 4 { "credit_alphanum4" } else { "credit_alphanum12" };
+                        (asset_type.to_string(), Some(code), Some(issuer))
+                    }
+                };
+                let paging_token = next.payments.len().to_string();
+                next.payments.push(Payment {
+                    paging_token,
+                    kind: "payment".to_string(),
+                    created_at: created_at.clone(),
+                    transaction_hash: hash.clone(),
+                    transaction_successful: true,
+                    from: Some(from),
+                    to: Some(to.account),
+                    from_muxed: None,
+                    to_muxed_id: to.id.map(|id| id.to_string()),
+                    amount: Some(Stroops(amount as u64).to_xlm_string()),
+                    asset_type: Some(asset_type),
+                    asset_code,
+                    asset_issuer,
+                    transaction: Some(record.clone()),
+                });
+            }
+        }
+
+        next.transactions.insert(hash, record.clone());
+        *state = next;
+        Ok(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::{sign, to_base64, Keypair, TransactionBuilder};
+
+    const SECRET: &str = "SBGWSG6BTNCKCOB3DIFBGCVMUPQFYPA2G4O34RMTB343OYPXU5DJDVMN";
+    const DESTINATION: &str = "GCZEAWUJY3BRHCOKU6C5WRLCF5RFSGY22UGBPBXWL4T4G4SSEQMIYMCX";
+
+    #[test]
+    fn payments_move_balances_and_need_the_next_sequence() {
+        let ledger = MockLedger::default();
+        let keypair = Keypair::from_secret(SECRET).unwrap();
+        ledger.fund(&keypair.public_key(), Stroops(1_000 * 10_000_000));
+        ledger.fund(DESTINATION, Stroops(10 * 10_000_000));
+
+        let sequence = ledger.account(&keypair.public_key()).unwrap().sequence_number().unwrap();
+        let envelope = |sequence: i64| {
+            let tx = TransactionBuilder::new(&keypair.public_key(), sequence).unwrap()
+                .payment(DESTINATION, Asset::Native, 25 * 10_000_000).unwrap()
+                .build().unwrap();
+            to_base64(&sign(tx, OFFLINE_PASSPHRASE, &[&keypair]).unwrap()).unwrap()
+        };
+
+        let applied = ledger.submit_transaction(&envelope(sequence)).unwrap();
+        assert_eq!(ledger.transaction(&applied.hash).unwrap().fee_charged, BASE_FEE as u64);
+        let balance = |account: &str| ledger.account(account).unwrap().balances.last().unwrap().balance.clone();
+        assert_eq!(balance(DESTINATION), "35.0000000");
+        assert_eq!(balance(&keypair.public_key()), "974.9999900");
+        assert_eq!(ledger.payments(DESTINATION, 10).len(), 1);
+
+        let replayed = ledger.submit_transaction(&envelope(sequence)).unwrap_err();
+        assert_eq!(replayed.transaction_code(), Some("tx_bad_seq"));
+    }
+}
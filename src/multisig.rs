@@ -0,0 +1,314 @@
+use std::fmt;
+use stellar_xdr::curr::{
+    DecoratedSignature, OperationBody, SetOptionsOp, Signer, SignerKey, Transaction, Uint256,
+};
+
+use crate::error::VaultError;
+use crate::horizon::Account;
+use crate::signer;
+use crate::transaction;
+
+// ============================================================================
+// MULTI-SIGNATURE ACCOUNTS
+// ============================================================================
+
+// Which of an account's thresholds a transaction has to meet. Payments are
+// medium; changing signers or thresholds, or merging the account, is high.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Threshold {
+    Low,
+    Medium,
+    High,
+}
+
+impl fmt::Display for Threshold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Threshold::Low => write!(f, "low"),
+            Threshold::Medium => write!(f, "medium"),
+            Threshold::High => write!(f, "high"),
+        }
+    }
+}
+
+// Only counts operations on the transaction's own source account
+pub fn required_threshold(tx: &Transaction) -> Threshold {
+    tx.operations.iter()
+        .filter(|operation| operation.source_account.is_none())
+        .map(|operation| match &operation.body {
+            OperationBody::SetOptions(options) if options.signer.is_some() || options.master_weight.is_some()
+                || options.low_threshold.is_some() || options.med_threshold.is_some()
+                || options.high_threshold.is_some() => Threshold::High,
+            OperationBody::AccountMerge(_) => Threshold::High,
+            OperationBody::BumpSequence(_) | OperationBody::AllowTrust(_) | OperationBody::SetTrustLineFlags(_) => Threshold::Low,
+            _ => Threshold::Medium,
+        })
+        .max()
+        .unwrap_or(Threshold::Low)
+}
+
+// The ed25519 signers of an account and the weight each operation class needs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignerSet {
+    pub account: String,
+    // Master key included; other signer types can't sign from this CLI
+    pub signers: Vec<(String, u8)>,
+    pub low: u8,
+    pub medium: u8,
+    pub high: u8,
+}
+
+impl SignerSet {
+    pub fn from_account(account: &Account) -> Self {
+        SignerSet {
+            account: account.account_id.clone(),
+            signers: account.signers.iter()
+                .filter(|signer| signer.kind == "ed25519_public_key" && signer.weight > 0)
+                .map(|signer| (signer.key.clone(), signer.weight))
+                .collect(),
+            low: account.thresholds.low_threshold,
+            medium: account.thresholds.med_threshold,
+            high: account.thresholds.high_threshold,
+        }
+    }
+
+    // An account not created yet, which only its own key can sign for
+    pub fn master_only(account: &str) -> Self {
+        SignerSet { account: account.to_string(), signers: vec![(account.to_string(), 1)], low: 0, medium: 0, high: 0 }
+    }
+
+    // More than the master key alone can authorize
+    pub fn is_multisig(&self) -> bool {
+        self.signers.len() > 1
+    }
+
+    // A threshold of 0 still needs one signature with some weight
+    pub fn required(&self, threshold: Threshold) -> u32 {
+        let needed = match threshold {
+            Threshold::Low => self.low,
+            Threshold::Medium => self.medium,
+            Threshold::High => self.high,
+        };
+        needed.max(1) as u32
+    }
+
+    pub fn weight(&self, key: &str) -> u8 {
+        self.signers.iter()
+            .find(|(signer, _)| signer == key)
+            .map(|(_, weight)| *weight)
+            .unwrap_or(0)
+    }
+
+    // Each key counts once, however many times it is listed
+    pub fn weight_of<'a>(&self, keys: impl IntoIterator<Item = &'a str>) -> u32 {
+        let mut seen = Vec::new();
+        keys.into_iter()
+            .filter(|key| {
+                let first = !seen.contains(key);
+                seen.push(*key);
+                first
+            })
+            .map(|key| self.weight(key) as u32)
+            .sum()
+    }
+
+    pub fn total_weight(&self) -> u32 {
+        self.signers.iter().map(|(_, weight)| *weight as u32).sum()
+    }
+
+    pub fn check(&self, keys: &[&str], threshold: Threshold) -> Result<u32, VaultError> {
+        let weight = self.weight_of(keys.iter().copied());
+        let required = self.required(threshold);
+        if weight < required {
+            return Err(VaultError::Validation(format!("Signatures carry weight {} of the {} {} needs for {} operations",
+                weight, required, self.account, threshold)));
+        }
+        Ok(weight)
+    }
+
+    pub fn with_signer(mut self, key: &str, weight: u8) -> Self {
+        self.signers.retain(|(signer, _)| signer != key);
+        if weight > 0 {
+            self.signers.push((key.to_string(), weight));
+        }
+        self
+    }
+
+    pub fn with_thresholds(mut self, low: u8, medium: u8, high: u8) -> Self {
+        self.low = low;
+        self.medium = medium;
+        self.high = high;
+        self
+    }
+
+    // Refuses a configuration the signers could never change back
+    pub fn ensure_reachable(&self) -> Result<(), VaultError> {
+        if self.total_weight() < self.required(Threshold::High) {
+            return Err(VaultError::Validation(format!("Signers would hold weight {} but the high threshold is {}; the account would be locked",
+                self.total_weight(), self.required(Threshold::High))));
+        }
+        Ok(())
+    }
+
+    // Keys whose signatures on the transaction are valid, and how many
+    // signatures match no signer (Horizon refuses those as tx_bad_auth_extra)
+    pub fn signed_by(&self, tx: &Transaction, signatures: &[DecoratedSignature], passphrase: &str) -> Result<(Vec<String>, usize), VaultError> {
+        let hash = tx.hash(transaction::network_id(passphrase))?;
+        let mut signed = Vec::new();
+        let mut unknown = 0;
+        for signature in signatures {
+            let signer = self.signers.iter()
+                .map(|(key, _)| key)
+                .find(|key| signer::verify(key, &hash, signature));
+            match signer {
+                Some(key) if !signed.contains(key) => signed.push(key.clone()),
+                _ => unknown += 1,
+            }
+        }
+        Ok((signed, unknown))
+    }
+}
+
+// Adds, reweights or (at weight 0) removes a signer. The master key can't be
+// added as a signer; its weight is the account's master weight.
+pub fn set_signer(account: &str, key: &str, weight: u8) -> Result<OperationBody, VaultError> {
+    let public_key = stellar_strkey::ed25519::PublicKey::from_string(key)
+        .map_err(|_| VaultError::Validation(format!("Invalid signer {}", key)))?;
+    let mut options = empty_options();
+    if key == account {
+        options.master_weight = Some(weight as u32);
+    } else {
+        options.signer = Some(Signer { key: SignerKey::Ed25519(Uint256(public_key.0)), weight: weight as u32 });
+    }
+    Ok(OperationBody::SetOptions(options))
+}
+
+// Hands `old`'s signing power to `new` in one transaction, with new
+// thresholds if given: the new signer goes in first and the old one comes
+// out last. Signatures are checked against the signers before it, so the
+// keys loaded now are the ones that have to reach the high threshold.
+pub fn rotation(signer_set: &SignerSet, old: &str, new: &str, weight: Option<u8>, thresholds: Option<(u8, u8, u8)>)
+    -> Result<(SignerSet, Vec<OperationBody>), VaultError> {
+    let old_weight = signer_set.weight(old);
+    if old_weight == 0 {
+        return Err(VaultError::Validation(format!("{} is not a signer of {}", old, signer_set.account)));
+    }
+    if new == old {
+        return Err(VaultError::Validation("The new signer has to be a different key".into()));
+    }
+    if signer_set.weight(new) > 0 {
+        return Err(VaultError::Validation(format!("{} already signs for {}; change its weight with 'multisig signer'", new, signer_set.account)));
+    }
+    let weight = weight.unwrap_or(old_weight);
+    if weight == 0 {
+        return Err(VaultError::Validation("The new signer needs a weight above 0".into()));
+    }
+
+    let mut updated = signer_set.clone().with_signer(new, weight);
+    let mut operations = vec![set_signer(&signer_set.account, new, weight)?];
+    if let Some((low, medium, high)) = thresholds {
+        updated = updated.with_thresholds(low, medium, high);
+        operations.push(set_thresholds(low, medium, high));
+    }
+    let updated = updated.with_signer(old, 0);
+    operations.push(set_signer(&signer_set.account, old, 0)?);
+    updated.ensure_reachable()?;
+    Ok((updated, operations))
+}
+
+pub fn set_thresholds(low: u8, medium: u8, high: u8) -> OperationBody {
+    let mut options = empty_options();
+    options.low_threshold = Some(low as u32);
+    options.med_threshold = Some(medium as u32);
+    options.high_threshold = Some(high as u32);
+    OperationBody::SetOptions(options)
+}
+
+fn empty_options() -> SetOptionsOp {
+    SetOptionsOp {
+        inflation_dest: None,
+        clear_flags: None,
+        set_flags: None,
+        master_weight: None,
+        low_threshold: None,
+        med_threshold: None,
+        high_threshold: None,
+        home_domain: None,
+        signer: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::{Keypair, TransactionBuilder};
+    use stellar_xdr::curr::{Asset, TransactionEnvelope};
+
+    const PASSPHRASE: &str = "Test SDF Network ; September 2015";
+
+    #[test]
+    fn payments_need_the_medium_threshold_from_signers() {
+        let master = Keypair::from_seed(&[1; 32]);
+        let operator = Keypair::from_seed(&[2; 32]);
+        let outsider = Keypair::from_seed(&[3; 32]);
+        let signers = SignerSet {
+            account: master.public_key(),
+            signers: vec![(master.public_key(), 1), (operator.public_key(), 1)],
+            low: 1,
+            medium: 2,
+            high: 2,
+        };
+        assert!(signers.is_multisig());
+
+        let tx = TransactionBuilder::new(&master.public_key(), 1).unwrap()
+            .payment(&outsider.public_key(), Asset::Native, 10_000_000).unwrap()
+            .build().unwrap();
+        assert_eq!(required_threshold(&tx), Threshold::Medium);
+
+        let TransactionEnvelope::Tx(one) = transaction::sign(tx.clone(), PASSPHRASE, &[&master]).unwrap() else { unreachable!() };
+        let (signed, unknown) = signers.signed_by(&tx, &one.signatures, PASSPHRASE).unwrap();
+        assert_eq!((signed.len(), unknown), (1, 0));
+        assert!(signers.check(&[&master.public_key()], Threshold::Medium).is_err());
+
+        let TransactionEnvelope::Tx(all) = transaction::sign(tx.clone(), PASSPHRASE, &[&master, &operator, &outsider]).unwrap() else { unreachable!() };
+        let (signed, unknown) = signers.signed_by(&tx, &all.signatures, PASSPHRASE).unwrap();
+        assert_eq!(unknown, 1);
+        let signed: Vec<&str> = signed.iter().map(String::as_str).collect();
+        assert_eq!(signers.check(&signed, Threshold::Medium).unwrap(), 2);
+
+        // Dropping a signer would leave too little weight to ever reach high again
+        assert!(signers.clone().with_signer(&operator.public_key(), 0).ensure_reachable().is_err());
+        assert!(signers.with_signer(&operator.public_key(), 0).with_thresholds(1, 1, 1).ensure_reachable().is_ok());
+    }
+
+    #[test]
+    fn rotation_swaps_a_signer_in_one_transaction() {
+        let master = Keypair::from_seed(&[1; 32]);
+        let operator = Keypair::from_seed(&[2; 32]);
+        let replacement = Keypair::from_seed(&[3; 32]);
+        let signers = SignerSet {
+            account: master.public_key(),
+            signers: vec![(master.public_key(), 1), (operator.public_key(), 1)],
+            low: 1,
+            medium: 2,
+            high: 2,
+        };
+
+        let (updated, operations) = rotation(&signers, &operator.public_key(), &replacement.public_key(), None, None).unwrap();
+        assert_eq!(updated.signers, vec![(master.public_key(), 1), (replacement.public_key(), 1)]);
+        assert_eq!(operations.len(), 2);
+        let OperationBody::SetOptions(removal) = &operations[1] else { unreachable!() };
+        assert_eq!(removal.signer.as_ref().map(|signer| signer.weight), Some(0));
+
+        // Retiring the master key zeroes its weight, and new thresholds go in between
+        let (updated, operations) = rotation(&signers, &master.public_key(), &replacement.public_key(), Some(2), Some((1, 2, 3))).unwrap();
+        assert_eq!(updated.weight(&master.public_key()), 0);
+        assert_eq!(updated.total_weight(), 3);
+        let OperationBody::SetOptions(removal) = &operations[2] else { unreachable!() };
+        assert_eq!(removal.master_weight, Some(0));
+
+        // Too little weight left for the new high threshold, or nothing to rotate
+        assert!(rotation(&signers, &operator.public_key(), &replacement.public_key(), None, Some((1, 2, 3))).is_err());
+        assert!(rotation(&signers, &replacement.public_key(), &operator.public_key(), None, None).is_err());
+    }
+}
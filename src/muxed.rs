@@ -0,0 +1,108 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+
+use crate::RiskLevel;
+use crate::error::VaultError;
+
+// ============================================================================
+// MUXED ACCOUNTS
+// ============================================================================
+
+// An M... address is a G... account plus a 64-bit ID. Payments to it land in
+// the underlying account; the ID only tells the receiver who they are for,
+// which is how exchanges share one account between their customers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Address {
+    // G... form of the underlying account
+    pub account: String,
+    pub id: Option<u64>,
+}
+
+// Accepts either a G... or an M... address
+pub fn parse(address: &str) -> Result<Address, VaultError> {
+    if let Ok(key) = stellar_strkey::ed25519::PublicKey::from_string(address) {
+        return Ok(Address { account: key.to_string(), id: None });
+    }
+    if let Ok(muxed) = stellar_strkey::ed25519::MuxedAccount::from_string(address) {
+        return Ok(Address {
+            account: stellar_strkey::ed25519::PublicKey(muxed.ed25519).to_string(),
+            id: Some(muxed.id),
+        });
+    }
+    Err(VaultError::Validation(format!("Invalid Stellar address (expected G... or M...): {}", address)))
+}
+
+pub fn is_valid(address: &str) -> bool {
+    parse(address).is_ok()
+}
+
+// The M... address for `id` on `account`
+pub fn address(account: &str, id: u64) -> Result<String, VaultError> {
+    let key = stellar_strkey::ed25519::PublicKey::from_string(account)
+        .map_err(|_| VaultError::Validation(format!("Invalid Stellar account {}", account)))?;
+    Ok(stellar_strkey::ed25519::MuxedAccount { ed25519: key.0, id }.to_string())
+}
+
+// ============================================================================
+// DEPOSIT ROUTING
+// ============================================================================
+
+// Who a vault sub-address belongs to. XLM sent to it is credited to that
+// user's position, whoever sent it, so deposits from an exchange withdrawal
+// need neither a memo nor the user's own key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DepositRoute {
+    pub user: String,
+    pub risk: RiskLevel,
+    pub created_at: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DepositRoutes {
+    routes: BTreeMap<u64, DepositRoute>,
+    next_id: u64,
+}
+
+impl DepositRoutes {
+    // A user keeps the same sub-address per vault once assigned
+    pub fn assign(&mut self, user: &str, risk: RiskLevel, now: u64) -> u64 {
+        if let Some((&id, _)) = self.routes.iter().find(|(_, r)| r.user == user && r.risk == risk) {
+            return id;
+        }
+        self.next_id += 1;
+        self.routes.insert(self.next_id, DepositRoute { user: user.to_string(), risk, created_at: now });
+        self.next_id
+    }
+
+    pub fn get(&self, id: u64) -> Option<&DepositRoute> {
+        self.routes.get(&id)
+    }
+
+    pub fn for_user(&self, user: &str) -> Vec<(u64, &DepositRoute)> {
+        self.routes.iter()
+            .filter(|(_, route)| route.user == user)
+            .map(|(&id, route)| (id, route))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT: &str = "GCZEAWUJY3BRHCOKU6C5WRLCF5RFSGY22UGBPBXWL4T4G4SSEQMIYMCX";
+
+    #[test]
+    fn sub_addresses_round_trip_to_the_vault_account() {
+        let mut routes = DepositRoutes::default();
+        let id = routes.assign("GUSER", RiskLevel::Medium, 0);
+        assert_eq!(routes.assign("GUSER", RiskLevel::Medium, 10), id);
+        assert_ne!(routes.assign("GUSER", RiskLevel::High, 10), id);
+
+        let muxed = address(VAULT, id).unwrap();
+        assert!(muxed.starts_with('M'));
+        assert_eq!(parse(&muxed).unwrap(), Address { account: VAULT.to_string(), id: Some(id) });
+        assert_eq!(parse(VAULT).unwrap().id, None);
+        assert!(!is_valid("MABC"));
+    }
+}
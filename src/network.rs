@@ -0,0 +1,130 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::VaultError;
+
+pub const TESTNET_PASSPHRASE: &str = "Test SDF Network ; September 2015";
+pub const MAINNET_PASSPHRASE: &str = "Public Global Stellar Network ; September 2015";
+pub const FUTURENET_PASSPHRASE: &str = "Test SDF Future Network ; October 2022";
+pub const OFFLINE_PASSPHRASE: &str = "StellarVault Offline Mock Ledger";
+
+// ============================================================================
+// NETWORKS
+// ============================================================================
+
+// Everything that differs between Stellar networks: where Horizon lives, the
+// passphrase transactions are signed for, and where to link for explorers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Network {
+    Testnet,
+    Mainnet,
+    Futurenet,
+    // An in-memory mock ledger, for demos and development with no network
+    Offline,
+    Custom {
+        horizon_url: String,
+        passphrase: String,
+        explorer_url: Option<String>,
+    },
+}
+
+impl Network {
+    pub fn horizon_url(&self) -> &str {
+        match self {
+            Network::Testnet => "https://horizon-testnet.stellar.org",
+            Network::Mainnet => "https://horizon.stellar.org",
+            Network::Futurenet => "https://horizon-futurenet.stellar.org",
+            Network::Offline => "in-memory",
+            Network::Custom { horizon_url, .. } => horizon_url,
+        }
+    }
+
+    pub fn passphrase(&self) -> &str {
+        match self {
+            Network::Testnet => TESTNET_PASSPHRASE,
+            Network::Mainnet => MAINNET_PASSPHRASE,
+            Network::Futurenet => FUTURENET_PASSPHRASE,
+            Network::Offline => OFFLINE_PASSPHRASE,
+            Network::Custom { passphrase, .. } => passphrase,
+        }
+    }
+
+    // Base URL of a block explorer with /tx/{hash} and /account/{id} pages
+    pub fn explorer_url(&self) -> Option<&str> {
+        match self {
+            Network::Testnet => Some("https://testnet.stellarscan.io"),
+            Network::Mainnet => Some("https://stellarscan.io"),
+            Network::Futurenet => Some("https://stellar.expert/explorer/futurenet"),
+            Network::Offline => None,
+            Network::Custom { explorer_url, .. } => explorer_url.as_deref(),
+        }
+    }
+
+    // Where test XLM comes from, on networks that have a friendbot
+    pub fn friendbot_url(&self) -> Option<&str> {
+        match self {
+            Network::Testnet => Some("https://friendbot.stellar.org"),
+            Network::Futurenet => Some("https://friendbot-futurenet.stellar.org"),
+            Network::Mainnet | Network::Offline | Network::Custom { .. } => None,
+        }
+    }
+
+    // Public Soroban RPC servers for contract calls. SDF runs none for
+    // mainnet, so there it has to be configured.
+    pub fn soroban_rpc_url(&self) -> Option<&str> {
+        match self {
+            Network::Testnet => Some("https://soroban-testnet.stellar.org"),
+            Network::Futurenet => Some("https://rpc-futurenet.stellar.org"),
+            Network::Mainnet | Network::Offline | Network::Custom { .. } => None,
+        }
+    }
+
+    pub fn is_offline(&self) -> bool {
+        *self == Network::Offline
+    }
+
+    // Custom networks count as mainnet when they sign for it, whatever Horizon they use
+    pub fn is_mainnet(&self) -> bool {
+        self.passphrase() == MAINNET_PASSPHRASE
+    }
+
+    pub fn tx_link(&self, hash: &str) -> String {
+        match self.explorer_url() {
+            Some(base) => format!("{}/tx/{}", base, hash),
+            None => hash.to_string(),
+        }
+    }
+
+    pub fn account_link(&self, account: &str) -> String {
+        match self.explorer_url() {
+            Some(base) => format!("{}/account/{}", base, account),
+            None => account.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Network::Testnet => write!(f, "Stellar Testnet"),
+            Network::Mainnet => write!(f, "Stellar Mainnet"),
+            Network::Futurenet => write!(f, "Stellar Futurenet"),
+            Network::Offline => write!(f, "offline mock ledger"),
+            Network::Custom { horizon_url, .. } => write!(f, "custom network ({})", horizon_url),
+        }
+    }
+}
+
+impl FromStr for Network {
+    type Err = VaultError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "testnet" | "test" => Ok(Network::Testnet),
+            "mainnet" | "public" | "pubnet" => Ok(Network::Mainnet),
+            "futurenet" => Ok(Network::Futurenet),
+            "offline" | "mock" => Ok(Network::Offline),
+            other => Err(VaultError::Validation(format!("Unknown network {:?} (expected testnet, mainnet, futurenet or offline)", other))),
+        }
+    }
+}
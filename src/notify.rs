@@ -0,0 +1,508 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use chacha20poly1305::aead::OsRng;
+use chacha20poly1305::aead::rand_core::RngCore;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::task::{JoinHandle, JoinSet};
+
+use crate::alerts::Alert;
+use crate::amount::Stroops;
+use crate::claims::ClaimStatus;
+use crate::events::{VaultEvent, YieldCredit};
+use crate::failover::Failover;
+use crate::network::Network;
+use crate::{unix_now, RiskLevel, Vault};
+
+// "t=<unix time>,v1=<hex HMAC-SHA256 of '<unix time>.<body>'>"
+pub const SIGNATURE_HEADER: &str = "X-StellarVault-Signature";
+pub const DELIVERY_ATTEMPTS: u32 = 5;
+pub const TELEGRAM_API: &str = "https://api.telegram.org";
+// Doubled after each failed attempt
+const FIRST_RETRY: Duration = Duration::from_secs(1);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+// How long a finished command waits for deliveries still being retried
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(20);
+
+// ============================================================================
+// NOTIFICATIONS
+// ============================================================================
+
+// What operators get told about, as they happen
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Notification {
+    DepositConfirmed {
+        user: String,
+        risk: RiskLevel,
+        // Code of the vault's asset, which amounts are in
+        asset: String,
+        amount_stroops: u64,
+        shares_minted: u64,
+        tx_hash: String,
+    },
+    WithdrawalCompleted {
+        user: String,
+        risk: RiskLevel,
+        asset: String,
+        shares_burned: u64,
+        // Paid out, after the penalty
+        amount_stroops: u64,
+        penalty_stroops: u64,
+        tx_hash: String,
+    },
+    Harvest {
+        risk: RiskLevel,
+        asset: String,
+        yield_stroops: u64,
+        performance_fee: u64,
+        // Each position's share of the yield
+        credits: Vec<YieldCredit>,
+    },
+    CircuitBreakerTripped {
+        risk: RiskLevel,
+        reason: String,
+    },
+    ClaimDecided {
+        claim_id: u64,
+        claimant: String,
+        amount_stroops: u64,
+        status: ClaimStatus,
+        note: String,
+    },
+    // An alert rule broke
+    Alert(Alert),
+    // A recurring plan's run didn't go ahead, such as for a short balance
+    RecurringSkipped {
+        plan_id: u64,
+        user: String,
+        // What the plan does, as listed by 'recurring list'
+        plan: String,
+        reason: String,
+    },
+    // Horizon calls moved to another server, or back to the primary
+    HorizonFailover(Failover),
+    // A fee increase, strategy or treasury change that takes effect once its
+    // delay is out, unless cancelled first
+    AdminChangeQueued {
+        change_id: u64,
+        change: String,
+        takes_effect_at: u64,
+    },
+}
+
+impl Notification {
+    // The events worth a notification; the rest are bookkeeping
+    pub fn from_event(event: &VaultEvent, vaults: &HashMap<RiskLevel, Vault>) -> Option<Notification> {
+        let asset = |risk: &RiskLevel| vaults.get(risk).map_or("XLM", Vault::asset_code).to_string();
+        match event {
+            VaultEvent::Deposit { user, risk, amount_stroops, shares_minted, tx_hash, .. } => Some(Notification::DepositConfirmed {
+                user: user.clone(),
+                risk: *risk,
+                asset: asset(risk),
+                amount_stroops: *amount_stroops,
+                shares_minted: *shares_minted,
+                tx_hash: tx_hash.clone(),
+            }),
+            VaultEvent::Withdrawal { user, risk, shares_burned, gross_stroops, penalty_stroops, tx_hash } => Some(Notification::WithdrawalCompleted {
+                user: user.clone(),
+                risk: *risk,
+                asset: asset(risk),
+                shares_burned: *shares_burned,
+                amount_stroops: gross_stroops.saturating_sub(*penalty_stroops),
+                penalty_stroops: *penalty_stroops,
+                tx_hash: tx_hash.clone(),
+            }),
+            VaultEvent::Harvest { risk, strategy_yield, performance_fee, credits, .. } => Some(Notification::Harvest {
+                risk: *risk,
+                asset: asset(risk),
+                yield_stroops: strategy_yield.iter().sum(),
+                performance_fee: *performance_fee,
+                credits: credits.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Notification::DepositConfirmed { .. } => "deposit_confirmed",
+            Notification::WithdrawalCompleted { .. } => "withdrawal_completed",
+            Notification::Harvest { .. } => "harvest",
+            Notification::CircuitBreakerTripped { .. } => "circuit_breaker_tripped",
+            Notification::ClaimDecided { .. } => "claim_decided",
+            Notification::Alert(_) => "alert",
+            Notification::RecurringSkipped { .. } => "recurring_skipped",
+            Notification::HorizonFailover(_) => "horizon_failover",
+            Notification::AdminChangeQueued { .. } => "admin_change_queued",
+        }
+    }
+}
+
+// A notification as delivered. Retries keep the same id, so receivers can
+// drop ones they have already seen.
+#[derive(Debug, Clone, Serialize)]
+pub struct Message {
+    pub id: String,
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub notification: Notification,
+}
+
+impl Message {
+    pub fn new(notification: Notification) -> Self {
+        let mut id = [0u8; 16];
+        OsRng.fill_bytes(&mut id);
+        Message { id: hex(&id), timestamp: unix_now(), notification }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// ============================================================================
+// CHANNELS
+// ============================================================================
+
+#[derive(Debug)]
+pub enum DeliveryError {
+    // Worth trying again later: timeouts, connection errors, 5xx and 429
+    Transient(String),
+    // The receiver turned the message down and will again
+    Permanent(String),
+}
+
+impl fmt::Display for DeliveryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeliveryError::Transient(e) | DeliveryError::Permanent(e) => f.write_str(e),
+        }
+    }
+}
+
+pub type DeliveryFuture<'a> = Pin<Box<dyn Future<Output = Result<(), DeliveryError>> + Send + 'a>>;
+
+// Somewhere notifications are sent
+pub trait Channel: Send + Sync {
+    // Where messages go, for the delivery log; never a secret
+    fn target(&self) -> String;
+    // Channels that only care about some notifications skip the rest
+    fn wants(&self, _notification: &Notification) -> bool {
+        true
+    }
+    fn send<'a>(&'a self, message: &'a Message) -> DeliveryFuture<'a>;
+}
+
+// POSTs each message as JSON, signed with the shared secret so the receiver
+// can tell it came from this vault and when
+pub struct Webhook {
+    url: String,
+    secret: Vec<u8>,
+    http: reqwest::Client,
+}
+
+impl Webhook {
+    pub fn new(url: &str, secret: &str) -> Self {
+        Webhook {
+            url: url.to_string(),
+            secret: secret.as_bytes().to_vec(),
+            http: reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build().unwrap_or_default(),
+        }
+    }
+
+    pub fn signature(&self, timestamp: u64, body: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC takes keys of any length");
+        mac.update(format!("{}.", timestamp).as_bytes());
+        mac.update(body.as_bytes());
+        format!("t={},v1={}", timestamp, hex(&mac.finalize().into_bytes()))
+    }
+}
+
+impl Channel for Webhook {
+    // Hook URLs often carry a token, so only the host is logged
+    fn target(&self) -> String {
+        match reqwest::Url::parse(&self.url) {
+            Ok(url) => format!("webhook {}", url.host_str().unwrap_or("?")),
+            Err(_) => "webhook".to_string(),
+        }
+    }
+
+    fn send<'a>(&'a self, message: &'a Message) -> DeliveryFuture<'a> {
+        Box::pin(async move {
+            let body = serde_json::to_string(message).map_err(|e| DeliveryError::Permanent(e.to_string()))?;
+            // Signed afresh on each attempt, so a receiver can refuse stale replays
+            let signature = self.signature(unix_now(), &body);
+            let response = self.http.post(&self.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(SIGNATURE_HEADER, signature)
+                .body(body)
+                .send()
+                .await
+                .map_err(|e| DeliveryError::Transient(e.to_string()))?;
+            let status = response.status();
+            if status.is_success() {
+                Ok(())
+            } else if status.is_server_error() || status.as_u16() == 408 || status.as_u16() == 429 {
+                Err(DeliveryError::Transient(format!("HTTP {}", status)))
+            } else {
+                Err(DeliveryError::Permanent(format!("HTTP {}", status)))
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TelegramConfig {
+    // From the environment only
+    pub bot_token: String,
+    // A numeric chat id, or @channel for a channel the bot posts in
+    pub chat_id: String,
+    // The account whose activity is sent
+    pub account: String,
+}
+
+// Tells one account's owner, through a Telegram bot, when their deposits
+// confirm, harvests credit their positions and withdrawals are paid out
+pub struct Telegram {
+    config: TelegramConfig,
+    network: Network,
+    http: reqwest::Client,
+}
+
+#[derive(serde::Deserialize)]
+struct TelegramReply {
+    description: Option<String>,
+}
+
+impl Telegram {
+    pub fn new(config: TelegramConfig, network: &Network) -> Self {
+        Telegram {
+            config,
+            network: network.clone(),
+            http: reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build().unwrap_or_default(),
+        }
+    }
+
+    // The chat message for a notification, when it concerns the account
+    pub fn text(&self, notification: &Notification) -> Option<String> {
+        let account = self.config.account.as_str();
+        let amount = |stroops: u64| Stroops(stroops).to_xlm_string();
+        match notification {
+            Notification::DepositConfirmed { user, risk, asset, amount_stroops, shares_minted, tx_hash } if user == account => Some(format!(
+                "✅ Deposit confirmed: {} {} into the {:?} Risk Vault for {} shares\n{}",
+                amount(*amount_stroops), asset, risk, shares_minted, self.network.tx_link(tx_hash))),
+            Notification::Harvest { risk, asset, credits, .. } => {
+                let credit = credits.iter().find(|credit| credit.user == account && credit.amount_stroops > 0)?;
+                let credited = if credit.compounded {
+                    format!("compounded into {} shares", credit.shares)
+                } else {
+                    "credited to your position".to_string()
+                };
+                Some(format!("🌾 Harvest: {} {} of yield from the {:?} Risk Vault {}\n{}",
+                    amount(credit.amount_stroops), asset, risk, credited, self.network.account_link(account)))
+            }
+            Notification::WithdrawalCompleted { user, risk, asset, amount_stroops, penalty_stroops, tx_hash, .. } if user == account => {
+                let penalty = if *penalty_stroops > 0 {
+                    format!(" after a {} {} early withdrawal penalty", amount(*penalty_stroops), asset)
+                } else {
+                    String::new()
+                };
+                Some(format!("💸 Withdrawal paid out: {} {} from the {:?} Risk Vault{}\n{}",
+                    amount(*amount_stroops), asset, risk, penalty, self.network.tx_link(tx_hash)))
+            }
+            Notification::RecurringSkipped { plan_id, user, plan, reason } if user == account => Some(format!(
+                "⏭️ Recurring plan #{} skipped this time ({}): {}", plan_id, plan, reason)),
+            _ => None,
+        }
+    }
+}
+
+impl Channel for Telegram {
+    // The bot token is a secret; the chat isn't
+    fn target(&self) -> String {
+        format!("telegram chat {}", self.config.chat_id)
+    }
+
+    fn wants(&self, notification: &Notification) -> bool {
+        self.text(notification).is_some()
+    }
+
+    fn send<'a>(&'a self, message: &'a Message) -> DeliveryFuture<'a> {
+        Box::pin(async move {
+            let Some(text) = self.text(&message.notification) else {
+                return Ok(());
+            };
+            let url = format!("{}/bot{}/sendMessage", TELEGRAM_API, self.config.bot_token);
+            let response = self.http.post(&url)
+                .json(&serde_json::json!({
+                    "chat_id": self.config.chat_id,
+                    "text": text,
+                    "disable_web_page_preview": true,
+                }))
+                .send()
+                .await
+                // reqwest puts the URL, and so the token, in its errors
+                .map_err(|e| DeliveryError::Transient(e.without_url().to_string()))?;
+            let status = response.status();
+            if status.is_success() {
+                return Ok(());
+            }
+            let description = response.json::<TelegramReply>().await.ok()
+                .and_then(|reply| reply.description)
+                .unwrap_or_default();
+            let error = format!("HTTP {} {}", status, description).trim_end().to_string();
+            if status.is_server_error() || status.as_u16() == 429 {
+                Err(DeliveryError::Transient(error))
+            } else {
+                Err(DeliveryError::Permanent(error))
+            }
+        })
+    }
+}
+
+// ============================================================================
+// DELIVERY
+// ============================================================================
+
+#[derive(Debug, Clone, Default)]
+pub struct NotifyConfig {
+    pub webhook_urls: Vec<String>,
+    // Signs webhook payloads; from the environment only
+    pub webhook_secret: Option<String>,
+    pub telegram: Option<TelegramConfig>,
+}
+
+impl NotifyConfig {
+    pub fn channels(&self, network: &Network) -> Vec<Box<dyn Channel>> {
+        let secret = self.webhook_secret.as_deref().unwrap_or_default();
+        let mut channels: Vec<Box<dyn Channel>> = self.webhook_urls.iter()
+            .map(|url| Box::new(Webhook::new(url, secret)) as Box<dyn Channel>)
+            .collect();
+        if let Some(telegram) = &self.telegram {
+            channels.push(Box::new(Telegram::new(telegram.clone(), network)));
+        }
+        channels
+    }
+}
+
+// Delivers notifications in the background, each to every channel, retrying
+// failures with backoff. The vault holds a sender; finish() once it is done.
+pub struct Notifier {
+    sender: UnboundedSender<Notification>,
+    worker: JoinHandle<()>,
+}
+
+impl Notifier {
+    // None when no channel is configured
+    pub fn start(config: &NotifyConfig, network: &Network) -> Option<Notifier> {
+        let channels: Vec<Arc<dyn Channel>> = config.channels(network).into_iter().map(Arc::from).collect();
+        if channels.is_empty() {
+            return None;
+        }
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let worker = tokio::spawn(run(channels, receiver));
+        Some(Notifier { sender, worker })
+    }
+
+    pub fn sender(&self) -> UnboundedSender<Notification> {
+        self.sender.clone()
+    }
+
+    // Waits a while for deliveries in flight. Every other sender has to be
+    // dropped first, or this waits out the whole timeout.
+    pub async fn finish(self) {
+        drop(self.sender);
+        if tokio::time::timeout(FLUSH_TIMEOUT, self.worker).await.is_err() {
+            say!("⚠️  Stopped waiting for notifications still being delivered");
+        }
+    }
+}
+
+async fn run(channels: Vec<Arc<dyn Channel>>, mut notifications: UnboundedReceiver<Notification>) {
+    let mut deliveries = JoinSet::new();
+    while let Some(notification) = notifications.recv().await {
+        let message = Arc::new(Message::new(notification));
+        for channel in channels.iter().filter(|channel| channel.wants(&message.notification)) {
+            deliveries.spawn(deliver(channel.clone(), message.clone()));
+        }
+        while deliveries.try_join_next().is_some() {}
+    }
+    while deliveries.join_next().await.is_some() {}
+}
+
+async fn deliver(channel: Arc<dyn Channel>, message: Arc<Message>) {
+    let target = channel.target();
+    let kind = message.notification.kind();
+    let mut wait = FIRST_RETRY;
+    for attempt in 1..=DELIVERY_ATTEMPTS {
+        match channel.send(&message).await {
+            Ok(()) => {
+                tracing::info!(id = %message.id, kind, target = %target, attempt, "notification delivered");
+                return;
+            }
+            Err(DeliveryError::Transient(e)) if attempt < DELIVERY_ATTEMPTS => {
+                tracing::warn!(id = %message.id, kind, target = %target, attempt, error = %e,
+                    retry_in_secs = wait.as_secs(), "notification delivery failed");
+                tokio::time::sleep(wait).await;
+                wait *= 2;
+            }
+            Err(e) => {
+                tracing::error!(id = %message.id, kind, target = %target, attempt, error = %e, "notification not delivered");
+                say!("⚠️  Could not deliver the {} notification to {}: {}", kind, target, e);
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signs_the_timestamp_and_body() {
+        let webhook = Webhook::new("https://hooks.example.com/T0KEN", "whsec");
+        let message = Message::new(Notification::CircuitBreakerTripped { risk: RiskLevel::High, reason: "share price moved".into() });
+        let body = serde_json::to_string(&message).unwrap();
+        let payload: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(payload["type"], "circuit_breaker_tripped");
+        assert_eq!(payload["id"], message.id.as_str());
+
+        let signature = webhook.signature(1_700_000_000, &body);
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"whsec").unwrap();
+        mac.update(format!("1700000000.{}", body).as_bytes());
+        assert_eq!(signature, format!("t=1700000000,v1={}", hex(&mac.finalize().into_bytes())));
+        assert_ne!(signature, webhook.signature(1_700_000_001, &body));
+        assert_eq!(webhook.target(), "webhook hooks.example.com");
+    }
+
+    #[test]
+    fn telegram_only_tells_the_account_about_its_own_activity() {
+        let config = TelegramConfig { bot_token: "123:ABC".into(), chat_id: "42".into(), account: "GME".into() };
+        let telegram = Telegram::new(config, &Network::Testnet);
+        let deposit = |user: &str| Notification::DepositConfirmed {
+            user: user.into(), risk: RiskLevel::Low, asset: "XLM".into(),
+            amount_stroops: 250_000_000, shares_minted: 248_750_000, tx_hash: "abc".into(),
+        };
+        let text = telegram.text(&deposit("GME")).unwrap();
+        assert!(text.starts_with("✅ Deposit confirmed: 25.0000000 XLM into the Low Risk Vault"));
+        assert!(text.ends_with(&Network::Testnet.tx_link("abc")));
+        assert!(!telegram.wants(&deposit("GSOMEONEELSE")));
+
+        let credit = |user: &str| YieldCredit { user: user.into(), amount_stroops: 1_000_000, compounded: true, shares: 990_000 };
+        let harvest = Notification::Harvest {
+            risk: RiskLevel::Medium, asset: "XLM".into(), yield_stroops: 2_000_000, performance_fee: 200_000,
+            credits: vec![credit("GSOMEONEELSE"), credit("GME")],
+        };
+        assert!(telegram.text(&harvest).unwrap().contains("0.1000000 XLM of yield from the Medium Risk Vault compounded into 990000 shares"));
+        assert!(!telegram.wants(&Notification::CircuitBreakerTripped { risk: RiskLevel::Low, reason: "moved".into() }));
+        assert!(!telegram.target().contains("ABC"));
+    }
+}
@@ -0,0 +1,182 @@
+use stellar_xdr::curr::{
+    Asset, Limits, OperationBody, Preconditions, ReadXdr, SetOptionsOp, SignerKey, Transaction,
+    TransactionEnvelope, TransactionV1Envelope,
+};
+
+use crate::amount::Stroops;
+use crate::error::VaultError;
+use crate::signer::Signer;
+use crate::transaction;
+
+// ============================================================================
+// OFFLINE SIGNING
+// ============================================================================
+
+// Transactions travel between machines as base64 TransactionEnvelope XDR:
+// built unsigned where Horizon is reachable, signed where the key lives, and
+// submitted later from anywhere online.
+
+pub fn unsigned(transaction: Transaction) -> TransactionEnvelope {
+    TransactionEnvelope::Tx(TransactionV1Envelope {
+        tx: transaction,
+        signatures: Default::default(),
+    })
+}
+
+pub fn decode(xdr: &str) -> Result<TransactionEnvelope, VaultError> {
+    // Pasted XDR often picks up line breaks
+    let xdr: String = xdr.split_whitespace().collect();
+    TransactionEnvelope::from_xdr_base64(xdr, Limits::none())
+        .map_err(|e| format!("Not a transaction envelope: {}", e).into())
+}
+
+fn inner(envelope: &TransactionEnvelope) -> Result<&TransactionV1Envelope, VaultError> {
+    match envelope {
+        TransactionEnvelope::Tx(inner) => Ok(inner),
+        _ => Err("Only v1 transaction envelopes are supported".into()),
+    }
+}
+
+// The hash signers commit to; it depends on the network passphrase
+pub fn hash(envelope: &TransactionEnvelope, passphrase: &str) -> Result<String, VaultError> {
+    transaction::hash_hex(&inner(envelope)?.tx, passphrase)
+}
+
+pub fn signature_count(envelope: &TransactionEnvelope) -> usize {
+    match envelope {
+        TransactionEnvelope::TxV0(e) => e.signatures.len(),
+        TransactionEnvelope::Tx(e) => e.signatures.len(),
+        TransactionEnvelope::TxFeeBump(e) => e.signatures.len(),
+    }
+}
+
+// Adds a signature, keeping any already there
+pub fn sign(envelope: TransactionEnvelope, passphrase: &str, signer: &dyn Signer) -> Result<TransactionEnvelope, VaultError> {
+    let TransactionEnvelope::Tx(mut inner) = envelope else {
+        return Err("Only v1 transaction envelopes are supported".into());
+    };
+    let signature = signer.sign_transaction(&inner.tx, passphrase)?;
+    if inner.signatures.iter().any(|s| s.hint == signature.hint && s.signature == signature.signature) {
+        return Err(format!("Already signed by {}", signer.public_key()).into());
+    }
+
+    let mut signatures = inner.signatures.to_vec();
+    signatures.push(signature);
+    inner.signatures = signatures.try_into()
+        .map_err(|_| "A transaction holds at most 20 signatures")?;
+    Ok(TransactionEnvelope::Tx(inner))
+}
+
+fn describe_asset(asset: &Asset) -> String {
+    match asset {
+        Asset::Native => "XLM".to_string(),
+        Asset::CreditAlphanum4(a) => format!("{}:{}", a.asset_code, a.issuer),
+        Asset::CreditAlphanum12(a) => format!("{}:{}", a.asset_code, a.issuer),
+    }
+}
+
+// Human-readable lines to check before signing or submitting
+pub fn describe(envelope: &TransactionEnvelope, passphrase: &str) -> Result<Vec<String>, VaultError> {
+    let tx = &inner(envelope)?.tx;
+    let mut lines = vec![
+        format!("Hash: {}", hash(envelope, passphrase)?),
+        format!("Source: {}", tx.source_account),
+        format!("Sequence: {}", tx.seq_num.0),
+        format!("Max Fee: {} stroops", tx.fee),
+    ];
+
+    if let Some(memo) = transaction::describe_memo(&tx.memo) {
+        lines.push(format!("Memo: {}", memo));
+    }
+
+    let (time_bounds, ledger_bounds) = match &tx.cond {
+        Preconditions::None => (None, None),
+        Preconditions::Time(bounds) => (Some(bounds), None),
+        Preconditions::V2(conditions) => (conditions.time_bounds.as_ref(), conditions.ledger_bounds.as_ref()),
+    };
+    if let Some(bounds) = time_bounds {
+        lines.push(format!("Valid: unix {} to {}", bounds.min_time.0,
+            if bounds.max_time.0 == 0 { "no expiry".to_string() } else { bounds.max_time.0.to_string() }));
+    }
+    if let Some(bounds) = ledger_bounds {
+        lines.push(format!("Ledgers: {} to {}", bounds.min_ledger, bounds.max_ledger));
+    }
+
+    for (i, operation) in tx.operations.iter().enumerate() {
+        let source = operation.source_account.as_ref()
+            .map(|account| format!(" (source {})", account))
+            .unwrap_or_default();
+        let detail = match &operation.body {
+            OperationBody::Payment(payment) => {
+                let amount = match payment.asset {
+                    Asset::Native => Stroops(payment.amount as u64).to_string(),
+                    _ => format!("{} {}", Stroops(payment.amount as u64).to_xlm_string(), describe_asset(&payment.asset)),
+                };
+                format!("pay {} to {}", amount, payment.destination)
+            }
+            OperationBody::SetOptions(options) => describe_options(options),
+            body => body.name().to_string(),
+        };
+        lines.push(format!("Operation {}: {}{}", i + 1, detail, source));
+    }
+
+    lines.push(format!("Signatures: {}", signature_count(envelope)));
+    Ok(lines)
+}
+
+// Signer and threshold changes in full; other options by name
+fn describe_options(options: &SetOptionsOp) -> String {
+    let mut changes = Vec::new();
+    if let Some(signer) = &options.signer {
+        let key = match &signer.key {
+            SignerKey::Ed25519(key) => stellar_strkey::ed25519::PublicKey(key.0).to_string(),
+            _ => "a non-ed25519 signer".to_string(),
+        };
+        changes.push(match signer.weight {
+            0 => format!("remove signer {}", key),
+            weight => format!("set signer {} to weight {}", key, weight),
+        });
+    }
+    if let Some(weight) = options.master_weight {
+        changes.push(format!("set master key weight to {}", weight));
+    }
+    for (name, threshold) in [("low", options.low_threshold), ("medium", options.med_threshold), ("high", options.high_threshold)] {
+        if let Some(threshold) = threshold {
+            changes.push(format!("set {} threshold to {}", name, threshold));
+        }
+    }
+    if options.inflation_dest.is_some() || options.clear_flags.is_some() || options.set_flags.is_some() || options.home_domain.is_some() {
+        changes.push("change account flags or home domain".to_string());
+    }
+    if changes.is_empty() {
+        return "set options (no changes)".to_string();
+    }
+    changes.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::{Keypair, TransactionBuilder};
+
+    const SECRET: &str = "SBGWSG6BTNCKCOB3DIFBGCVMUPQFYPA2G4O34RMTB343OYPXU5DJDVMN";
+    const DESTINATION: &str = "GCZEAWUJY3BRHCOKU6C5WRLCF5RFSGY22UGBPBXWL4T4G4SSEQMIYMCX";
+
+    #[test]
+    fn signing_offline_matches_signing_online() {
+        let keypair = Keypair::from_secret(SECRET).unwrap();
+        let passphrase = "Test SDF Network ; September 2015";
+        let build = || TransactionBuilder::new(&keypair.public_key(), 7).unwrap()
+            .payment(DESTINATION, Asset::Native, 10_000_000).unwrap()
+            .build().unwrap();
+
+        // Through base64 and back, with line breaks from pasting
+        let exported = transaction::to_base64(&unsigned(build())).unwrap();
+        let wrapped = format!("{}\n{}", &exported[..40], &exported[40..]);
+        let signed = sign(decode(&wrapped).unwrap(), passphrase, &keypair).unwrap();
+
+        assert_eq!(signature_count(&signed), 1);
+        assert_eq!(signed, transaction::sign(build(), passphrase, &[&keypair]).unwrap());
+        assert!(sign(signed, passphrase, &keypair).is_err());
+    }
+}
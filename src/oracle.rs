@@ -0,0 +1,313 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use stellar_xdr::curr::ScVal;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+
+use crate::amount::{mul_div, Rounding, STROOPS_PER_XLM};
+use crate::assets::AssetId;
+use crate::error::VaultError;
+use crate::soroban::{self, RpcClient};
+use crate::unix_now;
+
+// USD prices and values carry 7 decimals, like amounts
+pub const USD_SCALE: u64 = STROOPS_PER_XLM;
+pub const DEFAULT_INTERVAL_SECS: u64 = 60;
+pub const DEFAULT_MAX_AGE_SECS: u64 = 900;
+
+// "$1234.56"
+pub fn format_usd(value: u64) -> String {
+    format!("${}.{:02}", value / USD_SCALE, value % USD_SCALE / (USD_SCALE / 100))
+}
+
+// ============================================================================
+// PRICE SOURCES
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quote {
+    // USD per whole unit of the asset
+    pub usd: u64,
+    // When the source priced it, in seconds
+    pub timestamp: u64,
+}
+
+pub type PriceFuture<'a> = Pin<Box<dyn Future<Output = Result<Option<Quote>, VaultError>> + Send + 'a>>;
+
+// Prices assets in USD. `price` resolves to None when the source doesn't
+// cover the asset; `asset` None is XLM.
+pub trait PriceSource: Send + Sync {
+    fn name(&self) -> String;
+    fn price<'a>(&'a self, asset: Option<&'a AssetId>) -> PriceFuture<'a>;
+}
+
+// How a Reflector contract names the assets it prices
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReflectorFeed {
+    // Stellar assets by their asset contract (the Stellar DEX feed)
+    Stellar,
+    // Tickers as symbols, "XLM" or "USDC" (the exchanges feed)
+    Other,
+}
+
+impl ReflectorFeed {
+    pub fn parse(feed: &str) -> Result<ReflectorFeed, VaultError> {
+        match feed.to_lowercase().as_str() {
+            "stellar" => Ok(ReflectorFeed::Stellar),
+            "other" => Ok(ReflectorFeed::Other),
+            _ => Err(VaultError::Validation(format!("Unknown Reflector feed {:?} (expected stellar or other)", feed))),
+        }
+    }
+}
+
+// A Reflector oracle contract (SEP-40), read through simulation. Its quote
+// asset is taken as USD.
+pub struct Reflector {
+    rpc: RpcClient,
+    contract: String,
+    feed: ReflectorFeed,
+    // Account the reads are simulated as
+    source_account: String,
+    passphrase: String,
+    decimals: Mutex<Option<u32>>,
+}
+
+impl Reflector {
+    pub fn new(rpc: RpcClient, contract: &str, feed: ReflectorFeed, source_account: &str, passphrase: &str) -> Result<Self, VaultError> {
+        soroban::contract_id(contract)?;
+        Ok(Reflector {
+            rpc,
+            contract: contract.to_string(),
+            feed,
+            source_account: source_account.to_string(),
+            passphrase: passphrase.to_string(),
+            decimals: Mutex::new(None),
+        })
+    }
+
+    fn asset_arg(&self, asset: Option<&AssetId>) -> Result<ScVal, VaultError> {
+        match self.feed {
+            ReflectorFeed::Stellar => {
+                let xdr_asset = match asset {
+                    Some(asset) => asset.to_xdr()?,
+                    None => stellar_xdr::curr::Asset::Native,
+                };
+                let token = soroban::asset_contract(&xdr_asset, &self.passphrase)?;
+                soroban::vec(vec![soroban::symbol("Stellar")?, soroban::address(&token)?])
+            }
+            ReflectorFeed::Other => {
+                let code = asset.map_or("XLM", |asset| asset.code.as_str());
+                soroban::vec(vec![soroban::symbol("Other")?, soroban::symbol(code)?])
+            }
+        }
+    }
+
+    async fn decimals(&self) -> Result<u32, VaultError> {
+        if let Some(decimals) = *self.decimals.lock().unwrap_or_else(PoisonError::into_inner) {
+            return Ok(decimals);
+        }
+        let decimals = match self.rpc.read(&self.source_account, soroban::invoke(&self.contract, "decimals", vec![])?).await? {
+            ScVal::U32(decimals) => decimals,
+            other => return Err(format!("Oracle {} returned {} for decimals", self.contract, soroban::describe(&other)).into()),
+        };
+        *self.decimals.lock().unwrap_or_else(PoisonError::into_inner) = Some(decimals);
+        Ok(decimals)
+    }
+}
+
+// Reflector's PriceData: {price: i128, timestamp: u64}, or () when the asset
+// has no price yet
+pub fn parse_price(value: &ScVal, decimals: u32) -> Result<Option<Quote>, VaultError> {
+    if *value == ScVal::Void {
+        return Ok(None);
+    }
+    let price = soroban::field(value, "price").and_then(soroban::to_i128)
+        .ok_or("Oracle returned a price without a value")?;
+    let timestamp = match soroban::field(value, "timestamp") {
+        Some(ScVal::U64(timestamp)) => *timestamp,
+        _ => return Err("Oracle returned a price without a timestamp".into()),
+    };
+    let scale = 10u128.checked_pow(decimals).ok_or("Oracle reports too many decimals")?;
+    let usd = u128::try_from(price).ok()
+        .map(|price| price * USD_SCALE as u128 / scale)
+        .and_then(|usd| u64::try_from(usd).ok())
+        .ok_or_else(|| format!("Oracle price {} is out of range", price))?;
+    // Normalised in case a feed reports milliseconds
+    let timestamp = if timestamp > 100_000_000_000 { timestamp / 1_000 } else { timestamp };
+    Ok(Some(Quote { usd, timestamp }))
+}
+
+impl PriceSource for Reflector {
+    fn name(&self) -> String {
+        format!("Reflector {}", self.contract)
+    }
+
+    fn price<'a>(&'a self, asset: Option<&'a AssetId>) -> PriceFuture<'a> {
+        Box::pin(async move {
+            let decimals = self.decimals().await?;
+            let operation = soroban::invoke(&self.contract, "lastprice", vec![self.asset_arg(asset)?])?;
+            parse_price(&self.rpc.read(&self.source_account, operation).await?, decimals)
+        })
+    }
+}
+
+// Fixed prices by asset code or CODE:ISSUER, for testnet, demos and as a
+// last resort when the oracle can't be reached. They never go stale.
+#[derive(Debug, Clone, Default)]
+pub struct MockPrices {
+    prices: BTreeMap<String, u64>,
+}
+
+impl MockPrices {
+    pub fn new(prices: BTreeMap<String, u64>) -> Self {
+        MockPrices { prices }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prices.is_empty()
+    }
+
+    pub fn get(&self, asset: Option<&AssetId>) -> Option<u64> {
+        match asset {
+            Some(asset) => self.prices.get(&asset.to_string()).or_else(|| self.prices.get(&asset.code)).copied(),
+            None => self.prices.get("XLM").copied(),
+        }
+    }
+}
+
+impl PriceSource for MockPrices {
+    fn name(&self) -> String {
+        "mock prices".to_string()
+    }
+
+    fn price<'a>(&'a self, asset: Option<&'a AssetId>) -> PriceFuture<'a> {
+        let quote = self.get(asset).map(|usd| Quote { usd, timestamp: unix_now() });
+        Box::pin(async move { Ok(quote) })
+    }
+}
+
+// ============================================================================
+// PRICE BOOK
+// ============================================================================
+
+fn key(asset: Option<&AssetId>) -> String {
+    asset.map_or("XLM".to_string(), |asset| asset.to_string())
+}
+
+// The latest USD price of each vault asset and where it came from. Prices
+// older than the max age aren't used, so USD limits fail closed.
+#[derive(Debug, Clone)]
+pub struct PriceBook {
+    pub max_age_secs: u64,
+    quotes: BTreeMap<String, (Quote, String)>,
+}
+
+impl Default for PriceBook {
+    fn default() -> Self {
+        PriceBook { max_age_secs: DEFAULT_MAX_AGE_SECS, quotes: BTreeMap::new() }
+    }
+}
+
+impl PriceBook {
+    pub fn record(&mut self, asset: Option<&AssetId>, quote: Quote, source: &str) {
+        self.quotes.insert(key(asset), (quote, source.to_string()));
+    }
+
+    // The asset's price and its source, if fresh
+    pub fn quote(&self, asset: Option<&AssetId>, now: u64) -> Option<(Quote, &str)> {
+        self.quotes.get(&key(asset))
+            .filter(|(quote, _)| now.saturating_sub(quote.timestamp) <= self.max_age_secs)
+            .map(|(quote, source)| (*quote, source.as_str()))
+    }
+
+    // What `amount` of the asset is worth in USD at its fresh price
+    pub fn usd_value(&self, asset: Option<&AssetId>, amount: u64, now: u64) -> Option<u64> {
+        let (quote, _) = self.quote(asset, now)?;
+        mul_div(amount, quote.usd, USD_SCALE, Rounding::Down).ok()
+    }
+}
+
+// ============================================================================
+// PRICE FEED
+// ============================================================================
+
+pub struct PriceUpdate {
+    pub asset: Option<AssetId>,
+    // The quote and the source it came from
+    pub result: Result<(Quote, String), String>,
+}
+
+// Prices every asset now and then every `interval`, in the background,
+// falling back to the mock prices when the oracle fails or has no price.
+// Dropping the receiver stops the task.
+pub fn spawn(oracle: Option<Box<dyn PriceSource>>, fallback: MockPrices, assets: Vec<Option<AssetId>>, interval: Duration) -> mpsc::UnboundedReceiver<PriceUpdate> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let oracle: Option<Arc<dyn PriceSource>> = oracle.map(Arc::from);
+
+    tokio::spawn(async move {
+        loop {
+            // Every asset is priced at once and reported as its price comes in
+            let mut quotes = JoinSet::new();
+            for asset in &assets {
+                let (oracle, asset) = (oracle.clone(), asset.clone());
+                quotes.spawn(async move {
+                    let from_oracle = match &oracle {
+                        Some(oracle) => oracle.price(asset.as_ref()).await
+                            .map(|quote| quote.map(|quote| (quote, oracle.name())))
+                            .map_err(|e| format!("{}: {}", oracle.name(), e)),
+                        None => Ok(None),
+                    };
+                    (asset, from_oracle)
+                });
+            }
+            while let Some(quoted) = quotes.join_next().await {
+                let Ok((asset, from_oracle)) = quoted else { continue };
+                let mock = |usd| Quote { usd, timestamp: unix_now() };
+                let result = match (from_oracle, fallback.get(asset.as_ref())) {
+                    (Ok(Some(priced)), _) => Ok(priced),
+                    (Ok(None), Some(usd)) => Ok((mock(usd), fallback.name())),
+                    (Err(e), Some(usd)) => Ok((mock(usd), format!("{} after {}", fallback.name(), e))),
+                    (Ok(None), None) => Err(format!("no price for {}", key(asset.as_ref()))),
+                    (Err(e), None) => Err(e),
+                };
+                if sender.send(PriceUpdate { asset, result }).is_err() {
+                    return;
+                }
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reflector_prices_value_assets_in_usd() {
+        let price_data = soroban::record(vec![
+            ("price", soroban::i128(12_345_000_000_000)),
+            ("timestamp", ScVal::U64(1_700_000_000_000)),
+        ]).unwrap();
+        let quote = parse_price(&price_data, 14).unwrap().unwrap();
+        assert_eq!(quote, Quote { usd: 1_234_500, timestamp: 1_700_000_000 });
+        assert_eq!(parse_price(&ScVal::Void, 14).unwrap(), None);
+
+        let usdc = AssetId::parse("USDC:GBBD47IF6LWK7P7MDEVSCWR7DPUWV3NY3DTQEVFL4NAT4AQH3ZLLFLA5").unwrap();
+        let mut book = PriceBook::default();
+        book.record(None, quote, "Reflector");
+        assert_eq!(book.usd_value(None, 1_000 * STROOPS_PER_XLM, quote.timestamp + 60), Some(1_234_500_000));
+        assert_eq!(format_usd(book.usd_value(None, 1_000 * STROOPS_PER_XLM, quote.timestamp).unwrap()), "$123.45");
+        assert_eq!(book.usd_value(None, STROOPS_PER_XLM, quote.timestamp + DEFAULT_MAX_AGE_SECS + 1), None);
+        assert_eq!(book.usd_value(Some(&usdc), STROOPS_PER_XLM, quote.timestamp), None);
+
+        let mock = MockPrices::new(BTreeMap::from([("USDC".to_string(), USD_SCALE)]));
+        assert_eq!(mock.get(Some(&usdc)), Some(USD_SCALE));
+        assert_eq!(mock.get(None), None);
+    }
+}
@@ -0,0 +1,55 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+// ============================================================================
+// OUTPUT MODE
+// ============================================================================
+
+// With --output json a command prints one JSON document to stdout, its
+// result or {"error": ...}, and everything meant for people goes to stderr
+static JSON: AtomicBool = AtomicBool::new(false);
+
+pub fn set_json(json: bool) {
+    JSON.store(json, Ordering::Relaxed);
+}
+
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+// While the TUI owns the terminal, messages are kept for its activity panel
+// instead of being printed over it
+static CAPTURED: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+pub fn capture(on: bool) {
+    *CAPTURED.lock().unwrap() = on.then(Vec::new);
+}
+
+// Messages said since the last call, oldest first
+pub fn take_captured() -> Vec<String> {
+    CAPTURED.lock().unwrap().as_mut().map(std::mem::take).unwrap_or_default()
+}
+
+// say! lines in the log, kept off the console that printed them
+pub const LOG_TARGET: &str = "stellarvault::output";
+
+// Backs say!
+pub fn say(args: fmt::Arguments) {
+    tracing::info!(target: LOG_TARGET, "{}", args);
+    if let Some(captured) = CAPTURED.lock().unwrap().as_mut() {
+        captured.push(args.to_string());
+    } else if is_json() {
+        eprintln!("{}", args);
+    } else {
+        println!("{}", args);
+    }
+}
+
+// Prints a command's result in JSON mode; the human-readable version has
+// already been said
+pub fn emit(result: &serde_json::Value) {
+    if is_json() {
+        println!("{}", result);
+    }
+}
@@ -0,0 +1,106 @@
+use stellar_xdr::curr::{Asset, OperationBody};
+
+use crate::amount::{Rounding, Stroops};
+use crate::assets::AssetId;
+use crate::error::VaultError;
+use crate::horizon::HorizonClient;
+use crate::transaction;
+
+// How far below the quoted amount a conversion may land, in basis points
+pub const DEFAULT_MAX_SLIPPAGE_BPS: u16 = 100;
+pub const MAX_SLIPPAGE_BPS: u16 = 5_000;
+
+// ============================================================================
+// PATH PAYMENTS
+// ============================================================================
+
+// The best route Horizon found for turning one asset into another. Assets
+// are None for XLM.
+#[derive(Debug, Clone)]
+pub struct Quote {
+    pub send_asset: Option<AssetId>,
+    pub send_amount: Stroops,
+    pub dest_asset: Option<AssetId>,
+    pub expected: Stroops,
+    // The least the payment may deliver before the network rejects it
+    pub min_received: Stroops,
+    pub path: Vec<Option<AssetId>>,
+}
+
+impl Quote {
+    // Destination units per source unit
+    pub fn rate(&self) -> f64 {
+        self.expected.0 as f64 / self.send_amount.0 as f64
+    }
+
+    pub fn path_xdr(&self) -> Result<Vec<Asset>, VaultError> {
+        self.path.iter().map(|asset| to_xdr(asset.as_ref())).collect()
+    }
+
+    // The conversion as one operation paying `destination`, which may be
+    // the source account itself
+    pub fn operation(&self, destination: &str) -> Result<OperationBody, VaultError> {
+        transaction::path_payment_strict_send(destination, to_xdr(self.send_asset.as_ref())?, self.send_amount.0,
+            to_xdr(self.dest_asset.as_ref())?, self.min_received.0, self.path_xdr()?)
+    }
+}
+
+pub fn to_xdr(asset: Option<&AssetId>) -> Result<Asset, VaultError> {
+    match asset {
+        Some(asset) => asset.to_xdr(),
+        None => Ok(Asset::Native),
+    }
+}
+
+// "native" or CODE:ISSUER, as Horizon's query parameters take it
+pub fn canonical(asset: Option<&AssetId>) -> String {
+    asset.map_or("native".to_string(), AssetId::to_string)
+}
+
+// "XLM" or the asset code, for display
+pub fn label(asset: Option<&AssetId>) -> &str {
+    asset.map_or("XLM", |asset| asset.code.as_str())
+}
+
+pub fn min_received(expected: Stroops, slippage_bps: u16) -> Result<Stroops, VaultError> {
+    if slippage_bps > MAX_SLIPPAGE_BPS {
+        return Err(VaultError::Validation(format!("Slippage of {} bps is above the {} bps limit", slippage_bps, MAX_SLIPPAGE_BPS)));
+    }
+    Ok(expected.checked_sub(expected.bps(slippage_bps, Rounding::Down)?)?)
+}
+
+pub async fn quote(horizon: &HorizonClient, send_asset: Option<&AssetId>, send_amount: Stroops,
+                   dest_asset: Option<&AssetId>, slippage_bps: u16) -> Result<Quote, VaultError> {
+    let paths = horizon.strict_send_paths(&canonical(send_asset), &send_amount.to_xlm_string(), &canonical(dest_asset)).await?;
+
+    let (expected, best) = paths.into_iter()
+        .filter_map(|path| Stroops::from_xlm_str(&path.destination_amount).ok().map(|amount| (amount, path)))
+        .max_by_key(|(amount, _)| *amount)
+        .ok_or_else(|| VaultError::Validation(format!("No market converts {} to {} right now", label(send_asset), label(dest_asset))))?;
+    let path = best.path.iter()
+        .map(|hop| AssetId::from_horizon(&hop.asset_type, hop.asset_code.as_deref(), hop.asset_issuer.as_deref()))
+        .collect::<Result<_, _>>()?;
+
+    Ok(Quote {
+        send_asset: send_asset.cloned(),
+        send_amount,
+        dest_asset: dest_asset.cloned(),
+        expected,
+        min_received: min_received(expected, slippage_bps)?,
+        path,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slippage_lowers_the_minimum_and_is_capped() {
+        assert_eq!(min_received(Stroops(10_000_000), 100).unwrap(), Stroops(9_900_000));
+        assert_eq!(min_received(Stroops(10_000_000), 0).unwrap(), Stroops(10_000_000));
+        // Rounding keeps the minimum on the user's side
+        assert_eq!(min_received(Stroops(999), 100).unwrap(), Stroops(990));
+        assert!(min_received(Stroops(10_000_000), MAX_SLIPPAGE_BPS + 1).is_err());
+    }
+}
@@ -0,0 +1,251 @@
+use serde::{Deserialize, Serialize};
+
+use crate::RiskLevel;
+use crate::error::VaultError;
+
+// ============================================================================
+// TWO-PHASE DEPOSITS
+// ============================================================================
+
+// A deposit is recorded as Intended before its payment is sent, then moves
+// PaymentSent -> Confirmed -> SharesMinted. If minting fails
+// once the payment is confirmed it moves to RefundPending instead, and then to
+// Refunded once the XLM has been sent back. Unconfirmed payments are never
+// refunded, since the vault may not have received them; one still unknown
+// after its transaction's time bounds have passed can never be applied, and
+// moves to Expired, as does one whose payment was never sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DepositStage {
+    Intended,
+    PaymentSent,
+    Confirmed,
+    SharesMinted,
+    RefundPending,
+    Refunded,
+    Expired,
+}
+
+impl DepositStage {
+    pub fn is_settled(self) -> bool {
+        matches!(self, DepositStage::SharesMinted | DepositStage::Refunded | DepositStage::Expired)
+    }
+
+    // The vault may not have the XLM yet, so it is neither credited nor refunded
+    pub fn is_unconfirmed(self) -> bool {
+        matches!(self, DepositStage::Intended | DepositStage::PaymentSent)
+    }
+}
+
+// Text memo attached to a deposit's payment, so the on-chain payment can be
+// matched back to its record (28 bytes at most)
+pub fn deposit_memo(id: u64) -> String {
+    format!("SYIA deposit #{}", id)
+}
+
+pub fn parse_deposit_memo(memo: &str) -> Option<u64> {
+    memo.trim().strip_prefix("SYIA deposit #")?.parse().ok()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingDeposit {
+    pub id: u64,
+    pub user: String,
+    pub risk: RiskLevel,
+    pub amount_stroops: u64,
+    pub tx_hash: String,
+    #[serde(default)]
+    pub memo: String,
+    // 0 until Horizon reports the ledger the payment closed in
+    pub ledger: u32,
+    // Unix time the payment's transaction stops being valid; 0 if unbounded
+    #[serde(default)]
+    pub expires_at: u64,
+    // The latest ledger when an Intended deposit was recorded, so a search
+    // for its payment knows how far back to look; 0 if unknown
+    #[serde(default)]
+    pub since_ledger: u32,
+    pub stage: DepositStage,
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub shares_minted: u64,
+    pub failure: Option<String>,
+    pub refund_tx: Option<String>,
+    // Who paid, when that isn't `user`: XLM sent to a user's vault
+    // sub-address from elsewhere is refunded to where it came from
+    #[serde(default)]
+    pub sender: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DepositBook {
+    deposits: Vec<PendingDeposit>,
+    next_id: u64,
+}
+
+impl DepositBook {
+    // The id the next deposit will get, so its memo can go on the payment
+    // before the deposit is recorded
+    pub fn next_id(&self) -> u64 {
+        self.next_id + 1
+    }
+
+    // Records a deposit before its payment is sent, so a crash in between
+    // leaves something to look for
+    pub fn intend(&mut self, user: &str, risk: RiskLevel, amount_stroops: u64, since_ledger: u32, now: u64) -> u64 {
+        let id = self.begin(user, risk, amount_stroops, "", 0, now);
+        let last = self.deposits.len() - 1;
+        let deposit = &mut self.deposits[last];
+        deposit.stage = DepositStage::Intended;
+        deposit.since_ledger = since_ledger;
+        id
+    }
+
+    pub fn begin(&mut self, user: &str, risk: RiskLevel, amount_stroops: u64, tx_hash: &str, ledger: u32, now: u64) -> u64 {
+        self.next_id += 1;
+        self.deposits.push(PendingDeposit {
+            id: self.next_id,
+            user: user.to_string(),
+            risk,
+            amount_stroops,
+            tx_hash: tx_hash.to_string(),
+            memo: deposit_memo(self.next_id),
+            ledger,
+            expires_at: 0,
+            since_ledger: 0,
+            stage: DepositStage::PaymentSent,
+            created_at: now,
+            updated_at: now,
+            shares_minted: 0,
+            failure: None,
+            refund_tx: None,
+            sender: None,
+        });
+        self.next_id
+    }
+
+    pub fn get(&self, id: u64) -> Option<&PendingDeposit> {
+        self.deposits.iter().find(|d| d.id == id)
+    }
+
+    pub fn find_by_tx(&self, tx_hash: &str) -> Option<&PendingDeposit> {
+        self.deposits.iter().find(|d| d.tx_hash == tx_hash)
+    }
+
+    fn get_mut(&mut self, id: u64) -> Result<&mut PendingDeposit, VaultError> {
+        self.deposits.iter_mut().find(|d| d.id == id)
+            .ok_or_else(|| format!("Deposit #{} not found", id).into())
+    }
+
+    // Only forward transitions are allowed; a settled deposit never changes
+    pub fn advance(&mut self, id: u64, stage: DepositStage, now: u64) -> Result<(), VaultError> {
+        let deposit = self.get_mut(id)?;
+        let allowed = matches!(
+            (deposit.stage, stage),
+            (DepositStage::PaymentSent, DepositStage::Confirmed)
+                | (DepositStage::Confirmed, DepositStage::SharesMinted)
+                | (DepositStage::Confirmed, DepositStage::RefundPending)
+                | (DepositStage::RefundPending, DepositStage::Refunded)
+                | (DepositStage::PaymentSent, DepositStage::Expired)
+        );
+        if !allowed {
+            return Err(format!("Deposit #{} cannot move from {:?} to {:?}", id, deposit.stage, stage).into());
+        }
+
+        deposit.stage = stage;
+        deposit.updated_at = now;
+        Ok(())
+    }
+
+    // Records the transaction and ledger Horizon reports for a payment that
+    // was sent unconfirmed, or only intended when last saved
+    pub fn set_payment(&mut self, id: u64, tx_hash: &str, ledger: u32, now: u64) -> Result<(), VaultError> {
+        let deposit = self.get_mut(id)?;
+        if !deposit.stage.is_unconfirmed() {
+            return Err(format!("Deposit #{} is already {:?}", id, deposit.stage).into());
+        }
+        deposit.stage = DepositStage::PaymentSent;
+        deposit.tx_hash = tx_hash.to_string();
+        deposit.ledger = ledger;
+        deposit.updated_at = now;
+        Ok(())
+    }
+
+    pub fn set_sender(&mut self, id: u64, sender: &str) -> Result<(), VaultError> {
+        self.get_mut(id)?.sender = Some(sender.to_string());
+        Ok(())
+    }
+
+    pub fn set_expiry(&mut self, id: u64, expires_at: u64) -> Result<(), VaultError> {
+        self.get_mut(id)?.expires_at = expires_at;
+        Ok(())
+    }
+
+    // What a converting payment actually delivered, which is at least the
+    // quoted minimum recorded up front
+    pub fn set_amount(&mut self, id: u64, amount_stroops: u64) -> Result<(), VaultError> {
+        let deposit = self.get_mut(id)?;
+        if !deposit.stage.is_unconfirmed() {
+            return Err(format!("Deposit #{} is already {:?}", id, deposit.stage).into());
+        }
+        deposit.amount_stroops = amount_stroops;
+        Ok(())
+    }
+
+    pub fn mark_minted(&mut self, id: u64, shares: u64, now: u64) -> Result<(), VaultError> {
+        self.advance(id, DepositStage::SharesMinted, now)?;
+        self.get_mut(id)?.shares_minted = shares;
+        Ok(())
+    }
+
+    pub fn mark_failed(&mut self, id: u64, reason: &str, now: u64) -> Result<(), VaultError> {
+        self.advance(id, DepositStage::RefundPending, now)?;
+        self.get_mut(id)?.failure = Some(reason.to_string());
+        Ok(())
+    }
+
+    // Remembers a refund that was submitted but not confirmed, so a retry
+    // checks it before sending another
+    pub fn note_refund_sent(&mut self, id: u64, tx_hash: &str, now: u64) -> Result<(), VaultError> {
+        let deposit = self.get_mut(id)?;
+        if deposit.stage != DepositStage::RefundPending {
+            return Err(format!("Deposit #{} is {:?}, not awaiting a refund", id, deposit.stage).into());
+        }
+        deposit.refund_tx = Some(tx_hash.to_string());
+        deposit.updated_at = now;
+        Ok(())
+    }
+
+    pub fn mark_refunded(&mut self, id: u64, tx_hash: &str, now: u64) -> Result<(), VaultError> {
+        self.advance(id, DepositStage::Refunded, now)?;
+        self.get_mut(id)?.refund_tx = Some(tx_hash.to_string());
+        Ok(())
+    }
+
+    // Only for a payment Horizon doesn't know about once it can no longer land
+    pub fn mark_expired(&mut self, id: u64, now: u64) -> Result<(), VaultError> {
+        let deposit = self.get(id).ok_or_else(|| format!("Deposit #{} not found", id))?;
+        if deposit.ledger != 0 || deposit.expires_at == 0 || now <= deposit.expires_at {
+            return Err(format!("Deposit #{}'s payment can still be included", id).into());
+        }
+        self.advance(id, DepositStage::Expired, now)
+    }
+
+    // Only for an Intended deposit whose payment was never sent
+    pub fn abandon(&mut self, id: u64, reason: &str, now: u64) -> Result<(), VaultError> {
+        let deposit = self.get_mut(id)?;
+        if deposit.stage != DepositStage::Intended {
+            return Err(format!("Deposit #{} is {:?}, not waiting to be sent", id, deposit.stage).into());
+        }
+        deposit.stage = DepositStage::Expired;
+        deposit.failure = Some(reason.to_string());
+        deposit.updated_at = now;
+        Ok(())
+    }
+
+    pub fn unsettled(&self) -> Vec<PendingDeposit> {
+        self.deposits.iter()
+            .filter(|d| !d.stage.is_settled())
+            .cloned()
+            .collect()
+    }
+}
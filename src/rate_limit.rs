@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Past this many clients, ones whose buckets have refilled are forgotten
+const MAX_TRACKED: usize = 10_000;
+
+// ============================================================================
+// RATE LIMITING
+// ============================================================================
+
+// A token bucket per client: each holds a minute's worth of requests and
+// refills at the same rate, so a client can burst up to its limit and then
+// keeps to it on average.
+pub struct RateLimiter {
+    per_minute: u32,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(per_minute: u32) -> Self {
+        RateLimiter { per_minute, buckets: Mutex::new(HashMap::new()) }
+    }
+
+    fn refill_per_sec(&self) -> f64 {
+        self.per_minute as f64 / 60.0
+    }
+
+    fn refill(&self, bucket: &mut Bucket, now: Instant) {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec()).min(self.per_minute as f64);
+        bucket.updated = now;
+    }
+
+    // Spends one of `client`'s requests, or says how long until it has one
+    pub fn check(&self, client: &str, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED && !buckets.contains_key(client) {
+            buckets.retain(|_, bucket| {
+                self.refill(bucket, now);
+                bucket.tokens < self.per_minute as f64
+            });
+        }
+        let bucket = buckets.entry(client.to_string())
+            .or_insert(Bucket { tokens: self.per_minute as f64, updated: now });
+        self.refill(bucket, now);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.refill_per_sec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bursts_to_the_limit_then_refills() {
+        let limiter = RateLimiter::new(60);
+        let start = Instant::now();
+        for _ in 0..60 {
+            limiter.check("1.2.3.4", start).unwrap();
+        }
+        let wait = limiter.check("1.2.3.4", start).unwrap_err();
+        assert_eq!(wait.as_secs_f64().ceil(), 1.0);
+        // Other clients have buckets of their own
+        limiter.check("5.6.7.8", start).unwrap();
+
+        limiter.check("1.2.3.4", start + Duration::from_secs(1)).unwrap();
+        assert!(limiter.check("1.2.3.4", start + Duration::from_secs(1)).is_err());
+    }
+}
@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::VaultError;
+use crate::StrategyAllocation;
+
+// Drift tolerated when no threshold is given
+pub const DEFAULT_MAX_DRIFT_BPS: u64 = 100;
+
+// ============================================================================
+// DRIFT
+// ============================================================================
+
+#[derive(Debug, Clone)]
+pub struct StrategyDrift {
+    pub strategy: String,
+    pub current: u64,
+    pub target: u64,
+    // Positive when over-allocated, relative to the vault's strategy total
+    pub drift_bps: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RebalanceMove {
+    pub from: String,
+    pub to: String,
+    pub amount: u64,
+}
+
+// Unpaid yield still sits inside the strategy, so it counts towards its weight
+pub fn strategy_balance(strategy: &StrategyAllocation) -> u64 {
+    strategy.total_allocated + strategy.current_yield
+}
+
+pub fn compute_drift(strategies: &[StrategyAllocation]) -> Vec<StrategyDrift> {
+    let total: u64 = strategies.iter().map(strategy_balance).sum();
+
+    strategies.iter()
+        .map(|s| {
+            let current = strategy_balance(s);
+            let target = (total as u128 * s.allocation_percentage as u128 / 100) as u64;
+            let drift_bps = if total == 0 {
+                0
+            } else {
+                ((current as i128 - target as i128) * 10_000 / total as i128) as i64
+            };
+
+            StrategyDrift {
+                strategy: s.strategy.clone(),
+                current,
+                target,
+                drift_bps,
+            }
+        })
+        .collect()
+}
+
+pub fn max_drift_bps(drifts: &[StrategyDrift]) -> u64 {
+    drifts.iter().map(|d| d.drift_bps.unsigned_abs()).max().unwrap_or(0)
+}
+
+// ============================================================================
+// MOVES
+// ============================================================================
+
+// Greedily pairs over-allocated strategies with under-allocated ones so every
+// move has a single source and destination
+fn plan_moves(drifts: &[StrategyDrift]) -> Vec<(usize, usize, u64)> {
+    let mut surplus: Vec<(usize, u64)> = Vec::new();
+    let mut deficit: Vec<(usize, u64)> = Vec::new();
+
+    for (i, d) in drifts.iter().enumerate() {
+        if d.current > d.target {
+            surplus.push((i, d.current - d.target));
+        } else if d.target > d.current {
+            deficit.push((i, d.target - d.current));
+        }
+    }
+
+    let mut moves = Vec::new();
+    let (mut si, mut di) = (0, 0);
+    while si < surplus.len() && di < deficit.len() {
+        let amount = surplus[si].1.min(deficit[di].1);
+        if amount > 0 {
+            moves.push((surplus[si].0, deficit[di].0, amount));
+        }
+        surplus[si].1 -= amount;
+        deficit[di].1 -= amount;
+        if surplus[si].1 == 0 {
+            si += 1;
+        }
+        if deficit[di].1 == 0 {
+            di += 1;
+        }
+    }
+
+    moves
+}
+
+pub fn rebalance_strategies(strategies: &mut [StrategyAllocation]) -> Vec<RebalanceMove> {
+    let drifts = compute_drift(strategies);
+    let mut applied = Vec::new();
+
+    for (from, to, amount) in plan_moves(&drifts) {
+        let m = RebalanceMove {
+            from: strategies[from].strategy.clone(),
+            to: strategies[to].strategy.clone(),
+            amount,
+        };
+        transfer(strategies, from, to, amount);
+        applied.push(m);
+    }
+
+    applied
+}
+
+// Draws down principal first, then any unpaid yield held by the strategy
+fn transfer(strategies: &mut [StrategyAllocation], from: usize, to: usize, amount: u64) {
+    let source = &mut strategies[from];
+    let from_allocated = amount.min(source.total_allocated);
+    source.total_allocated -= from_allocated;
+    source.current_yield = source.current_yield.saturating_sub(amount - from_allocated);
+
+    strategies[to].total_allocated += amount;
+}
+
+// Re-applies a recorded move, e.g. when replaying the event log
+pub fn apply_move(strategies: &mut [StrategyAllocation], m: &RebalanceMove) -> Result<(), VaultError> {
+    let index_of = |id: &str| strategies.iter()
+        .position(|s| s.strategy == id)
+        .ok_or_else(|| format!("Vault has no {} strategy", id));
+    let from = index_of(&m.from)?;
+    let to = index_of(&m.to)?;
+    transfer(strategies, from, to, m.amount);
+    Ok(())
+}
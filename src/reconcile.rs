@@ -0,0 +1,110 @@
+use crate::amount::Stroops;
+use crate::RiskLevel;
+
+// A divergence smaller than this share of the local figure is rounding or
+// timing, not a reason to pause a vault
+pub const DEFAULT_TOLERANCE_BPS: u16 = 10;
+
+// ============================================================================
+// LEDGER RECONCILIATION
+// ============================================================================
+
+// What a figure adds up from, labelled, in stroops
+pub type Parts = Vec<(String, u64)>;
+
+// How an on-chain figure should relate to local accounting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expect {
+    // The ledger and the books hold the same number
+    Equal,
+    // The ledger holds at least what the books owe; any extra is the
+    // account's own funds, reserves or rounding dust
+    Covers,
+}
+
+// One figure read straight from the ledger, next to what local accounting
+// says it should be
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub label: String,
+    pub on_chain: u64,
+    pub local: u64,
+    pub expect: Expect,
+    // The vaults whose books the figure comes from
+    pub vaults: Vec<RiskLevel>,
+    // What each side is made of, when it is a sum
+    pub on_chain_parts: Parts,
+    pub local_parts: Parts,
+}
+
+impl Check {
+    pub fn equal(label: String, on_chain: u64, local: u64) -> Self {
+        Check::new(label, on_chain, local, Expect::Equal)
+    }
+
+    pub fn covers(label: String, on_chain: u64, local: u64) -> Self {
+        Check::new(label, on_chain, local, Expect::Covers)
+    }
+
+    fn new(label: String, on_chain: u64, local: u64, expect: Expect) -> Self {
+        Check { label, on_chain, local, expect, vaults: Vec::new(), on_chain_parts: Vec::new(), local_parts: Vec::new() }
+    }
+
+    pub fn for_vaults(mut self, vaults: Vec<RiskLevel>) -> Self {
+        self.vaults = vaults;
+        self
+    }
+
+    pub fn with_parts(mut self, on_chain: Parts, local: Parts) -> Self {
+        self.on_chain_parts = on_chain;
+        self.local_parts = local;
+        self
+    }
+
+    pub fn diverges(&self) -> bool {
+        match self.expect {
+            Expect::Equal => self.on_chain != self.local,
+            Expect::Covers => self.on_chain < self.local,
+        }
+    }
+
+    // Diverges by more than `tolerance_bps` of the local figure
+    pub fn is_material(&self, tolerance_bps: u16) -> bool {
+        let gap = match self.expect {
+            Expect::Equal => self.on_chain.abs_diff(self.local),
+            Expect::Covers => self.local.saturating_sub(self.on_chain),
+        };
+        gap > 0 && gap as u128 * 10_000 > tolerance_bps as u128 * self.local as u128
+    }
+
+    // On-chain minus local, as a decimal amount
+    pub fn difference(&self) -> String {
+        if self.on_chain >= self.local {
+            format!("+{}", Stroops(self.on_chain - self.local).to_xlm_string())
+        } else {
+            format!("-{}", Stroops(self.local - self.on_chain).to_xlm_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn surplus_only_diverges_when_figures_must_match() {
+        assert!(!Check::equal("shares".into(), 10, 10).diverges());
+        assert!(Check::equal("shares".into(), 11, 10).diverges());
+        assert!(!Check::covers("balance".into(), 11, 10).diverges());
+
+        let shortfall = Check::covers("balance".into(), 9, 10);
+        assert!(shortfall.diverges());
+        assert!(shortfall.difference().starts_with('-'));
+
+        // 1 short of 10,000 is 1 basis point
+        let dust = Check::covers("balance".into(), 9_999, 10_000);
+        assert!(dust.diverges() && !dust.is_material(DEFAULT_TOLERANCE_BPS));
+        assert!(dust.is_material(0));
+        assert!(Check::equal("shares".into(), 1, 0).is_material(DEFAULT_TOLERANCE_BPS));
+    }
+}
@@ -0,0 +1,230 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+
+use crate::error::VaultError;
+use crate::RiskLevel;
+
+const DAY_SECS: u64 = 86_400;
+const WEEKDAYS: [&str; 7] = ["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"];
+
+// ============================================================================
+// CADENCE
+// ============================================================================
+
+// When a plan runs, in UTC
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "every", rename_all = "snake_case")]
+pub enum Cadence {
+    Day { at_secs: u64 },
+    // Weekday 0 is Monday
+    Week { weekday: u8, at_secs: u64 },
+}
+
+impl Cadence {
+    // "daily", "monday" or "every monday", each with an optional "HH:MM"
+    pub fn parse(input: &str) -> Result<Cadence, VaultError> {
+        let invalid = || VaultError::Validation(format!(
+            "Invalid schedule (expected daily or a weekday such as monday, optionally with HH:MM in UTC): {}", input));
+        let lowered = input.trim().to_lowercase();
+        let mut words = lowered.split_whitespace().peekable();
+        if words.peek() == Some(&"every") {
+            words.next();
+        }
+        let day = words.next().ok_or_else(invalid)?;
+        let at_secs = match words.next() {
+            Some(time) => {
+                let (hours, minutes) = time.split_once(':').ok_or_else(invalid)?;
+                let hours: u64 = hours.parse().map_err(|_| invalid())?;
+                let minutes: u64 = minutes.parse().map_err(|_| invalid())?;
+                if hours > 23 || minutes > 59 {
+                    return Err(invalid());
+                }
+                hours * 3_600 + minutes * 60
+            }
+            None => 0,
+        };
+        if words.next().is_some() {
+            return Err(invalid());
+        }
+        if day == "daily" || day == "day" {
+            return Ok(Cadence::Day { at_secs });
+        }
+        // Three letters are enough: "mon", "tue"
+        let weekday = WEEKDAYS.iter()
+            .position(|name| day.len() >= 3 && name.starts_with(day))
+            .ok_or_else(invalid)?;
+        Ok(Cadence::Week { weekday: weekday as u8, at_secs })
+    }
+
+    // The first run strictly after `after`
+    pub fn next_after(&self, after: u64) -> u64 {
+        let day = after / DAY_SECS;
+        match *self {
+            Cadence::Day { at_secs } => {
+                let candidate = day * DAY_SECS + at_secs;
+                if candidate > after { candidate } else { candidate + DAY_SECS }
+            }
+            Cadence::Week { weekday, at_secs } => {
+                // 1 January 1970 was a Thursday
+                let today = (day + 3) % 7;
+                let ahead = (weekday as u64 + 7 - today) % 7;
+                let candidate = (day + ahead) * DAY_SECS + at_secs;
+                if candidate > after { candidate } else { candidate + 7 * DAY_SECS }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Cadence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (day, at_secs) = match self {
+            Cadence::Day { at_secs } => ("day".to_string(), at_secs),
+            Cadence::Week { weekday, at_secs } => {
+                let name = WEEKDAYS[*weekday as usize % 7];
+                (name[..1].to_uppercase() + &name[1..], at_secs)
+            }
+        };
+        write!(f, "every {} at {:02}:{:02} UTC", day, at_secs / 3_600, at_secs % 3_600 / 60)
+    }
+}
+
+// ============================================================================
+// PLANS
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PlanAction {
+    // Paid in the vault's own asset
+    Deposit { risk: RiskLevel, amount_stroops: u64 },
+    // Paid by the vault to `payee`, which needn't be the user's own account
+    Withdraw { risk: RiskLevel, payout: Payout, payee: String },
+}
+
+impl PlanAction {
+    pub fn risk(&self) -> RiskLevel {
+        match self {
+            PlanAction::Deposit { risk, .. } | PlanAction::Withdraw { risk, .. } => *risk,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Payout {
+    // Whatever yield has accumulated since the last payout, leaving the
+    // shares in place
+    Yield,
+    // Shares worth this much are redeemed; an early withdrawal penalty comes
+    // out of it
+    Fixed { amount_stroops: u64 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanRun {
+    pub at: u64,
+    // False when the run was skipped, such as for a short balance
+    pub executed: bool,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecurringPlan {
+    pub id: u64,
+    pub user: String,
+    pub action: PlanAction,
+    pub cadence: Cadence,
+    pub created_at: u64,
+    pub next_run_at: u64,
+    pub runs: u64,
+    pub skips: u64,
+    pub last_run: Option<PlanRun>,
+}
+
+// Plans that repeat an action on a cadence. They only run while watch or
+// serve is signing for their user; runs missed while nothing was running are
+// not caught up, the plan just carries on from its next occurrence.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecurringBook {
+    plans: Vec<RecurringPlan>,
+    next_id: u64,
+}
+
+impl RecurringBook {
+    pub fn add(&mut self, user: &str, action: PlanAction, cadence: Cadence, now: u64) -> &RecurringPlan {
+        self.next_id += 1;
+        self.plans.push(RecurringPlan {
+            id: self.next_id,
+            user: user.to_string(),
+            action,
+            cadence,
+            created_at: now,
+            next_run_at: cadence.next_after(now),
+            runs: 0,
+            skips: 0,
+            last_run: None,
+        });
+        &self.plans[self.plans.len() - 1]
+    }
+
+    // Users can only cancel their own plans
+    pub fn cancel(&mut self, user: &str, id: u64) -> Result<RecurringPlan, VaultError> {
+        let index = self.plans.iter()
+            .position(|plan| plan.id == id && plan.user == user)
+            .ok_or_else(|| VaultError::Validation(format!("You have no recurring plan #{}", id)))?;
+        Ok(self.plans.remove(index))
+    }
+
+    pub fn for_user<'a>(&'a self, user: &'a str) -> impl Iterator<Item = &'a RecurringPlan> {
+        self.plans.iter().filter(move |plan| plan.user == user)
+    }
+
+    pub fn due(&self, now: u64) -> Vec<RecurringPlan> {
+        self.plans.iter().filter(|plan| plan.next_run_at <= now).cloned().collect()
+    }
+
+    // Records a run, or a skip when `result` is an error, and moves the plan
+    // on to its next occurrence either way
+    pub fn record(&mut self, id: u64, now: u64, result: Result<String, String>) {
+        let Some(plan) = self.plans.iter_mut().find(|plan| plan.id == id) else {
+            return;
+        };
+        let executed = result.is_ok();
+        if executed { plan.runs += 1 } else { plan.skips += 1 }
+        plan.next_run_at = plan.cadence.next_after(now);
+        plan.last_run = Some(PlanRun { at: now, executed, detail: result.unwrap_or_else(|e| e) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Monday 6 January 2025, 00:00 UTC
+    const MONDAY: u64 = 1_736_121_600;
+
+    #[test]
+    fn runs_on_the_next_occurrence_and_moves_on_after_a_skip() {
+        let cadence = Cadence::parse("every Monday 09:30").unwrap();
+        assert_eq!(cadence, Cadence::Week { weekday: 0, at_secs: 34_200 });
+        assert_eq!(cadence.to_string(), "every Monday at 09:30 UTC");
+        assert_eq!(cadence.next_after(MONDAY), MONDAY + 34_200);
+        assert_eq!(cadence.next_after(MONDAY + 34_200), MONDAY + 7 * DAY_SECS + 34_200);
+        assert_eq!(Cadence::parse("fri").unwrap().next_after(MONDAY), MONDAY + 4 * DAY_SECS);
+        assert_eq!(Cadence::parse("daily 23:00").unwrap().next_after(MONDAY + 82_800), MONDAY + DAY_SECS + 82_800);
+        assert!(Cadence::parse("fortnightly").is_err());
+        assert!(Cadence::parse("monday 25:00").is_err());
+
+        let mut book = RecurringBook::default();
+        let id = book.add("GUSER", PlanAction::Deposit { risk: RiskLevel::Medium, amount_stroops: 500_000_000 }, cadence, MONDAY).id;
+        assert!(book.due(MONDAY + 34_199).is_empty());
+        assert_eq!(book.due(MONDAY + 40_000).len(), 1);
+        book.record(id, MONDAY + 40_000, Err("Insufficient XLM balance".into()));
+        assert!(book.due(MONDAY + 40_000).is_empty());
+        let plan = book.for_user("GUSER").next().unwrap();
+        assert_eq!((plan.runs, plan.skips, plan.next_run_at), (0, 1, MONDAY + 7 * DAY_SECS + 34_200));
+
+        assert!(book.cancel("GOTHER", id).is_err());
+        assert!(book.cancel("GUSER", id).is_ok());
+    }
+}
@@ -0,0 +1,146 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::amount::Stroops;
+use crate::builder::VaultBuilder;
+use crate::error::VaultError;
+use crate::oracle::format_usd;
+use crate::vault_config;
+use crate::{format_duration, Vault};
+
+// ============================================================================
+// VAULT DEFINITIONS WATCHER
+// ============================================================================
+
+// Notices edits to the vault definitions file. Checked between CLI actions,
+// like the payment stream, so changes apply before the next one.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl ConfigWatcher {
+    pub fn new(path: &Path) -> Self {
+        ConfigWatcher {
+            path: path.to_path_buf(),
+            modified: modified(path),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // The file's definitions when it has changed since the last check. Each
+    // edit is read once, so a bad one is reported once and then waits for
+    // the next save.
+    pub fn poll(&mut self) -> Option<Result<Vec<VaultBuilder>, VaultError>> {
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        match modified {
+            Some(_) => Some(vault_config::load(&self.path)),
+            None => Some(Err(VaultError::Validation(format!("{} was removed", self.path.display())))),
+        }
+    }
+}
+
+// ============================================================================
+// CHANGE DESCRIPTIONS
+// ============================================================================
+
+fn bps(value: u16) -> String {
+    format!("{} bps", value)
+}
+
+fn cap(value: Option<u64>) -> String {
+    value.map_or("none".to_string(), |v| Stroops(v).to_xlm_string())
+}
+
+// Every setting that differs between two definitions of a vault, one line each
+pub fn describe_changes(before: &Vault, after: &Vault) -> Vec<String> {
+    let mut changes = Vec::new();
+    let mut compare = |label: &str, old: String, new: String| {
+        if old != new {
+            changes.push(format!("{}: {} → {}", label, old, new));
+        }
+    };
+
+    compare("insurance fee", bps(before.insurance_fee), bps(after.insurance_fee));
+    let bounds = |v: &Vault| v.premium_bounds.map_or("none".to_string(), |b| format!("{}-{} bps", b.min_bps, b.max_bps));
+    compare("premium bounds", bounds(before), bounds(after));
+    compare("management fee", bps(before.fees.management_fee_bps), bps(after.fees.management_fee_bps));
+    compare("performance fee", bps(before.fees.performance_fee_bps), bps(after.fees.performance_fee_bps));
+    compare("vault cap", cap(before.max_tvl), cap(after.max_tvl));
+    compare("per-user cap", cap(before.per_user_cap), cap(after.per_user_cap));
+    let usd_cap = |value: Option<u64>| value.map_or("none".to_string(), format_usd);
+    compare("USD vault cap", usd_cap(before.max_tvl_usd), usd_cap(after.max_tvl_usd));
+    compare("USD per-user cap", usd_cap(before.per_user_cap_usd), usd_cap(after.per_user_cap_usd));
+    compare("minimum deposit", Stroops(before.min_deposit).to_xlm_string(), Stroops(after.min_deposit).to_xlm_string());
+    compare("lockup", format_duration(before.lockup_secs), format_duration(after.lockup_secs));
+    compare("early withdrawal penalty", bps(before.early_withdrawal_penalty), bps(after.early_withdrawal_penalty));
+    compare("liquidity buffer", bps(before.liquidity_buffer_bps), bps(after.liquidity_buffer_bps));
+    compare("circuit limit", bps(before.circuit.max_price_move_bps), bps(after.circuit.max_price_move_bps));
+
+    for strategy in &after.strategies {
+        match before.strategies.iter().find(|s| s.strategy == strategy.strategy) {
+            Some(old) => {
+                compare(&format!("{} allocation", strategy.strategy),
+                    format!("{}%", old.allocation_percentage), format!("{}%", strategy.allocation_percentage));
+                compare(&format!("{} APY", strategy.strategy), bps(old.current_apy), bps(strategy.current_apy));
+            }
+            None => compare(&strategy.strategy, "none".to_string(),
+                format!("{}% at {}", strategy.allocation_percentage, bps(strategy.current_apy))),
+        }
+    }
+    for strategy in before.strategies.iter().filter(|s| !after.strategies.iter().any(|a| a.strategy == s.strategy)) {
+        compare(&strategy.strategy, format!("{}%", strategy.allocation_percentage), "removed".to_string());
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::default_vaults;
+    use crate::strategy;
+
+    #[test]
+    fn edits_are_picked_up_and_described() {
+        let path = std::env::temp_dir().join(format!("stellarvault-reload-{}.toml", std::process::id()));
+        // Stamps each save a second apart, as some filesystems keep whole seconds
+        let save = |contents: &str, at: u64| {
+            fs::write(&path, contents).unwrap();
+            let stamp = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000 + at);
+            fs::File::options().write(true).open(&path).unwrap().set_modified(stamp).unwrap();
+        };
+        let example = include_str!("../vaults.example.toml");
+        save(example, 0);
+        let mut watcher = ConfigWatcher::new(&path);
+        assert!(watcher.poll().is_none());
+
+        let edited = example.replace("allocation = 60", "allocation = 50");
+        save(&edited, 1);
+        assert!(watcher.poll().unwrap().is_err());
+        assert!(watcher.poll().is_none());
+
+        save(&edited.replace("allocation = 40", "allocation = 50"), 2);
+        let vaults = watcher.poll().unwrap().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let before = default_vaults()[1].clone().build().unwrap();
+        let mut after = before.clone();
+        after.redefine(&vaults[1].clone().build().unwrap());
+        assert_eq!(describe_changes(&before, &after), vec![
+            format!("{} allocation: 60% → 50%", strategy::AQUA_LIQUIDITY_POOL),
+            format!("{} allocation: 40% → 50%", strategy::YIELDBLOX_LENDING),
+        ]);
+    }
+}
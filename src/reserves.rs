@@ -0,0 +1,331 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::OsRng;
+use chacha20poly1305::aead::rand_core::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use stellar_xdr::curr::{DecoratedSignature, Signature, SignatureHint};
+
+use crate::error::VaultError;
+use crate::signer::{self, Signer};
+use crate::soroban::hex;
+use crate::RiskLevel;
+
+// Leaves and nodes are hashed under different prefixes, so a node can't be
+// passed off as someone's position
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+// ============================================================================
+// MERKLE SUM TREE
+// ============================================================================
+
+// A user's position as committed to. The salt keeps anyone holding the root
+// from confirming a guessed account and balance.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Liability {
+    pub account: String,
+    pub shares: u64,
+    // What the shares are worth at the vault's share price, in stroops
+    pub value_stroops: u64,
+    pub salt: String,
+}
+
+impl Liability {
+    // sha256(0x00 || "<salt>:<account>:<risk>:<shares>:<value_stroops>")
+    pub fn leaf(&self, risk: RiskLevel) -> Node {
+        let mut hasher = Sha256::new();
+        hasher.update([LEAF_PREFIX]);
+        hasher.update(format!("{}:{}:{:?}:{}:{}", self.salt, self.account, risk, self.shares, self.value_stroops));
+        Node { hash: hasher.finalize().into(), sum: self.value_stroops }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Node {
+    pub hash: [u8; 32],
+    // Total value of the positions under the node
+    pub sum: u64,
+}
+
+impl Node {
+    // sha256(0x01 || left hash || left sum || right hash || right sum), sums
+    // as 8 big-endian bytes. Committing to the sums means no position can be
+    // left out of the root's total without changing the root.
+    fn parent(left: &Node, right: &Node) -> Result<Node, VaultError> {
+        let mut hasher = Sha256::new();
+        hasher.update([NODE_PREFIX]);
+        hasher.update(left.hash);
+        hasher.update(left.sum.to_be_bytes());
+        hasher.update(right.hash);
+        hasher.update(right.sum.to_be_bytes());
+        let sum = left.sum.checked_add(right.sum).ok_or("Liabilities overflow the sum tree")?;
+        Ok(Node { hash: hasher.finalize().into(), sum })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Left,
+    Right,
+}
+
+// The sibling met on the way from a leaf to the root
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProofStep {
+    pub side: Side,
+    pub hash: String,
+    pub sum: u64,
+}
+
+// Levels from the leaves up. A node left without a sibling moves up a level
+// unchanged.
+struct SumTree {
+    levels: Vec<Vec<Node>>,
+}
+
+impl SumTree {
+    fn build(leaves: Vec<Node>) -> Result<Self, VaultError> {
+        let mut levels = vec![leaves];
+        while levels.last().is_some_and(|level| level.len() > 1) {
+            let next = levels.last().into_iter().flat_map(|level| level.chunks(2))
+                .map(|pair| match pair {
+                    [left, right] => Node::parent(left, right),
+                    [single] => Ok(*single),
+                    _ => unreachable!("chunks of two"),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            levels.push(next);
+        }
+        Ok(SumTree { levels })
+    }
+
+    // An empty tree has an all-zero root worth nothing
+    fn root(&self) -> Node {
+        self.levels.last().and_then(|level| level.first()).copied().unwrap_or(Node { hash: [0; 32], sum: 0 })
+    }
+
+    fn path(&self, mut index: usize) -> Vec<ProofStep> {
+        let mut path = Vec::new();
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = index ^ 1;
+            if let Some(node) = level.get(sibling) {
+                let side = if sibling < index { Side::Left } else { Side::Right };
+                path.push(ProofStep { side, hash: hex(&node.hash), sum: node.sum });
+            }
+            index /= 2;
+        }
+        path
+    }
+}
+
+fn unhex(input: &str) -> Result<[u8; 32], VaultError> {
+    let invalid = || VaultError::Validation(format!("Not a 32-byte hex hash: {}", input));
+    if input.len() != 64 || !input.is_ascii() {
+        return Err(invalid());
+    }
+    let mut bytes = [0u8; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&input[2 * i..2 * i + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(bytes)
+}
+
+// ============================================================================
+// REPORT
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Holding {
+    pub label: String,
+    pub stroops: u64,
+}
+
+// What the vault holds of one asset on-chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reserves {
+    // XLM or CODE:ISSUER
+    pub asset: String,
+    pub holdings: Vec<Holding>,
+    pub total_stroops: u64,
+}
+
+// One vault's positions, committed to as the root of a sum tree
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Liabilities {
+    pub risk: RiskLevel,
+    pub asset: String,
+    pub root: String,
+    pub total_stroops: u64,
+    pub positions: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Summary {
+    pub vault: String,
+    pub network: String,
+    // The latest ledger when the balances were read
+    pub ledger: u32,
+    pub generated_at: u64,
+    pub reserves: Vec<Reserves>,
+    pub liabilities: Vec<Liabilities>,
+}
+
+impl Summary {
+    // Assets whose vaults owe their users more than the vault holds, with
+    // what is held and owed
+    pub fn shortfalls(&self) -> Vec<(String, u64, u64)> {
+        self.reserves.iter()
+            .filter_map(|reserves| {
+                let owed = self.liabilities.iter()
+                    .filter(|l| l.asset == reserves.asset)
+                    .map(|l| l.total_stroops)
+                    .fold(0u64, u64::saturating_add);
+                (owed > reserves.total_stroops).then(|| (reserves.asset.clone(), reserves.total_stroops, owed))
+            })
+            .collect()
+    }
+}
+
+// The summary with a vault key's SEP-53 signature of its JSON
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedSummary {
+    pub summary: Summary,
+    pub signer: String,
+    // base64
+    pub signature: String,
+}
+
+impl SignedSummary {
+    // Signed by the first of `signers` that can sign messages
+    pub fn sign(summary: Summary, signers: &[Box<dyn Signer>]) -> Result<Self, VaultError> {
+        let message = serde_json::to_vec(&summary)?;
+        let mut refusals = Vec::new();
+        for signer in signers {
+            match signer.sign_message(&message) {
+                Ok(signature) => return Ok(SignedSummary {
+                    summary,
+                    signer: signer.public_key(),
+                    signature: BASE64.encode(signature.signature.as_slice()),
+                }),
+                Err(e) => refusals.push(e.to_string()),
+            }
+        }
+        Err(VaultError::Validation(format!("No vault key could sign the summary: {}",
+            if refusals.is_empty() { "none is unlocked".to_string() } else { refusals.join("; ") })))
+    }
+
+    pub fn verify(&self) -> Result<(), VaultError> {
+        let key = stellar_strkey::ed25519::PublicKey::from_string(&self.signer)
+            .map_err(|_| format!("Not a G... address: {}", self.signer))?;
+        let bytes = BASE64.decode(&self.signature).map_err(|_| "The signature isn't base64")?;
+        let signature = DecoratedSignature {
+            hint: SignatureHint([key.0[28], key.0[29], key.0[30], key.0[31]]),
+            signature: Signature(bytes.try_into().map_err(|_| "The signature is too long")?),
+        };
+        let message = serde_json::to_vec(&self.summary)?;
+        if !signer::verify(&self.signer, &signer::message_hash(&message), &signature) {
+            return Err(VaultError::Validation(format!("The summary's signature by {} doesn't match it", self.signer)));
+        }
+        Ok(())
+    }
+}
+
+// Shows one position is counted in a vault's root
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub risk: RiskLevel,
+    pub liability: Liability,
+    pub path: Vec<ProofStep>,
+}
+
+impl InclusionProof {
+    pub fn check(&self, summary: &Summary) -> Result<(), VaultError> {
+        let vault = summary.liabilities.iter().find(|l| l.risk == self.risk)
+            .ok_or_else(|| format!("The summary has no {:?} Risk Vault", self.risk))?;
+        let mut node = self.liability.leaf(self.risk);
+        for step in &self.path {
+            let sibling = Node { hash: unhex(&step.hash)?, sum: step.sum };
+            node = match step.side {
+                Side::Left => Node::parent(&sibling, &node)?,
+                Side::Right => Node::parent(&node, &sibling)?,
+            };
+        }
+        if hex(&node.hash) != vault.root || node.sum != vault.total_stroops {
+            return Err(VaultError::Validation(format!("The position isn't in the {:?} Risk Vault's root", self.risk)));
+        }
+        Ok(())
+    }
+}
+
+// A vault's positions as (account, shares, value_stroops)
+pub struct VaultPositions {
+    pub risk: RiskLevel,
+    pub asset: String,
+    pub positions: Vec<(String, u64, u64)>,
+}
+
+// Commits to each vault's positions, returning the summary and every
+// position's inclusion proof
+pub fn build(vault: &str, network: &str, ledger: u32, generated_at: u64, reserves: Vec<Reserves>,
+             vaults: Vec<VaultPositions>) -> Result<(Summary, Vec<InclusionProof>), VaultError> {
+    let mut liabilities = Vec::new();
+    let mut proofs = Vec::new();
+    for VaultPositions { risk, asset, mut positions } in vaults {
+        positions.sort();
+        let committed: Vec<Liability> = positions.into_iter()
+            .map(|(account, shares, value_stroops)| {
+                let mut salt = [0u8; 16];
+                OsRng.fill_bytes(&mut salt);
+                Liability { account, shares, value_stroops, salt: hex(&salt) }
+            })
+            .collect();
+        let tree = SumTree::build(committed.iter().map(|liability| liability.leaf(risk)).collect())?;
+        let root = tree.root();
+        liabilities.push(Liabilities { risk, asset, root: hex(&root.hash), total_stroops: root.sum, positions: committed.len() });
+        for (index, liability) in committed.into_iter().enumerate() {
+            proofs.push(InclusionProof { risk, liability, path: tree.path(index) });
+        }
+    }
+    let summary = Summary { vault: vault.to_string(), network: network.to_string(), ledger, generated_at, reserves, liabilities };
+    Ok((summary, proofs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::Keypair;
+
+    #[test]
+    fn every_position_proves_into_the_signed_root() {
+        let reserves = vec![Reserves {
+            asset: "XLM".into(),
+            holdings: vec![Holding { label: "vault account".into(), stroops: 1_000 }],
+            total_stroops: 1_000,
+        }];
+        let positions = (1..=5).map(|n| (format!("GUSER{}", n), n * 10, n * 11)).collect();
+        let vaults = vec![VaultPositions { risk: RiskLevel::Medium, asset: "XLM".into(), positions }];
+        let (summary, proofs) = build("GVAULT", "Test SDF Network ; September 2015", 42, 1_700_000_000, reserves, vaults).unwrap();
+        assert_eq!(summary.liabilities[0].total_stroops, 165);
+        assert!(summary.shortfalls().is_empty());
+        for proof in &proofs {
+            proof.check(&summary).unwrap();
+        }
+
+        // A changed balance, or a proof against another vault, fails
+        let mut forged = proofs[4].clone();
+        forged.liability.value_stroops -= 1;
+        assert!(forged.check(&summary).is_err());
+        forged = proofs[4].clone();
+        forged.risk = RiskLevel::High;
+        assert!(forged.check(&summary).is_err());
+
+        let key: Box<dyn Signer> = Box::new(Keypair::from_seed(&[7; 32]));
+        let mut signed = SignedSummary::sign(summary, &[key]).unwrap();
+        signed.verify().unwrap();
+        signed.summary.reserves[0].total_stroops = 100;
+        assert!(signed.verify().is_err());
+        assert_eq!(signed.summary.shortfalls(), [("XLM".to_string(), 100, 165)]);
+    }
+}
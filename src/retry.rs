@@ -0,0 +1,115 @@
+use std::fmt::Display;
+use std::future::Future;
+use std::time::Duration;
+use chacha20poly1305::aead::OsRng;
+use chacha20poly1305::aead::rand_core::RngCore;
+
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+pub const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
+pub const DEFAULT_MAX_RETRIES: u32 = 5;
+pub const DEFAULT_RETRY_BACKOFF_MS: u64 = 1_000;
+// Waits stop doubling here
+pub const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+// ============================================================================
+// RETRY POLICY
+// ============================================================================
+
+// How Horizon and Soroban RPC calls are timed out and retried. Only failures
+// that say nothing about the request itself are retried (dropped connections,
+// timeouts, 429s and 5xxs), and only for calls that are safe to repeat:
+// submissions resend the same signed envelope, which can apply at most once,
+// and are retried by the submitter, which checks whether an earlier attempt
+// landed first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub connect_timeout: Duration,
+    // For the whole request, body included; streams aren't bound by it
+    pub request_timeout: Duration,
+    pub max_retries: u32,
+    // Doubled after each failed attempt, up to MAX_BACKOFF
+    pub initial_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            connect_timeout: Duration::from_secs(DEFAULT_CONNECT_TIMEOUT_SECS),
+            request_timeout: Duration::from_secs(DEFAULT_REQUEST_TIMEOUT_SECS),
+            max_retries: DEFAULT_MAX_RETRIES,
+            initial_backoff: Duration::from_millis(DEFAULT_RETRY_BACKOFF_MS),
+        }
+    }
+}
+
+impl RetryPolicy {
+    // Request timeouts are set per request, so streams can stay open
+    pub fn http_client(&self) -> reqwest::Client {
+        reqwest::Client::builder()
+            .connect_timeout(self.connect_timeout)
+            .build()
+            .unwrap_or_default()
+    }
+
+    // The wait before retry number `retry` (from 0): exponential and capped,
+    // with its upper half random so clients that failed together don't all
+    // come back together
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let base = self.initial_backoff.saturating_mul(2u32.saturating_pow(retry)).min(MAX_BACKOFF);
+        let half = base / 2;
+        half + Duration::from_millis(OsRng.next_u64() % (half.as_millis() as u64 + 1))
+    }
+
+    // Runs `call` until it succeeds, fails in a way `transient` says won't
+    // go away, or the retries run out
+    pub async fn run<T, E, F, Fut>(&self, what: &str, transient: impl Fn(&E) -> bool, mut call: F) -> Result<T, E>
+    where
+        E: Display,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let mut retry = 0;
+        loop {
+            match call().await {
+                Err(e) if retry < self.max_retries && transient(&e) => {
+                    let delay = self.backoff(retry);
+                    tracing::warn!(call = what, retry = retry + 1, delay_ms = delay.as_millis() as u64, error = %e, "retrying");
+                    tokio::time::sleep(delay).await;
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[tokio::test]
+    async fn retries_transient_failures_with_growing_capped_waits() {
+        let policy = RetryPolicy { initial_backoff: Duration::from_millis(1), max_retries: 3, ..RetryPolicy::default() };
+        let calls = Cell::new(0);
+        let result: Result<u32, String> = policy.run("account", |e: &String| e.contains("503"), || {
+            calls.set(calls.get() + 1);
+            let attempt = calls.get();
+            async move { if attempt < 3 { Err("503".to_string()) } else { Ok(attempt) } }
+        }).await;
+        assert_eq!(result, Ok(3));
+
+        // Not transient: given up on at once
+        calls.set(0);
+        let result: Result<u32, String> = policy.run("account", |e: &String| e.contains("503"), || {
+            calls.set(calls.get() + 1);
+            async { Err("404".to_string()) }
+        }).await;
+        assert_eq!((result, calls.get()), (Err("404".to_string()), 1));
+
+        let slow = RetryPolicy { initial_backoff: Duration::from_secs(4), ..RetryPolicy::default() };
+        assert!((Duration::from_secs(2)..=Duration::from_secs(4)).contains(&slow.backoff(0)));
+        assert!((Duration::from_secs(8)..=Duration::from_secs(16)).contains(&slow.backoff(2)));
+        assert!((MAX_BACKOFF / 2..=MAX_BACKOFF).contains(&slow.backoff(10)));
+    }
+}
@@ -0,0 +1,167 @@
+use std::fmt;
+use std::time::Duration;
+use chacha20poly1305::aead::OsRng;
+use chacha20poly1305::aead::rand_core::RngCore;
+use serde::Serialize;
+
+use crate::RiskLevel;
+
+// Shorter intervals would keep the vault busy with upkeep between requests
+pub const MIN_INTERVAL_SECS: u64 = 60;
+
+// ============================================================================
+// JOBS
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Job {
+    // One vault, or every vault when None
+    Harvest(Option<RiskLevel>),
+    Rebalance { risk: Option<RiskLevel>, max_drift_bps: u64 },
+    // Fetches every [apy_oracle] source once
+    ApyRefresh,
+    // Moves premiums towards the coverage policy's band
+    CoverageCheck,
+}
+
+impl fmt::Display for Job {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (name, risk) = match self {
+            Job::Harvest(risk) => ("harvest", risk),
+            Job::Rebalance { risk, .. } => ("rebalance", risk),
+            Job::ApyRefresh => ("apy_refresh", &None),
+            Job::CoverageCheck => ("coverage_check", &None),
+        };
+        match risk {
+            Some(risk) => write!(f, "{} {}", name, format!("{:?}", risk).to_lowercase()),
+            None => f.write_str(name),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScheduledJob {
+    pub job: Job,
+    pub interval_secs: u64,
+    // Up to this many seconds are added at random to each wait, so jobs
+    // scheduled alike don't all start together
+    pub jitter_secs: u64,
+}
+
+// ============================================================================
+// SCHEDULER
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LastRun {
+    pub started_at: u64,
+    pub duration_ms: u64,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobStatus {
+    pub job: String,
+    pub interval_secs: u64,
+    pub next_run_at: u64,
+    pub running: bool,
+    pub runs: u64,
+    pub failures: u64,
+    pub last_run: Option<LastRun>,
+}
+
+// Runs jobs on intervals, counted from when each run finished: a run that
+// overruns its interval delays the next one instead of stacking up behind
+// it, and a job is never due while its last run is still going.
+pub struct Scheduler {
+    jobs: Vec<(ScheduledJob, JobStatus)>,
+}
+
+fn jitter(max_secs: u64) -> u64 {
+    if max_secs == 0 { 0 } else { OsRng.next_u64() % (max_secs + 1) }
+}
+
+impl Scheduler {
+    // Each job first runs one interval after `now`
+    pub fn new(jobs: Vec<ScheduledJob>, now: u64) -> Self {
+        let jobs = jobs.into_iter()
+            .map(|scheduled| {
+                let status = JobStatus {
+                    job: scheduled.job.to_string(),
+                    interval_secs: scheduled.interval_secs,
+                    next_run_at: now + scheduled.interval_secs + jitter(scheduled.jitter_secs),
+                    running: false,
+                    runs: 0,
+                    failures: 0,
+                    last_run: None,
+                };
+                (scheduled, status)
+            })
+            .collect();
+        Scheduler { jobs }
+    }
+
+    // The jobs due at `now`, by index, marked as running
+    pub fn start_due(&mut self, now: u64) -> Vec<(usize, Job)> {
+        self.jobs.iter_mut().enumerate()
+            .filter(|(_, (_, status))| !status.running && status.next_run_at <= now)
+            .map(|(index, (scheduled, status))| {
+                status.running = true;
+                (index, scheduled.job.clone())
+            })
+            .collect()
+    }
+
+    pub fn finish(&mut self, index: usize, started_at: u64, took: Duration, result: Result<(), String>) {
+        let Some((scheduled, status)) = self.jobs.get_mut(index) else {
+            return;
+        };
+        let finished_at = started_at + took.as_secs();
+        status.running = false;
+        status.runs += 1;
+        if result.is_err() {
+            status.failures += 1;
+        }
+        status.next_run_at = finished_at + scheduled.interval_secs + jitter(scheduled.jitter_secs);
+        status.last_run = Some(LastRun {
+            started_at,
+            duration_ms: took.as_millis() as u64,
+            ok: result.is_ok(),
+            error: result.err(),
+        });
+    }
+
+    pub fn status(&self) -> Vec<JobStatus> {
+        self.jobs.iter().map(|(_, status)| status.clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_each_job_an_interval_after_its_last_finished() {
+        let harvest = ScheduledJob { job: Job::Harvest(Some(RiskLevel::Low)), interval_secs: 3_600, jitter_secs: 0 };
+        let coverage = ScheduledJob { job: Job::CoverageCheck, interval_secs: 600, jitter_secs: 30 };
+        let mut scheduler = Scheduler::new(vec![harvest, coverage], 1_000);
+        assert!(scheduler.start_due(1_599).is_empty());
+
+        let due = scheduler.start_due(1_630);
+        assert_eq!(due, vec![(1, Job::CoverageCheck)]);
+        // Still running, so not due again
+        assert!(scheduler.start_due(5_000).iter().all(|(index, _)| *index != 1));
+        scheduler.finish(1, 5_000, Duration::from_secs(20), Err("insurance pool unavailable".into()));
+
+        let status = scheduler.status();
+        assert_eq!(status[0].job, "harvest low");
+        assert!(status[0].running);
+        let coverage = &status[1];
+        assert!(!coverage.running);
+        assert_eq!((coverage.runs, coverage.failures), (1, 1));
+        assert!((5_620..=5_650).contains(&coverage.next_run_at));
+        assert_eq!(coverage.last_run.as_ref().unwrap().error.as_deref(), Some("insurance pool unavailable"));
+    }
+}
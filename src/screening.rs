@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use serde::{Deserialize, Serialize};
+
+use crate::error::VaultError;
+use crate::muxed;
+
+// Blocked operations listed by 'admin lists' and GET /address-lists
+pub const BLOCKED_SHOWN: usize = 50;
+
+// ============================================================================
+// ADDRESS SCREENING
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressList {
+    Allow,
+    Deny,
+}
+
+impl AddressList {
+    pub fn parse(name: &str) -> Result<Self, VaultError> {
+        match name.to_lowercase().as_str() {
+            "allow" | "allowlist" => Ok(AddressList::Allow),
+            "deny" | "denylist" => Ok(AddressList::Deny),
+            _ => Err(VaultError::Validation(format!("Unknown list (expected allowlist or denylist): {}", name))),
+        }
+    }
+}
+
+impl fmt::Display for AddressList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressList::Allow => write!(f, "allowlist"),
+            AddressList::Deny => write!(f, "denylist"),
+        }
+    }
+}
+
+// What screening stands in front of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Screened {
+    // Crediting a deposit to an account, or taking one from its sender
+    Deposit,
+    // Withdrawals, yield, refunds and claims
+    Payout,
+}
+
+impl fmt::Display for Screened {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Screened::Deposit => write!(f, "Deposit"),
+            Screened::Payout => write!(f, "Payout"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListedAddress {
+    pub address: String,
+    // Why it was listed, such as a case reference
+    pub note: String,
+    pub added_at: u64,
+}
+
+// Accounts the vault won't take deposits from or pay, and, when the
+// allowlist is enforced, the only ones it will. Entries are G... accounts,
+// which cover every M... address on them, or single M... addresses.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AddressLists {
+    allowed: BTreeMap<String, ListedAddress>,
+    denied: BTreeMap<String, ListedAddress>,
+}
+
+impl AddressLists {
+    fn list(&self, list: AddressList) -> &BTreeMap<String, ListedAddress> {
+        match list {
+            AddressList::Allow => &self.allowed,
+            AddressList::Deny => &self.denied,
+        }
+    }
+
+    fn list_mut(&mut self, list: AddressList) -> &mut BTreeMap<String, ListedAddress> {
+        match list {
+            AddressList::Allow => &mut self.allowed,
+            AddressList::Deny => &mut self.denied,
+        }
+    }
+
+    // Lists the address, taking it off the other list; a listed address
+    // gets the new note
+    pub fn add(&mut self, list: AddressList, address: &str, note: &str, now: u64) -> Result<(), VaultError> {
+        muxed::parse(address)?;
+        let other = match list {
+            AddressList::Allow => AddressList::Deny,
+            AddressList::Deny => AddressList::Allow,
+        };
+        self.list_mut(other).remove(address);
+        let entry = ListedAddress { address: address.to_string(), note: note.to_string(), added_at: now };
+        self.list_mut(list).insert(address.to_string(), entry);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, list: AddressList, address: &str) -> Result<ListedAddress, VaultError> {
+        self.list_mut(list).remove(address)
+            .ok_or_else(|| VaultError::Validation(format!("{} is not on the {}", address, list)))
+    }
+
+    pub fn entries(&self, list: AddressList) -> impl Iterator<Item = &ListedAddress> {
+        self.list(list).values()
+    }
+
+    // The entry covering `address`: its own, or its account's
+    fn covering(&self, list: AddressList, address: &str) -> Option<&ListedAddress> {
+        let entries = self.list(list);
+        entries.get(address).or_else(|| muxed::parse(address).ok().and_then(|parsed| entries.get(&parsed.account)))
+    }
+
+    // Why the vault may not take deposits from or pay `address`, if it may
+    // not. The denylist wins over the allowlist.
+    pub fn check(&self, address: &str, allowlist_only: bool) -> Result<(), String> {
+        if let Some(entry) = self.covering(AddressList::Deny, address) {
+            return Err(match entry.note.is_empty() {
+                true => format!("{} is on the denylist", address),
+                false => format!("{} is on the denylist ({})", address, entry.note),
+            });
+        }
+        if allowlist_only && self.covering(AddressList::Allow, address).is_none() {
+            return Err(format!("{} is not on the allowlist", address));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACCOUNT: &str = "GDRXE2BQUC3AZNPVFSCEZ76NJ3WWL25FYFK6RGZGIEKWE4SOOHSUJUJ6";
+    const OTHER: &str = "GCZEAWUJY3BRHCOKU6C5WRLCF5RFSGY22UGBPBXWL4T4G4SSEQMIYMCX";
+
+    #[test]
+    fn denylist_wins_and_accounts_cover_their_muxed_addresses() {
+        let mut lists = AddressLists::default();
+        let sub_address = muxed::address(ACCOUNT, 7).unwrap();
+        assert!(lists.check(&sub_address, false).is_ok());
+        assert!(lists.check(&sub_address, true).unwrap_err().contains("not on the allowlist"));
+
+        lists.add(AddressList::Allow, ACCOUNT, "", 1_000).unwrap();
+        assert!(lists.check(&sub_address, true).is_ok());
+        assert!(lists.check(OTHER, true).is_err());
+
+        // Denying moves the account off the allowlist
+        lists.add(AddressList::Deny, ACCOUNT, "case 42", 1_100).unwrap();
+        assert_eq!(lists.entries(AddressList::Allow).count(), 0);
+        assert!(lists.check(&sub_address, false).unwrap_err().contains("case 42"));
+
+        lists.remove(AddressList::Deny, ACCOUNT).unwrap();
+        assert!(lists.check(ACCOUNT, false).is_ok());
+        assert!(lists.remove(AddressList::Deny, ACCOUNT).is_err());
+        assert!(lists.add(AddressList::Deny, "not an address", "", 1_200).is_err());
+    }
+}
@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+use stellar_xdr::curr::{ManageBuyOfferOp, ManageSellOfferOp, OperationBody, Price};
+
+use crate::amount::{mul_div, Rounding, Stroops};
+use crate::assets::AssetId;
+use crate::error::VaultError;
+use crate::horizon::{Offer, OrderBook, PriceLevel, Trade};
+use crate::path_payment;
+
+// Widest spread the strategy will quote, in basis points
+pub const MAX_SPREAD_BPS: u16 = 2_000;
+
+// ============================================================================
+// MARKET MAKING
+// ============================================================================
+
+// Passive offers on one pair, quoted either side of the order book's mid
+// price. The base is the vault's own asset and the counter is the configured
+// one; None means XLM for either. Yield is the spread earned on volume that
+// has been both sold and bought back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketMaker {
+    pub counter: Option<AssetId>,
+    pub spread_bps: u16,
+    #[serde(default)]
+    pub fills: Fills,
+    // Realized spread already handed to harvest, in the base asset
+    #[serde(default)]
+    pub harvested: u64,
+    // Paging token of the last trade counted
+    #[serde(default)]
+    pub trade_cursor: Option<String>,
+}
+
+// Running totals of the vault's fills on the pair, in stroops
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fills {
+    pub base_sold: u64,
+    pub counter_received: u64,
+    pub base_bought: u64,
+    pub counter_spent: u64,
+}
+
+impl Fills {
+    // Profit on volume that went out and came back, valued in the base
+    // asset at the average sell price
+    pub fn realized_spread(&self) -> u64 {
+        let matched = self.base_sold.min(self.base_bought);
+        if matched == 0 {
+            return 0;
+        }
+        let received = mul_div(self.counter_received, matched, self.base_sold, Rounding::Down).unwrap_or(0);
+        let spent = mul_div(self.counter_spent, matched, self.base_bought, Rounding::Up).unwrap_or(u64::MAX);
+        let profit = received.saturating_sub(spent);
+        mul_div(profit, self.base_sold, self.counter_received, Rounding::Down).unwrap_or(0)
+    }
+
+    // Counter asset from sells not yet spent on buying back
+    pub fn counter_inventory(&self) -> u64 {
+        self.counter_received.saturating_sub(self.counter_spent)
+    }
+}
+
+impl MarketMaker {
+    pub fn new(counter: Option<AssetId>, spread_bps: u16) -> Result<Self, VaultError> {
+        if spread_bps == 0 || spread_bps > MAX_SPREAD_BPS {
+            return Err(format!("Spread must be between 1 and {} bps: {}", MAX_SPREAD_BPS, spread_bps).into());
+        }
+        Ok(MarketMaker { counter, spread_bps, fills: Fills::default(), harvested: 0, trade_cursor: None })
+    }
+
+    // Adds a trade to the fills if `account` traded this pair, returning
+    // whether it counted
+    pub fn record(&mut self, trade: &Trade, account: &str, base: Option<&AssetId>) -> Result<bool, VaultError> {
+        self.trade_cursor = Some(trade.paging_token.clone());
+        let base_side = (
+            AssetId::from_horizon(&trade.base_asset_type, trade.base_asset_code.as_deref(), trade.base_asset_issuer.as_deref())?,
+            Stroops::from_xlm_str(&trade.base_amount)?.0,
+        );
+        let counter_side = (
+            AssetId::from_horizon(&trade.counter_asset_type, trade.counter_asset_code.as_deref(), trade.counter_asset_issuer.as_deref())?,
+            Stroops::from_xlm_str(&trade.counter_amount)?.0,
+        );
+        let ((sold, sold_amount), (bought, bought_amount)) = if trade.base_account.as_deref() == Some(account) {
+            (base_side, counter_side)
+        } else if trade.counter_account.as_deref() == Some(account) {
+            (counter_side, base_side)
+        } else {
+            return Ok(false);
+        };
+
+        let counter = self.counter.as_ref();
+        if sold.as_ref() == base && bought.as_ref() == counter {
+            self.fills.base_sold += sold_amount;
+            self.fills.counter_received += bought_amount;
+        } else if sold.as_ref() == counter && bought.as_ref() == base {
+            self.fills.counter_spent += sold_amount;
+            self.fills.base_bought += bought_amount;
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    // Spread realized since the last harvest
+    pub fn take_unharvested(&mut self) -> u64 {
+        let realized = self.fills.realized_spread();
+        let unharvested = realized.saturating_sub(self.harvested);
+        self.harvested = self.harvested.max(realized);
+        unharvested
+    }
+
+    // Ask and bid around `mid`, in counter per base
+    pub fn quote_prices(&self, mid: f64) -> (f64, f64) {
+        let half_spread = self.spread_bps as f64 / 20_000.0;
+        (mid * (1.0 + half_spread), mid * (1.0 - half_spread))
+    }
+
+    // Operations that cancel `open` offers on the pair and quote afresh: half
+    // of `allocation` offered for sale, and up to the other half bid for with
+    // counter already earned from sells
+    pub fn offer_operations(&self, base: Option<&AssetId>, open: &[Offer], mid: f64, allocation: u64) -> Result<Vec<OperationBody>, VaultError> {
+        let base_xdr = path_payment::to_xdr(base)?;
+        let counter_xdr = path_payment::to_xdr(self.counter.as_ref())?;
+        let mut operations = Vec::new();
+
+        for offer in open {
+            let selling = AssetId::from_horizon(&offer.selling.asset_type, offer.selling.asset_code.as_deref(), offer.selling.asset_issuer.as_deref())?;
+            let buying = AssetId::from_horizon(&offer.buying.asset_type, offer.buying.asset_code.as_deref(), offer.buying.asset_issuer.as_deref())?;
+            let on_pair = (selling.as_ref() == base && buying.as_ref() == self.counter.as_ref())
+                || (selling.as_ref() == self.counter.as_ref() && buying.as_ref() == base);
+            if !on_pair {
+                continue;
+            }
+            // A sell offer with amount 0 deletes the offer whatever its side
+            operations.push(OperationBody::ManageSellOffer(ManageSellOfferOp {
+                selling: path_payment::to_xdr(selling.as_ref())?,
+                buying: path_payment::to_xdr(buying.as_ref())?,
+                amount: 0,
+                price: Price { n: offer.price_r.n, d: offer.price_r.d },
+                offer_id: i64::try_from(offer.id)?,
+            }));
+        }
+
+        let (ask, bid) = self.quote_prices(mid);
+        let sell_amount = allocation / 2;
+        if sell_amount > 0 {
+            operations.push(OperationBody::ManageSellOffer(ManageSellOfferOp {
+                selling: base_xdr.clone(),
+                buying: counter_xdr.clone(),
+                amount: i64::try_from(sell_amount)?,
+                price: to_price(ask)?,
+                offer_id: 0,
+            }));
+        }
+        let affordable = (self.fills.counter_inventory() as f64 / bid) as u64;
+        let buy_amount = (allocation / 2).min(affordable);
+        if buy_amount > 0 {
+            operations.push(OperationBody::ManageBuyOffer(ManageBuyOfferOp {
+                selling: counter_xdr,
+                buying: base_xdr,
+                buy_amount: i64::try_from(buy_amount)?,
+                price: to_price(bid)?,
+                offer_id: 0,
+            }));
+        }
+        Ok(operations)
+    }
+}
+
+// Midpoint of the best bid and ask, or whichever side exists
+pub fn mid_price(book: &OrderBook) -> Option<f64> {
+    let best = |levels: &[PriceLevel]| levels.first().and_then(|level| level.price.parse::<f64>().ok());
+    match (best(&book.bids), best(&book.asks)) {
+        (Some(bid), Some(ask)) => Some((bid + ask) / 2.0),
+        (Some(price), None) | (None, Some(price)) => Some(price),
+        (None, None) => None,
+    }
+}
+
+// Nearest ratio with a power-of-ten denominator that fits the XDR's i32s
+pub fn to_price(price: f64) -> Result<Price, VaultError> {
+    if !price.is_finite() || price <= 0.0 {
+        return Err(format!("Invalid offer price {}", price).into());
+    }
+    let mut d: i64 = 10_000_000;
+    while d > 1 && price * d as f64 > i32::MAX as f64 {
+        d /= 10;
+    }
+    let n = (price * d as f64).round() as i64;
+    if n == 0 || n > i32::MAX as i64 {
+        return Err(format!("Offer price {} is out of range", price).into());
+    }
+    Ok(Price { n: n as i32, d: d as i32 })
+}
+
+// "XLM" or the code of either side, for display
+pub fn pair_label(base: Option<&AssetId>, counter: Option<&AssetId>) -> String {
+    format!("{}/{}", path_payment::label(base), path_payment::label(counter))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spread_is_realized_only_on_round_trips() {
+        // Sold 100 at 1.01, bought 100 back at 0.99
+        let fills = Fills {
+            base_sold: 1_000_000_000,
+            counter_received: 1_010_000_000,
+            base_bought: 1_000_000_000,
+            counter_spent: 990_000_000,
+        };
+        assert_eq!(fills.realized_spread(), 19_801_980);
+        assert_eq!(fills.counter_inventory(), 20_000_000);
+
+        // Nothing bought back yet, so nothing is realized
+        let one_sided = Fills { base_bought: 0, counter_spent: 0, ..fills };
+        assert_eq!(one_sided.realized_spread(), 0);
+
+        let mut maker = MarketMaker::new(None, 50).unwrap();
+        maker.fills = fills;
+        assert_eq!(maker.take_unharvested(), 19_801_980);
+        assert_eq!(maker.take_unharvested(), 0);
+        assert!(MarketMaker::new(None, 0).is_err());
+    }
+}
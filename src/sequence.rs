@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::error::VaultError;
+use crate::horizon::HorizonClient;
+
+// ============================================================================
+// SEQUENCE NUMBERS
+// ============================================================================
+
+// Hands out sequence numbers per account without a Horizon round trip each
+// time, so transactions built back to back don't reuse one. Clones share the
+// same cache; the lock is held while fetching so two callers can't both
+// start from the same Horizon value.
+#[derive(Debug, Clone, Default)]
+pub struct SequenceManager {
+    // Last sequence number handed out for each account
+    issued: Arc<Mutex<HashMap<String, i64>>>,
+}
+
+impl SequenceManager {
+    // The account sequence to build the next transaction on (the builder
+    // uses one more than this)
+    pub async fn reserve(&self, horizon: &HorizonClient, account: &str) -> Result<i64, VaultError> {
+        let mut issued = self.issued.lock().await;
+        let current = match issued.get(account) {
+            Some(&sequence) => sequence,
+            None => horizon.fresh_account(account).await?.sequence_number()?,
+        };
+        issued.insert(account.to_string(), current + 1);
+        Ok(current)
+    }
+
+    // Gives back a reservation whose transaction was never submitted. If a
+    // later one has been handed out since, the cache is dropped instead.
+    pub async fn release(&self, account: &str, reserved: i64) {
+        let mut issued = self.issued.lock().await;
+        if issued.get(account) == Some(&(reserved + 1)) {
+            issued.insert(account.to_string(), reserved);
+        } else {
+            issued.remove(account);
+        }
+    }
+
+    // Forgets the account's cached sequence so the next reservation refetches
+    // it, e.g. after tx_bad_seq or a rejection that consumed nothing
+    pub async fn invalidate(&self, account: &str) {
+        self.issued.lock().await.remove(account);
+    }
+}
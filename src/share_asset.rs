@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+
+use crate::assets::AssetId;
+use crate::horizon::Balance;
+use crate::RiskLevel;
+
+// ============================================================================
+// SHARE ASSETS
+// ============================================================================
+
+// Vault shares use the same 7 decimal places as Stellar assets, so one share
+// unit maps to one stroop-sized unit of the share asset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShareAsset {
+    pub code: &'static str,
+    pub issuer: String,
+}
+
+impl ShareAsset {
+    pub fn for_vault(risk: RiskLevel, issuer: &str) -> Self {
+        // Asset codes are limited to 12 alphanumeric characters
+        let code = match risk {
+            RiskLevel::Low => "SYIALOW",
+            RiskLevel::Medium => "SYIAMED",
+            RiskLevel::High => "SYIAHIGH",
+        };
+
+        ShareAsset {
+            code,
+            issuer: issuer.to_string(),
+        }
+    }
+
+    pub fn id(&self) -> AssetId {
+        AssetId { code: self.code.to_string(), issuer: self.issuer.clone() }
+    }
+
+    fn matches(&self, balance: &Balance) -> bool {
+        balance.asset_code.as_deref() == Some(self.code)
+            && balance.asset_issuer.as_deref() == Some(self.issuer.as_str())
+    }
+
+    // Horizon lists a balance entry for every trustline the account holds
+    pub fn has_trustline(&self, balances: &[Balance]) -> bool {
+        balances.iter().any(|b| self.matches(b))
+    }
+
+    pub fn on_chain_balance(&self, balances: &[Balance]) -> Option<f64> {
+        balances.iter()
+            .find(|b| self.matches(b))
+            .and_then(|b| b.balance.parse().ok())
+    }
+}
+
+// ============================================================================
+// ISSUANCE LEDGER
+// ============================================================================
+
+// Tracks which internally minted shares still have to be issued on-chain and
+// which burned shares the holder still owes back to the issuer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareTokenRecord {
+    pub user: String,
+    pub risk: RiskLevel,
+    pub issued: u64,
+    pub pending_mint: u64,
+    pub pending_burn: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShareIssuance {
+    records: Vec<ShareTokenRecord>,
+}
+
+impl ShareIssuance {
+    fn record_mut(&mut self, user: &str, risk: RiskLevel) -> &mut ShareTokenRecord {
+        let index = match self.records.iter().position(|r| r.user == user && r.risk == risk) {
+            Some(index) => index,
+            None => {
+                self.records.push(ShareTokenRecord {
+                    user: user.to_string(),
+                    risk,
+                    issued: 0,
+                    pending_mint: 0,
+                    pending_burn: 0,
+                });
+                self.records.len() - 1
+            }
+        };
+        &mut self.records[index]
+    }
+
+    pub fn queue_mint(&mut self, user: &str, risk: RiskLevel, shares: u64) {
+        self.record_mut(user, risk).pending_mint += shares;
+    }
+
+    // Shares that were never issued on-chain are simply cancelled; the rest
+    // must come back from the holder before the burn completes
+    pub fn queue_burn(&mut self, user: &str, risk: RiskLevel, shares: u64) {
+        let record = self.record_mut(user, risk);
+        let cancelled = shares.min(record.pending_mint);
+        record.pending_mint -= cancelled;
+        record.pending_burn += shares - cancelled;
+    }
+
+    pub fn records(&self) -> &[ShareTokenRecord] {
+        &self.records
+    }
+
+    pub fn get(&self, user: &str, risk: RiskLevel) -> Option<&ShareTokenRecord> {
+        self.records.iter().find(|r| r.user == user && r.risk == risk)
+    }
+}
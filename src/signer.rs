@@ -0,0 +1,63 @@
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use sha2::{Digest, Sha256};
+use stellar_xdr::curr::{DecoratedSignature, Transaction};
+
+use crate::error::VaultError;
+use crate::transaction::{self, Keypair};
+
+#[cfg(feature = "ledger")]
+pub mod ledger;
+pub mod remote;
+
+// ============================================================================
+// SIGNERS
+// ============================================================================
+
+// Approves transactions for an account: a key held in memory, or a hardware
+// wallet that shows the transaction and waits for the user to confirm it
+pub trait Signer {
+    // G... strkey form
+    fn public_key(&self) -> String;
+    fn sign_transaction(&self, tx: &Transaction, passphrase: &str) -> Result<DecoratedSignature, VaultError>;
+    // Signs arbitrary bytes as SEP-53 describes. Remote signers and hardware
+    // wallets only approve transactions.
+    fn sign_message(&self, _message: &[u8]) -> Result<DecoratedSignature, VaultError> {
+        Err(VaultError::Validation(format!("{} can only sign transactions", self.public_key())))
+    }
+}
+
+impl Signer for Keypair {
+    fn public_key(&self) -> String {
+        Keypair::public_key(self)
+    }
+
+    fn sign_transaction(&self, tx: &Transaction, passphrase: &str) -> Result<DecoratedSignature, VaultError> {
+        self.sign_hash(&tx.hash(transaction::network_id(passphrase))?)
+    }
+
+    fn sign_message(&self, message: &[u8]) -> Result<DecoratedSignature, VaultError> {
+        self.sign_hash(&message_hash(message))
+    }
+}
+
+// SEP-53: what is signed for a message, so it can never be a transaction hash
+pub fn message_hash(message: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"Stellar Signed Message:\n");
+    hasher.update(message);
+    hasher.finalize().into()
+}
+
+// Whether `signature` is `key`'s signature of the transaction hash
+pub fn verify(key: &str, hash: &[u8; 32], signature: &DecoratedSignature) -> bool {
+    let Ok(public_key) = stellar_strkey::ed25519::PublicKey::from_string(key) else {
+        return false;
+    };
+    if signature.hint.0 != public_key.0[28..] {
+        return false;
+    }
+    let (Ok(verifying_key), Ok(signature)) = (VerifyingKey::from_bytes(&public_key.0), Signature::from_slice(signature.signature.as_slice())) else {
+        return false;
+    };
+    verifying_key.verify(hash, &signature).is_ok()
+}
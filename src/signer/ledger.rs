@@ -0,0 +1,129 @@
+use ledger_transport::APDUCommand;
+use ledger_transport_hid::{hidapi::HidApi, TransportNativeHID};
+use stellar_xdr::curr::{
+    DecoratedSignature, Hash, Limits, Signature, SignatureHint, Transaction, TransactionSignaturePayload,
+    TransactionSignaturePayloadTaggedTransaction, WriteXdr,
+};
+
+use super::Signer;
+use crate::error::VaultError;
+use crate::transaction;
+
+// Stellar app APDUs
+const CLA: u8 = 0xe0;
+const INS_GET_PUBLIC_KEY: u8 = 0x02;
+const INS_SIGN_TX: u8 = 0x04;
+const P1_FIRST: u8 = 0x00;
+const P1_MORE: u8 = 0x80;
+const P2_LAST: u8 = 0x00;
+const P2_MORE: u8 = 0x80;
+const CHUNK_SIZE: usize = 255;
+
+const SW_OK: u16 = 0x9000;
+const SW_CANCELLED: u16 = 0x6985;
+const SW_DATA_TOO_LARGE: u16 = 0xb004;
+const SW_APP_NOT_OPEN: u16 = 0x6e00;
+const SW_LOCKED: u16 = 0x5515;
+
+// Hardened BIP-44 path m/44'/148'/account', as SEP-5 derives Stellar keys
+fn derivation_path(account: u32) -> Vec<u8> {
+    let mut path = vec![3];
+    for index in [44, 148, account] {
+        path.extend_from_slice(&(0x8000_0000 | index).to_be_bytes());
+    }
+    path
+}
+
+fn status_error(status: u16) -> VaultError {
+    match status {
+        SW_CANCELLED => "Rejected on the Ledger".into(),
+        SW_LOCKED => "The Ledger is locked; unlock it and try again".into(),
+        SW_APP_NOT_OPEN => "Open the Stellar app on the Ledger".into(),
+        SW_DATA_TOO_LARGE => "The transaction is too large for the Ledger to display".into(),
+        status => format!("Ledger returned status {:#06x}", status).into(),
+    }
+}
+
+// A key held on a Ledger running the Stellar app. Every signature is
+// confirmed on the device, which shows the transaction being signed.
+pub struct LedgerSigner {
+    transport: TransportNativeHID,
+    account: u32,
+    public_key: String,
+}
+
+impl LedgerSigner {
+    // Connects to the first Ledger found and reads the key for `account`
+    pub fn connect(account: u32) -> Result<Self, VaultError> {
+        let api = HidApi::new().map_err(|e| format!("Could not access USB devices: {}", e))?;
+        let transport = TransportNativeHID::new(&api)
+            .map_err(|e| format!("No Ledger found ({}); connect it and open the Stellar app", e))?;
+
+        let command = APDUCommand {
+            cla: CLA,
+            ins: INS_GET_PUBLIC_KEY,
+            p1: 0x00,
+            p2: 0x00,
+            data: derivation_path(account),
+        };
+        let answer = transport.exchange(&command).map_err(|e| format!("Ledger communication failed: {}", e))?;
+        if answer.retcode() != SW_OK {
+            return Err(status_error(answer.retcode()));
+        }
+        let key: [u8; 32] = answer.data().get(..32)
+            .and_then(|key| key.try_into().ok())
+            .ok_or("The Ledger returned a malformed public key")?;
+
+        Ok(LedgerSigner {
+            transport,
+            account,
+            public_key: stellar_strkey::ed25519::PublicKey(key).to_string(),
+        })
+    }
+
+    pub fn account(&self) -> u32 {
+        self.account
+    }
+}
+
+impl Signer for LedgerSigner {
+    fn public_key(&self) -> String {
+        self.public_key.clone()
+    }
+
+    // The device parses and displays the transaction, so it is sent whole
+    // rather than as a hash, in chunks after the derivation path
+    fn sign_transaction(&self, tx: &Transaction, passphrase: &str) -> Result<DecoratedSignature, VaultError> {
+        let payload = TransactionSignaturePayload {
+            network_id: Hash(transaction::network_id(passphrase)),
+            tagged_transaction: TransactionSignaturePayloadTaggedTransaction::Tx(tx.clone()),
+        }.to_xdr(Limits::none())?;
+        let mut data = derivation_path(self.account);
+        data.extend_from_slice(&payload);
+
+        say!("   🔐 Confirm the transaction on your Ledger...");
+        let chunks: Vec<&[u8]> = data.chunks(CHUNK_SIZE).collect();
+        let mut signature = Vec::new();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let command = APDUCommand {
+                cla: CLA,
+                ins: INS_SIGN_TX,
+                p1: if i == 0 { P1_FIRST } else { P1_MORE },
+                p2: if i + 1 == chunks.len() { P2_LAST } else { P2_MORE },
+                data: chunk.to_vec(),
+            };
+            let answer = self.transport.exchange(&command).map_err(|e| format!("Ledger communication failed: {}", e))?;
+            if answer.retcode() != SW_OK {
+                return Err(status_error(answer.retcode()));
+            }
+            signature = answer.data().to_vec();
+        }
+
+        let key = stellar_strkey::ed25519::PublicKey::from_string(&self.public_key)
+            .map_err(|_| "Invalid Ledger public key")?;
+        Ok(DecoratedSignature {
+            hint: SignatureHint([key.0[28], key.0[29], key.0[30], key.0[31]]),
+            signature: Signature(signature.try_into().map_err(|_| "The Ledger returned a malformed signature")?),
+        })
+    }
+}
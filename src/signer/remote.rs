@@ -0,0 +1,210 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::time::Duration;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use stellar_xdr::curr::{DecoratedSignature, Signature, SignatureHint, Transaction};
+
+use super::{verify, Signer};
+use crate::error::VaultError;
+use crate::offline;
+use crate::transaction;
+
+pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+// Where the signing service listens: unix:///path/to.sock or http://host:port/path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Endpoint {
+    Unix(PathBuf),
+    Http { host: String, path: String },
+}
+
+impl Endpoint {
+    pub fn parse(url: &str) -> Result<Self, VaultError> {
+        if let Some(path) = url.strip_prefix("unix://") {
+            return Ok(Endpoint::Unix(PathBuf::from(path)));
+        }
+        if let Some(rest) = url.strip_prefix("http://") {
+            let (host, path) = match rest.find('/') {
+                Some(i) => (&rest[..i], &rest[i..]),
+                None => (rest, "/"),
+            };
+            if host.is_empty() {
+                return Err(format!("Signer URL {} has no host", url).into());
+            }
+            let host = if host.contains(':') { host.to_string() } else { format!("{}:80", host) };
+            return Ok(Endpoint::Http { host, path: path.to_string() });
+        }
+        Err(format!("Signer URL must start with unix:// or http://: {}", url).into())
+    }
+}
+
+// Sent to the service, which replies with the signature or an error
+#[derive(Serialize)]
+struct SignRequest<'a> {
+    public_key: &'a str,
+    network_passphrase: &'a str,
+    // Hex hash to sign
+    hash: &'a str,
+    // The unsigned transaction as base64 envelope XDR, for services that
+    // check what they are signing
+    transaction: &'a str,
+}
+
+#[derive(Deserialize)]
+struct SignResponse {
+    // Base64 ed25519 signature of the hash
+    signature: Option<String>,
+    error: Option<String>,
+}
+
+// A key held by the integrator's own signing service. Requests are one JSON
+// object; over a Unix socket both request and reply are a single line, over
+// HTTP they are the POST body and response body. Signatures are checked
+// against the key before use.
+pub struct RemoteSigner {
+    endpoint: Endpoint,
+    public_key: String,
+    timeout: Duration,
+}
+
+impl RemoteSigner {
+    pub fn new(url: &str, public_key: &str, timeout: Duration) -> Result<Self, VaultError> {
+        stellar_strkey::ed25519::PublicKey::from_string(public_key)
+            .map_err(|_| format!("Invalid signer key {}", public_key))?;
+        Ok(RemoteSigner {
+            endpoint: Endpoint::parse(url)?,
+            public_key: public_key.to_string(),
+            timeout,
+        })
+    }
+
+    fn exchange(&self, body: &str) -> Result<String, VaultError> {
+        match &self.endpoint {
+            #[cfg(unix)]
+            Endpoint::Unix(path) => {
+                let mut stream = std::os::unix::net::UnixStream::connect(path)
+                    .map_err(|e| format!("Could not reach the signer at {}: {}", path.display(), e))?;
+                stream.set_read_timeout(Some(self.timeout))?;
+                stream.set_write_timeout(Some(self.timeout))?;
+                stream.write_all(body.as_bytes())?;
+                stream.write_all(b"\n")?;
+
+                let mut reply = String::new();
+                BufReader::new(stream).read_line(&mut reply)
+                    .map_err(|e| format!("No reply from the signer at {}: {}", path.display(), e))?;
+                Ok(reply)
+            }
+            #[cfg(not(unix))]
+            Endpoint::Unix(_) => Err("Unix socket signers are not supported on this platform".into()),
+            Endpoint::Http { host, path } => {
+                let address = host.to_socket_addrs()?.next()
+                    .ok_or_else(|| format!("Could not resolve {}", host))?;
+                let mut stream = TcpStream::connect_timeout(&address, self.timeout)
+                    .map_err(|e| format!("Could not reach the signer at {}: {}", host, e))?;
+                stream.set_read_timeout(Some(self.timeout))?;
+                stream.set_write_timeout(Some(self.timeout))?;
+                // HTTP/1.0 so the reply is never chunked and ends when the connection closes
+                write!(stream, "POST {} HTTP/1.0\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                    path, host, body.len(), body)?;
+
+                let mut reply = String::new();
+                stream.read_to_string(&mut reply)
+                    .map_err(|e| format!("No reply from the signer at {}: {}", host, e))?;
+                let (head, body) = reply.split_once("\r\n\r\n")
+                    .ok_or("Malformed HTTP reply from the signer")?;
+                let status = head.split_whitespace().nth(1).unwrap_or("");
+                if !status.starts_with('2') {
+                    let error = serde_json::from_str::<SignResponse>(body).ok().and_then(|r| r.error);
+                    return Err(format!("Signer replied {}: {}", status, error.unwrap_or_else(|| body.trim().to_string())).into());
+                }
+                Ok(body.to_string())
+            }
+        }
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn public_key(&self) -> String {
+        self.public_key.clone()
+    }
+
+    fn sign_transaction(&self, tx: &Transaction, passphrase: &str) -> Result<DecoratedSignature, VaultError> {
+        let hash = tx.hash(transaction::network_id(passphrase))?;
+        let request = serde_json::to_string(&SignRequest {
+            public_key: &self.public_key,
+            network_passphrase: passphrase,
+            hash: &hash.iter().map(|b| format!("{:02x}", b)).collect::<String>(),
+            transaction: &transaction::to_base64(&offline::unsigned(tx.clone()))?,
+        })?;
+
+        let reply: SignResponse = serde_json::from_str(&self.exchange(&request)?)
+            .map_err(|e| format!("Malformed reply from the signer: {}", e))?;
+        let signature = match (reply.signature, reply.error) {
+            (Some(signature), _) => signature,
+            (None, Some(error)) => return Err(format!("Signer refused: {}", error).into()),
+            (None, None) => return Err("Signer replied without a signature".into()),
+        };
+        let bytes = BASE64.decode(signature.trim())
+            .map_err(|e| format!("Signer returned an invalid signature: {}", e))?;
+
+        let key = stellar_strkey::ed25519::PublicKey::from_string(&self.public_key)
+            .map_err(|_| "Invalid signer key")?;
+        let signature = DecoratedSignature {
+            hint: SignatureHint([key.0[28], key.0[29], key.0[30], key.0[31]]),
+            signature: Signature(bytes.try_into().map_err(|_| "Signer returned a signature of the wrong length")?),
+        };
+        if !verify(&self.public_key, &hash, &signature) {
+            return Err(format!("Signer returned a signature that isn't from {} for this transaction", self.public_key).into());
+        }
+        Ok(signature)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+    use stellar_xdr::curr::Asset;
+    use crate::transaction::{Keypair, TransactionBuilder};
+
+    const PASSPHRASE: &str = "Test SDF Network ; September 2015";
+
+    // Answers one request per key: the first correctly, the second with a
+    // signature from the wrong key
+    fn serve(socket: PathBuf, keys: Vec<Keypair>) {
+        let listener = UnixListener::bind(&socket).unwrap();
+        std::thread::spawn(move || {
+            for key in keys {
+                let (stream, _) = listener.accept().unwrap();
+                let mut line = String::new();
+                BufReader::new(&stream).read_line(&mut line).unwrap();
+                let request: serde_json::Value = serde_json::from_str(&line).unwrap();
+                let envelope = offline::decode(request["transaction"].as_str().unwrap()).unwrap();
+                let stellar_xdr::curr::TransactionEnvelope::Tx(envelope) = envelope else { unreachable!() };
+                let signature = key.sign_transaction(&envelope.tx, PASSPHRASE).unwrap();
+                let reply = serde_json::json!({ "signature": BASE64.encode(signature.signature.as_slice()) });
+                writeln!(&stream, "{}", reply).unwrap();
+            }
+        });
+    }
+
+    #[test]
+    fn signatures_from_the_service_are_verified() {
+        let key = Keypair::from_seed(&[7; 32]);
+        let socket = std::env::temp_dir().join(format!("stellarvault-signer-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+        serve(socket.clone(), vec![Keypair::from_seed(&[7; 32]), Keypair::from_seed(&[8; 32])]);
+
+        let signer = RemoteSigner::new(&format!("unix://{}", socket.display()), &key.public_key(), Duration::from_secs(5)).unwrap();
+        let tx = TransactionBuilder::new(&key.public_key(), 1).unwrap()
+            .payment(&key.public_key(), Asset::Native, 1).unwrap()
+            .build().unwrap();
+
+        assert_eq!(signer.sign_transaction(&tx, PASSPHRASE).unwrap(), key.sign_transaction(&tx, PASSPHRASE).unwrap());
+        assert!(signer.sign_transaction(&tx, PASSPHRASE).is_err());
+        let _ = std::fs::remove_file(&socket);
+    }
+}
@@ -0,0 +1,611 @@
+use std::fmt;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use stellar_xdr::curr::{
+    AccountId, Asset, ContractDataDurability, ContractExecutable, ContractId, ContractIdPreimage,
+    ContractIdPreimageFromAddress, CreateContractArgs, CreateContractArgsV2, Hash, HashIdPreimage,
+    HashIdPreimageContractId, HostFunction, Int128Parts, InvokeContractArgs, InvokeHostFunctionOp, LedgerEntryData,
+    LedgerKey, LedgerKeyContractData, Limits, OperationBody, PublicKey, ReadXdr, ScAddress, ScMap, ScMapEntry, ScString, ScSymbol,
+    ScVal, ScVec, SorobanAuthorizationEntry, SorobanTransactionData, TransactionEnvelope, TransactionMeta,
+    TransactionResult, TransactionV1Envelope, Uint256, WriteXdr,
+};
+use thiserror::Error;
+
+use crate::error::VaultError;
+use crate::retry::RetryPolicy;
+use crate::transaction;
+
+// ============================================================================
+// ERRORS
+// ============================================================================
+
+#[derive(Debug, Error)]
+pub enum RpcError {
+    #[error("Soroban RPC request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    // A JSON-RPC error object: the server understood and refused the request
+    #[error("Soroban RPC error {code}: {message}")]
+    Rpc { code: i64, message: String },
+    #[error("Unexpected Soroban RPC response: {0}")]
+    Malformed(String),
+}
+
+impl RpcError {
+    // Worth retrying: the request may not have reached the server, or the
+    // server was briefly unable to answer
+    pub fn is_transient(&self) -> bool {
+        match self {
+            RpcError::Http(e) => e.is_timeout() || e.is_connect() || e.is_request()
+                || e.status().is_some_and(|status| status.is_server_error() || status.as_u16() == 429),
+            RpcError::Rpc { .. } | RpcError::Malformed(_) => false,
+        }
+    }
+}
+
+// ============================================================================
+// RPC
+// ============================================================================
+
+// getLedgerEntries takes at most this many keys per request
+const MAX_LEDGER_KEYS: usize = 200;
+
+// Contract calls are simulated against a Soroban RPC server, which works out
+// the ledger entries they touch, their resource fee and the authorizations
+// they need, then sent through it and polled until they apply.
+#[derive(Debug, Clone)]
+pub struct RpcClient {
+    url: String,
+    http: reqwest::Client,
+    retry: RetryPolicy,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcErrorBody>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcErrorBody {
+    code: i64,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SimulateResponse {
+    // Set when the call itself failed, e.g. the contract panicked
+    error: Option<String>,
+    transaction_data: Option<String>,
+    #[serde(default)]
+    results: Vec<SimulateResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SimulateResult {
+    #[serde(default)]
+    auth: Vec<String>,
+    xdr: String,
+}
+
+// What a simulated call needs before it can be submitted, and what it
+// returned. The transaction data carries the resource fee.
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    pub transaction_data: SorobanTransactionData,
+    pub auth: Vec<SorobanAuthorizationEntry>,
+    pub result: ScVal,
+}
+
+// sendTransaction's answer: PENDING, DUPLICATE, TRY_AGAIN_LATER or ERROR
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendResult {
+    pub status: String,
+    // Why an ERROR was refused, as a TransactionResult
+    pub error_result_xdr: Option<String>,
+}
+
+// getTransaction's answer: SUCCESS, FAILED or NOT_FOUND, the last meaning
+// not applied yet or older than the server keeps
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionInfo {
+    pub status: String,
+    pub ledger: Option<u32>,
+    // Unix seconds, sent as a string or a number depending on the version
+    pub created_at: Option<serde_json::Value>,
+    pub result_xdr: Option<String>,
+    pub result_meta_xdr: Option<String>,
+}
+
+impl TransactionInfo {
+    pub fn fee_charged(&self) -> Option<u64> {
+        let result = TransactionResult::from_xdr_base64(self.result_xdr.as_deref()?, Limits::none()).ok()?;
+        u64::try_from(result.fee_charged).ok()
+    }
+
+    // What the contract call returned
+    pub fn return_value(&self) -> Option<ScVal> {
+        match TransactionMeta::from_xdr_base64(self.result_meta_xdr.as_deref()?, Limits::none()).ok()? {
+            TransactionMeta::V3(meta) => meta.soroban_meta.map(|soroban| soroban.return_value),
+            TransactionMeta::V4(meta) => meta.soroban_meta.and_then(|soroban| soroban.return_value),
+            _ => None,
+        }
+    }
+
+    pub fn created_at(&self) -> String {
+        match &self.created_at {
+            Some(serde_json::Value::String(secs)) => secs.clone(),
+            Some(value) => value.to_string(),
+            None => String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct LedgerEntriesResponse {
+    #[serde(default)]
+    entries: Vec<LedgerEntryResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LedgerEntryResult {
+    xdr: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LatestLedger {
+    sequence: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Health {
+    oldest_ledger: u32,
+}
+
+// One page of getEvents, with the cursor to continue from
+#[derive(Debug, Deserialize)]
+pub struct EventPage {
+    #[serde(default)]
+    pub events: Vec<Event>,
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    pub id: String,
+    pub ledger: u32,
+    pub ledger_closed_at: String,
+    pub contract_id: String,
+    pub tx_hash: String,
+    // Base64 ScVals
+    pub topic: Vec<String>,
+    pub value: String,
+}
+
+impl Event {
+    pub fn topics(&self) -> Result<Vec<ScVal>, VaultError> {
+        Ok(self.topic.iter().map(|topic| ScVal::from_xdr_base64(topic, Limits::none())).collect::<Result<_, _>>()?)
+    }
+
+    pub fn data(&self) -> Result<ScVal, VaultError> {
+        Ok(ScVal::from_xdr_base64(&self.value, Limits::none())?)
+    }
+}
+
+// Whose code a contract runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Executable {
+    // Hex hash of the uploaded Wasm
+    Wasm(String),
+    StellarAsset,
+}
+
+impl fmt::Display for Executable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Executable::Wasm(hash) => write!(f, "Wasm {}", hash),
+            Executable::StellarAsset => write!(f, "Stellar asset contract"),
+        }
+    }
+}
+
+impl RpcClient {
+    pub fn new(url: &str) -> Self {
+        let retry = RetryPolicy::default();
+        RpcClient {
+            url: url.trim_end_matches('/').to_string(),
+            http: retry.http_client(),
+            retry,
+        }
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.http = retry.http_client();
+        self.retry = retry;
+        self
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    // Every method but sendTransaction only reads, so is retried
+    async fn call<T: DeserializeOwned>(&self, method: &str, params: serde_json::Value) -> Result<T, RpcError> {
+        self.retry.run(method, RpcError::is_transient, || self.call_once(method, &params)).await
+    }
+
+    async fn call_once<T: DeserializeOwned>(&self, method: &str, params: &serde_json::Value) -> Result<T, RpcError> {
+        let request = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response: RpcResponse<T> = self.http.post(&self.url)
+            .json(&request)
+            .timeout(self.retry.request_timeout)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if let Some(error) = response.error {
+            return Err(RpcError::Rpc { code: error.code, message: error.message });
+        }
+        response.result.ok_or_else(|| RpcError::Malformed(format!("{} returned no result", method)))
+    }
+
+    // Runs a contract call from `source`, which must exist, without applying
+    // it. Simulation doesn't check the sequence number, so any will do.
+    pub async fn simulate(&self, source: &str, operation: OperationBody) -> Result<Simulation, VaultError> {
+        let envelope = TransactionEnvelope::Tx(TransactionV1Envelope {
+            tx: transaction::TransactionBuilder::new(source, 0)?.operation(operation)?.build()?,
+            signatures: Default::default(),
+        });
+        let params = serde_json::json!({ "transaction": transaction::to_base64(&envelope)? });
+        let simulated: SimulateResponse = self.call("simulateTransaction", params).await?;
+        if let Some(error) = simulated.error {
+            return Err(format!("Contract call failed in simulation: {}", error).into());
+        }
+
+        let transaction_data = simulated.transaction_data.ok_or("Simulation returned no transaction data")?;
+        let result = simulated.results.into_iter().next().ok_or("Simulation returned no result")?;
+        Ok(Simulation {
+            transaction_data: SorobanTransactionData::from_xdr_base64(transaction_data, Limits::none())?,
+            auth: result.auth.iter()
+                .map(|entry| SorobanAuthorizationEntry::from_xdr_base64(entry, Limits::none()))
+                .collect::<Result<_, _>>()?,
+            result: ScVal::from_xdr_base64(&result.xdr, Limits::none())?,
+        })
+    }
+
+    // What a read-only call returns
+    pub async fn read(&self, source: &str, operation: OperationBody) -> Result<ScVal, VaultError> {
+        Ok(self.simulate(source, operation).await?.result)
+    }
+
+    // Queues a signed envelope; PENDING only means it was accepted for a
+    // ledger, not that it applied
+    pub async fn send_transaction(&self, envelope_xdr: &str) -> Result<SendResult, RpcError> {
+        // Resent by the submitter, which knows whether the envelope may have landed
+        self.call_once("sendTransaction", &serde_json::json!({ "transaction": envelope_xdr })).await
+    }
+
+    pub async fn get_transaction(&self, hash: &str) -> Result<TransactionInfo, RpcError> {
+        self.call("getTransaction", serde_json::json!({ "hash": hash })).await
+    }
+
+    // Current ledger entries for `keys`; keys with no live entry are left out
+    pub async fn get_ledger_entries(&self, keys: &[LedgerKey]) -> Result<Vec<LedgerEntryData>, VaultError> {
+        let keys = keys.iter().map(|key| key.to_xdr_base64(Limits::none())).collect::<Result<Vec<_>, _>>()?;
+        let response: LedgerEntriesResponse = self.call("getLedgerEntries", serde_json::json!({ "keys": keys })).await?;
+        Ok(response.entries.iter()
+            .map(|entry| LedgerEntryData::from_xdr_base64(&entry.xdr, Limits::none()))
+            .collect::<Result<_, _>>()?)
+    }
+
+    // Persistent storage entries of `contract` under `keys`, as (key, value)
+    // pairs; keys with no live entry are left out
+    pub async fn contract_data(&self, contract: &str, keys: &[ScVal]) -> Result<Vec<(ScVal, ScVal)>, VaultError> {
+        let address = ScAddress::Contract(ContractId(contract_id(contract)?));
+        let mut found = Vec::new();
+        for chunk in keys.chunks(MAX_LEDGER_KEYS) {
+            let keys: Vec<LedgerKey> = chunk.iter()
+                .map(|key| LedgerKey::ContractData(LedgerKeyContractData {
+                    contract: address.clone(),
+                    key: key.clone(),
+                    durability: ContractDataDurability::Persistent,
+                }))
+                .collect();
+            found.extend(self.get_ledger_entries(&keys).await?.into_iter().filter_map(|entry| match entry {
+                LedgerEntryData::ContractData(data) => Some((data.key, data.val)),
+                _ => None,
+            }));
+        }
+        Ok(found)
+    }
+
+    // What `holder` owns of a Stellar asset contract's token, read from the
+    // balance entry the asset contract keeps for contract holders
+    pub async fn asset_balance(&self, token: &str, holder: &str) -> Result<u64, VaultError> {
+        let key = vec(vec![symbol("Balance")?, address(holder)?])?;
+        let entries = self.contract_data(token, &[key]).await?;
+        match entries.first() {
+            Some((_, balance)) => field(balance, "amount").and_then(to_amount)
+                .ok_or_else(|| format!("Unreadable {} balance of {}", token, holder).into()),
+            None => Ok(0),
+        }
+    }
+
+    // What code a contract runs, or None if it isn't deployed (or has expired)
+    pub async fn contract_executable(&self, contract: &str) -> Result<Option<Executable>, VaultError> {
+        let key = LedgerKey::ContractData(LedgerKeyContractData {
+            contract: ScAddress::Contract(ContractId(contract_id(contract)?)),
+            key: ScVal::LedgerKeyContractInstance,
+            durability: ContractDataDurability::Persistent,
+        });
+        let entries = self.get_ledger_entries(&[key]).await?;
+        Ok(entries.into_iter().find_map(|entry| match entry {
+            LedgerEntryData::ContractData(data) => match data.val {
+                ScVal::ContractInstance(instance) => Some(match instance.executable {
+                    ContractExecutable::Wasm(hash) => Executable::Wasm(hex(&hash.0)),
+                    ContractExecutable::StellarAsset => Executable::StellarAsset,
+                }),
+                _ => None,
+            },
+            _ => None,
+        }))
+    }
+
+    pub async fn latest_ledger(&self) -> Result<u32, RpcError> {
+        let latest: LatestLedger = self.call("getLatestLedger", serde_json::json!({})).await?;
+        Ok(latest.sequence)
+    }
+
+    // The first ledger the server still has events for
+    pub async fn oldest_ledger(&self) -> Result<u32, RpcError> {
+        let health: Health = self.call("getHealth", serde_json::json!({})).await?;
+        Ok(health.oldest_ledger)
+    }
+
+    // Events `contracts` emitted, from `start_ledger` or after `cursor`. The
+    // server only keeps a few days of events.
+    pub async fn get_events(&self, contracts: &[String], start_ledger: Option<u32>, cursor: Option<&str>, limit: u32) -> Result<EventPage, RpcError> {
+        let mut params = serde_json::json!({
+            "filters": [{ "type": "contract", "contractIds": contracts }],
+            "pagination": { "limit": limit },
+        });
+        match cursor {
+            Some(cursor) => params["pagination"]["cursor"] = cursor.into(),
+            None => params["startLedger"] = start_ledger.unwrap_or(1).into(),
+        }
+        self.call("getEvents", params).await
+    }
+}
+
+// ============================================================================
+// CONTRACT CALLS
+// ============================================================================
+
+pub fn contract_id(contract: &str) -> Result<Hash, VaultError> {
+    let contract = stellar_strkey::Contract::from_string(contract)
+        .map_err(|_| format!("Invalid contract address {} (expected C...)", contract))?;
+    Ok(Hash(contract.0))
+}
+
+// The built-in token contract a classic asset gets on `passphrase`'s network
+pub fn asset_contract(asset: &Asset, passphrase: &str) -> Result<String, VaultError> {
+    derived_contract_id(ContractIdPreimage::Asset(asset.clone()), passphrase)
+}
+
+// The contract `deployer` creates with `salt`, known before it exists
+pub fn deployed_contract(deployer: &str, salt: [u8; 32], passphrase: &str) -> Result<String, VaultError> {
+    derived_contract_id(from_address(deployer, salt)?, passphrase)
+}
+
+fn derived_contract_id(contract_id_preimage: ContractIdPreimage, passphrase: &str) -> Result<String, VaultError> {
+    let preimage = HashIdPreimage::ContractId(HashIdPreimageContractId {
+        network_id: Hash(transaction::network_id(passphrase)),
+        contract_id_preimage,
+    });
+    let id: [u8; 32] = Sha256::digest(preimage.to_xdr(Limits::none())?).into();
+    Ok(stellar_strkey::Contract(id).to_string())
+}
+
+fn from_address(deployer: &str, salt: [u8; 32]) -> Result<ContractIdPreimage, VaultError> {
+    Ok(ContractIdPreimage::Address(ContractIdPreimageFromAddress { address: sc_address(deployer)?, salt: Uint256(salt) }))
+}
+
+pub fn invoke(contract: &str, function: &str, args: Vec<ScVal>) -> Result<OperationBody, VaultError> {
+    host_function(HostFunction::InvokeContract(InvokeContractArgs {
+        contract_address: ScAddress::Contract(ContractId(contract_id(contract)?)),
+        function_name: ScSymbol(function.try_into()?),
+        args: args.try_into()?,
+    }))
+}
+
+// Installs contract code on the network; contracts are created from its hash
+pub fn upload_wasm(wasm: &[u8]) -> Result<OperationBody, VaultError> {
+    host_function(HostFunction::UploadContractWasm(wasm.to_vec().try_into()?))
+}
+
+pub fn wasm_hash(wasm: &[u8]) -> [u8; 32] {
+    Sha256::digest(wasm).into()
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Creates a contract running uploaded code, passing `constructor_args` to
+// its constructor; deployed_contract gives its address
+pub fn create_contract(deployer: &str, wasm_hash: [u8; 32], salt: [u8; 32], constructor_args: Vec<ScVal>) -> Result<OperationBody, VaultError> {
+    host_function(HostFunction::CreateContractV2(CreateContractArgsV2 {
+        contract_id_preimage: from_address(deployer, salt)?,
+        executable: ContractExecutable::Wasm(Hash(wasm_hash)),
+        constructor_args: constructor_args.try_into()?,
+    }))
+}
+
+// Deploys a classic asset's built-in token contract, which anyone may do once
+pub fn create_asset_contract(asset: &Asset) -> Result<OperationBody, VaultError> {
+    host_function(HostFunction::CreateContract(CreateContractArgs {
+        contract_id_preimage: ContractIdPreimage::Asset(asset.clone()),
+        executable: ContractExecutable::StellarAsset,
+    }))
+}
+
+fn host_function(host_function: HostFunction) -> Result<OperationBody, VaultError> {
+    Ok(OperationBody::InvokeHostFunction(InvokeHostFunctionOp { host_function, auth: Default::default() }))
+}
+
+// The call with the authorizations simulation asked for attached
+pub fn authorize(operation: OperationBody, auth: Vec<SorobanAuthorizationEntry>) -> Result<OperationBody, VaultError> {
+    let OperationBody::InvokeHostFunction(mut invoke) = operation else {
+        return Err("Only contract calls carry authorizations".into());
+    };
+    invoke.auth = auth.try_into()?;
+    Ok(OperationBody::InvokeHostFunction(invoke))
+}
+
+// ============================================================================
+// VALUES
+// ============================================================================
+
+// A G... account or C... contract
+pub fn address(address: &str) -> Result<ScVal, VaultError> {
+    Ok(ScVal::Address(sc_address(address)?))
+}
+
+fn sc_address(address: &str) -> Result<ScAddress, VaultError> {
+    if let Ok(contract) = stellar_strkey::Contract::from_string(address) {
+        return Ok(ScAddress::Contract(ContractId(Hash(contract.0))));
+    }
+    let key = stellar_strkey::ed25519::PublicKey::from_string(address)
+        .map_err(|_| format!("Invalid Soroban address {} (expected G... or C...)", address))?;
+    Ok(ScAddress::Account(AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(key.0)))))
+}
+
+pub fn i128(value: i128) -> ScVal {
+    ScVal::I128(Int128Parts { hi: (value >> 64) as i64, lo: value as u64 })
+}
+
+pub fn to_i128(value: &ScVal) -> Option<i128> {
+    match value {
+        ScVal::I128(parts) => Some(((parts.hi as i128) << 64) | parts.lo as i128),
+        _ => None,
+    }
+}
+
+// A token amount as contracts take it; Stellar amounts fit in either
+pub fn amount(stroops: u64) -> ScVal {
+    i128(stroops as i128)
+}
+
+pub fn to_amount(value: &ScVal) -> Option<u64> {
+    to_i128(value).and_then(|amount| u64::try_from(amount).ok())
+}
+
+// The C... or G... form of an address a contract returned
+pub fn to_address(value: &ScVal) -> Option<String> {
+    match value {
+        ScVal::Address(ScAddress::Contract(ContractId(Hash(id)))) => Some(stellar_strkey::Contract(*id).to_string()),
+        ScVal::Address(ScAddress::Account(AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(key))))) =>
+            Some(stellar_strkey::ed25519::PublicKey(*key).to_string()),
+        _ => None,
+    }
+}
+
+pub fn symbol(name: &str) -> Result<ScVal, VaultError> {
+    Ok(ScVal::Symbol(ScSymbol(name.try_into()?)))
+}
+
+pub fn string(value: &str) -> Result<ScVal, VaultError> {
+    Ok(ScVal::String(ScString(value.try_into()?)))
+}
+
+pub fn vec(items: Vec<ScVal>) -> Result<ScVal, VaultError> {
+    Ok(ScVal::Vec(Some(ScVec(items.try_into()?))))
+}
+
+// The elements of a vector or tuple a contract returned
+pub fn items(value: &ScVal) -> Option<&[ScVal]> {
+    match value {
+        ScVal::Vec(Some(items)) => Some(items.as_slice()),
+        _ => None,
+    }
+}
+
+// A contract struct, which is a map keyed by field name in sorted order
+pub fn record(mut fields: Vec<(&str, ScVal)>) -> Result<ScVal, VaultError> {
+    fields.sort_by_key(|(name, _)| *name);
+    let entries = fields.into_iter()
+        .map(|(name, val)| Ok(ScMapEntry { key: symbol(name)?, val }))
+        .collect::<Result<Vec<_>, VaultError>>()?;
+    Ok(ScVal::Map(Some(ScMap(entries.try_into()?))))
+}
+
+// A contract map; the host wants its keys in sorted order
+pub fn map(mut entries: Vec<(ScVal, ScVal)>) -> Result<ScVal, VaultError> {
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let entries: Vec<ScMapEntry> = entries.into_iter().map(|(key, val)| ScMapEntry { key, val }).collect();
+    Ok(ScVal::Map(Some(ScMap(entries.try_into()?))))
+}
+
+// A field of a contract struct
+pub fn field<'a>(value: &'a ScVal, name: &str) -> Option<&'a ScVal> {
+    let ScVal::Map(Some(map)) = value else {
+        return None;
+    };
+    map.iter().find(|entry| matches!(&entry.key, ScVal::Symbol(symbol) if symbol.0.as_slice() == name.as_bytes()))
+        .map(|entry| &entry.val)
+}
+
+// The value under a u32 key of a contract map
+pub fn entry(value: &ScVal, key: u32) -> Option<&ScVal> {
+    let ScVal::Map(Some(map)) = value else {
+        return None;
+    };
+    map.iter().find(|entry| entry.key == ScVal::U32(key)).map(|entry| &entry.val)
+}
+
+// A short rendering of a contract value, for display
+pub fn describe(value: &ScVal) -> String {
+    match value {
+        ScVal::Bool(b) => b.to_string(),
+        ScVal::Void => "()".to_string(),
+        ScVal::U32(n) => n.to_string(),
+        ScVal::I32(n) => n.to_string(),
+        ScVal::U64(n) => n.to_string(),
+        ScVal::I64(n) => n.to_string(),
+        ScVal::I128(_) => to_i128(value).map_or_else(String::new, |n| n.to_string()),
+        ScVal::Symbol(symbol) => String::from_utf8_lossy(symbol.0.as_slice()).into_owned(),
+        ScVal::String(string) => format!("{:?}", String::from_utf8_lossy(string.0.as_slice())),
+        ScVal::Address(_) => to_address(value).unwrap_or_default(),
+        ScVal::Vec(Some(items)) => format!("[{}]", items.iter().map(describe).collect::<Vec<_>>().join(", ")),
+        ScVal::Map(Some(map)) => format!("{{{}}}", map.iter()
+            .map(|entry| format!("{}: {}", describe(&entry.key), describe(&entry.val)))
+            .collect::<Vec<_>>().join(", ")),
+        other => format!("{:?}", other.discriminant()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_round_trip_and_records_sort_their_fields() {
+        assert_eq!(to_i128(&i128(-5)), Some(-5));
+        assert_eq!(to_i128(&i128(i128::MAX)), Some(i128::MAX));
+        assert_eq!(to_amount(&i128(-1)), None);
+
+        let pool = stellar_strkey::Contract([9; 32]).to_string();
+        assert_eq!(to_address(&address(&pool).unwrap()).as_deref(), Some(pool.as_str()));
+
+        let request = record(vec![("request_type", ScVal::U32(0)), ("amount", amount(10))]).unwrap();
+        assert_eq!(field(&request, "amount").and_then(to_amount), Some(10));
+        assert_eq!(describe(&request), "{amount: 10, request_type: 0}");
+    }
+}
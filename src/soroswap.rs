@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+use stellar_xdr::curr::{OperationBody, ScVal};
+
+use crate::assets::AssetId;
+use crate::error::VaultError;
+use crate::soroban;
+
+// ============================================================================
+// ROUTER CALLS
+// ============================================================================
+
+// Soroswap pairs are constant-product pools of two token contracts, reached
+// through the router. Amounts are in stroops and deadlines in Unix seconds.
+
+pub fn pair_for(router: &str, token_a: &str, token_b: &str) -> Result<OperationBody, VaultError> {
+    soroban::invoke(router, "router_pair_for", vec![soroban::address(token_a)?, soroban::address(token_b)?])
+}
+
+// What `amount_in` of the path's first token buys of each token along it
+pub fn get_amounts_out(router: &str, amount_in: u64, path: &[&str]) -> Result<OperationBody, VaultError> {
+    let path = path.iter().map(|token| soroban::address(token)).collect::<Result<_, _>>()?;
+    soroban::invoke(router, "router_get_amounts_out", vec![soroban::amount(amount_in), soroban::vec(path)?])
+}
+
+pub fn swap(router: &str, account: &str, amount_in: u64, min_out: u64, path: &[&str], deadline: u64) -> Result<OperationBody, VaultError> {
+    let path = path.iter().map(|token| soroban::address(token)).collect::<Result<_, _>>()?;
+    soroban::invoke(router, "swap_exact_tokens_for_tokens", vec![
+        soroban::amount(amount_in),
+        soroban::amount(min_out),
+        soroban::vec(path)?,
+        soroban::address(account)?,
+        ScVal::U64(deadline),
+    ])
+}
+
+// Adds up to the desired amounts at the pair's price, failing if either side
+// would go in below its minimum
+pub fn add_liquidity(router: &str, account: &str, (token_a, desired_a, min_a): (&str, u64, u64),
+                     (token_b, desired_b, min_b): (&str, u64, u64), deadline: u64) -> Result<OperationBody, VaultError> {
+    soroban::invoke(router, "add_liquidity", vec![
+        soroban::address(token_a)?,
+        soroban::address(token_b)?,
+        soroban::amount(desired_a),
+        soroban::amount(desired_b),
+        soroban::amount(min_a),
+        soroban::amount(min_b),
+        soroban::address(account)?,
+        ScVal::U64(deadline),
+    ])
+}
+
+pub fn remove_liquidity(router: &str, account: &str, (token_a, min_a): (&str, u64), (token_b, min_b): (&str, u64),
+                        shares: u64, deadline: u64) -> Result<OperationBody, VaultError> {
+    soroban::invoke(router, "remove_liquidity", vec![
+        soroban::address(token_a)?,
+        soroban::address(token_b)?,
+        soroban::amount(shares),
+        soroban::amount(min_a),
+        soroban::amount(min_b),
+        soroban::address(account)?,
+        ScVal::U64(deadline),
+    ])
+}
+
+// The last amount of a router_get_amounts_out result: what the swap delivers
+pub fn amount_out(amounts: &ScVal) -> Result<u64, VaultError> {
+    soroban::items(amounts)
+        .and_then(|amounts| amounts.last())
+        .and_then(soroban::to_amount)
+        .ok_or_else(|| "Router returned no amount out".into())
+}
+
+// ============================================================================
+// PAIRS
+// ============================================================================
+
+pub fn token_0(pair: &str) -> Result<OperationBody, VaultError> {
+    soroban::invoke(pair, "token_0", Vec::new())
+}
+
+pub fn get_reserves(pair: &str) -> Result<OperationBody, VaultError> {
+    soroban::invoke(pair, "get_reserves", Vec::new())
+}
+
+// Pair contracts are also the token for their shares
+pub fn total_shares(pair: &str) -> Result<OperationBody, VaultError> {
+    soroban::invoke(pair, "total_supply", Vec::new())
+}
+
+pub fn shares_of(pair: &str, account: &str) -> Result<OperationBody, VaultError> {
+    soroban::invoke(pair, "balance", vec![soroban::address(account)?])
+}
+
+// A pair's reserves with the vault's asset as the base
+#[derive(Debug, Clone)]
+pub struct PairState {
+    pub pair: String,
+    pub base_reserve: u64,
+    pub counter_reserve: u64,
+    pub total_shares: u64,
+}
+
+impl PairState {
+    // Counter per base unit
+    pub fn price(&self) -> f64 {
+        self.counter_reserve as f64 / self.base_reserve as f64
+    }
+}
+
+// get_reserves' (token_0, token_1) pair, swapped if `base_is_token_0` is false
+pub fn reserves(value: &ScVal, base_is_token_0: bool) -> Result<(u64, u64), VaultError> {
+    let (reserve_0, reserve_1) = match soroban::items(value) {
+        Some([reserve_0, reserve_1]) => (soroban::to_amount(reserve_0), soroban::to_amount(reserve_1)),
+        _ => (None, None),
+    };
+    let (reserve_0, reserve_1) = reserve_0.zip(reserve_1).ok_or("Pair returned unreadable reserves")?;
+    Ok(if base_is_token_0 { (reserve_0, reserve_1) } else { (reserve_1, reserve_0) })
+}
+
+// ============================================================================
+// POSITION
+// ============================================================================
+
+// The vault's liquidity in the Soroswap pair of its asset and `counter` (None
+// for XLM), both as their Stellar asset contracts. Pool fees stay in the
+// reserves, so yield is growth in the shares' value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoroswapPosition {
+    pub router: String,
+    pub counter: Option<AssetId>,
+    #[serde(default)]
+    pub shares: u64,
+    // Vault asset put into the pair and not yet taken out
+    #[serde(default)]
+    pub deposited: u64,
+    // Value at the last sync, in the vault's asset
+    #[serde(default)]
+    pub value: u64,
+    // Highest value already credited or deposited; growth above it is yield
+    #[serde(default)]
+    pub high_water: u64,
+}
+
+impl SoroswapPosition {
+    pub fn new(router: &str, counter: Option<AssetId>) -> Result<Self, VaultError> {
+        soroban::contract_id(router)?;
+        Ok(SoroswapPosition { router: router.to_string(), counter, shares: 0, deposited: 0, value: 0, high_water: 0 })
+    }
+
+    pub fn record_deposit(&mut self, amount: u64) {
+        self.deposited += amount;
+        self.high_water += amount;
+    }
+
+    pub fn record_withdrawal(&mut self) {
+        self.shares = 0;
+        self.deposited = 0;
+        self.value = 0;
+        self.high_water = 0;
+    }
+
+    // Fee growth since the last harvest
+    pub fn take_unharvested(&mut self) -> u64 {
+        let gain = self.value.saturating_sub(self.high_water);
+        self.high_water = self.high_water.max(self.value);
+        gain
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserves_follow_the_base_token() {
+        let value = soroban::vec(vec![soroban::amount(5), soroban::amount(9)]).unwrap();
+        assert_eq!(reserves(&value, true).unwrap(), (5, 9));
+        assert_eq!(reserves(&value, false).unwrap(), (9, 5));
+        assert!(reserves(&soroban::vec(vec![soroban::amount(5)]).unwrap(), true).is_err());
+
+        let router = stellar_strkey::Contract([3; 32]).to_string();
+        let mut position = SoroswapPosition::new(&router, None).unwrap();
+        position.record_deposit(1_000);
+        position.value = 1_003;
+        assert_eq!(position.take_unharvested(), 3);
+        assert_eq!(position.take_unharvested(), 0);
+    }
+}
@@ -0,0 +1,3774 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::{SystemTime, UNIX_EPOCH};
+use stellar_xdr::curr::Memo;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+
+use crate::{amm, assets, blend, contract_index, events, federation, fees, horizon, ingest, insurance, journal, muxed, path_payment, pending_deposits, rebalance, reload, reserves, sdex, soroban, soroswap, strategy, transaction, vault_contract};
+use crate::accounting::DepositQuote;
+use crate::amount::{mul_div, MathError, Rounding, Shares, Stroops};
+use crate::assets::AssetId;
+use crate::builder::{check_strategies, StellarVaultBuilder, MAX_EARLY_WITHDRAWAL_PENALTY_BPS, MAX_MANAGEMENT_FEE_BPS, MAX_PERFORMANCE_FEE_BPS};
+use crate::claims::{ClaimBook, ClaimStatus};
+use crate::client::{check_memo_not_required, UserRegistry, PAYMENT_TIMEOUT_SECS};
+use crate::contract_index::{ContractEvent, ContractIndex, IndexEvent};
+use crate::error::{DepositError, VaultError};
+use crate::events::{EventRecord, VaultEvent, YieldCredit, YieldPayout};
+use crate::fee_strategy::{FeeEstimate, FeeStrategy};
+use crate::horizon::{HorizonClient, Payment};
+use crate::ingest::Incoming;
+use crate::insurance::{CoveragePolicy, CoverageReport, InsuranceInvestment, INSURANCE_VAULT};
+use crate::idempotency::{IdempotencyBook, KeyedDeposit, Outcome};
+use crate::journal::{JournalEntry, Located, OperationJournal, OperationKind, RECOVERY_SCAN, RESOLVE_AFTER_SECS};
+use crate::muxed::DepositRoutes;
+use crate::network::Network;
+use crate::notify::Notification;
+use crate::oracle::PriceBook;
+use crate::pending_deposits::{DepositBook, DepositStage, PendingDeposit};
+use crate::rebalance::RebalanceMove;
+use crate::reconcile::{Check, Parts};
+use crate::reserves::{InclusionProof, SignedSummary};
+use crate::screening::{AddressList, AddressLists, ListedAddress, Screened};
+use crate::recurring::RecurringBook;
+use crate::share_asset::{ShareAsset, ShareIssuance};
+use crate::timelock::{AdminChange, QueuedChange, StrategyTarget, Timelock};
+use crate::signer::Signer;
+use crate::storage::{DepositRecord, PositionRecord, Store, VaultState, WithdrawalRecord, YieldRecord};
+use crate::strategy::HarvestContext;
+use crate::submission::{SubmitError, Submitter};
+use crate::transaction::Keypair;
+use crate::vault::{DepositPreview, HarvestReport, WithdrawalOutcome, WithdrawalPreview, WithdrawalReceipt};
+use crate::withdrawal_queue::WithdrawalQueue;
+use crate::{format_duration, unix_now, Portfolio, PositionSummary, RiskLevel, StellarClient, StrategyAllocation, TransactionReceipt, UserPosition, Vault};
+
+// ============================================================================
+// STELLARVAULT
+// ============================================================================
+
+/// The vaults, the users' positions in them and the vault account that holds
+/// their funds. Deposits, withdrawals, harvests, fees and insurance all go
+/// through here, and every change is logged and persisted to its [`Store`].
+pub struct StellarVault {
+    pub vaults: HashMap<RiskLevel, Vault>,
+    pub user_positions: HashMap<(String, RiskLevel), UserPosition>,
+    pub insurance_pool: u64,
+    // Premiums and penalties from vaults holding other assets, by CODE:ISSUER;
+    // insurance_pool, and so claims and cover, are XLM only
+    pub asset_insurance: BTreeMap<String, u64>,
+    pub insurance_investment: InsuranceInvestment,
+    pub coverage_policy: CoveragePolicy,
+    pub claims: ClaimBook,
+    pub users: UserRegistry,
+    pub vault_signer: Option<StellarClient>,
+    pub vault_address: String,
+    // Operators whose keys, once unlocked, co-sign for a multisig vault account
+    pub vault_signer_addresses: Vec<String>,
+    pub treasury_address: Option<String>,
+    // Memo the treasury's federation record asks payments to carry
+    pub treasury_memo: Option<Memo>,
+    // When set, vault shares are mirrored as a Stellar asset from this issuer
+    pub share_issuer: Option<String>,
+    pub share_issuance: ShareIssuance,
+    pub withdrawal_queue: WithdrawalQueue,
+    pub pending_deposits: DepositBook,
+    // Paging token of the last vault payment the ingestion stream handled
+    pub ingest_cursor: Option<String>,
+    pub federation: federation::Resolver,
+    // Vault sub-addresses (M...) handed out to users, by muxed ID
+    pub deposit_routes: DepositRoutes,
+    // The vault contract's shares and pools, rebuilt from its events
+    pub contract_index: ContractIndex,
+    // Recurring deposits and withdrawals users have scheduled
+    pub recurring: RecurringBook,
+    // Payouts written down before their payment goes out
+    pub journal: OperationJournal,
+    // API deposit requests by the key their client sent, with their outcome
+    pub idempotency: IdempotencyBook,
+    // How long a key is remembered
+    pub idempotency_window_secs: u64,
+    // Sensitive admin changes waiting out their delay
+    pub timelock: Timelock,
+    pub admin_delay_secs: u64,
+    // Accounts deposits and payouts are screened against
+    pub address_lists: AddressLists,
+    // Only allowlisted accounts may deposit or be paid
+    pub allowlist_only: bool,
+    // Sequence number of the last event appended to the log
+    pub event_seq: u64,
+    // Receives a copy of each event once it is in the log, for live subscribers
+    pub event_feed: Option<UnboundedSender<EventRecord>>,
+    // Where deposits, withdrawals, harvests, breaker trips and claim
+    // decisions are sent to be delivered to operators
+    pub notifications: Option<UnboundedSender<Notification>>,
+    pub network: Network,
+    pub horizon: HorizonClient,
+    // Shared by every signing client, so the vault key and a user key that
+    // are the same account never hand out the same sequence number
+    pub submitter: Submitter,
+    // Account whose key, once unlocked, pays for fee bumps
+    pub fee_payer_address: Option<String>,
+    // Slippage allowed when a deposit is converted with a path payment
+    pub max_slippage_bps: u16,
+    // Where contract calls are simulated, when a strategy makes them
+    pub soroban: Option<soroban::RpcClient>,
+    // When set, shares are minted and burned by this contract, not locally
+    pub vault_contract: Option<String>,
+    // The contract's share token for each risk level, looked up at startup
+    pub share_tokens: Vec<(RiskLevel, String)>,
+    pub storage: Box<dyn Store>,
+    // Latest USD prices of the vault assets, when an oracle feeds them
+    pub prices: PriceBook,
+}
+
+impl StellarVault {
+    /// A vault for the account at `vault_address` running the standard
+    /// tiers, with its state restored from the default store, or rebuilt
+    /// from its event log. [`StellarVaultBuilder`] defines custom vaults.
+    pub fn new(vault_address: &str, network: &Network, fee_strategy: FeeStrategy) -> Result<Self, VaultError> {
+        StellarVaultBuilder::new(vault_address, network)
+            .fee_strategy(fee_strategy)
+            .build()
+    }
+
+    /// Registers a user who signs with a local secret key.
+    pub fn register_user(&mut self, secret_key: &str, public_key: &str) -> Result<(), VaultError> {
+        self.users.register(secret_key, public_key, &self.network, &self.submitter)
+    }
+
+    pub fn register_signer(&mut self, signer: Box<dyn Signer>) -> Result<String, VaultError> {
+        self.users.register_signer(signer, &self.network, &self.submitter)
+    }
+
+    // Withdrawals are paid from the vault account, so they need its key or,
+    // if it is multisig, enough operator keys to meet its medium threshold.
+    // Each key added here signs every outgoing vault payment.
+    pub fn add_vault_signer(&mut self, secret_key: &str) -> Result<String, VaultError> {
+        let mut client = match self.vault_signer.take() {
+            Some(client) => client,
+            None => StellarClient::for_account(&self.vault_address, &self.network, &self.submitter)?,
+        };
+        let result = client.add_secret_key(secret_key);
+        if !client.signers.is_empty() {
+            self.vault_signer = Some(client);
+        }
+        result
+    }
+
+    // Drops a key the vault account no longer accepts, such as a rotated-out one
+    pub fn remove_vault_signer(&mut self, public_key: &str) -> bool {
+        let Some(client) = self.vault_signer.as_mut() else {
+            return false;
+        };
+        let removed = client.remove_signer(public_key);
+        if client.signers.is_empty() {
+            self.vault_signer = None;
+        }
+        removed
+    }
+
+    // A vault key held outside this process, e.g. by an external signing service
+    pub fn add_external_vault_signer(&mut self, signer: Box<dyn Signer>) -> Result<String, VaultError> {
+        let client = match self.vault_signer.as_mut() {
+            Some(client) => client,
+            None => self.vault_signer.insert(StellarClient::for_account(&self.vault_address, &self.network, &self.submitter)?),
+        };
+        Ok(client.add_signer(signer))
+    }
+
+    pub fn set_fee_payer(&mut self, secret_key: &str) -> Result<(), VaultError> {
+        let keypair = Keypair::from_secret(secret_key)?;
+        if self.fee_payer_address.as_ref().is_some_and(|address| *address != keypair.public_key()) {
+            return Err(VaultError::Validation("Secret key does not belong to the configured fee payer".into()));
+        }
+        self.fee_payer_address = Some(keypair.public_key());
+        self.submitter.set_fee_payer(keypair);
+        Ok(())
+    }
+
+    // Resolves name*domain addresses through federation, printing what they
+    // stand for; anything else must be a G... or M... address
+    pub async fn resolve_address(&self, address: &str) -> Result<(String, Option<Memo>), VaultError> {
+        if !federation::is_federation_address(address) {
+            muxed::parse(address)?;
+            return Ok((address.to_string(), None));
+        }
+        let record = self.federation.resolve(address).await?;
+        say!("   🔎 {} → {}", record.stellar_address, record.account_id);
+        if let Some(memo) = record.memo.as_ref().and_then(transaction::describe_memo) {
+            say!("      Required memo: {}", memo);
+        }
+        Ok((record.account_id, record.memo))
+    }
+
+    // Switching assets would misprice every existing share, so only empty
+    // vaults can change what they hold
+    pub fn set_vault_asset(&mut self, risk: RiskLevel, asset: Option<AssetId>) -> Result<(), VaultError> {
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        if vault.asset != asset && vault.total_shares > 0 {
+            return Err(VaultError::Validation(format!("The {:?} Risk Vault still holds {} and can't switch to {}", risk,
+                vault.asset_code(), asset.as_ref().map_or("XLM", |a| a.code.as_str()))));
+        }
+        vault.asset = asset;
+        Ok(())
+    }
+
+    // Gives the market-making strategy `allocation` percent of the vault's
+    // strategy funds, scaling the other strategies down to make room. Fills
+    // recorded for the same pair are kept.
+    pub fn enable_market_making(&mut self, risk: RiskLevel, maker: sdex::MarketMaker, allocation: u8) -> Result<(), VaultError> {
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        if maker.counter == vault.asset {
+            return Err(VaultError::Validation(format!("The {:?} Risk Vault can't make a market in {} against itself", risk, vault.asset_code())));
+        }
+        vault.set_add_on_allocation(strategy::SDEX_MARKET_MAKING, allocation)?;
+        match &mut vault.market_making {
+            Some(existing) if existing.counter == maker.counter => existing.spread_bps = maker.spread_bps,
+            _ => vault.market_making = Some(maker),
+        }
+        Ok(())
+    }
+
+    // Runs the SoroswapLiquidity strategy with `allocation` percent of the
+    // vault's funds. A position in the same pair is kept.
+    pub fn enable_soroswap(&mut self, risk: RiskLevel, position: soroswap::SoroswapPosition, allocation: u8) -> Result<(), VaultError> {
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        if position.counter == vault.asset {
+            return Err(VaultError::Validation(format!("A pair needs two different assets, not {} twice", vault.asset_code())));
+        }
+        let same_pair = vault.soroswap.as_ref()
+            .is_some_and(|existing| existing.router == position.router && existing.counter == position.counter);
+        if !same_pair && vault.soroswap.as_ref().is_some_and(|existing| existing.shares > 0) {
+            return Err(VaultError::Validation("The vault still holds liquidity in its current Soroswap pair; withdraw it with 'soroswap' first".into()));
+        }
+        vault.set_add_on_allocation(strategy::SOROSWAP_LIQUIDITY, allocation)?;
+        if !same_pair {
+            vault.soroswap = Some(position);
+        }
+        Ok(())
+    }
+
+    // Points the vault's AquaLiquidityPool strategy at the on-chain pool
+    // pairing its asset with `counter`. A position in the same pool is kept.
+    pub fn enable_liquidity_pool(&mut self, risk: RiskLevel, counter: Option<AssetId>) -> Result<(), VaultError> {
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        if !vault.strategies.iter().any(|s| s.strategy == strategy::AQUA_LIQUIDITY_POOL) {
+            return Err(VaultError::Validation(format!("The {:?} Risk Vault has no AquaLiquidityPool strategy", risk)));
+        }
+        if counter == vault.asset {
+            return Err(VaultError::Validation(format!("A pool needs two different assets, not {} twice", vault.asset_code())));
+        }
+        match &vault.liquidity_pool {
+            Some(position) if position.counter == counter => {}
+            Some(position) if position.shares > 0 => {
+                return Err(VaultError::Validation("The vault still holds shares in its current pool; withdraw them with 'amm' first".into()));
+            }
+            _ => vault.liquidity_pool = Some(amm::PoolPosition::new(counter)),
+        }
+        Ok(())
+    }
+
+    // Points the vault's YieldBloxLending strategy at a Blend pool. A
+    // position in the same pool is kept.
+    pub fn enable_blend(&mut self, risk: RiskLevel, pool: &str) -> Result<(), VaultError> {
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        if !vault.strategies.iter().any(|s| s.strategy == strategy::YIELDBLOX_LENDING) {
+            return Err(VaultError::Validation(format!("The {:?} Risk Vault has no YieldBloxLending strategy", risk)));
+        }
+        match &vault.lending {
+            Some(position) if position.pool == pool => {}
+            Some(position) if position.b_tokens > 0 => {
+                return Err(VaultError::Validation(format!("The vault still has funds lent to pool {}; withdraw them with 'blend' first", position.pool)));
+            }
+            _ => vault.lending = Some(blend::LendingPosition::new(pool)?),
+        }
+        Ok(())
+    }
+
+    // XLM insurance pool, or the premiums held for an asset vault
+    pub fn insurance_balance(&self, asset: Option<&AssetId>) -> Stroops {
+        match asset {
+            Some(asset) => Stroops(self.asset_insurance.get(&asset.to_string()).copied().unwrap_or(0)),
+            None => Stroops(self.insurance_pool),
+        }
+    }
+
+    pub fn set_insurance_balance(&mut self, asset: Option<&AssetId>, balance: Stroops) {
+        match asset {
+            Some(asset) => {
+                self.asset_insurance.insert(asset.to_string(), balance.0);
+            }
+            None => self.insurance_pool = balance.0,
+        }
+    }
+
+    // Deposits held across all vaults, per asset
+    pub fn asset_totals(&self) -> BTreeMap<String, u64> {
+        let mut totals = BTreeMap::new();
+        for vault in self.vaults.values() {
+            let asset = vault.asset.as_ref().map_or("XLM".to_string(), AssetId::to_string);
+            *totals.entry(asset).or_default() += vault.total_value;
+        }
+        totals
+    }
+
+    pub fn set_treasury_address(&mut self, treasury_address: &str) -> Result<(), VaultError> {
+        // A muxed treasury lets an exchange or custodian attribute fee payments
+        if !muxed::is_valid(treasury_address) {
+            return Err(VaultError::Validation("Invalid treasury address format (must be a G... or M... address)".into()));
+        }
+        self.treasury_address = Some(treasury_address.to_string());
+        Ok(())
+    }
+
+    pub fn set_share_issuer(&mut self, issuer: &str) -> Result<(), VaultError> {
+        if !issuer.starts_with('G') || issuer.len() != 56 {
+            return Err(VaultError::Validation("Invalid share issuer format (must start with G and be 56 chars)".into()));
+        }
+        self.share_issuer = Some(issuer.to_string());
+        Ok(())
+    }
+
+    pub fn share_asset(&self, risk: RiskLevel) -> Option<ShareAsset> {
+        self.share_issuer.as_ref().map(|issuer| ShareAsset::for_vault(risk, issuer))
+    }
+
+    pub fn snapshot(&self) -> VaultState {
+        VaultState {
+            vaults: self.vaults.values().cloned().collect(),
+            positions: self.user_positions.iter()
+                .map(|((user, risk), position)| PositionRecord {
+                    user: user.clone(),
+                    risk: *risk,
+                    position: position.clone(),
+                })
+                .collect(),
+            insurance_pool: self.insurance_pool,
+            asset_insurance: self.asset_insurance.clone(),
+            claims: self.claims.clone(),
+            insurance_investment: self.insurance_investment.clone(),
+            share_issuance: self.share_issuance.clone(),
+            withdrawal_queue: self.withdrawal_queue.clone(),
+            pending_deposits: self.pending_deposits.clone(),
+            ingest_cursor: self.ingest_cursor.clone(),
+            deposit_routes: self.deposit_routes.clone(),
+            contract_index: self.contract_index.clone(),
+            recurring: self.recurring.clone(),
+            journal: self.journal.clone(),
+            idempotency: self.idempotency.clone(),
+            // One reached through federation is resolved again at startup,
+            // since the memo it needs isn't saved
+            treasury_address: self.treasury_address.clone().filter(|_| self.treasury_memo.is_none()),
+            timelock: self.timelock.clone(),
+            admin_delay_secs: Some(self.admin_delay_secs),
+            address_lists: self.address_lists.clone(),
+        }
+    }
+
+    pub fn restore(&mut self, state: VaultState) {
+        for vault in state.vaults {
+            self.vaults.insert(vault.risk_level, vault);
+        }
+        self.user_positions = state.positions.into_iter()
+            .map(|record| ((record.user, record.risk), record.position))
+            .collect();
+        // State saved before deposits were locked one by one locks the
+        // whole position from its last deposit, as it did then
+        for position in self.user_positions.values_mut() {
+            if position.last_deposit_at > 0 {
+                let (shares, deposited_at) = (position.shares, std::mem::take(&mut position.last_deposit_at));
+                position.lock(shares, deposited_at);
+            }
+        }
+        self.insurance_pool = state.insurance_pool;
+        self.asset_insurance = state.asset_insurance;
+        self.claims = state.claims;
+        self.insurance_investment = state.insurance_investment;
+        self.share_issuance = state.share_issuance;
+        self.withdrawal_queue = state.withdrawal_queue;
+        self.pending_deposits = state.pending_deposits;
+        self.ingest_cursor = state.ingest_cursor;
+        self.deposit_routes = state.deposit_routes;
+        self.contract_index = state.contract_index;
+        self.recurring = state.recurring;
+        self.journal = state.journal;
+        self.idempotency = state.idempotency;
+        if state.treasury_address.is_some() {
+            self.treasury_address = state.treasury_address;
+        }
+        self.timelock = state.timelock;
+        if let Some(admin_delay_secs) = state.admin_delay_secs {
+            self.admin_delay_secs = admin_delay_secs;
+        }
+        self.address_lists = state.address_lists;
+    }
+
+    pub fn persist(&self) -> Result<(), VaultError> {
+        self.storage.save(&self.snapshot())
+    }
+
+    // Appends to the event log. Like the history records, a failed write is
+    // reported but never undoes an operation whose funds already moved.
+    pub fn log_event(&mut self, event: VaultEvent) {
+        if let Some(notification) = Notification::from_event(&event, &self.vaults) {
+            self.notify(notification);
+        }
+        let record = EventRecord {
+            seq: self.event_seq + 1,
+            timestamp: unix_now(),
+            event,
+        };
+        match self.storage.append_event(&record) {
+            Ok(()) => {
+                self.event_seq = record.seq;
+                tracing::info!(seq = record.seq, kind = record.event.kind(),
+                    event = %serde_json::to_string(&record.event).unwrap_or_default(), "vault event");
+                if let Some(feed) = &self.event_feed {
+                    let _ = feed.send(record);
+                }
+            }
+            Err(e) => say!("   ⚠️  Could not append {} event to the log: {}", record.event.kind(), e),
+        }
+    }
+
+    pub fn notify(&self, notification: Notification) {
+        if let Some(notifications) = &self.notifications {
+            let _ = notifications.send(notification);
+        }
+    }
+
+    // Replaces balances with the ones rebuilt from the log; settings such as
+    // auto-compounding aren't events and are kept from the current state
+    pub fn apply_replay(&mut self, replayed: events::Replay) {
+        let mut positions = replayed.positions;
+        for (key, position) in positions.iter_mut() {
+            if let Some(current) = self.user_positions.get(key) {
+                position.auto_compound = current.auto_compound;
+            }
+        }
+
+        for (risk, replayed_vault) in replayed.vaults {
+            if let Some(vault) = self.vaults.get_mut(&risk) {
+                vault.total_value = replayed_vault.total_value;
+                vault.total_shares = replayed_vault.total_shares;
+                vault.liquid_reserve = replayed_vault.liquid_reserve;
+                vault.last_harvest = replayed_vault.last_harvest;
+                vault.strategies = replayed_vault.strategies;
+                vault.fee_accrual = replayed_vault.fee_accrual;
+            }
+        }
+        self.user_positions = positions;
+        self.insurance_pool = replayed.insurance_pool;
+        self.asset_insurance = replayed.asset_insurance;
+        self.insurance_investment.shares = replayed.insurance_shares;
+        self.insurance_investment.principal = replayed.insurance_principal;
+    }
+
+    pub fn replay_events(&self) -> Result<(events::Replay, usize), VaultError> {
+        let events = self.storage.load_events()?;
+        let replayed = events::replay(&self.vaults, &events)?;
+        Ok((replayed, events.len()))
+    }
+
+    pub fn rebuild_from_events(&mut self) -> Result<usize, VaultError> {
+        let (replayed, count) = self.replay_events()?;
+        self.apply_replay(replayed);
+        self.persist()?;
+        Ok(count)
+    }
+
+    // Accrues the management fee and logs it, so replay sees the same TVL
+    pub fn accrue_fees(&mut self, risk: RiskLevel) -> Result<(), VaultError> {
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let fee = fees::accrue_management_fee(vault, unix_now())?;
+        if fee > 0 {
+            self.log_event(VaultEvent::ManagementFeeAccrued { risk, amount_stroops: fee });
+        }
+        Ok(())
+    }
+
+    // Refuses a deposit or payout involving an address the lists rule out,
+    // logging the refusal so it is part of the audit trail
+    pub fn screen(&mut self, operation: Screened, address: &str, risk: RiskLevel, amount_stroops: u64) -> Result<(), VaultError> {
+        let Err(reason) = self.address_lists.check(address, self.allowlist_only) else {
+            return Ok(());
+        };
+        say!("   ⛔ {} blocked: {}", operation, reason);
+        self.log_event(VaultEvent::OperationBlocked {
+            operation,
+            address: address.to_string(),
+            risk,
+            amount_stroops,
+            reason: reason.clone(),
+        });
+        Err(VaultError::Validation(format!("{} blocked: {}", operation, reason)))
+    }
+
+    pub fn list_address(&mut self, list: AddressList, address: &str, note: &str) -> Result<(), VaultError> {
+        self.address_lists.add(list, address, note, unix_now())?;
+        self.persist()
+    }
+
+    pub fn unlist_address(&mut self, list: AddressList, address: &str) -> Result<ListedAddress, VaultError> {
+        let removed = self.address_lists.remove(list, address)?;
+        self.persist()?;
+        Ok(removed)
+    }
+
+    // The latest operations the lists refused, newest first
+    pub fn blocked_operations(&self, limit: usize) -> Result<Vec<EventRecord>, VaultError> {
+        let events = self.storage.load_events()?;
+        Ok(events.into_iter().rev()
+            .filter(|record| matches!(record.event, VaultEvent::OperationBlocked { .. }))
+            .take(limit)
+            .collect())
+    }
+
+    pub fn check_deposit_limits(&self, user: &str, risk: RiskLevel, amount_stroops: u64) -> Result<(), VaultError> {
+        let vault = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+
+        if amount_stroops < vault.min_deposit {
+            return Err(DepositError::BelowMinimum { amount: amount_stroops, minimum: vault.min_deposit }.into());
+        }
+
+        if let Some(cap) = vault.max_tvl {
+            if vault.total_value.checked_add(amount_stroops).ok_or(MathError::Overflow)? > cap {
+                return Err(DepositError::VaultCapExceeded { tvl: vault.total_value, amount: amount_stroops, cap }.into());
+            }
+        }
+
+        if let Some(cap) = vault.per_user_cap {
+            let position_value = self.get_position(user, risk).map(|p| p.value_stroops).unwrap_or(0);
+            if position_value.checked_add(amount_stroops).ok_or(MathError::Overflow)? > cap {
+                return Err(DepositError::UserCapExceeded { position_value, amount: amount_stroops, cap }.into());
+            }
+        }
+
+        if vault.max_tvl_usd.is_some() || vault.per_user_cap_usd.is_some() {
+            let now = unix_now();
+            let usd = |amount: u64| -> Result<u64, VaultError> {
+                let amount = amount.checked_add(amount_stroops).ok_or(MathError::Overflow)?;
+                self.prices.usd_value(vault.asset.as_ref(), amount, now)
+                    .ok_or_else(|| DepositError::NoPrice { asset: vault.asset_code().to_string() }.into())
+            };
+            if let Some(cap_usd) = vault.max_tvl_usd {
+                let value_usd = usd(vault.total_value)?;
+                if value_usd > cap_usd {
+                    return Err(DepositError::UsdCapExceeded { scope: "vault TVL", value_usd, cap_usd }.into());
+                }
+            }
+            if let Some(cap_usd) = vault.per_user_cap_usd {
+                let value_usd = usd(self.get_position(user, risk).map(|p| p.value_stroops).unwrap_or(0))?;
+                if value_usd > cap_usd {
+                    return Err(DepositError::UsdCapExceeded { scope: "your position", value_usd, cap_usd }.into());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // ensure_operational without tripping the breaker, for previews
+    pub fn check_operational(&self, risk: RiskLevel) -> Result<(), VaultError> {
+        let vault = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        if vault.circuit.paused {
+            let reason = vault.circuit.reason.as_deref().unwrap_or("paused by admin");
+            return Err(VaultError::Validation(format!("{:?} Risk Vault is paused: {}", risk, reason)));
+        }
+        if let Some(moved) = vault.circuit.exceeded(vault.get_share_price()) {
+            return Err(VaultError::Validation(format!(
+                "{:?} Risk Vault would pause: share price moved {:.2}% since the last operation (limit {:.2}%)",
+                risk, moved as f64 / 100.0, vault.circuit.max_price_move_bps as f64 / 100.0)));
+        }
+        Ok(())
+    }
+
+    // Rejects operations on a paused vault and trips the circuit breaker when
+    // the share price has moved too far since the last deposit or withdrawal
+    pub fn ensure_operational(&mut self, risk: RiskLevel) -> Result<(), VaultError> {
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        if vault.circuit.paused {
+            let reason = vault.circuit.reason.as_deref().unwrap_or("paused by admin");
+            return Err(VaultError::Validation(format!("{:?} Risk Vault is paused: {}", risk, reason)));
+        }
+
+        let share_price = vault.get_share_price();
+        if let Some(moved) = vault.circuit.exceeded(share_price) {
+            let reason = format!(
+                "circuit breaker tripped: share price moved {:.2}% since the last operation (limit {:.2}%)",
+                moved as f64 / 100.0,
+                vault.circuit.max_price_move_bps as f64 / 100.0);
+            vault.circuit.pause(&reason, unix_now());
+            self.notify(Notification::CircuitBreakerTripped { risk, reason: reason.clone() });
+            self.persist()?;
+            return Err(VaultError::Validation(format!("{:?} Risk Vault is paused: {}", risk, reason)));
+        }
+
+        Ok(())
+    }
+
+    // Operations that send transactions don't start while Horizon's circuit
+    // breaker is open: one it couldn't finish would be left half done.
+    // Deposits found on the payment stream are credited regardless, since
+    // that needs nothing from Horizon.
+    pub fn ensure_network(&self) -> Result<(), VaultError> {
+        Ok(self.horizon.available()?)
+    }
+
+    pub fn checkpoint_share_price(&mut self, risk: RiskLevel) {
+        if let Some(vault) = self.vaults.get_mut(&risk) {
+            vault.circuit.last_share_price = vault.get_share_price();
+        }
+    }
+
+    pub fn pause_vault(&mut self, risk: RiskLevel, reason: &str) -> Result<(), VaultError> {
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        if vault.circuit.paused {
+            return Err(VaultError::Validation(format!("{:?} Risk Vault is already paused", risk)));
+        }
+        vault.circuit.pause(reason, unix_now());
+        self.persist()
+    }
+
+    pub fn resume_vault(&mut self, risk: RiskLevel) -> Result<(), VaultError> {
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        if !vault.circuit.paused {
+            return Err(VaultError::Validation(format!("{:?} Risk Vault is not paused", risk)));
+        }
+        let share_price = vault.get_share_price();
+        vault.circuit.resume(share_price);
+        self.persist()
+    }
+
+    pub fn set_circuit_limit(&mut self, risk: RiskLevel, max_price_move_bps: u16) -> Result<(), VaultError> {
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        vault.circuit.max_price_move_bps = max_price_move_bps;
+        self.persist()
+    }
+
+    // The user's side of a deposit: a trustline for the share tokens, enough
+    // of the asset being paid, and XLM left over for the fee and reserve
+    pub async fn check_deposit_funds(&self, user: &str, risk: RiskLevel, paid_asset: Option<&AssetId>, paid: Stroops,
+                                     fee: &FeeEstimate) -> Result<(), VaultError> {
+        let client = self.users.get(user)?;
+
+        // Share tokens can only be received once the user trusts the share asset
+        if let Some(asset) = self.share_asset(risk) {
+            let balances = client.get_balances().await?;
+            if !asset.has_trustline(&balances) {
+                return Err(VaultError::Validation(format!(
+                    "Add a trustline to {}:{} before depositing so you can receive vault shares ('shares trust')",
+                    asset.code, asset.issuer)));
+            }
+        }
+
+        // Paying in an issued asset needs a funded trustline; XLM still pays the fee
+        if let Some(asset) = paid_asset {
+            match client.get_asset_balance(asset).await? {
+                None => return Err(VaultError::Validation(format!(
+                    "Paying in {} needs a trustline first: 'trust {} {}'",
+                    asset.code, asset.code, asset.issuer))),
+                Some(balance) if balance < paid => return Err(VaultError::InsufficientBalance {
+                    asset: asset.code.clone(), available: balance }),
+                Some(_) => {}
+            }
+        }
+
+        // The account keeps its two base reserves after paying
+        let paid_xlm = if paid_asset.is_some() { Stroops::ZERO } else { paid };
+        let spent = paid_xlm.checked_add(Stroops(fee.per_operation as u64))?;
+        let needed = spent.checked_add(Stroops(2 * transaction::BASE_RESERVE))?;
+        match client.get_xlm_balance().await {
+            Ok(balance) => {
+                say!("\n💰 Account Balance:");
+                say!("   Current: {:.2} XLM", balance.0 as f64 / 10_000_000.0);
+                say!("   After Deposit: {:.2} XLM", (balance.0 as f64 - spent.0 as f64) / 10_000_000.0);
+
+                if balance < needed {
+                    return Err(VaultError::InsufficientBalance { asset: "XLM".to_string(), available: balance });
+                }
+            }
+            Err(e) => {
+                say!("   ⚠️  Could not fetch account info: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs a deposit's checks, share math and transaction construction,
+    /// stopping before anything is signed or sent.
+    pub async fn preview_deposit(&self, user: &str, risk: RiskLevel, amount_stroops: u64,
+                                 conversion: Option<&path_payment::Quote>) -> Result<DepositPreview, VaultError> {
+        let amount = Stroops(conversion.map_or(amount_stroops, |quote| quote.min_received.0));
+        self.check_operational(risk)?;
+        self.check_deposit_limits(user, risk, amount.0)?;
+        if self.vault_contract.is_some() {
+            return Err(VaultError::Validation("Dry runs don't cover vault contract deposits yet".into()));
+        }
+
+        let mut vault = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?.clone();
+        if let Some(quote) = conversion {
+            if quote.dest_asset != vault.asset {
+                return Err(VaultError::Validation(format!("The {:?} Risk Vault takes {}, not {}", risk,
+                    vault.asset_code(), path_payment::label(quote.dest_asset.as_ref()))));
+            }
+        }
+        let (paid_asset, paid) = match conversion {
+            Some(quote) => (quote.send_asset.clone(), quote.send_amount),
+            None => (vault.asset.clone(), amount),
+        };
+        let fee = self.submitter.fee_strategy.estimate(&self.horizon).await;
+        self.check_deposit_funds(user, risk, paid_asset.as_ref(), paid, &fee).await?;
+
+        // Quoted against the vault as the deposit would find it, fees accrued
+        fees::accrue_management_fee(&mut vault, unix_now())?;
+        let quote = vault.pool().deposit(amount, vault.insurance_fee)?;
+        self.insurance_balance(vault.asset.as_ref()).checked_add(quote.insurance)?;
+
+        let memo = transaction::text_memo(&pending_deposits::deposit_memo(self.pending_deposits.next_id()))?;
+        let transaction = self.users.get(user)?
+            .preview_payment(&self.vault_address, vault.xdr_asset()?, amount, conversion, memo).await?;
+        Ok(DepositPreview {
+            risk,
+            amount,
+            insurance: quote.insurance,
+            net: quote.net,
+            shares: quote.shares.0,
+            transaction,
+        })
+    }
+
+    // Checks a deposit's idempotency key before it runs. Unless the key
+    // already answers for it, the key is saved as started before anything
+    // is paid.
+    pub fn start_keyed_deposit(&mut self, user: &str, risk: RiskLevel, key: &str, request: &str) -> Result<KeyedDeposit, VaultError> {
+        let now = unix_now();
+        let window = self.idempotency_window_secs;
+        let earlier = self.idempotency.lookup(user, key, request, now, window)?
+            .map(|earlier| (earlier.outcome.clone(), earlier.at));
+        match earlier {
+            Some((Outcome::Succeeded { reply }, _)) => return Ok(KeyedDeposit::Replay(reply)),
+            Some((Outcome::Started { deposit_id }, at)) => {
+                let record = deposit_id.and_then(|id| self.pending_deposits.get(id))
+                    .filter(|record| record.user == user && record.risk == risk && record.created_at >= at);
+                match record {
+                    // Expired deposits never reached the vault
+                    Some(record) if record.stage == DepositStage::Expired => {}
+                    Some(record) => return Ok(KeyedDeposit::Conflict(format!(
+                        "The deposit made for idempotency key {} is #{}, now {:?}; check the account's positions rather than depositing again",
+                        key, record.id, record.stage))),
+                    // Contract deposits keep no record to tell whether one went through
+                    None if self.vault_contract.is_some() => return Ok(KeyedDeposit::Conflict(format!(
+                        "The deposit first made for idempotency key {} didn't finish and may have gone through; check the account's positions, then retry with a new key",
+                        key))),
+                    None => {}
+                }
+            }
+            None => {}
+        }
+        self.idempotency.record(user, key, request, Outcome::Started { deposit_id: None }, now, window);
+        self.persist()?;
+        Ok(KeyedDeposit::Run)
+    }
+
+    // Saves the answer to a keyed deposit. One that failed before making a
+    // deposit record sent nothing, so its key is dropped and a retry runs.
+    pub fn finish_keyed_deposit(&mut self, user: &str, key: &str, request: &str, reply: Option<&serde_json::Value>) {
+        let now = unix_now();
+        match reply {
+            Some(reply) => {
+                let outcome = Outcome::Succeeded { reply: reply.clone() };
+                self.idempotency.record(user, key, request, outcome, now, self.idempotency_window_secs);
+            }
+            None => {
+                let unattached = matches!(self.idempotency.lookup(user, key, request, now, self.idempotency_window_secs),
+                    Ok(Some(earlier)) if earlier.outcome == Outcome::Started { deposit_id: None });
+                if !unattached || self.vault_contract.is_some() {
+                    return;
+                }
+                self.idempotency.forget(user, key);
+            }
+        }
+        if let Err(e) = self.persist() {
+            say!("⚠️  Could not save the answer to idempotency key {}: {}", key, e);
+        }
+    }
+
+    /// Pays `amount_stroops` from `user` into the vault and mints their
+    /// shares, returning the shares, the fee and the payment's receipt.
+    ///
+    /// With a conversion quote the user pays in the quote's asset and the
+    /// vault is credited whatever the path payment delivers; limits are
+    /// checked against the quote's minimum.
+    pub async fn deposit(&mut self, user: &str, risk: RiskLevel, amount_stroops: u64,
+                     conversion: Option<&path_payment::Quote>) -> Result<(u64, Stroops, TransactionReceipt), VaultError> {
+        self.deposit_keyed(user, risk, amount_stroops, conversion, None).await
+    }
+
+    /// [`deposit`](Self::deposit) under an idempotency key started with
+    /// [`start_keyed_deposit`](Self::start_keyed_deposit). The key is tied to
+    /// the deposit's record in the same save that makes the record.
+    #[tracing::instrument(skip_all, err, fields(user = %user, risk = ?risk, amount_stroops = amount_stroops, converted = conversion.is_some()))]
+    pub async fn deposit_keyed(&mut self, user: &str, risk: RiskLevel, amount_stroops: u64, conversion: Option<&path_payment::Quote>,
+                               key: Option<&str>) -> Result<(u64, Stroops, TransactionReceipt), VaultError> {
+        let amount_stroops = conversion.map_or(amount_stroops, |quote| quote.min_received.0);
+        // Pause state and limits are checked before any funds move
+        self.ensure_operational(risk)?;
+        self.ensure_network()?;
+        self.check_deposit_limits(user, risk, amount_stroops)?;
+        self.screen(Screened::Deposit, user, risk, amount_stroops)?;
+        if self.vault_contract.is_some() {
+            if conversion.is_some() {
+                return Err(VaultError::Validation("The vault contract only takes the vault's own asset; deposit without converting".into()));
+            }
+            return self.deposit_to_contract(user, risk, Stroops(amount_stroops)).await;
+        }
+
+        let amount = Stroops(amount_stroops);
+        let vault_asset = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?.asset.clone();
+        if let Some(quote) = conversion {
+            if quote.dest_asset != vault_asset {
+                return Err(VaultError::Validation(format!("The {:?} Risk Vault takes {}, not {}", risk,
+                    path_payment::label(vault_asset.as_ref()), path_payment::label(quote.dest_asset.as_ref()))));
+            }
+        }
+        // What leaves the user's account: the vault's asset, or the one being converted
+        let (paid_asset, paid) = match conversion {
+            Some(quote) => (quote.send_asset.clone(), quote.send_amount),
+            None => (vault_asset.clone(), amount),
+        };
+
+        say!("\n💼 Initiating deposit to StellarVault (SYIA)...");
+        say!("   Risk Level: {:?}", risk);
+        match &vault_asset {
+            Some(asset) => say!("   Amount: {} {}", amount.to_xlm_string(), asset.code),
+            None => say!("   Amount: {}", amount),
+        }
+        let fee = self.submitter.fee_strategy.estimate(&self.horizon).await;
+        say!("   Estimated Network Fee: {}", fee);
+        self.check_deposit_funds(user, risk, paid_asset.as_ref(), paid, &fee).await?;
+
+        // Quote the deposit before any funds move, so arithmetic errors are
+        // caught while the XLM is still in the user's account
+        self.accrue_fees(risk)?;
+        let vault = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let quote = vault.pool().deposit(amount, vault.insurance_fee)?;
+        self.insurance_balance(vault.asset.as_ref()).checked_add(quote.insurance)?;
+
+        // Phase one: record the deposit before its payment goes out, so one
+        // interrupted while sending can be looked up by its memo
+        let since_ledger = self.latest_ledger().await;
+        let id = self.pending_deposits.intend(user, risk, amount_stroops, since_ledger, unix_now());
+        if let Some(key) = key {
+            self.idempotency.attach(user, key, id);
+        }
+        if let Err(e) = self.persist() {
+            self.pending_deposits.abandon(id, &format!("could not be saved: {}", e), unix_now())?;
+            return Err(format!("Could not save the deposit to {}, so nothing was sent: {}", self.storage.describe(), e).into());
+        }
+        let memo = pending_deposits::deposit_memo(id);
+        let client = self.users.get(user)?;
+        let asset = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?.xdr_asset()?;
+        let sent = match conversion {
+            Some(quote) => client.path_pay(&self.vault_address, quote, transaction::text_memo(&memo)?).await,
+            None => client.send_asset(&self.vault_address, asset, amount, transaction::text_memo(&memo)?).await,
+        };
+        let receipt = match sent {
+            Ok(receipt) => {
+                say!("\n🎉 Transaction submitted to Stellar Network!");
+                receipt
+            }
+            Err(e) => {
+                // A payment that may still land is recorded rather than
+                // failed, so it is credited once confirmed instead of re-sent
+                if let Some(SubmitError::Unconfirmed { hash, valid_until, .. }) = e.submit() {
+                    self.pending_deposits.set_payment(id, hash, 0, unix_now())?;
+                    // Once the payment's time bounds pass it can be written off
+                    if let Some(valid_until) = valid_until {
+                        self.pending_deposits.set_expiry(id, *valid_until)?;
+                    }
+                    if let Err(e) = self.persist() {
+                        say!("   ⚠️  Could not save vault state to {}: {}", self.storage.describe(), e);
+                    }
+                    return Err(format!("Deposit #{}: {}; it will be credited once confirmed", id, e).into());
+                }
+                self.pending_deposits.abandon(id, &e.to_string(), unix_now())?;
+                if let Err(e) = self.persist() {
+                    say!("   ⚠️  Could not save vault state to {}: {}", self.storage.describe(), e);
+                }
+                return Err(format!("Transaction failed: {}", e).into());
+            }
+        };
+
+        // A conversion credits what actually arrived, which is at least the minimum
+        let amount_stroops = match conversion {
+            Some(_) => transaction::path_payment_received(&receipt.result_xdr).unwrap_or_else(|e| {
+                say!("   ⚠️  Could not read the converted amount ({}); crediting the quoted minimum", e);
+                amount_stroops
+            }),
+            None => amount_stroops,
+        };
+
+        // The payment is on-chain, so record it before touching balances
+        self.pending_deposits.set_amount(id, amount_stroops)?;
+        self.pending_deposits.set_payment(id, &receipt.hash, receipt.ledger, unix_now())?;
+        if let Err(e) = self.persist() {
+            say!("   ⚠️  Could not save vault state to {}: {}", self.storage.describe(), e);
+        }
+
+        // Phase two: mint shares, or send the XLM back if that fails
+        match self.complete_deposit(id) {
+            Ok(shares) => Ok((shares, Stroops(amount_stroops), receipt)),
+            Err(e) if self.pending_deposits.get(id).is_some_and(|d| d.stage.is_unconfirmed()) => {
+                Err(format!("Payment {} was sent but {}; it will be credited once confirmed", receipt.hash, e).into())
+            }
+            Err(e) => {
+                let refund = match self.refund_deposit(id, &e.to_string()).await {
+                    Ok(refund) => format!("refunded in transaction {}", refund.hash),
+                    Err(refund_err) => format!("refund pending: {}", refund_err),
+                };
+                Err(format!("Deposit {} could not be credited ({}); {}", receipt.hash, e, refund).into())
+            }
+        }
+    }
+
+    // Confirms a recorded payment and mints its shares. Balances are only
+    // kept if the whole mint succeeds and is saved; otherwise they roll back.
+    pub fn complete_deposit(&mut self, id: u64) -> Result<u64, VaultError> {
+        let deposit = self.pending_deposits.get(id)
+            .ok_or_else(|| format!("Deposit #{} not found", id))?
+            .clone();
+
+        if deposit.stage == DepositStage::Intended {
+            return Err(format!("deposit #{}'s payment has not been sent", id).into());
+        }
+        if deposit.stage == DepositStage::PaymentSent {
+            // Horizon only reports a ledger once the transaction has closed
+            if deposit.ledger == 0 {
+                return Err(format!("payment {} has not been confirmed in a ledger", deposit.tx_hash).into());
+            }
+            self.pending_deposits.advance(id, DepositStage::Confirmed, unix_now())?;
+        }
+
+        let before = self.snapshot();
+        let minted = self.mint_deposit(&deposit).and_then(|quote| {
+            self.persist()?;
+            Ok(quote)
+        });
+        let quote = match minted {
+            Ok(quote) => quote,
+            Err(e) => {
+                self.restore(before);
+                return Err(e);
+            }
+        };
+
+        let shares_minted = quote.shares.0;
+        let insurance_stroops = quote.insurance.0;
+        self.log_event(VaultEvent::Deposit {
+            user: deposit.user.clone(),
+            risk: deposit.risk,
+            amount_stroops: deposit.amount_stroops,
+            insurance_stroops,
+            shares_minted,
+            tx_hash: deposit.tx_hash.clone(),
+        });
+
+        match self.adjust_premiums() {
+            Ok(report) => print_premium_changes(&report),
+            Err(e) => say!("   ⚠️  Could not adjust insurance premiums: {}", e),
+        }
+
+        let record = DepositRecord {
+            user: deposit.user,
+            risk: deposit.risk,
+            amount_stroops: deposit.amount_stroops,
+            insurance_stroops,
+            shares_minted,
+            tx_hash: deposit.tx_hash,
+            timestamp: unix_now(),
+        };
+        if let Err(e) = self.storage.record_deposit(&record) {
+            say!("   ⚠️  Could not record deposit history: {}", e);
+        }
+
+        Ok(shares_minted)
+    }
+
+    pub fn mint_deposit(&mut self, deposit: &PendingDeposit) -> Result<DepositQuote, VaultError> {
+        let risk = deposit.risk;
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        if vault.circuit.paused {
+            return Err(format!("{:?} Risk Vault was paused before the shares were minted", risk).into());
+        }
+
+        let mut pool = vault.pool();
+        let quote = pool.deposit(Stroops(deposit.amount_stroops), vault.insurance_fee)?;
+        let asset = vault.asset.clone();
+        let insurance_pool = self.insurance_balance(asset.as_ref()).checked_add(quote.insurance)?;
+
+        self.set_insurance_balance(asset.as_ref(), insurance_pool);
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        vault.set_pool(pool);
+        if vault.last_harvest == 0 {
+            vault.last_harvest = unix_now();
+        }
+        vault.allocate(quote.net.0);
+
+        // A position never holds more shares than the checked vault total
+        let key = (deposit.user.clone(), risk);
+        let position = self.user_positions.entry(key).or_default();
+        position.shares += quote.shares.0;
+        position.cost_basis = position.cost_basis.saturating_add(deposit.amount_stroops);
+        position.insurance_paid = position.insurance_paid.saturating_add(quote.insurance.0);
+        position.lock(quote.shares.0, unix_now());
+
+        if self.share_issuer.is_some() {
+            self.share_issuance.queue_mint(&deposit.user, risk, quote.shares.0);
+        }
+        self.checkpoint_share_price(risk);
+        self.pending_deposits.mark_minted(deposit.id, quote.shares.0, unix_now())?;
+
+        Ok(quote)
+    }
+
+    // Sends a confirmed deposit's XLM back in full. A failed refund stays
+    // RefundPending and is retried by settle_pending_deposits.
+    pub async fn refund_deposit(&mut self, id: u64, reason: &str) -> Result<TransactionReceipt, VaultError> {
+        let deposit = self.pending_deposits.get(id)
+            .ok_or_else(|| format!("Deposit #{} not found", id))?
+            .clone();
+        if deposit.stage != DepositStage::RefundPending {
+            self.pending_deposits.mark_failed(id, reason, unix_now())?;
+            if let Err(e) = self.persist() {
+                say!("   ⚠️  Could not save vault state to {}: {}", self.storage.describe(), e);
+            }
+        }
+
+        // A refund that may still be in flight is looked up, never re-sent
+        // until its time bounds have passed
+        if let (DepositStage::RefundPending, Some(hash)) = (deposit.stage, deposit.refund_tx.as_deref()) {
+            match self.horizon.transaction(hash).await {
+                Ok(transaction) if transaction.successful => {
+                    self.pending_deposits.mark_refunded(id, hash, unix_now())?;
+                    self.persist()?;
+                    return Ok(TransactionReceipt::from(transaction));
+                }
+                Ok(_) => {}
+                Err(e) if e.is_not_found() && unix_now() > deposit.updated_at + PAYMENT_TIMEOUT_SECS => {}
+                Err(e) => return Err(format!("Refund {} is not confirmed yet: {}", hash, e).into()),
+            }
+        }
+        // One interrupted before its hash was saved is looked for by its memo
+        let memo = format!("SYIA refund #{}", id);
+        if deposit.stage == DepositStage::RefundPending && deposit.refund_tx.is_none() {
+            if let Ok(Located::Landed(payment)) = self.locate_payment(&self.vault_address, &memo, 0).await {
+                if let Some(transaction) = payment.transaction {
+                    self.pending_deposits.mark_refunded(id, &transaction.hash, unix_now())?;
+                    self.persist()?;
+                    return Ok(TransactionReceipt::from(transaction));
+                }
+            }
+        }
+
+        // A refund the lists refuse waits until they allow it; only the
+        // first refusal is logged
+        let refund_to = deposit.sender.as_deref().unwrap_or(&deposit.user);
+        if deposit.stage == DepositStage::RefundPending {
+            self.address_lists.check(refund_to, self.allowlist_only)
+                .map_err(|reason| VaultError::Validation(format!("{} blocked: {}", Screened::Payout, reason)))?;
+        } else {
+            self.screen(Screened::Payout, refund_to, deposit.risk, deposit.amount_stroops)?;
+        }
+        let signer = self.vault_signer.as_ref()
+            .ok_or("Vault signing key is not configured; the refund will be retried once it is")?;
+        let asset = self.vaults.get(&deposit.risk).ok_or(VaultError::VaultNotFound(deposit.risk))?.xdr_asset()?;
+        let receipt = match signer.send_asset(refund_to, asset, Stroops(deposit.amount_stroops), transaction::text_memo(&memo)?).await {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                if let Some(SubmitError::Unconfirmed { hash, .. }) = e.submit() {
+                    self.pending_deposits.note_refund_sent(id, hash, unix_now())?;
+                    if let Err(e) = self.persist() {
+                        say!("   ⚠️  Could not save vault state to {}: {}", self.storage.describe(), e);
+                    }
+                }
+                return Err(format!("Refund payment failed: {}", e).into());
+            }
+        };
+
+        self.pending_deposits.mark_refunded(id, &receipt.hash, unix_now())?;
+        if let Err(e) = self.persist() {
+            say!("   ⚠️  Could not save vault state to {}: {}", self.storage.describe(), e);
+        }
+
+        Ok(receipt)
+    }
+
+    // Looks the payment up on Horizon and records the ledger it closed in
+    pub async fn confirm_payment(&mut self, id: u64, tx_hash: &str) -> Result<(), VaultError> {
+        let transaction = self.horizon.transaction(tx_hash).await?;
+        if !transaction.successful {
+            return Err(format!("payment {} failed on-chain", transaction.hash).into());
+        }
+        self.pending_deposits.set_payment(id, &transaction.hash, transaction.ledger, unix_now())?;
+        self.persist()
+    }
+
+    // The network's latest ledger as of the cached fee stats, which is never
+    // later than the real one; 0 if Horizon can't say
+    async fn latest_ledger(&self) -> u32 {
+        self.horizon.fee_stats().await.ok()
+            .and_then(|stats| stats.last_ledger.parse().ok())
+            .unwrap_or(0)
+    }
+
+    // Searches the vault account's recent payments for one from `from`
+    // tagged `memo`
+    async fn locate_payment(&self, from: &str, memo: &str, since_ledger: u32) -> Result<Located, VaultError> {
+        let payments = self.horizon.payments(&self.vault_address, RECOVERY_SCAN).await?;
+        Ok(journal::locate(payments, from, memo, since_ledger, RECOVERY_SCAN))
+    }
+
+    // Looks for the payment of a deposit interrupted while it was being sent,
+    // crediting it if it landed and closing the record if it never will
+    async fn settle_intended_deposit(&mut self, deposit: &PendingDeposit) -> Result<String, String> {
+        let located = self.locate_payment(&deposit.user, &deposit.memo, deposit.since_ledger).await
+            .map_err(|e| e.to_string())?;
+        match located {
+            Located::Landed(payment) => {
+                let credited = match ingest::classify(&payment, &self.vault_address) {
+                    Incoming::Deposit { from, asset, amount, tx_hash, ledger, deposit_id, risk, route_id } => {
+                        self.credit_incoming(&from, asset.as_ref(), amount, &tx_hash, ledger, deposit_id, risk, route_id).await
+                    }
+                    Incoming::Ignore => None,
+                };
+                match credited {
+                    Some((id, outcome)) if id == deposit.id => outcome,
+                    _ => Err(format!("its memo is on transaction {}, which doesn't match it; check it by hand", payment.transaction_hash)),
+                }
+            }
+            Located::Missing if unix_now() > deposit.updated_at + RESOLVE_AFTER_SECS => {
+                self.pending_deposits.abandon(deposit.id, "interrupted before its payment was sent", unix_now())
+                    .map_err(|e| e.to_string())?;
+                if let Err(e) = self.persist() {
+                    say!("   ⚠️  Could not save vault state to {}: {}", self.storage.describe(), e);
+                }
+                Ok("interrupted before its payment was sent; nothing was sent".to_string())
+            }
+            Located::Missing => Err("its payment isn't on the ledger yet, but may still be included".to_string()),
+            Located::Unknown => Err(format!("its payment isn't among the vault's last {} payments; look for memo \"{}\"",
+                RECOVERY_SCAN, deposit.memo)),
+        }
+    }
+
+    // Finishes deposits interrupted after their payment, e.g. by a crash
+    pub async fn settle_pending_deposits(&mut self) -> Vec<(PendingDeposit, Result<String, String>)> {
+        let mut results = Vec::new();
+
+        for deposit in self.pending_deposits.unsettled() {
+            if deposit.stage == DepositStage::Intended {
+                let outcome = self.settle_intended_deposit(&deposit).await;
+                results.push((deposit, outcome));
+                continue;
+            }
+            if deposit.stage == DepositStage::PaymentSent && deposit.ledger == 0 {
+                if let Err(e) = self.confirm_payment(deposit.id, &deposit.tx_hash).await {
+                    // Unknown to Horizon after its time bounds, the payment never happened
+                    let unknown = e.horizon().is_some_and(|e| e.is_not_found());
+                    let outcome = if unknown && self.pending_deposits.mark_expired(deposit.id, unix_now()).is_ok() {
+                        if let Err(e) = self.persist() {
+                            say!("   ⚠️  Could not save vault state to {}: {}", self.storage.describe(), e);
+                        }
+                        Ok("expired before its payment was included; nothing was sent".to_string())
+                    } else {
+                        Err(e.to_string())
+                    };
+                    results.push((deposit, outcome));
+                    continue;
+                }
+            }
+
+            let outcome = match deposit.stage {
+                DepositStage::PaymentSent | DepositStage::Confirmed => self.finish_deposit(deposit.id).await,
+                DepositStage::RefundPending => {
+                    let reason = deposit.failure.clone().unwrap_or_default();
+                    self.refund_deposit(deposit.id, &reason).await
+                        .map(|receipt| format!("refunded in transaction {}", receipt.hash))
+                        .map_err(|e| e.to_string())
+                }
+                DepositStage::Intended | DepositStage::SharesMinted | DepositStage::Refunded | DepositStage::Expired => continue,
+            };
+            results.push((deposit, outcome));
+        }
+
+        results
+    }
+
+    // Mints a recorded deposit's shares, or refunds it if it can't be credited
+    pub async fn finish_deposit(&mut self, id: u64) -> Result<String, String> {
+        match self.complete_deposit(id) {
+            Ok(shares) => Ok(format!("{} shares minted", shares)),
+            Err(e) if self.pending_deposits.get(id).is_some_and(|d| d.stage.is_unconfirmed()) => {
+                Err(e.to_string())
+            }
+            Err(e) => self.refund_deposit(id, &e.to_string()).await
+                .map(|receipt| format!("refunded in transaction {}", receipt.hash))
+                .map_err(|refund_err| format!("{}; {}", e, refund_err)),
+        }
+    }
+
+    // Handles a payment from the vault account's stream. Payments sent by
+    // this CLI are matched to their deposit record by hash or memo; any other
+    // XLM paid in becomes a deposit for its sender. Returns the deposit it
+    // settled, if any.
+    pub async fn ingest_payment(&mut self, payment: &Payment) -> Option<(u64, Result<String, String>)> {
+        let outcome = match ingest::classify(payment, &self.vault_address) {
+            Incoming::Ignore => None,
+            Incoming::Deposit { from, asset, amount, tx_hash, ledger, deposit_id, risk, route_id } => {
+                self.credit_incoming(&from, asset.as_ref(), amount, &tx_hash, ledger, deposit_id, risk, route_id).await
+            }
+        };
+
+        self.ingest_cursor = Some(payment.paging_token.clone());
+        if let Err(e) = self.persist() {
+            say!("   ⚠️  Could not save vault state to {}: {}", self.storage.describe(), e);
+        }
+        outcome
+    }
+
+    // Folds a vault contract event into the index and its history, once
+    pub fn index_contract_event(&mut self, event: &soroban::Event) -> Result<Option<ContractEvent>, VaultError> {
+        let (topics, data) = (event.topics()?, event.data()?);
+        let parsed = match self.share_tokens.iter().find(|(_, token)| *token == event.contract_id) {
+            Some((risk, _)) => ContractEvent::parse_share_transfer(*risk, &topics, &data),
+            None => ContractEvent::parse(&topics, &data),
+        };
+        let Some(parsed) = parsed else {
+            return Ok(None);
+        };
+        if !self.contract_index.apply(&event.id, event.ledger, &parsed) {
+            return Ok(None);
+        }
+
+        let recorded = match &parsed {
+            ContractEvent::Deposit { user, risk, amount, fee, shares } => self.storage.record_deposit(&DepositRecord {
+                user: user.clone(),
+                risk: *risk,
+                amount_stroops: *amount,
+                insurance_stroops: *fee,
+                shares_minted: *shares,
+                tx_hash: event.tx_hash.clone(),
+                timestamp: unix_now(),
+            }),
+            ContractEvent::Withdraw { user, risk, amount, shares } => self.storage.record_withdrawal(&WithdrawalRecord {
+                user: user.clone(),
+                risk: *risk,
+                shares_burned: *shares,
+                amount_stroops: *amount,
+                tx_hash: event.tx_hash.clone(),
+                timestamp: unix_now(),
+            }),
+            ContractEvent::Harvest { risk, amount } => self.storage.record_yield(&YieldRecord {
+                risk: *risk,
+                strategy: "VaultContract".to_string(),
+                amount_stroops: *amount,
+                timestamp: unix_now(),
+            }),
+            ContractEvent::Claim { .. } | ContractEvent::ShareTransfer { .. } => Ok(()),
+        };
+        if let Err(e) = recorded {
+            say!("   ⚠️  Could not record contract history: {}", e);
+        }
+        self.persist()?;
+        Ok(Some(parsed))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn credit_incoming(&mut self, from: &str, asset: Option<&AssetId>, amount: Stroops, tx_hash: &str, ledger: u32,
+                             deposit_id: Option<u64>, risk: Option<RiskLevel>, route_id: Option<u64>) -> Option<(u64, Result<String, String>)> {
+        let now = unix_now();
+        // An Intended deposit only has the quoted minimum of a conversion to go by
+        let memo_match = deposit_id.and_then(|id| self.pending_deposits.get(id))
+            .filter(|d| d.stage == DepositStage::Intended || d.stage == DepositStage::PaymentSent && d.ledger == 0)
+            .filter(|d| d.user == from && self.vaults.get(&d.risk).is_some_and(|vault| vault.asset.as_ref() == asset))
+            .filter(|d| d.amount_stroops == amount.0 || d.stage == DepositStage::Intended && amount.0 >= d.amount_stroops)
+            .map(|d| d.id);
+        let known = self.pending_deposits.find_by_tx(tx_hash).map(|d| d.id).or(memo_match);
+
+        if let Some(id) = known {
+            let deposit = self.pending_deposits.get(id)?.clone();
+            return match deposit.stage {
+                DepositStage::Intended | DepositStage::PaymentSent => {
+                    if deposit.ledger == 0 {
+                        let recorded = self.pending_deposits.set_amount(id, amount.0)
+                            .and_then(|_| self.pending_deposits.set_payment(id, tx_hash, ledger, now));
+                        if let Err(e) = recorded {
+                            return Some((id, Err(e.to_string())));
+                        }
+                    }
+                    Some((id, self.finish_deposit(id).await))
+                }
+                DepositStage::Confirmed => Some((id, self.finish_deposit(id).await)),
+                // Already credited, or left for settle_pending_deposits to refund
+                _ => None,
+            };
+        }
+
+        // Unsolicited: the XLM has already arrived, so a deposit the vault
+        // can't accept is sent straight back. One paid to a vault sub-address
+        // belongs to whoever the address was handed out to.
+        let route = route_id.and_then(|id| self.deposit_routes.get(id)).cloned();
+        let user = route.as_ref().map(|r| r.user.as_str()).unwrap_or(from);
+        let risk = route.as_ref().map(|r| r.risk)
+            .or(risk)
+            .or_else(|| deposit_id.and_then(|id| self.pending_deposits.get(id)).map(|d| d.risk))
+            .unwrap_or(RiskLevel::Low);
+        // A refund would go out in the vault's asset, so anything else is
+        // left for the operator to return by hand
+        let vault_asset = self.vaults.get(&risk).and_then(|vault| vault.asset.as_ref());
+        if vault_asset != asset {
+            let paid = asset.map_or("XLM".to_string(), AssetId::to_string);
+            say!("📥 ⚠️  {} paid {} {} in {}, which the {:?} Risk Vault doesn't hold; return it manually",
+                from, amount.to_xlm_string(), paid, tx_hash, risk);
+            return None;
+        }
+        // Nor is anything sent back to a sender the lists refuse
+        if let Err(e) = self.screen(Screened::Deposit, from, risk, amount.0) {
+            say!("📥 ⚠️  {} from {} in {} is held for the operator: {}", amount.to_xlm_string(), from, tx_hash, e);
+            return None;
+        }
+        let id = self.pending_deposits.begin(user, risk, amount.0, tx_hash, ledger, now);
+        let recorded = self.pending_deposits.advance(id, DepositStage::Confirmed, now)
+            .and_then(|_| if user != from { self.pending_deposits.set_sender(id, from) } else { Ok(()) });
+        if let Err(e) = recorded {
+            return Some((id, Err(e.to_string())));
+        }
+        if let Err(e) = self.persist() {
+            say!("   ⚠️  Could not save vault state to {}: {}", self.storage.describe(), e);
+        }
+
+        let accepted = self.ensure_operational(risk)
+            .and_then(|_| self.check_deposit_limits(user, risk, amount.0))
+            .and_then(|_| if user != from { self.screen(Screened::Deposit, user, risk, amount.0) } else { Ok(()) })
+            .and_then(|_| self.accrue_fees(risk));
+        let outcome = match accepted {
+            Ok(()) => self.finish_deposit(id).await,
+            Err(e) => match self.refund_deposit(id, &e.to_string()).await {
+                Ok(receipt) => Ok(format!("not accepted ({}); refunded in transaction {}", e, receipt.hash)),
+                Err(refund_err) => Err(format!("not accepted ({}); {}", e, refund_err)),
+            },
+        };
+        Some((id, outcome))
+    }
+
+    // The vault sub-address that credits `user`'s position in `risk`'s vault
+    pub fn deposit_address(&mut self, user: &str, risk: RiskLevel) -> Result<String, VaultError> {
+        self.screen(Screened::Deposit, user, risk, 0)?;
+        let id = self.deposit_routes.assign(user, risk, unix_now());
+        self.persist()?;
+        muxed::address(&self.vault_address, id)
+    }
+
+    /// Redeems `shares` for `user`. Pays out immediately when the vault's
+    /// liquid reserve covers the request, otherwise queues it behind any
+    /// earlier requests for the same vault.
+    #[tracing::instrument(skip_all, err, fields(user = %user, risk = ?risk, shares = shares))]
+    pub async fn withdraw(&mut self, user: &str, risk: RiskLevel, shares: u64) -> Result<WithdrawalOutcome, VaultError> {
+        if !self.users.contains(user) {
+            return Err(VaultError::Validation(format!("User {} is not registered", user)));
+        }
+        if shares == 0 {
+            return Err(VaultError::Validation("Withdrawal must burn at least one share".into()));
+        }
+        self.ensure_operational(risk)?;
+        self.ensure_network()?;
+        // Before queueing, so a refused withdrawal never holds up the queue
+        self.screen(Screened::Payout, user, risk, 0)?;
+        if self.vault_contract.is_some() {
+            return self.withdraw_from_contract(user, risk, shares).await.map(WithdrawalOutcome::Completed);
+        }
+
+        let key = (user.to_string(), risk);
+        let held = self.user_positions.get(&key).map(|p| p.shares).unwrap_or(0);
+        let available = held.saturating_sub(self.withdrawal_queue.queued_shares(user, risk));
+        if available < shares {
+            return Err(VaultError::InsufficientShares { requested: shares, available });
+        }
+
+        self.accrue_fees(risk)?;
+        let vault = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let amount_stroops = vault.pool().value_of(Shares(shares), Rounding::Down)?.0;
+
+        if amount_stroops > vault.liquid_reserve || self.withdrawal_queue.has_pending(risk) {
+            let id = self.withdrawal_queue.enqueue(user, risk, shares, unix_now());
+            self.persist()?;
+            let ahead = self.withdrawal_queue.position(id).unwrap_or(0);
+            return Ok(WithdrawalOutcome::Queued { id, ahead });
+        }
+
+        self.execute_withdrawal(user, risk, shares).await
+            .map(WithdrawalOutcome::Completed)
+    }
+
+    /// Redeems `shares` for `user` and pays them to `payee` rather than the
+    /// user's own account. Unwinds strategies to pay it at once, the way the
+    /// withdrawal queue does; never queues, so while earlier requests are
+    /// waiting or the strategies can't free enough, nothing is withdrawn.
+    #[tracing::instrument(skip_all, err, fields(user = %user, risk = ?risk, shares = shares, payee = %payee))]
+    pub async fn withdraw_to(&mut self, user: &str, risk: RiskLevel, shares: u64, payee: &str) -> Result<WithdrawalReceipt, VaultError> {
+        if !self.users.contains(user) {
+            return Err(VaultError::Validation(format!("User {} is not registered", user)));
+        }
+        if shares == 0 {
+            return Err(VaultError::Validation("Withdrawal must burn at least one share".into()));
+        }
+        self.ensure_operational(risk)?;
+        self.ensure_network()?;
+        if self.vault_contract.is_some() {
+            return Err(VaultError::Validation("Vault contract withdrawals can only be paid to the shareholder".into()));
+        }
+
+        let held = self.user_positions.get(&(user.to_string(), risk)).map(|p| p.shares).unwrap_or(0);
+        let available = held.saturating_sub(self.withdrawal_queue.queued_shares(user, risk));
+        if available < shares {
+            return Err(VaultError::InsufficientShares { requested: shares, available });
+        }
+
+        if self.withdrawal_queue.has_pending(risk) {
+            return Err(VaultError::Validation(format!(
+                "Queued withdrawals from the {:?} Risk Vault are paid first", risk)));
+        }
+        self.accrue_fees(risk)?;
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let amount_stroops = vault.pool().value_of(Shares(shares), Rounding::Down)?.0;
+        vault.free_liquidity(amount_stroops);
+        if amount_stroops > vault.liquid_reserve {
+            return Err(VaultError::Validation(format!(
+                "The {:?} Risk Vault's liquid reserve can't pay this out right now", risk)));
+        }
+        self.execute_withdrawal_to(user, risk, shares, payee).await
+    }
+
+    /// Runs a withdrawal's checks, payout math and transaction construction,
+    /// stopping before anything is signed or sent.
+    pub async fn preview_withdraw(&self, user: &str, risk: RiskLevel, shares: u64) -> Result<WithdrawalPreview, VaultError> {
+        if !self.users.contains(user) {
+            return Err(VaultError::Validation(format!("User {} is not registered", user)));
+        }
+        if shares == 0 {
+            return Err(VaultError::Validation("Withdrawal must burn at least one share".into()));
+        }
+        self.check_operational(risk)?;
+        if self.vault_contract.is_some() {
+            return Err(VaultError::Validation("Dry runs don't cover vault contract withdrawals yet".into()));
+        }
+
+        let held = self.user_positions.get(&(user.to_string(), risk)).map(|p| p.shares).unwrap_or(0);
+        let available = held.saturating_sub(self.withdrawal_queue.queued_shares(user, risk));
+        if available < shares {
+            return Err(VaultError::InsufficientShares { requested: shares, available });
+        }
+
+        let mut vault = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?.clone();
+        fees::accrue_management_fee(&mut vault, unix_now())?;
+        let locked = self.locked_part(user, risk, shares);
+        let quote = vault.pool().withdraw_partly_locked(Shares(shares), Shares(locked), vault.early_withdrawal_penalty)?;
+        let queued = quote.gross.0 > vault.liquid_reserve || self.withdrawal_queue.has_pending(risk);
+
+        let transaction = if queued {
+            None
+        } else {
+            let signer = self.vault_signer.as_ref()
+                .ok_or("Vault signing key is not configured; withdrawals are unavailable")?;
+            Some(signer.preview_payment(user, vault.xdr_asset()?, quote.net, None, Memo::None).await?)
+        };
+        Ok(WithdrawalPreview {
+            risk,
+            shares,
+            gross: quote.gross,
+            penalty: quote.penalty,
+            net: quote.net,
+            queued,
+            transaction,
+        })
+    }
+
+    pub async fn execute_withdrawal(&mut self, user: &str, risk: RiskLevel, shares: u64) -> Result<WithdrawalReceipt, VaultError> {
+        self.execute_withdrawal_to(user, risk, shares, user).await
+    }
+
+    async fn execute_withdrawal_to(&mut self, user: &str, risk: RiskLevel, shares: u64, payee: &str) -> Result<WithdrawalReceipt, VaultError> {
+        self.pay_withdrawal(user, risk, shares, payee, None).await
+    }
+
+    // Pays a withdrawal, journaled so that a crash between the payment and
+    // the books can be settled on restart; `queued` is the withdrawal queue
+    // entry it pays, removed in the same save as the shares are burned
+    async fn pay_withdrawal(&mut self, user: &str, risk: RiskLevel, shares: u64, payee: &str, queued: Option<u64>) -> Result<WithdrawalReceipt, VaultError> {
+        self.ensure_operational(risk)?;
+        self.ensure_network()?;
+        self.accrue_fees(risk)?;
+        let vault = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+
+        let locked = self.locked_part(user, risk, shares);
+        let quote = vault.pool().withdraw_partly_locked(Shares(shares), Shares(locked), vault.early_withdrawal_penalty)?;
+        let (amount, penalty, net) = (quote.gross, quote.penalty, quote.net);
+        self.insurance_balance(vault.asset.as_ref()).checked_add(penalty)?;
+        let asset = vault.xdr_asset()?;
+
+        say!("\n💸 Initiating withdrawal from StellarVault (SYIA)...");
+        say!("   Risk Level: {:?}", risk);
+        say!("   Shares: {}", shares);
+        say!("   Amount: {} {}", amount.to_xlm_string(), vault.asset_code());
+        if penalty > Stroops::ZERO {
+            say!("   ⏳ Early withdrawal: {} of the shares are in lockup for up to {}", locked,
+                format_duration(self.lock_remaining_secs(user, risk)));
+            say!("   Penalty: {} ({:.2}%)", penalty,
+                vault.early_withdrawal_penalty as f64 / 100.0);
+        }
+
+        const NO_SIGNER: &str = "Vault signing key is not configured; withdrawals are unavailable";
+        if self.vault_signer.is_none() {
+            return Err(NO_SIGNER.into());
+        }
+        if payee != user {
+            say!("   Paid To: {}", payee);
+        }
+        let kind = OperationKind::Withdrawal { shares, gross_stroops: amount.0, penalty_stroops: penalty.0, queued };
+        let id = self.begin_payout(user, risk, kind, payee, net.0).await?;
+        let memo = transaction::text_memo(&journal::payout_memo(id))?;
+        let signer = self.vault_signer.as_ref().ok_or(NO_SIGNER)?;
+        let sent = signer.send_asset(payee, asset, net, memo).await;
+        let receipt = self.payout_sent(id, sent).map_err(|e| format!("Withdrawal payment failed: {}", e))?;
+        self.book_payout(id).map_err(|e| format!(
+            "Withdrawal {} was paid but not booked ({}); it is retried on the next start", receipt.hash, e))?;
+
+        Ok(WithdrawalReceipt {
+            gross_stroops: amount.0,
+            penalty_stroops: penalty.0,
+            net_stroops: net.0,
+            transaction: receipt,
+        })
+    }
+
+    // Writes a payout into the journal before its payment goes out; nothing
+    // is sent unless the entry is saved. Payouts carry their journal memo
+    // instead of none, so an account that asks for its own can't be paid.
+    async fn begin_payout(&mut self, user: &str, risk: RiskLevel, kind: OperationKind, payee: &str, amount_stroops: u64) -> Result<u64, VaultError> {
+        self.screen(Screened::Payout, user, risk, amount_stroops)?;
+        if payee != user {
+            self.screen(Screened::Payout, payee, risk, amount_stroops)?;
+        }
+        check_memo_not_required(&self.horizon, payee).await?;
+        let since_ledger = self.latest_ledger().await;
+        let id = self.journal.intend(user, risk, kind, payee, amount_stroops, since_ledger, unix_now());
+        if let Err(e) = self.persist() {
+            self.journal.abandon(id, &format!("could not be saved: {}", e), unix_now())?;
+            return Err(format!("Could not save payout #{} to {}, so nothing was sent: {}", id, self.storage.describe(), e).into());
+        }
+        Ok(id)
+    }
+
+    // Records how a payout's payment went. One that may still land is left
+    // Sent for settle_payouts; one that never will is abandoned.
+    fn payout_sent(&mut self, id: u64, sent: Result<TransactionReceipt, VaultError>) -> Result<TransactionReceipt, VaultError> {
+        let e = match sent {
+            Ok(receipt) => {
+                self.journal.note_sent(id, &receipt.hash, receipt.ledger, unix_now())?;
+                return Ok(receipt);
+            }
+            Err(e) => e,
+        };
+        match e.submit() {
+            Some(SubmitError::Unconfirmed { hash, .. }) => self.journal.note_sent(id, hash, 0, unix_now())?,
+            _ => self.journal.abandon(id, &e.to_string(), unix_now())?,
+        }
+        if let Err(e) = self.persist() {
+            say!("   ⚠️  Could not save vault state to {}: {}", self.storage.describe(), e);
+        }
+        Err(e)
+    }
+
+    // Changes the books for a payout whose payment is in a ledger and closes
+    // its journal entry, all in one save
+    fn book_payout(&mut self, id: u64) -> Result<(), VaultError> {
+        let entry = self.journal.get(id).ok_or_else(|| format!("Payout #{} not found", id))?.clone();
+        let tx_hash = entry.tx_hash.clone().unwrap_or_default();
+        let risk = entry.risk;
+        let key = (entry.user.clone(), risk);
+
+        match entry.kind {
+            OperationKind::Withdrawal { shares, gross_stroops, penalty_stroops, queued } => {
+                let vault = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+                let mut pool = vault.pool();
+                pool.total_value = pool.total_value.checked_sub(Stroops(gross_stroops))?;
+                pool.total_shares = pool.total_shares.checked_sub(Shares(shares))?;
+                let vault_asset = vault.asset.clone();
+                let insurance_pool = self.insurance_balance(vault_asset.as_ref()).checked_add(Stroops(penalty_stroops))?;
+                self.set_insurance_balance(vault_asset.as_ref(), insurance_pool);
+
+                let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+                vault.set_pool(pool);
+                vault.deallocate(gross_stroops);
+                let lockup_secs = vault.lockup_secs;
+
+                self.log_event(VaultEvent::Withdrawal {
+                    user: entry.user.clone(),
+                    risk,
+                    shares_burned: shares,
+                    gross_stroops,
+                    penalty_stroops,
+                    tx_hash: tx_hash.clone(),
+                });
+
+                if self.share_issuer.is_some() {
+                    self.share_issuance.queue_burn(&entry.user, risk, shares);
+                }
+                self.checkpoint_share_price(risk);
+
+                if let Some(position) = self.user_positions.get_mut(&key) {
+                    let cost_removed = mul_div(position.cost_basis, shares, position.shares, Rounding::Down)
+                        .unwrap_or(position.cost_basis);
+                    position.cost_basis -= cost_removed;
+                    position.burn(shares, lockup_secs, unix_now());
+                }
+                if let Some(queued) = queued {
+                    self.withdrawal_queue.remove(queued);
+                }
+            }
+            OperationKind::YieldPayout => {
+                if let Some(position) = self.user_positions.get_mut(&key) {
+                    position.accumulated_yield = position.accumulated_yield.saturating_sub(entry.amount_stroops);
+                }
+                self.log_event(VaultEvent::YieldDistributed {
+                    risk,
+                    payouts: vec![YieldPayout { user: entry.user.clone(), amount_stroops: entry.amount_stroops }],
+                    tx_hash: tx_hash.clone(),
+                });
+            }
+        }
+
+        self.journal.complete(id, unix_now())?;
+        if let Err(e) = self.persist() {
+            say!("   ⚠️  Could not save vault state to {}: {}", self.storage.describe(), e);
+        }
+
+        if let OperationKind::Withdrawal { shares, .. } = entry.kind {
+            let record = WithdrawalRecord {
+                user: entry.user,
+                risk,
+                shares_burned: shares,
+                amount_stroops: entry.amount_stroops,
+                tx_hash,
+                timestamp: unix_now(),
+            };
+            if let Err(e) = self.storage.record_withdrawal(&record) {
+                say!("   ⚠️  Could not record withdrawal history: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    // Finishes payouts interrupted between their payment and their books,
+    // e.g. by a crash: one whose payment landed is booked, and one whose
+    // payment never will is abandoned with the books untouched
+    pub async fn settle_payouts(&mut self) -> Vec<(JournalEntry, Result<String, String>)> {
+        let mut results = Vec::new();
+        for entry in self.journal.open() {
+            let outcome = self.settle_payout(&entry).await.map_err(|e| e.to_string());
+            results.push((entry, outcome));
+        }
+        results
+    }
+
+    async fn settle_payout(&mut self, entry: &JournalEntry) -> Result<String, VaultError> {
+        // A known hash answers directly; a rebuilt transaction is found by memo
+        if let Some(hash) = entry.tx_hash.as_deref() {
+            match self.horizon.transaction(hash).await {
+                Ok(transaction) if transaction.successful => {
+                    self.journal.note_sent(entry.id, &transaction.hash, transaction.ledger, unix_now())?;
+                    self.book_payout(entry.id)?;
+                    return Ok(format!("paid in transaction {}; now booked", transaction.hash));
+                }
+                Ok(_) => {}
+                Err(e) if e.is_not_found() => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        match self.locate_payment(&self.vault_address, &entry.memo, entry.since_ledger).await? {
+            Located::Landed(payment) => {
+                let ledger = payment.transaction.as_ref().map_or(0, |transaction| transaction.ledger);
+                self.journal.note_sent(entry.id, &payment.transaction_hash, ledger, unix_now())?;
+                self.book_payout(entry.id)?;
+                Ok(format!("paid in transaction {}; now booked", payment.transaction_hash))
+            }
+            Located::Missing if unix_now() > entry.updated_at + RESOLVE_AFTER_SECS => {
+                self.journal.abandon(entry.id, "interrupted before its payment was sent", unix_now())?;
+                self.persist()?;
+                Ok("interrupted before its payment was sent; nothing was sent".to_string())
+            }
+            Located::Missing => Err("its payment isn't on the ledger yet, but may still be included".into()),
+            Located::Unknown => Err(format!("its payment isn't among the vault's last {} payments; look for memo \"{}\"",
+                RECOVERY_SCAN, entry.memo).into()),
+        }
+    }
+
+    // Unwinds strategy allocation to pay queued withdrawals in FIFO order. A
+    // failed payout stops that vault's queue so later requests can't jump it.
+    pub async fn process_withdrawal_queue(&mut self) -> Vec<(u64, Result<WithdrawalReceipt, String>)> {
+        let mut results = Vec::new();
+
+        for risk in RiskLevel::ALL {
+            if self.vaults.get(&risk).is_some_and(|v| v.circuit.paused) {
+                continue;
+            }
+            while let Some(entry) = self.withdrawal_queue.front(risk).cloned() {
+                let vault = match self.vaults.get_mut(&risk) {
+                    Some(v) => v,
+                    None => break,
+                };
+                let amount_stroops = match vault.pool().value_of(Shares(entry.shares), Rounding::Down) {
+                    Ok(amount) => amount.0,
+                    Err(e) => {
+                        results.push((entry.id, Err(e.to_string())));
+                        break;
+                    }
+                };
+                vault.free_liquidity(amount_stroops);
+                if vault.liquid_reserve < amount_stroops {
+                    break;
+                }
+                // Listed since it was queued: it leaves the queue, and the
+                // shares stay with the account
+                if let Err(e) = self.screen(Screened::Payout, &entry.user, risk, amount_stroops) {
+                    self.withdrawal_queue.remove(entry.id);
+                    results.push((entry.id, Err(e.to_string())));
+                    continue;
+                }
+
+                // Paying the entry takes it off the queue
+                match self.pay_withdrawal(&entry.user, risk, entry.shares, &entry.user, Some(entry.id)).await {
+                    Ok(receipt) => results.push((entry.id, Ok(receipt))),
+                    Err(e) => {
+                        results.push((entry.id, Err(e.to_string())));
+                        break;
+                    }
+                }
+            }
+        }
+
+        if !results.is_empty() {
+            if let Err(e) = self.persist() {
+                say!("   ⚠️  Could not save vault state to {}: {}", self.storage.describe(), e);
+            }
+        }
+
+        results
+    }
+
+    // Until the user's last deposit in the vault is out of its lockup
+    pub fn lock_remaining_secs(&self, user: &str, risk: RiskLevel) -> u64 {
+        let lockup = self.vaults.get(&risk).map(|v| v.lockup_secs).unwrap_or(0);
+        self.user_positions.get(&(user.to_string(), risk))
+            .map_or(0, |position| position.lock_remaining_secs(lockup, unix_now()))
+    }
+
+    // How many of `shares` withdrawn now would pay the early withdrawal
+    // penalty: only shares from deposits still in their lockup, and only
+    // once the rest of the position is used up
+    pub fn locked_part(&self, user: &str, risk: RiskLevel, shares: u64) -> u64 {
+        let lockup = self.vaults.get(&risk).map(|v| v.lockup_secs).unwrap_or(0);
+        self.user_positions.get(&(user.to_string(), risk))
+            .map_or(0, |position| position.locked_part(shares.min(position.shares), lockup, unix_now()))
+    }
+
+    pub fn get_position(&self, user: &str, risk: RiskLevel) -> Option<PositionSummary> {
+        let position = self.user_positions.get(&(user.to_string(), risk))?;
+        let vault = self.vaults.get(&risk)?;
+        let value_stroops = vault.pool().value_of(Shares(position.shares), Rounding::Down).ok()?.0;
+
+        Some(PositionSummary {
+            risk,
+            shares: position.shares,
+            value_stroops,
+            accumulated_yield: position.accumulated_yield,
+            cost_basis: position.cost_basis,
+            insurance_paid: position.insurance_paid,
+            apy: vault.blended_apy(),
+            auto_compound: position.auto_compound,
+            lock_remaining_secs: self.lock_remaining_secs(user, risk),
+        })
+    }
+
+    pub fn set_auto_compound(&mut self, user: &str, risk: RiskLevel, enabled: bool) -> Result<(), VaultError> {
+        let position = self.user_positions.get_mut(&(user.to_string(), risk))
+            .ok_or_else(|| format!("No position in the {:?} Risk Vault", risk))?;
+        position.auto_compound = enabled;
+        self.persist()
+    }
+
+    // Net yield a harvest would credit now. Strategies measured on-chain are
+    // estimated from their APY, so this is a preview, not what harvest pays.
+    pub fn pending_yield(&self, risk: RiskLevel) -> Result<u64, VaultError> {
+        let vault = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let elapsed_secs = if vault.last_harvest == 0 { 0 } else { unix_now().saturating_sub(vault.last_harvest) };
+        let context = HarvestContext { elapsed_secs, measured: None };
+        let earned: u64 = vault.strategies.iter()
+            .map(|allocation| strategy::lookup(&allocation.strategy).harvest(allocation, &context))
+            .sum();
+        Ok(earned - fees::performance_fee(earned, vault.fees.performance_fee_bps)?)
+    }
+
+    // The share price with management fees accrued up to now
+    pub fn live_share_price(&self, risk: RiskLevel) -> Result<u64, VaultError> {
+        let mut vault = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?.clone();
+        fees::accrue_management_fee(&mut vault, unix_now())?;
+        Ok(vault.get_share_price())
+    }
+
+    // Accrues strategy yield since the last harvest and credits it pro rata to
+    // shareholders; auto-compounding positions get new shares at the current price
+    #[tracing::instrument(skip_all, err, fields(risk = ?risk))]
+    pub fn harvest(&mut self, risk: RiskLevel) -> Result<HarvestReport, VaultError> {
+        let now = unix_now();
+        self.accrue_fees(risk)?;
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let elapsed_secs = if vault.last_harvest == 0 { 0 } else { now.saturating_sub(vault.last_harvest) };
+        vault.last_harvest = now;
+
+        let mut report = HarvestReport {
+            risk,
+            elapsed_secs,
+            total_yield: 0,
+            performance_fee: 0,
+            distributed_yield: 0,
+            compounded_yield: 0,
+            compounded_shares: 0,
+        };
+
+        let mut yield_records = Vec::new();
+        let mut strategy_yield = Vec::new();
+        let mut credits = Vec::new();
+        let (mut insurance_yield, mut insurance_shares) = (0, 0);
+        let spread_yield = vault.market_making.as_mut().map_or(0, sdex::MarketMaker::take_unharvested);
+        let pool_yield = vault.liquidity_pool.as_mut().map(amm::PoolPosition::take_unharvested);
+        let lending_yield = vault.lending.as_mut().map(blend::LendingPosition::take_unharvested);
+        let soroswap_yield = vault.soroswap.as_mut().map_or(0, soroswap::SoroswapPosition::take_unharvested);
+        for strategy in &mut vault.strategies {
+            let measured = match strategy.strategy.as_str() {
+                strategy::SDEX_MARKET_MAKING => Some(spread_yield),
+                strategy::SOROSWAP_LIQUIDITY => Some(soroswap_yield),
+                strategy::AQUA_LIQUIDITY_POOL => pool_yield,
+                strategy::YIELDBLOX_LENDING => lending_yield,
+                _ => None,
+            };
+            let context = HarvestContext { elapsed_secs, measured };
+            let earned = strategy::lookup(&strategy.strategy).harvest(strategy, &context);
+            strategy.current_yield += earned;
+            strategy_yield.push(earned);
+            report.total_yield += earned;
+            if earned > 0 {
+                yield_records.push(YieldRecord {
+                    risk,
+                    strategy: strategy.strategy.clone(),
+                    amount_stroops: earned,
+                    timestamp: now,
+                });
+            }
+        }
+
+        report.performance_fee = fees::performance_fee(report.total_yield, vault.fees.performance_fee_bps)?;
+        vault.fee_accrual.performance_accrued += report.performance_fee;
+        let net_yield = report.total_yield - report.performance_fee;
+
+        if net_yield > 0 && vault.total_shares > 0 {
+            let share_price = vault.get_share_price();
+            let total_shares = vault.total_shares;
+
+            for ((user, position_risk), position) in self.user_positions.iter_mut() {
+                if *position_risk != risk || position.shares == 0 {
+                    continue;
+                }
+
+                let user_yield = (net_yield as u128 * position.shares as u128 / total_shares as u128) as u64;
+                let mut credit = YieldCredit {
+                    user: user.clone(),
+                    amount_stroops: user_yield,
+                    compounded: position.auto_compound,
+                    shares: 0,
+                };
+                if position.auto_compound {
+                    let new_shares = (user_yield as u128 * 10_000_000 / share_price as u128) as u64;
+                    position.shares += new_shares;
+                    vault.total_value += user_yield;
+                    vault.total_shares += new_shares;
+                    vault.allocate(user_yield);
+                    report.compounded_yield += user_yield;
+                    report.compounded_shares += new_shares;
+                    credit.shares = new_shares;
+                } else {
+                    position.accumulated_yield += user_yield;
+                    report.distributed_yield += user_yield;
+                }
+                credits.push(credit);
+            }
+
+            // The insurance pool's stake always compounds
+            if risk == INSURANCE_VAULT && self.insurance_investment.shares > 0 {
+                let pool_yield = (net_yield as u128 * self.insurance_investment.shares as u128 / total_shares as u128) as u64;
+                let new_shares = vault.mint(pool_yield)?;
+                self.insurance_investment.shares += new_shares;
+                report.compounded_yield += pool_yield;
+                report.compounded_shares += new_shares;
+                insurance_yield = pool_yield;
+                insurance_shares = new_shares;
+            }
+        }
+
+        self.log_event(VaultEvent::Harvest {
+            risk,
+            strategy_yield,
+            performance_fee: report.performance_fee,
+            credits,
+            insurance_yield,
+            insurance_shares,
+        });
+        self.persist()?;
+        for record in &yield_records {
+            if let Err(e) = self.storage.record_yield(record) {
+                say!("   ⚠️  Could not record yield history: {}", e);
+            }
+        }
+
+        Ok(report)
+    }
+
+    pub fn portfolio(&self, user: &str) -> Portfolio {
+        let positions: Vec<PositionSummary> = RiskLevel::ALL.iter()
+            .filter_map(|risk| self.get_position(user, *risk))
+            .filter(|p| p.shares > 0 || p.accumulated_yield > 0)
+            .collect();
+
+        let total_value: u64 = positions.iter().map(|p| p.value_stroops).sum();
+        let weighted_apy: u128 = positions.iter()
+            .map(|p| p.value_stroops as u128 * p.apy as u128)
+            .sum();
+        let blended_apy = if total_value == 0 { 0 } else { (weighted_apy / total_value as u128) as u16 };
+
+        Portfolio {
+            total_value,
+            total_yield: positions.iter().map(|p| p.accumulated_yield).sum(),
+            total_cost_basis: positions.iter().map(|p| p.cost_basis).sum(),
+            total_insurance_paid: positions.iter().map(|p| p.insurance_paid).sum(),
+            blended_apy,
+            positions,
+        }
+    }
+
+    // Moves allocation between strategies when any of them drifts further than
+    // max_drift_bps from its target share of the vault
+    pub fn rebalance(&mut self, risk: RiskLevel, max_drift_bps: u64) -> Result<Vec<RebalanceMove>, VaultError> {
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let drifts = rebalance::compute_drift(&vault.strategies);
+        let max_drift = rebalance::max_drift_bps(&drifts);
+
+        say!("\n⚖️  Allocation drift for {:?} Risk Vault:", risk);
+        for d in &drifts {
+            say!("   {}: {:.7} XLM (target {:.7} XLM, drift {:+} bps)",
+                d.strategy,
+                d.current as f64 / 10_000_000.0,
+                d.target as f64 / 10_000_000.0,
+                d.drift_bps);
+        }
+
+        if max_drift <= max_drift_bps {
+            say!("   ✅ Max drift {} bps is within the {} bps threshold", max_drift, max_drift_bps);
+            return Ok(Vec::new());
+        }
+
+        let moves = rebalance::rebalance_strategies(&mut vault.strategies);
+        for m in &moves {
+            say!("   🔀 Moved {:.7} XLM from {:?} to {:?}", m.amount as f64 / 10_000_000.0, m.from, m.to);
+        }
+
+        if !moves.is_empty() {
+            self.log_event(VaultEvent::Rebalance { risk, moves: moves.clone() });
+        }
+        self.persist()?;
+        Ok(moves)
+    }
+
+    // Pays all outstanding management and performance fees to the treasury
+    pub async fn collect_fees(&mut self, risk: RiskLevel) -> Result<(u64, TransactionReceipt), VaultError> {
+        let treasury = self.treasury_address.clone()
+            .ok_or("Treasury address is not configured")?;
+
+        self.accrue_fees(risk)?;
+        let vault = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let amount_stroops = vault.fee_accrual.outstanding();
+        if amount_stroops == 0 {
+            return Err(VaultError::Validation("No fees to collect".into()));
+        }
+        let asset = vault.xdr_asset()?;
+
+        let signer = self.vault_signer.as_ref()
+            .ok_or("Vault signing key is not configured; fees cannot be collected")?;
+        // A treasury reached through federation may need its own memo
+        let memo = match self.treasury_memo.clone() {
+            Some(memo) => memo,
+            None => transaction::text_memo("SYIA fees")?,
+        };
+        let receipt = signer.send_asset(&treasury, asset, Stroops(amount_stroops), memo).await
+            .map_err(|e| format!("Fee payment failed: {}", e))?;
+
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        vault.fee_accrual.management_accrued = 0;
+        vault.fee_accrual.performance_accrued = 0;
+        vault.fee_accrual.total_collected += amount_stroops;
+
+        self.log_event(VaultEvent::FeesCollected { risk, amount_stroops, tx_hash: receipt.hash.clone() });
+        if let Err(e) = self.persist() {
+            say!("   ⚠️  Could not save vault state to {}: {}", self.storage.describe(), e);
+        }
+
+        Ok((amount_stroops, receipt))
+    }
+
+    pub fn file_claim(&mut self, user: &str, risk: RiskLevel, amount_stroops: u64, loss_event: &str) -> Result<u64, VaultError> {
+        let position = self.user_positions.get(&(user.to_string(), risk))
+            .ok_or_else(|| format!("No position in the {:?} Risk Vault to claim against", risk))?;
+        if amount_stroops == 0 || amount_stroops > position.cost_basis {
+            return Err(VaultError::Validation(format!("Claim amount must be between 0 and your cost basis of {} XLM",
+                position.cost_basis as f64 / 10_000_000.0)));
+        }
+        if loss_event.trim().is_empty() {
+            return Err(VaultError::Validation("Describe the loss event the claim is filed against".into()));
+        }
+
+        let id = self.claims.file(user, risk, amount_stroops, loss_event.trim(), unix_now());
+        self.persist()?;
+        Ok(id)
+    }
+
+    pub fn deny_claim(&mut self, id: u64, note: &str) -> Result<(), VaultError> {
+        self.claims.decide(id, ClaimStatus::Denied, Some(note.to_string()), unix_now())?;
+        self.notify_claim_decision(id, ClaimStatus::Denied, note);
+        self.persist()
+    }
+
+    fn notify_claim_decision(&self, id: u64, status: ClaimStatus, note: &str) {
+        if let Some(claim) = self.claims.get(id) {
+            self.notify(Notification::ClaimDecided {
+                claim_id: id,
+                claimant: claim.claimant.clone(),
+                amount_stroops: claim.amount_stroops,
+                status,
+                note: note.to_string(),
+            });
+        }
+    }
+
+    // Approval is persisted before the payout so a failed payment can be retried
+    // with pay_claim without re-deciding the claim
+    pub async fn approve_claim(&mut self, id: u64, note: &str) -> Result<TransactionReceipt, VaultError> {
+        if self.vault_signer.is_none() {
+            return Err(VaultError::Validation("Vault signing key is not configured; claims cannot be paid".into()));
+        }
+        let amount_stroops = self.claims.get(id)
+            .ok_or_else(|| format!("Claim #{} not found", id))?
+            .amount_stroops;
+        if amount_stroops > self.insurance_assets() {
+            return Err(VaultError::Validation(format!("Insurance pool holds only {} XLM",
+                self.insurance_assets() as f64 / 10_000_000.0)));
+        }
+
+        self.claims.decide(id, ClaimStatus::Approved, Some(note.to_string()), unix_now())?;
+        self.notify_claim_decision(id, ClaimStatus::Approved, note);
+        self.persist()?;
+        self.pay_claim(id).await
+    }
+
+    pub async fn pay_claim(&mut self, id: u64) -> Result<TransactionReceipt, VaultError> {
+        let claim = self.claims.get(id)
+            .ok_or_else(|| format!("Claim #{} not found", id))?
+            .clone();
+        if claim.status != ClaimStatus::Approved {
+            return Err(VaultError::Validation(format!("Claim #{} is {:?}, not awaiting payout", id, claim.status)));
+        }
+        if claim.amount_stroops > self.insurance_assets() {
+            return Err(VaultError::Validation("Insurance pool cannot cover this claim".into()));
+        }
+        self.screen(Screened::Payout, &claim.claimant, claim.risk, claim.amount_stroops)?;
+        if claim.amount_stroops > self.insurance_pool {
+            self.redeem_insurance(claim.amount_stroops - self.insurance_pool)?;
+        }
+
+        let signer = self.vault_signer.as_ref()
+            .ok_or("Vault signing key is not configured; claims cannot be paid")?;
+        let receipt = signer.send_payment(&claim.claimant, Stroops(claim.amount_stroops), transaction::text_memo(&format!("SYIA claim #{}", id))?).await
+            .map_err(|e| format!("Claim payout failed: {}", e))?;
+
+        self.insurance_pool -= claim.amount_stroops;
+        self.claims.mark_paid(id, &receipt.hash)?;
+        self.log_event(VaultEvent::ClaimPaid {
+            claim_id: id,
+            amount_stroops: claim.amount_stroops,
+            tx_hash: receipt.hash.clone(),
+        });
+
+        if let Err(e) = self.persist() {
+            say!("   ⚠️  Could not save vault state to {}: {}", self.storage.describe(), e);
+        }
+
+        // Payouts drain cover, so premiums may need to rise straight away
+        match self.adjust_premiums() {
+            Ok(report) => print_premium_changes(&report),
+            Err(e) => say!("   ⚠️  Could not adjust insurance premiums: {}", e),
+        }
+
+        Ok(receipt)
+    }
+
+    // Counts the vault account's DEX trades since the last sync towards the
+    // market-making fills, returning how many were on the pair
+    pub async fn sync_market_making(&mut self, risk: RiskLevel) -> Result<usize, VaultError> {
+        let vault = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let Some(maker) = &vault.market_making else {
+            return Ok(0);
+        };
+        let mut cursor = maker.trade_cursor.clone();
+        let mut trades = Vec::new();
+        loop {
+            let page = self.horizon.trades(&self.vault_address, cursor.as_deref(), 200).await?;
+            let Some(last) = page.last() else { break };
+            cursor = Some(last.paging_token.clone());
+            let full = page.len() == 200;
+            trades.extend(page);
+            if !full {
+                break;
+            }
+        }
+
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let base = vault.asset.clone();
+        let Some(maker) = vault.market_making.as_mut() else {
+            return Ok(0);
+        };
+        let mut counted = 0;
+        for trade in &trades {
+            if maker.record(trade, &self.vault_address, base.as_ref())? {
+                counted += 1;
+            }
+        }
+        self.persist()?;
+        Ok(counted)
+    }
+
+    // Cancels the vault's offers on the market-making pair and quotes both
+    // sides again around the current mid price
+    pub async fn refresh_offers(&self, risk: RiskLevel) -> Result<TransactionReceipt, VaultError> {
+        let signer = self.vault_signer.as_ref()
+            .ok_or("Vault signing key is not configured; offers cannot be placed")?;
+        let vault = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let maker = vault.market_making.as_ref()
+            .ok_or_else(|| format!("The {:?} Risk Vault has no market making configured", risk))?;
+        let allocation = vault.strategies.iter()
+            .find(|s| s.strategy == strategy::SDEX_MARKET_MAKING)
+            .map_or(0, |s| s.total_allocated);
+
+        let pair = sdex::pair_label(vault.asset.as_ref(), maker.counter.as_ref());
+        let book = self.horizon.order_book(&path_payment::canonical(vault.asset.as_ref()),
+            &path_payment::canonical(maker.counter.as_ref())).await?;
+        let mid = sdex::mid_price(&book).ok_or_else(|| format!("The {} order book is empty, so there is no price to quote around", pair))?;
+        let open = self.horizon.offers(&self.vault_address).await?;
+        let operations = maker.offer_operations(vault.asset.as_ref(), &open, mid, allocation)?;
+        if operations.is_empty() {
+            return Err(VaultError::Validation(format!("Nothing to quote on {}: the strategy has no allocation yet", pair)));
+        }
+        signer.submit_operations(operations).await
+    }
+
+    // Reads the vault's pool shares and values them from the pool reserves.
+    // None when the vault has no pool configured.
+    pub async fn sync_liquidity_pool(&mut self, risk: RiskLevel) -> Result<Option<horizon::LiquidityPool>, VaultError> {
+        let vault = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let Some(position) = &vault.liquidity_pool else {
+            return Ok(None);
+        };
+        let base = vault.asset.clone();
+        let pool_id = amm::pool_id_hex(&position.parameters(base.as_ref())?)?;
+        let pool = self.horizon.liquidity_pool(&pool_id).await
+            .map_err(|e| format!("Could not load pool {}: {}", pool_id, e))?;
+        let balances = self.horizon.account(&self.vault_address).await?.balances;
+        let shares = match balances.iter().find(|b| b.liquidity_pool_id.as_deref() == Some(pool_id.as_str())) {
+            Some(balance) => Stroops::from_xlm_str(&balance.balance)?.0,
+            None => 0,
+        };
+        let total_shares = Stroops::from_xlm_str(&pool.total_shares)?.0;
+        let value = amm::position_value(shares, total_shares, amm::reserve(&pool, base.as_ref())?);
+
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let code = vault.asset_code().to_string();
+        if let Some(position) = vault.liquidity_pool.as_mut() {
+            if value < position.high_water {
+                say!("   ⚠️  The pool stake is worth {} {}, below the {} already counted; no yield until it recovers",
+                    Stroops(value).to_xlm_string(), code, Stroops(position.high_water).to_xlm_string());
+            }
+            position.shares = shares;
+            position.value = value;
+        }
+        self.persist()?;
+        Ok(Some(pool))
+    }
+
+    // Moves the AquaLiquidityPool allocation not yet in the pool into it:
+    // half is swapped for the counter asset, then both halves are deposited
+    // at the pool's price, in one transaction
+    pub async fn deposit_liquidity(&mut self, risk: RiskLevel) -> Result<(TransactionReceipt, u64), VaultError> {
+        let pool = self.sync_liquidity_pool(risk).await?
+            .ok_or_else(|| format!("The {:?} Risk Vault has no liquidity pool configured", risk))?;
+        let signer = self.vault_signer.as_ref()
+            .ok_or("Vault signing key is not configured; liquidity cannot be moved")?;
+        let vault = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let position = vault.liquidity_pool.as_ref().ok_or("No liquidity pool configured")?;
+        let allocated = vault.strategies.iter()
+            .find(|s| s.strategy == strategy::AQUA_LIQUIDITY_POOL)
+            .map_or(0, |s| s.total_allocated);
+        let amount = allocated.saturating_sub(position.deposited);
+        if amount < 2 {
+            return Err(VaultError::Validation("Nothing to deposit: the strategy's allocation is already in the pool".into()));
+        }
+        if Stroops::from_xlm_str(&pool.total_shares)? == Stroops::ZERO {
+            return Err(VaultError::Validation(format!("Pool {} is empty; it needs seeding before it has a price to deposit at", pool.id)));
+        }
+
+        let (base, counter) = (vault.asset.clone(), position.counter.clone());
+        let parameters = position.parameters(base.as_ref())?;
+        let half = amount / 2;
+        let swap = path_payment::quote(&self.horizon, base.as_ref(), Stroops(half), counter.as_ref(), self.max_slippage_bps).await?;
+        let (base_reserve, counter_reserve) = (amm::reserve(&pool, base.as_ref())?, amm::reserve(&pool, counter.as_ref())?);
+        let base_first = path_payment::to_xdr(base.as_ref())? <= path_payment::to_xdr(counter.as_ref())?;
+        let (max_a, max_b, price) = if base_first {
+            (amount - half, swap.min_received.0, base_reserve as f64 / counter_reserve as f64)
+        } else {
+            (swap.min_received.0, amount - half, counter_reserve as f64 / base_reserve as f64)
+        };
+
+        let mut operations = Vec::new();
+        let balances = self.horizon.account(&self.vault_address).await?.balances;
+        if let Some(counter) = &counter {
+            if counter.trustline(&balances).is_none() {
+                operations.push(assets::change_trust(&counter.to_xdr()?, assets::UNLIMITED)?);
+            }
+        }
+        let pool_id = amm::pool_id_hex(&parameters)?;
+        if !balances.iter().any(|b| b.liquidity_pool_id.as_deref() == Some(pool_id.as_str())) {
+            operations.push(amm::share_trust(&parameters));
+        }
+        operations.push(swap.operation(&self.vault_address)?);
+        operations.push(amm::deposit(&parameters, max_a, max_b, price, self.max_slippage_bps)?);
+        let receipt = signer.submit_operations(operations).await?;
+
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        if let Some(position) = vault.liquidity_pool.as_mut() {
+            position.record_deposit(amount);
+        }
+        if let Err(e) = self.sync_liquidity_pool(risk).await {
+            say!("   ⚠️  Deposited, but could not re-read the pool: {}", e);
+        }
+        Ok((receipt, amount))
+    }
+
+    // Takes every pool share out and swaps the counter half back
+    pub async fn withdraw_liquidity(&mut self, risk: RiskLevel) -> Result<TransactionReceipt, VaultError> {
+        let pool = self.sync_liquidity_pool(risk).await?
+            .ok_or_else(|| format!("The {:?} Risk Vault has no liquidity pool configured", risk))?;
+        let signer = self.vault_signer.as_ref()
+            .ok_or("Vault signing key is not configured; liquidity cannot be moved")?;
+        let vault = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let position = vault.liquidity_pool.as_ref().ok_or("No liquidity pool configured")?;
+        if position.shares == 0 {
+            return Err(VaultError::Validation("The vault holds no shares in the pool".into()));
+        }
+
+        let (base, counter) = (vault.asset.clone(), position.counter.clone());
+        let parameters = position.parameters(base.as_ref())?;
+        let total_shares = Stroops::from_xlm_str(&pool.total_shares)?.0;
+        let share_of = |reserve: u64| mul_div(position.shares, reserve, total_shares, Rounding::Down);
+        let min_base = path_payment::min_received(Stroops(share_of(amm::reserve(&pool, base.as_ref())?)?), self.max_slippage_bps)?;
+        let min_counter = path_payment::min_received(Stroops(share_of(amm::reserve(&pool, counter.as_ref())?)?), self.max_slippage_bps)?;
+        let (min_a, min_b) = if path_payment::to_xdr(base.as_ref())? <= path_payment::to_xdr(counter.as_ref())? {
+            (min_base.0, min_counter.0)
+        } else {
+            (min_counter.0, min_base.0)
+        };
+        let swap_back = path_payment::quote(&self.horizon, counter.as_ref(), min_counter, base.as_ref(), self.max_slippage_bps).await?;
+
+        let operations = vec![
+            amm::withdraw(&parameters, position.shares, min_a, min_b)?,
+            swap_back.operation(&self.vault_address)?,
+        ];
+        let receipt = signer.submit_operations(operations).await?;
+
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        if let Some(position) = vault.liquidity_pool.as_mut() {
+            position.record_withdrawal();
+        }
+        self.persist()?;
+        Ok(receipt)
+    }
+
+    // The RPC server, pool and vault asset token a Blend call needs
+    pub fn blend_call(&self, risk: RiskLevel) -> Result<(&soroban::RpcClient, String, String), VaultError> {
+        let vault = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let position = vault.lending.as_ref()
+            .ok_or_else(|| format!("The {:?} Risk Vault has no Blend pool configured", risk))?;
+        let rpc = self.soroban.as_ref()
+            .ok_or("No Soroban RPC server is configured; set soroban_rpc_url")?;
+        let token = soroban::asset_contract(&vault.xdr_asset()?, self.network.passphrase())?;
+        Ok((rpc, position.pool.clone(), token))
+    }
+
+    // Reads the vault's b-tokens and the reserve's b_rate from the pool,
+    // valuing the position and taking the strategy's APY from the rate's
+    // growth. False when the vault lends to no pool.
+    pub async fn sync_blend(&mut self, risk: RiskLevel) -> Result<bool, VaultError> {
+        if self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?.lending.is_none() {
+            return Ok(false);
+        }
+        let (rpc, pool, token) = self.blend_call(risk)?;
+        let reserve = rpc.read(&self.vault_address, blend::get_reserve(&pool, &token)?).await
+            .map_err(|e| format!("Could not read pool {}: {}", pool, e))?;
+        let (index, b_rate) = blend::parse_reserve(&reserve)?;
+        let positions = rpc.read(&self.vault_address, blend::get_positions(&pool, &self.vault_address)?).await
+            .map_err(|e| format!("Could not read the vault's positions in pool {}: {}", pool, e))?;
+        let b_tokens = blend::supplied_b_tokens(&positions, index)?;
+
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let code = vault.asset_code().to_string();
+        let Some(position) = vault.lending.as_mut() else {
+            return Ok(false);
+        };
+        position.observe(b_tokens, b_rate, unix_now());
+        if position.value < position.high_water {
+            say!("   ⚠️  The Blend position is worth {} {}, below the {} already counted; no yield until it recovers",
+                Stroops(position.value).to_xlm_string(), code, Stroops(position.high_water).to_xlm_string());
+        }
+        if let Some(apy) = position.supply_apy_bps {
+            if let Some(strategy) = vault.strategies.iter_mut().find(|s| s.strategy == strategy::YIELDBLOX_LENDING) {
+                strategy.current_apy = apy;
+            }
+        }
+        self.persist()?;
+        Ok(true)
+    }
+
+    // Lends the YieldBloxLending allocation not yet in the pool
+    pub async fn supply_blend(&mut self, risk: RiskLevel) -> Result<(TransactionReceipt, u64), VaultError> {
+        self.sync_blend(risk).await?;
+        let signer = self.vault_signer.as_ref()
+            .ok_or("Vault signing key is not configured; funds cannot be lent")?;
+        let (rpc, pool, token) = self.blend_call(risk)?;
+        let vault = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let supplied = vault.lending.as_ref().map_or(0, |position| position.supplied);
+        let allocated = vault.strategies.iter()
+            .find(|s| s.strategy == strategy::YIELDBLOX_LENDING)
+            .map_or(0, |s| s.total_allocated);
+        let amount = allocated.saturating_sub(supplied);
+        if amount == 0 {
+            return Err(VaultError::Validation("Nothing to supply: the strategy's allocation is already lent".into()));
+        }
+        let (receipt, _) = signer.invoke_contract(rpc, blend::supply(&pool, &self.vault_address, &token, amount)?).await?;
+
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        if let Some(position) = vault.lending.as_mut() {
+            position.record_supply(amount);
+        }
+        if let Err(e) = self.sync_blend(risk).await {
+            say!("   ⚠️  Supplied, but could not re-read the pool: {}", e);
+        }
+        Ok((receipt, amount))
+    }
+
+    // Takes everything lent to the pool back, interest included, returning
+    // the position's value at the last sync
+    pub async fn withdraw_blend(&mut self, risk: RiskLevel) -> Result<(TransactionReceipt, u64), VaultError> {
+        self.sync_blend(risk).await?;
+        let signer = self.vault_signer.as_ref()
+            .ok_or("Vault signing key is not configured; funds cannot be withdrawn")?;
+        let (rpc, pool, token) = self.blend_call(risk)?;
+        let vault = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let value = vault.lending.as_ref().map_or(0, |position| position.value);
+        if value == 0 {
+            return Err(VaultError::Validation("The vault has nothing lent to the pool".into()));
+        }
+        // Asking for more than the position takes interest accrued since the sync too
+        let (receipt, _) = signer.invoke_contract(rpc, blend::withdraw(&pool, &self.vault_address, &token, value.saturating_mul(2))?).await?;
+
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        if let Some(position) = vault.lending.as_mut() {
+            position.record_withdrawal();
+        }
+        self.persist()?;
+        Ok((receipt, value))
+    }
+
+    // The RPC server, router and the two token contracts, vault asset first,
+    // a Soroswap call needs
+    pub fn soroswap_call(&self, risk: RiskLevel) -> Result<(&soroban::RpcClient, String, String, String), VaultError> {
+        let vault = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let position = vault.soroswap.as_ref()
+            .ok_or_else(|| format!("The {:?} Risk Vault has no Soroswap pair configured", risk))?;
+        let rpc = self.soroban.as_ref()
+            .ok_or("No Soroban RPC server is configured; set soroban_rpc_url")?;
+        let base = soroban::asset_contract(&vault.xdr_asset()?, self.network.passphrase())?;
+        let counter = soroban::asset_contract(&path_payment::to_xdr(position.counter.as_ref())?, self.network.passphrase())?;
+        Ok((rpc, position.router.clone(), base, counter))
+    }
+
+    // Reads the pair's reserves and the vault's shares of it, valuing the
+    // position. None when the vault has no Soroswap pair configured.
+    pub async fn sync_soroswap(&mut self, risk: RiskLevel) -> Result<Option<soroswap::PairState>, VaultError> {
+        if self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?.soroswap.is_none() {
+            return Ok(None);
+        }
+        let (rpc, router, base, counter) = self.soroswap_call(risk)?;
+        let source = self.vault_address.as_str();
+        let read_error = |e: VaultError| format!("Could not read the Soroswap pair: {}", e);
+        let pair = rpc.read(source, soroswap::pair_for(&router, &base, &counter)?).await.map_err(read_error)?;
+        let pair = soroban::to_address(&pair).ok_or("Router returned no pair address")?;
+        let token_0 = rpc.read(source, soroswap::token_0(&pair)?).await.map_err(read_error)?;
+        let reserves = rpc.read(source, soroswap::get_reserves(&pair)?).await.map_err(read_error)?;
+        let (base_reserve, counter_reserve) = soroswap::reserves(&reserves, soroban::to_address(&token_0).as_deref() == Some(base.as_str()))?;
+        let total_shares = rpc.read(source, soroswap::total_shares(&pair)?).await.map_err(read_error)?;
+        let total_shares = soroban::to_amount(&total_shares).ok_or("Pair returned an unreadable share supply")?;
+        let shares = rpc.read(source, soroswap::shares_of(&pair, source)?).await.map_err(read_error)?;
+        let shares = soroban::to_amount(&shares).ok_or("Pair returned an unreadable share balance")?;
+        let value = amm::position_value(shares, total_shares, base_reserve);
+
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let code = vault.asset_code().to_string();
+        if let Some(position) = vault.soroswap.as_mut() {
+            if value < position.high_water {
+                say!("   ⚠️  The Soroswap liquidity is worth {} {}, below the {} already counted; no yield until it recovers",
+                    Stroops(value).to_xlm_string(), code, Stroops(position.high_water).to_xlm_string());
+            }
+            position.shares = shares;
+            position.value = value;
+        }
+        self.persist()?;
+        Ok(Some(soroswap::PairState { pair, base_reserve, counter_reserve, total_shares }))
+    }
+
+    // What `amount` of the vault asset (or of the counter asset, when
+    // `from_base` is false) swaps for through the router right now
+    pub async fn soroswap_quote(&self, risk: RiskLevel, amount: u64, from_base: bool) -> Result<u64, VaultError> {
+        let (rpc, router, base, counter) = self.soroswap_call(risk)?;
+        let path = if from_base { [base.as_str(), counter.as_str()] } else { [counter.as_str(), base.as_str()] };
+        let amounts = rpc.read(&self.vault_address, soroswap::get_amounts_out(&router, amount, &path)?).await?;
+        soroswap::amount_out(&amounts)
+    }
+
+    // Moves the SoroswapLiquidity allocation not yet in the pair into it:
+    // half is swapped for the counter asset through the router, then both
+    // halves are added. Contract calls go one per transaction, so a failed
+    // add leaves the swapped half in the vault account.
+    pub async fn deposit_soroswap(&mut self, risk: RiskLevel) -> Result<(TransactionReceipt, u64), VaultError> {
+        let pair = self.sync_soroswap(risk).await?
+            .ok_or_else(|| format!("The {:?} Risk Vault has no Soroswap pair configured", risk))?;
+        if pair.total_shares == 0 {
+            return Err(VaultError::Validation(format!("Soroswap pair {} is empty; it needs seeding before it has a price to add at", pair.pair)));
+        }
+        let signer = self.vault_signer.as_ref()
+            .ok_or("Vault signing key is not configured; liquidity cannot be moved")?;
+        let (rpc, router, base, counter) = self.soroswap_call(risk)?;
+        let vault = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let position = vault.soroswap.as_ref().ok_or("No Soroswap pair configured")?;
+        let allocated = vault.strategies.iter()
+            .find(|s| s.strategy == strategy::SOROSWAP_LIQUIDITY)
+            .map_or(0, |s| s.total_allocated);
+        let amount = allocated.saturating_sub(position.deposited);
+        if amount < 2 {
+            return Err(VaultError::Validation("Nothing to deposit: the strategy's allocation is already in the pair".into()));
+        }
+
+        if let Some(counter_asset) = &position.counter {
+            let balances = self.horizon.account(&self.vault_address).await?.balances;
+            if counter_asset.trustline(&balances).is_none() {
+                signer.submit_operations(vec![assets::change_trust(&counter_asset.to_xdr()?, assets::UNLIMITED)?]).await?;
+            }
+        }
+        let half = amount / 2;
+        let expected = self.soroswap_quote(risk, half, true).await?;
+        let counter_min = path_payment::min_received(Stroops(expected), self.max_slippage_bps)?.0;
+        let deadline = unix_now() + PAYMENT_TIMEOUT_SECS;
+        let (_, swapped) = signer.invoke_contract(rpc,
+            soroswap::swap(&router, &self.vault_address, half, counter_min, &[&base, &counter], deadline)?).await?;
+        // The swap returns the amounts along its path; add what it actually delivered
+        let received = swapped.as_ref().and_then(|amounts| soroswap::amount_out(amounts).ok()).unwrap_or(counter_min);
+
+        let base_amount = amount - half;
+        let base_min = path_payment::min_received(Stroops(base_amount), self.max_slippage_bps)?.0;
+        let counter_floor = path_payment::min_received(Stroops(received), self.max_slippage_bps)?.0;
+        let deadline = unix_now() + PAYMENT_TIMEOUT_SECS;
+        let (receipt, _) = signer.invoke_contract(rpc, soroswap::add_liquidity(&router, &self.vault_address,
+            (&base, base_amount, base_min), (&counter, received, counter_floor), deadline)?).await
+            .map_err(|e| format!("Swapped half the deposit, but adding liquidity failed; the swapped {} stays in the vault account: {}",
+                path_payment::label(position.counter.as_ref()), e))?;
+
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        if let Some(position) = vault.soroswap.as_mut() {
+            position.record_deposit(amount);
+        }
+        if let Err(e) = self.sync_soroswap(risk).await {
+            say!("   ⚠️  Deposited, but could not re-read the pair: {}", e);
+        }
+        Ok((receipt, amount))
+    }
+
+    // Removes all the vault's liquidity and swaps the counter half back
+    pub async fn withdraw_soroswap(&mut self, risk: RiskLevel) -> Result<TransactionReceipt, VaultError> {
+        let pair = self.sync_soroswap(risk).await?
+            .ok_or_else(|| format!("The {:?} Risk Vault has no Soroswap pair configured", risk))?;
+        let signer = self.vault_signer.as_ref()
+            .ok_or("Vault signing key is not configured; liquidity cannot be moved")?;
+        let (rpc, router, base, counter) = self.soroswap_call(risk)?;
+        let vault = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let shares = vault.soroswap.as_ref().map_or(0, |position| position.shares);
+        if shares == 0 {
+            return Err(VaultError::Validation("The vault holds no liquidity in the pair".into()));
+        }
+
+        let share_of = |reserve: u64| mul_div(shares, reserve, pair.total_shares, Rounding::Down);
+        let base_min = path_payment::min_received(Stroops(share_of(pair.base_reserve)?), self.max_slippage_bps)?.0;
+        let counter_min = path_payment::min_received(Stroops(share_of(pair.counter_reserve)?), self.max_slippage_bps)?.0;
+        let deadline = unix_now() + PAYMENT_TIMEOUT_SECS;
+        let (receipt, removed) = signer.invoke_contract(rpc, soroswap::remove_liquidity(&router, &self.vault_address,
+            (&base, base_min), (&counter, counter_min), shares, deadline)?).await?;
+        // remove_liquidity returns the two amounts taken out, in argument order
+        let counter_out = removed.as_ref()
+            .and_then(|amounts| soroswap::amount_out(amounts).ok())
+            .unwrap_or(counter_min);
+
+        let swap_back = async {
+            let expected = self.soroswap_quote(risk, counter_out, false).await?;
+            let min_out = path_payment::min_received(Stroops(expected), self.max_slippage_bps)?.0;
+            let deadline = unix_now() + PAYMENT_TIMEOUT_SECS;
+            signer.invoke_contract(rpc, soroswap::swap(&router, &self.vault_address, counter_out, min_out, &[&counter, &base], deadline)?).await
+        };
+        if let Err(e) = swap_back.await {
+            say!("   ⚠️  Liquidity removed, but swapping the counter asset back failed; it stays in the vault account: {}", e);
+        }
+
+        let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        if let Some(position) = vault.soroswap.as_mut() {
+            position.record_withdrawal();
+        }
+        self.persist()?;
+        Ok(receipt)
+    }
+
+    // The RPC server and vault contract, when shares live on-chain
+    pub fn vault_contract_call(&self) -> Result<(&soroban::RpcClient, &str), VaultError> {
+        let contract = self.vault_contract.as_deref().ok_or("No vault contract is configured; set vault_contract")?;
+        let rpc = self.soroban.as_ref().ok_or("The vault contract needs a Soroban RPC server; set soroban_rpc_url")?;
+        Ok((rpc, contract))
+    }
+
+    // The contract takes the user's tokens and mints their shares in one
+    // call, so there is no payment to reconcile and nothing to refund
+    pub async fn deposit_to_contract(&mut self, user: &str, risk: RiskLevel, amount: Stroops) -> Result<(u64, Stroops, TransactionReceipt), VaultError> {
+        let (rpc, contract) = self.vault_contract_call()?;
+        say!("\n💼 Depositing {} into vault contract {}...", amount.to_xlm_string(), contract);
+        let client = self.users.get(user)?;
+        let (receipt, minted) = client.invoke_contract(rpc, vault_contract::deposit(contract, user, risk, amount.0)?).await?;
+        let shares = minted.as_ref().and_then(soroban::to_amount)
+            .ok_or_else(|| format!("Deposit {} applied, but the contract returned no share count", receipt.hash))?;
+        Ok((shares, amount, receipt))
+    }
+
+    pub async fn withdraw_from_contract(&mut self, user: &str, risk: RiskLevel, shares: u64) -> Result<WithdrawalReceipt, VaultError> {
+        let (rpc, contract) = self.vault_contract_call()?;
+        say!("\n💸 Redeeming {} shares from vault contract {}...", shares, contract);
+        let client = self.users.get(user)?;
+        let (receipt, paid) = client.invoke_contract(rpc, vault_contract::withdraw(contract, user, risk, shares)?).await?;
+        let amount = paid.as_ref().and_then(soroban::to_amount)
+            .ok_or_else(|| format!("Withdrawal {} applied, but the contract returned no amount", receipt.hash))?;
+        Ok(WithdrawalReceipt { gross_stroops: amount, penalty_stroops: 0, net_stroops: amount, transaction: receipt })
+    }
+
+    pub async fn load_share_tokens(&mut self) -> Result<(), VaultError> {
+        let (rpc, contract) = self.vault_contract_call()?;
+        let mut share_tokens = Vec::new();
+        for risk in RiskLevel::ALL {
+            let token = rpc.read(&self.vault_address, vault_contract::share_token(contract, risk)?).await?;
+            let token = soroban::to_address(&token).ok_or("Vault contract returned an unreadable share token")?;
+            share_tokens.push((risk, token));
+        }
+        self.share_tokens = share_tokens;
+        Ok(())
+    }
+
+    // Follows the vault contract's events and its share tokens' transfers
+    // from where the index left off
+    pub fn contract_stream(&self) -> Option<UnboundedReceiver<IndexEvent>> {
+        let (rpc, contract) = self.vault_contract_call().ok()?;
+        say!("📜 Indexing events of vault contract {}", contract);
+        let mut contracts = vec![contract.to_string()];
+        contracts.extend(self.share_tokens.iter().map(|(_, token)| token.clone()));
+        Some(contract_index::spawn(rpc.clone(), contracts, self.contract_index.cursor.clone()))
+    }
+
+    pub async fn contract_vault(&self, risk: RiskLevel) -> Result<vault_contract::ContractVault, VaultError> {
+        let (rpc, contract) = self.vault_contract_call()?;
+        let state = rpc.read(&self.vault_address, vault_contract::get_vault(contract, risk)?).await?;
+        vault_contract::ContractVault::parse(&state)
+    }
+
+    pub async fn contract_shares(&self, user: &str, risk: RiskLevel) -> Result<u64, VaultError> {
+        let (rpc, contract) = self.vault_contract_call()?;
+        let shares = rpc.read(&self.vault_address, vault_contract::shares_of(contract, user, risk)?).await?;
+        soroban::to_amount(&shares).ok_or_else(|| "Vault contract returned an unreadable share count".into())
+    }
+
+    // Copies a vault's insurance fee and strategy allocations into the
+    // contract; the vault account is the contract's admin
+    pub async fn configure_contract(&self, risk: RiskLevel) -> Result<TransactionReceipt, VaultError> {
+        let (rpc, contract) = self.vault_contract_call()?;
+        let signer = self.vault_signer.as_ref()
+            .ok_or("Vault signing key is not configured; the vault contract cannot be administered")?;
+        let vault = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+        let strategies: Vec<(String, u32)> = vault.strategies.iter()
+            .filter(|s| s.allocation_percentage > 0)
+            .map(|s| (s.strategy.clone(), s.allocation_percentage as u32 * 100))
+            .collect();
+        let operation = vault_contract::configure(contract, risk, vault.insurance_fee, &strategies)?;
+        Ok(signer.invoke_contract(rpc, operation).await?.0)
+    }
+
+    // Pays harvested yield from the vault account into the contract, where
+    // it raises the share price for every holder
+    pub async fn harvest_to_contract(&self, risk: RiskLevel, amount: u64) -> Result<TransactionReceipt, VaultError> {
+        let (rpc, contract) = self.vault_contract_call()?;
+        let signer = self.vault_signer.as_ref()
+            .ok_or("Vault signing key is not configured; the vault contract cannot be administered")?;
+        Ok(signer.invoke_contract(rpc, vault_contract::harvest(contract, risk, amount)?).await?.0)
+    }
+
+    // Installs the vault and share token code, creates a share token per risk
+    // level and an instance holding the vaults' asset, administered by the
+    // vault account, then copies every vault's fee and strategies into it.
+    // Returns the new contract's address.
+    pub async fn deploy_contract(&mut self, wasm: &[u8], share_token_wasm: &[u8]) -> Result<String, VaultError> {
+        let rpc = self.soroban.as_ref().ok_or("Deploying the vault contract needs a Soroban RPC server; set soroban_rpc_url")?;
+        let signer = self.vault_signer.as_ref()
+            .ok_or("Vault signing key is not configured; the vault contract cannot be deployed")?;
+        let asset = self.vaults.get(&RiskLevel::Low).ok_or(VaultError::VaultNotFound(RiskLevel::Low))?.asset.clone();
+        if self.vaults.values().any(|v| v.asset != asset) {
+            return Err(VaultError::Validation("The vault contract holds a single token, but the vaults take different assets".into()));
+        }
+        let passphrase = self.network.passphrase();
+
+        let xdr_asset = path_payment::to_xdr(asset.as_ref())?;
+        let token = soroban::asset_contract(&xdr_asset, passphrase)?;
+        if rpc.contract_executable(&token).await?.is_none() {
+            say!("   🪙 Deploying the asset contract {}", token);
+            signer.invoke_contract(rpc, soroban::create_asset_contract(&xdr_asset)?).await?;
+        }
+
+        for code in [wasm, share_token_wasm] {
+            say!("   📦 Uploading {} bytes of contract code ({})", code.len(), soroban::hex(&soroban::wasm_hash(code)));
+            signer.invoke_contract(rpc, soroban::upload_wasm(code)?).await?;
+        }
+
+        // The share tokens name the vault contract as their admin, so they
+        // are created first, at the address it will get
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+        let salt = soroban::wasm_hash(format!("{}:{}", self.vault_address, nanos).as_bytes());
+        let contract = soroban::deployed_contract(&self.vault_address, salt, passphrase)?;
+        let mut share_tokens = Vec::new();
+        for risk in RiskLevel::ALL {
+            let token_salt = soroban::wasm_hash(format!("{}:{:?}", soroban::hex(&salt), risk).as_bytes());
+            let share_token = soroban::deployed_contract(&self.vault_address, token_salt, passphrase)?;
+            let symbol = ShareAsset::for_vault(risk, &self.vault_address).code;
+            let args = vault_contract::share_token_args(&contract, &format!("StellarVault {:?} Risk Share", risk), symbol)?;
+            say!("   🎟️  Creating {} share token {}", symbol, share_token);
+            let operation = soroban::create_contract(&self.vault_address, soroban::wasm_hash(share_token_wasm), token_salt, args)?;
+            signer.invoke_contract(rpc, operation).await?;
+            share_tokens.push((risk, share_token));
+        }
+
+        say!("   🏗️  Creating {}", contract);
+        let constructor_args = vault_contract::constructor_args(&self.vault_address, &token, &share_tokens)?;
+        signer.invoke_contract(rpc, soroban::create_contract(&self.vault_address, soroban::wasm_hash(wasm), salt, constructor_args)?).await?;
+
+        // Events of the previous contract no longer describe anyone's shares
+        self.vault_contract = Some(contract.clone());
+        self.share_tokens = share_tokens;
+        self.contract_index = ContractIndex::default();
+        self.persist()?;
+        for risk in RiskLevel::ALL {
+            self.configure_contract(risk).await
+                .map_err(|e| format!("{} was created, but configuring its {:?} Risk Vault failed: {}", contract, risk, e))?;
+        }
+        Ok(contract)
+    }
+
+    // The vault contract's storage read straight from the ledger, against
+    // the read model indexed from its events, and the token it holds against
+    // what its vaults and reserves account for
+    pub async fn reconcile_contract(&self) -> Result<Vec<Check>, VaultError> {
+        let (rpc, contract) = self.vault_contract_call()?;
+        let keys = RiskLevel::ALL.iter().map(|&risk| vault_contract::vault_key(risk)).collect::<Result<Vec<_>, _>>()?;
+        let entries = rpc.contract_data(contract, &keys).await?;
+        let stored = |key: &stellar_xdr::curr::ScVal| entries.iter().find(|(k, _)| k == key).map(|(_, value)| value);
+
+        let mut checks = Vec::new();
+        let mut accounted = 0;
+        let mut vaults = Vec::new();
+        for risk in RiskLevel::ALL {
+            let on_chain = stored(&vault_contract::vault_key(risk)?).map(vault_contract::ContractVault::parse).transpose()?;
+            let indexed = self.contract_index.vault(risk);
+            if on_chain.is_none() && indexed.is_none() {
+                continue;
+            }
+            let (assets, shares, reserve) = on_chain.map_or((0, 0, 0), |v| (v.total_assets, v.total_shares, v.insurance_reserve));
+            let (indexed_assets, indexed_shares, indexed_reserve) =
+                indexed.map_or((0, 0, 0), |v| (v.total_assets, v.total_shares, v.insurance_reserve));
+            checks.push(Check::equal(format!("{:?} total assets", risk), assets, indexed_assets).for_vaults(vec![risk]));
+            checks.push(Check::equal(format!("{:?} total shares", risk), shares, indexed_shares).for_vaults(vec![risk]));
+            checks.push(Check::equal(format!("{:?} insurance reserve", risk), reserve, indexed_reserve).for_vaults(vec![risk]));
+            accounted += assets + reserve;
+            vaults.push(risk);
+        }
+        if self.share_tokens.is_empty() {
+            return Err(VaultError::Validation("The vault's share tokens are unknown, so share balances can't be read".into()));
+        }
+        for (risk, share_token) in &self.share_tokens {
+            let holders: Vec<_> = self.contract_index.positions.iter().filter(|p| p.risk == *risk).collect();
+            let keys = holders.iter().map(|p| vault_contract::share_balance_key(&p.user)).collect::<Result<Vec<_>, _>>()?;
+            let balances = rpc.contract_data(share_token, &keys).await?;
+            for position in holders {
+                let key = vault_contract::share_balance_key(&position.user)?;
+                let shares = balances.iter().find(|(k, _)| *k == key).and_then(|(_, v)| soroban::to_amount(v)).unwrap_or(0);
+                checks.push(Check::equal(format!("{} {:?} shares", position.user, risk), shares, position.shares).for_vaults(vec![*risk]));
+            }
+        }
+
+        let token = rpc.read(&self.vault_address, vault_contract::token(contract)?).await?;
+        let token = soroban::to_address(&token).ok_or("Vault contract returned an unreadable token")?;
+        let held = rpc.asset_balance(&token, contract).await?;
+        checks.push(Check::covers("Token held against assets and reserves".to_string(), held, accounted).for_vaults(vaults));
+        Ok(checks)
+    }
+
+    // What the vault holds in each of its vaults' assets: the vault account's
+    // balance on Horizon, then every Blend, Soroswap and AMM pool position at
+    // its last synced value
+    pub async fn holdings(&self) -> Result<Vec<(Option<AssetId>, Parts)>, VaultError> {
+        let balances = self.horizon.account(&self.vault_address).await?.balances;
+        let mut holdings: Vec<(Option<AssetId>, Parts)> = Vec::new();
+        for vault in RiskLevel::ALL.iter().filter_map(|risk| self.vaults.get(risk)) {
+            let index = match holdings.iter().position(|(asset, _)| *asset == vault.asset) {
+                Some(index) => index,
+                None => {
+                    let balance = match &vault.asset {
+                        Some(asset) => asset.trustline(&balances),
+                        None => balances.iter().find(|b| b.is_native()),
+                    };
+                    let in_account = match balance {
+                        Some(balance) => Stroops::from_xlm_str(&balance.balance)?.0,
+                        None => 0,
+                    };
+                    holdings.push((vault.asset.clone(), vec![("vault account".to_string(), in_account)]));
+                    holdings.len() - 1
+                }
+            };
+            let positions = [
+                ("Blend", vault.lending.as_ref().map(|p| p.value)),
+                ("Soroswap", vault.soroswap.as_ref().map(|p| p.value)),
+                ("AMM pool", vault.liquidity_pool.as_ref().map(|p| p.value)),
+            ];
+            for (strategy, value) in positions {
+                if let Some(value) = value {
+                    holdings[index].1.push((format!("{:?} {} position", vault.risk_level, strategy), value));
+                }
+            }
+        }
+        Ok(holdings)
+    }
+
+    // The vault account's balances on Horizon against what the books owe in
+    // each asset: TVL, uncollected fees and insurance. Funds out in Blend,
+    // Soroswap or an AMM pool count at their last synced value.
+    pub async fn reconcile_account(&self) -> Result<Vec<Check>, VaultError> {
+        let mut checks = Vec::new();
+        for (asset, on_chain_parts) in self.holdings().await? {
+            let held: u64 = on_chain_parts.iter().map(|(_, value)| value).sum();
+            let insurance = self.insurance_balance(asset.as_ref()).0;
+            let mut owed = insurance;
+            let mut local_parts = Vec::new();
+            let mut vaults = Vec::new();
+            for vault in RiskLevel::ALL.iter().filter_map(|risk| self.vaults.get(risk)).filter(|v| v.asset == asset) {
+                let risk = vault.risk_level;
+                owed += vault.total_value + vault.fee_accrual.outstanding();
+                local_parts.push((format!("{:?} TVL", risk), vault.total_value));
+                if vault.liquid_reserve > 0 {
+                    local_parts.push((format!("{:?} liquid buffer (in TVL)", risk), vault.liquid_reserve));
+                }
+                if vault.fee_accrual.outstanding() > 0 {
+                    local_parts.push((format!("{:?} uncollected fees", risk), vault.fee_accrual.outstanding()));
+                }
+                vaults.push(risk);
+            }
+            local_parts.push(("insurance pool".to_string(), insurance));
+            let code = asset.as_ref().map_or("XLM", |a| a.code.as_str());
+            checks.push(Check::covers(format!("{} held against TVL, fees and insurance", code), held, owed)
+                .for_vaults(vaults)
+                .with_parts(on_chain_parts, local_parts));
+        }
+        Ok(checks)
+    }
+
+    // Reconciles the books against the ledger, re-reading every strategy
+    // position first so funds out in Blend, Soroswap or an AMM pool count at
+    // what they are worth now
+    pub async fn reconcile(&mut self) -> Result<Vec<Check>, VaultError> {
+        if self.vault_contract.is_some() {
+            return self.reconcile_contract().await;
+        }
+        self.sync_strategies().await?;
+        self.reconcile_account().await
+    }
+
+    // Re-reads every vault's Blend, Soroswap and AMM pool position
+    async fn sync_strategies(&mut self) -> Result<(), VaultError> {
+        let risks: Vec<RiskLevel> = self.vaults.keys().copied().collect();
+        for risk in risks {
+            self.sync_blend(risk).await
+                .map_err(|e| format!("Could not read the {:?} Risk Vault's Blend position: {}", risk, e))?;
+            self.sync_soroswap(risk).await
+                .map_err(|e| format!("Could not read the {:?} Risk Vault's Soroswap position: {}", risk, e))?;
+            self.sync_liquidity_pool(risk).await
+                .map_err(|e| format!("Could not read the {:?} Risk Vault's pool stake: {}", risk, e))?;
+        }
+        Ok(())
+    }
+
+    // Proof of reserves: what the vault holds on-chain at the latest ledger,
+    // strategy positions re-read first, and every user's position committed
+    // to a Merkle sum tree per vault, signed with a local vault key. Each
+    // user gets an inclusion proof to check their position is counted.
+    pub async fn proof_of_reserves(&mut self) -> Result<(SignedSummary, Vec<InclusionProof>), VaultError> {
+        if self.vault_contract.is_some() {
+            return Err(VaultError::Validation(
+                "Shares and reserves are already public in the vault contract's storage; read them there".into()));
+        }
+        if self.vault_signer.is_none() {
+            return Err(VaultError::Validation("Vault signing key is not configured; the summary cannot be signed".into()));
+        }
+        self.sync_strategies().await?;
+        let ledger = self.latest_ledger().await;
+        if ledger == 0 {
+            return Err(VaultError::Validation("Could not read the latest ledger from Horizon".into()));
+        }
+        let label = |asset: Option<&AssetId>| asset.map_or_else(|| "XLM".to_string(), AssetId::to_string);
+        let reserves = self.holdings().await?.into_iter()
+            .map(|(asset, parts)| reserves::Reserves {
+                asset: label(asset.as_ref()),
+                total_stroops: parts.iter().map(|(_, stroops)| stroops).sum(),
+                holdings: parts.into_iter().map(|(label, stroops)| reserves::Holding { label, stroops }).collect(),
+            })
+            .collect();
+        let mut vaults = Vec::new();
+        for vault in RiskLevel::ALL.iter().filter_map(|risk| self.vaults.get(risk)) {
+            let mut positions = Vec::new();
+            for ((user, risk), position) in &self.user_positions {
+                if *risk == vault.risk_level && position.shares > 0 {
+                    let value = vault.pool().value_of(Shares(position.shares), Rounding::Down)?;
+                    positions.push((user.clone(), position.shares, value.0));
+                }
+            }
+            vaults.push(reserves::VaultPositions { risk: vault.risk_level, asset: label(vault.asset.as_ref()), positions });
+        }
+        let (summary, proofs) = reserves::build(&self.vault_address, self.network.passphrase(), ledger, unix_now(), reserves, vaults)?;
+        let signers = &self.vault_signer.as_ref().ok_or("Vault signing key is not configured")?.signers;
+        Ok((SignedSummary::sign(summary, signers)?, proofs))
+    }
+
+    // Pauses every vault behind a check that diverges by more than
+    // `tolerance_bps`, telling operators why; returns the vaults paused now
+    pub fn pause_mismatched(&mut self, checks: &[Check], tolerance_bps: u16) -> Result<Vec<RiskLevel>, VaultError> {
+        let mut paused = Vec::new();
+        for check in checks.iter().filter(|check| check.is_material(tolerance_bps)) {
+            for &risk in &check.vaults {
+                let vault = self.vaults.get_mut(&risk).ok_or(VaultError::VaultNotFound(risk))?;
+                if vault.circuit.paused {
+                    continue;
+                }
+                let reason = format!("reconciliation: {} is {} on-chain against {} in the books",
+                    check.label, Stroops(check.on_chain).to_xlm_string(), Stroops(check.local).to_xlm_string());
+                vault.circuit.pause(&reason, unix_now());
+                self.notify(Notification::CircuitBreakerTripped { risk, reason });
+                paused.push(risk);
+            }
+        }
+        if !paused.is_empty() {
+            self.persist()?;
+        }
+        Ok(paused)
+    }
+
+    // Uploads new code and has the configured contract switch to it; shares
+    // and vault state carry over
+    pub async fn upgrade_contract(&self, wasm: &[u8]) -> Result<TransactionReceipt, VaultError> {
+        let (rpc, contract) = self.vault_contract_call()?;
+        let signer = self.vault_signer.as_ref()
+            .ok_or("Vault signing key is not configured; the vault contract cannot be upgraded")?;
+        let wasm_hash = soroban::wasm_hash(wasm);
+        if let Some(soroban::Executable::Wasm(current)) = rpc.contract_executable(contract).await? {
+            if current == soroban::hex(&wasm_hash) {
+                return Err(VaultError::Validation(format!("{} already runs this code", contract)));
+            }
+        }
+
+        say!("   📦 Uploading {} bytes of contract code ({})", wasm.len(), soroban::hex(&wasm_hash));
+        signer.invoke_contract(rpc, soroban::upload_wasm(wasm)?).await?;
+        Ok(signer.invoke_contract(rpc, vault_contract::upgrade(contract, wasm_hash)?).await?.0)
+    }
+
+    // Pays accumulated yield out of the vault account as claimable balances,
+    // which users take with claim-yield whenever they like. Positions beyond
+    // one transaction's worth are left for the next run.
+    pub async fn distribute_yield(&mut self, risk: RiskLevel) -> Result<Option<(TransactionReceipt, Vec<YieldPayout>)>, VaultError> {
+        let due: Vec<YieldPayout> = self.user_positions.iter()
+            .filter(|((_, position_risk), position)| *position_risk == risk && position.accumulated_yield > 0)
+            .map(|((user, _), position)| YieldPayout { user: user.clone(), amount_stroops: position.accumulated_yield })
+            .collect();
+        // Refused accounts keep their yield until the lists allow paying it
+        let mut payouts = Vec::new();
+        for payout in due {
+            if payouts.len() == transaction::MAX_OPERATIONS {
+                break;
+            }
+            if self.screen(Screened::Payout, &payout.user, risk, payout.amount_stroops).is_ok() {
+                payouts.push(payout);
+            }
+        }
+        if payouts.is_empty() {
+            return Ok(None);
+        }
+        let signer = self.vault_signer.as_ref()
+            .ok_or("Vault signing key is not configured; yield cannot be distributed")?;
+        let asset = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?.xdr_asset()?;
+        let receipt = match signer.create_claimable_balances(asset, &payouts).await {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                if let Some(SubmitError::Unconfirmed { hash, .. }) = e.submit() {
+                    return Err(format!("Yield distribution {} is unconfirmed; check it before distributing again: {}", hash, e).into());
+                }
+                return Err(format!("Yield distribution failed: {}", e).into());
+            }
+        };
+
+        for payout in &payouts {
+            if let Some(position) = self.user_positions.get_mut(&(payout.user.clone(), risk)) {
+                position.accumulated_yield -= payout.amount_stroops;
+            }
+        }
+        self.log_event(VaultEvent::YieldDistributed {
+            risk,
+            payouts: payouts.clone(),
+            tx_hash: receipt.hash.clone(),
+        });
+        if let Err(e) = self.persist() {
+            say!("   ⚠️  Could not save vault state to {}: {}", self.storage.describe(), e);
+        }
+        Ok(Some((receipt, payouts)))
+    }
+
+    // Pays one position's accumulated yield straight to `payee`, as a payment
+    // rather than a claimable balance
+    pub async fn pay_yield_to(&mut self, user: &str, risk: RiskLevel, payee: &str) -> Result<(TransactionReceipt, u64), VaultError> {
+        self.ensure_operational(risk)?;
+        self.ensure_network()?;
+        let amount_stroops = self.user_positions.get(&(user.to_string(), risk)).map_or(0, |p| p.accumulated_yield);
+        if amount_stroops == 0 {
+            return Err(VaultError::Validation(format!("No yield has accumulated in the {:?} Risk Vault since the last payout", risk)));
+        }
+        const NO_SIGNER: &str = "Vault signing key is not configured; yield cannot be paid out";
+        if self.vault_signer.is_none() {
+            return Err(NO_SIGNER.into());
+        }
+        let asset = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?.xdr_asset()?;
+        let id = self.begin_payout(user, risk, OperationKind::YieldPayout, payee, amount_stroops).await?;
+        let memo = transaction::text_memo(&journal::payout_memo(id))?;
+        let signer = self.vault_signer.as_ref().ok_or(NO_SIGNER)?;
+        let sent = signer.send_asset(payee, asset, Stroops(amount_stroops), memo).await;
+        let receipt = self.payout_sent(id, sent).map_err(|e| format!("Yield payment failed: {}", e))?;
+        self.book_payout(id)?;
+        Ok((receipt, amount_stroops))
+    }
+
+    // Liquid insurance pool plus the current value of its invested stake
+    pub fn insurance_assets(&self) -> u64 {
+        let invested = self.vaults.get(&INSURANCE_VAULT)
+            .map(|v| self.insurance_investment.value(v))
+            .unwrap_or(0);
+        self.insurance_pool + invested
+    }
+
+    // XLM vaults only, since that's all the insurance pool covers
+    pub fn total_tvl(&self) -> u64 {
+        self.asset_totals().get("XLM").copied().unwrap_or(0)
+    }
+
+    pub fn coverage_ratio_bps(&self) -> Option<u64> {
+        insurance::coverage_ratio_bps(self.insurance_assets(), self.total_tvl())
+    }
+
+    // Steps every vault's insurance fee up when coverage is below the policy
+    // floor and down when it is above the ceiling, within each vault's bounds
+    pub fn adjust_premiums(&mut self) -> Result<CoverageReport, VaultError> {
+        let coverage_bps = self.coverage_ratio_bps();
+        let action = self.coverage_policy.action_for(coverage_bps);
+
+        let mut changes = Vec::new();
+        for risk in RiskLevel::ALL {
+            let vault = match self.vaults.get_mut(&risk) {
+                Some(v) => v,
+                None => continue,
+            };
+            let bounds = match vault.premium_bounds {
+                Some(b) => b,
+                None => continue,
+            };
+
+            let new_fee = self.coverage_policy.adjusted_fee(vault.insurance_fee, bounds, action);
+            if new_fee != vault.insurance_fee {
+                changes.push((risk, vault.insurance_fee, new_fee));
+                vault.insurance_fee = new_fee;
+            }
+        }
+
+        if !changes.is_empty() {
+            self.persist()?;
+        }
+
+        Ok(CoverageReport {
+            coverage_bps,
+            action,
+            changes,
+        })
+    }
+
+    pub fn set_insurance_investment_target(&mut self, target_bps: u16) -> Result<(), VaultError> {
+        if target_bps > 10_000 {
+            return Err(VaultError::Validation("Investment target cannot exceed 100%".into()));
+        }
+        self.insurance_investment.target_bps = target_bps;
+        self.persist()
+    }
+
+    // Moves insurance capital in or out of INSURANCE_VAULT to meet the target.
+    // Both sides already live in the vault account, so no payment is needed.
+    pub fn rebalance_insurance_investment(&mut self) -> Result<i128, VaultError> {
+        self.accrue_fees(INSURANCE_VAULT)?;
+        let vault = self.vaults.get_mut(&INSURANCE_VAULT).ok_or(VaultError::VaultNotFound(INSURANCE_VAULT))?;
+        let delta = self.insurance_investment.rebalance_delta(self.insurance_pool, vault);
+
+        if delta > 0 {
+            let amount = (delta as u64).min(self.insurance_pool);
+            let shares = vault.mint(amount)?;
+            self.insurance_pool -= amount;
+            self.insurance_investment.shares += shares;
+            self.insurance_investment.principal += amount;
+            self.log_event(VaultEvent::InsuranceInvested { amount_stroops: amount, shares });
+        } else if delta < 0 {
+            self.redeem_insurance(delta.unsigned_abs() as u64)?;
+        }
+
+        self.persist()?;
+        Ok(delta)
+    }
+
+    // Burns enough of the pool's invested shares to free amount_stroops of liquidity
+    pub fn redeem_insurance(&mut self, amount_stroops: u64) -> Result<(), VaultError> {
+        let vault = self.vaults.get_mut(&INSURANCE_VAULT).ok_or(VaultError::VaultNotFound(INSURANCE_VAULT))?;
+        let shares = vault.pool()
+            .shares_for(Stroops(amount_stroops), Rounding::Up)?.0
+            .min(self.insurance_investment.shares);
+        if shares == 0 {
+            return Ok(());
+        }
+
+        let principal_removed = (self.insurance_investment.principal as u128 * shares as u128
+            / self.insurance_investment.shares as u128) as u64;
+        let released = vault.burn(shares)?;
+        self.insurance_investment.shares -= shares;
+        self.insurance_investment.principal -= principal_removed;
+        self.insurance_pool += released;
+        self.log_event(VaultEvent::InsuranceRedeemed {
+            shares,
+            amount_stroops: released,
+            principal_removed,
+        });
+        Ok(())
+    }
+
+    pub fn get_vault_info(&self, risk: RiskLevel) -> Option<&Vault> {
+        self.vaults.get(&risk)
+    }
+
+    // Sets a strategy's APY in every vault running it, returning the vaults
+    // whose rate changed with their previous one
+    pub fn set_strategy_apy(&mut self, id: &str, apy_bps: u16) -> Vec<(RiskLevel, u16)> {
+        let mut changed = Vec::new();
+        for risk in RiskLevel::ALL {
+            let Some(vault) = self.vaults.get_mut(&risk) else {
+                continue;
+            };
+            for strategy in vault.strategies.iter_mut().filter(|s| s.strategy == id && s.current_apy != apy_bps) {
+                changed.push((risk, strategy.current_apy));
+                strategy.current_apy = apy_bps;
+            }
+        }
+        changed
+    }
+
+    // Every vault's TVL in USD, with the total of those that could be priced
+    pub fn tvl_usd(&self) -> (u64, Vec<(RiskLevel, Option<u64>)>) {
+        let now = unix_now();
+        let vaults: Vec<(RiskLevel, Option<u64>)> = RiskLevel::ALL.into_iter()
+            .filter_map(|risk| self.vaults.get(&risk).map(|vault| (risk, self.prices.usd_value(vault.asset.as_ref(), vault.total_value, now))))
+            .collect();
+        (vaults.iter().filter_map(|(_, usd)| *usd).sum(), vaults)
+    }
+
+    // Gives the running vaults the settings of checked definitions, keeping
+    // their balances, and logs each vault's changes. New risk levels start
+    // empty; ones the definitions leave out keep running as they are.
+    pub fn reconfigure(&mut self, definitions: &HashMap<RiskLevel, Vault>) -> Vec<(RiskLevel, Vec<String>)> {
+        let mut applied = Vec::new();
+        for risk in RiskLevel::ALL {
+            let Some(definition) = definitions.get(&risk) else {
+                continue;
+            };
+            let changes = match self.vaults.get_mut(&risk) {
+                Some(vault) => {
+                    let before = vault.clone();
+                    vault.redefine(definition);
+                    reload::describe_changes(&before, vault)
+                }
+                None => {
+                    self.vaults.insert(risk, definition.clone());
+                    vec!["vault created".to_string()]
+                }
+            };
+            if !changes.is_empty() {
+                self.log_event(VaultEvent::Reconfigured { risk, changes: changes.clone(), definition: Box::new(definition.clone()) });
+                applied.push((risk, changes));
+            }
+        }
+        applied
+    }
+
+    // Applies a vault definitions file as reconfigure does, except that with
+    // an admin delay set, raised fees or lockups and changed strategies are
+    // proposed like any other admin change: the vault keeps its current ones
+    // until the queued change comes due. A change already pending isn't
+    // queued twice.
+    pub fn apply_definitions(&mut self, definitions: &HashMap<RiskLevel, Vault>) -> (Vec<(RiskLevel, Vec<String>)>, Vec<QueuedChange>) {
+        if self.admin_delay_secs == 0 {
+            return (self.reconfigure(definitions), Vec::new());
+        }
+        let targets = |vault: &Vault| -> Vec<(String, u8)> {
+            vault.strategies.iter().map(|s| (s.strategy.clone(), s.allocation_percentage)).collect()
+        };
+        let mut adjusted = definitions.clone();
+        let mut delayed = Vec::new();
+        for (risk, definition) in adjusted.iter_mut() {
+            let Some(vault) = self.vaults.get(risk) else {
+                continue;
+            };
+            let fees = &definition.fees;
+            if fees.management_fee_bps > vault.fees.management_fee_bps || fees.performance_fee_bps > vault.fees.performance_fee_bps {
+                delayed.push(AdminChange::Fees {
+                    risk: *risk,
+                    management_fee_bps: fees.management_fee_bps,
+                    performance_fee_bps: fees.performance_fee_bps,
+                });
+                definition.fees = vault.fees.clone();
+            }
+            if definition.lockup_secs > vault.lockup_secs || definition.early_withdrawal_penalty > vault.early_withdrawal_penalty {
+                delayed.push(AdminChange::Lockup {
+                    risk: *risk,
+                    lockup_secs: definition.lockup_secs,
+                    early_withdrawal_penalty: definition.early_withdrawal_penalty,
+                });
+                definition.lockup_secs = vault.lockup_secs;
+                definition.early_withdrawal_penalty = vault.early_withdrawal_penalty;
+            }
+            let mut redefined = vault.clone();
+            redefined.redefine(definition);
+            if targets(&redefined) != targets(vault) {
+                delayed.push(AdminChange::Strategies {
+                    risk: *risk,
+                    targets: definition.strategies.iter()
+                        .map(|s| StrategyTarget { id: s.strategy.clone(), allocation: s.allocation_percentage, apy_bps: s.current_apy })
+                        .collect(),
+                });
+                definition.strategies = vault.strategies.clone();
+            }
+        }
+
+        let applied = self.reconfigure(&adjusted);
+        let mut queued = Vec::new();
+        for change in delayed {
+            if self.timelock.pending().any(|pending| pending.change == change) {
+                continue;
+            }
+            let description = change.describe();
+            match self.propose(change) {
+                Ok(Proposal::Queued(change)) => queued.push(change),
+                Ok(Proposal::Applied(_)) => {}
+                Err(e) => say!("⚠️  Not queueing {}: {}", description, e),
+            }
+        }
+        (applied, queued)
+    }
+
+    // New fee rates for one vault, within the limits vault definitions are
+    // held to. Management fees owed so far accrue at the old rate first.
+    pub fn set_fees(&mut self, risk: RiskLevel, management_fee_bps: u16, performance_fee_bps: u16) -> Result<Vec<String>, VaultError> {
+        check_fee_limits(management_fee_bps, performance_fee_bps)?;
+        self.accrue_fees(risk)?;
+        let mut definition = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?.clone();
+        definition.fees.management_fee_bps = management_fee_bps;
+        definition.fees.performance_fee_bps = performance_fee_bps;
+        let changes = self.reconfigure(&HashMap::from([(risk, definition)]))
+            .pop()
+            .map(|(_, changes)| changes)
+            .unwrap_or_default();
+        self.persist()?;
+        Ok(changes)
+    }
+
+    // A new lockup and early withdrawal penalty for one vault. Both count
+    // from each deposit as it stands, so a raise reaches existing positions.
+    pub fn set_lockup(&mut self, risk: RiskLevel, lockup_secs: u64, early_withdrawal_penalty: u16) -> Result<Vec<String>, VaultError> {
+        check_penalty_limit(early_withdrawal_penalty)?;
+        let mut definition = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?.clone();
+        definition.lockup_secs = lockup_secs;
+        definition.early_withdrawal_penalty = early_withdrawal_penalty;
+        let changes = self.reconfigure(&HashMap::from([(risk, definition)]))
+            .pop()
+            .map(|(_, changes)| changes)
+            .unwrap_or_default();
+        self.persist()?;
+        Ok(changes)
+    }
+
+    // New deposit caps for one vault, None lifting a cap, held to the same
+    // rules as vault definitions. Deposits already above a lowered cap stay.
+    pub fn set_caps(&mut self, risk: RiskLevel, caps: Caps) -> Result<Vec<String>, VaultError> {
+        let mut definition = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?.clone();
+        for cap in [caps.per_user_cap, caps.max_tvl].into_iter().flatten() {
+            if cap < definition.min_deposit {
+                return Err(VaultError::Validation(format!("Caps must allow at least the {} minimum deposit",
+                    Stroops(definition.min_deposit).to_xlm_string())));
+            }
+        }
+        for (per_user, max_tvl) in [(caps.per_user_cap, caps.max_tvl), (caps.per_user_cap_usd, caps.max_tvl_usd)] {
+            if let (Some(per_user), Some(max_tvl)) = (per_user, max_tvl) {
+                if per_user > max_tvl {
+                    return Err(VaultError::Validation("The per-user cap is above the vault cap".into()));
+                }
+            }
+        }
+        if caps.per_user_cap_usd == Some(0) || caps.max_tvl_usd == Some(0) {
+            return Err(VaultError::Validation("USD caps must be above zero".into()));
+        }
+        definition.max_tvl = caps.max_tvl;
+        definition.per_user_cap = caps.per_user_cap;
+        definition.max_tvl_usd = caps.max_tvl_usd;
+        definition.per_user_cap_usd = caps.per_user_cap_usd;
+        let changes = self.reconfigure(&HashMap::from([(risk, definition)]))
+            .pop()
+            .map(|(_, changes)| changes)
+            .unwrap_or_default();
+        self.persist()?;
+        Ok(changes)
+    }
+
+    // New strategy targets for one vault; funds in a dropped strategy stay
+    // until a rebalance moves them out
+    pub fn set_strategies(&mut self, risk: RiskLevel, targets: &[StrategyTarget]) -> Result<Vec<String>, VaultError> {
+        let mut definition = self.vaults.get(&risk).ok_or(VaultError::VaultNotFound(risk))?.clone();
+        definition.strategies = strategy_allocations(targets);
+        check_strategies(&definition.strategies)
+            .map_err(|reason| VaultError::Validation(format!("{:?} Risk Vault: {}", risk, reason)))?;
+        let changes = self.reconfigure(&HashMap::from([(risk, definition)]))
+            .pop()
+            .map(|(_, changes)| changes)
+            .unwrap_or_default();
+        self.persist()?;
+        Ok(changes)
+    }
+
+    // Applies an admin change now, or queues it for admin_delay_secs if it
+    // could cost depositors: raising a fee or the lockup, changing strategies
+    // or moving the treasury. It is checked either way, so a queued change is one
+    // that could be applied today.
+    pub fn propose(&mut self, change: AdminChange) -> Result<Proposal, VaultError> {
+        let delayed = match &change {
+            AdminChange::Fees { risk, management_fee_bps, performance_fee_bps } => {
+                check_fee_limits(*management_fee_bps, *performance_fee_bps)?;
+                let fees = &self.vaults.get(risk).ok_or(VaultError::VaultNotFound(*risk))?.fees;
+                *management_fee_bps > fees.management_fee_bps || *performance_fee_bps > fees.performance_fee_bps
+            }
+            AdminChange::Strategies { risk, targets } => {
+                if !self.vaults.contains_key(risk) {
+                    return Err(VaultError::VaultNotFound(*risk));
+                }
+                check_strategies(&strategy_allocations(targets))
+                    .map_err(|reason| VaultError::Validation(format!("{:?} Risk Vault: {}", risk, reason)))?;
+                true
+            }
+            AdminChange::Lockup { risk, lockup_secs, early_withdrawal_penalty } => {
+                check_penalty_limit(*early_withdrawal_penalty)?;
+                let vault = self.vaults.get(risk).ok_or(VaultError::VaultNotFound(*risk))?;
+                *lockup_secs > vault.lockup_secs || *early_withdrawal_penalty > vault.early_withdrawal_penalty
+            }
+            AdminChange::Treasury { address } => {
+                if !muxed::is_valid(address) {
+                    return Err(VaultError::Validation("Invalid treasury address format (must be a G... or M... address)".into()));
+                }
+                true
+            }
+            AdminChange::AdminDelay { delay_secs } => *delay_secs < self.admin_delay_secs,
+        };
+        if !delayed || self.admin_delay_secs == 0 {
+            return self.apply_change(&change).map(Proposal::Applied);
+        }
+        let queued = self.timelock.queue(change, unix_now(), self.admin_delay_secs).clone();
+        self.persist()?;
+        self.notify(Notification::AdminChangeQueued {
+            change_id: queued.id,
+            change: queued.change.describe(),
+            takes_effect_at: queued.takes_effect_at,
+        });
+        Ok(Proposal::Queued(queued))
+    }
+
+    fn apply_change(&mut self, change: &AdminChange) -> Result<Vec<String>, VaultError> {
+        match change {
+            AdminChange::Fees { risk, management_fee_bps, performance_fee_bps } => {
+                self.set_fees(*risk, *management_fee_bps, *performance_fee_bps)
+            }
+            AdminChange::Strategies { risk, targets } => self.set_strategies(*risk, targets),
+            AdminChange::Lockup { risk, lockup_secs, early_withdrawal_penalty } => {
+                self.set_lockup(*risk, *lockup_secs, *early_withdrawal_penalty)
+            }
+            AdminChange::Treasury { address } => {
+                let previous = self.treasury_address.clone();
+                self.set_treasury_address(address)?;
+                self.treasury_memo = None;
+                self.persist()?;
+                Ok(vec![format!("treasury: {} → {}", previous.as_deref().unwrap_or("none"), address)])
+            }
+            AdminChange::AdminDelay { delay_secs } => {
+                let previous = std::mem::replace(&mut self.admin_delay_secs, *delay_secs);
+                self.persist()?;
+                Ok(vec![format!("admin delay: {} → {}", format_duration(previous), format_duration(*delay_secs))])
+            }
+        }
+    }
+
+    // Applies the queued admin changes whose delay is out, oldest first
+    pub fn apply_due_changes(&mut self) -> Vec<(QueuedChange, Result<Vec<String>, VaultError>)> {
+        let mut applied = Vec::new();
+        for queued in self.timelock.due(unix_now()) {
+            let result = self.apply_change(&queued.change);
+            self.timelock.settle(queued.id, result.as_ref().map(|_| ()).map_err(|e| e.to_string()), unix_now());
+            if let Err(e) = self.persist() {
+                say!("⚠️  Could not save admin change #{}: {}", queued.id, e);
+            }
+            applied.push((queued, result));
+        }
+        applied
+    }
+
+    // Takes the admin delay the configuration asks for. A longer one applies
+    // at once. A shorter one is itself an admin change, queued for the delay
+    // in force, and the vault won't start on it until that change is due.
+    pub fn configure_admin_delay(&mut self, delay_secs: u64) -> Result<(), VaultError> {
+        if delay_secs >= self.admin_delay_secs {
+            self.admin_delay_secs = delay_secs;
+            return Ok(());
+        }
+        let change = AdminChange::AdminDelay { delay_secs };
+        let pending = self.timelock.pending().find(|queued| queued.change == change).cloned();
+        let queued = match pending {
+            Some(queued) => queued,
+            None => match self.propose(change)? {
+                Proposal::Queued(queued) => queued,
+                Proposal::Applied(_) => return Ok(()),
+            },
+        };
+        let now = unix_now();
+        if queued.takes_effect_at > now {
+            return Err(VaultError::Validation(format!(
+                "The configured admin delay of {} is shorter than the {} in force. Admin change #{} lowers it in {}; keep the delay at {} until then.",
+                format_duration(delay_secs), format_duration(self.admin_delay_secs), queued.id,
+                format_duration(queued.takes_effect_at - now), format_duration(self.admin_delay_secs))));
+        }
+        let result = self.apply_change(&queued.change);
+        self.timelock.settle(queued.id, result.as_ref().map(|_| ()).map_err(|e| e.to_string()), now);
+        self.persist()?;
+        result.map(|_| ())
+    }
+
+    pub fn cancel_change(&mut self, id: u64) -> Result<QueuedChange, VaultError> {
+        let cancelled = self.timelock.cancel(id, unix_now())?;
+        self.persist()?;
+        Ok(cancelled)
+    }
+}
+
+fn check_fee_limits(management_fee_bps: u16, performance_fee_bps: u16) -> Result<(), VaultError> {
+    let limits = [
+        ("management fee", management_fee_bps, MAX_MANAGEMENT_FEE_BPS),
+        ("performance fee", performance_fee_bps, MAX_PERFORMANCE_FEE_BPS),
+    ];
+    for (label, bps, max) in limits {
+        if bps > max {
+            return Err(VaultError::Validation(format!("{} of {} bps is above the {} bps limit", label, bps, max)));
+        }
+    }
+    Ok(())
+}
+
+fn check_penalty_limit(early_withdrawal_penalty: u16) -> Result<(), VaultError> {
+    if early_withdrawal_penalty > MAX_EARLY_WITHDRAWAL_PENALTY_BPS {
+        return Err(VaultError::Validation(format!("early withdrawal penalty of {} bps is above the {} bps limit",
+            early_withdrawal_penalty, MAX_EARLY_WITHDRAWAL_PENALTY_BPS)));
+    }
+    Ok(())
+}
+
+fn strategy_allocations(targets: &[StrategyTarget]) -> Vec<StrategyAllocation> {
+    targets.iter()
+        .map(|target| StrategyAllocation {
+            strategy: target.id.clone(),
+            allocation_percentage: target.allocation,
+            current_apy: target.apy_bps,
+            total_allocated: 0,
+            current_yield: 0,
+        })
+        .collect()
+}
+
+// What became of an admin change
+#[derive(Debug, Clone)]
+pub enum Proposal {
+    // Taken effect, with the settings it changed
+    Applied(Vec<String>),
+    // Waiting out the admin delay
+    Queued(QueuedChange),
+}
+
+// A vault's deposit caps, in stroops of its asset or USD (7 decimals)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Caps {
+    pub max_tvl: Option<u64>,
+    pub per_user_cap: Option<u64>,
+    pub max_tvl_usd: Option<u64>,
+    pub per_user_cap_usd: Option<u64>,
+}
+
+impl Caps {
+    pub fn of(vault: &Vault) -> Self {
+        Caps {
+            max_tvl: vault.max_tvl,
+            per_user_cap: vault.per_user_cap,
+            max_tvl_usd: vault.max_tvl_usd,
+            per_user_cap_usd: vault.per_user_cap_usd,
+        }
+    }
+}
+
+pub fn print_premium_changes(report: &CoverageReport) {
+    for (risk, old_fee, new_fee) in &report.changes {
+        say!("   🛡️  {:?} Risk Vault insurance fee {:.2}% → {:.2}% (coverage {})",
+            risk,
+            *old_fee as f64 / 100.0,
+            *new_fee as f64 / 100.0,
+            format_coverage(report.coverage_bps));
+    }
+}
+
+pub fn format_coverage(coverage_bps: Option<u64>) -> String {
+    match coverage_bps {
+        Some(bps) => format!("{:.2}%", bps as f64 / 100.0),
+        None => "n/a".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::VaultBuilder;
+    use crate::mock_ledger::{MockLedger, FAUCET_STROOPS};
+
+    const XLM: u64 = 10_000_000;
+
+    // A vault with one definition, lending all of it out, on the offline mock
+    // ledger, and a funded user. Each test takes its own seed, since the mock
+    // ledger is shared.
+    fn offline_vault(seed: u8, definition: VaultBuilder) -> (StellarVault, String) {
+        let ledger = MockLedger::shared();
+        let vault_key = Keypair::from_seed(&[seed; 32]);
+        let user_key = Keypair::from_seed(&[seed + 100; 32]);
+        ledger.fund(&vault_key.public_key(), Stroops(FAUCET_STROOPS));
+        ledger.fund(&user_key.public_key(), Stroops(FAUCET_STROOPS));
+        let mut vault = StellarVaultBuilder::new(&vault_key.public_key(), &Network::Offline)
+            .vault(definition.strategy(strategy::YIELDBLOX_LENDING, 100, 350))
+            .build()
+            .unwrap();
+        vault.add_vault_signer(&vault_key.secret_key()).unwrap();
+        vault.register_user(&user_key.secret_key(), &user_key.public_key()).unwrap();
+        (vault, user_key.public_key())
+    }
+
+    #[tokio::test]
+    async fn deposits_past_the_cap_are_refused_before_paying() {
+        let (mut vault, user) = offline_vault(40, VaultBuilder::new(RiskLevel::Low).per_user_cap(100 * XLM));
+        vault.deposit(&user, RiskLevel::Low, 60 * XLM, None).await.unwrap();
+        let paid = MockLedger::shared().account(&user).unwrap();
+
+        let refused = vault.deposit(&user, RiskLevel::Low, 50 * XLM, None).await;
+        assert!(matches!(refused, Err(VaultError::DepositLimit(DepositError::UserCapExceeded { cap, .. })) if cap == 100 * XLM),
+            "{:?}", refused.map(|(shares, ..)| shares));
+        // Nothing left the user's account for the refused one
+        assert_eq!(MockLedger::shared().account(&user).unwrap().sequence, paid.sequence);
+        assert_eq!(vault.user_positions[&(user, RiskLevel::Low)].cost_basis, 60 * XLM);
+    }
+
+    #[tokio::test]
+    async fn withdrawals_inside_the_lockup_pay_the_penalty_to_insurance() {
+        let definition = VaultBuilder::new(RiskLevel::Low).lockup(86_400, 300).liquidity_buffer(10_000);
+        let (mut vault, user) = offline_vault(41, definition);
+        let (shares, ..) = vault.deposit(&user, RiskLevel::Low, 100 * XLM, None).await.unwrap();
+        let insured = vault.insurance_pool;
+
+        let Ok(WithdrawalOutcome::Completed(early)) = vault.withdraw(&user, RiskLevel::Low, shares / 2).await else {
+            panic!("the withdrawal should be paid from the liquid reserve");
+        };
+        assert!(early.gross_stroops > 0);
+        assert_eq!(early.penalty_stroops, early.gross_stroops * 300 / 10_000);
+        assert_eq!(early.net_stroops, early.gross_stroops - early.penalty_stroops);
+        assert_eq!(vault.insurance_pool, insured + early.penalty_stroops);
+
+        // Once the lockup is over, nothing is held back
+        vault.user_positions.get_mut(&(user.clone(), RiskLevel::Low)).unwrap().locked[0].deposited_at -= 86_400;
+        let Ok(WithdrawalOutcome::Completed(late)) = vault.withdraw(&user, RiskLevel::Low, shares / 4).await else {
+            panic!("the withdrawal should be paid from the liquid reserve");
+        };
+        assert_eq!(late.penalty_stroops, 0);
+        assert_eq!(late.net_stroops, late.gross_stroops);
+    }
+
+    #[tokio::test]
+    async fn withdrawals_past_the_reserve_queue_and_are_paid_in_order() {
+        let (mut vault, user) = offline_vault(42, VaultBuilder::new(RiskLevel::Low).liquidity_buffer(1_000));
+        let (shares, ..) = vault.deposit(&user, RiskLevel::Low, 100 * XLM, None).await.unwrap();
+
+        // Half the vault is more than its 10% reserve, and anything after
+        // it waits its turn however small
+        let Ok(WithdrawalOutcome::Queued { id: first, ahead: 0 }) = vault.withdraw(&user, RiskLevel::Low, shares / 2).await else {
+            panic!("the withdrawal should be queued");
+        };
+        let Ok(WithdrawalOutcome::Queued { id: second, ahead: 1 }) = vault.withdraw(&user, RiskLevel::Low, shares / 100).await else {
+            panic!("the withdrawal should be queued behind the first");
+        };
+        assert!(vault.withdraw(&user, RiskLevel::Low, shares).await.is_err());
+
+        let results = vault.process_withdrawal_queue().await;
+        assert_eq!(results.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![first, second]);
+        assert!(results.iter().all(|(_, result)| result.is_ok()), "{:?}", results);
+        assert!(!vault.withdrawal_queue.has_pending(RiskLevel::Low));
+        assert_eq!(vault.user_positions[&(user, RiskLevel::Low)].shares, shares - shares / 2 - shares / 100);
+    }
+
+    #[tokio::test]
+    async fn paused_vaults_refuse_deposits_and_withdrawals_until_resumed() {
+        let (mut vault, user) = offline_vault(43, VaultBuilder::new(RiskLevel::Low).liquidity_buffer(10_000));
+        let (shares, ..) = vault.deposit(&user, RiskLevel::Low, 100 * XLM, None).await.unwrap();
+
+        vault.pause_vault(RiskLevel::Low, "oracle outage").unwrap();
+        let paused = |result: Result<(), VaultError>| matches!(result, Err(VaultError::Validation(reason)) if reason.contains("oracle outage"));
+        assert!(paused(vault.deposit(&user, RiskLevel::Low, 10 * XLM, None).await.map(|_| ())));
+        assert!(paused(vault.withdraw(&user, RiskLevel::Low, shares / 2).await.map(|_| ())));
+        assert_eq!(vault.user_positions[&(user.clone(), RiskLevel::Low)].shares, shares);
+
+        vault.resume_vault(RiskLevel::Low).unwrap();
+        vault.deposit(&user, RiskLevel::Low, 10 * XLM, None).await.unwrap();
+        assert!(matches!(vault.withdraw(&user, RiskLevel::Low, shares / 2).await, Ok(WithdrawalOutcome::Completed(_))));
+    }
+
+    #[test]
+    fn edited_definitions_wait_out_the_admin_delay_for_fee_rises_and_strategies() {
+        let (mut vault, _) = offline_vault(44, VaultBuilder::new(RiskLevel::Low).fees(50, 1_000));
+        let definitions = |builder: VaultBuilder| crate::builder::build_vaults(vec![builder]).unwrap();
+        let strategies = |vault: &StellarVault| -> Vec<(String, u8)> {
+            vault.vaults[&RiskLevel::Low].strategies.iter().map(|s| (s.strategy.clone(), s.allocation_percentage)).collect()
+        };
+
+        // A fee cut applies at once
+        let cut = definitions(VaultBuilder::new(RiskLevel::Low).fees(25, 1_000).strategy(strategy::YIELDBLOX_LENDING, 100, 350));
+        let (applied, queued) = vault.apply_definitions(&cut);
+        assert_eq!((applied.len(), queued.len()), (1, 0));
+        assert_eq!(vault.vaults[&RiskLevel::Low].fees.management_fee_bps, 25);
+
+        // A fee rise and new strategies are queued, and not twice
+        let raised = definitions(VaultBuilder::new(RiskLevel::Low).fees(100, 1_000)
+            .strategy(strategy::YIELDBLOX_LENDING, 60, 350)
+            .strategy(strategy::MONEY_MARKET, 40, 900));
+        let (applied, queued) = vault.apply_definitions(&raised);
+        assert!(applied.is_empty(), "{:?}", applied);
+        assert!(matches!(queued[..], [
+            QueuedChange { change: AdminChange::Fees { management_fee_bps: 100, .. }, .. },
+            QueuedChange { change: AdminChange::Strategies { .. }, .. },
+        ]), "{:?}", queued);
+        assert_eq!(vault.vaults[&RiskLevel::Low].fees.management_fee_bps, 25);
+        assert_eq!(strategies(&vault), vec![(strategy::YIELDBLOX_LENDING.to_string(), 100)]);
+        assert!(vault.apply_definitions(&raised).1.is_empty());
+        assert_eq!(vault.timelock.pending().count(), 2);
+
+        // Without a delay they apply like any other setting
+        vault.admin_delay_secs = 0;
+        let (applied, queued) = vault.apply_definitions(&raised);
+        assert_eq!((applied.len(), queued.len()), (1, 0));
+        assert_eq!(vault.vaults[&RiskLevel::Low].fees.management_fee_bps, 100);
+        assert_eq!(strategies(&vault).len(), 2);
+    }
+
+    #[test]
+    fn edited_definitions_wait_out_the_admin_delay_for_lockup_rises() {
+        let (mut vault, _) = offline_vault(45, VaultBuilder::new(RiskLevel::Low).lockup(86_400, 300));
+        let definitions = |lockup_secs, penalty_bps| crate::builder::build_vaults(vec![VaultBuilder::new(RiskLevel::Low)
+            .lockup(lockup_secs, penalty_bps)
+            .strategy(strategy::YIELDBLOX_LENDING, 100, 350)]).unwrap();
+        let lockup = |vault: &StellarVault| (vault.vaults[&RiskLevel::Low].lockup_secs, vault.vaults[&RiskLevel::Low].early_withdrawal_penalty);
+
+        // A higher penalty is queued and the current one kept meanwhile
+        let (applied, queued) = vault.apply_definitions(&definitions(86_400, 1_000));
+        assert!(applied.is_empty(), "{:?}", applied);
+        assert!(matches!(queued[..], [
+            QueuedChange { change: AdminChange::Lockup { lockup_secs: 86_400, early_withdrawal_penalty: 1_000, .. }, .. },
+        ]), "{:?}", queued);
+        assert_eq!(lockup(&vault), (86_400, 300));
+        assert!(vault.apply_definitions(&definitions(86_400, 1_000)).1.is_empty());
+
+        // A cut applies at once
+        let (applied, queued) = vault.apply_definitions(&definitions(3_600, 100));
+        assert_eq!((applied.len(), queued.len()), (1, 0));
+        assert_eq!(lockup(&vault), (3_600, 100));
+
+        // And the queued raise takes effect once it comes due
+        vault.timelock = Timelock::default();
+        vault.timelock.queue(AdminChange::Lockup { risk: RiskLevel::Low, lockup_secs: 86_400, early_withdrawal_penalty: 1_000 }, 0, 0);
+        assert!(vault.apply_due_changes().into_iter().all(|(_, result)| result.is_ok()));
+        assert_eq!(lockup(&vault), (86_400, 1_000));
+    }
+
+    #[test]
+    fn a_shorter_admin_delay_waits_out_the_saved_one() {
+        let (mut vault, _) = offline_vault(46, VaultBuilder::new(RiskLevel::Low));
+        assert_eq!(vault.admin_delay_secs, crate::timelock::DEFAULT_ADMIN_DELAY_SECS);
+
+        // The cut is queued once, and refused until it is due
+        assert!(matches!(vault.configure_admin_delay(0), Err(VaultError::Validation(_))));
+        assert!(matches!(vault.configure_admin_delay(0), Err(VaultError::Validation(_))));
+        let pending: Vec<AdminChange> = vault.timelock.pending().map(|queued| queued.change.clone()).collect();
+        assert_eq!(pending, vec![AdminChange::AdminDelay { delay_secs: 0 }]);
+        assert_eq!(vault.admin_delay_secs, crate::timelock::DEFAULT_ADMIN_DELAY_SECS);
+
+        vault.timelock = Timelock::default();
+        let id = vault.timelock.queue(AdminChange::AdminDelay { delay_secs: 0 }, 0, 0).id;
+        vault.configure_admin_delay(0).unwrap();
+        assert_eq!(vault.admin_delay_secs, 0);
+        assert_eq!(vault.timelock.get(id).unwrap().status, crate::timelock::ChangeStatus::Applied);
+
+        // A longer one applies at once, and is what gets saved
+        vault.configure_admin_delay(3_600).unwrap();
+        assert_eq!(vault.snapshot().admin_delay_secs, Some(3_600));
+    }
+
+    #[tokio::test]
+    async fn a_keyed_deposit_that_sent_nothing_runs_again_on_retry() {
+        let (mut vault, user) = offline_vault(47, VaultBuilder::new(RiskLevel::Low));
+        let request = "deposit 10.0000000 XLM to the Low Risk Vault";
+
+        // Refused before its deposit record is made, so the key is dropped
+        vault.pause_vault(RiskLevel::Low, "maintenance").unwrap();
+        assert_eq!(vault.start_keyed_deposit(&user, RiskLevel::Low, "order-1", request).unwrap(), KeyedDeposit::Run);
+        assert!(vault.deposit_keyed(&user, RiskLevel::Low, 10 * XLM, None, Some("order-1")).await.is_err());
+        vault.finish_keyed_deposit(&user, "order-1", request, None);
+        vault.resume_vault(RiskLevel::Low).unwrap();
+
+        // Another deposit takes the record id the first would have had
+        let interleaved = vault.pending_deposits.next_id();
+        vault.deposit(&user, RiskLevel::Low, 20 * XLM, None).await.unwrap();
+
+        // The retry runs, and is tied to its own record
+        assert_eq!(vault.start_keyed_deposit(&user, RiskLevel::Low, "order-1", request).unwrap(), KeyedDeposit::Run);
+        let (shares, ..) = vault.deposit_keyed(&user, RiskLevel::Low, 10 * XLM, None, Some("order-1")).await.unwrap();
+        let started = vault.idempotency.lookup(&user, "order-1", request, unix_now(), vault.idempotency_window_secs).unwrap().unwrap();
+        let Outcome::Started { deposit_id: Some(id) } = started.outcome else {
+            panic!("{:?}", started.outcome);
+        };
+        assert_ne!(id, interleaved);
+        assert_eq!(vault.pending_deposits.get(id).unwrap().amount_stroops, 10 * XLM);
+
+        let reply = serde_json::json!({ "shares": shares });
+        vault.finish_keyed_deposit(&user, "order-1", request, Some(&reply));
+        assert_eq!(vault.start_keyed_deposit(&user, RiskLevel::Low, "order-1", request).unwrap(), KeyedDeposit::Replay(reply));
+    }
+
+    #[tokio::test]
+    async fn deposit_funds_are_checked_to_the_stroop() {
+        let (vault, user) = offline_vault(48, VaultBuilder::new(RiskLevel::Low));
+        let fee = vault.submitter.fee_strategy.estimate(&vault.horizon).await;
+        let balance = vault.users.get(&user).unwrap().get_xlm_balance().await.unwrap();
+        let reserve = Stroops(2 * transaction::BASE_RESERVE);
+        let most = Stroops(balance.0 - reserve.0 - fee.per_operation as u64);
+
+        assert!(vault.check_deposit_funds(&user, RiskLevel::Low, None, most, &fee).await.is_ok());
+        let over = vault.check_deposit_funds(&user, RiskLevel::Low, None, Stroops(most.0 + 1), &fee).await;
+        assert!(matches!(over, Err(VaultError::InsufficientBalance { available, .. }) if available == balance), "{:?}", over);
+    }
+
+    #[tokio::test]
+    async fn a_top_up_locks_only_its_own_shares() {
+        let definition = VaultBuilder::new(RiskLevel::Low).lockup(86_400, 300).liquidity_buffer(10_000);
+        let (mut vault, user) = offline_vault(49, definition);
+        let (old, ..) = vault.deposit(&user, RiskLevel::Low, 100 * XLM, None).await.unwrap();
+        vault.user_positions.get_mut(&(user.clone(), RiskLevel::Low)).unwrap().locked[0].deposited_at -= 86_400;
+        let (topped_up, ..) = vault.deposit(&user, RiskLevel::Low, 50 * XLM, None).await.unwrap();
+        assert_eq!(vault.locked_part(&user, RiskLevel::Low, old + topped_up), topped_up);
+
+        // The shares that were already free stay free
+        let Ok(WithdrawalOutcome::Completed(free)) = vault.withdraw(&user, RiskLevel::Low, old).await else {
+            panic!("the withdrawal should be paid from the liquid reserve");
+        };
+        assert_eq!(free.penalty_stroops, 0);
+
+        // Only the top-up pays the penalty, until its own lockup is over
+        let Ok(WithdrawalOutcome::Completed(early)) = vault.withdraw(&user, RiskLevel::Low, topped_up / 2).await else {
+            panic!("the withdrawal should be paid from the liquid reserve");
+        };
+        assert_eq!(early.penalty_stroops, early.gross_stroops * 300 / 10_000);
+        assert!(vault.lock_remaining_secs(&user, RiskLevel::Low) > 0);
+    }
+}
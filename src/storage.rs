@@ -0,0 +1,284 @@
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::claims::ClaimBook;
+use crate::contract_index::ContractIndex;
+use crate::error::VaultError;
+use crate::events::EventRecord;
+use crate::insurance::InsuranceInvestment;
+use crate::muxed::DepositRoutes;
+use crate::idempotency::IdempotencyBook;
+use crate::journal::OperationJournal;
+use crate::pending_deposits::DepositBook;
+use crate::recurring::RecurringBook;
+use crate::screening::AddressLists;
+use crate::share_asset::ShareIssuance;
+use crate::timelock::Timelock;
+use crate::withdrawal_queue::WithdrawalQueue;
+use crate::{RiskLevel, UserPosition, Vault};
+
+#[cfg(feature = "storage-sqlite")]
+pub mod sqlite;
+
+pub const DEFAULT_STATE_FILE: &str = "stellarvault_state.json";
+#[cfg(feature = "storage-sqlite")]
+pub const DEFAULT_DATABASE_FILE: &str = "stellarvault.db";
+
+// ============================================================================
+// PERSISTED STATE
+// ============================================================================
+
+// JSON object keys must be strings, so positions are stored as a flat list
+// instead of the (user, risk) keyed map used in memory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionRecord {
+    pub user: String,
+    pub risk: RiskLevel,
+    pub position: UserPosition,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultState {
+    pub vaults: Vec<Vault>,
+    pub positions: Vec<PositionRecord>,
+    pub insurance_pool: u64,
+    #[serde(default)]
+    pub asset_insurance: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub claims: ClaimBook,
+    #[serde(default)]
+    pub insurance_investment: InsuranceInvestment,
+    #[serde(default)]
+    pub share_issuance: ShareIssuance,
+    #[serde(default)]
+    pub withdrawal_queue: WithdrawalQueue,
+    #[serde(default)]
+    pub pending_deposits: DepositBook,
+    #[serde(default)]
+    pub ingest_cursor: Option<String>,
+    #[serde(default)]
+    pub deposit_routes: DepositRoutes,
+    #[serde(default)]
+    pub contract_index: ContractIndex,
+    #[serde(default)]
+    pub recurring: RecurringBook,
+    #[serde(default)]
+    pub journal: OperationJournal,
+    #[serde(default)]
+    pub idempotency: IdempotencyBook,
+    // Set with 'admin set-treasury'; a treasury configured at startup is only
+    // used while none is saved
+    #[serde(default)]
+    pub treasury_address: Option<String>,
+    #[serde(default)]
+    pub timelock: Timelock,
+    // The admin delay in force; a configured one only lowers it once a
+    // queued cut has waited this one out. None in state saved before it was.
+    #[serde(default)]
+    pub admin_delay_secs: Option<u64>,
+    #[serde(default)]
+    pub address_lists: AddressLists,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepositRecord {
+    pub user: String,
+    pub risk: RiskLevel,
+    pub amount_stroops: u64,
+    pub insurance_stroops: u64,
+    pub shares_minted: u64,
+    pub tx_hash: String,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WithdrawalRecord {
+    pub user: String,
+    pub risk: RiskLevel,
+    pub shares_burned: u64,
+    pub amount_stroops: u64,
+    pub tx_hash: String,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct YieldRecord {
+    pub risk: RiskLevel,
+    pub strategy: String,
+    pub amount_stroops: u64,
+    pub timestamp: u64,
+}
+
+// ============================================================================
+// STORE TRAIT
+// ============================================================================
+
+pub trait Store {
+    fn describe(&self) -> String;
+    fn load(&self) -> Result<Option<VaultState>, VaultError>;
+    fn save(&self, state: &VaultState) -> Result<(), VaultError>;
+
+    // History is optional: the JSON file only keeps current balances
+    fn record_deposit(&self, _record: &DepositRecord) -> Result<(), VaultError> {
+        Ok(())
+    }
+
+    fn record_withdrawal(&self, _record: &WithdrawalRecord) -> Result<(), VaultError> {
+        Ok(())
+    }
+
+    fn record_yield(&self, _record: &YieldRecord) -> Result<(), VaultError> {
+        Ok(())
+    }
+
+    // The event log is append-only: entries are never rewritten or removed
+    fn append_event(&self, record: &EventRecord) -> Result<(), VaultError>;
+    fn load_events(&self) -> Result<Vec<EventRecord>, VaultError>;
+}
+
+pub fn open_default() -> Result<Box<dyn Store>, VaultError> {
+    #[cfg(feature = "storage-sqlite")]
+    {
+        let store = sqlite::SqliteStore::open(DEFAULT_DATABASE_FILE)?;
+
+        // Carry balances and the event log over from the JSON files on the
+        // first run with sqlite
+        let json = JsonStorage::new(DEFAULT_STATE_FILE);
+        if store.load()?.is_none() {
+            if let Some(state) = json.load()? {
+                store.save(&state)?;
+            }
+        }
+        if store.load_events()?.is_empty() {
+            for record in json.load_events()? {
+                store.append_event(&record)?;
+            }
+        }
+
+        Ok(Box::new(store))
+    }
+
+    #[cfg(not(feature = "storage-sqlite"))]
+    {
+        Ok(Box::new(JsonStorage::new(DEFAULT_STATE_FILE)))
+    }
+}
+
+// ============================================================================
+// IN-MEMORY STORAGE
+// ============================================================================
+
+// Keeps nothing past the process, for the offline mock ledger: its balances
+// vanish on exit, so saved vault state would never match them again
+#[derive(Default)]
+pub struct MemoryStorage {
+    state: Mutex<Option<VaultState>>,
+    events: Mutex<Vec<EventRecord>>,
+}
+
+impl Store for MemoryStorage {
+    fn describe(&self) -> String {
+        "memory".to_string()
+    }
+
+    fn load(&self) -> Result<Option<VaultState>, VaultError> {
+        Ok(self.state.lock().unwrap().clone())
+    }
+
+    fn save(&self, state: &VaultState) -> Result<(), VaultError> {
+        *self.state.lock().unwrap() = Some(state.clone());
+        Ok(())
+    }
+
+    fn append_event(&self, record: &EventRecord) -> Result<(), VaultError> {
+        self.events.lock().unwrap().push(record.clone());
+        Ok(())
+    }
+
+    fn load_events(&self) -> Result<Vec<EventRecord>, VaultError> {
+        Ok(self.events.lock().unwrap().clone())
+    }
+}
+
+// ============================================================================
+// JSON FILE STORAGE
+// ============================================================================
+
+// Events go to a JSON-lines file next to the state file, one record per line
+pub struct JsonStorage {
+    path: PathBuf,
+    events_path: PathBuf,
+}
+
+impl JsonStorage {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        JsonStorage {
+            path: path.as_ref().to_path_buf(),
+            events_path: path.as_ref().with_extension("events.jsonl"),
+        }
+    }
+}
+
+impl Store for JsonStorage {
+    fn describe(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn load(&self) -> Result<Option<VaultState>, VaultError> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+
+        let contents = fs::read_to_string(&self.path)
+            .map_err(|e| VaultError::Storage(format!("Failed to read {}: {}", self.path.display(), e)))?;
+        let state: VaultState = serde_json::from_str(&contents)
+            .map_err(|e| VaultError::Storage(format!("Corrupt vault state in {}: {}", self.path.display(), e)))?;
+
+        Ok(Some(state))
+    }
+
+    fn save(&self, state: &VaultState) -> Result<(), VaultError> {
+        let json = serde_json::to_string_pretty(state)?;
+
+        // Write to a temp file first so a crash mid-write never leaves a
+        // truncated state file behind
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, json)
+            .map_err(|e| VaultError::Storage(format!("Failed to write {}: {}", tmp_path.display(), e)))?;
+        fs::rename(&tmp_path, &self.path)
+            .map_err(|e| VaultError::Storage(format!("Failed to replace {}: {}", self.path.display(), e)))?;
+
+        Ok(())
+    }
+
+    fn append_event(&self, record: &EventRecord) -> Result<(), VaultError> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.events_path)
+            .map_err(|e| VaultError::Storage(format!("Failed to open {}: {}", self.events_path.display(), e)))?;
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+        // The log is what state is rebuilt from after a crash
+        file.sync_data()?;
+        Ok(())
+    }
+
+    fn load_events(&self) -> Result<Vec<EventRecord>, VaultError> {
+        if !self.events_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let contents = fs::read_to_string(&self.events_path)
+            .map_err(|e| VaultError::Storage(format!("Failed to read {}: {}", self.events_path.display(), e)))?;
+        contents.lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(i, line)| serde_json::from_str(line)
+                .map_err(|e| VaultError::Storage(format!("Corrupt event on line {} of {}: {}", i + 1, self.events_path.display(), e))))
+            .collect()
+    }
+}
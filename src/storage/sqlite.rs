@@ -0,0 +1,445 @@
+use std::path::Path;
+use rusqlite::{params, Connection};
+
+use super::{DepositRecord, PositionRecord, Store, VaultState, WithdrawalRecord, YieldRecord};
+use crate::claims::{Claim, ClaimBook};
+use crate::error::VaultError;
+use crate::events::EventRecord;
+use crate::insurance::InsuranceInvestment;
+use crate::{risk_level_to_string, UserPosition, Vault};
+
+// Full structs are kept in `data` as JSON so new fields don't need a
+// migration; the numeric columns next to them exist for ad-hoc queries.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS meta (
+        key   TEXT PRIMARY KEY,
+        value INTEGER NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS vaults (
+        risk          TEXT PRIMARY KEY,
+        total_value   INTEGER NOT NULL,
+        total_shares  INTEGER NOT NULL,
+        insurance_fee INTEGER NOT NULL,
+        data          TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS positions (
+        user              TEXT NOT NULL,
+        risk              TEXT NOT NULL,
+        shares            INTEGER NOT NULL,
+        accumulated_yield INTEGER NOT NULL,
+        data              TEXT NOT NULL,
+        PRIMARY KEY (user, risk)
+    );
+
+    CREATE TABLE IF NOT EXISTS deposits (
+        id                INTEGER PRIMARY KEY AUTOINCREMENT,
+        user              TEXT NOT NULL,
+        risk              TEXT NOT NULL,
+        amount_stroops    INTEGER NOT NULL,
+        insurance_stroops INTEGER NOT NULL,
+        shares_minted     INTEGER NOT NULL,
+        tx_hash           TEXT NOT NULL,
+        created_at        INTEGER NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS withdrawals (
+        id             INTEGER PRIMARY KEY AUTOINCREMENT,
+        user           TEXT NOT NULL,
+        risk           TEXT NOT NULL,
+        shares_burned  INTEGER NOT NULL,
+        amount_stroops INTEGER NOT NULL,
+        tx_hash        TEXT NOT NULL,
+        created_at     INTEGER NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS yield_events (
+        id             INTEGER PRIMARY KEY AUTOINCREMENT,
+        risk           TEXT NOT NULL,
+        strategy       TEXT NOT NULL,
+        amount_stroops INTEGER NOT NULL,
+        created_at     INTEGER NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS claims (
+        id             INTEGER PRIMARY KEY,
+        claimant       TEXT NOT NULL,
+        risk           TEXT NOT NULL,
+        amount_stroops INTEGER NOT NULL,
+        status         TEXT NOT NULL,
+        data           TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS insurance_investment (
+        id         INTEGER PRIMARY KEY CHECK (id = 1),
+        target_bps INTEGER NOT NULL,
+        shares     INTEGER NOT NULL,
+        principal  INTEGER NOT NULL
+    );
+
+    -- Append-only operation log; rows are only ever inserted
+    CREATE TABLE IF NOT EXISTS events (
+        seq        INTEGER PRIMARY KEY,
+        kind       TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        data       TEXT NOT NULL
+    );
+
+    -- Auxiliary state that is never queried column-wise, stored as JSON
+    CREATE TABLE IF NOT EXISTS documents (
+        key  TEXT PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_deposits_user ON deposits (user);
+    CREATE INDEX IF NOT EXISTS idx_withdrawals_user ON withdrawals (user);
+";
+
+pub struct SqliteStore {
+    path: String,
+    conn: Connection,
+}
+
+impl SqliteStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, VaultError> {
+        let conn = Connection::open(path.as_ref())
+            .map_err(|e| VaultError::Storage(format!("Failed to open database {}: {}", path.as_ref().display(), e)))?;
+        conn.execute_batch(SCHEMA)?;
+
+        Ok(SqliteStore {
+            path: path.as_ref().display().to_string(),
+            conn,
+        })
+    }
+
+    fn load_document(&self, key: &str) -> Result<Option<String>, VaultError> {
+        match self.conn.query_row("SELECT data FROM documents WHERE key = ?1", params![key], |row| row.get(0)) {
+            Ok(data) => Ok(Some(data)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl Store for SqliteStore {
+    fn describe(&self) -> String {
+        format!("sqlite:{}", self.path)
+    }
+
+    fn load(&self) -> Result<Option<VaultState>, VaultError> {
+        let mut stmt = self.conn.prepare("SELECT data FROM vaults")?;
+        let vaults = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .map(|data| Ok(serde_json::from_str::<Vault>(&data?)?))
+            .collect::<Result<Vec<_>, VaultError>>()?;
+
+        if vaults.is_empty() {
+            return Ok(None);
+        }
+
+        let mut stmt = self.conn.prepare("SELECT user, data FROM positions")?;
+        let positions = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+            .map(|row| {
+                let (user, data) = row?;
+                let (risk, position): (_, UserPosition) = serde_json::from_str(&data)?;
+                Ok(PositionRecord { user, risk, position })
+            })
+            .collect::<Result<Vec<_>, VaultError>>()?;
+
+        let mut stmt = self.conn.prepare("SELECT data FROM claims ORDER BY id")?;
+        let claims = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .map(|data| Ok(serde_json::from_str::<Claim>(&data?)?))
+            .collect::<Result<Vec<_>, VaultError>>()?;
+
+        let insurance_pool: i64 = self.conn
+            .query_row("SELECT value FROM meta WHERE key = 'insurance_pool'", [], |row| row.get(0))
+            .unwrap_or(0);
+        let insurance_investment = self.conn
+            .query_row(
+                "SELECT target_bps, shares, principal FROM insurance_investment WHERE id = 1",
+                [],
+                |row| Ok(InsuranceInvestment {
+                    target_bps: row.get::<_, i64>(0)? as u16,
+                    shares: row.get::<_, i64>(1)? as u64,
+                    principal: row.get::<_, i64>(2)? as u64,
+                }),
+            )
+            .unwrap_or_default();
+
+        let share_issuance = match self.load_document("share_issuance")? {
+            Some(data) => serde_json::from_str(&data)?,
+            None => Default::default(),
+        };
+
+        let withdrawal_queue = match self.load_document("withdrawal_queue")? {
+            Some(data) => serde_json::from_str(&data)?,
+            None => Default::default(),
+        };
+        let pending_deposits = match self.load_document("pending_deposits")? {
+            Some(data) => serde_json::from_str(&data)?,
+            None => Default::default(),
+        };
+        let ingest_cursor = match self.load_document("ingest_cursor")? {
+            Some(data) => serde_json::from_str(&data)?,
+            None => None,
+        };
+        let deposit_routes = match self.load_document("deposit_routes")? {
+            Some(data) => serde_json::from_str(&data)?,
+            None => Default::default(),
+        };
+        let asset_insurance = match self.load_document("asset_insurance")? {
+            Some(data) => serde_json::from_str(&data)?,
+            None => Default::default(),
+        };
+        let contract_index = match self.load_document("contract_index")? {
+            Some(data) => serde_json::from_str(&data)?,
+            None => Default::default(),
+        };
+
+        let recurring = match self.load_document("recurring")? {
+            Some(data) => serde_json::from_str(&data)?,
+            None => Default::default(),
+        };
+        let journal = match self.load_document("journal")? {
+            Some(data) => serde_json::from_str(&data)?,
+            None => Default::default(),
+        };
+        let idempotency = match self.load_document("idempotency")? {
+            Some(data) => serde_json::from_str(&data)?,
+            None => Default::default(),
+        };
+        let treasury_address = match self.load_document("treasury_address")? {
+            Some(data) => serde_json::from_str(&data)?,
+            None => None,
+        };
+        let timelock = match self.load_document("timelock")? {
+            Some(data) => serde_json::from_str(&data)?,
+            None => Default::default(),
+        };
+        let admin_delay_secs = match self.load_document("admin_delay_secs")? {
+            Some(data) => serde_json::from_str(&data)?,
+            None => None,
+        };
+        let address_lists = match self.load_document("address_lists")? {
+            Some(data) => serde_json::from_str(&data)?,
+            None => Default::default(),
+        };
+
+        Ok(Some(VaultState {
+            vaults,
+            positions,
+            insurance_pool: insurance_pool as u64,
+            asset_insurance,
+            claims: ClaimBook::from_claims(claims),
+            insurance_investment,
+            share_issuance,
+            withdrawal_queue,
+            pending_deposits,
+            ingest_cursor,
+            deposit_routes,
+            contract_index,
+            recurring,
+            journal,
+            idempotency,
+            treasury_address,
+            timelock,
+            admin_delay_secs,
+            address_lists,
+        }))
+    }
+
+    fn save(&self, state: &VaultState) -> Result<(), VaultError> {
+        let tx = self.conn.unchecked_transaction()?;
+
+        for vault in &state.vaults {
+            tx.execute(
+                "INSERT OR REPLACE INTO vaults (risk, total_value, total_shares, insurance_fee, data)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    risk_level_to_string(vault.risk_level),
+                    vault.total_value as i64,
+                    vault.total_shares as i64,
+                    vault.insurance_fee as i64,
+                    serde_json::to_string(vault)?,
+                ],
+            )?;
+        }
+
+        for record in &state.positions {
+            tx.execute(
+                "INSERT OR REPLACE INTO positions (user, risk, shares, accumulated_yield, data)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    record.user,
+                    risk_level_to_string(record.risk),
+                    record.position.shares as i64,
+                    record.position.accumulated_yield as i64,
+                    serde_json::to_string(&(record.risk, &record.position))?,
+                ],
+            )?;
+        }
+
+        for claim in state.claims.all() {
+            tx.execute(
+                "INSERT OR REPLACE INTO claims (id, claimant, risk, amount_stroops, status, data)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    claim.id as i64,
+                    claim.claimant,
+                    risk_level_to_string(claim.risk),
+                    claim.amount_stroops as i64,
+                    format!("{:?}", claim.status),
+                    serde_json::to_string(claim)?,
+                ],
+            )?;
+        }
+
+        tx.execute(
+            "INSERT OR REPLACE INTO insurance_investment (id, target_bps, shares, principal)
+             VALUES (1, ?1, ?2, ?3)",
+            params![
+                state.insurance_investment.target_bps as i64,
+                state.insurance_investment.shares as i64,
+                state.insurance_investment.principal as i64,
+            ],
+        )?;
+
+        tx.execute(
+            "INSERT OR REPLACE INTO documents (key, data) VALUES ('share_issuance', ?1)",
+            params![serde_json::to_string(&state.share_issuance)?],
+        )?;
+        tx.execute(
+            "INSERT OR REPLACE INTO documents (key, data) VALUES ('withdrawal_queue', ?1)",
+            params![serde_json::to_string(&state.withdrawal_queue)?],
+        )?;
+        tx.execute(
+            "INSERT OR REPLACE INTO documents (key, data) VALUES ('pending_deposits', ?1)",
+            params![serde_json::to_string(&state.pending_deposits)?],
+        )?;
+        tx.execute(
+            "INSERT OR REPLACE INTO documents (key, data) VALUES ('ingest_cursor', ?1)",
+            params![serde_json::to_string(&state.ingest_cursor)?],
+        )?;
+        tx.execute(
+            "INSERT OR REPLACE INTO documents (key, data) VALUES ('deposit_routes', ?1)",
+            params![serde_json::to_string(&state.deposit_routes)?],
+        )?;
+        tx.execute(
+            "INSERT OR REPLACE INTO documents (key, data) VALUES ('asset_insurance', ?1)",
+            params![serde_json::to_string(&state.asset_insurance)?],
+        )?;
+        tx.execute(
+            "INSERT OR REPLACE INTO documents (key, data) VALUES ('contract_index', ?1)",
+            params![serde_json::to_string(&state.contract_index)?],
+        )?;
+        tx.execute(
+            "INSERT OR REPLACE INTO documents (key, data) VALUES ('recurring', ?1)",
+            params![serde_json::to_string(&state.recurring)?],
+        )?;
+        tx.execute(
+            "INSERT OR REPLACE INTO documents (key, data) VALUES ('journal', ?1)",
+            params![serde_json::to_string(&state.journal)?],
+        )?;
+        tx.execute(
+            "INSERT OR REPLACE INTO documents (key, data) VALUES ('idempotency', ?1)",
+            params![serde_json::to_string(&state.idempotency)?],
+        )?;
+        tx.execute(
+            "INSERT OR REPLACE INTO documents (key, data) VALUES ('treasury_address', ?1)",
+            params![serde_json::to_string(&state.treasury_address)?],
+        )?;
+        tx.execute(
+            "INSERT OR REPLACE INTO documents (key, data) VALUES ('timelock', ?1)",
+            params![serde_json::to_string(&state.timelock)?],
+        )?;
+        tx.execute(
+            "INSERT OR REPLACE INTO documents (key, data) VALUES ('admin_delay_secs', ?1)",
+            params![serde_json::to_string(&state.admin_delay_secs)?],
+        )?;
+        tx.execute(
+            "INSERT OR REPLACE INTO documents (key, data) VALUES ('address_lists', ?1)",
+            params![serde_json::to_string(&state.address_lists)?],
+        )?;
+
+        tx.execute(
+            "INSERT OR REPLACE INTO meta (key, value) VALUES ('insurance_pool', ?1)",
+            params![state.insurance_pool as i64],
+        )?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn record_deposit(&self, record: &DepositRecord) -> Result<(), VaultError> {
+        self.conn.execute(
+            "INSERT INTO deposits (user, risk, amount_stroops, insurance_stroops, shares_minted, tx_hash, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                record.user,
+                risk_level_to_string(record.risk),
+                record.amount_stroops as i64,
+                record.insurance_stroops as i64,
+                record.shares_minted as i64,
+                record.tx_hash,
+                record.timestamp as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn record_withdrawal(&self, record: &WithdrawalRecord) -> Result<(), VaultError> {
+        self.conn.execute(
+            "INSERT INTO withdrawals (user, risk, shares_burned, amount_stroops, tx_hash, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                record.user,
+                risk_level_to_string(record.risk),
+                record.shares_burned as i64,
+                record.amount_stroops as i64,
+                record.tx_hash,
+                record.timestamp as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn record_yield(&self, record: &YieldRecord) -> Result<(), VaultError> {
+        self.conn.execute(
+            "INSERT INTO yield_events (risk, strategy, amount_stroops, created_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                risk_level_to_string(record.risk),
+                record.strategy,
+                record.amount_stroops as i64,
+                record.timestamp as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn append_event(&self, record: &EventRecord) -> Result<(), VaultError> {
+        self.conn.execute(
+            "INSERT INTO events (seq, kind, created_at, data) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                record.seq as i64,
+                record.event.kind(),
+                record.timestamp as i64,
+                serde_json::to_string(record)?,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn load_events(&self) -> Result<Vec<EventRecord>, VaultError> {
+        let mut stmt = self.conn.prepare("SELECT data FROM events ORDER BY seq")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        let mut events = Vec::new();
+        for data in rows {
+            events.push(serde_json::from_str(&data?)?);
+        }
+        Ok(events)
+    }
+}
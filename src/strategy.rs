@@ -0,0 +1,251 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
+
+use crate::error::VaultError;
+use crate::{StrategyAllocation, SECONDS_PER_YEAR};
+
+// Ids of the built-in strategies. They are the names earlier releases saved
+// and configured the vault contract with, so existing state keeps loading.
+pub const AQUA_LIQUIDITY_POOL: &str = "AquaLiquidityPool";
+pub const YIELDBLOX_LENDING: &str = "YieldBloxLending";
+pub const MONEY_MARKET: &str = "MoneyMarket";
+pub const SDEX_MARKET_MAKING: &str = "SdexMarketMaking";
+pub const SOROSWAP_LIQUIDITY: &str = "SoroswapLiquidity";
+
+// ============================================================================
+// STRATEGY TRAIT
+// ============================================================================
+
+/// What a harvest knows about one strategy's yield.
+#[derive(Debug, Clone, Copy)]
+pub struct HarvestContext {
+    pub elapsed_secs: u64,
+    /// Yield measured on-chain since the last harvest, for strategies whose
+    /// positions the vault tracks itself (pool shares, lending, offers).
+    pub measured: Option<u64>,
+}
+
+/// A protocol the vaults can put funds into.
+///
+/// The vault keeps one [`StrategyAllocation`] per strategy it runs and calls
+/// these hooks as funds move; implementations update the allocation's books
+/// and report what it earns. The defaults suit a strategy that earns a fixed
+/// APY on whatever it holds. Strategies are looked up by [`Strategy::id`],
+/// which is saved with the vault's state, so it must not change once funds
+/// have been allocated.
+///
+/// ```
+/// use stellarvault::strategy::{self, HarvestContext, Strategy};
+/// use stellarvault::StrategyAllocation;
+///
+/// // Pays a flat 1 XLM per harvest, however long it has been
+/// struct Drip;
+///
+/// impl Strategy for Drip {
+///     fn id(&self) -> &str {
+///         "Drip"
+///     }
+///
+///     fn harvest(&self, _: &StrategyAllocation, _: &HarvestContext) -> u64 {
+///         10_000_000
+///     }
+/// }
+///
+/// strategy::register(Drip).unwrap();
+/// assert!(strategy::is_registered("Drip"));
+/// ```
+pub trait Strategy: Send + Sync {
+    fn id(&self) -> &str;
+
+    /// Add-on strategies keep the share they are given; the others are
+    /// scaled to fit around them.
+    fn is_add_on(&self) -> bool {
+        false
+    }
+
+    /// Puts `amount` stroops into the strategy.
+    fn allocate(&self, allocation: &mut StrategyAllocation, amount: u64) {
+        allocation.total_allocated = allocation.total_allocated.saturating_add(amount);
+    }
+
+    /// Takes up to `amount` stroops out, returning what was taken.
+    fn withdraw(&self, allocation: &mut StrategyAllocation, amount: u64) -> u64 {
+        let taken = amount.min(allocation.total_allocated);
+        allocation.total_allocated -= taken;
+        taken
+    }
+
+    /// Expected yearly yield, in basis points.
+    fn current_apy(&self, allocation: &StrategyAllocation) -> u16 {
+        allocation.current_apy
+    }
+
+    /// Yield earned since the last harvest, in stroops.
+    fn harvest(&self, allocation: &StrategyAllocation, context: &HarvestContext) -> u64 {
+        context.measured.unwrap_or_else(|| {
+            accrued_yield(allocation.total_allocated, self.current_apy(allocation), context.elapsed_secs)
+        })
+    }
+}
+
+/// Simple interest on `principal` at `apy_bps` over `elapsed_secs`.
+pub fn accrued_yield(principal: u64, apy_bps: u16, elapsed_secs: u64) -> u64 {
+    (principal as u128 * apy_bps as u128 * elapsed_secs as u128 / (10_000 * SECONDS_PER_YEAR as u128)) as u64
+}
+
+// ============================================================================
+// BUILT-IN STRATEGIES
+// ============================================================================
+
+// Liquidity in an Aquarius pool on the Stellar DEX; earns trading fees
+pub struct AquaLiquidityPool;
+
+impl Strategy for AquaLiquidityPool {
+    fn id(&self) -> &str {
+        AQUA_LIQUIDITY_POOL
+    }
+}
+
+// Supplied to a Blend pool; earns its lending rate
+pub struct YieldBloxLending;
+
+impl Strategy for YieldBloxLending {
+    fn id(&self) -> &str {
+        YIELDBLOX_LENDING
+    }
+}
+
+pub struct MoneyMarket;
+
+impl Strategy for MoneyMarket {
+    fn id(&self) -> &str {
+        MONEY_MARKET
+    }
+}
+
+// Passive offers on the Stellar DEX; earns realized spread, not an APY
+pub struct SdexMarketMaking;
+
+impl Strategy for SdexMarketMaking {
+    fn id(&self) -> &str {
+        SDEX_MARKET_MAKING
+    }
+
+    fn is_add_on(&self) -> bool {
+        true
+    }
+}
+
+// Liquidity in a Soroswap pair; earns the pair's swap fees
+pub struct SoroswapLiquidity;
+
+impl Strategy for SoroswapLiquidity {
+    fn id(&self) -> &str {
+        SOROSWAP_LIQUIDITY
+    }
+
+    fn is_add_on(&self) -> bool {
+        true
+    }
+}
+
+// Stands in for a strategy saved by a build that registered it and this one
+// doesn't, so its funds stay on the books and keep accruing at their APY
+struct Unregistered(String);
+
+impl Strategy for Unregistered {
+    fn id(&self) -> &str {
+        &self.0
+    }
+}
+
+// ============================================================================
+// REGISTRY
+// ============================================================================
+
+fn registry() -> &'static RwLock<BTreeMap<String, Arc<dyn Strategy>>> {
+    static REGISTRY: OnceLock<RwLock<BTreeMap<String, Arc<dyn Strategy>>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let built_in: [Arc<dyn Strategy>; 5] = [
+            Arc::new(AquaLiquidityPool),
+            Arc::new(YieldBloxLending),
+            Arc::new(MoneyMarket),
+            Arc::new(SdexMarketMaking),
+            Arc::new(SoroswapLiquidity),
+        ];
+        RwLock::new(built_in.into_iter().map(|s| (s.id().to_string(), s)).collect())
+    })
+}
+
+/// Makes a strategy available to every vault in the process. Register
+/// plug-in strategies before building a vault that uses them.
+pub fn register(strategy: impl Strategy + 'static) -> Result<(), VaultError> {
+    let mut strategies = registry().write().unwrap_or_else(PoisonError::into_inner);
+    if strategies.contains_key(strategy.id()) {
+        return Err(VaultError::Validation(format!("A strategy with id {:?} is already registered", strategy.id())));
+    }
+    strategies.insert(strategy.id().to_string(), Arc::new(strategy));
+    Ok(())
+}
+
+pub fn is_registered(id: &str) -> bool {
+    registry().read().unwrap_or_else(PoisonError::into_inner).contains_key(id)
+}
+
+/// Every registered strategy id, in order.
+pub fn registered() -> Vec<String> {
+    registry().read().unwrap_or_else(PoisonError::into_inner).keys().cloned().collect()
+}
+
+/// The strategy with this id; one that isn't registered behaves as if it
+/// earned a fixed APY.
+pub fn lookup(id: &str) -> Arc<dyn Strategy> {
+    match registry().read().unwrap_or_else(PoisonError::into_inner).get(id) {
+        Some(strategy) => strategy.clone(),
+        None => Arc::new(Unregistered(id.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::VaultBuilder;
+    use crate::RiskLevel;
+
+    // Keeps a tenth of every allocation as a reserve it never lends out
+    struct Reserved;
+
+    impl Strategy for Reserved {
+        fn id(&self) -> &str {
+            "Reserved"
+        }
+
+        fn current_apy(&self, allocation: &StrategyAllocation) -> u16 {
+            allocation.current_apy * 9 / 10
+        }
+    }
+
+    #[test]
+    fn plug_in_strategies_take_part_in_vault_accounting() {
+        register(Reserved).unwrap();
+        assert!(register(Reserved).is_err());
+        assert!(lookup(SOROSWAP_LIQUIDITY).is_add_on());
+
+        let mut vault = VaultBuilder::new(RiskLevel::Medium)
+            .strategy("Reserved", 50, 1000)
+            .strategy(MONEY_MARKET, 50, 1000)
+            .liquidity_buffer(0)
+            .build()
+            .unwrap();
+        assert_eq!(vault.blended_apy(), 950);
+        assert!(VaultBuilder::new(RiskLevel::Low).strategy("Missing", 100, 0).build().is_err());
+
+        vault.allocate(1_000);
+        let context = HarvestContext { elapsed_secs: SECONDS_PER_YEAR, measured: None };
+        let earned: Vec<u64> = vault.strategies.iter().map(|s| lookup(&s.strategy).harvest(s, &context)).collect();
+        assert_eq!(earned, vec![45, 50]);
+
+        vault.deallocate(400);
+        assert_eq!(vault.strategies.iter().map(|s| s.total_allocated).sum::<u64>(), 600);
+    }
+}
@@ -0,0 +1,419 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use stellar_xdr::curr::{Transaction, TransactionEnvelope};
+use thiserror::Error;
+
+use crate::error::VaultError;
+use crate::fee_strategy::FeeStrategy;
+use crate::horizon::{self, HorizonClient, HorizonError};
+use crate::retry::{RetryPolicy, MAX_BACKOFF};
+use crate::sequence::SequenceManager;
+use crate::signer::Signer;
+use crate::soroban::{RpcClient, TransactionInfo};
+use crate::transaction::{self, Keypair};
+
+// Roughly one ledger, for Horizon to ingest a transaction core already applied
+const INGESTION_DELAY_SECS: u64 = 6;
+// Timeouts on one envelope before it is wrapped in a fee bump
+const BUMP_AFTER_TIMEOUTS: u32 = 2;
+// How often to ask Soroban RPC whether a sent contract call has applied
+const POLL_INTERVAL_SECS: u64 = 2;
+
+// ============================================================================
+// SUBMISSION
+// ============================================================================
+
+#[derive(Debug, Error)]
+pub enum SubmitError {
+    // Nothing was applied, and no envelope that was sent can still be
+    #[error("{0}")]
+    Rejected(String),
+    // Gave up while the last envelope could still make it into a ledger,
+    // until `valid_until` if its time bounds are known
+    #[error("transaction {hash} is not confirmed yet and may still be included ({reason})")]
+    Unconfirmed { hash: String, reason: String, valid_until: Option<u64> },
+}
+
+// Everything signing clients share to get transactions into a ledger. Clones
+// share the sequence cache, the fee payer and the unconfirmed envelopes.
+#[derive(Clone)]
+pub struct Submitter {
+    horizon: HorizonClient,
+    passphrase: String,
+    sequences: SequenceManager,
+    pub fee_strategy: FeeStrategy,
+    // Pays for fee bumps; without one, stuck transactions just wait
+    fee_payer: Arc<Mutex<Option<Arc<Keypair>>>>,
+    // Signed envelopes that timed out, by hash, so `bump` can rescue them
+    unconfirmed: Arc<Mutex<HashMap<String, TransactionEnvelope>>>,
+}
+
+impl Submitter {
+    pub fn new(horizon: HorizonClient, passphrase: &str, fee_strategy: FeeStrategy) -> Self {
+        Submitter {
+            horizon,
+            passphrase: passphrase.to_string(),
+            sequences: SequenceManager::default(),
+            fee_strategy,
+            fee_payer: Arc::new(Mutex::new(None)),
+            unconfirmed: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn set_fee_payer(&self, fee_payer: Keypair) {
+        *self.fee_payer.lock().unwrap() = Some(Arc::new(fee_payer));
+    }
+
+    pub fn fee_payer(&self) -> Option<String> {
+        self.fee_payer.lock().unwrap().as_ref().map(|k| k.public_key())
+    }
+
+    // Attempts and waits follow the Horizon client's retry policy
+    fn retry(&self) -> RetryPolicy {
+        self.horizon.retry_policy()
+    }
+
+    fn max_attempts(&self) -> u32 {
+        self.retry().max_retries + 1
+    }
+
+    async fn wait(&self, retries: &mut u32, reason: &str) {
+        let delay = self.retry().backoff(*retries);
+        say!("   ⏳ {}; retrying in {:.1}s", reason, delay.as_secs_f64());
+        tokio::time::sleep(delay).await;
+        *retries += 1;
+    }
+
+    pub fn horizon(&self) -> &HorizonClient {
+        &self.horizon
+    }
+
+    pub fn unconfirmed(&self) -> Vec<String> {
+        self.unconfirmed.lock().unwrap().keys().cloned().collect()
+    }
+
+    // Wraps a signed envelope in a fee bump at the strategy's maximum fee
+    fn bump_envelope(&self, inner: &TransactionEnvelope) -> Result<String, VaultError> {
+        let fee_payer = self.fee_payer.lock().unwrap().clone()
+            .ok_or("No fee payer is configured; set fee_payer and unlock its key")?;
+        let bump = transaction::fee_bump(inner, &fee_payer.public_key(), self.fee_strategy.max_fee)?;
+        let envelope = transaction::sign_fee_bump(bump, &self.passphrase, &[&fee_payer])?;
+        transaction::to_base64(&envelope)
+    }
+
+    // Builds, signs with every signer and submits a transaction from `source`
+    // until it is in a ledger.
+    //
+    // Transient failures (timeouts, 5xx, dropped connections) resubmit the same
+    // signed envelope, which is idempotent: it can only ever apply once. Only a
+    // tx_bad_seq or tx_too_late, which prove that envelope will never apply,
+    // rebuild the transaction on a fresh sequence number, and only after every
+    // earlier envelope has been checked on Horizon, so a payment is never sent
+    // twice. Sequence numbers come from the shared manager, which is reset
+    // whenever a rejection means its cached value can't be trusted.
+    //
+    // With a fee payer configured, an envelope stuck behind surge pricing is
+    // wrapped in a fee bump; the inner transaction, and so its hash, is the same.
+    pub async fn submit<F>(&self, source: &str, signers: &[&dyn Signer], build: F) -> Result<horizon::Transaction, SubmitError>
+    where
+        F: Fn(i64) -> Result<Transaction, VaultError>,
+    {
+        let horizon = &self.horizon;
+        let source = source.to_string();
+        let mut sent: Vec<String> = Vec::new();
+        let mut uncertain = false;
+        let mut retries = 0;
+        let mut attempts = 0;
+
+        'build: loop {
+            let sequence = loop {
+                match self.sequences.reserve(horizon, &source).await {
+                    Ok(sequence) => break sequence,
+                    Err(e) if attempts < self.max_attempts()
+                        && e.horizon().is_some_and(HorizonError::is_transient) => {
+                        attempts += 1;
+                        self.wait(&mut retries, &e.to_string()).await;
+                    }
+                    // Anything sent before this point is known not to apply
+                    Err(e) => return Err(SubmitError::Rejected(e.to_string())),
+                }
+            };
+
+            let signed = build(sequence).and_then(|tx| {
+                let hash = transaction::hash_hex(&tx, &self.passphrase)?;
+                let valid_until = transaction::valid_until(&tx);
+                let envelope = transaction::sign(tx, &self.passphrase, signers)?;
+                let encoded = transaction::to_base64(&envelope)?;
+                Ok((hash, valid_until, envelope, encoded))
+            });
+            let (hash, valid_until, inner, mut envelope) = match signed {
+                Ok(signed) => signed,
+                Err(e) => {
+                    self.sequences.release(&source, sequence).await;
+                    return Err(SubmitError::Rejected(e.to_string()));
+                }
+            };
+            sent.push(hash.clone());
+            let mut timeouts = 0;
+            let mut bumped = false;
+
+            loop {
+                attempts += 1;
+                let error = match horizon.submit_transaction(&envelope).await {
+                    Ok(applied) => return Ok(applied),
+                    Err(e) => e,
+                };
+
+                let code = error.transaction_code();
+                let stuck = code == Some("tx_insufficient_fee")
+                    || (error.is_transient() && timeouts + 1 >= BUMP_AFTER_TIMEOUTS);
+                if stuck && !bumped && self.fee_payer().is_some() && attempts < self.max_attempts() {
+                    match self.bump_envelope(&inner) {
+                        Ok(bump) => {
+                            say!("   ⛽ Transaction {} is stuck; fee-bumping to {} stroops per operation",
+                                hash, self.fee_strategy.max_fee);
+                            envelope = bump;
+                            bumped = true;
+                            uncertain |= error.is_transient();
+                            continue;
+                        }
+                        Err(e) => say!("   ⚠️  Could not fee-bump transaction {}: {}", hash, e),
+                    }
+                }
+
+                match code {
+                    Some(code @ ("tx_bad_seq" | "tx_too_late")) => {
+                        // An earlier envelope may be the reason this one was refused
+                        if uncertain {
+                            tokio::time::sleep(Duration::from_secs(INGESTION_DELAY_SECS)).await;
+                        }
+                        if let Some(applied) = find_applied(horizon, &sent, valid_until).await? {
+                            return landed(applied);
+                        }
+                        self.sequences.invalidate(&source).await;
+                        if attempts >= self.max_attempts() {
+                            return Err(SubmitError::Rejected(format!("Transaction {} rejected: {}", hash, error)));
+                        }
+                        say!("   🔁 Transaction {} rejected with {}; rebuilding on a fresh sequence number", hash, code);
+                        uncertain = false;
+                        continue 'build;
+                    }
+                    _ if error.is_transient() => {
+                        uncertain = true;
+                        timeouts += 1;
+                        if attempts >= self.max_attempts() {
+                            self.unconfirmed.lock().unwrap().insert(hash.clone(), inner);
+                            return Err(SubmitError::Unconfirmed { hash, reason: error.to_string(), valid_until });
+                        }
+                        self.wait(&mut retries, &error.to_string()).await;
+                        // It may have been applied while we waited
+                        match horizon.transaction(&hash).await {
+                            Ok(applied) => return landed(applied),
+                            Err(e) if e.is_not_found() => {}
+                            Err(e) => say!("   ⚠️  Could not look up transaction {}: {}", hash, e),
+                        }
+                    }
+                    _ => {
+                        // Most rejections consume no sequence number; refetch rather than guess
+                        self.sequences.invalidate(&source).await;
+                        return Err(SubmitError::Rejected(format!("Transaction {} failed: {}", hash, error)));
+                    }
+                }
+            }
+        }
+    }
+
+    // Builds, signs and sends a contract call through Soroban RPC, then polls
+    // until it applies. Contract calls are simulated against the state they
+    // were built for, so an envelope that can no longer apply is reported
+    // rather than rebuilt: the caller simulates again and decides.
+    pub async fn submit_contract_call<F>(&self, rpc: &RpcClient, source: &str, signers: &[&dyn Signer], build: F)
+        -> Result<(String, TransactionInfo), SubmitError>
+    where
+        F: FnOnce(i64) -> Result<Transaction, VaultError>,
+    {
+        let result = self.send_contract_call(rpc, source, signers, build).await;
+        // Horizon never saw it, so its cached balances are cleared here
+        self.horizon.invalidate_accounts();
+        result
+    }
+
+    async fn send_contract_call<F>(&self, rpc: &RpcClient, source: &str, signers: &[&dyn Signer], build: F)
+        -> Result<(String, TransactionInfo), SubmitError>
+    where
+        F: FnOnce(i64) -> Result<Transaction, VaultError>,
+    {
+        let sequence = self.sequences.reserve(&self.horizon, source).await
+            .map_err(|e| SubmitError::Rejected(e.to_string()))?;
+        let signed = build(sequence).and_then(|tx| {
+            let hash = transaction::hash_hex(&tx, &self.passphrase)?;
+            let valid_until = transaction::valid_until(&tx);
+            let envelope = transaction::sign(tx, &self.passphrase, signers)?;
+            Ok((hash, valid_until, transaction::to_base64(&envelope)?))
+        });
+        let (hash, valid_until, envelope) = match signed {
+            Ok(signed) => signed,
+            Err(e) => {
+                self.sequences.release(source, sequence).await;
+                return Err(SubmitError::Rejected(e.to_string()));
+            }
+        };
+        let unconfirmed = |reason: String| SubmitError::Unconfirmed { hash: hash.clone(), reason, valid_until };
+        let mut retries = 0;
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+            match rpc.send_transaction(&envelope).await {
+                Ok(sent) if sent.status == "PENDING" || sent.status == "DUPLICATE" => break,
+                Ok(sent) if sent.status == "ERROR" => {
+                    self.sequences.invalidate(source).await;
+                    return Err(SubmitError::Rejected(format!("Transaction {} refused: {}",
+                        hash, sent.error_result_xdr.as_deref().unwrap_or("no result"))));
+                }
+                Ok(sent) if attempts < self.max_attempts() => self.wait(&mut retries, &format!("Soroban RPC answered {}", sent.status)).await,
+                Ok(sent) => return Err(unconfirmed(format!("Soroban RPC answered {}", sent.status))),
+                Err(e) if e.is_transient() && attempts < self.max_attempts() => self.wait(&mut retries, &e.to_string()).await,
+                // The envelope may have reached the server before the error
+                Err(e) if e.is_transient() => return Err(unconfirmed(e.to_string())),
+                Err(e) => {
+                    self.sequences.release(source, sequence).await;
+                    return Err(SubmitError::Rejected(format!("Transaction {} not sent: {}", hash, e)));
+                }
+            }
+        }
+
+        let sent_at = crate::unix_now();
+        let mut failures = 0;
+        loop {
+            tokio::time::sleep(Duration::from_secs(POLL_INTERVAL_SECS)).await;
+            match rpc.get_transaction(&hash).await {
+                Ok(info) if info.status == "SUCCESS" => return Ok((hash, info)),
+                Ok(info) if info.status == "FAILED" => {
+                    return Err(SubmitError::Rejected(format!("Transaction {} failed on-chain", hash)));
+                }
+                Ok(_) => match valid_until {
+                    Some(until) if crate::unix_now() > until + INGESTION_DELAY_SECS => {
+                        self.sequences.invalidate(source).await;
+                        return Err(SubmitError::Rejected(format!("Transaction {} expired before it applied", hash)));
+                    }
+                    Some(_) => {}
+                    // Without time bounds it could apply any time; stop watching
+                    None if crate::unix_now() > sent_at + MAX_BACKOFF.as_secs() * self.max_attempts() as u64 => {
+                        return Err(unconfirmed("still not in a ledger".to_string()));
+                    }
+                    None => {}
+                },
+                Err(e) => {
+                    failures += 1;
+                    if failures >= self.max_attempts() {
+                        return Err(unconfirmed(e.to_string()));
+                    }
+                    say!("   ⚠️  Could not look up transaction {}: {}", hash, e);
+                }
+            }
+        }
+    }
+
+    // Submits an envelope signed elsewhere. It can't be rebuilt, so only
+    // transient failures are retried, by resending the same envelope.
+    pub async fn submit_signed(&self, envelope: &TransactionEnvelope) -> Result<horizon::Transaction, SubmitError> {
+        let TransactionEnvelope::Tx(signed) = envelope else {
+            return Err(SubmitError::Rejected("Only v1 transaction envelopes are supported".to_string()));
+        };
+        let hash = transaction::hash_hex(&signed.tx, &self.passphrase)
+            .map_err(|e| SubmitError::Rejected(e.to_string()))?;
+        let valid_until = transaction::valid_until(&signed.tx);
+        let encoded = transaction::to_base64(envelope).map_err(|e| SubmitError::Rejected(e.to_string()))?;
+        let mut retries = 0;
+        let mut attempt = 0;
+
+        loop {
+            attempt += 1;
+            let error = match self.horizon.submit_transaction(&encoded).await {
+                Ok(applied) => return Ok(applied),
+                Err(e) => e,
+            };
+            if !error.is_transient() {
+                // A refusal may just mean an earlier attempt already landed
+                if attempt > 1 && matches!(error.transaction_code(), Some("tx_bad_seq" | "tx_too_late")) {
+                    tokio::time::sleep(Duration::from_secs(INGESTION_DELAY_SECS)).await;
+                    if let Some(applied) = find_applied(&self.horizon, std::slice::from_ref(&hash), valid_until).await? {
+                        return landed(applied);
+                    }
+                }
+                return Err(SubmitError::Rejected(format!("Transaction {} failed: {}", hash, error)));
+            }
+            if attempt >= self.max_attempts() {
+                self.unconfirmed.lock().unwrap().insert(hash.clone(), envelope.clone());
+                return Err(SubmitError::Unconfirmed { hash, reason: error.to_string(), valid_until });
+            }
+            self.wait(&mut retries, &error.to_string()).await;
+            match self.horizon.transaction(&hash).await {
+                Ok(applied) => return landed(applied),
+                Err(e) if e.is_not_found() => {}
+                Err(e) => say!("   ⚠️  Could not look up transaction {}: {}", hash, e),
+            }
+        }
+    }
+
+    // Re-sends a transaction that timed out earlier this session, wrapped in a
+    // fee bump paid by the fee payer
+    pub async fn bump(&self, hash: &str) -> Result<horizon::Transaction, SubmitError> {
+        match self.horizon.transaction(hash).await {
+            Ok(applied) => {
+                self.unconfirmed.lock().unwrap().remove(hash);
+                return landed(applied);
+            }
+            Err(e) if e.is_not_found() => {}
+            Err(e) => return Err(SubmitError::Unconfirmed { hash: hash.to_string(), reason: e.to_string(), valid_until: None }),
+        }
+
+        let inner = self.unconfirmed.lock().unwrap().get(hash).cloned()
+            .ok_or_else(|| SubmitError::Rejected(format!(
+                "Transaction {} was not submitted in this session, or has already been confirmed", hash)))?;
+        let envelope = self.bump_envelope(&inner).map_err(|e| SubmitError::Rejected(e.to_string()))?;
+
+        match self.horizon.submit_transaction(&envelope).await {
+            Ok(applied) => {
+                self.unconfirmed.lock().unwrap().remove(hash);
+                Ok(applied)
+            }
+            Err(e) if e.is_transient() => Err(SubmitError::Unconfirmed {
+                hash: hash.to_string(),
+                reason: e.to_string(),
+                valid_until: None,
+            }),
+            Err(e) => {
+                // A refused envelope can't apply later, so there's nothing left to bump
+                if matches!(e.transaction_code(), Some("tx_bad_seq" | "tx_too_late")) {
+                    self.unconfirmed.lock().unwrap().remove(hash);
+                }
+                Err(SubmitError::Rejected(format!("Fee bump for {} failed: {}", hash, e)))
+            }
+        }
+    }
+}
+
+
+// Checks every envelope sent so far; an error means we can't rule any out
+async fn find_applied(horizon: &HorizonClient, sent: &[String], valid_until: Option<u64>) -> Result<Option<horizon::Transaction>, SubmitError> {
+    for hash in sent {
+        match horizon.transaction(hash).await {
+            Ok(applied) => return Ok(Some(applied)),
+            Err(e) if e.is_not_found() => {}
+            Err(e) => return Err(SubmitError::Unconfirmed { hash: hash.clone(), reason: e.to_string(), valid_until }),
+        }
+    }
+    Ok(None)
+}
+
+// A transaction in a ledger that failed still consumed its sequence number
+fn landed(applied: horizon::Transaction) -> Result<horizon::Transaction, SubmitError> {
+    if applied.successful {
+        Ok(applied)
+    } else {
+        Err(SubmitError::Rejected(format!("Transaction {} failed on-chain", applied.hash)))
+    }
+}
@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::VaultError;
+use crate::{format_duration, RiskLevel};
+
+// Long enough for depositors to notice a queued change and withdraw
+pub const DEFAULT_ADMIN_DELAY_SECS: u64 = 48 * 3_600;
+
+// ============================================================================
+// ADMIN CHANGES
+// ============================================================================
+
+// One strategy's target in a vault, as 'admin set-strategies' gives it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StrategyTarget {
+    pub id: String,
+    pub allocation: u8,
+    pub apy_bps: u16,
+}
+
+// An admin change that could cost depositors, so it waits out the delay
+// before taking effect. Values are the ones to set, not adjustments.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AdminChange {
+    Fees { risk: RiskLevel, management_fee_bps: u16, performance_fee_bps: u16 },
+    Strategies { risk: RiskLevel, targets: Vec<StrategyTarget> },
+    Lockup { risk: RiskLevel, lockup_secs: u64, early_withdrawal_penalty: u16 },
+    Treasury { address: String },
+    // Only a cut waits; it is queued from the configured delay at startup
+    AdminDelay { delay_secs: u64 },
+}
+
+impl AdminChange {
+    pub fn describe(&self) -> String {
+        match self {
+            AdminChange::Fees { risk, management_fee_bps, performance_fee_bps } => format!(
+                "{:?} Risk Vault fees to {:.2}% management and {:.2}% performance",
+                risk, *management_fee_bps as f64 / 100.0, *performance_fee_bps as f64 / 100.0),
+            AdminChange::Strategies { risk, targets } => {
+                let targets: Vec<String> = targets.iter().map(|t| format!("{} {}%", t.id, t.allocation)).collect();
+                format!("{:?} Risk Vault strategies to {}", risk, targets.join(", "))
+            }
+            AdminChange::Lockup { risk, lockup_secs, early_withdrawal_penalty } => format!(
+                "{:?} Risk Vault lockup to {} with a {:.2}% early withdrawal penalty",
+                risk, format_duration(*lockup_secs), *early_withdrawal_penalty as f64 / 100.0),
+            AdminChange::Treasury { address } => format!("treasury to {}", address),
+            AdminChange::AdminDelay { delay_secs } => format!("admin delay to {}", format_duration(*delay_secs)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeStatus {
+    Queued,
+    Applied,
+    Cancelled,
+    // Due, but refused when applied, such as a strategy since unregistered
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedChange {
+    pub id: u64,
+    pub change: AdminChange,
+    pub queued_at: u64,
+    // Applied at the first chance from then on
+    pub takes_effect_at: u64,
+    pub status: ChangeStatus,
+    pub settled_at: Option<u64>,
+    pub note: Option<String>,
+}
+
+// Admin changes waiting out their delay, and what became of earlier ones.
+// A queued change can be cancelled until it is applied; once due, it is
+// applied by the next vault command, or by watch or serve as they run.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Timelock {
+    changes: Vec<QueuedChange>,
+    next_id: u64,
+}
+
+impl Timelock {
+    pub fn queue(&mut self, change: AdminChange, now: u64, delay_secs: u64) -> &QueuedChange {
+        self.next_id += 1;
+        self.changes.push(QueuedChange {
+            id: self.next_id,
+            change,
+            queued_at: now,
+            takes_effect_at: now.saturating_add(delay_secs),
+            status: ChangeStatus::Queued,
+            settled_at: None,
+            note: None,
+        });
+        &self.changes[self.changes.len() - 1]
+    }
+
+    pub fn get(&self, id: u64) -> Option<&QueuedChange> {
+        self.changes.iter().find(|c| c.id == id)
+    }
+
+    pub fn pending(&self) -> impl Iterator<Item = &QueuedChange> {
+        self.changes.iter().filter(|c| c.status == ChangeStatus::Queued)
+    }
+
+    // Oldest first, so changes to the same setting land in the order queued
+    pub fn due(&self, now: u64) -> Vec<QueuedChange> {
+        self.pending().filter(|c| c.takes_effect_at <= now).cloned().collect()
+    }
+
+    pub fn cancel(&mut self, id: u64, now: u64) -> Result<QueuedChange, VaultError> {
+        let change = self.changes.iter_mut().find(|c| c.id == id)
+            .ok_or_else(|| VaultError::Validation(format!("No admin change #{}", id)))?;
+        if change.status != ChangeStatus::Queued {
+            return Err(VaultError::Validation(format!("Admin change #{} is already {:?}", id, change.status)));
+        }
+        change.status = ChangeStatus::Cancelled;
+        change.settled_at = Some(now);
+        Ok(change.clone())
+    }
+
+    // Records a due change as applied, or failed with the reason
+    pub fn settle(&mut self, id: u64, result: Result<(), String>, now: u64) {
+        let Some(change) = self.changes.iter_mut().find(|c| c.id == id && c.status == ChangeStatus::Queued) else {
+            return;
+        };
+        match result {
+            Ok(()) => change.status = ChangeStatus::Applied,
+            Err(reason) => {
+                change.status = ChangeStatus::Failed;
+                change.note = Some(reason);
+            }
+        }
+        change.settled_at = Some(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_wait_out_the_delay_unless_cancelled() {
+        let mut timelock = Timelock::default();
+        let fees = AdminChange::Fees { risk: RiskLevel::Low, management_fee_bps: 100, performance_fee_bps: 1_500 };
+        let fees = timelock.queue(fees, 1_000, 3_600).id;
+        let treasury = timelock.queue(AdminChange::Treasury { address: "GTREASURY".into() }, 1_100, 3_600).id;
+        assert_eq!(timelock.get(fees).unwrap().takes_effect_at, 4_600);
+
+        assert!(timelock.due(4_599).is_empty());
+        assert_eq!(timelock.due(4_700).iter().map(|c| c.id).collect::<Vec<_>>(), vec![fees, treasury]);
+
+        timelock.cancel(treasury, 4_650).unwrap();
+        assert!(timelock.cancel(treasury, 4_660).is_err());
+        timelock.settle(fees, Ok(()), 4_700);
+        assert!(timelock.cancel(fees, 4_710).is_err());
+        assert_eq!(timelock.get(fees).unwrap().status, ChangeStatus::Applied);
+        assert_eq!(timelock.pending().count(), 0);
+        assert!(timelock.due(10_000).is_empty());
+    }
+}
@@ -0,0 +1,529 @@
+use chacha20poly1305::aead::OsRng;
+use chacha20poly1305::aead::rand_core::RngCore;
+use ed25519_dalek::{Signer as _, SigningKey};
+use sha2::{Digest, Sha256};
+use stellar_xdr::curr::{
+    AccountId, Asset, BeginSponsoringFutureReservesOp, ClaimClaimableBalanceOp, ClaimPredicate,
+    ClaimableBalanceId, Claimant, ClaimantV0, CreateAccountOp, CreateClaimableBalanceOp,
+    DecoratedSignature, FeeBumpTransaction, FeeBumpTransactionEnvelope, FeeBumpTransactionExt,
+    FeeBumpTransactionInnerTx, Hash, InnerTransactionResultResult, LedgerBounds, Limits, Memo, MuxedAccount, MuxedAccountMed25519,
+    Operation, OperationBody, OperationResult, OperationResultTr, PathPaymentStrictSendOp, PathPaymentStrictSendResult,
+    PaymentOp, PublicKey, Preconditions, PreconditionsV2, ReadXdr, SequenceNumber, Signature, SignatureHint, SorobanTransactionData,
+    TimeBounds, TimePoint, Transaction,
+    TransactionEnvelope, TransactionExt, TransactionResult, TransactionResultResult, TransactionV1Envelope, Uint256, WriteXdr,
+};
+
+use crate::assets;
+use crate::error::VaultError;
+use crate::signer::Signer;
+
+// Minimum inclusion fee per operation, in stroops
+pub const BASE_FEE: u32 = 100;
+pub const MAX_OPERATIONS: usize = 100;
+// Every account holds 2 base reserves, plus one per subentry such as a trustline
+pub const BASE_RESERVE: u64 = 5_000_000;
+
+// ============================================================================
+// KEYS
+// ============================================================================
+
+pub struct Keypair {
+    signing_key: SigningKey,
+}
+
+impl Keypair {
+    pub fn from_secret(secret_key: &str) -> Result<Self, VaultError> {
+        let seed = stellar_strkey::ed25519::PrivateKey::from_string(secret_key)
+            .map_err(|_| "Invalid Stellar secret key")?;
+        Ok(Keypair {
+            signing_key: SigningKey::from_bytes(&seed.0),
+        })
+    }
+
+    pub fn from_seed(seed: &[u8; 32]) -> Self {
+        Keypair {
+            signing_key: SigningKey::from_bytes(seed),
+        }
+    }
+
+    // A fresh key from the operating system's randomness
+    pub fn random() -> Self {
+        let mut seed = [0u8; 32];
+        OsRng.fill_bytes(&mut seed);
+        Self::from_seed(&seed)
+    }
+
+    // S... strkey form
+    pub fn secret_key(&self) -> String {
+        stellar_strkey::ed25519::PrivateKey(self.signing_key.to_bytes()).to_string()
+    }
+
+    pub fn public_key_bytes(&self) -> [u8; 32] {
+        self.signing_key.verifying_key().to_bytes()
+    }
+
+    // G... strkey form
+    pub fn public_key(&self) -> String {
+        stellar_strkey::ed25519::PublicKey(self.public_key_bytes()).to_string()
+    }
+
+    // Signs a transaction hash; the hint is the last 4 bytes of the public key
+    pub fn sign_hash(&self, hash: &[u8; 32]) -> Result<DecoratedSignature, VaultError> {
+        let public_key = self.public_key_bytes();
+        let signature = self.signing_key.sign(hash);
+        Ok(DecoratedSignature {
+            hint: SignatureHint([public_key[28], public_key[29], public_key[30], public_key[31]]),
+            signature: Signature(signature.to_bytes().to_vec().try_into()?),
+        })
+    }
+}
+
+// G... accounts and M... muxed addresses, which pay into the same account
+pub fn text_memo(text: &str) -> Result<Memo, VaultError> {
+    let bytes = text.as_bytes().to_vec().try_into()
+        .map_err(|_| format!("Memo {:?} is longer than 28 bytes", text))?;
+    Ok(Memo::Text(bytes))
+}
+
+// For display; None when there is no memo
+pub fn describe_memo(memo: &Memo) -> Option<String> {
+    match memo {
+        Memo::None => None,
+        Memo::Text(text) => Some(String::from_utf8_lossy(text.as_slice()).into_owned()),
+        Memo::Id(id) => Some(format!("ID {}", id)),
+        Memo::Hash(hash) | Memo::Return(hash) => Some(format!("hash {}", hash.0.iter().map(|b| format!("{:02x}", b)).collect::<String>())),
+    }
+}
+
+pub fn account_id(address: &str) -> Result<AccountId, VaultError> {
+    let key = stellar_strkey::ed25519::PublicKey::from_string(address)
+        .map_err(|_| format!("Invalid Stellar account {} (expected G...)", address))?;
+    Ok(AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(key.0))))
+}
+
+// XLM needed to create an account that then opens `subentries` trustlines
+pub fn minimum_balance(subentries: u64) -> u64 {
+    (2 + subentries) * BASE_RESERVE
+}
+
+// Horizon's claimable balance IDs: the 4-byte type (0 for V0) then the hash, in hex
+pub fn parse_balance_id(id: &str) -> Result<ClaimableBalanceId, VaultError> {
+    let invalid = || format!("Invalid claimable balance ID {}", id);
+    let hex = id.strip_prefix("00000000").filter(|hex| hex.len() == 64).ok_or_else(invalid)?;
+    let mut hash = [0u8; 32];
+    for (i, byte) in hash.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2).ok_or_else(invalid)?, 16).map_err(|_| invalid())?;
+    }
+    Ok(ClaimableBalanceId::ClaimableBalanceIdTypeV0(Hash(hash)))
+}
+
+pub fn parse_account(address: &str) -> Result<MuxedAccount, VaultError> {
+    if let Ok(key) = stellar_strkey::ed25519::PublicKey::from_string(address) {
+        return Ok(MuxedAccount::Ed25519(Uint256(key.0)));
+    }
+    let muxed = stellar_strkey::ed25519::MuxedAccount::from_string(address)
+        .map_err(|_| format!("Invalid Stellar account {}", address))?;
+    Ok(MuxedAccount::MuxedEd25519(MuxedAccountMed25519 { id: muxed.id, ed25519: Uint256(muxed.ed25519) }))
+}
+
+// ============================================================================
+// BUILDING
+// ============================================================================
+
+pub struct TransactionBuilder {
+    source: MuxedAccount,
+    // Sequence number the transaction will consume (account sequence + 1)
+    sequence: i64,
+    fee_per_operation: u32,
+    memo: Memo,
+    time_bounds: Option<TimeBounds>,
+    ledger_bounds: Option<LedgerBounds>,
+    operations: Vec<Operation>,
+    // Footprint and resource fee of a contract call, from simulation
+    soroban_data: Option<SorobanTransactionData>,
+}
+
+impl TransactionBuilder {
+    pub fn new(source: &str, account_sequence: i64) -> Result<Self, VaultError> {
+        let sequence = account_sequence.checked_add(1)
+            .ok_or("Account sequence number is exhausted")?;
+        Ok(TransactionBuilder {
+            source: parse_account(source)?,
+            sequence,
+            fee_per_operation: BASE_FEE,
+            memo: Memo::None,
+            time_bounds: None,
+            ledger_bounds: None,
+            operations: Vec::new(),
+            soroban_data: None,
+        })
+    }
+
+    pub fn memo(mut self, memo: Memo) -> Self {
+        self.memo = memo;
+        self
+    }
+
+    // Inclusion fee bid per operation, in stroops
+    pub fn fee_per_operation(mut self, fee: u32) -> Self {
+        self.fee_per_operation = fee.max(BASE_FEE);
+        self
+    }
+
+    // Unix times the transaction is valid between; 0 leaves that side open
+    pub fn time_bounds(mut self, min_time: u64, max_time: u64) -> Result<Self, VaultError> {
+        if max_time != 0 && max_time < min_time {
+            return Err(format!("Time bounds end ({}) before they start ({})", max_time, min_time).into());
+        }
+        self.time_bounds = Some(TimeBounds {
+            min_time: TimePoint(min_time),
+            max_time: TimePoint(max_time),
+        });
+        Ok(self)
+    }
+
+    // Ledgers the transaction can close in; the maximum is exclusive and 0
+    // leaves it open. Unlike time bounds these don't depend on the local clock.
+    pub fn ledger_bounds(mut self, min_ledger: u32, max_ledger: u32) -> Result<Self, VaultError> {
+        if max_ledger != 0 && max_ledger <= min_ledger {
+            return Err(format!("Ledger bounds end ({}) before they start ({})", max_ledger, min_ledger).into());
+        }
+        self.ledger_bounds = Some(LedgerBounds { min_ledger, max_ledger });
+        Ok(self)
+    }
+
+    // Resources a contract call was simulated to need; its resource fee is
+    // paid on top of the inclusion fee
+    pub fn soroban_data(mut self, data: SorobanTransactionData) -> Self {
+        self.soroban_data = Some(data);
+        self
+    }
+
+    pub fn operation(mut self, body: OperationBody) -> Result<Self, VaultError> {
+        if self.operations.len() >= MAX_OPERATIONS {
+            return Err(format!("A transaction holds at most {} operations", MAX_OPERATIONS).into());
+        }
+        self.operations.push(Operation {
+            source_account: None,
+            body,
+        });
+        Ok(self)
+    }
+
+    // An operation on behalf of another account, which has to sign as well
+    pub fn operation_for(mut self, source: &str, body: OperationBody) -> Result<Self, VaultError> {
+        let source = parse_account(source)?;
+        self = self.operation(body)?;
+        if let Some(operation) = self.operations.last_mut() {
+            operation.source_account = Some(source);
+        }
+        Ok(self)
+    }
+
+    pub fn create_account(self, destination: &str, starting_balance_stroops: u64) -> Result<Self, VaultError> {
+        let starting_balance = i64::try_from(starting_balance_stroops)
+            .map_err(|_| format!("Starting balance of {} stroops is too large", starting_balance_stroops))?;
+        self.operation(OperationBody::CreateAccount(CreateAccountOp {
+            destination: account_id(destination)?,
+            starting_balance,
+        }))
+    }
+
+    // Until the matching end_sponsoring, reserves for entries `sponsored`
+    // creates (its account, trustlines) are held by the transaction source
+    pub fn begin_sponsoring(self, sponsored: &str) -> Result<Self, VaultError> {
+        self.operation(OperationBody::BeginSponsoringFutureReserves(BeginSponsoringFutureReservesOp {
+            sponsored_id: account_id(sponsored)?,
+        }))
+    }
+
+    // Run by the sponsored account, which thereby accepts the sponsorship
+    pub fn end_sponsoring(self, sponsored: &str) -> Result<Self, VaultError> {
+        self.operation_for(sponsored, OperationBody::EndSponsoringFutureReserves)
+    }
+
+    // Opens an unlimited trustline from `account` to a credit asset
+    pub fn trust(self, account: &str, asset: Asset) -> Result<Self, VaultError> {
+        self.operation_for(account, assets::change_trust(&asset, assets::UNLIMITED)?)
+    }
+
+    // Parks `amount_stroops` for `claimant` to take whenever they like; the
+    // source holds one base reserve until it is claimed
+    pub fn claimable_balance(self, claimant: &str, asset: Asset, amount_stroops: u64) -> Result<Self, VaultError> {
+        if amount_stroops == 0 {
+            return Err("Claimable balance must be greater than zero".into());
+        }
+        let amount = i64::try_from(amount_stroops)
+            .map_err(|_| format!("Claimable balance of {} stroops is too large", amount_stroops))?;
+        let claimant = Claimant::ClaimantTypeV0(ClaimantV0 {
+            destination: account_id(claimant)?,
+            predicate: ClaimPredicate::Unconditional,
+        });
+        self.operation(OperationBody::CreateClaimableBalance(CreateClaimableBalanceOp {
+            asset,
+            amount,
+            claimants: vec![claimant].try_into()?,
+        }))
+    }
+
+    pub fn claim_balance(self, balance_id: &str) -> Result<Self, VaultError> {
+        self.operation(OperationBody::ClaimClaimableBalance(ClaimClaimableBalanceOp {
+            balance_id: parse_balance_id(balance_id)?,
+        }))
+    }
+
+    pub fn payment(self, destination: &str, asset: Asset, amount_stroops: u64) -> Result<Self, VaultError> {
+        if amount_stroops == 0 {
+            return Err("Payment amount must be greater than zero".into());
+        }
+        let amount = i64::try_from(amount_stroops)
+            .map_err(|_| format!("Payment of {} stroops is too large", amount_stroops))?;
+        self.operation(OperationBody::Payment(PaymentOp {
+            destination: parse_account(destination)?,
+            asset,
+            amount,
+        }))
+    }
+
+    // Sends exactly `send_stroops` of one asset and delivers at least
+    // `dest_min_stroops` of another, trading through `path` on the DEX
+    pub fn path_payment_strict_send(self, destination: &str, send_asset: Asset, send_stroops: u64,
+                                    dest_asset: Asset, dest_min_stroops: u64, path: Vec<Asset>) -> Result<Self, VaultError> {
+        self.operation(path_payment_strict_send(destination, send_asset, send_stroops, dest_asset, dest_min_stroops, path)?)
+    }
+
+    pub fn build(self) -> Result<Transaction, VaultError> {
+        if self.operations.is_empty() {
+            return Err("A transaction needs at least one operation".into());
+        }
+        if self.soroban_data.is_some() && self.operations.len() != 1 {
+            return Err("A contract call must be the transaction's only operation".into());
+        }
+        let resource_fee = match &self.soroban_data {
+            Some(data) => u32::try_from(data.resource_fee).map_err(|_| "Contract resource fee is out of range")?,
+            None => 0,
+        };
+        let fee = self.fee_per_operation.checked_mul(self.operations.len() as u32)
+            .and_then(|fee| fee.checked_add(resource_fee))
+            .ok_or("Transaction fee overflows")?;
+
+        Ok(Transaction {
+            source_account: self.source,
+            fee,
+            seq_num: SequenceNumber(self.sequence),
+            cond: match (self.time_bounds, self.ledger_bounds) {
+                (None, None) => Preconditions::None,
+                (Some(time_bounds), None) => Preconditions::Time(time_bounds),
+                (time_bounds, ledger_bounds) => Preconditions::V2(PreconditionsV2 {
+                    time_bounds,
+                    ledger_bounds,
+                    min_seq_num: None,
+                    min_seq_age: stellar_xdr::curr::Duration(0),
+                    min_seq_ledger_gap: 0,
+                    extra_signers: Default::default(),
+                }),
+            },
+            memo: self.memo,
+            operations: self.operations.try_into()?,
+            ext: match self.soroban_data {
+                Some(data) => TransactionExt::V1(data),
+                None => TransactionExt::V0,
+            },
+        })
+    }
+}
+
+// Unix time after which the transaction can no longer be included, if bounded
+pub fn valid_until(transaction: &Transaction) -> Option<u64> {
+    let time_bounds = match &transaction.cond {
+        Preconditions::Time(bounds) => Some(bounds),
+        Preconditions::V2(conditions) => conditions.time_bounds.as_ref(),
+        Preconditions::None => None,
+    };
+    time_bounds.map(|b| b.max_time.0).filter(|&max_time| max_time != 0)
+}
+
+// ============================================================================
+// SIGNING
+// ============================================================================
+
+pub fn network_id(passphrase: &str) -> [u8; 32] {
+    Sha256::digest(passphrase.as_bytes()).into()
+}
+
+// Hex transaction hash, as Horizon reports it
+pub fn hash_hex(transaction: &Transaction, passphrase: &str) -> Result<String, VaultError> {
+    let hash = transaction.hash(network_id(passphrase))?;
+    Ok(hash.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+pub fn sign(transaction: Transaction, passphrase: &str, signers: &[&dyn Signer]) -> Result<TransactionEnvelope, VaultError> {
+    let signatures = signers.iter()
+        .map(|signer| signer.sign_transaction(&transaction, passphrase))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(TransactionEnvelope::Tx(TransactionV1Envelope {
+        tx: transaction,
+        signatures: signatures.try_into()?,
+    }))
+}
+
+// ============================================================================
+// FEE BUMPS
+// ============================================================================
+
+// Wraps a signed transaction so `fee_source` pays a higher fee. The inner
+// transaction keeps its hash and signatures, so it still applies at most once.
+pub fn fee_bump(inner: &TransactionEnvelope, fee_source: &str, fee_per_operation: u32) -> Result<FeeBumpTransaction, VaultError> {
+    let TransactionEnvelope::Tx(inner) = inner else {
+        return Err("Only signed v1 transactions can be fee-bumped".into());
+    };
+    // The bump counts as an extra operation, and its rate can't be below the inner one
+    let operations = inner.tx.operations.len() as u64;
+    let rate = (fee_per_operation as u64).max((inner.tx.fee as u64).div_ceil(operations));
+    let fee = rate.checked_mul(operations + 1)
+        .and_then(|fee| i64::try_from(fee).ok())
+        .ok_or("Fee bump fee overflows")?;
+
+    Ok(FeeBumpTransaction {
+        fee_source: parse_account(fee_source)?,
+        fee,
+        inner_tx: FeeBumpTransactionInnerTx::Tx(inner.clone()),
+        ext: FeeBumpTransactionExt::V0,
+    })
+}
+
+pub fn sign_fee_bump(transaction: FeeBumpTransaction, passphrase: &str, signers: &[&Keypair]) -> Result<TransactionEnvelope, VaultError> {
+    let hash = transaction.hash(network_id(passphrase))?;
+    let signatures = signers.iter()
+        .map(|signer| signer.sign_hash(&hash))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(TransactionEnvelope::TxFeeBump(FeeBumpTransactionEnvelope {
+        tx: transaction,
+        signatures: signatures.try_into()?,
+    }))
+}
+
+pub fn path_payment_strict_send(destination: &str, send_asset: Asset, send_stroops: u64,
+                                dest_asset: Asset, dest_min_stroops: u64, path: Vec<Asset>) -> Result<OperationBody, VaultError> {
+    if send_stroops == 0 || dest_min_stroops == 0 {
+        return Err("Path payment amounts must be greater than zero".into());
+    }
+    let send_amount = i64::try_from(send_stroops)
+        .map_err(|_| format!("Path payment of {} stroops is too large", send_stroops))?;
+    let dest_min = i64::try_from(dest_min_stroops)
+        .map_err(|_| format!("Minimum of {} stroops is too large", dest_min_stroops))?;
+    Ok(OperationBody::PathPaymentStrictSend(PathPaymentStrictSendOp {
+        send_asset,
+        send_amount,
+        destination: parse_account(destination)?,
+        dest_asset,
+        dest_min,
+        path: path.try_into().map_err(|_| "A payment path can trade through at most 5 assets")?,
+    }))
+}
+
+// What the first strict-send path payment in a successful transaction
+// delivered, read from Horizon's result_xdr
+pub fn path_payment_received(result_xdr: &str) -> Result<u64, VaultError> {
+    let results = match TransactionResult::from_xdr_base64(result_xdr, Limits::none())?.result {
+        TransactionResultResult::TxSuccess(results) => results,
+        TransactionResultResult::TxFeeBumpInnerSuccess(inner) => match inner.result.result {
+            InnerTransactionResultResult::TxSuccess(results) => results,
+            _ => return Err("Fee-bumped transaction did not succeed".into()),
+        },
+        _ => return Err("Transaction did not succeed".into()),
+    };
+    results.iter().find_map(|result| match result {
+        OperationResult::OpInner(OperationResultTr::PathPaymentStrictSend(PathPaymentStrictSendResult::Success(success))) =>
+            u64::try_from(success.last.amount).ok(),
+        _ => None,
+    }).ok_or_else(|| "Transaction has no path payment result".into())
+}
+
+pub fn to_base64(envelope: &TransactionEnvelope) -> Result<String, VaultError> {
+    Ok(envelope.to_xdr_base64(Limits::none())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "SBGWSG6BTNCKCOB3DIFBGCVMUPQFYPA2G4O34RMTB343OYPXU5DJDVMN";
+    const DESTINATION: &str = "GCZEAWUJY3BRHCOKU6C5WRLCF5RFSGY22UGBPBXWL4T4G4SSEQMIYMCX";
+
+    #[test]
+    fn fee_bump_keeps_the_inner_transaction() {
+        let keypair = Keypair::from_secret(SECRET).unwrap();
+        let passphrase = "Test SDF Network ; September 2015";
+        let tx = TransactionBuilder::new(&keypair.public_key(), 41).unwrap()
+            .fee_per_operation(200)
+            .payment(DESTINATION, Asset::Native, 10_000_000).unwrap()
+            .build().unwrap();
+        let inner_hash = hash_hex(&tx, passphrase).unwrap();
+        let inner = sign(tx, passphrase, &[&keypair]).unwrap();
+
+        // One payment plus the bump itself, at the higher of the two rates
+        let bump = fee_bump(&inner, DESTINATION, 150).unwrap();
+        assert_eq!(bump.fee, 400);
+        let bump = fee_bump(&inner, DESTINATION, 5_000).unwrap();
+        assert_eq!(bump.fee, 10_000);
+
+        let FeeBumpTransactionInnerTx::Tx(wrapped) = &bump.inner_tx;
+        assert_eq!(wrapped.tx.seq_num, SequenceNumber(42));
+        assert_eq!(hash_hex(&wrapped.tx, passphrase).unwrap(), inner_hash);
+        assert!(matches!(sign_fee_bump(bump, passphrase, &[&keypair]).unwrap(), TransactionEnvelope::TxFeeBump(_)));
+    }
+
+    #[test]
+    fn trustlines_for_a_new_account_run_as_that_account() {
+        let operator = Keypair::from_secret(SECRET).unwrap().public_key();
+        let share = assets::AssetId::new("SYIAMED", &operator).unwrap().to_xdr().unwrap();
+
+        let tx = TransactionBuilder::new(&operator, 1).unwrap()
+            .create_account(DESTINATION, minimum_balance(1)).unwrap()
+            .trust(DESTINATION, share).unwrap()
+            .build().unwrap();
+        assert_eq!(tx.operations[0].source_account, None);
+        assert_eq!(tx.operations[1].source_account, Some(parse_account(DESTINATION).unwrap()));
+        assert_eq!(minimum_balance(1), 15_000_000);
+
+        let sponsored = TransactionBuilder::new(&operator, 1).unwrap()
+            .begin_sponsoring(DESTINATION).unwrap()
+            .create_account(DESTINATION, 0).unwrap()
+            .end_sponsoring(DESTINATION).unwrap()
+            .build().unwrap();
+        assert_eq!(sponsored.operations[0].source_account, None);
+        assert_eq!(sponsored.operations[2].source_account, Some(parse_account(DESTINATION).unwrap()));
+    }
+
+    #[test]
+    fn balance_ids_parse_from_horizon_hex() {
+        let id = format!("00000000{}", "ab".repeat(32));
+        assert_eq!(parse_balance_id(&id).unwrap(), ClaimableBalanceId::ClaimableBalanceIdTypeV0(Hash([0xab; 32])));
+        assert!(parse_balance_id(&"ab".repeat(36)).is_err());
+        assert!(parse_balance_id(&format!("00000000{}", "zz".repeat(32))).is_err());
+    }
+
+    #[test]
+    fn ledger_bounds_use_v2_preconditions() {
+        let source = Keypair::from_secret(SECRET).unwrap().public_key();
+        let builder = || TransactionBuilder::new(&source, 1).unwrap()
+            .payment(DESTINATION, Asset::Native, 1).unwrap();
+
+        let timed = builder().time_bounds(0, 1_700_000_300).unwrap().build().unwrap();
+        assert!(matches!(timed.cond, Preconditions::Time(_)));
+        assert_eq!(valid_until(&timed), Some(1_700_000_300));
+
+        let both = builder().time_bounds(0, 1_700_000_300).unwrap()
+            .ledger_bounds(0, 5_060).unwrap()
+            .build().unwrap();
+        let Preconditions::V2(conditions) = &both.cond else { panic!("expected v2 preconditions") };
+        assert_eq!(conditions.ledger_bounds.as_ref().map(|b| b.max_ledger), Some(5_060));
+        assert_eq!(valid_until(&both), Some(1_700_000_300));
+
+        assert!(builder().time_bounds(200, 100).is_err());
+        assert!(builder().ledger_bounds(10, 10).is_err());
+    }
+}
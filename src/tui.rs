@@ -0,0 +1,344 @@
+use std::time::{Duration, Instant};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Clear, List, ListItem, Paragraph, Row, Table, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::amount::{Stroops, PRICE_SCALE};
+use crate::error::VaultError;
+use crate::horizon::Payment;
+use crate::vault::WithdrawalOutcome;
+use crate::{output, strategy, RiskLevel, StellarVault};
+
+// How often the wallet balance and payments are reloaded from Horizon
+const REFRESH_SECS: u64 = 15;
+// Input is checked this often, so the loop can notice a due refresh
+const POLL_MILLIS: u64 = 250;
+const RECENT_PAYMENTS: u32 = 10;
+// Lines of activity kept for the log panel
+const ACTIVITY_LINES: usize = 200;
+
+// ============================================================================
+// DASHBOARD STATE
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Deposit,
+    Withdraw,
+}
+
+// The deposit or withdraw form: a vault and an amount in XLM, or shares
+struct Form {
+    action: Action,
+    risk: RiskLevel,
+    input: String,
+}
+
+struct Dashboard {
+    user: String,
+    balance: Option<String>,
+    payments: Vec<Payment>,
+    activity: Vec<String>,
+    status: String,
+    form: Option<Form>,
+    refreshed: Option<Instant>,
+}
+
+impl Dashboard {
+    fn log(&mut self, lines: impl IntoIterator<Item = String>) {
+        for line in lines {
+            // Messages are written for a terminal: blank lines and banners
+            // between sections don't belong in a panel
+            let line = line.trim().to_string();
+            if !line.is_empty() && !line.starts_with("===") {
+                self.activity.push(line);
+            }
+        }
+        let excess = self.activity.len().saturating_sub(ACTIVITY_LINES);
+        self.activity.drain(..excess);
+    }
+
+    async fn refresh(&mut self, vault: &StellarVault) {
+        self.balance = match vault.users.get(&self.user) {
+            Ok(client) => client.get_balance().await.ok().map(|balance| format!("{:.2} XLM", balance)),
+            Err(_) => None,
+        };
+        match vault.horizon.payments(&self.user, RECENT_PAYMENTS).await {
+            Ok(payments) => self.payments = payments,
+            Err(e) => self.status = format!("Could not load payments: {}", e),
+        }
+        self.refreshed = Some(Instant::now());
+    }
+
+    fn refresh_due(&self) -> bool {
+        self.refreshed.is_none_or(|at| at.elapsed() >= Duration::from_secs(REFRESH_SECS))
+    }
+}
+
+// ============================================================================
+// EVENT LOOP
+// ============================================================================
+
+/// Runs the full-screen dashboard for `user` until they quit. Messages the
+/// vault would print go to its activity panel instead.
+pub async fn run(vault: &mut StellarVault, user: &str) -> Result<(), VaultError> {
+    let mut terminal = ratatui::init();
+    output::capture(true);
+    let result = event_loop(&mut terminal, vault, user).await;
+    output::capture(false);
+    ratatui::restore();
+    result
+}
+
+async fn event_loop(terminal: &mut DefaultTerminal, vault: &mut StellarVault, user: &str) -> Result<(), VaultError> {
+    let mut dashboard = Dashboard {
+        user: user.to_string(),
+        balance: None,
+        payments: Vec::new(),
+        activity: Vec::new(),
+        status: "d deposit · w withdraw · r refresh · q quit".to_string(),
+        form: None,
+        refreshed: None,
+    };
+
+    loop {
+        if dashboard.refresh_due() {
+            dashboard.refresh(vault).await;
+        }
+        dashboard.log(output::take_captured());
+        terminal.draw(|frame| draw(frame, vault, &dashboard))?;
+
+        if !event::poll(Duration::from_millis(POLL_MILLIS))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match dashboard.form.as_mut() {
+            None => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('r') => {
+                    dashboard.refresh(vault).await;
+                    dashboard.status = "Refreshed".to_string();
+                }
+                KeyCode::Char(c @ ('d' | 'w')) => {
+                    // The client asks for a typed confirmation on mainnet,
+                    // which a raw-mode terminal can't take
+                    if vault.network.is_mainnet() {
+                        dashboard.status = "On mainnet, use the deposit and withdraw commands, which confirm each transaction".to_string();
+                    } else {
+                        let action = if c == 'd' { Action::Deposit } else { Action::Withdraw };
+                        dashboard.form = Some(Form { action, risk: RiskLevel::Low, input: String::new() });
+                    }
+                }
+                _ => {}
+            },
+            Some(form) => match key.code {
+                KeyCode::Esc => dashboard.form = None,
+                KeyCode::Tab | KeyCode::Right => form.risk = next_risk(form.risk),
+                KeyCode::BackTab | KeyCode::Left => {
+                    form.risk = next_risk(next_risk(form.risk));
+                }
+                KeyCode::Backspace => {
+                    form.input.pop();
+                }
+                KeyCode::Char(c) if c.is_ascii_digit() || (c == '.' && form.action == Action::Deposit) => form.input.push(c),
+                KeyCode::Enter => {
+                    if let Some(form) = dashboard.form.take() {
+                        dashboard.status = submit(vault, user, &form).await;
+                        dashboard.refresh(vault).await;
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
+}
+
+fn next_risk(risk: RiskLevel) -> RiskLevel {
+    match risk {
+        RiskLevel::Low => RiskLevel::Medium,
+        RiskLevel::Medium => RiskLevel::High,
+        RiskLevel::High => RiskLevel::Low,
+    }
+}
+
+// Runs the form's deposit or withdrawal, returning the line for the status bar
+async fn submit(vault: &mut StellarVault, user: &str, form: &Form) -> String {
+    match form.action {
+        Action::Deposit => {
+            let amount = match Stroops::from_xlm_str(&form.input) {
+                Ok(amount) if amount > Stroops::ZERO => amount,
+                _ => return format!("Not an amount: {:?}", form.input),
+            };
+            match vault.deposit(user, form.risk, amount.0, None).await {
+                Ok((shares, credited, receipt)) => format!("✅ Deposited {} into the {:?} Risk Vault for {} shares ({})",
+                    credited.to_xlm_string(), form.risk, shares, receipt.hash),
+                Err(e) => format!("❌ Deposit failed: {}", e),
+            }
+        }
+        Action::Withdraw => {
+            let shares = match form.input.parse::<u64>() {
+                Ok(shares) if shares > 0 => shares,
+                _ => return format!("Not a share count: {:?}", form.input),
+            };
+            match vault.withdraw(user, form.risk, shares).await {
+                Ok(WithdrawalOutcome::Completed(receipt)) => format!("✅ Withdrew {} from the {:?} Risk Vault ({})",
+                    Stroops(receipt.net_stroops).to_xlm_string(), form.risk, receipt.transaction.hash),
+                Ok(WithdrawalOutcome::Queued { id, ahead }) => format!("⏳ Withdrawal queued as request #{} ({} ahead)", id, ahead),
+                Err(e) => format!("❌ Withdrawal failed: {}", e),
+            }
+        }
+    }
+}
+
+// ============================================================================
+// RENDERING
+// ============================================================================
+
+fn xlm(stroops: u64) -> String {
+    Stroops(stroops).to_xlm_string()
+}
+
+fn percent(bps: u16) -> String {
+    format!("{:.2}%", bps as f64 / 100.0)
+}
+
+fn draw(frame: &mut Frame, vault: &StellarVault, dashboard: &Dashboard) {
+    let [header, top, middle, bottom, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(6),
+        Constraint::Min(6),
+        Constraint::Min(6),
+        Constraint::Length(1),
+    ]).areas(frame.area());
+    let [positions_area, strategies_area] = Layout::horizontal([Constraint::Percentage(50); 2]).areas(middle);
+    let [payments_area, activity_area] = Layout::horizontal([Constraint::Percentage(50); 2]).areas(bottom);
+
+    let title = format!(" StellarVault (SYIA) · {} · {} · {} ", vault.network, dashboard.user,
+        dashboard.balance.as_deref().unwrap_or("balance unavailable"));
+    frame.render_widget(Line::from(title).bold(), header);
+
+    draw_vaults(frame, vault, top);
+    draw_positions(frame, vault, dashboard, positions_area);
+    draw_strategies(frame, vault, strategies_area);
+    draw_payments(frame, dashboard, payments_area);
+
+    let activity: Vec<ListItem> = dashboard.activity.iter().rev()
+        .take(activity_area.height as usize)
+        .rev()
+        .map(|line| ListItem::new(line.as_str()))
+        .collect();
+    frame.render_widget(List::new(activity).block(Block::bordered().title(" Activity ")), activity_area);
+
+    frame.render_widget(Line::from(dashboard.status.as_str()).style(Style::new().fg(Color::Cyan)), footer);
+
+    if let Some(form) = &dashboard.form {
+        draw_form(frame, form);
+    }
+}
+
+fn draw_vaults(frame: &mut Frame, vault: &StellarVault, area: Rect) {
+    let rows = RiskLevel::ALL.into_iter().filter_map(|risk| vault.get_vault_info(risk)).map(|info| {
+        Row::new(vec![
+            format!("{:?}", info.risk_level),
+            format!("{} {}", xlm(info.total_value), info.asset_code()),
+            format!("{:.7}", info.get_share_price() as f64 / PRICE_SCALE as f64),
+            percent(info.blended_apy()),
+            percent(info.insurance_fee),
+            if info.circuit.paused { "paused".to_string() } else { "open".to_string() },
+        ])
+    });
+    let table = Table::new(rows, [
+        Constraint::Length(8),
+        Constraint::Min(18),
+        Constraint::Length(12),
+        Constraint::Length(8),
+        Constraint::Length(10),
+        Constraint::Length(8),
+    ])
+        .header(Row::new(["Vault", "TVL", "Share Price", "APY", "Insurance", "Status"]).add_modifier(Modifier::BOLD))
+        .block(Block::bordered().title(" Vaults "));
+    frame.render_widget(table, area);
+}
+
+fn draw_positions(frame: &mut Frame, vault: &StellarVault, dashboard: &Dashboard, area: Rect) {
+    let portfolio = vault.portfolio(&dashboard.user);
+    let rows = portfolio.positions.iter().map(|position| {
+        Row::new(vec![
+            format!("{:?}", position.risk),
+            position.shares.to_string(),
+            xlm(position.value_stroops),
+            xlm(position.accumulated_yield),
+        ])
+    });
+    let title = format!(" Your Positions · {} total ", xlm(portfolio.total_value));
+    let table = Table::new(rows, [Constraint::Length(8), Constraint::Min(12), Constraint::Min(14), Constraint::Min(12)])
+        .header(Row::new(["Vault", "Shares", "Value", "Yield"]).add_modifier(Modifier::BOLD))
+        .block(Block::bordered().title(title));
+    frame.render_widget(table, area);
+}
+
+fn draw_strategies(frame: &mut Frame, vault: &StellarVault, area: Rect) {
+    let rows = RiskLevel::ALL.into_iter().filter_map(|risk| vault.get_vault_info(risk)).flat_map(|info| {
+        info.strategies.iter().map(move |allocation| {
+            let apy = strategy::lookup(&allocation.strategy).current_apy(allocation);
+            Row::new(vec![
+                format!("{:?}", info.risk_level),
+                allocation.strategy.clone(),
+                format!("{}%", allocation.allocation_percentage),
+                percent(apy),
+                xlm(allocation.total_allocated),
+            ])
+        })
+    });
+    let table = Table::new(rows, [
+        Constraint::Length(8),
+        Constraint::Min(18),
+        Constraint::Length(6),
+        Constraint::Length(8),
+        Constraint::Min(12),
+    ])
+        .header(Row::new(["Vault", "Strategy", "Share", "APY", "Allocated"]).add_modifier(Modifier::BOLD))
+        .block(Block::bordered().title(" Strategy Allocations "));
+    frame.render_widget(table, area);
+}
+
+fn draw_payments(frame: &mut Frame, dashboard: &Dashboard, area: Rect) {
+    let rows = dashboard.payments.iter().map(|payment| {
+        let incoming = payment.to.as_deref() == Some(dashboard.user.as_str());
+        Row::new(vec![
+            if incoming { "in" } else { "out" }.to_string(),
+            format!("{} {}", payment.amount.as_deref().unwrap_or("-"), payment.asset_code.as_deref().unwrap_or("XLM")),
+            payment.transaction_hash.chars().take(12).collect(),
+        ])
+    });
+    let table = Table::new(rows, [Constraint::Length(4), Constraint::Min(18), Constraint::Length(13)])
+        .header(Row::new(["", "Amount", "Transaction"]).add_modifier(Modifier::BOLD))
+        .block(Block::bordered().title(" Recent Transactions "));
+    frame.render_widget(table, area);
+}
+
+fn draw_form(frame: &mut Frame, form: &Form) {
+    let [area] = Layout::horizontal([Constraint::Length(52)]).flex(Flex::Center).areas(frame.area());
+    let [area] = Layout::vertical([Constraint::Length(7)]).flex(Flex::Center).areas(area);
+    let (title, unit) = match form.action {
+        Action::Deposit => (" Deposit ", "Amount"),
+        Action::Withdraw => (" Withdraw ", "Shares"),
+    };
+    let text = vec![
+        Line::from(format!("Vault: ◀ {:?} Risk ▶  (Tab to change)", form.risk)),
+        Line::from(format!("{}: {}▏", unit, form.input)),
+        Line::from(""),
+        Line::from("Enter to submit · Esc to cancel").style(Style::new().fg(Color::DarkGray)),
+    ];
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(text).wrap(Wrap { trim: false }).block(Block::bordered().title(title)), area);
+}
@@ -0,0 +1,450 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{amm, blend, sdex, soroswap, strategy};
+use crate::accounting::SharePool;
+use crate::amount::{MathError, SharePrice, Shares, Stroops};
+use crate::assets::AssetId;
+use crate::circuit_breaker::CircuitBreaker;
+use crate::error::VaultError;
+use crate::fees::{FeeAccrual, FeeConfig};
+use crate::insurance::PremiumBounds;
+use crate::client::TransactionPreview;
+use crate::TransactionReceipt;
+
+// ============================================================================
+// ENUMS & STRUCTS
+// ============================================================================
+
+/// One of the three vault tiers. Each has its own strategies, share price
+/// and insurance premium.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RiskLevel {
+    Low,
+    Medium,
+    High,
+}
+
+impl RiskLevel {
+    pub const ALL: [RiskLevel; 3] = [RiskLevel::Low, RiskLevel::Medium, RiskLevel::High];
+}
+
+/// A strategy's slice of a vault: the percentage of its funds it holds, the
+/// yield it is expected to earn and what it currently has allocated.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategyAllocation {
+    // Id of the registered Strategy that runs it
+    #[serde(alias = "strategy_type")]
+    pub strategy: String,
+    pub allocation_percentage: u8,
+    pub current_apy: u16,
+    pub total_allocated: u64,
+    pub current_yield: u64,
+}
+
+/// A single risk tier: its asset, strategies, share supply and assets under
+/// management, fees and deposit limits.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Vault {
+    pub risk_level: RiskLevel,
+    pub total_value: u64,
+    pub total_shares: u64,
+    pub insurance_fee: u16,
+    // Share of incoming funds kept liquid for instant withdrawals, in basis points
+    #[serde(default)]
+    pub liquidity_buffer_bps: u16,
+    #[serde(default)]
+    pub liquid_reserve: u64,
+    // When set, insurance_fee is scaled within these bounds to track coverage
+    #[serde(default)]
+    pub premium_bounds: Option<PremiumBounds>,
+    pub strategies: Vec<StrategyAllocation>,
+    #[serde(default)]
+    pub last_harvest: u64,
+    // Exposure limits, all in stroops; None means uncapped
+    #[serde(default)]
+    pub max_tvl: Option<u64>,
+    #[serde(default)]
+    pub per_user_cap: Option<u64>,
+    // The same limits in USD (7 decimals), checked at the oracle price
+    #[serde(default)]
+    pub max_tvl_usd: Option<u64>,
+    #[serde(default)]
+    pub per_user_cap_usd: Option<u64>,
+    #[serde(default)]
+    pub min_deposit: u64,
+    // Shares withdrawn within lockup_secs of the deposit that bought them pay a penalty to the insurance pool
+    #[serde(default)]
+    pub lockup_secs: u64,
+    #[serde(default)]
+    pub early_withdrawal_penalty: u16,
+    #[serde(default)]
+    pub fees: FeeConfig,
+    #[serde(default)]
+    pub fee_accrual: FeeAccrual,
+    #[serde(default)]
+    pub circuit: CircuitBreaker,
+    // What the vault takes deposits in and pays out; None is XLM. Amounts
+    // are in the asset's units, which share XLM's 7 decimal places.
+    #[serde(default)]
+    pub asset: Option<AssetId>,
+    // Settings and fills for the SdexMarketMaking strategy, when it runs
+    #[serde(default)]
+    pub market_making: Option<sdex::MarketMaker>,
+    // Stake in the on-chain pool the AquaLiquidityPool strategy deposits into
+    #[serde(default)]
+    pub liquidity_pool: Option<amm::PoolPosition>,
+    // What the YieldBloxLending strategy has lent to a Blend pool
+    #[serde(default)]
+    pub lending: Option<blend::LendingPosition>,
+    // Liquidity the SoroswapLiquidity strategy holds, when it runs
+    #[serde(default)]
+    pub soroswap: Option<soroswap::SoroswapPosition>,
+}
+
+impl Vault {
+    pub fn xdr_asset(&self) -> Result<stellar_xdr::curr::Asset, VaultError> {
+        match &self.asset {
+            Some(asset) => asset.to_xdr(),
+            None => Ok(stellar_xdr::curr::Asset::Native),
+        }
+    }
+
+    pub fn asset_code(&self) -> &str {
+        self.asset.as_ref().map_or("XLM", |asset| asset.code.as_str())
+    }
+
+    pub fn pool(&self) -> SharePool {
+        SharePool {
+            total_value: Stroops(self.total_value),
+            total_shares: Shares(self.total_shares),
+        }
+    }
+
+    pub fn set_pool(&mut self, pool: SharePool) {
+        self.total_value = pool.total_value.0;
+        self.total_shares = pool.total_shares.0;
+    }
+
+    // Raw price for display and the circuit breaker
+    pub fn get_share_price(&self) -> u64 {
+        self.pool().share_price().unwrap_or(SharePrice::INITIAL).0
+    }
+
+    // Keeps the buffer share of incoming stroops liquid and splits the rest
+    // across strategies by target allocation. Allocations are bookkeeping
+    // inside total_value, which callers have already checked, so they saturate.
+    pub fn allocate(&mut self, amount_stroops: u64) {
+        let buffer = (amount_stroops as u128 * self.liquidity_buffer_bps as u128 / 10_000) as u64;
+        self.liquid_reserve = self.liquid_reserve.saturating_add(buffer);
+        let invested = amount_stroops - buffer;
+        for strategy in &mut self.strategies {
+            let alloc = (invested as u128 * strategy.allocation_percentage as u128 / 100) as u64;
+            strategy::lookup(&strategy.strategy).allocate(strategy, alloc);
+        }
+    }
+
+    // Pays outgoing stroops from the liquid reserve first, then from strategies
+    pub fn deallocate(&mut self, amount_stroops: u64) {
+        let from_reserve = amount_stroops.min(self.liquid_reserve);
+        self.liquid_reserve -= from_reserve;
+        let remaining = amount_stroops - from_reserve;
+        for strategy in &mut self.strategies {
+            let dealloc = (remaining as u128 * strategy.allocation_percentage as u128 / 100) as u64;
+            strategy::lookup(&strategy.strategy).withdraw(strategy, dealloc);
+        }
+    }
+
+    // Unwinds strategy allocation into the liquid reserve until it holds at
+    // least target_stroops, returning how much was freed
+    pub fn free_liquidity(&mut self, target_stroops: u64) -> u64 {
+        let needed = target_stroops.saturating_sub(self.liquid_reserve);
+        let allocated: u64 = self.strategies.iter().map(|s| s.total_allocated).sum();
+        if needed == 0 || allocated == 0 {
+            return 0;
+        }
+
+        let mut freed = 0;
+        for strategy in &mut self.strategies {
+            let take = (needed as u128 * strategy.total_allocated as u128).div_ceil(allocated as u128) as u64;
+            freed += strategy::lookup(&strategy.strategy).withdraw(strategy, take);
+        }
+        self.liquid_reserve += freed;
+        freed
+    }
+
+    // Mints shares for stroops entering the vault at the current share price
+    pub fn mint(&mut self, amount_stroops: u64) -> Result<u64, MathError> {
+        let mut pool = self.pool();
+        let shares = pool.mint(Stroops(amount_stroops))?;
+        self.set_pool(pool);
+        self.allocate(amount_stroops);
+        Ok(shares.0)
+    }
+
+    // Burns shares at the current share price and returns the stroops released
+    pub fn burn(&mut self, shares: u64) -> Result<u64, MathError> {
+        let mut pool = self.pool();
+        let amount = pool.burn(Shares(shares))?;
+        self.set_pool(pool);
+        self.deallocate(amount.0);
+        Ok(amount.0)
+    }
+
+    // Allocation-weighted APY across strategies, in basis points
+    pub fn blended_apy(&self) -> u16 {
+        let weighted: u32 = self.strategies.iter()
+            .map(|s| strategy::lookup(&s.strategy).current_apy(s) as u32 * s.allocation_percentage as u32)
+            .sum();
+        (weighted / 100) as u16
+    }
+
+    // Gives an add-on strategy `allocation` percent, scaling the built-in
+    // strategies to share what the add-ons leave
+    pub fn set_add_on_allocation(&mut self, id: &str, allocation: u8) -> Result<(), VaultError> {
+        let is_add_on = |s: &StrategyAllocation| strategy::lookup(&s.strategy).is_add_on();
+        let other_add_ons: u32 = self.strategies.iter()
+            .filter(|s| is_add_on(s) && s.strategy != id)
+            .map(|s| s.allocation_percentage as u32)
+            .sum();
+        if other_add_ons + allocation as u32 >= 100 {
+            return Err(VaultError::Validation(format!("{} can't take {}%: other add-on strategies already hold {}%", id, allocation, other_add_ons)));
+        }
+        let built_in: u32 = self.strategies.iter()
+            .filter(|s| !is_add_on(s))
+            .map(|s| s.allocation_percentage as u32)
+            .sum();
+        let room = 100 - other_add_ons - allocation as u32;
+        let mut assigned = 0;
+        for strategy in self.strategies.iter_mut().filter(|s| !is_add_on(s)) {
+            strategy.allocation_percentage = (strategy.allocation_percentage as u32 * room / built_in.max(1)) as u8;
+            assigned += strategy.allocation_percentage as u32;
+        }
+        // Rounding leftovers go to the first strategy so targets sum to 100
+        if let Some(first) = self.strategies.iter_mut().find(|s| !is_add_on(s)) {
+            first.allocation_percentage += (room - assigned) as u8;
+        }
+        match self.strategies.iter_mut().find(|s| s.strategy == id) {
+            Some(strategy) => strategy.allocation_percentage = allocation,
+            None => self.strategies.push(StrategyAllocation {
+                strategy: id.to_string(),
+                allocation_percentage: allocation,
+                current_apy: 0,
+                total_allocated: 0,
+                current_yield: 0,
+            }),
+        }
+        Ok(())
+    }
+
+    // Takes the settings of a redefined vault while keeping this one's
+    // balances. Add-on strategies keep their share; strategies the
+    // definition drops keep their funds at a 0% target until a rebalance
+    // moves them out.
+    pub fn redefine(&mut self, definition: &Vault) {
+        self.premium_bounds = definition.premium_bounds;
+        self.insurance_fee = match &definition.premium_bounds {
+            // The premium floats within the bounds, so keep where it has got to
+            Some(bounds) => self.insurance_fee.clamp(bounds.min_bps, bounds.max_bps),
+            None => definition.insurance_fee,
+        };
+        self.fees = definition.fees.clone();
+        self.max_tvl = definition.max_tvl;
+        self.per_user_cap = definition.per_user_cap;
+        self.max_tvl_usd = definition.max_tvl_usd;
+        self.per_user_cap_usd = definition.per_user_cap_usd;
+        self.min_deposit = definition.min_deposit;
+        self.lockup_secs = definition.lockup_secs;
+        self.early_withdrawal_penalty = definition.early_withdrawal_penalty;
+        self.liquidity_buffer_bps = definition.liquidity_buffer_bps;
+        self.circuit.max_price_move_bps = definition.circuit.max_price_move_bps;
+
+        let mut saved = std::mem::take(&mut self.strategies);
+        for defined in &definition.strategies {
+            let mut strategy = defined.clone();
+            if let Some(index) = saved.iter().position(|s| s.strategy == defined.strategy) {
+                let held = saved.remove(index);
+                strategy.total_allocated = held.total_allocated;
+                strategy.current_yield = held.current_yield;
+            }
+            self.strategies.push(strategy);
+        }
+        let (add_ons, dropped): (Vec<_>, Vec<_>) = saved.into_iter()
+            .partition(|s| strategy::lookup(&s.strategy).is_add_on());
+        let add_on_targets: Vec<(String, u8)> = add_ons.iter().map(|s| (s.strategy.clone(), s.allocation_percentage)).collect();
+        self.strategies.extend(add_ons);
+        self.strategies.extend(dropped.into_iter()
+            .filter(|s| s.total_allocated > 0)
+            .map(|s| StrategyAllocation { allocation_percentage: 0, ..s }));
+        for (id, allocation) in add_on_targets {
+            if let Err(e) = self.set_add_on_allocation(&id, allocation) {
+                say!("⚠️  {:?} Risk Vault: {}", self.risk_level, e);
+            }
+        }
+    }
+}
+
+/// A user's shares in one vault, with what they paid for them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UserPosition {
+    pub shares: u64,
+    pub accumulated_yield: u64,
+    // Gross stroops paid in (before insurance), reduced pro rata on withdrawal
+    #[serde(default)]
+    pub cost_basis: u64,
+    #[serde(default)]
+    pub insurance_paid: u64,
+    // Harvested yield is reinvested as shares instead of accruing to accumulated_yield
+    #[serde(default)]
+    pub auto_compound: bool,
+    // Shares each deposit bought, until its lockup is over. Shares from
+    // other deposits or compounded yield can be withdrawn without penalty.
+    #[serde(default)]
+    pub locked: Vec<LockedShares>,
+    // Only in state saved before deposits were locked one by one; restore
+    // moves it into `locked`
+    #[serde(default)]
+    pub last_deposit_at: u64,
+}
+
+/// Shares one deposit bought, locked for the vault's lockup from then.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedShares {
+    pub shares: u64,
+    pub deposited_at: u64,
+}
+
+impl UserPosition {
+    pub fn lock(&mut self, shares: u64, now: u64) {
+        self.locked.push(LockedShares { shares, deposited_at: now });
+    }
+
+    // Shares still inside the lockup at `now`
+    pub fn locked_shares(&self, lockup_secs: u64, now: u64) -> u64 {
+        let locked: u64 = self.locked.iter()
+            .filter(|lot| lot.deposited_at.saturating_add(lockup_secs) > now)
+            .map(|lot| lot.shares)
+            .sum();
+        locked.min(self.shares)
+    }
+
+    // Until the last locked deposit is free
+    pub fn lock_remaining_secs(&self, lockup_secs: u64, now: u64) -> u64 {
+        self.locked.iter()
+            .map(|lot| lot.deposited_at.saturating_add(lockup_secs).saturating_sub(now))
+            .max()
+            .unwrap_or(0)
+    }
+
+    // Of `shares` about to be withdrawn, how many are still locked; the
+    // unlocked ones go first
+    pub fn locked_part(&self, shares: u64, lockup_secs: u64, now: u64) -> u64 {
+        let unlocked = self.shares - self.locked_shares(lockup_secs, now);
+        shares.saturating_sub(unlocked)
+    }
+
+    // Takes withdrawn shares off the position, and off its locked deposits
+    // once the unlocked shares are used up, oldest first
+    pub fn burn(&mut self, shares: u64, lockup_secs: u64, now: u64) {
+        let mut from_locked = self.locked_part(shares, lockup_secs, now);
+        self.locked.retain(|lot| lot.deposited_at.saturating_add(lockup_secs) > now);
+        for lot in self.locked.iter_mut() {
+            let taken = lot.shares.min(from_locked);
+            lot.shares -= taken;
+            from_locked -= taken;
+        }
+        self.locked.retain(|lot| lot.shares > 0);
+        self.shares -= shares;
+    }
+}
+
+/// A position valued at the vault's current share price.
+#[derive(Debug, Clone)]
+pub struct PositionSummary {
+    pub risk: RiskLevel,
+    pub shares: u64,
+    pub value_stroops: u64,
+    pub accumulated_yield: u64,
+    pub cost_basis: u64,
+    pub insurance_paid: u64,
+    pub apy: u16,
+    pub auto_compound: bool,
+    pub lock_remaining_secs: u64,
+}
+
+impl PositionSummary {
+    pub fn unrealized_pnl(&self) -> i128 {
+        self.value_stroops as i128 + self.accumulated_yield as i128 - self.cost_basis as i128
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WithdrawalReceipt {
+    pub gross_stroops: u64,
+    pub penalty_stroops: u64,
+    pub net_stroops: u64,
+    pub transaction: TransactionReceipt,
+}
+
+/// What a deposit would do, without moving any funds.
+#[derive(Debug, Clone)]
+pub struct DepositPreview {
+    pub risk: RiskLevel,
+    // What the vault would be credited: the amount, or a conversion's minimum
+    pub amount: Stroops,
+    pub insurance: Stroops,
+    pub net: Stroops,
+    pub shares: u64,
+    pub transaction: TransactionPreview,
+}
+
+/// What a withdrawal would do, without moving any funds.
+#[derive(Debug, Clone)]
+pub struct WithdrawalPreview {
+    pub risk: RiskLevel,
+    pub shares: u64,
+    pub gross: Stroops,
+    pub penalty: Stroops,
+    pub net: Stroops,
+    // The liquid reserve can't cover it yet, so it would wait in the queue
+    // and nothing would be sent now
+    pub queued: bool,
+    pub transaction: Option<TransactionPreview>,
+}
+
+/// A withdrawal either paid out straight away or waiting in the queue.
+#[derive(Debug, Clone)]
+pub enum WithdrawalOutcome {
+    Completed(WithdrawalReceipt),
+    Queued { id: u64, ahead: usize },
+}
+
+#[derive(Debug, Clone)]
+pub struct HarvestReport {
+    pub risk: RiskLevel,
+    pub elapsed_secs: u64,
+    pub total_yield: u64,
+    pub performance_fee: u64,
+    pub distributed_yield: u64,
+    pub compounded_yield: u64,
+    pub compounded_shares: u64,
+}
+
+/// Every position a user holds, totalled across vaults.
+#[derive(Debug, Clone)]
+pub struct Portfolio {
+    pub positions: Vec<PositionSummary>,
+    pub total_value: u64,
+    pub total_yield: u64,
+    pub total_cost_basis: u64,
+    pub total_insurance_paid: u64,
+    pub blended_apy: u16,
+}
+
+
+pub fn risk_level_to_string(risk: RiskLevel) -> &'static str {
+    match risk {
+        RiskLevel::Low => "Low",
+        RiskLevel::Medium => "Medium",
+        RiskLevel::High => "High",
+    }
+}
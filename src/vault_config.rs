@@ -0,0 +1,202 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use serde::Deserialize;
+
+use crate::amount::{Stroops, STROOPS_PER_XLM};
+use crate::builder::{VaultBuilder, DEFAULT_MANAGEMENT_FEE_BPS, DEFAULT_PERFORMANCE_FEE_BPS};
+use crate::config::parse_risk;
+use crate::error::VaultError;
+
+pub const DEFAULT_VAULTS_FILE: &str = "vaults.toml";
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+// ============================================================================
+// VAULT DEFINITIONS FILE
+// ============================================================================
+
+// vaults.toml: one [[vault]] table per risk level with its [[vault.strategy]]
+// entries. Keys left out keep VaultBuilder's defaults.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VaultsFile {
+    vault: Vec<VaultEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VaultEntry {
+    risk: String,
+    strategy: Vec<StrategyEntry>,
+    insurance_fee_bps: Option<u16>,
+    premium_bounds_bps: Option<[u16; 2]>,
+    management_fee_bps: Option<u16>,
+    performance_fee_bps: Option<u16>,
+    min_deposit: Option<Amount>,
+    max_tvl: Option<Amount>,
+    per_user_cap: Option<Amount>,
+    max_tvl_usd: Option<Amount>,
+    per_user_cap_usd: Option<Amount>,
+    lockup_days: Option<u64>,
+    early_withdrawal_penalty_bps: Option<u16>,
+    liquidity_buffer_bps: Option<u16>,
+    circuit_limit_bps: Option<u16>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StrategyEntry {
+    id: String,
+    allocation: u8,
+    apy_bps: u16,
+}
+
+// Whole units of the vault's asset, or a decimal string such as "0.5"
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Amount {
+    Units(u64),
+    Decimal(String),
+}
+
+impl Amount {
+    fn stroops(&self, key: &str, origin: &str) -> Result<u64, VaultError> {
+        let invalid = || VaultError::Validation(format!("{}: {} is not a valid amount", origin, key));
+        match self {
+            Amount::Units(units) => units.checked_mul(STROOPS_PER_XLM).ok_or_else(invalid),
+            Amount::Decimal(text) => Stroops::from_xlm_str(text).map(|s| s.0).map_err(|_| invalid()),
+        }
+    }
+}
+
+// Reads and checks the vault definitions in `path`
+pub fn load(path: &Path) -> Result<Vec<VaultBuilder>, VaultError> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| VaultError::Storage(format!("Failed to read {}: {}", path.display(), e)))?;
+    parse(&contents, &path.display().to_string())
+}
+
+// Every vault is built once here so a bad definition stops startup with the
+// file's name in the message, before any state is touched
+pub fn parse(contents: &str, file_name: &str) -> Result<Vec<VaultBuilder>, VaultError> {
+    let file: VaultsFile = toml::from_str(contents)
+        .map_err(|e| VaultError::Validation(format!("Invalid vault definition in {}: {}", file_name, e)))?;
+    if file.vault.is_empty() {
+        return Err(VaultError::Validation(format!("{} defines no vaults", file_name)));
+    }
+
+    let mut risks = HashSet::new();
+    let mut vaults = Vec::new();
+    for entry in file.vault {
+        let origin = format!("[[vault]] in {}", file_name);
+        let risk = parse_risk(&entry.risk, &origin)?;
+        if !risks.insert(risk) {
+            return Err(VaultError::Validation(format!("{} defines the {:?} Risk Vault twice", file_name, risk)));
+        }
+        let origin = format!("{} vault in {}", entry.risk.to_lowercase(), file_name);
+
+        let mut vault = VaultBuilder::new(risk);
+        for strategy in &entry.strategy {
+            vault = vault.strategy(&strategy.id, strategy.allocation, strategy.apy_bps);
+        }
+        if let Some(fee) = entry.insurance_fee_bps {
+            vault = vault.insurance_fee(fee);
+        }
+        if let Some([min, max]) = entry.premium_bounds_bps {
+            vault = vault.premium_bounds(min, max);
+        }
+        if entry.management_fee_bps.is_some() || entry.performance_fee_bps.is_some() {
+            vault = vault.fees(
+                entry.management_fee_bps.unwrap_or(DEFAULT_MANAGEMENT_FEE_BPS),
+                entry.performance_fee_bps.unwrap_or(DEFAULT_PERFORMANCE_FEE_BPS),
+            );
+        }
+        if let Some(amount) = &entry.min_deposit {
+            vault = vault.min_deposit(amount.stroops("min_deposit", &origin)?);
+        }
+        if let Some(amount) = &entry.max_tvl {
+            vault = vault.max_tvl(amount.stroops("max_tvl", &origin)?);
+        }
+        if let Some(amount) = &entry.per_user_cap {
+            vault = vault.per_user_cap(amount.stroops("per_user_cap", &origin)?);
+        }
+        if let Some(amount) = &entry.max_tvl_usd {
+            vault = vault.max_tvl_usd(amount.stroops("max_tvl_usd", &origin)?);
+        }
+        if let Some(amount) = &entry.per_user_cap_usd {
+            vault = vault.per_user_cap_usd(amount.stroops("per_user_cap_usd", &origin)?);
+        }
+        if entry.lockup_days.is_some() || entry.early_withdrawal_penalty_bps.is_some() {
+            let secs = entry.lockup_days.unwrap_or(0).checked_mul(SECONDS_PER_DAY)
+                .ok_or_else(|| VaultError::Validation(format!("{}: lockup_days is too long", origin)))?;
+            vault = vault.lockup(secs, entry.early_withdrawal_penalty_bps.unwrap_or(0));
+        }
+        if let Some(buffer) = entry.liquidity_buffer_bps {
+            vault = vault.liquidity_buffer(buffer);
+        }
+        if let Some(limit) = entry.circuit_limit_bps {
+            vault = vault.circuit_limit(limit);
+        }
+
+        vault.clone().build().map_err(|e| VaultError::Validation(format!("{}: {}", file_name, e)))?;
+        vaults.push(vault);
+    }
+    Ok(vaults)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::default_vaults;
+    use crate::RiskLevel;
+
+    #[test]
+    fn vault_definitions_load_from_toml() {
+        let vaults = parse(r#"
+            [[vault]]
+            risk = "low"
+            insurance_fee_bps = 50
+            premium_bounds_bps = [25, 100]
+            max_tvl = "250000.5"
+            per_user_cap = 10000
+            lockup_days = 7
+            early_withdrawal_penalty_bps = 100
+
+            [[vault.strategy]]
+            id = "YieldBloxLending"
+            allocation = 70
+            apy_bps = 350
+
+            [[vault.strategy]]
+            id = "MoneyMarket"
+            allocation = 30
+            apy_bps = 900
+        "#, "vaults.toml").unwrap();
+        let vault = vaults[0].clone().build().unwrap();
+        assert_eq!(vault.risk_level, RiskLevel::Low);
+        assert_eq!(vault.blended_apy(), 515);
+        assert_eq!(vault.max_tvl, Some(2_500_005_000_000));
+        assert_eq!(vault.per_user_cap, Some(10_000 * STROOPS_PER_XLM));
+        assert_eq!(vault.lockup_secs, 7 * SECONDS_PER_DAY);
+
+        let unbalanced = r#"
+            [[vault]]
+            risk = "high"
+            [[vault.strategy]]
+            id = "MoneyMarket"
+            allocation = 90
+            apy_bps = 1500
+        "#;
+        let error = parse(unbalanced, "vaults.toml").unwrap_err().to_string();
+        assert!(error.contains("vaults.toml") && error.contains("not 100%"), "{}", error);
+        assert!(parse("[[vault]]\nrisk = \"low\"\nstrategy = []\nfee = 1", "vaults.toml").is_err());
+
+        // The example mirrors the standard tiers
+        let example = parse(include_str!("../vaults.example.toml"), "example").unwrap();
+        for (loaded, standard) in example.into_iter().zip(default_vaults()) {
+            let (loaded, standard) = (loaded.build().unwrap(), standard.build().unwrap());
+            assert_eq!(serde_json::to_value(&loaded).unwrap(), serde_json::to_value(&standard).unwrap());
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use stellar_xdr::curr::{OperationBody, ScVal};
+
+use crate::soroban;
+use crate::RiskLevel;
+use crate::error::VaultError;
+
+// ============================================================================
+// VAULT CONTRACT CALLS
+// ============================================================================
+
+// The vault contract in contracts/vault keeps each risk level's shares and
+// share price on-chain. Amounts and shares are in stroops.
+
+// Risk levels are contract enums, which travel as a vector holding the
+// variant's name
+pub fn risk(risk: RiskLevel) -> Result<ScVal, VaultError> {
+    let name = match risk {
+        RiskLevel::Low => "Low",
+        RiskLevel::Medium => "Medium",
+        RiskLevel::High => "High",
+    };
+    soroban::vec(vec![soroban::symbol(name)?])
+}
+
+pub fn parse_risk(value: &ScVal) -> Option<RiskLevel> {
+    match soroban::items(value)? {
+        [name] => match soroban::describe(name).as_str() {
+            "Low" => Some(RiskLevel::Low),
+            "Medium" => Some(RiskLevel::Medium),
+            "High" => Some(RiskLevel::High),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Sets the risk level's insurance fee and strategy allocations, which must
+// add up to 10,000 basis points
+pub fn configure(contract: &str, level: RiskLevel, insurance_fee_bps: u16, strategies: &[(String, u32)]) -> Result<OperationBody, VaultError> {
+    let strategies = strategies.iter()
+        .map(|(kind, allocation_bps)| soroban::record(vec![
+            ("allocation_bps", ScVal::U32(*allocation_bps)),
+            ("kind", soroban::symbol(kind)?),
+        ]))
+        .collect::<Result<_, _>>()?;
+    soroban::invoke(contract, "configure", vec![risk(level)?, ScVal::U32(insurance_fee_bps as u32), soroban::vec(strategies)?])
+}
+
+// Returns the shares minted
+pub fn deposit(contract: &str, user: &str, level: RiskLevel, amount: u64) -> Result<OperationBody, VaultError> {
+    soroban::invoke(contract, "deposit", vec![soroban::address(user)?, risk(level)?, soroban::amount(amount)])
+}
+
+// Returns the amount paid out
+pub fn withdraw(contract: &str, user: &str, level: RiskLevel, shares: u64) -> Result<OperationBody, VaultError> {
+    soroban::invoke(contract, "withdraw", vec![soroban::address(user)?, risk(level)?, soroban::amount(shares)])
+}
+
+// Pays yield in from the contract's admin
+pub fn harvest(contract: &str, level: RiskLevel, amount: u64) -> Result<OperationBody, VaultError> {
+    soroban::invoke(contract, "harvest", vec![risk(level)?, soroban::amount(amount)])
+}
+
+// Switches the contract to uploaded code; only its admin may
+pub fn upgrade(contract: &str, wasm_hash: [u8; 32]) -> Result<OperationBody, VaultError> {
+    soroban::invoke(contract, "upgrade", vec![ScVal::Bytes(wasm_hash.to_vec().try_into()?)])
+}
+
+pub fn get_vault(contract: &str, level: RiskLevel) -> Result<OperationBody, VaultError> {
+    soroban::invoke(contract, "vault", vec![risk(level)?])
+}
+
+pub fn token(contract: &str) -> Result<OperationBody, VaultError> {
+    soroban::invoke(contract, "token", Vec::new())
+}
+
+// The admin, the deposit token and each risk level's share token, which
+// must already exist with the contract as their admin
+pub fn constructor_args(admin: &str, token: &str, share_tokens: &[(RiskLevel, String)]) -> Result<Vec<ScVal>, VaultError> {
+    let share_tokens = share_tokens.iter()
+        .map(|(level, share_token)| Ok((risk(*level)?, soroban::address(share_token)?)))
+        .collect::<Result<Vec<_>, VaultError>>()?;
+    Ok(vec![soroban::address(admin)?, soroban::address(token)?, soroban::map(share_tokens)?])
+}
+
+pub fn share_token(contract: &str, level: RiskLevel) -> Result<OperationBody, VaultError> {
+    soroban::invoke(contract, "share_token", vec![risk(level)?])
+}
+
+pub fn shares_of(contract: &str, user: &str, level: RiskLevel) -> Result<OperationBody, VaultError> {
+    soroban::invoke(contract, "shares", vec![soroban::address(user)?, risk(level)?])
+}
+
+// ============================================================================
+// SHARE TOKENS
+// ============================================================================
+
+// contracts/share_token: a SEP-41 token per risk level, minted and burned by
+// the vault contract. Its code can't be upgraded, so holders can rely on it.
+
+pub fn share_token_args(admin: &str, name: &str, symbol: &str) -> Result<Vec<ScVal>, VaultError> {
+    Ok(vec![soroban::address(admin)?, soroban::string(name)?, soroban::string(symbol)?])
+}
+
+// Storage key of a holder's balance, for reading it straight from the ledger
+pub fn share_balance_key(holder: &str) -> Result<ScVal, VaultError> {
+    soroban::vec(vec![soroban::symbol("Balance")?, soroban::address(holder)?])
+}
+
+// ============================================================================
+// STATE
+// ============================================================================
+
+// Storage key of a risk level's pool, for reading it straight from the ledger
+pub fn vault_key(level: RiskLevel) -> Result<ScVal, VaultError> {
+    soroban::vec(vec![soroban::symbol("Vault")?, risk(level)?])
+}
+
+// A risk level's pool as the contract holds it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractVault {
+    pub total_assets: u64,
+    pub total_shares: u64,
+    pub insurance_fee_bps: u32,
+    pub insurance_reserve: u64,
+    pub strategies: Vec<(String, u32)>,
+}
+
+impl ContractVault {
+    pub fn parse(value: &ScVal) -> Result<Self, VaultError> {
+        let amount = |name: &str| soroban::field(value, name).and_then(soroban::to_amount)
+            .ok_or_else(|| format!("Vault contract returned no {}", name));
+        let insurance_fee_bps = match soroban::field(value, "insurance_fee_bps") {
+            Some(ScVal::U32(bps)) => *bps,
+            _ => return Err("Vault contract returned no insurance fee".into()),
+        };
+        let strategies = soroban::field(value, "strategies").and_then(soroban::items).unwrap_or_default().iter()
+            .map(|strategy| match (soroban::field(strategy, "kind"), soroban::field(strategy, "allocation_bps")) {
+                (Some(kind), Some(ScVal::U32(bps))) => Ok((soroban::describe(kind), *bps)),
+                _ => Err("Vault contract returned an unreadable strategy"),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(ContractVault {
+            total_assets: amount("total_assets")?,
+            total_shares: amount("total_shares")?,
+            insurance_fee_bps,
+            insurance_reserve: amount("insurance_reserve")?,
+            strategies,
+        })
+    }
+
+    // Value of one share, in the vault's asset
+    pub fn share_price(&self) -> f64 {
+        if self.total_shares == 0 {
+            return 1.0;
+        }
+        self.total_assets as f64 / self.total_shares as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vault_state_reads_back_from_its_contract_form() {
+        let strategy = soroban::record(vec![
+            ("kind", soroban::symbol("YieldBloxLending").unwrap()),
+            ("allocation_bps", ScVal::U32(10_000)),
+        ]).unwrap();
+        let state = soroban::record(vec![
+            ("total_assets", soroban::amount(1_100)),
+            ("total_shares", soroban::amount(1_000)),
+            ("insurance_fee_bps", ScVal::U32(50)),
+            ("insurance_reserve", soroban::amount(5)),
+            ("strategies", soroban::vec(vec![strategy]).unwrap()),
+        ]).unwrap();
+
+        let vault = ContractVault::parse(&state).unwrap();
+        assert_eq!(vault.strategies, vec![("YieldBloxLending".to_string(), 10_000)]);
+        assert_eq!(vault.share_price(), 1.1);
+        assert!(ContractVault::parse(&soroban::amount(1)).is_err());
+        assert_eq!(risk(RiskLevel::Medium).unwrap(), soroban::vec(vec![soroban::symbol("Medium").unwrap()]).unwrap());
+    }
+}
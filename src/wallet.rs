@@ -0,0 +1,85 @@
+use bip39::{Language, Mnemonic, MnemonicType, Seed};
+use hmac::{Hmac, Mac};
+use sha2::Sha512;
+
+use crate::error::VaultError;
+use crate::transaction::Keypair;
+
+// ============================================================================
+// KEY GENERATION
+// ============================================================================
+
+// SLIP-10 hardened offset and the SEP-5 path m/44'/148'/account'
+const HARDENED: u32 = 0x8000_0000;
+const BIP44_PURPOSE: u32 = 44;
+const STELLAR_COIN_TYPE: u32 = 148;
+
+// A fresh 24-word English phrase
+pub fn new_mnemonic() -> String {
+    Mnemonic::new(MnemonicType::Words24, Language::English).into_phrase()
+}
+
+// Derives the keypair for `account` per SEP-5, so the same phrase restores
+// the same accounts in any SEP-5 wallet
+pub fn from_mnemonic(phrase: &str, passphrase: &str, account: u32) -> Result<Keypair, VaultError> {
+    if account >= HARDENED {
+        return Err(VaultError::Validation(format!("Account index {} is out of range", account)));
+    }
+    let normalized = phrase.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    let mnemonic = Mnemonic::from_phrase(&normalized, Language::English)
+        .map_err(|e| VaultError::Validation(format!("Invalid mnemonic: {}", e)))?;
+    let seed = Seed::new(&mnemonic, passphrase);
+
+    let (mut key, mut chain_code) = split(hmac_sha512(b"ed25519 seed", seed.as_bytes()));
+    for index in [BIP44_PURPOSE, STELLAR_COIN_TYPE, account] {
+        // ed25519 only supports hardened children: 0x00 || key || ser32(index)
+        let mut data = Vec::with_capacity(37);
+        data.push(0);
+        data.extend_from_slice(&key);
+        data.extend_from_slice(&(index | HARDENED).to_be_bytes());
+        (key, chain_code) = split(hmac_sha512(&chain_code, &data));
+    }
+
+    Ok(Keypair::from_seed(&key))
+}
+
+fn hmac_sha512(key: &[u8], data: &[u8]) -> [u8; 64] {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+fn split(bytes: [u8; 64]) -> ([u8; 32], [u8; 32]) {
+    let mut key = [0u8; 32];
+    let mut chain_code = [0u8; 32];
+    key.copy_from_slice(&bytes[..32]);
+    chain_code.copy_from_slice(&bytes[32..]);
+    (key, chain_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test 1 from SEP-0005
+    const PHRASE: &str = "illness spike retreat truth genius clock brain pass fit cave bargain toe";
+
+    #[test]
+    fn derives_sep5_test_vector() {
+        let keypair = from_mnemonic(PHRASE, "", 0).unwrap();
+        assert_eq!(keypair.public_key(), "GDRXE2BQUC3AZNPVFSCEZ76NJ3WWL25FYFK6RGZGIEKWE4SOOHSUJUJ6");
+        assert_eq!(keypair.secret_key(), "SBGWSG6BTNCKCOB3DIFBGCVMUPQFYPA2G4O34RMTB343OYPXU5DJDVMN");
+
+        let second = from_mnemonic(PHRASE, "", 1).unwrap();
+        assert_eq!(second.public_key(), "GBAW5XGWORWVFE2XTJYDTLDHXTY2Q2MO73HYCGB3XMFMQ562Q2W2GJQX");
+    }
+
+    #[test]
+    fn generated_mnemonics_round_trip() {
+        let phrase = new_mnemonic();
+        assert_eq!(phrase.split_whitespace().count(), 24);
+        let a = from_mnemonic(&phrase, "", 0).unwrap();
+        let b = from_mnemonic(&phrase.to_uppercase(), "", 0).unwrap();
+        assert_eq!(a.public_key(), b.public_key());
+    }
+}
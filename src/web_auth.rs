@@ -0,0 +1,224 @@
+use base64::engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD};
+use base64::Engine;
+use chacha20poly1305::aead::OsRng;
+use chacha20poly1305::aead::rand_core::RngCore;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use stellar_xdr::curr::{DataValue, DecoratedSignature, ManageDataOp, Operation, OperationBody, Preconditions, String64, Transaction, TransactionEnvelope};
+
+use crate::error::VaultError;
+use crate::multisig::{SignerSet, Threshold};
+use crate::offline;
+use crate::signer;
+use crate::transaction::{self, Keypair, TransactionBuilder};
+
+// How long a client has to sign and return a challenge
+pub const CHALLENGE_TIMEOUT_SECS: u64 = 900;
+pub const TOKEN_LIFETIME_SECS: u64 = 86_400;
+// 64 characters once base64 encoded, as SEP-10 asks of the nonce
+const NONCE_BYTES: usize = 48;
+const TOKEN_HEADER: &str = r#"{"alg":"HS256","typ":"JWT"}"#;
+
+// ============================================================================
+// WEB AUTHENTICATION (SEP-10)
+// ============================================================================
+
+// Proves control of a Stellar account without moving funds: the server signs
+// a challenge transaction that can never be submitted, the client co-signs it
+// with the account's keys, and the server hands back a JWT for that account.
+pub struct WebAuth {
+    server: Keypair,
+    passphrase: String,
+    home_domain: String,
+    // Signs the tokens; fresh each run, so no token outlives the server
+    token_key: [u8; 32],
+}
+
+// A challenge this server issued, back from the client with its signatures
+pub struct Challenge {
+    pub account: String,
+    pub hash: String,
+    transaction: Transaction,
+    // All but the server's own
+    signatures: Vec<DecoratedSignature>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Claims {
+    iss: String,
+    sub: String,
+    iat: u64,
+    exp: u64,
+    // Hash of the challenge the token was issued for
+    jti: String,
+}
+
+fn rejected(reason: &str) -> VaultError {
+    VaultError::Validation(format!("Invalid challenge: {}", reason))
+}
+
+fn manage_data(name: &str, value: &[u8]) -> Result<OperationBody, VaultError> {
+    Ok(OperationBody::ManageData(ManageDataOp {
+        data_name: String64(name.try_into()?),
+        data_value: Some(DataValue(value.to_vec().try_into()?)),
+    }))
+}
+
+impl WebAuth {
+    pub fn new(server: Keypair, passphrase: &str, home_domain: &str) -> Self {
+        let mut token_key = [0u8; 32];
+        OsRng.fill_bytes(&mut token_key);
+        WebAuth { server, passphrase: passphrase.to_string(), home_domain: home_domain.to_string(), token_key }
+    }
+
+    // What stellar.toml would publish as SIGNING_KEY
+    pub fn signing_key(&self) -> String {
+        self.server.public_key()
+    }
+
+    pub fn passphrase(&self) -> &str {
+        &self.passphrase
+    }
+
+    fn auth_key(&self) -> String {
+        format!("{} auth", self.home_domain)
+    }
+
+    // Base64 XDR for the client to sign. Sequence 0 keeps it from ever being
+    // valid on the network.
+    pub fn challenge(&self, account: &str, now: u64) -> Result<String, VaultError> {
+        transaction::account_id(account)?;
+        let mut nonce = [0u8; NONCE_BYTES];
+        OsRng.fill_bytes(&mut nonce);
+        let server = self.signing_key();
+        let challenge = TransactionBuilder::new(&server, -1)?
+            .time_bounds(now, now + CHALLENGE_TIMEOUT_SECS)?
+            .operation_for(account, manage_data(&self.auth_key(), BASE64.encode(nonce).as_bytes())?)?
+            .operation_for(&server, manage_data("web_auth_domain", self.home_domain.as_bytes())?)?
+            .build()?;
+        transaction::to_base64(&transaction::sign(challenge, &self.passphrase, &[&self.server])?)
+    }
+
+    // Checks the challenge is one this server signed and that it is still
+    // current; the client's signatures are checked by `verify`
+    pub fn read_challenge(&self, xdr: &str, now: u64) -> Result<Challenge, VaultError> {
+        let TransactionEnvelope::Tx(envelope) = offline::decode(xdr)? else {
+            return Err(rejected("not a v1 transaction envelope"));
+        };
+        let server = self.signing_key();
+        let tx = envelope.tx;
+        if tx.source_account.to_string() != server || tx.seq_num.0 != 0 {
+            return Err(rejected("not issued by this server"));
+        }
+        let Preconditions::Time(bounds) = &tx.cond else {
+            return Err(rejected("no time bounds"));
+        };
+        if now < bounds.min_time.0 || now > bounds.max_time.0 {
+            return Err(rejected("expired; request a new one"));
+        }
+
+        let mut operations = tx.operations.iter();
+        let account = match operations.next() {
+            Some(Operation { source_account: Some(source), body: OperationBody::ManageData(data) })
+                if data.data_name.0.to_utf8_string_lossy() == self.auth_key()
+                    && data.data_value.as_ref().is_some_and(|nonce| nonce.0.len() == 64) => source.to_string(),
+            _ => return Err(rejected("the first operation isn't this server's auth request")),
+        };
+        // Anything after that is the server's own
+        let servers_own = |operation: &Operation| matches!(operation.body, OperationBody::ManageData(_))
+            && operation.source_account.as_ref().is_some_and(|source| source.to_string() == server);
+        if !operations.all(servers_own) {
+            return Err(rejected("it holds operations the server didn't add"));
+        }
+
+        let hash = tx.hash(transaction::network_id(&self.passphrase))?;
+        let mut signatures = envelope.signatures.to_vec();
+        let signed = signatures.len();
+        signatures.retain(|signature| !signer::verify(&server, &hash, signature));
+        if signatures.len() == signed {
+            return Err(rejected("the server's signature is missing"));
+        }
+        Ok(Challenge { account, hash: transaction::hash_hex(&tx, &self.passphrase)?, transaction: tx, signatures })
+    }
+
+    // The account's signers, or for an account not created yet its own key,
+    // must carry its medium threshold, and nobody else may have signed
+    pub fn verify(&self, challenge: &Challenge, signers: &SignerSet) -> Result<(), VaultError> {
+        let (signed_by, unknown) = signers.signed_by(&challenge.transaction, &challenge.signatures, &self.passphrase)?;
+        if unknown > 0 {
+            return Err(rejected(&format!("{} signature(s) are not from a signer of {}", unknown, challenge.account)));
+        }
+        let signed_by: Vec<&str> = signed_by.iter().map(String::as_str).collect();
+        signers.check(&signed_by, Threshold::Medium)?;
+        Ok(())
+    }
+
+    fn mac(&self) -> Hmac<Sha256> {
+        Hmac::<Sha256>::new_from_slice(&self.token_key).expect("HMAC takes keys of any length")
+    }
+
+    // An HS256 JWT naming the challenge's account as its subject
+    pub fn token(&self, challenge: &Challenge, now: u64) -> String {
+        let claims = Claims {
+            iss: self.home_domain.clone(),
+            sub: challenge.account.clone(),
+            iat: now,
+            exp: now + TOKEN_LIFETIME_SECS,
+            jti: challenge.hash.clone(),
+        };
+        let signed = format!("{}.{}",
+            URL_SAFE_NO_PAD.encode(TOKEN_HEADER),
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims).unwrap_or_default()));
+        let mut mac = self.mac();
+        mac.update(signed.as_bytes());
+        format!("{}.{}", signed, URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes()))
+    }
+
+    // The account a token from this server is for, while it is valid
+    pub fn authenticate(&self, token: &str, now: u64) -> Result<String, VaultError> {
+        let invalid = || VaultError::Validation("Invalid or expired token; authenticate at /auth again".into());
+        let (signed, signature) = token.rsplit_once('.').ok_or_else(invalid)?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| invalid())?;
+        let mut mac = self.mac();
+        mac.update(signed.as_bytes());
+        mac.verify_slice(&signature).map_err(|_| invalid())?;
+
+        let (_, claims) = signed.split_once('.').ok_or_else(invalid)?;
+        let claims: Claims = URL_SAFE_NO_PAD.decode(claims).ok()
+            .and_then(|claims| serde_json::from_slice(&claims).ok())
+            .ok_or_else(invalid)?;
+        if claims.exp <= now {
+            return Err(invalid());
+        }
+        Ok(claims.sub)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::OFFLINE_PASSPHRASE;
+
+    #[test]
+    fn signed_challenge_earns_a_token_for_the_account() {
+        let auth = WebAuth::new(Keypair::from_seed(&[7; 32]), OFFLINE_PASSPHRASE, "vault.example.com");
+        let client = Keypair::from_seed(&[8; 32]);
+        let account = client.public_key();
+        let signers = SignerSet::master_only(&account);
+
+        let issued = auth.challenge(&account, 1_000).unwrap();
+        let unsigned = auth.read_challenge(&issued, 1_100).unwrap();
+        assert!(auth.verify(&unsigned, &signers).is_err());
+        assert!(auth.read_challenge(&issued, 1_000 + CHALLENGE_TIMEOUT_SECS + 1).is_err());
+
+        let signed = offline::sign(offline::decode(&issued).unwrap(), OFFLINE_PASSPHRASE, &client).unwrap();
+        let challenge = auth.read_challenge(&transaction::to_base64(&signed).unwrap(), 1_100).unwrap();
+        auth.verify(&challenge, &signers).unwrap();
+
+        let token = auth.token(&challenge, 1_100);
+        assert_eq!(auth.authenticate(&token, 1_200).unwrap(), account);
+        assert!(auth.authenticate(&token, 1_100 + TOKEN_LIFETIME_SECS).is_err());
+        assert!(auth.authenticate(&format!("{}x", token), 1_200).is_err());
+    }
+}
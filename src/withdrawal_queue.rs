@@ -0,0 +1,73 @@
+use std::collections::VecDeque;
+use serde::{Deserialize, Serialize};
+
+use crate::RiskLevel;
+
+// ============================================================================
+// WITHDRAWAL QUEUE
+// ============================================================================
+
+// Withdrawals larger than a vault's liquid reserve wait here in FIFO order
+// until harvest or rebalance frees enough liquidity to pay them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedWithdrawal {
+    pub id: u64,
+    pub user: String,
+    pub risk: RiskLevel,
+    pub shares: u64,
+    pub requested_at: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WithdrawalQueue {
+    entries: VecDeque<QueuedWithdrawal>,
+    next_id: u64,
+}
+
+impl WithdrawalQueue {
+    pub fn enqueue(&mut self, user: &str, risk: RiskLevel, shares: u64, now: u64) -> u64 {
+        self.next_id += 1;
+        self.entries.push_back(QueuedWithdrawal {
+            id: self.next_id,
+            user: user.to_string(),
+            risk,
+            shares,
+            requested_at: now,
+        });
+        self.next_id
+    }
+
+    // Shares already promised to queued requests can't be withdrawn again
+    pub fn queued_shares(&self, user: &str, risk: RiskLevel) -> u64 {
+        self.entries.iter()
+            .filter(|e| e.user == user && e.risk == risk)
+            .map(|e| e.shares)
+            .sum()
+    }
+
+    pub fn has_pending(&self, risk: RiskLevel) -> bool {
+        self.entries.iter().any(|e| e.risk == risk)
+    }
+
+    pub fn front(&self, risk: RiskLevel) -> Option<&QueuedWithdrawal> {
+        self.entries.iter().find(|e| e.risk == risk)
+    }
+
+    // Number of requests for the same vault ahead of the given one
+    pub fn position(&self, id: u64) -> Option<usize> {
+        let entry = self.entries.iter().find(|e| e.id == id)?;
+        Some(self.entries.iter()
+            .take_while(|e| e.id != id)
+            .filter(|e| e.risk == entry.risk)
+            .count())
+    }
+
+    pub fn remove(&mut self, id: u64) -> Option<QueuedWithdrawal> {
+        let index = self.entries.iter().position(|e| e.id == id)?;
+        self.entries.remove(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &QueuedWithdrawal> {
+        self.entries.iter()
+    }
+}